@@ -20,3 +20,9 @@ from .user_pb2 import *
 from .user_pb2_grpc import *
 from .relay_pb2 import *
 from .relay_pb2_grpc import *
+from .codec_pb2 import *
+from .codec_pb2_grpc import *
+from .adr_pb2 import *
+from .adr_pb2_grpc import *
+from .fuota_pb2 import *
+from .fuota_pb2_grpc import *
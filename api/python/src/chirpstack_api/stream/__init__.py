@@ -2,3 +2,4 @@ from .frame_pb2 import *
 from .meta_pb2 import *
 from .api_request_pb2 import *
 from .backend_interfaces_pb2 import *
+from .gateway_pb2 import *
@@ -167,6 +167,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     .join("backend_interfaces.proto")
                     .to_str()
                     .unwrap(),
+                cs_dir
+                    .join("stream")
+                    .join("gateway.proto")
+                    .to_str()
+                    .unwrap(),
             ],
             &[
                 proto_dir.join("chirpstack").to_str().unwrap(),
@@ -220,12 +225,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     .join("device_profile_template.proto")
                     .to_str()
                     .unwrap(),
+                cs_dir.join("api").join("codec.proto").to_str().unwrap(),
                 cs_dir.join("api").join("device.proto").to_str().unwrap(),
                 cs_dir
                     .join("api")
                     .join("device_config_store.proto")
                     .to_str()
                     .unwrap(),
+                cs_dir.join("api").join("fuota.proto").to_str().unwrap(),
                 cs_dir.join("api").join("gateway.proto").to_str().unwrap(),
                 cs_dir
                     .join("api")
@@ -233,6 +240,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     .to_str()
                     .unwrap(),
                 cs_dir.join("api").join("relay.proto").to_str().unwrap(),
+                cs_dir.join("api").join("adr.proto").to_str().unwrap(),
             ],
             &[
                 proto_dir.join("chirpstack").to_str().unwrap(),
@@ -244,3 +244,39 @@ impl GatewayStats {
         }
     }
 }
+
+impl GatewayCommandExecRequest {
+    pub fn v4_migrate(&mut self) {
+        self.gateway_id_legacy = hex::decode(&self.gateway_id).unwrap();
+    }
+}
+
+impl GatewayCommandExecResponse {
+    pub fn v4_migrate(&mut self) {
+        if self.gateway_id.is_empty() {
+            self.gateway_id = hex::encode(&self.gateway_id_legacy);
+        }
+    }
+}
+
+impl RawPacketForwarderEvent {
+    pub fn v4_migrate(&mut self) {
+        if self.gateway_id.is_empty() {
+            self.gateway_id = hex::encode(&self.gateway_id_legacy);
+        }
+    }
+}
+
+impl RawPacketForwarderCommand {
+    pub fn v4_migrate(&mut self) {
+        self.gateway_id_legacy = hex::decode(&self.gateway_id).unwrap();
+    }
+}
+
+impl ConnState {
+    pub fn v4_migrate(&mut self) {
+        if self.gateway_id.is_empty() {
+            self.gateway_id = hex::encode(&self.gateway_id_legacy);
+        }
+    }
+}
@@ -32,6 +32,8 @@ impl Into<String> for LogCode {
             LogCode::DownlinkGateway => "DOWNLINK_GATEWAY",
             LogCode::RelayNewEndDevice => "RELAY_NEW_END_DEVICE",
             LogCode::FCntDown => "F_CNT_DOWN",
+            LogCode::DeviceQueueItemExpired => "DEVICE_QUEUE_ITEM_EXPIRED",
+            LogCode::FragmentationSession => "FRAGMENTATION_SESSION",
         }
         .to_string()
     }
@@ -0,0 +1,4 @@
+drop index idx_device_profile_codec_version_id;
+alter table device_profile drop column codec_version_id;
+drop table codec_version;
+drop table codec;
@@ -0,0 +1,28 @@
+create table codec (
+    id uuid primary key,
+    tenant_id uuid not null references tenant on delete cascade,
+    created_at timestamp with time zone not null,
+    updated_at timestamp with time zone not null,
+    name varchar(100) not null,
+    description text not null
+);
+
+create index idx_codec_tenant_id on codec (tenant_id);
+create index idx_codec_name_trgm on codec using gin (name gin_trgm_ops);
+
+create table codec_version (
+    id uuid primary key,
+    codec_id uuid not null references codec on delete cascade,
+    created_at timestamp with time zone not null,
+    version integer not null,
+    runtime varchar(20) not null,
+    script text not null,
+    tests jsonb not null,
+
+    unique (codec_id, version)
+);
+
+alter table device_profile
+    add column codec_version_id uuid null references codec_version;
+
+create index idx_device_profile_codec_version_id on device_profile (codec_version_id);
@@ -0,0 +1,7 @@
+drop index idx_api_key_application_id;
+
+alter table api_key
+    drop column application_id,
+    drop column scope,
+    drop column last_used_at,
+    drop column expires_at;
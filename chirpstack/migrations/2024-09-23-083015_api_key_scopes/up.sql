@@ -0,0 +1,10 @@
+alter table api_key
+    add column expires_at timestamp with time zone null,
+    add column last_used_at timestamp with time zone null,
+    add column scope varchar(30) not null default 'FULL',
+    add column application_id uuid null references application on delete cascade;
+
+alter table api_key
+    alter column scope drop default;
+
+create index idx_api_key_application_id on api_key (application_id);
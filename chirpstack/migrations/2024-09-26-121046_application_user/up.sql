@@ -0,0 +1,11 @@
+create table application_user (
+    application_id uuid not null references application on delete cascade,
+    user_id uuid not null references "user" on delete cascade,
+    created_at timestamp with time zone not null,
+    updated_at timestamp with time zone not null,
+    is_admin boolean not null,
+    is_device_admin boolean not null,
+    primary key (application_id, user_id)
+);
+
+create index idx_application_user_user_id on application_user (user_id);
@@ -0,0 +1,18 @@
+create table audit_log (
+    id uuid primary key,
+    created_at timestamp with time zone not null,
+    tenant_id uuid null,
+    user_id uuid null,
+    api_key_id uuid null,
+    service varchar(100) not null,
+    method varchar(100) not null,
+    target_id varchar(100) not null,
+    changes jsonb not null,
+    source_ip varchar(45) not null
+);
+
+create index idx_audit_log_created_at on audit_log (created_at);
+create index idx_audit_log_tenant_id_created_at on audit_log (tenant_id, created_at);
+create index idx_audit_log_user_id on audit_log (user_id);
+create index idx_audit_log_api_key_id on audit_log (api_key_id);
+create index idx_audit_log_target_id on audit_log (target_id);
@@ -0,0 +1,8 @@
+alter table tenant
+    drop column require_totp;
+
+drop table user_totp_recovery_code;
+
+alter table "user"
+    drop column totp_enabled,
+    drop column totp_secret;
@@ -0,0 +1,19 @@
+alter table "user"
+    add column totp_secret varchar(64) null,
+    add column totp_enabled boolean not null default false;
+
+alter table "user"
+    alter column totp_enabled drop default;
+
+create table user_totp_recovery_code (
+    user_id uuid not null references "user" on delete cascade,
+    code_hash varchar(64) not null,
+    created_at timestamp with time zone not null,
+    primary key (user_id, code_hash)
+);
+
+alter table tenant
+    add column require_totp boolean not null default false;
+
+alter table tenant
+    alter column require_totp drop default;
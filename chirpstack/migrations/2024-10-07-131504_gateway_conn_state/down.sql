@@ -0,0 +1,5 @@
+drop index idx_gateway_state_change_gateway_id_created_at;
+drop table gateway_state_change;
+
+alter table gateway
+    drop column is_online;
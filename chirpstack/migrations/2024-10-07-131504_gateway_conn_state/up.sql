@@ -0,0 +1,11 @@
+alter table gateway
+    add column is_online boolean null;
+
+create table gateway_state_change (
+    id uuid primary key,
+    gateway_id bytea not null references gateway on delete cascade,
+    created_at timestamp with time zone not null,
+    is_online boolean not null
+);
+
+create index idx_gateway_state_change_gateway_id_created_at on gateway_state_change(gateway_id, created_at);
@@ -0,0 +1,6 @@
+drop index idx_device_queue_item_expires_at;
+
+alter table device_queue_item
+    drop column priority;
+alter table device_queue_item
+    drop column expires_at;
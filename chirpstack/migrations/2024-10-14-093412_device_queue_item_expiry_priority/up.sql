@@ -0,0 +1,9 @@
+alter table device_queue_item
+    add column expires_at timestamp with time zone null;
+alter table device_queue_item
+    add column priority integer default 0 not null;
+
+alter table device_queue_item
+    alter column priority drop default;
+
+create index idx_device_queue_item_expires_at on device_queue_item (expires_at);
@@ -0,0 +1,2 @@
+drop index idx_fragmentation_session_dev_eui_frag_index;
+drop table fragmentation_session;
@@ -0,0 +1,18 @@
+create table fragmentation_session (
+    id uuid primary key,
+    dev_eui bytea not null references device on delete cascade,
+    created_at timestamp with time zone not null,
+    updated_at timestamp with time zone not null,
+    frag_index smallint not null,
+    nb_frag integer not null,
+    fragment_size smallint not null,
+    redundancy integer not null,
+    padding smallint not null,
+    nb_frag_sent integer not null,
+    nb_frag_received integer null,
+    missing_frag integer null,
+    status varchar(20) not null,
+    completed_at timestamp with time zone null
+);
+
+create index idx_fragmentation_session_dev_eui_frag_index on fragmentation_session(dev_eui, frag_index);
@@ -0,0 +1,7 @@
+drop index idx_fuota_campaign_device_dev_eui;
+drop table fuota_campaign_device;
+
+drop index idx_fuota_campaign_name_trgm;
+drop index idx_fuota_campaign_scheduler_run_after;
+drop index idx_fuota_campaign_application_id;
+drop table fuota_campaign;
@@ -0,0 +1,46 @@
+create table fuota_campaign (
+    id uuid primary key,
+    created_at timestamp with time zone not null,
+    updated_at timestamp with time zone not null,
+    started_at timestamp with time zone null,
+    completed_at timestamp with time zone null,
+    name varchar(100) not null,
+    application_id uuid not null references application on delete cascade,
+    device_profile_id uuid not null references device_profile on delete cascade,
+    multicast_group_id uuid null references multicast_group on delete set null,
+    multicast_group_type char(1) not null,
+    multicast_class_c_scheduling_type varchar(20) not null,
+    multicast_dr smallint not null,
+    multicast_class_b_ping_slot_nb_k smallint not null,
+    multicast_frequency bigint not null,
+    multicast_timeout smallint not null,
+    unicast_timeout integer not null,
+    fragment_size smallint not null,
+    redundancy integer not null,
+    payload bytea not null,
+    step varchar(20) not null,
+    scheduler_run_after timestamp with time zone not null,
+    session_start_at timestamp with time zone null
+);
+
+create index idx_fuota_campaign_application_id on fuota_campaign (application_id);
+create index idx_fuota_campaign_scheduler_run_after on fuota_campaign (scheduler_run_after);
+create index idx_fuota_campaign_name_trgm on fuota_campaign using gin (name gin_trgm_ops);
+
+create table fuota_campaign_device (
+    fuota_campaign_id uuid not null references fuota_campaign on delete cascade,
+    dev_eui bytea not null references device on delete cascade,
+    created_at timestamp with time zone not null,
+    updated_at timestamp with time zone not null,
+    mc_group_setup_completed_at timestamp with time zone null,
+    frag_session_setup_completed_at timestamp with time zone null,
+    mc_session_completed_at timestamp with time zone null,
+    frag_status_completed_at timestamp with time zone null,
+    nb_frag_received integer null,
+    missing_frag integer null,
+    error_msg text not null,
+
+    primary key (fuota_campaign_id, dev_eui)
+);
+
+create index idx_fuota_campaign_device_dev_eui on fuota_campaign_device (dev_eui);
@@ -0,0 +1,6 @@
+drop index idx_multicast_group_selector_device_profile_id;
+
+alter table multicast_group
+    drop column selector_tags,
+    drop column selector_device_profile_id,
+    drop column auto_membership;
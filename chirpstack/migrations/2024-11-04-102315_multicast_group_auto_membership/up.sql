@@ -0,0 +1,10 @@
+alter table multicast_group
+    add column auto_membership boolean not null default false,
+    add column selector_device_profile_id uuid null references device_profile on delete set null,
+    add column selector_tags jsonb not null default '{}';
+
+alter table multicast_group
+    alter column auto_membership drop default,
+    alter column selector_tags drop default;
+
+create index idx_multicast_group_selector_device_profile_id on multicast_group (selector_device_profile_id);
@@ -0,0 +1,2 @@
+drop index idx_multicast_group_queue_item_delivery_multicast_group_id_f_cnt;
+drop table multicast_group_queue_item_delivery;
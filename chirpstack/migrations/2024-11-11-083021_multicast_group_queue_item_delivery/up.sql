@@ -0,0 +1,11 @@
+create table multicast_group_queue_item_delivery (
+    multicast_group_queue_item_id uuid primary key,
+    multicast_group_id uuid not null references multicast_group on delete cascade,
+    created_at timestamp with time zone not null,
+    updated_at timestamp with time zone not null,
+    f_cnt bigint not null,
+    gateway_id bytea not null,
+    tx_ack_status varchar(30) null
+);
+
+create index idx_multicast_group_queue_item_delivery_multicast_group_id_f_cnt on multicast_group_queue_item_delivery (multicast_group_id, f_cnt);
@@ -0,0 +1,2 @@
+alter table device_profile
+    add column gateway_selection_strategy varchar(20) null;
@@ -0,0 +1,5 @@
+alter table "user"
+    add column totp_last_counter bigint not null default 0;
+
+alter table "user"
+    alter column totp_last_counter drop default;
@@ -0,0 +1,5 @@
+alter table device_keys
+    add column gen_app_key bytea not null default '\x00000000000000000000000000000000';
+
+alter table device_keys
+    alter column gen_app_key drop default;
@@ -0,0 +1 @@
+drop index idx_fragmentation_session_status_updated_at;
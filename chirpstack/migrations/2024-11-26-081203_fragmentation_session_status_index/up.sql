@@ -0,0 +1 @@
+create index idx_fragmentation_session_status_updated_at on fragmentation_session (status, updated_at);
@@ -0,0 +1 @@
+drop index idx_multicast_group_queue_item_delivery_updated_at;
@@ -0,0 +1 @@
+create index idx_multicast_group_queue_item_delivery_updated_at on multicast_group_queue_item_delivery (updated_at);
@@ -0,0 +1,5 @@
+alter table device_profile
+    add column supports_clock_sync boolean not null default false;
+
+alter table device_profile
+    alter column supports_clock_sync drop default;
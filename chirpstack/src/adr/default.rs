@@ -65,7 +65,7 @@ impl Algorithm {
             .count()
     }
 
-    fn get_max_snr(&self, req: &Request) -> f32 {
+    pub fn get_max_snr(&self, req: &Request) -> f32 {
         let mut max_snr: f32 = -999.0;
 
         for uh in &req.uplink_history {
@@ -77,6 +77,13 @@ impl Algorithm {
         max_snr
     }
 
+    // Returns the SNR margin and the number of steps by which the data-rate or TX power must
+    // be changed.
+    pub fn get_snr_margin_and_nb_step(&self, req: &Request) -> (f32, isize) {
+        let snr_margin = self.get_max_snr(req) - req.required_snr_for_dr - req.installation_margin;
+        (snr_margin, (snr_margin / 3.0) as isize)
+    }
+
     fn get_nb_trans(&self, current_nb_trans: u8, pkt_loss_rate: f32) -> u8 {
         let pkt_loss_table: [[u8; 3]; 4] = [[1, 1, 2], [1, 2, 3], [2, 3, 3], [3, 3, 3]];
 
@@ -171,9 +178,7 @@ impl Handler for Algorithm {
         resp.nb_trans = self.get_nb_trans(req.nb_trans, self.get_packet_loss_percentage(req));
 
         // Calculate the number of steps.
-        let snr_max = self.get_max_snr(req);
-        let snr_margin = snr_max - req.required_snr_for_dr - req.installation_margin;
-        let n_step = (snr_margin / 3.0) as isize;
+        let (_, n_step) = self.get_snr_margin_and_nb_step(req);
 
         // In case of negative steps the ADR algorithm will increase the TxPower
         // if possible. To avoid up / down / up / down TxPower changes, wait until
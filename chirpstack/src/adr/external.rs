@@ -0,0 +1,423 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use prost::Message;
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use tonic::transport::{Channel, Endpoint};
+use tracing::{info, warn};
+
+use super::{default, lora_lr_fhss, lr_fhss, Handler, Request, Response};
+use crate::{config, region};
+use chirpstack_api::api;
+use chirpstack_api::api::adr_plugin_service_client::AdrPluginServiceClient;
+use lrwn::region::DataRateModulation;
+
+enum Client {
+    Grpc(AdrPluginServiceClient<Channel>),
+    Http(reqwest::Client),
+}
+
+// Plugin implements an ADR algorithm which is provided by an external service. The ID and name
+// are requested once, on creating the plugin. In case the service returns an error or does not
+// respond within the configured timeout, the included ADR algorithm matching the data-rates of
+// the device is used.
+pub struct Plugin {
+    server: String,
+    timeout: Duration,
+    client: Client,
+    id: String,
+    name: String,
+}
+
+impl Plugin {
+    pub async fn new(conf: &config::AdrExternalPlugin) -> Result<Self> {
+        let client = match conf.protocol.as_ref() {
+            "grpc" => Client::Grpc(AdrPluginServiceClient::new(
+                Endpoint::from_shared(conf.server.clone())
+                    .context("Parse server")?
+                    .timeout(conf.timeout)
+                    .connect_timeout(conf.timeout)
+                    .connect_lazy(),
+            )),
+            "http" => Client::Http(reqwest::Client::builder().timeout(conf.timeout).build()?),
+            _ => return Err(anyhow!("Unexpected protocol: {}", conf.protocol)),
+        };
+
+        let mut p = Plugin {
+            server: conf.server.clone(),
+            timeout: conf.timeout,
+            client,
+            id: "".into(),
+            name: "".into(),
+        };
+
+        let info = p.get_info().await.context("Get ADR plugin info")?;
+        if info.id.is_empty() {
+            return Err(anyhow!("ADR plugin {} returned an empty ID", conf.server));
+        }
+
+        info!(server = %conf.server, id = %info.id, name = %info.name, "External ADR plugin info received");
+        p.id = info.id;
+        p.name = info.name;
+
+        Ok(p)
+    }
+
+    async fn get_info(&self) -> Result<api::GetAdrPluginInfoResponse> {
+        match &self.client {
+            Client::Grpc(c) => Ok(c.clone().get_info(()).await?.into_inner()),
+            Client::Http(c) => self.post(c, "GetInfo", &()).await,
+        }
+    }
+
+    async fn handle_external(&self, req: &Request) -> Result<Response> {
+        let req = api::HandleAdrRequest {
+            region_config_id: req.region_config_id.clone(),
+            region_common_name: req.region_common_name.to_string(),
+            dev_eui: req.dev_eui.to_string(),
+            mac_version: req.mac_version.to_string(),
+            reg_params_revision: req.reg_params_revision.to_string(),
+            adr: req.adr,
+            dr: req.dr.into(),
+            tx_power_index: req.tx_power_index.into(),
+            nb_trans: req.nb_trans.into(),
+            max_tx_power_index: req.max_tx_power_index.into(),
+            required_snr_for_dr: req.required_snr_for_dr,
+            installation_margin: req.installation_margin,
+            min_dr: req.min_dr.into(),
+            max_dr: req.max_dr.into(),
+            uplink_history: req
+                .uplink_history
+                .iter()
+                .map(|uh| api::AdrUplinkHistory {
+                    f_cnt: uh.f_cnt,
+                    max_snr: uh.max_snr,
+                    max_rssi: uh.max_rssi,
+                    tx_power_index: uh.tx_power_index,
+                    gateway_count: uh.gateway_count,
+                })
+                .collect(),
+            skip_f_cnt_check: req.skip_f_cnt_check,
+            device_variables: req.device_variables.clone(),
+        };
+
+        let resp = match &self.client {
+            Client::Grpc(c) => c.clone().handle(req).await?.into_inner(),
+            Client::Http(c) => self.post(c, "Handle", &req).await?,
+        };
+
+        Ok(Response {
+            dr: resp.dr.try_into().context("dr")?,
+            tx_power_index: resp.tx_power_index.try_into().context("tx_power_index")?,
+            nb_trans: resp.nb_trans.try_into().context("nb_trans")?,
+        })
+    }
+
+    // Posts the Protobuf encoded request to the server, with the RPC method as URL parameter.
+    async fn post<Req, Resp>(&self, c: &reqwest::Client, method: &str, req: &Req) -> Result<Resp>
+    where
+        Req: Message,
+        Resp: Message + Default,
+    {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, "application/octet-stream".parse().unwrap());
+
+        let resp = c
+            .post(&self.server)
+            .query(&[("method", method)])
+            .headers(headers)
+            .body(req.encode_to_vec())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(Resp::decode(resp.bytes().await?)?)
+    }
+}
+
+#[async_trait]
+impl Handler for Plugin {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get_id(&self) -> String {
+        self.id.clone()
+    }
+
+    async fn handle(&self, req: &Request) -> Result<Response> {
+        match self.handle_external(req).await {
+            Ok(v) => Ok(v),
+            Err(e) => {
+                let fallback = get_fallback_algorithm(req)?;
+                warn!(algorithm_id = %self.id, server = %self.server, timeout = ?self.timeout, fallback_algorithm_id = %fallback.get_id(), error = %e, "External ADR plugin error, falling back to included algorithm");
+                fallback.handle(req).await
+            }
+        }
+    }
+}
+
+// Returns the included ADR algorithm matching the enabled data-rates of the device: the LoRa
+// (default) algorithm, the LR-FHSS algorithm or the combined LoRa & LR-FHSS algorithm.
+fn get_fallback_algorithm(req: &Request) -> Result<Box<dyn Handler + Sync + Send>> {
+    let region_conf = region::get(&req.region_config_id).context("Get region config")?;
+
+    let (mut lora, mut lr_fhss) = (false, false);
+    for dr in req.min_dr..=req.max_dr {
+        match region_conf.get_data_rate(dr) {
+            Ok(DataRateModulation::Lora(_)) => lora = true,
+            Ok(DataRateModulation::LrFhss(_)) => lr_fhss = true,
+            _ => {}
+        }
+    }
+
+    Ok(match (lora, lr_fhss) {
+        (true, true) => Box::new(lora_lr_fhss::Algorithm::new()),
+        (false, true) => Box::new(lr_fhss::Algorithm::new()),
+        _ => Box::new(default::Algorithm::new()),
+    })
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::test;
+    use chirpstack_api::internal;
+    use httpmock::prelude::*;
+    use lrwn::EUI64;
+
+    fn get_request() -> Request {
+        Request {
+            region_config_id: "eu868".into(),
+            region_common_name: lrwn::region::CommonName::EU868,
+            dev_eui: EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            mac_version: lrwn::region::MacVersion::LORAWAN_1_0_3,
+            reg_params_revision: lrwn::region::Revision::A,
+            adr: true,
+            dr: 0,
+            tx_power_index: 0,
+            nb_trans: 1,
+            max_tx_power_index: 15,
+            required_snr_for_dr: -20.0,
+            installation_margin: 10.0,
+            min_dr: 0,
+            max_dr: 5,
+            uplink_history: (0..20)
+                .map(|i| internal::UplinkAdrHistory {
+                    f_cnt: i,
+                    max_snr: 10.0,
+                    max_rssi: -50,
+                    tx_power_index: 0,
+                    gateway_count: 1,
+                })
+                .collect(),
+            skip_f_cnt_check: false,
+            device_variables: [("foo".to_string(), "bar".to_string())]
+                .iter()
+                .cloned()
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_http() {
+        let _guard = test::prepare().await;
+        let server = MockServer::start();
+
+        let mut info_mock = server.mock(|when, then| {
+            when.method(POST).path("/").query_param("method", "GetInfo");
+            then.body(
+                api::GetAdrPluginInfoResponse {
+                    id: "external_id".into(),
+                    name: "External plugin".into(),
+                }
+                .encode_to_vec(),
+            );
+        });
+
+        let p = Plugin::new(&config::AdrExternalPlugin {
+            protocol: "http".into(),
+            server: server.url("/"),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        info_mock.assert();
+        info_mock.delete();
+
+        assert_eq!("external_id", p.get_id());
+        assert_eq!("External plugin", p.get_name());
+
+        // plugin response
+        let mut handle_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .query_param("method", "Handle")
+                .matches(|req| {
+                    let req =
+                        api::HandleAdrRequest::decode(req.body.as_deref().unwrap_or_default())
+                            .unwrap();
+                    req.dev_eui == "0102030405060708"
+                        && req.uplink_history.len() == 20
+                        && req.device_variables.get("foo") == Some(&"bar".to_string())
+                });
+            then.body(
+                api::HandleAdrResponse {
+                    dr: 3,
+                    tx_power_index: 1,
+                    nb_trans: 2,
+                }
+                .encode_to_vec(),
+            );
+        });
+
+        let req = get_request();
+        assert_eq!(
+            Response {
+                dr: 3,
+                tx_power_index: 1,
+                nb_trans: 2,
+            },
+            p.handle(&req).await.unwrap()
+        );
+        handle_mock.assert();
+        handle_mock.delete();
+
+        // plugin error, the default algorithm is used
+        let mut handle_mock = server.mock(|when, then| {
+            when.method(POST).path("/").query_param("method", "Handle");
+            then.status(500);
+        });
+
+        assert_eq!(
+            default::Algorithm::new().handle(&req).await.unwrap(),
+            p.handle(&req).await.unwrap()
+        );
+        handle_mock.assert();
+        handle_mock.delete();
+
+        // plugin timeout, the default algorithm is used
+        server.mock(|when, then| {
+            when.method(POST).path("/").query_param("method", "Handle");
+            then.delay(Duration::from_millis(500)).body(
+                api::HandleAdrResponse {
+                    dr: 3,
+                    tx_power_index: 1,
+                    nb_trans: 2,
+                }
+                .encode_to_vec(),
+            );
+        });
+
+        assert_eq!(
+            default::Algorithm::new().handle(&req).await.unwrap(),
+            p.handle(&req).await.unwrap()
+        );
+    }
+
+    struct TestService {}
+
+    #[tonic::async_trait]
+    impl api::adr_plugin_service_server::AdrPluginService for TestService {
+        async fn get_info(
+            &self,
+            _request: tonic::Request<()>,
+        ) -> Result<tonic::Response<api::GetAdrPluginInfoResponse>, tonic::Status> {
+            Ok(tonic::Response::new(api::GetAdrPluginInfoResponse {
+                id: "external_id".into(),
+                name: "External plugin".into(),
+            }))
+        }
+
+        async fn handle(
+            &self,
+            request: tonic::Request<api::HandleAdrRequest>,
+        ) -> Result<tonic::Response<api::HandleAdrResponse>, tonic::Status> {
+            let req = request.into_inner();
+            if req.device_variables.get("foo").cloned().unwrap_or_default() != "bar" {
+                return Err(tonic::Status::invalid_argument("foo must be bar"));
+            }
+
+            Ok(tonic::Response::new(api::HandleAdrResponse {
+                dr: req.max_dr,
+                tx_power_index: req.uplink_history.len() as u32,
+                nb_trans: 1,
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_grpc() {
+        let _guard = test::prepare().await;
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(api::adr_plugin_service_server::AdrPluginServiceServer::new(
+                    TestService {},
+                ))
+                .serve(addr),
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let p = Plugin::new(&config::AdrExternalPlugin {
+            protocol: "grpc".into(),
+            server: format!("http://{}", addr),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        assert_eq!("external_id", p.get_id());
+        assert_eq!("External plugin", p.get_name());
+
+        let mut req = get_request();
+        assert_eq!(
+            Response {
+                dr: 5,
+                tx_power_index: 20,
+                nb_trans: 1,
+            },
+            p.handle(&req).await.unwrap()
+        );
+
+        // plugin error, the default algorithm is used
+        req.device_variables.clear();
+        assert_eq!(
+            default::Algorithm::new().handle(&req).await.unwrap(),
+            p.handle(&req).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_grpc_unavailable() {
+        assert!(Plugin::new(&config::AdrExternalPlugin {
+            protocol: "grpc".into(),
+            server: "http://127.0.0.1:1".into(),
+            ..Default::default()
+        })
+        .await
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_fallback_algorithm() {
+        let _guard = test::prepare().await;
+
+        let mut req = get_request();
+        assert_eq!("default", get_fallback_algorithm(&req).unwrap().get_id());
+
+        req.min_dr = 10;
+        req.max_dr = 11;
+        assert_eq!("lr_fhss", get_fallback_algorithm(&req).unwrap().get_id());
+
+        req.min_dr = 0;
+        assert_eq!(
+            "lora_lr_fhss",
+            get_fallback_algorithm(&req).unwrap().get_id()
+        );
+    }
+}
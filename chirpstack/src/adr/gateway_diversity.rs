@@ -0,0 +1,441 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+
+use super::{Handler, Request, Response};
+use crate::region;
+
+// Number of gateways that should receive each uplink. Below this target,
+// an extra margin is applied to the SNR margin.
+const TARGET_GATEWAY_COUNT: f32 = 2.0;
+
+// Extra margin (dB) per missing gateway below the target gateway count.
+const GATEWAY_DIVERSITY_MARGIN: f32 = 3.0;
+
+pub struct Algorithm {}
+
+impl Algorithm {
+    pub fn new() -> Self {
+        Algorithm {}
+    }
+
+    fn required_history_count(&self) -> usize {
+        20
+    }
+
+    fn get_max_snr(&self, req: &Request) -> f32 {
+        req.uplink_history
+            .iter()
+            .map(|uh| uh.max_snr)
+            .fold(-999.0, f32::max)
+    }
+
+    // Returns the average number of gateways receiving the uplinks.
+    fn get_avg_gateway_count(&self, req: &Request) -> f32 {
+        if req.uplink_history.is_empty() {
+            return 0.0;
+        }
+
+        let total: u32 = req.uplink_history.iter().map(|uh| uh.gateway_count).sum();
+        total as f32 / req.uplink_history.len() as f32
+    }
+
+    // Returns the packet error rate (%), based on the f_cnt gaps in the uplink history.
+    fn get_packet_error_rate(&self, req: &Request) -> f32 {
+        if req.uplink_history.len() < self.required_history_count() {
+            return 0.0;
+        }
+
+        let first = req.uplink_history.first().unwrap().f_cnt;
+        let last = req.uplink_history.last().unwrap().f_cnt;
+        let expected = last.saturating_sub(first) + 1;
+        let received = req.uplink_history.len() as u32;
+
+        if expected <= received {
+            return 0.0;
+        }
+
+        (expected - received) as f32 / expected as f32 * 100.0
+    }
+
+    // Redundancy is only raised when the packet error rate is high. As the
+    // packet error rate is measured with the current nb_trans, it is lowered
+    // again once the packet error rate is low.
+    fn get_nb_trans(&self, current_nb_trans: u8, per: f32) -> u8 {
+        let current_nb_trans = current_nb_trans.clamp(1, 3);
+
+        if per >= 30.0 {
+            3
+        } else if per >= 10.0 {
+            (current_nb_trans + 1).min(3)
+        } else if per < 5.0 {
+            current_nb_trans - 1
+        } else {
+            current_nb_trans
+        }
+        .max(1)
+    }
+
+    // Unlike the default algorithm, a negative number of steps also lowers the
+    // data-rate (after increasing the tx-power) in case the gateway diversity
+    // is poor.
+    fn get_ideal_tx_power_index_and_dr(
+        nb_step: isize,
+        tx_power_index: u8,
+        dr: u8,
+        max_tx_power_index: u8,
+        min_dr: u8,
+        max_dr: u8,
+        lower_dr: bool,
+    ) -> (u8, u8) {
+        let mut tx_power_index = tx_power_index;
+        let mut dr = dr;
+
+        if nb_step > 0 {
+            for _ in 0..nb_step {
+                if dr < max_dr {
+                    // Increase the DR.
+                    dr += 1;
+                } else if tx_power_index < max_tx_power_index {
+                    // Decrease the tx-power.
+                    // (note that an increase in index decreases the tx-power)
+                    tx_power_index += 1;
+                }
+            }
+        } else {
+            for _ in nb_step..0 {
+                if tx_power_index > 0 {
+                    // Increase the tx-power.
+                    // (note that a decrease in index increases the tx-power)
+                    tx_power_index -= 1;
+                } else if lower_dr && dr > min_dr {
+                    // Decrease the DR.
+                    dr -= 1;
+                }
+            }
+        }
+
+        (tx_power_index, dr)
+    }
+}
+
+#[async_trait]
+impl Handler for Algorithm {
+    fn get_name(&self) -> String {
+        "Gateway diversity ADR algorithm (LoRa only)".to_string()
+    }
+
+    fn get_id(&self) -> String {
+        "gateway_diversity".to_string()
+    }
+
+    async fn handle(&self, req: &Request) -> Result<Response> {
+        let mut resp = Response {
+            dr: req.dr,
+            tx_power_index: req.tx_power_index,
+            nb_trans: req.nb_trans,
+        };
+
+        // If ADR is disabled, return with current values.
+        if !req.adr {
+            return Ok(resp);
+        }
+
+        // As this algorithm works on LoRa (125kHz) data-rates only, we need to
+        // find the max LoRa (125 kHz) data-rate.
+        let region_conf =
+            region::get(&req.region_config_id).context("Get region config for region")?;
+        let max_lora_dr = region_conf
+            .get_enabled_uplink_data_rates()
+            .into_iter()
+            .filter(|dr| {
+                let dr = region_conf.get_data_rate(*dr).unwrap();
+                if let lrwn::region::DataRateModulation::Lora(l) = dr {
+                    l.bandwidth == 125000
+                } else {
+                    false
+                }
+            })
+            .max()
+            .unwrap_or(0);
+        let max_dr = req.max_dr.min(max_lora_dr);
+
+        // Lower the DR only if it exceeds the max. allowed DR.
+        if req.dr > max_dr {
+            resp.dr = max_dr;
+        }
+
+        resp.nb_trans = self.get_nb_trans(req.nb_trans, self.get_packet_error_rate(req));
+
+        // Apply an extra margin in case the uplinks are received by less gateways
+        // than the target gateway count.
+        let avg_gateway_count = self.get_avg_gateway_count(req);
+        let diversity_margin =
+            (TARGET_GATEWAY_COUNT - avg_gateway_count).max(0.0) * GATEWAY_DIVERSITY_MARGIN;
+        let poor_diversity = avg_gateway_count < TARGET_GATEWAY_COUNT;
+
+        // Calculate the number of steps.
+        let snr_margin = self.get_max_snr(req)
+            - req.required_snr_for_dr
+            - req.installation_margin
+            - diversity_margin;
+        let nb_step = (snr_margin / 3.0) as isize;
+
+        // To avoid up / down / up / down changes, wait until we have at least the
+        // required number of uplink history elements before making negative steps.
+        if nb_step < 0 && req.uplink_history.len() != self.required_history_count() {
+            return Ok(resp);
+        }
+
+        let (tx_power_index, dr) = Self::get_ideal_tx_power_index_and_dr(
+            nb_step,
+            resp.tx_power_index,
+            resp.dr,
+            req.max_tx_power_index,
+            req.min_dr,
+            max_dr,
+            poor_diversity,
+        );
+
+        resp.tx_power_index = tx_power_index;
+        resp.dr = dr;
+
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::test;
+    use chirpstack_api::internal;
+    use std::str::FromStr;
+
+    fn get_uplink_history(
+        count: u32,
+        max_snr: f32,
+        gateway_count: u32,
+    ) -> Vec<internal::UplinkAdrHistory> {
+        (0..count)
+            .map(|i| internal::UplinkAdrHistory {
+                f_cnt: i,
+                max_snr,
+                gateway_count,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_id() {
+        let a = Algorithm::new();
+        assert_eq!("gateway_diversity", a.get_id());
+    }
+
+    #[test]
+    fn test_get_nb_trans() {
+        let a = Algorithm::new();
+
+        // (current nb_trans, per, expected nb_trans)
+        let tests = vec![
+            (1, 0.0, 1),
+            (3, 0.0, 2),
+            (2, 7.5, 2),
+            (1, 10.0, 2),
+            (2, 20.0, 3),
+            (3, 20.0, 3),
+            (1, 30.0, 3),
+            (0, 0.0, 1),
+        ];
+
+        for (current_nb_trans, per, expected_nb_trans) in tests {
+            assert_eq!(expected_nb_trans, a.get_nb_trans(current_nb_trans, per));
+        }
+    }
+
+    #[test]
+    fn test_get_packet_error_rate_and_gateway_count() {
+        let a = Algorithm::new();
+        let mut req = Request {
+            region_config_id: "eu868".into(),
+            region_common_name: lrwn::region::CommonName::EU868,
+            dev_eui: lrwn::EUI64::from_str("0102030405060708").unwrap(),
+            mac_version: lrwn::region::MacVersion::LORAWAN_1_0_4,
+            reg_params_revision: lrwn::region::Revision::RP002_1_0_3,
+            adr: true,
+            dr: 0,
+            tx_power_index: 0,
+            nb_trans: 1,
+            max_tx_power_index: 0,
+            required_snr_for_dr: 0.0,
+            installation_margin: 0.0,
+            min_dr: 0,
+            max_dr: 0,
+            uplink_history: vec![],
+            skip_f_cnt_check: false,
+            device_variables: Default::default(),
+        };
+
+        assert_eq!(0.0, a.get_packet_error_rate(&req));
+        assert_eq!(0.0, a.get_avg_gateway_count(&req));
+
+        // 20 uplinks, 5 missing (f_cnt 0 - 24).
+        req.uplink_history = (0..25)
+            .filter(|i| i % 5 != 2)
+            .map(|i| internal::UplinkAdrHistory {
+                f_cnt: i,
+                gateway_count: 1 + i % 2,
+                ..Default::default()
+            })
+            .collect();
+
+        assert_eq!(20.0, a.get_packet_error_rate(&req));
+        assert_eq!(1.5, a.get_avg_gateway_count(&req));
+    }
+
+    #[tokio::test]
+    async fn test_handle() {
+        let a = Algorithm::new();
+        let _guard = test::prepare().await;
+
+        let req_template = Request {
+            region_config_id: "eu868".into(),
+            region_common_name: lrwn::region::CommonName::EU868,
+            dev_eui: lrwn::EUI64::from_str("0102030405060708").unwrap(),
+            mac_version: lrwn::region::MacVersion::LORAWAN_1_0_4,
+            reg_params_revision: lrwn::region::Revision::RP002_1_0_3,
+            adr: true,
+            dr: 0,
+            tx_power_index: 0,
+            nb_trans: 1,
+            max_tx_power_index: 5,
+            required_snr_for_dr: -20.0,
+            installation_margin: 10.0,
+            min_dr: 0,
+            max_dr: 5,
+            uplink_history: vec![],
+            skip_f_cnt_check: false,
+            device_variables: Default::default(),
+        };
+
+        struct Test {
+            name: String,
+            request: Request,
+            response: Response,
+        }
+
+        let tests = vec![
+            Test {
+                name: "adr disabled".into(),
+                request: Request {
+                    adr: false,
+                    nb_trans: 3,
+                    uplink_history: get_uplink_history(20, 10.0, 1),
+                    ..req_template.clone()
+                },
+                response: Response {
+                    dr: 0,
+                    tx_power_index: 0,
+                    nb_trans: 3,
+                },
+            },
+            Test {
+                name: "good diversity: increase dr and decrease tx-power".into(),
+                request: Request {
+                    uplink_history: get_uplink_history(20, 10.0, 3),
+                    ..req_template.clone()
+                },
+                response: Response {
+                    dr: 5,
+                    tx_power_index: 1,
+                    nb_trans: 1,
+                },
+            },
+            Test {
+                name: "poor diversity: extra margin results in fewer steps".into(),
+                request: Request {
+                    uplink_history: get_uplink_history(20, 10.0, 1),
+                    ..req_template.clone()
+                },
+                response: Response {
+                    dr: 5,
+                    tx_power_index: 0,
+                    nb_trans: 1,
+                },
+            },
+            Test {
+                name: "poor diversity: increase tx-power, then lower dr".into(),
+                request: Request {
+                    dr: 5,
+                    tx_power_index: 1,
+                    required_snr_for_dr: -7.5,
+                    uplink_history: get_uplink_history(20, -7.5, 1),
+                    ..req_template.clone()
+                },
+                response: Response {
+                    dr: 2,
+                    tx_power_index: 0,
+                    nb_trans: 1,
+                },
+            },
+            Test {
+                name: "good diversity: dr is not lowered".into(),
+                request: Request {
+                    dr: 5,
+                    tx_power_index: 1,
+                    required_snr_for_dr: -7.5,
+                    uplink_history: get_uplink_history(20, -18.0, 2),
+                    ..req_template.clone()
+                },
+                response: Response {
+                    dr: 5,
+                    tx_power_index: 0,
+                    nb_trans: 1,
+                },
+            },
+            Test {
+                name: "poor diversity: not enough history to lower dr".into(),
+                request: Request {
+                    dr: 5,
+                    required_snr_for_dr: -7.5,
+                    uplink_history: get_uplink_history(10, -7.5, 1),
+                    ..req_template.clone()
+                },
+                response: Response {
+                    dr: 5,
+                    tx_power_index: 0,
+                    nb_trans: 1,
+                },
+            },
+            Test {
+                name: "high packet error rate: increase nb_trans".into(),
+                request: Request {
+                    dr: 5,
+                    nb_trans: 1,
+                    required_snr_for_dr: -7.5,
+                    installation_margin: 0.0,
+                    uplink_history: (0..40)
+                        .filter(|i| i % 2 == 0)
+                        .map(|i| internal::UplinkAdrHistory {
+                            f_cnt: i,
+                            max_snr: -7.5,
+                            gateway_count: 2,
+                            ..Default::default()
+                        })
+                        .collect(),
+                    ..req_template.clone()
+                },
+                response: Response {
+                    dr: 5,
+                    tx_power_index: 0,
+                    nb_trans: 3,
+                },
+            },
+        ];
+
+        for tst in &tests {
+            println!("> {}", tst.name);
+            let resp = a.handle(&tst.request).await.unwrap();
+            assert_eq!(tst.response, resp);
+        }
+    }
+}
@@ -1,21 +1,36 @@
 use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use anyhow::Result;
 use async_trait::async_trait;
 use tokio::sync::RwLock;
-use tracing::{info, trace, warn};
+use tokio::time::sleep;
+use tracing::{error, info, trace, warn};
 
-use crate::config;
+use crate::helpers::errors::PrintFullError;
+use crate::storage::{device, device_profile};
+use crate::{config, region};
 use chirpstack_api::internal;
 use lrwn::EUI64;
 
 pub mod default;
+pub mod external;
+pub mod gateway_diversity;
 pub mod lora_lr_fhss;
 pub mod lr_fhss;
 pub mod plugin;
 
+const EXTERNAL_PLUGIN_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
 lazy_static! {
-    static ref ADR_ALGORITHMS: RwLock<HashMap<String, Box<dyn Handler + Sync + Send>>> =
+    static ref ADR_ALGORITHMS: RwLock<HashMap<String, Arc<dyn Handler + Sync + Send>>> =
+        RwLock::new(HashMap::new());
+
+    // PLUGIN_FILES contains per ADR plugin file-path the last modification time
+    // and the ID of the loaded algorithm.
+    static ref PLUGIN_FILES: RwLock<HashMap<String, (Option<SystemTime>, String)>> =
         RwLock::new(HashMap::new());
 }
 
@@ -25,25 +40,135 @@ pub async fn setup() -> Result<()> {
 
     trace!("Setting up included algorithms");
     let a = default::Algorithm::new();
-    algos.insert(a.get_id(), Box::new(a));
+    algos.insert(a.get_id(), Arc::new(a));
 
     let a = lr_fhss::Algorithm::new();
-    algos.insert(a.get_id(), Box::new(a));
+    algos.insert(a.get_id(), Arc::new(a));
 
     let a = lora_lr_fhss::Algorithm::new();
-    algos.insert(a.get_id(), Box::new(a));
+    algos.insert(a.get_id(), Arc::new(a));
+
+    let a = gateway_diversity::Algorithm::new();
+    algos.insert(a.get_id(), Arc::new(a));
 
     trace!("Setting up plugins");
     let conf = config::get();
     for file_path in &conf.network.adr_plugins {
         info!(file_path = %file_path, "Setting up ADR plugin");
         let a = plugin::Plugin::new(file_path)?;
-        algos.insert(a.get_id(), Box::new(a));
+        if algos.contains_key(&a.get_id()) {
+            return Err(anyhow!(
+                "ADR plugin {} uses ID {} which is already in use",
+                file_path,
+                a.get_id()
+            ));
+        }
+        PLUGIN_FILES
+            .write()
+            .await
+            .insert(file_path.clone(), (get_modified(file_path), a.get_id()));
+        algos.insert(a.get_id(), Arc::new(a));
+    }
+
+    // The external plugins are registered in the background, such that an unavailable external
+    // service does not prevent ChirpStack from starting.
+    trace!("Setting up external plugins");
+    for plugin_conf in &conf.network.adr_external_plugins {
+        info!(protocol = %plugin_conf.protocol, server = %plugin_conf.server, "Setting up external ADR plugin");
+        tokio::spawn({
+            let plugin_conf = plugin_conf.clone();
+
+            async move {
+                register_external_plugin(&plugin_conf).await;
+            }
+        });
+    }
+
+    if !conf.network.adr_plugins.is_empty() && !conf.network.adr_plugins_reload_interval.is_zero() {
+        info!(interval = ?conf.network.adr_plugins_reload_interval, "Setting up ADR plugins reload loop");
+        tokio::spawn(async move {
+            plugin_reload_loop().await;
+        });
     }
 
     Ok(())
 }
 
+// Registers the external ADR plugin. In case the plugin info can not be retrieved, this is
+// retried until it succeeds. Plugins of which the ID is already in use (e.g. by one of the
+// included algorithms) are not registered.
+async fn register_external_plugin(conf: &config::AdrExternalPlugin) {
+    let a = loop {
+        match external::Plugin::new(conf).await {
+            Ok(v) => break v,
+            Err(e) => {
+                warn!(server = %conf.server, error = %e.full(), retry_in = ?EXTERNAL_PLUGIN_RETRY_INTERVAL, "Setting up external ADR plugin failed");
+                sleep(EXTERNAL_PLUGIN_RETRY_INTERVAL).await;
+            }
+        }
+    };
+
+    let mut algos = ADR_ALGORITHMS.write().await;
+    if algos.contains_key(&a.get_id()) {
+        error!(server = %conf.server, algorithm_id = %a.get_id(), "External ADR plugin ID is already in use, plugin is not registered");
+        return;
+    }
+
+    info!(server = %conf.server, algorithm_id = %a.get_id(), "External ADR plugin registered");
+    algos.insert(a.get_id(), Arc::new(a));
+}
+
+async fn plugin_reload_loop() {
+    let conf = config::get();
+
+    loop {
+        sleep(conf.network.adr_plugins_reload_interval).await;
+        trace!("Checking ADR plugins for changes");
+
+        let mut files = PLUGIN_FILES.write().await;
+        for (file_path, (modified, id)) in files.iter_mut() {
+            let m = get_modified(file_path);
+            if m == *modified {
+                continue;
+            }
+            *modified = m;
+
+            match reload_plugin(file_path, id).await {
+                Ok(v) => {
+                    info!(file_path = %file_path, algorithm_id = %v, "ADR plugin reloaded");
+                    *id = v;
+                }
+                Err(e) => {
+                    warn!(file_path = %file_path, algorithm_id = %id, error = %e, "Reloading ADR plugin failed, keeping previous version");
+                }
+            }
+        }
+    }
+}
+
+// Loads the ADR plugin from the given file-path and replaces the algorithm with the given ID.
+// In case the plugin fails to load, the current algorithm is kept. Returns the ID of the
+// reloaded algorithm.
+pub async fn reload_plugin(file_path: &str, current_id: &str) -> Result<String> {
+    let a = plugin::Plugin::new(file_path)?;
+    let id = a.get_id();
+
+    let mut algos = ADR_ALGORITHMS.write().await;
+    if id != current_id {
+        if algos.contains_key(&id) {
+            return Err(anyhow!("ADR algorithm {} already exists", id));
+        }
+        algos.remove(current_id);
+    }
+    algos.insert(id.clone(), Arc::new(a));
+
+    Ok(id)
+}
+
+fn get_modified(file_path: &str) -> Option<SystemTime> {
+    fs::metadata(file_path).and_then(|m| m.modified()).ok()
+}
+
 pub async fn get_algorithms() -> HashMap<String, String> {
     let mut out: HashMap<String, String> = HashMap::new();
 
@@ -56,8 +181,10 @@ pub async fn get_algorithms() -> HashMap<String, String> {
 }
 
 pub async fn handle(algo_id: &str, req: &Request) -> Response {
-    let algos = ADR_ALGORITHMS.read().await;
-    match algos.get(algo_id) {
+    // The algorithm is cloned out of the map, such that the lock is not held while the
+    // algorithm is handling the request (e.g. an external plugin making a network call).
+    let algo = ADR_ALGORITHMS.read().await.get(algo_id).cloned();
+    match algo {
         Some(v) => match v.handle(req).await {
             Ok(v) => v,
             Err(e) => {
@@ -113,9 +240,253 @@ pub struct Request {
     pub device_variables: HashMap<String, String>,
 }
 
+impl Request {
+    // Returns the request for the given device, using the device-session values and the
+    // network configuration of the given region. The max. TX power index and the required
+    // SNR are derived from the region configuration and the given data-rate.
+    pub fn new(
+        region_config_id: &str,
+        d: &device::Device,
+        dp: &device_profile::DeviceProfile,
+        ds: &internal::DeviceSession,
+        dr: u8,
+    ) -> Result<Self> {
+        let region_conf = region::get(region_config_id)?;
+        let network_conf = config::get_region_network(region_config_id)?;
+
+        Ok(Request {
+            region_config_id: region_config_id.to_string(),
+            region_common_name: region_conf.get_name(),
+            dev_eui: d.dev_eui,
+            mac_version: dp.mac_version,
+            reg_params_revision: dp.reg_params_revision,
+            adr: ds.adr,
+            dr,
+            tx_power_index: ds.tx_power_index as u8,
+            nb_trans: ds.nb_trans as u8,
+            max_tx_power_index: if ds.max_supported_tx_power_index != 0 {
+                ds.max_supported_tx_power_index as u8
+            } else {
+                let mut max_tx_power_index: u8 = 0;
+                for n in 0..16 {
+                    if region_conf.get_tx_power_offset(n).is_ok() {
+                        max_tx_power_index = n as u8;
+                    }
+                }
+                max_tx_power_index
+            },
+            required_snr_for_dr: match region_conf.get_data_rate(dr)? {
+                lrwn::region::DataRateModulation::Lora(params) => {
+                    config::get_required_snr_for_sf(params.spreading_factor)?
+                }
+                _ => 0.0,
+            },
+            installation_margin: network_conf.installation_margin,
+            min_dr: network_conf.min_dr,
+            max_dr: network_conf.max_dr,
+            uplink_history: ds.uplink_adr_history.clone(),
+            skip_f_cnt_check: ds.skip_f_cnt_check,
+            device_variables: d.variables.into_hashmap(),
+        })
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct Response {
     pub dr: u8,
     pub tx_power_index: u8,
     pub nb_trans: u8,
 }
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::test;
+    use prost::Message;
+
+    fn write_plugin(file_path: &str, id: &str, name: &str) {
+        let script = fs::read_to_string("../examples/adr_plugins/plugin_skeleton.js")
+            .unwrap()
+            .replace("example_id", id)
+            .replace("Example plugin", name);
+        fs::write(file_path, script).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reload_plugin() {
+        let _guard = test::prepare().await;
+        let file_path = std::env::temp_dir().join("chirpstack_test_reload_plugin.js");
+        let file_path = file_path.to_str().unwrap();
+
+        // initial load
+        write_plugin(file_path, "reload_test", "Reload test");
+        assert_eq!(
+            "reload_test",
+            reload_plugin(file_path, "reload_test").await.unwrap()
+        );
+        assert_eq!(
+            Some(&"Reload test".to_string()),
+            get_algorithms().await.get("reload_test")
+        );
+
+        // invalid plugin, the previous version is kept
+        fs::write(
+            file_path,
+            "export function id() { return \"reload_test\"; }",
+        )
+        .unwrap();
+        assert!(reload_plugin(file_path, "reload_test").await.is_err());
+        assert_eq!(
+            Some(&"Reload test".to_string()),
+            get_algorithms().await.get("reload_test")
+        );
+
+        // updated plugin
+        write_plugin(file_path, "reload_test", "Reload test v2");
+        assert_eq!(
+            "reload_test",
+            reload_plugin(file_path, "reload_test").await.unwrap()
+        );
+        assert_eq!(
+            Some(&"Reload test v2".to_string()),
+            get_algorithms().await.get("reload_test")
+        );
+
+        // changed ID, the previous ID is removed
+        write_plugin(file_path, "reload_test_2", "Reload test v3");
+        assert_eq!(
+            "reload_test_2",
+            reload_plugin(file_path, "reload_test").await.unwrap()
+        );
+        let algos = get_algorithms().await;
+        assert!(!algos.contains_key("reload_test"));
+        assert_eq!(
+            Some(&"Reload test v3".to_string()),
+            algos.get("reload_test_2")
+        );
+
+        // changed ID conflicting with an other algorithm
+        reload_plugin("../examples/adr_plugins/plugin_skeleton.js", "example_id")
+            .await
+            .unwrap();
+        write_plugin(file_path, "example_id", "Reload test v4");
+        assert!(reload_plugin(file_path, "reload_test_2").await.is_err());
+        assert_eq!(
+            Some(&"Reload test v3".to_string()),
+            get_algorithms().await.get("reload_test_2")
+        );
+
+        fs::remove_file(file_path).unwrap();
+        let mut algos = ADR_ALGORITHMS.write().await;
+        algos.remove("reload_test_2");
+        algos.remove("example_id");
+    }
+
+    #[tokio::test]
+    async fn test_register_external_plugin_id_in_use() {
+        let _guard = test::prepare().await;
+        let server = httpmock::MockServer::start();
+
+        let info_mock = server.mock(|when, then| {
+            when.method(httpmock::Method::POST)
+                .path("/")
+                .query_param("method", "GetInfo");
+            then.body(
+                chirpstack_api::api::GetAdrPluginInfoResponse {
+                    id: "default".into(),
+                    name: "External plugin".into(),
+                }
+                .encode_to_vec(),
+            );
+        });
+
+        register_external_plugin(&config::AdrExternalPlugin {
+            protocol: "http".into(),
+            server: server.url("/"),
+            ..Default::default()
+        })
+        .await;
+        info_mock.assert();
+
+        // the included algorithm has not been replaced
+        assert_eq!(
+            Some(&"Default ADR algorithm (LoRa only)".to_string()),
+            get_algorithms().await.get("default")
+        );
+    }
+
+    struct SlowAlgorithm {}
+
+    #[async_trait]
+    impl Handler for SlowAlgorithm {
+        fn get_name(&self) -> String {
+            "Slow algorithm".into()
+        }
+
+        fn get_id(&self) -> String {
+            "slow_test".into()
+        }
+
+        async fn handle(&self, req: &Request) -> Result<Response> {
+            sleep(Duration::from_secs(2)).await;
+            Ok(Response {
+                dr: req.dr,
+                tx_power_index: req.tx_power_index,
+                nb_trans: req.nb_trans,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reload_plugin_during_handle() {
+        let _guard = test::prepare().await;
+        ADR_ALGORITHMS
+            .write()
+            .await
+            .insert("slow_test".into(), Arc::new(SlowAlgorithm {}));
+
+        let req = Request {
+            region_config_id: "eu868".into(),
+            region_common_name: lrwn::region::CommonName::EU868,
+            dev_eui: EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            mac_version: lrwn::region::MacVersion::LORAWAN_1_0_3,
+            reg_params_revision: lrwn::region::Revision::A,
+            adr: true,
+            dr: 0,
+            tx_power_index: 0,
+            nb_trans: 1,
+            max_tx_power_index: 15,
+            required_snr_for_dr: -20.0,
+            installation_margin: 10.0,
+            min_dr: 0,
+            max_dr: 5,
+            uplink_history: vec![],
+            skip_f_cnt_check: false,
+            device_variables: HashMap::new(),
+        };
+        let handle_task = tokio::spawn(async move { handle("slow_test", &req).await });
+        sleep(Duration::from_millis(100)).await;
+
+        // reloading a plugin must not wait for the in-flight ADR request
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            reload_plugin("../examples/adr_plugins/plugin_skeleton.js", "example_id"),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(!handle_task.is_finished());
+        assert_eq!(
+            Response {
+                dr: 0,
+                tx_power_index: 0,
+                nb_trans: 1,
+            },
+            handle_task.await.unwrap()
+        );
+
+        let mut algos = ADR_ALGORITHMS.write().await;
+        algos.remove("slow_test");
+        algos.remove("example_id");
+    }
+}
@@ -27,6 +27,7 @@ impl Plugin {
             m_promise.finish()?;
             let id_func: rquickjs::Function = m.get("id").context("Get id function")?;
             let name_func: rquickjs::Function = m.get("name").context("Get name function")?;
+            let _: rquickjs::Function = m.get("handle").context("Get handle function")?;
 
             let id: String = id_func.call(()).context("Call id function")?;
             let name: String = name_func.call(()).context("Call name function")?;
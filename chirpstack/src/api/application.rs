@@ -6,11 +6,11 @@ use uuid::Uuid;
 use chirpstack_api::api;
 use chirpstack_api::api::application_service_server::ApplicationService;
 
-use super::auth::validator;
+use super::auth::{validator, AuthID};
 use super::error::ToStatus;
 use super::helpers;
 use crate::certificate;
-use crate::storage::{application, fields};
+use crate::storage::{application, audit_log, fields, user};
 
 pub struct Application {
     validator: validator::RequestValidator,
@@ -118,7 +118,9 @@ impl ApplicationService for Application {
             )
             .await?;
 
-        let _ = application::update(application::Application {
+        let a_old = application::get(&app_id).await.map_err(|e| e.status())?;
+
+        let a = application::update(application::Application {
             id: app_id,
             name: req_app.name.to_string(),
             description: req_app.description.to_string(),
@@ -132,6 +134,13 @@ impl ApplicationService for Application {
         resp.metadata_mut()
             .insert("x-log-application_id", req_app.id.parse().unwrap());
 
+        resp.extensions_mut().insert(
+            audit_log::Changes::new()
+                .field("name", &a_old.name, &a.name)
+                .field("description", &a_old.description, &a.description)
+                .field("tags", &a_old.tags, &a.tags),
+        );
+
         Ok(resp)
     }
 
@@ -149,11 +158,17 @@ impl ApplicationService for Application {
             )
             .await?;
 
+        let app = application::get(&app_id).await.map_err(|e| e.status())?;
+
         application::delete(&app_id).await.map_err(|e| e.status())?;
 
         let mut resp = Response::new(());
         resp.metadata_mut()
             .insert("x-log-application_id", req.id.parse().unwrap());
+        resp.metadata_mut().insert(
+            "x-log-tenant_id",
+            app.tenant_id.to_string().parse().unwrap(),
+        );
 
         Ok(resp)
     }
@@ -172,15 +187,26 @@ impl ApplicationService for Application {
             )
             .await?;
 
-        let filters = application::Filters {
+        let mut filters = application::Filters {
             tenant_id: Some(tenant_id),
             search: if req.search.is_empty() {
                 None
             } else {
                 Some(req.search.to_string())
             },
+            user_id: None,
         };
 
+        // Users which are not global admin might only have access to a subset of the
+        // applications through their application memberships.
+        let auth_id = request.extensions().get::<AuthID>().unwrap();
+        if let AuthID::User(id) = auth_id {
+            let u = user::get(id).await.map_err(|e| e.status())?;
+            if !u.is_admin {
+                filters.user_id = Some(u.id);
+            }
+        }
+
         let count = application::get_count(&filters)
             .await
             .map_err(|e| e.status())?;
@@ -1899,14 +1925,220 @@ impl ApplicationService for Application {
 
         Ok(resp)
     }
+
+    async fn add_user(
+        &self,
+        request: Request<api::AddApplicationUserRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req_user = match &request.get_ref().application_user {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("application_user is missing"));
+            }
+        };
+        let app_id = Uuid::from_str(&req_user.application_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateApplicationUsersAccess::new(validator::Flag::Create, app_id),
+            )
+            .await?;
+
+        let user_id = user::get_by_email(&req_user.email)
+            .await
+            .map_err(|e| e.status())?
+            .id;
+
+        let _ = application::add_user(application::ApplicationUser {
+            application_id: app_id,
+            user_id,
+            is_admin: req_user.is_admin,
+            is_device_admin: req_user.is_device_admin,
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut().insert(
+            "x-log-application_id",
+            req_user.application_id.parse().unwrap(),
+        );
+        resp.metadata_mut()
+            .insert("x-log-user_id", user_id.to_string().parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn get_user(
+        &self,
+        request: Request<api::GetApplicationUserRequest>,
+    ) -> Result<Response<api::GetApplicationUserResponse>, Status> {
+        let req = request.get_ref();
+        let app_id = Uuid::from_str(&req.application_id).map_err(|e| e.status())?;
+        let user_id = Uuid::from_str(&req.user_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateApplicationUserAccess::new(
+                    validator::Flag::Read,
+                    app_id,
+                    user_id,
+                ),
+            )
+            .await?;
+
+        let u = user::get(&user_id).await.map_err(|e| e.status())?;
+        let au = application::get_user(&app_id, &user_id)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::GetApplicationUserResponse {
+            application_user: Some(api::ApplicationUser {
+                application_id: app_id.to_string(),
+                user_id: au.user_id.to_string(),
+                email: u.email.clone(),
+                is_admin: au.is_admin,
+                is_device_admin: au.is_device_admin,
+            }),
+            created_at: Some(helpers::datetime_to_prost_timestamp(&au.created_at)),
+            updated_at: Some(helpers::datetime_to_prost_timestamp(&au.updated_at)),
+        });
+        resp.metadata_mut()
+            .insert("x-log-application_id", req.application_id.parse().unwrap());
+        resp.metadata_mut()
+            .insert("x-log-user_id", req.user_id.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn update_user(
+        &self,
+        request: Request<api::UpdateApplicationUserRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req_user = match &request.get_ref().application_user {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("application_user is missing"));
+            }
+        };
+        let app_id = Uuid::from_str(&req_user.application_id).map_err(|e| e.status())?;
+        let user_id = Uuid::from_str(&req_user.user_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateApplicationUserAccess::new(
+                    validator::Flag::Update,
+                    app_id,
+                    user_id,
+                ),
+            )
+            .await?;
+
+        application::update_user(application::ApplicationUser {
+            application_id: app_id,
+            user_id,
+            is_admin: req_user.is_admin,
+            is_device_admin: req_user.is_device_admin,
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut().insert(
+            "x-log-application_id",
+            req_user.application_id.parse().unwrap(),
+        );
+        resp.metadata_mut()
+            .insert("x-log-user_id", req_user.user_id.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn delete_user(
+        &self,
+        request: Request<api::DeleteApplicationUserRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.get_ref();
+        let app_id = Uuid::from_str(&req.application_id).map_err(|e| e.status())?;
+        let user_id = Uuid::from_str(&req.user_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateApplicationUserAccess::new(
+                    validator::Flag::Delete,
+                    app_id,
+                    user_id,
+                ),
+            )
+            .await?;
+
+        application::delete_user(&app_id, &user_id)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-application_id", req.application_id.parse().unwrap());
+        resp.metadata_mut()
+            .insert("x-log-user_id", req.user_id.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn list_users(
+        &self,
+        request: Request<api::ListApplicationUsersRequest>,
+    ) -> Result<Response<api::ListApplicationUsersResponse>, Status> {
+        let req = request.get_ref();
+        let app_id = Uuid::from_str(&req.application_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateApplicationUsersAccess::new(validator::Flag::List, app_id),
+            )
+            .await?;
+
+        let count = application::get_user_count(&app_id)
+            .await
+            .map_err(|e| e.status())?;
+        let result = application::get_users(&app_id, req.limit as i64, req.offset as i64)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::ListApplicationUsersResponse {
+            total_count: count as u32,
+            result: result
+                .iter()
+                .map(|au| api::ApplicationUserListItem {
+                    application_id: app_id.to_string(),
+                    user_id: au.user_id.to_string(),
+                    created_at: Some(helpers::datetime_to_prost_timestamp(&au.created_at)),
+                    updated_at: Some(helpers::datetime_to_prost_timestamp(&au.updated_at)),
+                    email: au.email.clone(),
+                    is_admin: au.is_admin,
+                    is_device_admin: au.is_device_admin,
+                })
+                .collect(),
+        });
+        resp.metadata_mut()
+            .insert("x-log-application_id", req.application_id.parse().unwrap());
+
+        Ok(resp)
+    }
 }
 
 #[cfg(test)]
 pub mod test {
     use super::*;
     use crate::api::auth::validator::RequestValidator;
-    use crate::api::auth::AuthID;
-    use crate::storage::{tenant, user};
+    use crate::storage::tenant;
     use crate::test;
 
     #[tokio::test]
@@ -3484,4 +3716,147 @@ pub mod test {
             list_resp
         );
     }
+
+    #[tokio::test]
+    async fn test_application_user() {
+        let _guard = test::prepare().await;
+        let app = get_application().await;
+        let app_other = application::create(application::Application {
+            tenant_id: app.tenant_id,
+            name: "test-app-other".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let u = get_user().await;
+        let app_user = user::create(user::User {
+            is_active: true,
+            email: "app@user".into(),
+            email_verified: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let service = Application::new(RequestValidator::new());
+
+        // add user
+        let add_req = get_request(
+            &u.id,
+            api::AddApplicationUserRequest {
+                application_user: Some(api::ApplicationUser {
+                    application_id: app.id.to_string(),
+                    email: "app@user".into(),
+                    ..Default::default()
+                }),
+            },
+        );
+        let _ = service.add_user(add_req).await.unwrap();
+
+        // get user
+        let get_req = get_request(
+            &app_user.id,
+            api::GetApplicationUserRequest {
+                application_id: app.id.to_string(),
+                user_id: app_user.id.to_string(),
+            },
+        );
+        let get_resp = service.get_user(get_req).await.unwrap();
+        assert_eq!(
+            Some(api::ApplicationUser {
+                application_id: app.id.to_string(),
+                user_id: app_user.id.to_string(),
+                email: "app@user".into(),
+                is_admin: false,
+                is_device_admin: false,
+            }),
+            get_resp.get_ref().application_user
+        );
+
+        // read-only application user can not update itself
+        let up_req = get_request(
+            &app_user.id,
+            api::UpdateApplicationUserRequest {
+                application_user: Some(api::ApplicationUser {
+                    application_id: app.id.to_string(),
+                    user_id: app_user.id.to_string(),
+                    is_admin: true,
+                    ..Default::default()
+                }),
+            },
+        );
+        assert!(service.update_user(up_req).await.is_err());
+
+        // update user
+        let up_req = get_request(
+            &u.id,
+            api::UpdateApplicationUserRequest {
+                application_user: Some(api::ApplicationUser {
+                    application_id: app.id.to_string(),
+                    user_id: app_user.id.to_string(),
+                    is_device_admin: true,
+                    ..Default::default()
+                }),
+            },
+        );
+        let _ = service.update_user(up_req).await.unwrap();
+
+        // list users
+        let list_req = get_request(
+            &app_user.id,
+            api::ListApplicationUsersRequest {
+                application_id: app.id.to_string(),
+                limit: 10,
+                offset: 0,
+            },
+        );
+        let list_resp = service.list_users(list_req).await.unwrap();
+        let list_resp = list_resp.get_ref();
+        assert_eq!(1, list_resp.total_count);
+        assert_eq!(app_user.id.to_string(), list_resp.result[0].user_id);
+        assert!(list_resp.result[0].is_device_admin);
+
+        // the application user only sees the application it is a member of
+        let list_req = get_request(
+            &app_user.id,
+            api::ListApplicationsRequest {
+                tenant_id: app.tenant_id.to_string(),
+                limit: 10,
+                ..Default::default()
+            },
+        );
+        let list_resp = service.list(list_req).await.unwrap();
+        let list_resp = list_resp.get_ref();
+        assert_eq!(1, list_resp.total_count);
+        assert_eq!(app.id.to_string(), list_resp.result[0].id);
+
+        // the application user can not read the other application
+        let get_req = get_request(
+            &app_user.id,
+            api::GetApplicationRequest {
+                id: app_other.id.to_string(),
+            },
+        );
+        assert!(service.get(get_req).await.is_err());
+
+        // delete user
+        let del_req = get_request(
+            &u.id,
+            api::DeleteApplicationUserRequest {
+                application_id: app.id.to_string(),
+                user_id: app_user.id.to_string(),
+            },
+        );
+        let _ = service.delete_user(del_req).await.unwrap();
+
+        // the user no longer has access
+        let list_req = get_request(
+            &app_user.id,
+            api::ListApplicationsRequest {
+                tenant_id: app.tenant_id.to_string(),
+                limit: 10,
+                ..Default::default()
+            },
+        );
+        assert!(service.list(list_req).await.is_err());
+    }
 }
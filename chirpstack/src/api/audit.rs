@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use anyhow::Result;
+use http::HeaderMap;
+use ipnet::IpNet;
+use tonic::transport::server::TcpConnectInfo;
+use tracing::warn;
+use uuid::Uuid;
+
+use super::auth::{self, AuthID};
+use crate::config;
+use crate::storage::{application, audit_log, codec, device, device_profile, gateway, multicast};
+use lrwn::EUI64;
+
+// Method (prefixes) of API methods which do not modify any state. These are not written to
+// the audit log.
+const READ_ONLY_METHODS: [&str; 9] = [
+    "Get",
+    "List",
+    "Stream",
+    "GlobalSearch",
+    "Login",
+    "OAuth2Login",
+    "OpenIdConnectLogin",
+    "Profile",
+    "Settings",
+];
+
+// The x-log- metadata keys which identify the target of the API call, from the most to the
+// least specific.
+const TARGET_KEYS: [&str; 13] = [
+    "dev_eui",
+    "device_dev_eui",
+    "relay_dev_eui",
+    "gateway_id",
+    "relay_id",
+    "multicast_group_id",
+    "codec_version_id",
+    "codec_id",
+    "device_profile_id",
+    "api_key_id",
+    "user_id",
+    "application_id",
+    "tenant_id",
+];
+
+// Request contains the request context that must be captured before the request is handled.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub auth_id: AuthID,
+    pub source_ip: String,
+}
+
+impl Request {
+    pub fn new<B>(req: &http::Request<B>) -> Self {
+        Request {
+            auth_id: get_auth_id(req.headers()),
+            source_ip: get_source_ip(req, &get_trusted_proxies()),
+        }
+    }
+}
+
+// Returns true if the given API method modifies state and must be written to the audit log.
+pub fn is_mutating(service: &str, method: &str) -> bool {
+    service.starts_with("api.") && !READ_ONLY_METHODS.iter().any(|m| method.starts_with(m))
+}
+
+pub async fn log_request(
+    req: &Request,
+    service: &str,
+    method: &str,
+    metadata: &HashMap<String, String>,
+    changes: Option<audit_log::Changes>,
+) -> Result<()> {
+    audit_log::create(audit_log::AuditLog {
+        tenant_id: get_tenant_id(metadata).await?,
+        user_id: match req.auth_id {
+            AuthID::User(v) => Some(v),
+            _ => None,
+        },
+        api_key_id: match req.auth_id {
+            AuthID::Key(v) => Some(v),
+            _ => None,
+        },
+        service: service.to_string(),
+        method: method.to_string(),
+        target_id: get_target_id(metadata),
+        changes: changes.unwrap_or_default().into(),
+        source_ip: req.source_ip.clone(),
+        ..Default::default()
+    })
+    .await?;
+
+    Ok(())
+}
+
+fn get_auth_id(headers: &HeaderMap) -> AuthID {
+    headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| auth::get_auth_id(v).ok())
+        .unwrap_or(AuthID::None)
+}
+
+// Returns the source IP of the request. In case the connection originates from one of the
+// configured trusted proxies, the X-Forwarded-For or X-Real-IP headers are used.
+fn get_source_ip<B>(req: &http::Request<B>, trusted_proxies: &[IpNet]) -> String {
+    let remote_ip = match req
+        .extensions()
+        .get::<TcpConnectInfo>()
+        .and_then(|v| v.remote_addr())
+    {
+        Some(v) => v.ip(),
+        None => return "".to_string(),
+    };
+
+    if !is_trusted_proxy(&remote_ip, trusted_proxies) {
+        return remote_ip.to_string();
+    }
+
+    // The X-Forwarded-For header is processed from right to left, as each proxy appends the
+    // address it received the request from. The first address which is not a trusted proxy
+    // is the client address.
+    if let Some(v) = req
+        .headers()
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+    {
+        let ips: Vec<IpAddr> = v
+            .split(',')
+            .filter_map(|v| IpAddr::from_str(v.trim()).ok())
+            .collect();
+
+        if let Some(ip) = ips
+            .iter()
+            .rev()
+            .find(|ip| !is_trusted_proxy(ip, trusted_proxies))
+            .or(ips.first())
+        {
+            return ip.to_string();
+        }
+    }
+
+    if let Some(ip) = req
+        .headers()
+        .get("x-real-ip")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| IpAddr::from_str(v.trim()).ok())
+    {
+        return ip.to_string();
+    }
+
+    remote_ip.to_string()
+}
+
+fn is_trusted_proxy(ip: &IpAddr, trusted_proxies: &[IpNet]) -> bool {
+    trusted_proxies.iter().any(|n| n.contains(ip))
+}
+
+// Returns the configured trusted proxies. Both IP addresses and networks in CIDR notation
+// are accepted.
+fn get_trusted_proxies() -> Vec<IpNet> {
+    let conf = config::get();
+    conf.api
+        .trusted_proxies
+        .iter()
+        .filter_map(|v| match IpNet::from_str(v) {
+            Ok(v) => Some(v),
+            Err(_) => match IpAddr::from_str(v) {
+                Ok(v) => Some(IpNet::from(v)),
+                Err(e) => {
+                    warn!(trusted_proxy = %v, error = %e, "Invalid trusted proxy");
+                    None
+                }
+            },
+        })
+        .collect()
+}
+
+fn get_target_id(metadata: &HashMap<String, String>) -> String {
+    TARGET_KEYS
+        .iter()
+        .find_map(|k| metadata.get(*k))
+        .cloned()
+        .unwrap_or_default()
+}
+
+// Returns the tenant ID by resolving the x-log- metadata. In case the referenced object does
+// not exist (e.g. it was deleted by the API call), the next key is tried.
+async fn get_tenant_id(metadata: &HashMap<String, String>) -> Result<Option<Uuid>> {
+    if let Some(v) = metadata.get("tenant_id") {
+        return Ok(Some(Uuid::from_str(v)?));
+    }
+
+    if let Some(v) = metadata.get("application_id") {
+        if let Ok(a) = application::get(&Uuid::from_str(v)?).await {
+            return Ok(Some(a.tenant_id));
+        }
+    }
+
+    if let Some(v) = metadata.get("dev_eui") {
+        if let Ok(d) = device::get(&EUI64::from_str(v)?).await {
+            let a = application::get(&d.application_id).await?;
+            return Ok(Some(a.tenant_id));
+        }
+    }
+
+    if let Some(v) = metadata.get("multicast_group_id") {
+        if let Ok(mg) = multicast::get(&Uuid::from_str(v)?).await {
+            let a = application::get(&mg.application_id).await?;
+            return Ok(Some(a.tenant_id));
+        }
+    }
+
+    if let Some(v) = metadata.get("gateway_id") {
+        if let Ok(gw) = gateway::get(&EUI64::from_str(v)?).await {
+            return Ok(Some(gw.tenant_id));
+        }
+    }
+
+    if let Some(v) = metadata.get("device_profile_id") {
+        if let Ok(dp) = device_profile::get(&Uuid::from_str(v)?).await {
+            return Ok(Some(dp.tenant_id));
+        }
+    }
+
+    if let Some(v) = metadata.get("codec_id") {
+        if let Ok(c) = codec::get(&Uuid::from_str(v)?).await {
+            return Ok(Some(c.tenant_id));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage::{application, device, device_profile, tenant};
+    use crate::test;
+    use std::net::SocketAddr;
+
+    #[test]
+    fn test_is_mutating() {
+        assert!(is_mutating("api.DeviceService", "Create"));
+        assert!(is_mutating("api.DeviceService", "Delete"));
+        assert!(is_mutating("api.DeviceService", "FlushQueue"));
+        assert!(is_mutating("api.InternalService", "CreateApiKey"));
+        assert!(!is_mutating("api.DeviceService", "Get"));
+        assert!(!is_mutating("api.DeviceService", "GetMetrics"));
+        assert!(!is_mutating("api.DeviceService", "List"));
+        assert!(!is_mutating("api.InternalService", "StreamDeviceFrames"));
+        assert!(!is_mutating("api.InternalService", "Login"));
+        assert!(!is_mutating(
+            "grpc.reflection.v1alpha.ServerReflection",
+            "ServerReflectionInfo"
+        ));
+    }
+
+    #[test]
+    fn test_get_source_ip() {
+        let trusted_proxies: Vec<IpNet> = vec![
+            "10.0.0.1/32".parse().unwrap(),
+            "172.16.0.0/12".parse().unwrap(),
+        ];
+
+        let request = |remote_addr: &str, headers: &[(&str, &str)]| {
+            let mut b = http::Request::builder();
+            for (k, v) in headers {
+                b = b.header(*k, *v);
+            }
+            let mut req = b.body(()).unwrap();
+            req.extensions_mut().insert(TcpConnectInfo {
+                local_addr: None,
+                remote_addr: Some(SocketAddr::from_str(remote_addr).unwrap()),
+            });
+            req
+        };
+
+        // untrusted peer, headers are ignored
+        let req = request(
+            "192.168.1.1:1234",
+            &[
+                ("x-forwarded-for", "192.168.1.10"),
+                ("x-real-ip", "192.168.1.11"),
+            ],
+        );
+        assert_eq!("192.168.1.1", get_source_ip(&req, &trusted_proxies));
+
+        // trusted peer, the right-most untrusted address is used
+        let req = request(
+            "10.0.0.1:1234",
+            &[("x-forwarded-for", "1.2.3.4, 192.168.1.10, 172.16.0.5")],
+        );
+        assert_eq!("192.168.1.10", get_source_ip(&req, &trusted_proxies));
+
+        // trusted peer, X-Real-IP
+        let req = request("172.16.1.2:1234", &[("x-real-ip", "192.168.1.11")]);
+        assert_eq!("192.168.1.11", get_source_ip(&req, &trusted_proxies));
+
+        // trusted peer, no headers
+        let req = request("10.0.0.1:1234", &[]);
+        assert_eq!("10.0.0.1", get_source_ip(&req, &trusted_proxies));
+
+        // no connection info
+        let req = http::Request::builder()
+            .header("x-forwarded-for", "192.168.1.10")
+            .body(())
+            .unwrap();
+        assert_eq!("", get_source_ip(&req, &trusted_proxies));
+    }
+
+    #[test]
+    fn test_get_target_id() {
+        let metadata: HashMap<String, String> = [
+            ("tenant_id".to_string(), "t".to_string()),
+            ("user_id".to_string(), "u".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!("u", get_target_id(&metadata));
+        assert_eq!("", get_target_id(&HashMap::new()));
+    }
+
+    #[tokio::test]
+    async fn test_get_tenant_id() {
+        let _guard = test::prepare().await;
+
+        let t = tenant::test::create_tenant().await;
+        let app = application::test::create_application(Some(t.id)).await;
+        let dp = device_profile::test::create_device_profile(Some(t.id)).await;
+        let dev = device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            Some(app.id),
+        )
+        .await;
+
+        let metadata: HashMap<String, String> = [("dev_eui".to_string(), dev.dev_eui.to_string())]
+            .into_iter()
+            .collect();
+        assert_eq!(Some(t.id), get_tenant_id(&metadata).await.unwrap());
+
+        // the device has been deleted
+        device::delete(&dev.dev_eui).await.unwrap();
+        assert_eq!(None, get_tenant_id(&metadata).await.unwrap());
+
+        // the application is used as fallback
+
+        let metadata: HashMap<String, String> = [
+            ("dev_eui".to_string(), dev.dev_eui.to_string()),
+            ("application_id".to_string(), app.id.to_string()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(Some(t.id), get_tenant_id(&metadata).await.unwrap());
+
+        let metadata: HashMap<String, String> =
+            [("device_profile_id".to_string(), dp.id.to_string())]
+                .into_iter()
+                .collect();
+        assert_eq!(Some(t.id), get_tenant_id(&metadata).await.unwrap());
+    }
+}
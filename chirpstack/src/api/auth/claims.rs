@@ -35,6 +35,21 @@ impl AuthClaim {
         }
     }
 
+    // Login token, issued after a valid email / password when a second
+    // authentication factor is required. It can not be used to access the API.
+    pub fn new_for_login(id: &Uuid) -> Self {
+        let nbf: DateTime<Utc> = Utc::now();
+        let exp = nbf.add(Duration::try_minutes(5).unwrap());
+
+        AuthClaim {
+            aud: "chirpstack".to_string(),
+            exp: Some(exp.timestamp() as usize),
+            iss: "chirpstack".to_string(),
+            sub: id.to_string(),
+            typ: "login".to_string(),
+        }
+    }
+
     pub fn new_for_api_key(id: &Uuid) -> Self {
         AuthClaim {
             aud: "chirpstack".to_string(),
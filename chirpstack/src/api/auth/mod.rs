@@ -4,6 +4,7 @@ use uuid::Uuid;
 
 pub mod claims;
 pub mod error;
+pub mod totp;
 pub mod validator;
 
 #[derive(PartialEq, Eq, Debug, Clone)]
@@ -14,7 +15,10 @@ pub enum AuthID {
 }
 
 pub fn auth_interceptor(mut req: Request<()>) -> Result<Request<()>, Status> {
-    let conf = config::get();
+    // The AuthID might already have been decoded by the API logger.
+    if req.extensions().get::<AuthID>().is_some() {
+        return Ok(req);
+    }
 
     let auth_str = match req.metadata().get("authorization") {
         Some(v) => match v.to_str() {
@@ -31,6 +35,16 @@ pub fn auth_interceptor(mut req: Request<()>) -> Result<Request<()>, Status> {
         }
     };
 
+    let id = get_auth_id(auth_str)?;
+    req.extensions_mut().insert(id);
+
+    Ok(req)
+}
+
+// Returns the AuthID for the given authorization metadata value.
+pub fn get_auth_id(auth_str: &str) -> Result<AuthID, Status> {
+    let conf = config::get();
+
     let auth_str = match auth_str.strip_prefix("Bearer ") {
         Some(v) => v,
         None => {
@@ -55,19 +69,11 @@ pub fn auth_interceptor(mut req: Request<()>) -> Result<Request<()>, Status> {
     };
 
     match token.typ.as_ref() {
-        "user" => {
-            req.extensions_mut().insert(AuthID::User(id));
-        }
-        "key" => {
-            req.extensions_mut().insert(AuthID::Key(id));
-        }
-        _ => {
-            return Err(Status::unauthenticated(format!(
-                "invalid token typ: {}",
-                token.typ
-            )));
-        }
-    };
-
-    Ok(req)
+        "user" => Ok(AuthID::User(id)),
+        "key" => Ok(AuthID::Key(id)),
+        _ => Err(Status::unauthenticated(format!(
+            "invalid token typ: {}",
+            token.typ
+        ))),
+    }
 }
@@ -0,0 +1,175 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use data_encoding::BASE32_NOPAD;
+use rand::{distributions::Alphanumeric, Rng, RngCore};
+use ring::hmac;
+
+// Number of digits of the TOTP code.
+const DIGITS: u32 = 6;
+
+// Time-step (seconds) of the TOTP code.
+const STEP: i64 = 30;
+
+// Number of time-steps before and after the current time-step which are accepted to allow for
+// clock drift between the server and the authenticator app.
+const SKEW: i64 = 1;
+
+// Number of recovery-codes generated on enabling TOTP.
+const RECOVERY_CODES: usize = 10;
+
+// Generates a new random (160 bit) TOTP secret, encoded as base32.
+pub fn generate_secret() -> String {
+    let mut b = [0; 20];
+    rand::thread_rng().fill_bytes(&mut b);
+    BASE32_NOPAD.encode(&b)
+}
+
+// Returns the otpauth:// URI (which can be presented as QR code) for the given secret.
+pub fn get_uri(issuer: &str, account: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        urlencoding::encode(issuer),
+        urlencoding::encode(account),
+        secret,
+        urlencoding::encode(issuer),
+        DIGITS,
+        STEP
+    )
+}
+
+// Generates a set of random recovery-codes, formatted as xxxxx-xxxxx.
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODES)
+        .map(|_| {
+            let s: String = rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(10)
+                .map(|c| (c as char).to_ascii_lowercase())
+                .collect();
+            format!("{}-{}", &s[..5], &s[5..])
+        })
+        .collect()
+}
+
+// Returns the time-step (counter) of the given code in case it is valid for the given secret
+// at the given time. To prevent replay attacks, the caller must reject codes of which the
+// time-step is not greater than the time-step of the last accepted code.
+pub fn verify_code(secret: &str, code: &str, time: DateTime<Utc>) -> Result<Option<i64>> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize {
+        return Ok(None);
+    }
+
+    let counter = time.timestamp() / STEP;
+    for c in (counter - SKEW)..=(counter + SKEW) {
+        if get_code(secret, c as u64)? == code {
+            return Ok(Some(c));
+        }
+    }
+
+    Ok(None)
+}
+
+// Implements the HOTP algorithm as described by RFC 4226.
+fn get_code(secret: &str, counter: u64) -> Result<String> {
+    let key = BASE32_NOPAD
+        .decode(secret.trim_end_matches('=').to_uppercase().as_bytes())
+        .map_err(|e| anyhow!("Decode TOTP secret error: {}", e))?;
+
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, &key);
+    let tag = hmac::sign(&key, &counter.to_be_bytes());
+    let hash = tag.as_ref();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+
+    Ok(format!(
+        "{:0width$}",
+        binary % 10_u32.pow(DIGITS),
+        width = DIGITS as usize
+    ))
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    pub fn generate_code(secret: &str, time: DateTime<Utc>) -> Result<String> {
+        get_code(secret, (time.timestamp() / STEP) as u64)
+    }
+
+    // Test-vectors from RFC 6238 (SHA1), truncated to 6 digits.
+    #[test]
+    fn test_generate_code() {
+        let secret = BASE32_NOPAD.encode(b"12345678901234567890");
+
+        let tests = vec![
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+        ];
+
+        for (ts, code) in tests {
+            assert_eq!(
+                code,
+                generate_code(&secret, Utc.timestamp_opt(ts, 0).unwrap()).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_code() {
+        let secret = generate_secret();
+        let now = Utc::now();
+        let code = generate_code(&secret, now).unwrap();
+
+        let counter = now.timestamp() / STEP;
+
+        assert_eq!(Some(counter), verify_code(&secret, &code, now).unwrap());
+        assert_eq!(
+            Some(counter),
+            verify_code(
+                &secret,
+                &code,
+                now + chrono::Duration::try_seconds(30).unwrap()
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            None,
+            verify_code(
+                &secret,
+                &code,
+                now + chrono::Duration::try_seconds(120).unwrap()
+            )
+            .unwrap()
+        );
+        assert_eq!(None, verify_code(&secret, "12345", now).unwrap());
+    }
+
+    #[test]
+    fn test_get_uri() {
+        assert_eq!(
+            "otpauth://totp/ChirpStack:user%40example.com?secret=JBSWY3DPEHPK3PXP&issuer=ChirpStack&algorithm=SHA1&digits=6&period=30",
+            get_uri("ChirpStack", "user@example.com", "JBSWY3DPEHPK3PXP")
+        );
+    }
+
+    #[test]
+    fn test_generate_recovery_codes() {
+        let codes = generate_recovery_codes();
+        assert_eq!(RECOVERY_CODES, codes.len());
+        for code in &codes {
+            assert_eq!(11, code.len());
+            assert_eq!(Some(5), code.find('-'));
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use async_trait::async_trait;
+use chrono::Utc;
 use diesel::{dsl, prelude::*};
 use diesel_async::RunQueryDsl;
 use tonic::{Extensions, Status};
@@ -11,10 +12,14 @@ use lrwn::EUI64;
 use super::error::Error;
 use crate::api::auth::AuthID;
 use crate::helpers::errors::PrintFullError;
-use crate::storage::get_async_db_conn;
+use crate::storage::error::Error as StorageError;
+use crate::storage::fields::ApiKeyScope;
+use crate::storage::gateway::RelayId;
 use crate::storage::schema::{
-    api_key, application, device, device_profile, gateway, multicast_group, tenant_user, user,
+    api_key, application, application_user, codec, codec_version, device, device_profile,
+    fuota_campaign, gateway, multicast_group, relay_gateway, tenant_user, user,
 };
+use crate::storage::{api_key as api_key_storage, get_async_db_conn};
 
 #[derive(Copy, Clone)]
 pub enum Flag {
@@ -49,10 +54,63 @@ impl RequestValidator {
 pub trait Validator {
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error>;
     async fn validate_key(&self, id: &Uuid) -> Result<i64, Error>;
+
+    // Returns the access flag, used for validating the API key scope.
+    fn flag(&self) -> Option<Flag> {
+        None
+    }
+
+    // Returns the API key scopes that grant access.
+    fn key_scopes(&self) -> Vec<ApiKeyScope> {
+        match self.flag() {
+            Some(Flag::Read) | Some(Flag::List) => vec![ApiKeyScope::FULL, ApiKeyScope::READ_ONLY],
+            _ => vec![ApiKeyScope::FULL],
+        }
+    }
+
+    // Returns the ID of the application to which the access applies, used for
+    // validating API keys that are restricted to a single application.
+    async fn application_id(&self) -> Result<Option<Uuid>, Error> {
+        Ok(None)
+    }
+
+    // Validates the API key expiration, scope and application restriction
+    // before validating the access itself.
+    async fn validate_api_key(&self, id: &Uuid) -> Result<i64, Error> {
+        let ak = match api_key_storage::get(id).await {
+            Ok(v) => v,
+            Err(StorageError::NotFound(_)) => return Ok(0),
+            Err(e) => return Err(anyhow::Error::new(e).into()),
+        };
+
+        if ak.is_expired() || !self.key_scopes().contains(&ak.scope) {
+            return Ok(0);
+        }
+
+        if let Some(application_id) = ak.application_id {
+            if self.application_id().await? != Some(application_id) {
+                return Ok(0);
+            }
+        }
+
+        let count = self.validate_key(id).await?;
+        // Skip the database round-trip when the key was used within the update interval.
+        let last_used_outdated = ak.last_used_at.map_or(true, |v| {
+            Utc::now() - v >= api_key_storage::LAST_USED_UPDATE_INTERVAL
+        });
+        if count > 0 && last_used_outdated {
+            api_key_storage::update_last_used(id)
+                .await
+                .map_err(anyhow::Error::new)?;
+        }
+
+        Ok(count)
+    }
+
     async fn validate(&self, id: &AuthID) -> Result<(), Status> {
         let res = match id {
             AuthID::User(id) => self.validate_user(id).await,
-            AuthID::Key(id) => self.validate_key(id).await,
+            AuthID::Key(id) => self.validate_api_key(id).await,
             AuthID::None => {
                 return Err(Status::unauthenticated("no authorization provided"));
             }
@@ -77,6 +135,33 @@ pub trait Validator {
     }
 }
 
+async fn device_application_id(dev_eui: &EUI64) -> Result<Option<Uuid>, Error> {
+    Ok(device::dsl::device
+        .select(device::dsl::application_id)
+        .find(dev_eui)
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .optional()?)
+}
+
+async fn fuota_campaign_application_id(id: &Uuid) -> Result<Option<Uuid>, Error> {
+    Ok(fuota_campaign::dsl::fuota_campaign
+        .select(fuota_campaign::dsl::application_id)
+        .find(id)
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .optional()?)
+}
+
+async fn multicast_group_application_id(id: &Uuid) -> Result<Option<Uuid>, Error> {
+    Ok(multicast_group::dsl::multicast_group
+        .select(multicast_group::dsl::application_id)
+        .find(id)
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .optional()?)
+}
+
 pub struct ValidateActiveUser {}
 
 impl ValidateActiveUser {
@@ -141,6 +226,10 @@ impl ValidateActiveUserOrKey {
 
 #[async_trait]
 impl Validator for ValidateActiveUserOrKey {
+    fn flag(&self) -> Option<Flag> {
+        Some(Flag::Read)
+    }
+
     async fn validate_key(&self, id: &Uuid) -> Result<i64, Error> {
         let count = api_key::dsl::api_key
             .select(dsl::count_star())
@@ -173,6 +262,10 @@ impl ValidateUsersAccess {
 
 #[async_trait]
 impl Validator for ValidateUsersAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -218,6 +311,10 @@ impl ValidateUserAccess {
 
 #[async_trait]
 impl Validator for ValidateUserAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -278,6 +375,10 @@ impl ValidateApiKeysAccess {
 
 #[async_trait]
 impl Validator for ValidateApiKeysAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -338,6 +439,10 @@ impl ValidateApiKeyAccess {
 
 #[async_trait]
 impl Validator for ValidateApiKeyAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -388,6 +493,10 @@ impl ValidateTenantsAccess {
 
 #[async_trait]
 impl Validator for ValidateTenantsAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -435,6 +544,10 @@ impl ValidateTenantAccess {
 
 #[async_trait]
 impl Validator for ValidateTenantAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -444,15 +557,27 @@ impl Validator for ValidateTenantAccess {
         match self.flag {
             // global admin
             // tenant user
+            // application user
             Flag::Read => {
                 q = q.filter(
-                    user::is_admin.eq(true).or(dsl::exists(
-                        tenant_user::dsl::tenant_user.filter(
-                            tenant_user::dsl::user_id
-                                .eq(user::dsl::id)
-                                .and(tenant_user::dsl::tenant_id.eq(&self.tenant_id)),
-                        ),
-                    )),
+                    user::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
+                            tenant_user::dsl::tenant_user.filter(
+                                tenant_user::dsl::user_id
+                                    .eq(user::dsl::id)
+                                    .and(tenant_user::dsl::tenant_id.eq(&self.tenant_id)),
+                            ),
+                        ))
+                        .or(dsl::exists(
+                            application_user::dsl::application_user
+                                .inner_join(application::table)
+                                .filter(
+                                    application::dsl::tenant_id
+                                        .eq(&self.tenant_id)
+                                        .and(application_user::dsl::user_id.eq(user::dsl::id)),
+                                ),
+                        )),
                 );
             }
 
@@ -510,6 +635,10 @@ impl ValidateTenantUsersAccess {
 
 #[async_trait]
 impl Validator for ValidateTenantUsersAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -595,6 +724,10 @@ impl ValidateTenantUserAccess {
 
 #[async_trait]
 impl Validator for ValidateTenantUserAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -668,6 +801,77 @@ impl Validator for ValidateTenantUserAccess {
     }
 }
 
+pub struct ValidateTenantAuditLogAccess {
+    flag: Flag,
+    tenant_id: Uuid,
+}
+
+impl ValidateTenantAuditLogAccess {
+    pub fn new(flag: Flag, tenant_id: Uuid) -> Self {
+        ValidateTenantAuditLogAccess { flag, tenant_id }
+    }
+}
+
+#[async_trait]
+impl Validator for ValidateTenantAuditLogAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
+    async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
+        let mut q = user::dsl::user
+            .select(dsl::count_star())
+            .filter(user::dsl::id.eq(id).and(user::dsl::is_active.eq(true)))
+            .into_boxed();
+
+        match self.flag {
+            // global admin
+            // tenant admin
+            Flag::List => {
+                q = q.filter(
+                    user::dsl::is_admin.eq(true).or(dsl::exists(
+                        tenant_user::dsl::tenant_user.filter(
+                            tenant_user::dsl::user_id
+                                .eq(user::dsl::id)
+                                .and(tenant_user::dsl::tenant_id.eq(&self.tenant_id))
+                                .and(tenant_user::dsl::is_admin.eq(true)),
+                        ),
+                    )),
+                );
+            }
+            _ => {
+                return Ok(0);
+            }
+        };
+
+        Ok(q.first(&mut get_async_db_conn().await?).await?)
+    }
+
+    async fn validate_key(&self, id: &Uuid) -> Result<i64, Error> {
+        let mut q = api_key::dsl::api_key
+            .select(dsl::count_star())
+            .find(id)
+            .into_boxed();
+
+        match self.flag {
+            // admin api key
+            // tenant api key (not restricted to an application)
+            Flag::List => {
+                q = q.filter(
+                    api_key::dsl::is_admin.eq(true).or(api_key::dsl::tenant_id
+                        .eq(&self.tenant_id)
+                        .and(api_key::dsl::application_id.is_null())),
+                );
+            }
+            _ => {
+                return Ok(0);
+            }
+        };
+
+        Ok(q.first(&mut get_async_db_conn().await?).await?)
+    }
+}
+
 pub struct ValidateApplicationsAccess {
     flag: Flag,
     tenant_id: Uuid,
@@ -681,6 +885,10 @@ impl ValidateApplicationsAccess {
 
 #[async_trait]
 impl Validator for ValidateApplicationsAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -709,15 +917,27 @@ impl Validator for ValidateApplicationsAccess {
             }
             // global admin
             // tenant user
+            // application user
             Flag::List => {
                 q = q.filter(
-                    user::dsl::is_admin.eq(true).or(dsl::exists(
-                        tenant_user::dsl::tenant_user.filter(
-                            tenant_user::dsl::user_id
-                                .eq(user::dsl::id)
-                                .and(tenant_user::dsl::tenant_id.eq(&self.tenant_id)),
-                        ),
-                    )),
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
+                            tenant_user::dsl::tenant_user.filter(
+                                tenant_user::dsl::user_id
+                                    .eq(user::dsl::id)
+                                    .and(tenant_user::dsl::tenant_id.eq(&self.tenant_id)),
+                            ),
+                        ))
+                        .or(dsl::exists(
+                            application_user::dsl::application_user
+                                .inner_join(application::table)
+                                .filter(
+                                    application::dsl::tenant_id
+                                        .eq(&self.tenant_id)
+                                        .and(application_user::dsl::user_id.eq(user::dsl::id)),
+                                ),
+                        )),
                 );
             }
             _ => {
@@ -778,6 +998,14 @@ impl ValidateApplicationAccess {
 
 #[async_trait]
 impl Validator for ValidateApplicationAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
+    async fn application_id(&self) -> Result<Option<Uuid>, Error> {
+        Ok(Some(self.application_id))
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -787,10 +1015,12 @@ impl Validator for ValidateApplicationAccess {
         match self.flag {
             // global admin
             // tenant user
+            // application user
             Flag::Read => {
-                q =
-                    q.filter(
-                        user::dsl::is_admin.eq(true).or(dsl::exists(
+                q = q.filter(
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
                             application::dsl::application
                                 .inner_join(tenant_user::table.on(
                                     tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
@@ -800,16 +1030,26 @@ impl Validator for ValidateApplicationAccess {
                                         .eq(&self.application_id)
                                         .and(tenant_user::dsl::user_id.eq(user::dsl::id)),
                                 ),
+                        ))
+                        .or(dsl::exists(
+                            application_user::dsl::application_user.filter(
+                                application_user::dsl::application_id
+                                    .eq(&self.application_id)
+                                    .and(application_user::dsl::user_id.eq(user::dsl::id)),
+                            ),
                         )),
-                    );
+                );
             }
             // global admin
             // tenant admin
             // tenant device admin
+            // application admin
+            // application device admin
             Flag::Update | Flag::Delete => {
-                q =
-                    q.filter(
-                        user::dsl::is_admin.eq(true).or(dsl::exists(
+                q = q.filter(
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
                             application::dsl::application
                                 .inner_join(tenant_user::table.on(
                                     tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
@@ -824,8 +1064,20 @@ impl Validator for ValidateApplicationAccess {
                                                 .or(tenant_user::dsl::is_device_admin.eq(true)),
                                         ),
                                 ),
+                        ))
+                        .or(dsl::exists(
+                            application_user::dsl::application_user.filter(
+                                application_user::dsl::application_id
+                                    .eq(&self.application_id)
+                                    .and(application_user::dsl::user_id.eq(user::dsl::id))
+                                    .and(
+                                        application_user::dsl::is_admin
+                                            .eq(true)
+                                            .or(application_user::dsl::is_device_admin.eq(true)),
+                                    ),
+                            ),
                         )),
-                    );
+                );
             }
             _ => {
                 return Ok(0);
@@ -862,18 +1114,30 @@ impl Validator for ValidateApplicationAccess {
     }
 }
 
-pub struct ValidateDeviceProfileTemplatesAccess {
+pub struct ValidateApplicationUsersAccess {
     flag: Flag,
+    application_id: Uuid,
 }
 
-impl ValidateDeviceProfileTemplatesAccess {
-    pub fn new(flag: Flag) -> Self {
-        ValidateDeviceProfileTemplatesAccess { flag }
+impl ValidateApplicationUsersAccess {
+    pub fn new(flag: Flag, application_id: Uuid) -> Self {
+        ValidateApplicationUsersAccess {
+            flag,
+            application_id,
+        }
     }
 }
 
 #[async_trait]
-impl Validator for ValidateDeviceProfileTemplatesAccess {
+impl Validator for ValidateApplicationUsersAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
+    async fn application_id(&self) -> Result<Option<Uuid>, Error> {
+        Ok(Some(self.application_id))
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -882,11 +1146,61 @@ impl Validator for ValidateDeviceProfileTemplatesAccess {
 
         match self.flag {
             // global admin
+            // tenant admin
+            // application admin
             Flag::Create => {
-                q = q.filter(user::dsl::is_admin.eq(true));
+                q = q.filter(
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
+                            application::dsl::application
+                                .inner_join(tenant_user::table.on(
+                                    tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
+                                ))
+                                .filter(
+                                    application::dsl::id
+                                        .eq(&self.application_id)
+                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id))
+                                        .and(tenant_user::dsl::is_admin.eq(true)),
+                                ),
+                        ))
+                        .or(dsl::exists(
+                            application_user::dsl::application_user.filter(
+                                application_user::dsl::application_id
+                                    .eq(&self.application_id)
+                                    .and(application_user::dsl::user_id.eq(user::dsl::id))
+                                    .and(application_user::dsl::is_admin.eq(true)),
+                            ),
+                        )),
+                );
+            }
+            // global admin
+            // tenant user
+            // application user
+            Flag::List => {
+                q = q.filter(
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
+                            application::dsl::application
+                                .inner_join(tenant_user::table.on(
+                                    tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
+                                ))
+                                .filter(
+                                    application::dsl::id
+                                        .eq(&self.application_id)
+                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id)),
+                                ),
+                        ))
+                        .or(dsl::exists(
+                            application_user::dsl::application_user.filter(
+                                application_user::dsl::application_id
+                                    .eq(&self.application_id)
+                                    .and(application_user::dsl::user_id.eq(user::dsl::id)),
+                            ),
+                        )),
+                );
             }
-            // any active user
-            Flag::List => {}
             _ => {
                 return Ok(0);
             }
@@ -898,16 +1212,21 @@ impl Validator for ValidateDeviceProfileTemplatesAccess {
     async fn validate_key(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = api_key::dsl::api_key
             .select(dsl::count_star())
-            .find(id)
+            .filter(api_key::dsl::id.eq(id))
             .into_boxed();
 
         match self.flag {
             // admin api key
-            Flag::Create => {
-                q = q.filter(api_key::dsl::is_admin.eq(true));
+            // tenant api key
+            Flag::Create | Flag::List => {
+                q = q.filter(api_key::dsl::is_admin.eq(true).or(dsl::exists(
+                    application::dsl::application.filter(
+                        application::dsl::id.eq(&self.application_id).and(
+                            api_key::dsl::tenant_id.eq(application::dsl::tenant_id.nullable()),
+                        ),
+                    ),
+                )));
             }
-            // any api key
-            Flag::List => {}
             _ => {
                 return Ok(0);
             }
@@ -917,18 +1236,32 @@ impl Validator for ValidateDeviceProfileTemplatesAccess {
     }
 }
 
-pub struct ValidateDeviceProfileTemplateAccess {
+pub struct ValidateApplicationUserAccess {
     flag: Flag,
+    application_id: Uuid,
+    user_id: Uuid,
 }
 
-impl ValidateDeviceProfileTemplateAccess {
-    pub fn new(flag: Flag) -> Self {
-        ValidateDeviceProfileTemplateAccess { flag }
+impl ValidateApplicationUserAccess {
+    pub fn new(flag: Flag, application_id: Uuid, user_id: Uuid) -> Self {
+        ValidateApplicationUserAccess {
+            flag,
+            application_id,
+            user_id,
+        }
     }
 }
 
 #[async_trait]
-impl Validator for ValidateDeviceProfileTemplateAccess {
+impl Validator for ValidateApplicationUserAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
+    async fn application_id(&self) -> Result<Option<Uuid>, Error> {
+        Ok(Some(self.application_id))
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -936,14 +1269,194 @@ impl Validator for ValidateDeviceProfileTemplateAccess {
             .into_boxed();
 
         match self.flag {
-            // any active user
-            Flag::Read => {}
-            // global admin user
-            Flag::Update | Flag::Delete => {
-                q = q.filter(user::dsl::is_admin.eq(true));
-            }
-            _ => {
-                return Ok(0);
+            // global admin
+            // tenant admin
+            // application admin
+            // user itself
+            Flag::Read => {
+                q = q.filter(
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
+                            application::dsl::application
+                                .inner_join(tenant_user::table.on(
+                                    tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
+                                ))
+                                .filter(
+                                    application::dsl::id
+                                        .eq(&self.application_id)
+                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id))
+                                        .and(tenant_user::dsl::is_admin.eq(true)),
+                                ),
+                        ))
+                        .or(dsl::exists(
+                            application_user::dsl::application_user.filter(
+                                application_user::dsl::application_id
+                                    .eq(&self.application_id)
+                                    .and(application_user::dsl::user_id.eq(user::dsl::id))
+                                    .and(
+                                        application_user::dsl::is_admin
+                                            .eq(true)
+                                            .or(application_user::dsl::user_id.eq(&self.user_id)),
+                                    ),
+                            ),
+                        )),
+                );
+            }
+            // global admin
+            // tenant admin
+            // application admin
+            Flag::Update | Flag::Delete => {
+                q = q.filter(
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
+                            application::dsl::application
+                                .inner_join(tenant_user::table.on(
+                                    tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
+                                ))
+                                .filter(
+                                    application::dsl::id
+                                        .eq(&self.application_id)
+                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id))
+                                        .and(tenant_user::dsl::is_admin.eq(true)),
+                                ),
+                        ))
+                        .or(dsl::exists(
+                            application_user::dsl::application_user.filter(
+                                application_user::dsl::application_id
+                                    .eq(&self.application_id)
+                                    .and(application_user::dsl::user_id.eq(user::dsl::id))
+                                    .and(application_user::dsl::is_admin.eq(true)),
+                            ),
+                        )),
+                );
+            }
+            _ => {
+                return Ok(0);
+            }
+        };
+
+        Ok(q.first(&mut get_async_db_conn().await?).await?)
+    }
+
+    async fn validate_key(&self, id: &Uuid) -> Result<i64, Error> {
+        let mut q = api_key::dsl::api_key
+            .select(dsl::count_star())
+            .filter(api_key::dsl::id.eq(id))
+            .into_boxed();
+
+        match self.flag {
+            // admin api key
+            // tenant api key
+            Flag::Read | Flag::Update | Flag::Delete => {
+                q = q.filter(api_key::dsl::is_admin.eq(true).or(dsl::exists(
+                    application::dsl::application.filter(
+                        application::dsl::id.eq(&self.application_id).and(
+                            api_key::dsl::tenant_id.eq(application::dsl::tenant_id.nullable()),
+                        ),
+                    ),
+                )));
+            }
+            _ => {
+                return Ok(0);
+            }
+        };
+
+        Ok(q.first(&mut get_async_db_conn().await?).await?)
+    }
+}
+
+pub struct ValidateDeviceProfileTemplatesAccess {
+    flag: Flag,
+}
+
+impl ValidateDeviceProfileTemplatesAccess {
+    pub fn new(flag: Flag) -> Self {
+        ValidateDeviceProfileTemplatesAccess { flag }
+    }
+}
+
+#[async_trait]
+impl Validator for ValidateDeviceProfileTemplatesAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
+    async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
+        let mut q = user::dsl::user
+            .select(dsl::count_star())
+            .filter(user::dsl::id.eq(id).and(user::dsl::is_active.eq(true)))
+            .into_boxed();
+
+        match self.flag {
+            // global admin
+            Flag::Create => {
+                q = q.filter(user::dsl::is_admin.eq(true));
+            }
+            // any active user
+            Flag::List => {}
+            _ => {
+                return Ok(0);
+            }
+        };
+
+        Ok(q.first(&mut get_async_db_conn().await?).await?)
+    }
+
+    async fn validate_key(&self, id: &Uuid) -> Result<i64, Error> {
+        let mut q = api_key::dsl::api_key
+            .select(dsl::count_star())
+            .find(id)
+            .into_boxed();
+
+        match self.flag {
+            // admin api key
+            Flag::Create => {
+                q = q.filter(api_key::dsl::is_admin.eq(true));
+            }
+            // any api key
+            Flag::List => {}
+            _ => {
+                return Ok(0);
+            }
+        };
+
+        Ok(q.first(&mut get_async_db_conn().await?).await?)
+    }
+}
+
+pub struct ValidateDeviceProfileTemplateAccess {
+    flag: Flag,
+}
+
+impl ValidateDeviceProfileTemplateAccess {
+    pub fn new(flag: Flag) -> Self {
+        ValidateDeviceProfileTemplateAccess { flag }
+    }
+}
+
+#[async_trait]
+impl Validator for ValidateDeviceProfileTemplateAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
+    async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
+        let mut q = user::dsl::user
+            .select(dsl::count_star())
+            .filter(user::dsl::id.eq(id).and(user::dsl::is_active.eq(true)))
+            .into_boxed();
+
+        match self.flag {
+            // any active user
+            Flag::Read => {}
+            // global admin user
+            Flag::Update | Flag::Delete => {
+                q = q.filter(user::dsl::is_admin.eq(true));
+            }
+            _ => {
+                return Ok(0);
             }
         };
 
@@ -985,6 +1498,10 @@ impl ValidateDeviceProfilesAccess {
 
 #[async_trait]
 impl Validator for ValidateDeviceProfilesAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -1013,15 +1530,27 @@ impl Validator for ValidateDeviceProfilesAccess {
             }
             // global admin
             // tenant user
+            // application user
             Flag::List => {
                 q = q.filter(
-                    user::dsl::is_admin.eq(true).or(dsl::exists(
-                        tenant_user::dsl::tenant_user.filter(
-                            tenant_user::dsl::user_id
-                                .eq(user::dsl::id)
-                                .and(tenant_user::dsl::tenant_id.eq(&self.tenant_id)),
-                        ),
-                    )),
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
+                            tenant_user::dsl::tenant_user.filter(
+                                tenant_user::dsl::user_id
+                                    .eq(user::dsl::id)
+                                    .and(tenant_user::dsl::tenant_id.eq(&self.tenant_id)),
+                            ),
+                        ))
+                        .or(dsl::exists(
+                            application_user::dsl::application_user
+                                .inner_join(application::table)
+                                .filter(
+                                    application::dsl::tenant_id
+                                        .eq(&self.tenant_id)
+                                        .and(application_user::dsl::user_id.eq(user::dsl::id)),
+                                ),
+                        )),
                 );
             }
             _ => {
@@ -1073,6 +1602,10 @@ impl ValidateDeviceProfileAccess {
 
 #[async_trait]
 impl Validator for ValidateDeviceProfileAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -1082,10 +1615,12 @@ impl Validator for ValidateDeviceProfileAccess {
         match self.flag {
             // global admin
             // tenant user
+            // application user
             Flag::Read => {
-                q =
-                    q.filter(
-                        user::dsl::is_admin.eq(true).or(dsl::exists(
+                q = q.filter(
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
                             device_profile::dsl::device_profile
                                 .inner_join(tenant_user::table.on(
                                     tenant_user::dsl::tenant_id.eq(device_profile::dsl::tenant_id),
@@ -1095,8 +1630,22 @@ impl Validator for ValidateDeviceProfileAccess {
                                         .eq(&self.device_profile_id)
                                         .and(tenant_user::dsl::user_id.eq(user::dsl::id)),
                                 ),
+                        ))
+                        .or(dsl::exists(
+                            device_profile::dsl::device_profile
+                                .inner_join(application::table.on(
+                                    application::dsl::tenant_id.eq(device_profile::dsl::tenant_id),
+                                ))
+                                .inner_join(application_user::table.on(
+                                    application_user::dsl::application_id.eq(application::dsl::id),
+                                ))
+                                .filter(
+                                    device_profile::dsl::id
+                                        .eq(&self.device_profile_id)
+                                        .and(application_user::dsl::user_id.eq(user::dsl::id)),
+                                ),
                         )),
-                    );
+                );
             }
             // global admin
             // tenant admin user
@@ -1157,22 +1706,23 @@ impl Validator for ValidateDeviceProfileAccess {
     }
 }
 
-pub struct ValidateDevicesAccess {
+pub struct ValidateCodecsAccess {
     flag: Flag,
-    application_id: Uuid,
+    tenant_id: Uuid,
 }
 
-impl ValidateDevicesAccess {
-    pub fn new(flag: Flag, app_id: Uuid) -> Self {
-        ValidateDevicesAccess {
-            flag,
-            application_id: app_id,
-        }
+impl ValidateCodecsAccess {
+    pub fn new(flag: Flag, tenant_id: Uuid) -> Self {
+        ValidateCodecsAccess { flag, tenant_id }
     }
 }
 
 #[async_trait]
-impl Validator for ValidateDevicesAccess {
+impl Validator for ValidateCodecsAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -1180,52 +1730,42 @@ impl Validator for ValidateDevicesAccess {
             .into_boxed();
 
         match self.flag {
-            // admin user
+            // global admin
             // tenant admin
             // tenant device admin
             Flag::Create => {
-                q =
-                    q.filter(
-                        user::dsl::is_admin.eq(true).or(dsl::exists(
-                            application::dsl::application
-                                .inner_join(tenant_user::table.on(
-                                    tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
-                                ))
-                                .filter(
-                                    application::dsl::id
-                                        .eq(&self.application_id)
-                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id))
-                                        .and(
-                                            tenant_user::dsl::is_admin
-                                                .eq(true)
-                                                .or(tenant_user::dsl::is_device_admin.eq(true)),
-                                        ),
+                q = q.filter(
+                    user::dsl::is_admin.eq(true).or(dsl::exists(
+                        tenant_user::dsl::tenant_user.filter(
+                            tenant_user::dsl::user_id
+                                .eq(user::dsl::id)
+                                .and(tenant_user::dsl::tenant_id.eq(&self.tenant_id))
+                                .and(
+                                    tenant_user::dsl::is_admin
+                                        .eq(true)
+                                        .or(tenant_user::dsl::is_device_admin.eq(true)),
                                 ),
-                        )),
-                    );
+                        ),
+                    )),
+                );
             }
-            // admin user
+            // global admin
             // tenant user
             Flag::List => {
-                q =
-                    q.filter(
-                        user::dsl::is_admin.eq(true).or(dsl::exists(
-                            application::dsl::application
-                                .inner_join(tenant_user::table.on(
-                                    tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
-                                ))
-                                .filter(
-                                    application::dsl::id
-                                        .eq(&self.application_id)
-                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id)),
-                                ),
-                        )),
-                    );
+                q = q.filter(
+                    user::dsl::is_admin.eq(true).or(dsl::exists(
+                        tenant_user::dsl::tenant_user.filter(
+                            tenant_user::dsl::user_id
+                                .eq(user::dsl::id)
+                                .and(tenant_user::dsl::tenant_id.eq(&self.tenant_id)),
+                        ),
+                    )),
+                );
             }
             _ => {
                 return Ok(0);
             }
-        }
+        };
 
         Ok(q.first(&mut get_async_db_conn().await?).await?)
     }
@@ -1233,43 +1773,45 @@ impl Validator for ValidateDevicesAccess {
     async fn validate_key(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = api_key::dsl::api_key
             .select(dsl::count_star())
-            .filter(api_key::dsl::id.eq(id))
+            .find(id)
             .into_boxed();
 
         match self.flag {
             // admin api key
             // tenant api key
             Flag::Create | Flag::List => {
-                q = q.filter(api_key::dsl::is_admin.eq(true).or(dsl::exists(
-                    application::dsl::application.filter(
-                        application::dsl::id.eq(&self.application_id).and(
-                            api_key::dsl::tenant_id.eq(application::dsl::tenant_id.nullable()),
-                        ),
-                    ),
-                )));
+                q = q.filter(
+                    api_key::dsl::is_admin
+                        .eq(true)
+                        .or(api_key::dsl::tenant_id.eq(&self.tenant_id)),
+                );
             }
             _ => {
                 return Ok(0);
             }
-        }
+        };
 
         Ok(q.first(&mut get_async_db_conn().await?).await?)
     }
 }
 
-pub struct ValidateDeviceAccess {
+pub struct ValidateCodecAccess {
     flag: Flag,
-    dev_eui: EUI64,
+    codec_id: Uuid,
 }
 
-impl ValidateDeviceAccess {
-    pub fn new(flag: Flag, dev_eui: EUI64) -> Self {
-        ValidateDeviceAccess { flag, dev_eui }
+impl ValidateCodecAccess {
+    pub fn new(flag: Flag, codec_id: Uuid) -> Self {
+        ValidateCodecAccess { flag, codec_id }
     }
 }
 
 #[async_trait]
-impl Validator for ValidateDeviceAccess {
+impl Validator for ValidateCodecAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -1277,54 +1819,52 @@ impl Validator for ValidateDeviceAccess {
             .into_boxed();
 
         match self.flag {
-            // admin user
+            // global admin
             // tenant user
             Flag::Read => {
-                q =
-                    q.filter(
-                        user::dsl::is_admin.eq(true).or(dsl::exists(
-                            device::dsl::device
-                                .inner_join(application::table)
-                                .inner_join(tenant_user::table.on(
-                                    tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
-                                ))
-                                .filter(
-                                    device::dsl::dev_eui
-                                        .eq(&self.dev_eui)
-                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id)),
-                                ),
-                        )),
-                    );
+                q = q.filter(
+                    user::dsl::is_admin.eq(true).or(dsl::exists(
+                        codec::dsl::codec
+                            .inner_join(
+                                tenant_user::table
+                                    .on(tenant_user::dsl::tenant_id.eq(codec::dsl::tenant_id)),
+                            )
+                            .filter(
+                                codec::dsl::id
+                                    .eq(&self.codec_id)
+                                    .and(tenant_user::dsl::user_id.eq(user::dsl::id)),
+                            ),
+                    )),
+                );
             }
-            // admin user
-            // tenant admin
+            // global admin
+            // tenant admin user
             // tenant device admin
             Flag::Update | Flag::Delete => {
-                q =
-                    q.filter(
-                        user::dsl::is_admin.eq(true).or(dsl::exists(
-                            device::dsl::device
-                                .inner_join(application::table)
-                                .inner_join(tenant_user::table.on(
-                                    tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
-                                ))
-                                .filter(
-                                    device::dsl::dev_eui
-                                        .eq(&self.dev_eui)
-                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id))
-                                        .and(
-                                            tenant_user::dsl::is_admin
-                                                .eq(true)
-                                                .or(tenant_user::dsl::is_device_admin.eq(true)),
-                                        ),
-                                ),
-                        )),
-                    );
+                q = q.filter(
+                    user::dsl::is_admin.eq(true).or(dsl::exists(
+                        codec::dsl::codec
+                            .inner_join(
+                                tenant_user::table
+                                    .on(tenant_user::dsl::tenant_id.eq(codec::dsl::tenant_id)),
+                            )
+                            .filter(
+                                codec::dsl::id
+                                    .eq(&self.codec_id)
+                                    .and(tenant_user::dsl::user_id.eq(user::dsl::id))
+                                    .and(
+                                        tenant_user::dsl::is_admin
+                                            .eq(true)
+                                            .or(tenant_user::dsl::is_device_admin.eq(true)),
+                                    ),
+                            ),
+                    )),
+                );
             }
             _ => {
                 return Ok(0);
             }
-        }
+        };
 
         Ok(q.first(&mut get_async_db_conn().await?).await?)
     }
@@ -1339,36 +1879,45 @@ impl Validator for ValidateDeviceAccess {
             // admin api key
             // tenant api key
             Flag::Read | Flag::Update | Flag::Delete => {
-                q = q.filter(api_key::dsl::is_admin.eq(true).or(dsl::exists(
-                    device::dsl::device.inner_join(application::table).filter(
-                        device::dsl::dev_eui.eq(self.dev_eui).and(
-                            api_key::dsl::tenant_id.eq(application::dsl::tenant_id.nullable()),
+                q = q.filter(
+                    api_key::dsl::is_admin.eq(true).or(dsl::exists(
+                        codec::dsl::codec.filter(
+                            codec::dsl::id
+                                .eq(&self.codec_id)
+                                .and(api_key::dsl::tenant_id.eq(codec::dsl::tenant_id.nullable())),
                         ),
-                    ),
-                )))
+                    )),
+                );
             }
             _ => {
                 return Ok(0);
             }
-        }
+        };
 
         Ok(q.first(&mut get_async_db_conn().await?).await?)
     }
 }
 
-pub struct ValidateDeviceQueueAccess {
+pub struct ValidateCodecVersionAccess {
     flag: Flag,
-    dev_eui: EUI64,
+    codec_version_id: Uuid,
 }
 
-impl ValidateDeviceQueueAccess {
-    pub fn new(flag: Flag, dev_eui: EUI64) -> Self {
-        ValidateDeviceQueueAccess { flag, dev_eui }
+impl ValidateCodecVersionAccess {
+    pub fn new(flag: Flag, codec_version_id: Uuid) -> Self {
+        ValidateCodecVersionAccess {
+            flag,
+            codec_version_id,
+        }
     }
 }
 
 #[async_trait]
-impl Validator for ValidateDeviceQueueAccess {
+impl Validator for ValidateCodecVersionAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -1376,29 +1925,29 @@ impl Validator for ValidateDeviceQueueAccess {
             .into_boxed();
 
         match self.flag {
-            // admin user
+            // global admin
             // tenant user
-            Flag::Create | Flag::List | Flag::Delete => {
-                q =
-                    q.filter(
-                        user::dsl::is_admin.eq(true).or(dsl::exists(
-                            device::dsl::device
-                                .inner_join(application::table)
-                                .inner_join(tenant_user::table.on(
-                                    tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
-                                ))
-                                .filter(
-                                    device::dsl::dev_eui
-                                        .eq(&self.dev_eui)
-                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id)),
-                                ),
-                        )),
-                    );
+            Flag::Read => {
+                q = q.filter(
+                    user::dsl::is_admin.eq(true).or(dsl::exists(
+                        codec_version::dsl::codec_version
+                            .inner_join(codec::table)
+                            .inner_join(
+                                tenant_user::table
+                                    .on(tenant_user::dsl::tenant_id.eq(codec::dsl::tenant_id)),
+                            )
+                            .filter(
+                                codec_version::dsl::id
+                                    .eq(&self.codec_version_id)
+                                    .and(tenant_user::dsl::user_id.eq(user::dsl::id)),
+                            ),
+                    )),
+                );
             }
             _ => {
                 return Ok(0);
             }
-        }
+        };
 
         Ok(q.first(&mut get_async_db_conn().await?).await?)
     }
@@ -1412,32 +1961,35 @@ impl Validator for ValidateDeviceQueueAccess {
         match self.flag {
             // admin api key
             // tenant api key
-            Flag::Create | Flag::List | Flag::Delete => {
-                q = q.filter(api_key::dsl::is_admin.eq(true).or(dsl::exists(
-                    device::dsl::device.inner_join(application::table).filter(
-                        device::dsl::dev_eui.eq(&self.dev_eui).and(
-                            api_key::dsl::tenant_id.eq(application::dsl::tenant_id.nullable()),
-                        ),
-                    ),
-                )));
+            Flag::Read => {
+                q =
+                    q.filter(
+                        api_key::dsl::is_admin.eq(true).or(dsl::exists(
+                            codec_version::dsl::codec_version
+                                .inner_join(codec::table)
+                                .filter(codec_version::dsl::id.eq(&self.codec_version_id).and(
+                                    api_key::dsl::tenant_id.eq(codec::dsl::tenant_id.nullable()),
+                                )),
+                        )),
+                    );
             }
             _ => {
                 return Ok(0);
             }
-        }
+        };
 
         Ok(q.first(&mut get_async_db_conn().await?).await?)
     }
 }
 
-pub struct ValidateDeviceConfigStoresAccess {
+pub struct ValidateDevicesAccess {
     flag: Flag,
     application_id: Uuid,
 }
 
-impl ValidateDeviceConfigStoresAccess {
+impl ValidateDevicesAccess {
     pub fn new(flag: Flag, app_id: Uuid) -> Self {
-        ValidateDeviceConfigStoresAccess {
+        ValidateDevicesAccess {
             flag,
             application_id: app_id,
         }
@@ -1445,10 +1997,94 @@ impl ValidateDeviceConfigStoresAccess {
 }
 
 #[async_trait]
-impl Validator for ValidateDeviceConfigStoresAccess {
-    async fn validate_user(&self, _: &Uuid) -> Result<i64, Error> {
-        // api key only
-        Ok(0)
+impl Validator for ValidateDevicesAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
+    async fn application_id(&self) -> Result<Option<Uuid>, Error> {
+        Ok(Some(self.application_id))
+    }
+
+    async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
+        let mut q = user::dsl::user
+            .select(dsl::count_star())
+            .filter(user::dsl::id.eq(id).and(user::dsl::is_active.eq(true)))
+            .into_boxed();
+
+        match self.flag {
+            // admin user
+            // tenant admin
+            // tenant device admin
+            // application admin
+            // application device admin
+            Flag::Create => {
+                q = q.filter(
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
+                            application::dsl::application
+                                .inner_join(tenant_user::table.on(
+                                    tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
+                                ))
+                                .filter(
+                                    application::dsl::id
+                                        .eq(&self.application_id)
+                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id))
+                                        .and(
+                                            tenant_user::dsl::is_admin
+                                                .eq(true)
+                                                .or(tenant_user::dsl::is_device_admin.eq(true)),
+                                        ),
+                                ),
+                        ))
+                        .or(dsl::exists(
+                            application_user::dsl::application_user.filter(
+                                application_user::dsl::application_id
+                                    .eq(&self.application_id)
+                                    .and(application_user::dsl::user_id.eq(user::dsl::id))
+                                    .and(
+                                        application_user::dsl::is_admin
+                                            .eq(true)
+                                            .or(application_user::dsl::is_device_admin.eq(true)),
+                                    ),
+                            ),
+                        )),
+                );
+            }
+            // admin user
+            // tenant user
+            // application user
+            Flag::List => {
+                q = q.filter(
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
+                            application::dsl::application
+                                .inner_join(tenant_user::table.on(
+                                    tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
+                                ))
+                                .filter(
+                                    application::dsl::id
+                                        .eq(&self.application_id)
+                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id)),
+                                ),
+                        ))
+                        .or(dsl::exists(
+                            application_user::dsl::application_user.filter(
+                                application_user::dsl::application_id
+                                    .eq(&self.application_id)
+                                    .and(application_user::dsl::user_id.eq(user::dsl::id)),
+                            ),
+                        )),
+                );
+            }
+            _ => {
+                return Ok(0);
+            }
+        }
+
+        Ok(q.first(&mut get_async_db_conn().await?).await?)
     }
 
     async fn validate_key(&self, id: &Uuid) -> Result<i64, Error> {
@@ -1460,7 +2096,7 @@ impl Validator for ValidateDeviceConfigStoresAccess {
         match self.flag {
             // admin api key
             // tenant api key
-            Flag::List => {
+            Flag::Create | Flag::List => {
                 q = q.filter(api_key::dsl::is_admin.eq(true).or(dsl::exists(
                     application::dsl::application.filter(
                         application::dsl::id.eq(&self.application_id).and(
@@ -1478,19 +2114,369 @@ impl Validator for ValidateDeviceConfigStoresAccess {
     }
 }
 
-pub struct ValidateDeviceConfigStoreAccess {
+pub struct ValidateDeviceAccess {
     flag: Flag,
     dev_eui: EUI64,
 }
 
-impl ValidateDeviceConfigStoreAccess {
+impl ValidateDeviceAccess {
     pub fn new(flag: Flag, dev_eui: EUI64) -> Self {
-        ValidateDeviceConfigStoreAccess { flag, dev_eui }
+        ValidateDeviceAccess { flag, dev_eui }
+    }
+}
+
+#[async_trait]
+impl Validator for ValidateDeviceAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
+    async fn application_id(&self) -> Result<Option<Uuid>, Error> {
+        device_application_id(&self.dev_eui).await
+    }
+
+    async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
+        let mut q = user::dsl::user
+            .select(dsl::count_star())
+            .filter(user::dsl::id.eq(id).and(user::dsl::is_active.eq(true)))
+            .into_boxed();
+
+        match self.flag {
+            // admin user
+            // tenant user
+            // application user
+            Flag::Read => {
+                q = q.filter(
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
+                            device::dsl::device
+                                .inner_join(application::table)
+                                .inner_join(tenant_user::table.on(
+                                    tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
+                                ))
+                                .filter(
+                                    device::dsl::dev_eui
+                                        .eq(&self.dev_eui)
+                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id)),
+                                ),
+                        ))
+                        .or(dsl::exists(
+                            device::dsl::device
+                                .inner_join(
+                                    application_user::table
+                                        .on(application_user::dsl::application_id
+                                            .eq(device::dsl::application_id)),
+                                )
+                                .filter(
+                                    device::dsl::dev_eui
+                                        .eq(&self.dev_eui)
+                                        .and(application_user::dsl::user_id.eq(user::dsl::id)),
+                                ),
+                        )),
+                );
+            }
+            // admin user
+            // tenant admin
+            // tenant device admin
+            // application admin
+            // application device admin
+            Flag::Update | Flag::Delete => {
+                q =
+                    q.filter(
+                        user::dsl::is_admin
+                            .eq(true)
+                            .or(dsl::exists(
+                                device::dsl::device
+                                    .inner_join(application::table)
+                                    .inner_join(tenant_user::table.on(
+                                        tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
+                                    ))
+                                    .filter(
+                                        device::dsl::dev_eui
+                                            .eq(&self.dev_eui)
+                                            .and(tenant_user::dsl::user_id.eq(user::dsl::id))
+                                            .and(
+                                                tenant_user::dsl::is_admin
+                                                    .eq(true)
+                                                    .or(tenant_user::dsl::is_device_admin.eq(true)),
+                                            ),
+                                    ),
+                            ))
+                            .or(dsl::exists(
+                                device::dsl::device
+                                    .inner_join(
+                                        application_user::table
+                                            .on(application_user::dsl::application_id
+                                                .eq(device::dsl::application_id)),
+                                    )
+                                    .filter(
+                                        device::dsl::dev_eui
+                                            .eq(&self.dev_eui)
+                                            .and(application_user::dsl::user_id.eq(user::dsl::id))
+                                            .and(application_user::dsl::is_admin.eq(true).or(
+                                                application_user::dsl::is_device_admin.eq(true),
+                                            )),
+                                    ),
+                            )),
+                    );
+            }
+            _ => {
+                return Ok(0);
+            }
+        }
+
+        Ok(q.first(&mut get_async_db_conn().await?).await?)
+    }
+
+    async fn validate_key(&self, id: &Uuid) -> Result<i64, Error> {
+        let mut q = api_key::dsl::api_key
+            .select(dsl::count_star())
+            .filter(api_key::dsl::id.eq(id))
+            .into_boxed();
+
+        match self.flag {
+            // admin api key
+            // tenant api key
+            Flag::Read | Flag::Update | Flag::Delete => {
+                q = q.filter(api_key::dsl::is_admin.eq(true).or(dsl::exists(
+                    device::dsl::device.inner_join(application::table).filter(
+                        device::dsl::dev_eui.eq(self.dev_eui).and(
+                            api_key::dsl::tenant_id.eq(application::dsl::tenant_id.nullable()),
+                        ),
+                    ),
+                )))
+            }
+            _ => {
+                return Ok(0);
+            }
+        }
+
+        Ok(q.first(&mut get_async_db_conn().await?).await?)
+    }
+}
+
+pub struct ValidateDeviceQueueAccess {
+    flag: Flag,
+    dev_eui: EUI64,
+}
+
+impl ValidateDeviceQueueAccess {
+    pub fn new(flag: Flag, dev_eui: EUI64) -> Self {
+        ValidateDeviceQueueAccess { flag, dev_eui }
+    }
+}
+
+#[async_trait]
+impl Validator for ValidateDeviceQueueAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
+    fn key_scopes(&self) -> Vec<ApiKeyScope> {
+        match self.flag {
+            Flag::Create => vec![ApiKeyScope::FULL, ApiKeyScope::DEVICE_QUEUE_ENQUEUE],
+            Flag::List => vec![ApiKeyScope::FULL, ApiKeyScope::READ_ONLY],
+            _ => vec![ApiKeyScope::FULL],
+        }
+    }
+
+    async fn application_id(&self) -> Result<Option<Uuid>, Error> {
+        device_application_id(&self.dev_eui).await
+    }
+
+    async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
+        let mut q = user::dsl::user
+            .select(dsl::count_star())
+            .filter(user::dsl::id.eq(id).and(user::dsl::is_active.eq(true)))
+            .into_boxed();
+
+        match self.flag {
+            // admin user
+            // tenant user
+            // application admin
+            // application device admin
+            Flag::Create | Flag::Delete => {
+                q =
+                    q.filter(
+                        user::dsl::is_admin
+                            .eq(true)
+                            .or(dsl::exists(
+                                device::dsl::device
+                                    .inner_join(application::table)
+                                    .inner_join(tenant_user::table.on(
+                                        tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
+                                    ))
+                                    .filter(
+                                        device::dsl::dev_eui
+                                            .eq(&self.dev_eui)
+                                            .and(tenant_user::dsl::user_id.eq(user::dsl::id)),
+                                    ),
+                            ))
+                            .or(dsl::exists(
+                                device::dsl::device
+                                    .inner_join(
+                                        application_user::table
+                                            .on(application_user::dsl::application_id
+                                                .eq(device::dsl::application_id)),
+                                    )
+                                    .filter(
+                                        device::dsl::dev_eui
+                                            .eq(&self.dev_eui)
+                                            .and(application_user::dsl::user_id.eq(user::dsl::id))
+                                            .and(application_user::dsl::is_admin.eq(true).or(
+                                                application_user::dsl::is_device_admin.eq(true),
+                                            )),
+                                    ),
+                            )),
+                    );
+            }
+            // admin user
+            // tenant user
+            // application user
+            Flag::List => {
+                q = q.filter(
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
+                            device::dsl::device
+                                .inner_join(application::table)
+                                .inner_join(tenant_user::table.on(
+                                    tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
+                                ))
+                                .filter(
+                                    device::dsl::dev_eui
+                                        .eq(&self.dev_eui)
+                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id)),
+                                ),
+                        ))
+                        .or(dsl::exists(
+                            device::dsl::device
+                                .inner_join(
+                                    application_user::table
+                                        .on(application_user::dsl::application_id
+                                            .eq(device::dsl::application_id)),
+                                )
+                                .filter(
+                                    device::dsl::dev_eui
+                                        .eq(&self.dev_eui)
+                                        .and(application_user::dsl::user_id.eq(user::dsl::id)),
+                                ),
+                        )),
+                );
+            }
+            _ => {
+                return Ok(0);
+            }
+        }
+
+        Ok(q.first(&mut get_async_db_conn().await?).await?)
+    }
+
+    async fn validate_key(&self, id: &Uuid) -> Result<i64, Error> {
+        let mut q = api_key::dsl::api_key
+            .select(dsl::count_star())
+            .filter(api_key::dsl::id.eq(id))
+            .into_boxed();
+
+        match self.flag {
+            // admin api key
+            // tenant api key
+            Flag::Create | Flag::List | Flag::Delete => {
+                q = q.filter(api_key::dsl::is_admin.eq(true).or(dsl::exists(
+                    device::dsl::device.inner_join(application::table).filter(
+                        device::dsl::dev_eui.eq(&self.dev_eui).and(
+                            api_key::dsl::tenant_id.eq(application::dsl::tenant_id.nullable()),
+                        ),
+                    ),
+                )));
+            }
+            _ => {
+                return Ok(0);
+            }
+        }
+
+        Ok(q.first(&mut get_async_db_conn().await?).await?)
+    }
+}
+
+pub struct ValidateDeviceConfigStoresAccess {
+    flag: Flag,
+    application_id: Uuid,
+}
+
+impl ValidateDeviceConfigStoresAccess {
+    pub fn new(flag: Flag, app_id: Uuid) -> Self {
+        ValidateDeviceConfigStoresAccess {
+            flag,
+            application_id: app_id,
+        }
+    }
+}
+
+#[async_trait]
+impl Validator for ValidateDeviceConfigStoresAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
+    async fn application_id(&self) -> Result<Option<Uuid>, Error> {
+        Ok(Some(self.application_id))
+    }
+
+    async fn validate_user(&self, _: &Uuid) -> Result<i64, Error> {
+        // api key only
+        Ok(0)
+    }
+
+    async fn validate_key(&self, id: &Uuid) -> Result<i64, Error> {
+        let mut q = api_key::dsl::api_key
+            .select(dsl::count_star())
+            .filter(api_key::dsl::id.eq(id))
+            .into_boxed();
+
+        match self.flag {
+            // admin api key
+            // tenant api key
+            Flag::List => {
+                q = q.filter(api_key::dsl::is_admin.eq(true).or(dsl::exists(
+                    application::dsl::application.filter(
+                        application::dsl::id.eq(&self.application_id).and(
+                            api_key::dsl::tenant_id.eq(application::dsl::tenant_id.nullable()),
+                        ),
+                    ),
+                )));
+            }
+            _ => {
+                return Ok(0);
+            }
+        }
+
+        Ok(q.first(&mut get_async_db_conn().await?).await?)
+    }
+}
+
+pub struct ValidateDeviceConfigStoreAccess {
+    flag: Flag,
+    dev_eui: EUI64,
+}
+
+impl ValidateDeviceConfigStoreAccess {
+    pub fn new(flag: Flag, dev_eui: EUI64) -> Self {
+        ValidateDeviceConfigStoreAccess { flag, dev_eui }
     }
 }
 
 #[async_trait]
 impl Validator for ValidateDeviceConfigStoreAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
+    async fn application_id(&self) -> Result<Option<Uuid>, Error> {
+        device_application_id(&self.dev_eui).await
+    }
+
     async fn validate_user(&self, _: &Uuid) -> Result<i64, Error> {
         // api key only
         Ok(0)
@@ -1536,6 +2522,10 @@ impl ValidateGatewaysAccess {
 
 #[async_trait]
 impl Validator for ValidateGatewaysAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -1621,6 +2611,10 @@ impl ValidateGatewayAccess {
 
 #[async_trait]
 impl Validator for ValidateGatewayAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -1706,9 +2700,126 @@ impl Validator for ValidateGatewayAccess {
     }
 }
 
-pub struct ValidateMulticastGroupsAccess {
+// Relay gateways are not stored in the gateway table, therefore this validates access to the
+// relay gateway identified by its tenant ID and relay ID, using the same rules as
+// ValidateGatewayAccess.
+pub struct ValidateRelayGatewayAccess {
     flag: Flag,
-    application_id: Uuid,
+    tenant_id: Uuid,
+    relay_id: RelayId,
+}
+
+impl ValidateRelayGatewayAccess {
+    pub fn new(flag: Flag, tenant_id: Uuid, relay_id: RelayId) -> Self {
+        ValidateRelayGatewayAccess {
+            flag,
+            tenant_id,
+            relay_id,
+        }
+    }
+}
+
+#[async_trait]
+impl Validator for ValidateRelayGatewayAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
+    async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
+        let mut q = user::dsl::user
+            .select(dsl::count_star())
+            .filter(user::dsl::id.eq(id).and(user::dsl::is_active.eq(true)))
+            .into_boxed();
+
+        match self.flag {
+            // admin user
+            // tenant user
+            Flag::Read => {
+                q =
+                    q.filter(
+                        user::dsl::is_admin.eq(true).or(dsl::exists(
+                            relay_gateway::dsl::relay_gateway
+                                .inner_join(tenant_user::table.on(
+                                    tenant_user::dsl::tenant_id.eq(relay_gateway::dsl::tenant_id),
+                                ))
+                                .filter(
+                                    relay_gateway::dsl::tenant_id
+                                        .eq(&self.tenant_id)
+                                        .and(relay_gateway::dsl::relay_id.eq(&self.relay_id))
+                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id)),
+                                ),
+                        )),
+                    );
+            }
+            // admin user
+            // tenant admin
+            // gateway admin
+            Flag::Update | Flag::Delete => {
+                q =
+                    q.filter(
+                        user::dsl::is_admin.eq(true).or(dsl::exists(
+                            relay_gateway::dsl::relay_gateway
+                                .inner_join(tenant_user::table.on(
+                                    tenant_user::dsl::tenant_id.eq(relay_gateway::dsl::tenant_id),
+                                ))
+                                .filter(
+                                    relay_gateway::dsl::tenant_id
+                                        .eq(&self.tenant_id)
+                                        .and(relay_gateway::dsl::relay_id.eq(&self.relay_id))
+                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id))
+                                        .and(
+                                            tenant_user::dsl::is_admin
+                                                .eq(true)
+                                                .or(tenant_user::dsl::is_gateway_admin.eq(true)),
+                                        ),
+                                ),
+                        )),
+                    );
+            }
+            _ => {
+                return Ok(0);
+            }
+        }
+
+        Ok(q.first(&mut get_async_db_conn().await?).await?)
+    }
+
+    async fn validate_key(&self, id: &Uuid) -> Result<i64, Error> {
+        let mut q = api_key::dsl::api_key
+            .select(dsl::count_star())
+            .filter(api_key::dsl::id.eq(id))
+            .into_boxed();
+
+        match self.flag {
+            // admin api key
+            // tenant api key
+            Flag::Read | Flag::Update | Flag::Delete => {
+                q = q.filter(
+                    api_key::dsl::is_admin.eq(true).or(dsl::exists(
+                        relay_gateway::dsl::relay_gateway.filter(
+                            relay_gateway::dsl::tenant_id
+                                .eq(&self.tenant_id)
+                                .and(relay_gateway::dsl::relay_id.eq(&self.relay_id))
+                                .and(
+                                    api_key::dsl::tenant_id
+                                        .eq(relay_gateway::dsl::tenant_id.nullable()),
+                                ),
+                        ),
+                    )),
+                );
+            }
+            _ => {
+                return Ok(0);
+            }
+        }
+
+        Ok(q.first(&mut get_async_db_conn().await?).await?)
+    }
+}
+
+pub struct ValidateMulticastGroupsAccess {
+    flag: Flag,
+    application_id: Uuid,
 }
 
 impl ValidateMulticastGroupsAccess {
@@ -1722,6 +2833,14 @@ impl ValidateMulticastGroupsAccess {
 
 #[async_trait]
 impl Validator for ValidateMulticastGroupsAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
+    async fn application_id(&self) -> Result<Option<Uuid>, Error> {
+        Ok(Some(self.application_id))
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -1732,10 +2851,13 @@ impl Validator for ValidateMulticastGroupsAccess {
             // admin user
             // tenant admin
             // tenant device admin
+            // application admin
+            // application device admin
             Flag::Create => {
-                q =
-                    q.filter(
-                        user::dsl::is_admin.eq(true).or(dsl::exists(
+                q = q.filter(
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
                             application::dsl::application
                                 .inner_join(tenant_user::table.on(
                                     tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
@@ -1750,15 +2872,29 @@ impl Validator for ValidateMulticastGroupsAccess {
                                                 .or(tenant_user::dsl::is_device_admin.eq(true)),
                                         ),
                                 ),
+                        ))
+                        .or(dsl::exists(
+                            application_user::dsl::application_user.filter(
+                                application_user::dsl::application_id
+                                    .eq(&self.application_id)
+                                    .and(application_user::dsl::user_id.eq(user::dsl::id))
+                                    .and(
+                                        application_user::dsl::is_admin
+                                            .eq(true)
+                                            .or(application_user::dsl::is_device_admin.eq(true)),
+                                    ),
+                            ),
                         )),
-                    );
+                );
             }
             // admin user
             // tenant user
+            // application user
             Flag::List => {
-                q =
-                    q.filter(
-                        user::dsl::is_admin.eq(true).or(dsl::exists(
+                q = q.filter(
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
                             application::dsl::application
                                 .inner_join(tenant_user::table.on(
                                     tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
@@ -1768,8 +2904,15 @@ impl Validator for ValidateMulticastGroupsAccess {
                                         .eq(&self.application_id)
                                         .and(tenant_user::dsl::user_id.eq(user::dsl::id)),
                                 ),
+                        ))
+                        .or(dsl::exists(
+                            application_user::dsl::application_user.filter(
+                                application_user::dsl::application_id
+                                    .eq(&self.application_id)
+                                    .and(application_user::dsl::user_id.eq(user::dsl::id)),
+                            ),
                         )),
-                    );
+                );
             }
             _ => {
                 return Ok(0);
@@ -1822,6 +2965,14 @@ impl ValidateMulticastGroupAccess {
 
 #[async_trait]
 impl Validator for ValidateMulticastGroupAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
+    async fn application_id(&self) -> Result<Option<Uuid>, Error> {
+        multicast_group_application_id(&self.multicast_group_id).await
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -1831,10 +2982,12 @@ impl Validator for ValidateMulticastGroupAccess {
         match self.flag {
             // admin user
             // tenant user
+            // application user
             Flag::Read => {
-                q =
-                    q.filter(
-                        user::dsl::is_admin.eq(true).or(dsl::exists(
+                q = q.filter(
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
                             multicast_group::dsl::multicast_group
                                 .inner_join(application::table)
                                 .inner_join(tenant_user::table.on(
@@ -1845,32 +2998,65 @@ impl Validator for ValidateMulticastGroupAccess {
                                         .eq(&self.multicast_group_id)
                                         .and(tenant_user::dsl::user_id.eq(user::dsl::id)),
                                 ),
+                        ))
+                        .or(dsl::exists(
+                            multicast_group::dsl::multicast_group
+                                .inner_join(
+                                    application_user::table
+                                        .on(application_user::dsl::application_id
+                                            .eq(multicast_group::dsl::application_id)),
+                                )
+                                .filter(
+                                    multicast_group::dsl::id
+                                        .eq(&self.multicast_group_id)
+                                        .and(application_user::dsl::user_id.eq(user::dsl::id)),
+                                ),
                         )),
-                    );
+                );
             }
             // admin user
             // tenant admin
             // tenant device admin
+            // application admin
+            // application device admin
             Flag::Update | Flag::Delete => {
                 q =
                     q.filter(
-                        user::dsl::is_admin.eq(true).or(dsl::exists(
-                            multicast_group::dsl::multicast_group
-                                .inner_join(application::table)
-                                .inner_join(tenant_user::table.on(
-                                    tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
-                                ))
-                                .filter(
-                                    multicast_group::dsl::id
-                                        .eq(&self.multicast_group_id)
-                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id))
-                                        .and(
-                                            tenant_user::dsl::is_admin
-                                                .eq(true)
-                                                .or(tenant_user::dsl::is_device_admin.eq(true)),
-                                        ),
-                                ),
-                        )),
+                        user::dsl::is_admin
+                            .eq(true)
+                            .or(dsl::exists(
+                                multicast_group::dsl::multicast_group
+                                    .inner_join(application::table)
+                                    .inner_join(tenant_user::table.on(
+                                        tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
+                                    ))
+                                    .filter(
+                                        multicast_group::dsl::id
+                                            .eq(&self.multicast_group_id)
+                                            .and(tenant_user::dsl::user_id.eq(user::dsl::id))
+                                            .and(
+                                                tenant_user::dsl::is_admin
+                                                    .eq(true)
+                                                    .or(tenant_user::dsl::is_device_admin.eq(true)),
+                                            ),
+                                    ),
+                            ))
+                            .or(dsl::exists(
+                                multicast_group::dsl::multicast_group
+                                    .inner_join(
+                                        application_user::table
+                                            .on(application_user::dsl::application_id
+                                                .eq(multicast_group::dsl::application_id)),
+                                    )
+                                    .filter(
+                                        multicast_group::dsl::id
+                                            .eq(&self.multicast_group_id)
+                                            .and(application_user::dsl::user_id.eq(user::dsl::id))
+                                            .and(application_user::dsl::is_admin.eq(true).or(
+                                                application_user::dsl::is_device_admin.eq(true),
+                                            )),
+                                    ),
+                            )),
                     );
             }
             _ => {
@@ -1926,6 +3112,14 @@ impl ValidateMulticastGroupQueueAccess {
 
 #[async_trait]
 impl Validator for ValidateMulticastGroupQueueAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
+    async fn application_id(&self) -> Result<Option<Uuid>, Error> {
+        multicast_group_application_id(&self.multicast_group_id).await
+    }
+
     async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
         let mut q = user::dsl::user
             .select(dsl::count_star())
@@ -1936,34 +3130,56 @@ impl Validator for ValidateMulticastGroupQueueAccess {
             // admin user
             // tenant admin
             // tenant device admin
+            // application admin
+            // application device admin
             Flag::Create | Flag::Delete => {
                 q =
                     q.filter(
-                        user::dsl::is_admin.eq(true).or(dsl::exists(
-                            multicast_group::dsl::multicast_group
-                                .inner_join(application::table)
-                                .inner_join(tenant_user::table.on(
-                                    tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
-                                ))
-                                .filter(
-                                    multicast_group::dsl::id
-                                        .eq(&self.multicast_group_id)
-                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id))
-                                        .and(
-                                            tenant_user::dsl::is_admin
-                                                .eq(true)
-                                                .or(tenant_user::dsl::is_device_admin.eq(true)),
-                                        ),
-                                ),
-                        )),
+                        user::dsl::is_admin
+                            .eq(true)
+                            .or(dsl::exists(
+                                multicast_group::dsl::multicast_group
+                                    .inner_join(application::table)
+                                    .inner_join(tenant_user::table.on(
+                                        tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
+                                    ))
+                                    .filter(
+                                        multicast_group::dsl::id
+                                            .eq(&self.multicast_group_id)
+                                            .and(tenant_user::dsl::user_id.eq(user::dsl::id))
+                                            .and(
+                                                tenant_user::dsl::is_admin
+                                                    .eq(true)
+                                                    .or(tenant_user::dsl::is_device_admin.eq(true)),
+                                            ),
+                                    ),
+                            ))
+                            .or(dsl::exists(
+                                multicast_group::dsl::multicast_group
+                                    .inner_join(
+                                        application_user::table
+                                            .on(application_user::dsl::application_id
+                                                .eq(multicast_group::dsl::application_id)),
+                                    )
+                                    .filter(
+                                        multicast_group::dsl::id
+                                            .eq(&self.multicast_group_id)
+                                            .and(application_user::dsl::user_id.eq(user::dsl::id))
+                                            .and(application_user::dsl::is_admin.eq(true).or(
+                                                application_user::dsl::is_device_admin.eq(true),
+                                            )),
+                                    ),
+                            )),
                     );
             }
             // admin user
             // tenant user
+            // application user
             Flag::List => {
-                q =
-                    q.filter(
-                        user::dsl::is_admin.eq(true).or(dsl::exists(
+                q = q.filter(
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
                             multicast_group::dsl::multicast_group
                                 .inner_join(application::table)
                                 .inner_join(tenant_user::table.on(
@@ -1974,8 +3190,21 @@ impl Validator for ValidateMulticastGroupQueueAccess {
                                         .eq(&self.multicast_group_id)
                                         .and(tenant_user::dsl::user_id.eq(user::dsl::id)),
                                 ),
+                        ))
+                        .or(dsl::exists(
+                            multicast_group::dsl::multicast_group
+                                .inner_join(
+                                    application_user::table
+                                        .on(application_user::dsl::application_id
+                                            .eq(multicast_group::dsl::application_id)),
+                                )
+                                .filter(
+                                    multicast_group::dsl::id
+                                        .eq(&self.multicast_group_id)
+                                        .and(application_user::dsl::user_id.eq(user::dsl::id)),
+                                ),
                         )),
-                    );
+                );
             }
             _ => {
                 return Ok(0);
@@ -2014,67 +3243,347 @@ impl Validator for ValidateMulticastGroupQueueAccess {
     }
 }
 
-#[cfg(test)]
-pub mod test {
-    use super::*;
-    use crate::storage::{
-        api_key, application, device, device_profile, gateway, multicast, tenant, user,
-    };
-    use crate::test;
-    use std::str::FromStr;
+pub struct ValidateFuotaCampaignsAccess {
+    flag: Flag,
+    application_id: Uuid,
+}
 
-    struct ValidatorTest<V>
-    where
-        V: Validator + Sync,
-    {
-        validators: Vec<V>,
-        id: AuthID,
-        ok: bool,
+impl ValidateFuotaCampaignsAccess {
+    pub fn new(flag: Flag, application_id: Uuid) -> Self {
+        ValidateFuotaCampaignsAccess {
+            flag,
+            application_id,
+        }
     }
+}
 
-    async fn run_tests<V>(tests: Vec<ValidatorTest<V>>)
-    where
-        V: Validator + Sync,
-    {
-        println!("Running tests");
-        for (i, tst) in tests.iter().enumerate() {
-            for (j, v) in tst.validators.iter().enumerate() {
-                assert_eq!(
-                    tst.ok,
-                    v.validate(&tst.id).await.is_ok(),
-                    "Test {}, assertion {}",
-                    i,
-                    j
-                );
-            }
-        }
+#[async_trait]
+impl Validator for ValidateFuotaCampaignsAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
     }
 
-    #[tokio::test]
-    async fn validate_is_admin() {
-        let _guard = test::prepare().await;
-        let users = vec![
-            user::User {
-                email: "admin@user".into(),
-                is_active: true,
-                is_admin: true,
-                ..Default::default()
-            },
-            user::User {
-                email: "inactive@user".into(),
-                is_active: false,
-                is_admin: false,
-                ..Default::default()
-            },
-            user::User {
-                email: "normal@user".into(),
-                is_active: true,
-                is_admin: false,
-                ..Default::default()
-            },
-        ];
-        for u in &users {
-            user::create(u.clone()).await.unwrap();
+    async fn application_id(&self) -> Result<Option<Uuid>, Error> {
+        Ok(Some(self.application_id))
+    }
+
+    async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
+        let mut q = user::dsl::user
+            .select(dsl::count_star())
+            .filter(user::dsl::id.eq(id).and(user::dsl::is_active.eq(true)))
+            .into_boxed();
+
+        match self.flag {
+            // admin user
+            // tenant admin
+            // tenant device admin
+            // application admin
+            // application device admin
+            Flag::Create => {
+                q = q.filter(
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
+                            application::dsl::application
+                                .inner_join(tenant_user::table.on(
+                                    tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
+                                ))
+                                .filter(
+                                    application::dsl::id
+                                        .eq(&self.application_id)
+                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id))
+                                        .and(
+                                            tenant_user::dsl::is_admin
+                                                .eq(true)
+                                                .or(tenant_user::dsl::is_device_admin.eq(true)),
+                                        ),
+                                ),
+                        ))
+                        .or(dsl::exists(
+                            application_user::dsl::application_user.filter(
+                                application_user::dsl::application_id
+                                    .eq(&self.application_id)
+                                    .and(application_user::dsl::user_id.eq(user::dsl::id))
+                                    .and(
+                                        application_user::dsl::is_admin
+                                            .eq(true)
+                                            .or(application_user::dsl::is_device_admin.eq(true)),
+                                    ),
+                            ),
+                        )),
+                );
+            }
+            // admin user
+            // tenant user
+            // application user
+            Flag::List => {
+                q = q.filter(
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
+                            application::dsl::application
+                                .inner_join(tenant_user::table.on(
+                                    tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
+                                ))
+                                .filter(
+                                    application::dsl::id
+                                        .eq(&self.application_id)
+                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id)),
+                                ),
+                        ))
+                        .or(dsl::exists(
+                            application_user::dsl::application_user.filter(
+                                application_user::dsl::application_id
+                                    .eq(&self.application_id)
+                                    .and(application_user::dsl::user_id.eq(user::dsl::id)),
+                            ),
+                        )),
+                );
+            }
+            _ => {
+                return Ok(0);
+            }
+        }
+
+        Ok(q.first(&mut get_async_db_conn().await?).await?)
+    }
+
+    async fn validate_key(&self, id: &Uuid) -> Result<i64, Error> {
+        let mut q = api_key::dsl::api_key
+            .select(dsl::count_star())
+            .filter(api_key::dsl::id.eq(id))
+            .into_boxed();
+
+        match self.flag {
+            // admin api key
+            // tenant api key
+            Flag::Create | Flag::List => {
+                q = q.filter(api_key::dsl::is_admin.eq(true).or(dsl::exists(
+                    application::dsl::application.filter(
+                        application::dsl::id.eq(&self.application_id).and(
+                            api_key::dsl::tenant_id.eq(application::dsl::tenant_id.nullable()),
+                        ),
+                    ),
+                )));
+            }
+            _ => {
+                return Ok(0);
+            }
+        }
+
+        Ok(q.first(&mut get_async_db_conn().await?).await?)
+    }
+}
+
+pub struct ValidateFuotaCampaignAccess {
+    flag: Flag,
+    fuota_campaign_id: Uuid,
+}
+
+impl ValidateFuotaCampaignAccess {
+    pub fn new(flag: Flag, fuota_campaign_id: Uuid) -> Self {
+        ValidateFuotaCampaignAccess {
+            flag,
+            fuota_campaign_id,
+        }
+    }
+}
+
+#[async_trait]
+impl Validator for ValidateFuotaCampaignAccess {
+    fn flag(&self) -> Option<Flag> {
+        Some(self.flag)
+    }
+
+    async fn application_id(&self) -> Result<Option<Uuid>, Error> {
+        fuota_campaign_application_id(&self.fuota_campaign_id).await
+    }
+
+    async fn validate_user(&self, id: &Uuid) -> Result<i64, Error> {
+        let mut q = user::dsl::user
+            .select(dsl::count_star())
+            .filter(user::dsl::id.eq(id).and(user::dsl::is_active.eq(true)))
+            .into_boxed();
+
+        match self.flag {
+            // admin user
+            // tenant user
+            // application user
+            Flag::Read => {
+                q = q.filter(
+                    user::dsl::is_admin
+                        .eq(true)
+                        .or(dsl::exists(
+                            fuota_campaign::dsl::fuota_campaign
+                                .inner_join(application::table)
+                                .inner_join(tenant_user::table.on(
+                                    tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
+                                ))
+                                .filter(
+                                    fuota_campaign::dsl::id
+                                        .eq(&self.fuota_campaign_id)
+                                        .and(tenant_user::dsl::user_id.eq(user::dsl::id)),
+                                ),
+                        ))
+                        .or(dsl::exists(
+                            fuota_campaign::dsl::fuota_campaign
+                                .inner_join(
+                                    application_user::table
+                                        .on(application_user::dsl::application_id
+                                            .eq(fuota_campaign::dsl::application_id)),
+                                )
+                                .filter(
+                                    fuota_campaign::dsl::id
+                                        .eq(&self.fuota_campaign_id)
+                                        .and(application_user::dsl::user_id.eq(user::dsl::id)),
+                                ),
+                        )),
+                );
+            }
+            // admin user
+            // tenant admin
+            // tenant device admin
+            // application admin
+            // application device admin
+            Flag::Update | Flag::Delete => {
+                q =
+                    q.filter(
+                        user::dsl::is_admin
+                            .eq(true)
+                            .or(dsl::exists(
+                                fuota_campaign::dsl::fuota_campaign
+                                    .inner_join(application::table)
+                                    .inner_join(tenant_user::table.on(
+                                        tenant_user::dsl::tenant_id.eq(application::dsl::tenant_id),
+                                    ))
+                                    .filter(
+                                        fuota_campaign::dsl::id
+                                            .eq(&self.fuota_campaign_id)
+                                            .and(tenant_user::dsl::user_id.eq(user::dsl::id))
+                                            .and(
+                                                tenant_user::dsl::is_admin
+                                                    .eq(true)
+                                                    .or(tenant_user::dsl::is_device_admin.eq(true)),
+                                            ),
+                                    ),
+                            ))
+                            .or(dsl::exists(
+                                fuota_campaign::dsl::fuota_campaign
+                                    .inner_join(
+                                        application_user::table
+                                            .on(application_user::dsl::application_id
+                                                .eq(fuota_campaign::dsl::application_id)),
+                                    )
+                                    .filter(
+                                        fuota_campaign::dsl::id
+                                            .eq(&self.fuota_campaign_id)
+                                            .and(application_user::dsl::user_id.eq(user::dsl::id))
+                                            .and(application_user::dsl::is_admin.eq(true).or(
+                                                application_user::dsl::is_device_admin.eq(true),
+                                            )),
+                                    ),
+                            )),
+                    );
+            }
+            _ => {
+                return Ok(0);
+            }
+        }
+
+        Ok(q.first(&mut get_async_db_conn().await?).await?)
+    }
+
+    async fn validate_key(&self, id: &Uuid) -> Result<i64, Error> {
+        let mut q = api_key::dsl::api_key
+            .select(dsl::count_star())
+            .filter(api_key::dsl::id.eq(id))
+            .into_boxed();
+
+        match self.flag {
+            // admin api key
+            // tenant api key
+            Flag::Read | Flag::Update | Flag::Delete => {
+                q = q.filter(
+                    api_key::dsl::is_admin.eq(true).or(dsl::exists(
+                        fuota_campaign::dsl::fuota_campaign
+                            .inner_join(application::table)
+                            .filter(fuota_campaign::dsl::id.eq(&self.fuota_campaign_id).and(
+                                api_key::dsl::tenant_id.eq(application::dsl::tenant_id.nullable()),
+                            )),
+                    )),
+                );
+            }
+            _ => {
+                return Ok(0);
+            }
+        }
+
+        Ok(q.first(&mut get_async_db_conn().await?).await?)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage::{
+        api_key, application, codec, device, device_profile, fuota, gateway, multicast, tenant,
+        user,
+    };
+    use crate::test;
+    use std::str::FromStr;
+
+    struct ValidatorTest<V>
+    where
+        V: Validator + Sync,
+    {
+        validators: Vec<V>,
+        id: AuthID,
+        ok: bool,
+    }
+
+    async fn run_tests<V>(tests: Vec<ValidatorTest<V>>)
+    where
+        V: Validator + Sync,
+    {
+        println!("Running tests");
+        for (i, tst) in tests.iter().enumerate() {
+            for (j, v) in tst.validators.iter().enumerate() {
+                assert_eq!(
+                    tst.ok,
+                    v.validate(&tst.id).await.is_ok(),
+                    "Test {}, assertion {}",
+                    i,
+                    j
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn validate_is_admin() {
+        let _guard = test::prepare().await;
+        let users = vec![
+            user::User {
+                email: "admin@user".into(),
+                is_active: true,
+                is_admin: true,
+                ..Default::default()
+            },
+            user::User {
+                email: "inactive@user".into(),
+                is_active: false,
+                is_admin: false,
+                ..Default::default()
+            },
+            user::User {
+                email: "normal@user".into(),
+                is_active: true,
+                is_admin: false,
+                ..Default::default()
+            },
+        ];
+        for u in &users {
+            user::create(u.clone()).await.unwrap();
         }
 
         let tests = vec![
@@ -2442,27 +3951,75 @@ pub mod test {
             },
         ];
         run_tests(tests).await;
-    }
-
-    #[tokio::test]
-    async fn tenant_user() {
-        let _guard = test::prepare().await;
 
-        let user = user::User {
-            email: "user@user".into(),
-            is_active: true,
-            ..Default::default()
-        };
-        let user_admin = user::User {
-            email: "admin@user".into(),
-            is_active: true,
-            is_admin: true,
-            ..Default::default()
-        };
-        let tenant_admin = user::User {
-            email: "tenant-admin@user".into(),
-            is_active: true,
-            ..Default::default()
+        // tenant audit log
+        let tests = vec![
+            // global admin and tenant admin can list
+            ValidatorTest {
+                validators: vec![ValidateTenantAuditLogAccess::new(Flag::List, tenant_a.id)],
+                id: AuthID::User(user_admin.id),
+                ok: true,
+            },
+            ValidatorTest {
+                validators: vec![ValidateTenantAuditLogAccess::new(Flag::List, tenant_a.id)],
+                id: AuthID::User(tenant_admin.id),
+                ok: true,
+            },
+            // tenant user and normal user can not list
+            ValidatorTest {
+                validators: vec![ValidateTenantAuditLogAccess::new(Flag::List, tenant_a.id)],
+                id: AuthID::User(tenant_user.id),
+                ok: false,
+            },
+            ValidatorTest {
+                validators: vec![ValidateTenantAuditLogAccess::new(Flag::List, tenant_a.id)],
+                id: AuthID::User(user.id),
+                ok: false,
+            },
+            // admin api key can list
+            ValidatorTest {
+                validators: vec![ValidateTenantAuditLogAccess::new(Flag::List, tenant_a.id)],
+                id: AuthID::Key(api_key_admin.id),
+                ok: true,
+            },
+            // tenant api key can list
+            ValidatorTest {
+                validators: vec![ValidateTenantAuditLogAccess::new(
+                    Flag::List,
+                    api_key_tenant.tenant_id.unwrap(),
+                )],
+                id: AuthID::Key(api_key_tenant.id),
+                ok: true,
+            },
+            // tenant api key can not list other tenants
+            ValidatorTest {
+                validators: vec![ValidateTenantAuditLogAccess::new(Flag::List, tenant_a.id)],
+                id: AuthID::Key(api_key_tenant.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+    }
+
+    #[tokio::test]
+    async fn tenant_user() {
+        let _guard = test::prepare().await;
+
+        let user = user::User {
+            email: "user@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+        let user_admin = user::User {
+            email: "admin@user".into(),
+            is_active: true,
+            is_admin: true,
+            ..Default::default()
+        };
+        let tenant_admin = user::User {
+            email: "tenant-admin@user".into(),
+            is_active: true,
+            ..Default::default()
         };
         let tenant_user = user::User {
             email: "tenant-user@user".into(),
@@ -3436,7 +4993,7 @@ pub mod test {
     }
 
     #[tokio::test]
-    async fn device() {
+    async fn codec() {
         let _guard = test::prepare().await;
 
         let user_active = user::User {
@@ -3482,15 +5039,40 @@ pub mod test {
             user::create(u.clone()).await.unwrap();
         }
 
+        let tenant_a = tenant::test::create_tenant().await;
+
         let api_key_admin = api_key::test::create_api_key(true, false).await;
         let api_key_tenant = api_key::test::create_api_key(false, true).await;
-        let api_key_other_tenant = api_key::test::create_api_key(false, true).await;
 
-        let app =
-            application::test::create_application(Some(api_key_tenant.tenant_id.unwrap())).await;
+        let c = codec::create(codec::Codec {
+            name: "test-codec".into(),
+            tenant_id: tenant_a.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let c_api_key_tenant = codec::create(codec::Codec {
+            name: "test-codec-tenant".into(),
+            tenant_id: api_key_tenant.tenant_id.unwrap(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let cv = codec::create_version(codec::CodecVersion {
+            codec_id: c.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let cv_api_key_tenant = codec::create_version(codec::CodecVersion {
+            codec_id: c_api_key_tenant.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
 
         tenant::add_user(tenant::TenantUser {
-            tenant_id: api_key_tenant.tenant_id.unwrap(),
+            tenant_id: tenant_a.id,
             user_id: tenant_admin.id,
             is_admin: true,
             ..Default::default()
@@ -3498,7 +5080,7 @@ pub mod test {
         .await
         .unwrap();
         tenant::add_user(tenant::TenantUser {
-            tenant_id: api_key_tenant.tenant_id.unwrap(),
+            tenant_id: tenant_a.id,
             user_id: tenant_device_admin.id,
             is_device_admin: true,
             ..Default::default()
@@ -3506,7 +5088,7 @@ pub mod test {
         .await
         .unwrap();
         tenant::add_user(tenant::TenantUser {
-            tenant_id: api_key_tenant.tenant_id.unwrap(),
+            tenant_id: tenant_a.id,
             user_id: tenant_gateway_admin.id,
             is_gateway_admin: true,
             ..Default::default()
@@ -3514,19 +5096,20 @@ pub mod test {
         .await
         .unwrap();
         tenant::add_user(tenant::TenantUser {
-            tenant_id: api_key_tenant.tenant_id.unwrap(),
+            tenant_id: tenant_a.id,
             user_id: tenant_user.id,
             ..Default::default()
         })
         .await
         .unwrap();
 
+        // codecs with user
         let tests = vec![
             // admin user can create and list
             ValidatorTest {
                 validators: vec![
-                    ValidateDevicesAccess::new(Flag::Create, app.id),
-                    ValidateDevicesAccess::new(Flag::List, app.id),
+                    ValidateCodecsAccess::new(Flag::Create, tenant_a.id),
+                    ValidateCodecsAccess::new(Flag::List, tenant_a.id),
                 ],
                 id: AuthID::User(user_admin.id),
                 ok: true,
@@ -3534,8 +5117,8 @@ pub mod test {
             // tenant admin user can create and list
             ValidatorTest {
                 validators: vec![
-                    ValidateDevicesAccess::new(Flag::Create, app.id),
-                    ValidateDevicesAccess::new(Flag::List, app.id),
+                    ValidateCodecsAccess::new(Flag::Create, tenant_a.id),
+                    ValidateCodecsAccess::new(Flag::List, tenant_a.id),
                 ],
                 id: AuthID::User(tenant_admin.id),
                 ok: true,
@@ -3543,29 +5126,41 @@ pub mod test {
             // tenant device admin can create and list
             ValidatorTest {
                 validators: vec![
-                    ValidateDevicesAccess::new(Flag::Create, app.id),
-                    ValidateDevicesAccess::new(Flag::List, app.id),
+                    ValidateCodecsAccess::new(Flag::Create, tenant_a.id),
+                    ValidateCodecsAccess::new(Flag::List, tenant_a.id),
                 ],
                 id: AuthID::User(tenant_device_admin.id),
                 ok: true,
             },
-            // tenant user can list
+            // tenant gateway admin can list
             ValidatorTest {
-                validators: vec![ValidateDevicesAccess::new(Flag::List, app.id)],
+                validators: vec![ValidateCodecsAccess::new(Flag::List, tenant_a.id)],
+                id: AuthID::User(tenant_gateway_admin.id),
+                ok: true,
+            },
+            // tenant users can list
+            ValidatorTest {
+                validators: vec![ValidateCodecsAccess::new(Flag::List, tenant_a.id)],
                 id: AuthID::User(tenant_user.id),
                 ok: true,
             },
-            // tenant user can not create
+            // tenant users can not create
             ValidatorTest {
-                validators: vec![ValidateDevicesAccess::new(Flag::Create, app.id)],
+                validators: vec![ValidateCodecsAccess::new(Flag::Create, tenant_a.id)],
                 id: AuthID::User(tenant_user.id),
                 ok: false,
             },
-            // other users can not create or list
+            // tenant gateway admin can not create
+            ValidatorTest {
+                validators: vec![ValidateCodecsAccess::new(Flag::Create, tenant_a.id)],
+                id: AuthID::User(tenant_gateway_admin.id),
+                ok: false,
+            },
+            // non-tenant users can not list or create
             ValidatorTest {
                 validators: vec![
-                    ValidateDevicesAccess::new(Flag::Create, app.id),
-                    ValidateDevicesAccess::new(Flag::List, app.id),
+                    ValidateCodecsAccess::new(Flag::Create, tenant_a.id),
+                    ValidateCodecsAccess::new(Flag::List, tenant_a.id),
                 ],
                 id: AuthID::User(user_active.id),
                 ok: false,
@@ -3573,12 +5168,13 @@ pub mod test {
         ];
         run_tests(tests).await;
 
+        // codecs with api key
         let tests = vec![
             // admin api key can create and list
             ValidatorTest {
                 validators: vec![
-                    ValidateDevicesAccess::new(Flag::Create, app.id),
-                    ValidateDevicesAccess::new(Flag::List, app.id),
+                    ValidateCodecsAccess::new(Flag::Create, tenant_a.id),
+                    ValidateCodecsAccess::new(Flag::List, tenant_a.id),
                 ],
                 id: AuthID::Key(api_key_admin.id),
                 ok: true,
@@ -3586,8 +5182,8 @@ pub mod test {
             // tenant api key can create and list
             ValidatorTest {
                 validators: vec![
-                    ValidateDevicesAccess::new(Flag::Create, app.id),
-                    ValidateDevicesAccess::new(Flag::List, app.id),
+                    ValidateCodecsAccess::new(Flag::Create, api_key_tenant.tenant_id.unwrap()),
+                    ValidateCodecsAccess::new(Flag::List, api_key_tenant.tenant_id.unwrap()),
                 ],
                 id: AuthID::Key(api_key_tenant.id),
                 ok: true,
@@ -3595,32 +5191,23 @@ pub mod test {
             // tenant api key can not create or list for other tenant
             ValidatorTest {
                 validators: vec![
-                    ValidateDevicesAccess::new(Flag::Create, app.id),
-                    ValidateDevicesAccess::new(Flag::List, app.id),
+                    ValidateCodecsAccess::new(Flag::Create, tenant_a.id),
+                    ValidateCodecsAccess::new(Flag::List, tenant_a.id),
                 ],
-                id: AuthID::Key(api_key_other_tenant.id),
+                id: AuthID::Key(api_key_tenant.id),
                 ok: false,
             },
         ];
         run_tests(tests).await;
 
-        let dp =
-            device_profile::test::create_device_profile(Some(api_key_tenant.tenant_id.unwrap()))
-                .await;
-        let dev = device::test::create_device(
-            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
-            dp.id,
-            Some(app.id),
-        )
-        .await;
-
+        // codec with user
         let tests = vec![
             // admin user can read, update and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateDeviceAccess::new(Flag::Read, dev.dev_eui),
-                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
-                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
+                    ValidateCodecAccess::new(Flag::Read, c.id),
+                    ValidateCodecAccess::new(Flag::Update, c.id),
+                    ValidateCodecAccess::new(Flag::Delete, c.id),
                 ],
                 id: AuthID::User(user_admin.id),
                 ok: true,
@@ -3628,9 +5215,9 @@ pub mod test {
             // tenant admin can read, update and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateDeviceAccess::new(Flag::Read, dev.dev_eui),
-                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
-                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
+                    ValidateCodecAccess::new(Flag::Read, c.id),
+                    ValidateCodecAccess::new(Flag::Update, c.id),
+                    ValidateCodecAccess::new(Flag::Delete, c.id),
                 ],
                 id: AuthID::User(tenant_admin.id),
                 ok: true,
@@ -3638,70 +5225,120 @@ pub mod test {
             // tenant device admin can read, update and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateDeviceAccess::new(Flag::Read, dev.dev_eui),
-                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
-                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
+                    ValidateCodecAccess::new(Flag::Read, c.id),
+                    ValidateCodecAccess::new(Flag::Update, c.id),
+                    ValidateCodecAccess::new(Flag::Delete, c.id),
                 ],
                 id: AuthID::User(tenant_device_admin.id),
                 ok: true,
             },
+            // tenant gateway admin can read
+            ValidatorTest {
+                validators: vec![ValidateCodecAccess::new(Flag::Read, c.id)],
+                id: AuthID::User(tenant_gateway_admin.id),
+                ok: true,
+            },
             // tenant user can read
             ValidatorTest {
-                validators: vec![ValidateDeviceAccess::new(Flag::Read, dev.dev_eui)],
+                validators: vec![ValidateCodecAccess::new(Flag::Read, c.id)],
                 id: AuthID::User(tenant_user.id),
                 ok: true,
             },
-            // tenant user can not update or delete
+            // tenant gateway admin can not update or delete
             ValidatorTest {
                 validators: vec![
-                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
-                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
+                    ValidateCodecAccess::new(Flag::Update, c.id),
+                    ValidateCodecAccess::new(Flag::Delete, c.id),
                 ],
-                id: AuthID::User(tenant_user.id),
+                id: AuthID::User(tenant_gateway_admin.id),
                 ok: false,
             },
-            // other user can not read, update and delete
+            // tenant user can not update or delete
             ValidatorTest {
                 validators: vec![
-                    ValidateDeviceAccess::new(Flag::Read, dev.dev_eui),
-                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
-                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
+                    ValidateCodecAccess::new(Flag::Update, c.id),
+                    ValidateCodecAccess::new(Flag::Delete, c.id),
                 ],
-                id: AuthID::User(user_active.id),
+                id: AuthID::User(tenant_user.id),
                 ok: false,
             },
         ];
         run_tests(tests).await;
 
+        // codec with api key
         let tests = vec![
             // admin api key can read, update and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateDeviceAccess::new(Flag::Read, dev.dev_eui),
-                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
-                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
+                    ValidateCodecAccess::new(Flag::Read, c.id),
+                    ValidateCodecAccess::new(Flag::Update, c.id),
+                    ValidateCodecAccess::new(Flag::Delete, c.id),
                 ],
                 id: AuthID::Key(api_key_admin.id),
                 ok: true,
             },
-            // tenant api key can read, update and delete
+            // tenant api key can read update and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateDeviceAccess::new(Flag::Read, dev.dev_eui),
-                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
-                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
+                    ValidateCodecAccess::new(Flag::Read, c_api_key_tenant.id),
+                    ValidateCodecAccess::new(Flag::Update, c_api_key_tenant.id),
+                    ValidateCodecAccess::new(Flag::Delete, c_api_key_tenant.id),
                 ],
                 id: AuthID::Key(api_key_tenant.id),
                 ok: true,
             },
-            // other api key can not read, update or delete
+            // tenant api key can not read, update or delete for other tenant
             ValidatorTest {
                 validators: vec![
-                    ValidateDeviceAccess::new(Flag::Read, dev.dev_eui),
-                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
-                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
+                    ValidateCodecAccess::new(Flag::Read, c.id),
+                    ValidateCodecAccess::new(Flag::Update, c.id),
+                    ValidateCodecAccess::new(Flag::Delete, c.id),
                 ],
-                id: AuthID::Key(api_key_other_tenant.id),
+                id: AuthID::Key(api_key_tenant.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // codec version
+        let tests = vec![
+            // admin user can read
+            ValidatorTest {
+                validators: vec![ValidateCodecVersionAccess::new(Flag::Read, cv.id)],
+                id: AuthID::User(user_admin.id),
+                ok: true,
+            },
+            // tenant user can read
+            ValidatorTest {
+                validators: vec![ValidateCodecVersionAccess::new(Flag::Read, cv.id)],
+                id: AuthID::User(tenant_user.id),
+                ok: true,
+            },
+            // active user can not read
+            ValidatorTest {
+                validators: vec![ValidateCodecVersionAccess::new(Flag::Read, cv.id)],
+                id: AuthID::User(user_active.id),
+                ok: false,
+            },
+            // admin api key can read
+            ValidatorTest {
+                validators: vec![ValidateCodecVersionAccess::new(Flag::Read, cv.id)],
+                id: AuthID::Key(api_key_admin.id),
+                ok: true,
+            },
+            // tenant api key can read
+            ValidatorTest {
+                validators: vec![ValidateCodecVersionAccess::new(
+                    Flag::Read,
+                    cv_api_key_tenant.id,
+                )],
+                id: AuthID::Key(api_key_tenant.id),
+                ok: true,
+            },
+            // tenant api key can not read for other tenant
+            ValidatorTest {
+                validators: vec![ValidateCodecVersionAccess::new(Flag::Read, cv.id)],
+                id: AuthID::Key(api_key_tenant.id),
                 ok: false,
             },
         ];
@@ -3709,7 +5346,7 @@ pub mod test {
     }
 
     #[tokio::test]
-    async fn device_queue() {
+    async fn device() {
         let _guard = test::prepare().await;
 
         let user_active = user::User {
@@ -3723,13 +5360,35 @@ pub mod test {
             is_admin: true,
             ..Default::default()
         };
+        let tenant_admin = user::User {
+            email: "tenant-admin@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+        let tenant_device_admin = user::User {
+            email: "tenant-device-admin@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+        let tenant_gateway_admin = user::User {
+            email: "tenant-gateway-admin@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
         let tenant_user = user::User {
             email: "tenant-user@user".into(),
             is_active: true,
             ..Default::default()
         };
 
-        for u in [&user_active, &user_admin, &tenant_user] {
+        for u in [
+            &user_active,
+            &user_admin,
+            &tenant_admin,
+            &tenant_gateway_admin,
+            &tenant_device_admin,
+            &tenant_user,
+        ] {
             user::create(u.clone()).await.unwrap();
         }
 
@@ -3742,49 +5401,81 @@ pub mod test {
 
         tenant::add_user(tenant::TenantUser {
             tenant_id: api_key_tenant.tenant_id.unwrap(),
-            user_id: tenant_user.id,
+            user_id: tenant_admin.id,
+            is_admin: true,
             ..Default::default()
         })
         .await
         .unwrap();
-
-        let dp =
-            device_profile::test::create_device_profile(Some(api_key_tenant.tenant_id.unwrap()))
-                .await;
-        let dev = device::test::create_device(
-            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
-            dp.id,
-            Some(app.id),
-        )
-        .await;
-
-        let tests = vec![
-            // admin user can create list and delete
-            ValidatorTest {
-                validators: vec![
-                    ValidateDeviceQueueAccess::new(Flag::Create, dev.dev_eui),
-                    ValidateDeviceQueueAccess::new(Flag::List, dev.dev_eui),
-                    ValidateDeviceQueueAccess::new(Flag::Delete, dev.dev_eui),
-                ],
-                id: AuthID::User(user_admin.id),
+        tenant::add_user(tenant::TenantUser {
+            tenant_id: api_key_tenant.tenant_id.unwrap(),
+            user_id: tenant_device_admin.id,
+            is_device_admin: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        tenant::add_user(tenant::TenantUser {
+            tenant_id: api_key_tenant.tenant_id.unwrap(),
+            user_id: tenant_gateway_admin.id,
+            is_gateway_admin: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        tenant::add_user(tenant::TenantUser {
+            tenant_id: api_key_tenant.tenant_id.unwrap(),
+            user_id: tenant_user.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let tests = vec![
+            // admin user can create and list
+            ValidatorTest {
+                validators: vec![
+                    ValidateDevicesAccess::new(Flag::Create, app.id),
+                    ValidateDevicesAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::User(user_admin.id),
                 ok: true,
             },
-            // tenant user can create list and delete
+            // tenant admin user can create and list
             ValidatorTest {
                 validators: vec![
-                    ValidateDeviceQueueAccess::new(Flag::Create, dev.dev_eui),
-                    ValidateDeviceQueueAccess::new(Flag::List, dev.dev_eui),
-                    ValidateDeviceQueueAccess::new(Flag::Delete, dev.dev_eui),
+                    ValidateDevicesAccess::new(Flag::Create, app.id),
+                    ValidateDevicesAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::User(tenant_admin.id),
+                ok: true,
+            },
+            // tenant device admin can create and list
+            ValidatorTest {
+                validators: vec![
+                    ValidateDevicesAccess::new(Flag::Create, app.id),
+                    ValidateDevicesAccess::new(Flag::List, app.id),
                 ],
+                id: AuthID::User(tenant_device_admin.id),
+                ok: true,
+            },
+            // tenant user can list
+            ValidatorTest {
+                validators: vec![ValidateDevicesAccess::new(Flag::List, app.id)],
                 id: AuthID::User(tenant_user.id),
                 ok: true,
             },
-            // other user can not create, list or delete
+            // tenant user can not create
+            ValidatorTest {
+                validators: vec![ValidateDevicesAccess::new(Flag::Create, app.id)],
+                id: AuthID::User(tenant_user.id),
+                ok: false,
+            },
+            // other users can not create or list
             ValidatorTest {
                 validators: vec![
-                    ValidateDeviceQueueAccess::new(Flag::Create, dev.dev_eui),
-                    ValidateDeviceQueueAccess::new(Flag::List, dev.dev_eui),
-                    ValidateDeviceQueueAccess::new(Flag::Delete, dev.dev_eui),
+                    ValidateDevicesAccess::new(Flag::Create, app.id),
+                    ValidateDevicesAccess::new(Flag::List, app.id),
                 ],
                 id: AuthID::User(user_active.id),
                 ok: false,
@@ -3793,59 +5484,35 @@ pub mod test {
         run_tests(tests).await;
 
         let tests = vec![
-            // admin api key can create, list and delete
+            // admin api key can create and list
             ValidatorTest {
                 validators: vec![
-                    ValidateDeviceQueueAccess::new(Flag::Create, dev.dev_eui),
-                    ValidateDeviceQueueAccess::new(Flag::List, dev.dev_eui),
-                    ValidateDeviceQueueAccess::new(Flag::Delete, dev.dev_eui),
+                    ValidateDevicesAccess::new(Flag::Create, app.id),
+                    ValidateDevicesAccess::new(Flag::List, app.id),
                 ],
                 id: AuthID::Key(api_key_admin.id),
                 ok: true,
             },
-            // tenant api key can create, list and delete
+            // tenant api key can create and list
             ValidatorTest {
                 validators: vec![
-                    ValidateDeviceQueueAccess::new(Flag::Create, dev.dev_eui),
-                    ValidateDeviceQueueAccess::new(Flag::List, dev.dev_eui),
-                    ValidateDeviceQueueAccess::new(Flag::Delete, dev.dev_eui),
+                    ValidateDevicesAccess::new(Flag::Create, app.id),
+                    ValidateDevicesAccess::new(Flag::List, app.id),
                 ],
                 id: AuthID::Key(api_key_tenant.id),
                 ok: true,
             },
-            // api key for other tenant cna not create, list or delete
+            // tenant api key can not create or list for other tenant
             ValidatorTest {
                 validators: vec![
-                    ValidateDeviceQueueAccess::new(Flag::Create, dev.dev_eui),
-                    ValidateDeviceQueueAccess::new(Flag::List, dev.dev_eui),
-                    ValidateDeviceQueueAccess::new(Flag::Delete, dev.dev_eui),
+                    ValidateDevicesAccess::new(Flag::Create, app.id),
+                    ValidateDevicesAccess::new(Flag::List, app.id),
                 ],
                 id: AuthID::Key(api_key_other_tenant.id),
                 ok: false,
             },
         ];
         run_tests(tests).await;
-    }
-
-    #[tokio::test]
-    async fn device_config_store() {
-        let _guard = test::prepare().await;
-
-        let user_admin = user::User {
-            email: "admin@user".into(),
-            is_active: true,
-            is_admin: true,
-            ..Default::default()
-        };
-
-        user::create(user_admin.clone()).await.unwrap();
-
-        let api_key_admin = api_key::test::create_api_key(true, false).await;
-        let api_key_tenant = api_key::test::create_api_key(false, true).await;
-        let api_key_other_tenant = api_key::test::create_api_key(false, true).await;
-
-        let app =
-            application::test::create_application(Some(api_key_tenant.tenant_id.unwrap())).await;
 
         let dp =
             device_profile::test::create_device_profile(Some(api_key_tenant.tenant_id.unwrap()))
@@ -3858,82 +5525,91 @@ pub mod test {
         .await;
 
         let tests = vec![
-            // users are never validated, this is API key only
+            // admin user can read, update and delete
             ValidatorTest {
-                validators: vec![ValidateDeviceConfigStoresAccess::new(Flag::List, app.id)],
+                validators: vec![
+                    ValidateDeviceAccess::new(Flag::Read, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
+                ],
                 id: AuthID::User(user_admin.id),
-                ok: false,
+                ok: true,
             },
-        ];
-        run_tests(tests).await;
-
-        let tests = vec![
-            // admin api key can list
+            // tenant admin can read, update and delete
             ValidatorTest {
-                validators: vec![ValidateDeviceConfigStoresAccess::new(Flag::List, app.id)],
-                id: AuthID::Key(api_key_admin.id),
+                validators: vec![
+                    ValidateDeviceAccess::new(Flag::Read, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
+                ],
+                id: AuthID::User(tenant_admin.id),
                 ok: true,
             },
-            // tenant api key can list
+            // tenant device admin can read, update and delete
             ValidatorTest {
-                validators: vec![ValidateDeviceConfigStoresAccess::new(Flag::List, app.id)],
-                id: AuthID::Key(api_key_tenant.id),
+                validators: vec![
+                    ValidateDeviceAccess::new(Flag::Read, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
+                ],
+                id: AuthID::User(tenant_device_admin.id),
                 ok: true,
             },
-            // tenant api key cannot list for other tenant
+            // tenant user can read
             ValidatorTest {
-                validators: vec![ValidateDeviceConfigStoresAccess::new(Flag::List, app.id)],
-                id: AuthID::Key(api_key_other_tenant.id),
+                validators: vec![ValidateDeviceAccess::new(Flag::Read, dev.dev_eui)],
+                id: AuthID::User(tenant_user.id),
+                ok: true,
+            },
+            // tenant user can not update or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
+                ],
+                id: AuthID::User(tenant_user.id),
                 ok: false,
             },
-        ];
-        run_tests(tests).await;
-
-        let tests = vec![
-            // users are never validated, this is API key only
+            // other user can not read, update and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateDeviceConfigStoreAccess::new(Flag::Create, dev.dev_eui),
-                    ValidateDeviceConfigStoreAccess::new(Flag::Read, dev.dev_eui),
-                    ValidateDeviceConfigStoreAccess::new(Flag::Update, dev.dev_eui),
-                    ValidateDeviceConfigStoreAccess::new(Flag::Delete, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Read, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
                 ],
-                id: AuthID::User(user_admin.id),
+                id: AuthID::User(user_active.id),
                 ok: false,
             },
         ];
         run_tests(tests).await;
 
         let tests = vec![
-            // admin api key can create, read, update, and delete
+            // admin api key can read, update and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateDeviceConfigStoreAccess::new(Flag::Create, dev.dev_eui),
-                    ValidateDeviceConfigStoreAccess::new(Flag::Read, dev.dev_eui),
-                    ValidateDeviceConfigStoreAccess::new(Flag::Update, dev.dev_eui),
-                    ValidateDeviceConfigStoreAccess::new(Flag::Delete, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Read, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
                 ],
                 id: AuthID::Key(api_key_admin.id),
                 ok: true,
             },
-            // tenant api key can create, read, update, and delete
+            // tenant api key can read, update and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateDeviceConfigStoreAccess::new(Flag::Create, dev.dev_eui),
-                    ValidateDeviceConfigStoreAccess::new(Flag::Read, dev.dev_eui),
-                    ValidateDeviceConfigStoreAccess::new(Flag::Update, dev.dev_eui),
-                    ValidateDeviceConfigStoreAccess::new(Flag::Delete, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Read, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
                 ],
                 id: AuthID::Key(api_key_tenant.id),
                 ok: true,
             },
-            // api key for other tenant cannot create, read, update, and delete
+            // other api key can not read, update or delete
             ValidatorTest {
                 validators: vec![
-                    ValidateDeviceConfigStoreAccess::new(Flag::Create, dev.dev_eui),
-                    ValidateDeviceConfigStoreAccess::new(Flag::Read, dev.dev_eui),
-                    ValidateDeviceConfigStoreAccess::new(Flag::Update, dev.dev_eui),
-                    ValidateDeviceConfigStoreAccess::new(Flag::Delete, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Read, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
                 ],
                 id: AuthID::Key(api_key_other_tenant.id),
                 ok: false,
@@ -3943,7 +5619,7 @@ pub mod test {
     }
 
     #[tokio::test]
-    async fn gateway() {
+    async fn device_queue() {
         let _guard = test::prepare().await;
 
         let user_active = user::User {
@@ -3957,124 +5633,68 @@ pub mod test {
             is_admin: true,
             ..Default::default()
         };
-        let tenant_admin = user::User {
-            email: "tenant-admin@user".into(),
-            is_active: true,
-            ..Default::default()
-        };
-        let tenant_gateway_admin = user::User {
-            email: "tenant-gateway-admin@user".into(),
-            is_active: true,
-            ..Default::default()
-        };
         let tenant_user = user::User {
             email: "tenant-user@user".into(),
             is_active: true,
             ..Default::default()
         };
 
-        for u in [
-            &user_active,
-            &user_admin,
-            &tenant_admin,
-            &tenant_gateway_admin,
-            &tenant_user,
-        ] {
+        for u in [&user_active, &user_admin, &tenant_user] {
             user::create(u.clone()).await.unwrap();
         }
 
-        let tenant_a = tenant::test::create_tenant().await;
-
         let api_key_admin = api_key::test::create_api_key(true, false).await;
         let api_key_tenant = api_key::test::create_api_key(false, true).await;
+        let api_key_other_tenant = api_key::test::create_api_key(false, true).await;
 
-        let gw = gateway::create(gateway::Gateway {
-            name: "test-gw".into(),
-            gateway_id: EUI64::from_str("0102030405060708").unwrap(),
-            tenant_id: tenant_a.id,
-            ..Default::default()
-        })
-        .await
-        .unwrap();
-        let gw_api_key_tenant = gateway::create(gateway::Gateway {
-            name: "test-gw-tenant".into(),
-            gateway_id: EUI64::from_str("0202030405060708").unwrap(),
-            tenant_id: api_key_tenant.tenant_id.unwrap(),
-            ..Default::default()
-        })
-        .await
-        .unwrap();
+        let app =
+            application::test::create_application(Some(api_key_tenant.tenant_id.unwrap())).await;
 
         tenant::add_user(tenant::TenantUser {
-            tenant_id: tenant_a.id,
-            user_id: tenant_admin.id,
-            is_admin: true,
-            ..Default::default()
-        })
-        .await
-        .unwrap();
-        tenant::add_user(tenant::TenantUser {
-            tenant_id: tenant_a.id,
-            user_id: tenant_gateway_admin.id,
-            is_gateway_admin: true,
-            ..Default::default()
-        })
-        .await
-        .unwrap();
-        tenant::add_user(tenant::TenantUser {
-            tenant_id: tenant_a.id,
-            user_id: tenant_user.id,
+            tenant_id: api_key_tenant.tenant_id.unwrap(),
+            user_id: tenant_user.id,
             ..Default::default()
         })
         .await
         .unwrap();
 
-        // gateways with user
+        let dp =
+            device_profile::test::create_device_profile(Some(api_key_tenant.tenant_id.unwrap()))
+                .await;
+        let dev = device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            Some(app.id),
+        )
+        .await;
+
         let tests = vec![
-            // admin user can create and list
+            // admin user can create list and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateGatewaysAccess::new(Flag::Create, tenant_a.id),
-                    ValidateGatewaysAccess::new(Flag::List, tenant_a.id),
+                    ValidateDeviceQueueAccess::new(Flag::Create, dev.dev_eui),
+                    ValidateDeviceQueueAccess::new(Flag::List, dev.dev_eui),
+                    ValidateDeviceQueueAccess::new(Flag::Delete, dev.dev_eui),
                 ],
                 id: AuthID::User(user_admin.id),
                 ok: true,
             },
-            // tenant admin can create and list
-            ValidatorTest {
-                validators: vec![
-                    ValidateGatewaysAccess::new(Flag::Create, tenant_a.id),
-                    ValidateGatewaysAccess::new(Flag::List, tenant_a.id),
-                ],
-                id: AuthID::User(tenant_admin.id),
-                ok: true,
-            },
-            // tenant gateway admin can create and list
+            // tenant user can create list and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateGatewaysAccess::new(Flag::Create, tenant_a.id),
-                    ValidateGatewaysAccess::new(Flag::List, tenant_a.id),
+                    ValidateDeviceQueueAccess::new(Flag::Create, dev.dev_eui),
+                    ValidateDeviceQueueAccess::new(Flag::List, dev.dev_eui),
+                    ValidateDeviceQueueAccess::new(Flag::Delete, dev.dev_eui),
                 ],
-                id: AuthID::User(tenant_gateway_admin.id),
-                ok: true,
-            },
-            // tenant user can list
-            ValidatorTest {
-                validators: vec![ValidateGatewaysAccess::new(Flag::List, tenant_a.id)],
                 id: AuthID::User(tenant_user.id),
                 ok: true,
             },
-            // tenant user can not create
-            ValidatorTest {
-                validators: vec![ValidateGatewaysAccess::new(Flag::Create, tenant_a.id)],
-                id: AuthID::User(tenant_user.id),
-                ok: false,
-            },
-            // other users can not create or list
+            // other user can not create, list or delete
             ValidatorTest {
                 validators: vec![
-                    ValidateGatewaysAccess::new(Flag::Create, tenant_a.id),
-                    ValidateGatewaysAccess::new(Flag::List, tenant_a.id),
+                    ValidateDeviceQueueAccess::new(Flag::Create, dev.dev_eui),
+                    ValidateDeviceQueueAccess::new(Flag::List, dev.dev_eui),
+                    ValidateDeviceQueueAccess::new(Flag::Delete, dev.dev_eui),
                 ],
                 id: AuthID::User(user_active.id),
                 ok: false,
@@ -4082,128 +5702,150 @@ pub mod test {
         ];
         run_tests(tests).await;
 
-        // gateways with api key
         let tests = vec![
-            // admin api key can create and list
+            // admin api key can create, list and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateGatewaysAccess::new(Flag::Create, tenant_a.id),
-                    ValidateGatewaysAccess::new(Flag::List, tenant_a.id),
+                    ValidateDeviceQueueAccess::new(Flag::Create, dev.dev_eui),
+                    ValidateDeviceQueueAccess::new(Flag::List, dev.dev_eui),
+                    ValidateDeviceQueueAccess::new(Flag::Delete, dev.dev_eui),
                 ],
                 id: AuthID::Key(api_key_admin.id),
                 ok: true,
             },
-            // tenant api key can create and list
+            // tenant api key can create, list and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateGatewaysAccess::new(Flag::Create, api_key_tenant.tenant_id.unwrap()),
-                    ValidateGatewaysAccess::new(Flag::List, api_key_tenant.tenant_id.unwrap()),
+                    ValidateDeviceQueueAccess::new(Flag::Create, dev.dev_eui),
+                    ValidateDeviceQueueAccess::new(Flag::List, dev.dev_eui),
+                    ValidateDeviceQueueAccess::new(Flag::Delete, dev.dev_eui),
                 ],
                 id: AuthID::Key(api_key_tenant.id),
                 ok: true,
             },
-            // tenant api key can not create or list for other tenant
+            // api key for other tenant cna not create, list or delete
             ValidatorTest {
                 validators: vec![
-                    ValidateGatewaysAccess::new(Flag::Create, tenant_a.id),
-                    ValidateGatewaysAccess::new(Flag::List, tenant_a.id),
+                    ValidateDeviceQueueAccess::new(Flag::Create, dev.dev_eui),
+                    ValidateDeviceQueueAccess::new(Flag::List, dev.dev_eui),
+                    ValidateDeviceQueueAccess::new(Flag::Delete, dev.dev_eui),
                 ],
-                id: AuthID::Key(api_key_tenant.id),
+                id: AuthID::Key(api_key_other_tenant.id),
                 ok: false,
             },
         ];
         run_tests(tests).await;
+    }
+
+    #[tokio::test]
+    async fn device_config_store() {
+        let _guard = test::prepare().await;
+
+        let user_admin = user::User {
+            email: "admin@user".into(),
+            is_active: true,
+            is_admin: true,
+            ..Default::default()
+        };
+
+        user::create(user_admin.clone()).await.unwrap();
+
+        let api_key_admin = api_key::test::create_api_key(true, false).await;
+        let api_key_tenant = api_key::test::create_api_key(false, true).await;
+        let api_key_other_tenant = api_key::test::create_api_key(false, true).await;
+
+        let app =
+            application::test::create_application(Some(api_key_tenant.tenant_id.unwrap())).await;
+
+        let dp =
+            device_profile::test::create_device_profile(Some(api_key_tenant.tenant_id.unwrap()))
+                .await;
+        let dev = device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            Some(app.id),
+        )
+        .await;
 
-        // gateway with user
         let tests = vec![
-            // admin user can read, update and delete
+            // users are never validated, this is API key only
             ValidatorTest {
-                validators: vec![
-                    ValidateGatewayAccess::new(Flag::Read, gw.gateway_id),
-                    ValidateGatewayAccess::new(Flag::Update, gw.gateway_id),
-                    ValidateGatewayAccess::new(Flag::Delete, gw.gateway_id),
-                ],
+                validators: vec![ValidateDeviceConfigStoresAccess::new(Flag::List, app.id)],
                 id: AuthID::User(user_admin.id),
-                ok: true,
-            },
-            // tenant admin can read, update and delete
-            ValidatorTest {
-                validators: vec![
-                    ValidateGatewayAccess::new(Flag::Read, gw.gateway_id),
-                    ValidateGatewayAccess::new(Flag::Update, gw.gateway_id),
-                    ValidateGatewayAccess::new(Flag::Delete, gw.gateway_id),
-                ],
-                id: AuthID::User(tenant_admin.id),
-                ok: true,
+                ok: false,
             },
-            // tenant gateway admin can read, update and delete
+        ];
+        run_tests(tests).await;
+
+        let tests = vec![
+            // admin api key can list
             ValidatorTest {
-                validators: vec![
-                    ValidateGatewayAccess::new(Flag::Read, gw.gateway_id),
-                    ValidateGatewayAccess::new(Flag::Update, gw.gateway_id),
-                    ValidateGatewayAccess::new(Flag::Delete, gw.gateway_id),
-                ],
-                id: AuthID::User(tenant_gateway_admin.id),
+                validators: vec![ValidateDeviceConfigStoresAccess::new(Flag::List, app.id)],
+                id: AuthID::Key(api_key_admin.id),
                 ok: true,
             },
-            // tenant user can read
+            // tenant api key can list
             ValidatorTest {
-                validators: vec![ValidateGatewayAccess::new(Flag::Read, gw.gateway_id)],
-                id: AuthID::User(tenant_user.id),
+                validators: vec![ValidateDeviceConfigStoresAccess::new(Flag::List, app.id)],
+                id: AuthID::Key(api_key_tenant.id),
                 ok: true,
             },
-            // tenant user can not update or delete
+            // tenant api key cannot list for other tenant
             ValidatorTest {
-                validators: vec![
-                    ValidateGatewayAccess::new(Flag::Update, gw.gateway_id),
-                    ValidateGatewayAccess::new(Flag::Delete, gw.gateway_id),
-                ],
-                id: AuthID::User(tenant_user.id),
+                validators: vec![ValidateDeviceConfigStoresAccess::new(Flag::List, app.id)],
+                id: AuthID::Key(api_key_other_tenant.id),
                 ok: false,
             },
-            // other user can not read, update or delete
+        ];
+        run_tests(tests).await;
+
+        let tests = vec![
+            // users are never validated, this is API key only
             ValidatorTest {
                 validators: vec![
-                    ValidateGatewayAccess::new(Flag::Read, gw.gateway_id),
-                    ValidateGatewayAccess::new(Flag::Update, gw.gateway_id),
-                    ValidateGatewayAccess::new(Flag::Delete, gw.gateway_id),
+                    ValidateDeviceConfigStoreAccess::new(Flag::Create, dev.dev_eui),
+                    ValidateDeviceConfigStoreAccess::new(Flag::Read, dev.dev_eui),
+                    ValidateDeviceConfigStoreAccess::new(Flag::Update, dev.dev_eui),
+                    ValidateDeviceConfigStoreAccess::new(Flag::Delete, dev.dev_eui),
                 ],
-                id: AuthID::User(user_active.id),
+                id: AuthID::User(user_admin.id),
                 ok: false,
             },
         ];
         run_tests(tests).await;
 
-        // gateway with api key
         let tests = vec![
-            // admin api key can read, update and delete
+            // admin api key can create, read, update, and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateGatewayAccess::new(Flag::Read, gw.gateway_id),
-                    ValidateGatewayAccess::new(Flag::Update, gw.gateway_id),
-                    ValidateGatewayAccess::new(Flag::Delete, gw.gateway_id),
+                    ValidateDeviceConfigStoreAccess::new(Flag::Create, dev.dev_eui),
+                    ValidateDeviceConfigStoreAccess::new(Flag::Read, dev.dev_eui),
+                    ValidateDeviceConfigStoreAccess::new(Flag::Update, dev.dev_eui),
+                    ValidateDeviceConfigStoreAccess::new(Flag::Delete, dev.dev_eui),
                 ],
                 id: AuthID::Key(api_key_admin.id),
                 ok: true,
             },
-            // tenant api key can read, update and delete
+            // tenant api key can create, read, update, and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateGatewayAccess::new(Flag::Read, gw_api_key_tenant.gateway_id),
-                    ValidateGatewayAccess::new(Flag::Update, gw_api_key_tenant.gateway_id),
-                    ValidateGatewayAccess::new(Flag::Delete, gw_api_key_tenant.gateway_id),
+                    ValidateDeviceConfigStoreAccess::new(Flag::Create, dev.dev_eui),
+                    ValidateDeviceConfigStoreAccess::new(Flag::Read, dev.dev_eui),
+                    ValidateDeviceConfigStoreAccess::new(Flag::Update, dev.dev_eui),
+                    ValidateDeviceConfigStoreAccess::new(Flag::Delete, dev.dev_eui),
                 ],
                 id: AuthID::Key(api_key_tenant.id),
                 ok: true,
             },
-            // tenant api key can not read, update or delete gw from other tenant
+            // api key for other tenant cannot create, read, update, and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateGatewayAccess::new(Flag::Read, gw.gateway_id),
-                    ValidateGatewayAccess::new(Flag::Update, gw.gateway_id),
-                    ValidateGatewayAccess::new(Flag::Delete, gw.gateway_id),
+                    ValidateDeviceConfigStoreAccess::new(Flag::Create, dev.dev_eui),
+                    ValidateDeviceConfigStoreAccess::new(Flag::Read, dev.dev_eui),
+                    ValidateDeviceConfigStoreAccess::new(Flag::Update, dev.dev_eui),
+                    ValidateDeviceConfigStoreAccess::new(Flag::Delete, dev.dev_eui),
                 ],
-                id: AuthID::Key(api_key_tenant.id),
+                id: AuthID::Key(api_key_other_tenant.id),
                 ok: false,
             },
         ];
@@ -4211,7 +5853,7 @@ pub mod test {
     }
 
     #[tokio::test]
-    async fn multicast_group() {
+    async fn gateway() {
         let _guard = test::prepare().await;
 
         let user_active = user::User {
@@ -4230,11 +5872,6 @@ pub mod test {
             is_active: true,
             ..Default::default()
         };
-        let tenant_device_admin = user::User {
-            email: "tenant-device-admin@user".into(),
-            is_active: true,
-            ..Default::default()
-        };
         let tenant_gateway_admin = user::User {
             email: "tenant-gateway-admin@user".into(),
             is_active: true,
@@ -4251,37 +5888,43 @@ pub mod test {
             &user_admin,
             &tenant_admin,
             &tenant_gateway_admin,
-            &tenant_device_admin,
             &tenant_user,
         ] {
             user::create(u.clone()).await.unwrap();
         }
 
+        let tenant_a = tenant::test::create_tenant().await;
+
         let api_key_admin = api_key::test::create_api_key(true, false).await;
         let api_key_tenant = api_key::test::create_api_key(false, true).await;
-        let api_key_other_tenant = api_key::test::create_api_key(false, true).await;
-
-        let app =
-            application::test::create_application(Some(api_key_tenant.tenant_id.unwrap())).await;
 
-        tenant::add_user(tenant::TenantUser {
+        let gw = gateway::create(gateway::Gateway {
+            name: "test-gw".into(),
+            gateway_id: EUI64::from_str("0102030405060708").unwrap(),
+            tenant_id: tenant_a.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let gw_api_key_tenant = gateway::create(gateway::Gateway {
+            name: "test-gw-tenant".into(),
+            gateway_id: EUI64::from_str("0202030405060708").unwrap(),
             tenant_id: api_key_tenant.tenant_id.unwrap(),
-            user_id: tenant_admin.id,
-            is_admin: true,
             ..Default::default()
         })
         .await
         .unwrap();
+
         tenant::add_user(tenant::TenantUser {
-            tenant_id: api_key_tenant.tenant_id.unwrap(),
-            user_id: tenant_device_admin.id,
-            is_device_admin: true,
+            tenant_id: tenant_a.id,
+            user_id: tenant_admin.id,
+            is_admin: true,
             ..Default::default()
         })
         .await
         .unwrap();
         tenant::add_user(tenant::TenantUser {
-            tenant_id: api_key_tenant.tenant_id.unwrap(),
+            tenant_id: tenant_a.id,
             user_id: tenant_gateway_admin.id,
             is_gateway_admin: true,
             ..Default::default()
@@ -4289,20 +5932,20 @@ pub mod test {
         .await
         .unwrap();
         tenant::add_user(tenant::TenantUser {
-            tenant_id: api_key_tenant.tenant_id.unwrap(),
+            tenant_id: tenant_a.id,
             user_id: tenant_user.id,
             ..Default::default()
         })
         .await
         .unwrap();
 
-        // multicast-groups with user
+        // gateways with user
         let tests = vec![
             // admin user can create and list
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupsAccess::new(Flag::Create, app.id),
-                    ValidateMulticastGroupsAccess::new(Flag::List, app.id),
+                    ValidateGatewaysAccess::new(Flag::Create, tenant_a.id),
+                    ValidateGatewaysAccess::new(Flag::List, tenant_a.id),
                 ],
                 id: AuthID::User(user_admin.id),
                 ok: true,
@@ -4310,38 +5953,38 @@ pub mod test {
             // tenant admin can create and list
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupsAccess::new(Flag::Create, app.id),
-                    ValidateMulticastGroupsAccess::new(Flag::List, app.id),
-                ],
+                    ValidateGatewaysAccess::new(Flag::Create, tenant_a.id),
+                    ValidateGatewaysAccess::new(Flag::List, tenant_a.id),
+                ],
                 id: AuthID::User(tenant_admin.id),
                 ok: true,
             },
-            // tenant device admin can create and list
+            // tenant gateway admin can create and list
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupsAccess::new(Flag::Create, app.id),
-                    ValidateMulticastGroupsAccess::new(Flag::List, app.id),
+                    ValidateGatewaysAccess::new(Flag::Create, tenant_a.id),
+                    ValidateGatewaysAccess::new(Flag::List, tenant_a.id),
                 ],
-                id: AuthID::User(tenant_device_admin.id),
+                id: AuthID::User(tenant_gateway_admin.id),
                 ok: true,
             },
             // tenant user can list
             ValidatorTest {
-                validators: vec![ValidateMulticastGroupsAccess::new(Flag::List, app.id)],
+                validators: vec![ValidateGatewaysAccess::new(Flag::List, tenant_a.id)],
                 id: AuthID::User(tenant_user.id),
                 ok: true,
             },
             // tenant user can not create
             ValidatorTest {
-                validators: vec![ValidateMulticastGroupsAccess::new(Flag::Create, app.id)],
+                validators: vec![ValidateGatewaysAccess::new(Flag::Create, tenant_a.id)],
                 id: AuthID::User(tenant_user.id),
                 ok: false,
             },
-            // other user can not create or list
+            // other users can not create or list
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupsAccess::new(Flag::Create, app.id),
-                    ValidateMulticastGroupsAccess::new(Flag::List, app.id),
+                    ValidateGatewaysAccess::new(Flag::Create, tenant_a.id),
+                    ValidateGatewaysAccess::new(Flag::List, tenant_a.id),
                 ],
                 id: AuthID::User(user_active.id),
                 ok: false,
@@ -4349,13 +5992,13 @@ pub mod test {
         ];
         run_tests(tests).await;
 
-        // multicast-groups with api key
+        // gateways with api key
         let tests = vec![
             // admin api key can create and list
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupsAccess::new(Flag::Create, app.id),
-                    ValidateMulticastGroupsAccess::new(Flag::List, app.id),
+                    ValidateGatewaysAccess::new(Flag::Create, tenant_a.id),
+                    ValidateGatewaysAccess::new(Flag::List, tenant_a.id),
                 ],
                 id: AuthID::Key(api_key_admin.id),
                 ok: true,
@@ -4363,8 +6006,8 @@ pub mod test {
             // tenant api key can create and list
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupsAccess::new(Flag::Create, app.id),
-                    ValidateMulticastGroupsAccess::new(Flag::List, app.id),
+                    ValidateGatewaysAccess::new(Flag::Create, api_key_tenant.tenant_id.unwrap()),
+                    ValidateGatewaysAccess::new(Flag::List, api_key_tenant.tenant_id.unwrap()),
                 ],
                 id: AuthID::Key(api_key_tenant.id),
                 ok: true,
@@ -4372,31 +6015,23 @@ pub mod test {
             // tenant api key can not create or list for other tenant
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupsAccess::new(Flag::Create, app.id),
-                    ValidateMulticastGroupsAccess::new(Flag::List, app.id),
+                    ValidateGatewaysAccess::new(Flag::Create, tenant_a.id),
+                    ValidateGatewaysAccess::new(Flag::List, tenant_a.id),
                 ],
-                id: AuthID::Key(api_key_other_tenant.id),
+                id: AuthID::Key(api_key_tenant.id),
                 ok: false,
             },
         ];
         run_tests(tests).await;
 
-        let mg = multicast::create(multicast::MulticastGroup {
-            name: "test-mg".into(),
-            application_id: app.id,
-            ..Default::default()
-        })
-        .await
-        .unwrap();
-
-        // multicast-group with user
+        // gateway with user
         let tests = vec![
             // admin user can read, update and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupAccess::new(Flag::Read, mg.id),
-                    ValidateMulticastGroupAccess::new(Flag::Update, mg.id),
-                    ValidateMulticastGroupAccess::new(Flag::Delete, mg.id),
+                    ValidateGatewayAccess::new(Flag::Read, gw.gateway_id),
+                    ValidateGatewayAccess::new(Flag::Update, gw.gateway_id),
+                    ValidateGatewayAccess::new(Flag::Delete, gw.gateway_id),
                 ],
                 id: AuthID::User(user_admin.id),
                 ok: true,
@@ -4404,34 +6039,34 @@ pub mod test {
             // tenant admin can read, update and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupAccess::new(Flag::Read, mg.id),
-                    ValidateMulticastGroupAccess::new(Flag::Update, mg.id),
-                    ValidateMulticastGroupAccess::new(Flag::Delete, mg.id),
+                    ValidateGatewayAccess::new(Flag::Read, gw.gateway_id),
+                    ValidateGatewayAccess::new(Flag::Update, gw.gateway_id),
+                    ValidateGatewayAccess::new(Flag::Delete, gw.gateway_id),
                 ],
                 id: AuthID::User(tenant_admin.id),
                 ok: true,
             },
-            // tenant device admin can read, update and delete
+            // tenant gateway admin can read, update and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupAccess::new(Flag::Read, mg.id),
-                    ValidateMulticastGroupAccess::new(Flag::Update, mg.id),
-                    ValidateMulticastGroupAccess::new(Flag::Delete, mg.id),
+                    ValidateGatewayAccess::new(Flag::Read, gw.gateway_id),
+                    ValidateGatewayAccess::new(Flag::Update, gw.gateway_id),
+                    ValidateGatewayAccess::new(Flag::Delete, gw.gateway_id),
                 ],
-                id: AuthID::User(tenant_device_admin.id),
+                id: AuthID::User(tenant_gateway_admin.id),
                 ok: true,
             },
             // tenant user can read
             ValidatorTest {
-                validators: vec![ValidateMulticastGroupAccess::new(Flag::Read, mg.id)],
+                validators: vec![ValidateGatewayAccess::new(Flag::Read, gw.gateway_id)],
                 id: AuthID::User(tenant_user.id),
                 ok: true,
             },
             // tenant user can not update or delete
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupAccess::new(Flag::Update, mg.id),
-                    ValidateMulticastGroupAccess::new(Flag::Delete, mg.id),
+                    ValidateGatewayAccess::new(Flag::Update, gw.gateway_id),
+                    ValidateGatewayAccess::new(Flag::Delete, gw.gateway_id),
                 ],
                 id: AuthID::User(tenant_user.id),
                 ok: false,
@@ -4439,9 +6074,9 @@ pub mod test {
             // other user can not read, update or delete
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupAccess::new(Flag::Read, mg.id),
-                    ValidateMulticastGroupAccess::new(Flag::Update, mg.id),
-                    ValidateMulticastGroupAccess::new(Flag::Delete, mg.id),
+                    ValidateGatewayAccess::new(Flag::Read, gw.gateway_id),
+                    ValidateGatewayAccess::new(Flag::Update, gw.gateway_id),
+                    ValidateGatewayAccess::new(Flag::Delete, gw.gateway_id),
                 ],
                 id: AuthID::User(user_active.id),
                 ok: false,
@@ -4449,14 +6084,14 @@ pub mod test {
         ];
         run_tests(tests).await;
 
-        // multicast-group with api key
+        // gateway with api key
         let tests = vec![
             // admin api key can read, update and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupAccess::new(Flag::Read, mg.id),
-                    ValidateMulticastGroupAccess::new(Flag::Update, mg.id),
-                    ValidateMulticastGroupAccess::new(Flag::Delete, mg.id),
+                    ValidateGatewayAccess::new(Flag::Read, gw.gateway_id),
+                    ValidateGatewayAccess::new(Flag::Update, gw.gateway_id),
+                    ValidateGatewayAccess::new(Flag::Delete, gw.gateway_id),
                 ],
                 id: AuthID::Key(api_key_admin.id),
                 ok: true,
@@ -4464,95 +6099,460 @@ pub mod test {
             // tenant api key can read, update and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupAccess::new(Flag::Read, mg.id),
-                    ValidateMulticastGroupAccess::new(Flag::Update, mg.id),
-                    ValidateMulticastGroupAccess::new(Flag::Delete, mg.id),
+                    ValidateGatewayAccess::new(Flag::Read, gw_api_key_tenant.gateway_id),
+                    ValidateGatewayAccess::new(Flag::Update, gw_api_key_tenant.gateway_id),
+                    ValidateGatewayAccess::new(Flag::Delete, gw_api_key_tenant.gateway_id),
                 ],
                 id: AuthID::Key(api_key_tenant.id),
                 ok: true,
             },
-            // other api key can not read, update or delete
+            // tenant api key can not read, update or delete gw from other tenant
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupAccess::new(Flag::Read, mg.id),
-                    ValidateMulticastGroupAccess::new(Flag::Update, mg.id),
-                    ValidateMulticastGroupAccess::new(Flag::Delete, mg.id),
+                    ValidateGatewayAccess::new(Flag::Read, gw.gateway_id),
+                    ValidateGatewayAccess::new(Flag::Update, gw.gateway_id),
+                    ValidateGatewayAccess::new(Flag::Delete, gw.gateway_id),
                 ],
-                id: AuthID::Key(api_key_other_tenant.id),
+                id: AuthID::Key(api_key_tenant.id),
                 ok: false,
             },
         ];
         run_tests(tests).await;
 
-        // multicast-group queue with user
+        // relay gateway
+        let relay = gateway::create_relay_gateway(gateway::RelayGateway {
+            tenant_id: tenant_a.id,
+            relay_id: RelayId::from_be_bytes([1, 2, 3, 4]),
+            name: "test-relay".into(),
+            region_config_id: "eu868".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let api_key_read_only = api_key::create(api_key::ApiKey {
+            name: "read-only".into(),
+            tenant_id: Some(tenant_a.id),
+            scope: ApiKeyScope::READ_ONLY,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
         let tests = vec![
-            // admin user can create, list and delete
+            // admin user can read, update and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupQueueAccess::new(Flag::Create, mg.id),
-                    ValidateMulticastGroupQueueAccess::new(Flag::List, mg.id),
-                    ValidateMulticastGroupQueueAccess::new(Flag::Delete, mg.id),
+                    ValidateRelayGatewayAccess::new(Flag::Read, tenant_a.id, relay.relay_id),
+                    ValidateRelayGatewayAccess::new(Flag::Update, tenant_a.id, relay.relay_id),
+                    ValidateRelayGatewayAccess::new(Flag::Delete, tenant_a.id, relay.relay_id),
                 ],
                 id: AuthID::User(user_admin.id),
                 ok: true,
             },
-            // tenant admin can create, list and delete
+            // tenant gateway admin can read, update and delete
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupQueueAccess::new(Flag::Create, mg.id),
-                    ValidateMulticastGroupQueueAccess::new(Flag::List, mg.id),
-                    ValidateMulticastGroupQueueAccess::new(Flag::Delete, mg.id),
+                    ValidateRelayGatewayAccess::new(Flag::Read, tenant_a.id, relay.relay_id),
+                    ValidateRelayGatewayAccess::new(Flag::Update, tenant_a.id, relay.relay_id),
+                    ValidateRelayGatewayAccess::new(Flag::Delete, tenant_a.id, relay.relay_id),
                 ],
-                id: AuthID::User(tenant_admin.id),
+                id: AuthID::User(tenant_gateway_admin.id),
                 ok: true,
             },
-            // tenant device admin can create, list and delete
+            // tenant user can read
             ValidatorTest {
-                validators: vec![
-                    ValidateMulticastGroupQueueAccess::new(Flag::Create, mg.id),
-                    ValidateMulticastGroupQueueAccess::new(Flag::List, mg.id),
-                    ValidateMulticastGroupQueueAccess::new(Flag::Delete, mg.id),
-                ],
-                id: AuthID::User(tenant_device_admin.id),
+                validators: vec![ValidateRelayGatewayAccess::new(
+                    Flag::Read,
+                    tenant_a.id,
+                    relay.relay_id,
+                )],
+                id: AuthID::User(tenant_user.id),
                 ok: true,
             },
-            // tenant user can list
+            // tenant user can not update or delete
             ValidatorTest {
-                validators: vec![ValidateMulticastGroupQueueAccess::new(Flag::List, mg.id)],
+                validators: vec![
+                    ValidateRelayGatewayAccess::new(Flag::Update, tenant_a.id, relay.relay_id),
+                    ValidateRelayGatewayAccess::new(Flag::Delete, tenant_a.id, relay.relay_id),
+                ],
                 id: AuthID::User(tenant_user.id),
+                ok: false,
+            },
+            // read-only api key can read
+            ValidatorTest {
+                validators: vec![ValidateRelayGatewayAccess::new(
+                    Flag::Read,
+                    tenant_a.id,
+                    relay.relay_id,
+                )],
+                id: AuthID::Key(api_key_read_only.id),
                 ok: true,
             },
-            // tenant user can not create or delete
+            // read-only api key can not update or delete
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupQueueAccess::new(Flag::Create, mg.id),
-                    ValidateMulticastGroupQueueAccess::new(Flag::Delete, mg.id),
+                    ValidateRelayGatewayAccess::new(Flag::Update, tenant_a.id, relay.relay_id),
+                    ValidateRelayGatewayAccess::new(Flag::Delete, tenant_a.id, relay.relay_id),
                 ],
-                id: AuthID::User(tenant_user.id),
+                id: AuthID::Key(api_key_read_only.id),
                 ok: false,
             },
-            // uther user can not create, list or delete
+            // tenant api key can not update or delete relay from other tenant
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupQueueAccess::new(Flag::Create, mg.id),
-                    ValidateMulticastGroupQueueAccess::new(Flag::List, mg.id),
-                    ValidateMulticastGroupQueueAccess::new(Flag::Delete, mg.id),
+                    ValidateRelayGatewayAccess::new(Flag::Update, tenant_a.id, relay.relay_id),
+                    ValidateRelayGatewayAccess::new(Flag::Delete, tenant_a.id, relay.relay_id),
                 ],
-                id: AuthID::User(user_active.id),
+                id: AuthID::Key(api_key_tenant.id),
                 ok: false,
             },
         ];
         run_tests(tests).await;
+    }
 
-        // multicast-group queue with api key
+    #[tokio::test]
+    async fn multicast_group() {
+        let _guard = test::prepare().await;
+
+        let user_active = user::User {
+            email: "user@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+        let user_admin = user::User {
+            email: "admin@user".into(),
+            is_active: true,
+            is_admin: true,
+            ..Default::default()
+        };
+        let tenant_admin = user::User {
+            email: "tenant-admin@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+        let tenant_device_admin = user::User {
+            email: "tenant-device-admin@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+        let tenant_gateway_admin = user::User {
+            email: "tenant-gateway-admin@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+        let tenant_user = user::User {
+            email: "tenant-user@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+
+        for u in [
+            &user_active,
+            &user_admin,
+            &tenant_admin,
+            &tenant_gateway_admin,
+            &tenant_device_admin,
+            &tenant_user,
+        ] {
+            user::create(u.clone()).await.unwrap();
+        }
+
+        let api_key_admin = api_key::test::create_api_key(true, false).await;
+        let api_key_tenant = api_key::test::create_api_key(false, true).await;
+        let api_key_other_tenant = api_key::test::create_api_key(false, true).await;
+
+        let app =
+            application::test::create_application(Some(api_key_tenant.tenant_id.unwrap())).await;
+
+        tenant::add_user(tenant::TenantUser {
+            tenant_id: api_key_tenant.tenant_id.unwrap(),
+            user_id: tenant_admin.id,
+            is_admin: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        tenant::add_user(tenant::TenantUser {
+            tenant_id: api_key_tenant.tenant_id.unwrap(),
+            user_id: tenant_device_admin.id,
+            is_device_admin: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        tenant::add_user(tenant::TenantUser {
+            tenant_id: api_key_tenant.tenant_id.unwrap(),
+            user_id: tenant_gateway_admin.id,
+            is_gateway_admin: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        tenant::add_user(tenant::TenantUser {
+            tenant_id: api_key_tenant.tenant_id.unwrap(),
+            user_id: tenant_user.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // multicast-groups with user
         let tests = vec![
-            // admin api key can create, list and delete
+            // admin user can create and list
             ValidatorTest {
                 validators: vec![
-                    ValidateMulticastGroupQueueAccess::new(Flag::Create, mg.id),
-                    ValidateMulticastGroupQueueAccess::new(Flag::List, mg.id),
-                    ValidateMulticastGroupQueueAccess::new(Flag::Delete, mg.id),
-                ],
+                    ValidateMulticastGroupsAccess::new(Flag::Create, app.id),
+                    ValidateMulticastGroupsAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::User(user_admin.id),
+                ok: true,
+            },
+            // tenant admin can create and list
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupsAccess::new(Flag::Create, app.id),
+                    ValidateMulticastGroupsAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::User(tenant_admin.id),
+                ok: true,
+            },
+            // tenant device admin can create and list
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupsAccess::new(Flag::Create, app.id),
+                    ValidateMulticastGroupsAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::User(tenant_device_admin.id),
+                ok: true,
+            },
+            // tenant user can list
+            ValidatorTest {
+                validators: vec![ValidateMulticastGroupsAccess::new(Flag::List, app.id)],
+                id: AuthID::User(tenant_user.id),
+                ok: true,
+            },
+            // tenant user can not create
+            ValidatorTest {
+                validators: vec![ValidateMulticastGroupsAccess::new(Flag::Create, app.id)],
+                id: AuthID::User(tenant_user.id),
+                ok: false,
+            },
+            // other user can not create or list
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupsAccess::new(Flag::Create, app.id),
+                    ValidateMulticastGroupsAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::User(user_active.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // multicast-groups with api key
+        let tests = vec![
+            // admin api key can create and list
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupsAccess::new(Flag::Create, app.id),
+                    ValidateMulticastGroupsAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::Key(api_key_admin.id),
+                ok: true,
+            },
+            // tenant api key can create and list
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupsAccess::new(Flag::Create, app.id),
+                    ValidateMulticastGroupsAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::Key(api_key_tenant.id),
+                ok: true,
+            },
+            // tenant api key can not create or list for other tenant
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupsAccess::new(Flag::Create, app.id),
+                    ValidateMulticastGroupsAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::Key(api_key_other_tenant.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        let mg = multicast::create(multicast::MulticastGroup {
+            name: "test-mg".into(),
+            application_id: app.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // multicast-group with user
+        let tests = vec![
+            // admin user can read, update and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupAccess::new(Flag::Read, mg.id),
+                    ValidateMulticastGroupAccess::new(Flag::Update, mg.id),
+                    ValidateMulticastGroupAccess::new(Flag::Delete, mg.id),
+                ],
+                id: AuthID::User(user_admin.id),
+                ok: true,
+            },
+            // tenant admin can read, update and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupAccess::new(Flag::Read, mg.id),
+                    ValidateMulticastGroupAccess::new(Flag::Update, mg.id),
+                    ValidateMulticastGroupAccess::new(Flag::Delete, mg.id),
+                ],
+                id: AuthID::User(tenant_admin.id),
+                ok: true,
+            },
+            // tenant device admin can read, update and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupAccess::new(Flag::Read, mg.id),
+                    ValidateMulticastGroupAccess::new(Flag::Update, mg.id),
+                    ValidateMulticastGroupAccess::new(Flag::Delete, mg.id),
+                ],
+                id: AuthID::User(tenant_device_admin.id),
+                ok: true,
+            },
+            // tenant user can read
+            ValidatorTest {
+                validators: vec![ValidateMulticastGroupAccess::new(Flag::Read, mg.id)],
+                id: AuthID::User(tenant_user.id),
+                ok: true,
+            },
+            // tenant user can not update or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupAccess::new(Flag::Update, mg.id),
+                    ValidateMulticastGroupAccess::new(Flag::Delete, mg.id),
+                ],
+                id: AuthID::User(tenant_user.id),
+                ok: false,
+            },
+            // other user can not read, update or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupAccess::new(Flag::Read, mg.id),
+                    ValidateMulticastGroupAccess::new(Flag::Update, mg.id),
+                    ValidateMulticastGroupAccess::new(Flag::Delete, mg.id),
+                ],
+                id: AuthID::User(user_active.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // multicast-group with api key
+        let tests = vec![
+            // admin api key can read, update and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupAccess::new(Flag::Read, mg.id),
+                    ValidateMulticastGroupAccess::new(Flag::Update, mg.id),
+                    ValidateMulticastGroupAccess::new(Flag::Delete, mg.id),
+                ],
+                id: AuthID::Key(api_key_admin.id),
+                ok: true,
+            },
+            // tenant api key can read, update and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupAccess::new(Flag::Read, mg.id),
+                    ValidateMulticastGroupAccess::new(Flag::Update, mg.id),
+                    ValidateMulticastGroupAccess::new(Flag::Delete, mg.id),
+                ],
+                id: AuthID::Key(api_key_tenant.id),
+                ok: true,
+            },
+            // other api key can not read, update or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupAccess::new(Flag::Read, mg.id),
+                    ValidateMulticastGroupAccess::new(Flag::Update, mg.id),
+                    ValidateMulticastGroupAccess::new(Flag::Delete, mg.id),
+                ],
+                id: AuthID::Key(api_key_other_tenant.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // multicast-group queue with user
+        let tests = vec![
+            // admin user can create, list and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupQueueAccess::new(Flag::Create, mg.id),
+                    ValidateMulticastGroupQueueAccess::new(Flag::List, mg.id),
+                    ValidateMulticastGroupQueueAccess::new(Flag::Delete, mg.id),
+                ],
+                id: AuthID::User(user_admin.id),
+                ok: true,
+            },
+            // tenant admin can create, list and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupQueueAccess::new(Flag::Create, mg.id),
+                    ValidateMulticastGroupQueueAccess::new(Flag::List, mg.id),
+                    ValidateMulticastGroupQueueAccess::new(Flag::Delete, mg.id),
+                ],
+                id: AuthID::User(tenant_admin.id),
+                ok: true,
+            },
+            // tenant device admin can create, list and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupQueueAccess::new(Flag::Create, mg.id),
+                    ValidateMulticastGroupQueueAccess::new(Flag::List, mg.id),
+                    ValidateMulticastGroupQueueAccess::new(Flag::Delete, mg.id),
+                ],
+                id: AuthID::User(tenant_device_admin.id),
+                ok: true,
+            },
+            // tenant user can list
+            ValidatorTest {
+                validators: vec![ValidateMulticastGroupQueueAccess::new(Flag::List, mg.id)],
+                id: AuthID::User(tenant_user.id),
+                ok: true,
+            },
+            // tenant user can not create or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupQueueAccess::new(Flag::Create, mg.id),
+                    ValidateMulticastGroupQueueAccess::new(Flag::Delete, mg.id),
+                ],
+                id: AuthID::User(tenant_user.id),
+                ok: false,
+            },
+            // uther user can not create, list or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupQueueAccess::new(Flag::Create, mg.id),
+                    ValidateMulticastGroupQueueAccess::new(Flag::List, mg.id),
+                    ValidateMulticastGroupQueueAccess::new(Flag::Delete, mg.id),
+                ],
+                id: AuthID::User(user_active.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // multicast-group queue with api key
+        let tests = vec![
+            // admin api key can create, list and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupQueueAccess::new(Flag::Create, mg.id),
+                    ValidateMulticastGroupQueueAccess::new(Flag::List, mg.id),
+                    ValidateMulticastGroupQueueAccess::new(Flag::Delete, mg.id),
+                ],
                 id: AuthID::Key(api_key_admin.id),
                 ok: true,
             },
@@ -4578,5 +6578,997 @@ pub mod test {
             },
         ];
         run_tests(tests).await;
+
+        let app_admin = user::User {
+            email: "app-admin@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+        let app_user = user::User {
+            email: "app-user@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+        for u in [&app_admin, &app_user] {
+            user::create(u.clone()).await.unwrap();
+        }
+        application::add_user(application::ApplicationUser {
+            application_id: app.id,
+            user_id: app_admin.id,
+            is_admin: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        application::add_user(application::ApplicationUser {
+            application_id: app.id,
+            user_id: app_user.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // multicast-groups with application user
+        let tests = vec![
+            // application admin can create and list
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupsAccess::new(Flag::Create, app.id),
+                    ValidateMulticastGroupsAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::User(app_admin.id),
+                ok: true,
+            },
+            // application user can list
+            ValidatorTest {
+                validators: vec![ValidateMulticastGroupsAccess::new(Flag::List, app.id)],
+                id: AuthID::User(app_user.id),
+                ok: true,
+            },
+            // application user can not create
+            ValidatorTest {
+                validators: vec![ValidateMulticastGroupsAccess::new(Flag::Create, app.id)],
+                id: AuthID::User(app_user.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // multicast-group with application user
+        let tests = vec![
+            // application admin can read, update and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupAccess::new(Flag::Read, mg.id),
+                    ValidateMulticastGroupAccess::new(Flag::Update, mg.id),
+                    ValidateMulticastGroupAccess::new(Flag::Delete, mg.id),
+                ],
+                id: AuthID::User(app_admin.id),
+                ok: true,
+            },
+            // application user can read
+            ValidatorTest {
+                validators: vec![ValidateMulticastGroupAccess::new(Flag::Read, mg.id)],
+                id: AuthID::User(app_user.id),
+                ok: true,
+            },
+            // application user can not update or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupAccess::new(Flag::Update, mg.id),
+                    ValidateMulticastGroupAccess::new(Flag::Delete, mg.id),
+                ],
+                id: AuthID::User(app_user.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // multicast-group queue with application user
+        let tests = vec![
+            // application admin can create, list and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupQueueAccess::new(Flag::Create, mg.id),
+                    ValidateMulticastGroupQueueAccess::new(Flag::List, mg.id),
+                    ValidateMulticastGroupQueueAccess::new(Flag::Delete, mg.id),
+                ],
+                id: AuthID::User(app_admin.id),
+                ok: true,
+            },
+            // application user can list
+            ValidatorTest {
+                validators: vec![ValidateMulticastGroupQueueAccess::new(Flag::List, mg.id)],
+                id: AuthID::User(app_user.id),
+                ok: true,
+            },
+            // application user can not create or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateMulticastGroupQueueAccess::new(Flag::Create, mg.id),
+                    ValidateMulticastGroupQueueAccess::new(Flag::Delete, mg.id),
+                ],
+                id: AuthID::User(app_user.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // tenant with application user
+        let tests = vec![
+            // application user can read the tenant
+            ValidatorTest {
+                validators: vec![ValidateTenantAccess::new(
+                    Flag::Read,
+                    api_key_tenant.tenant_id.unwrap(),
+                )],
+                id: AuthID::User(app_user.id),
+                ok: true,
+            },
+            // application user can not read other tenants
+            ValidatorTest {
+                validators: vec![ValidateTenantAccess::new(
+                    Flag::Read,
+                    api_key_other_tenant.tenant_id.unwrap(),
+                )],
+                id: AuthID::User(app_user.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+    }
+
+    #[tokio::test]
+    async fn fuota_campaign() {
+        let _guard = test::prepare().await;
+
+        let user_active = user::User {
+            email: "user@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+        let user_admin = user::User {
+            email: "admin@user".into(),
+            is_active: true,
+            is_admin: true,
+            ..Default::default()
+        };
+        let tenant_admin = user::User {
+            email: "tenant-admin@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+        let tenant_device_admin = user::User {
+            email: "tenant-device-admin@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+        let tenant_gateway_admin = user::User {
+            email: "tenant-gateway-admin@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+        let tenant_user = user::User {
+            email: "tenant-user@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+
+        for u in [
+            &user_active,
+            &user_admin,
+            &tenant_admin,
+            &tenant_gateway_admin,
+            &tenant_device_admin,
+            &tenant_user,
+        ] {
+            user::create(u.clone()).await.unwrap();
+        }
+
+        let api_key_admin = api_key::test::create_api_key(true, false).await;
+        let api_key_tenant = api_key::test::create_api_key(false, true).await;
+        let api_key_other_tenant = api_key::test::create_api_key(false, true).await;
+
+        let app =
+            application::test::create_application(Some(api_key_tenant.tenant_id.unwrap())).await;
+
+        tenant::add_user(tenant::TenantUser {
+            tenant_id: api_key_tenant.tenant_id.unwrap(),
+            user_id: tenant_admin.id,
+            is_admin: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        tenant::add_user(tenant::TenantUser {
+            tenant_id: api_key_tenant.tenant_id.unwrap(),
+            user_id: tenant_device_admin.id,
+            is_device_admin: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        tenant::add_user(tenant::TenantUser {
+            tenant_id: api_key_tenant.tenant_id.unwrap(),
+            user_id: tenant_gateway_admin.id,
+            is_gateway_admin: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        tenant::add_user(tenant::TenantUser {
+            tenant_id: api_key_tenant.tenant_id.unwrap(),
+            user_id: tenant_user.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // fuota-campaigns with user
+        let tests = vec![
+            // admin user can create and list
+            ValidatorTest {
+                validators: vec![
+                    ValidateFuotaCampaignsAccess::new(Flag::Create, app.id),
+                    ValidateFuotaCampaignsAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::User(user_admin.id),
+                ok: true,
+            },
+            // tenant admin can create and list
+            ValidatorTest {
+                validators: vec![
+                    ValidateFuotaCampaignsAccess::new(Flag::Create, app.id),
+                    ValidateFuotaCampaignsAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::User(tenant_admin.id),
+                ok: true,
+            },
+            // tenant device admin can create and list
+            ValidatorTest {
+                validators: vec![
+                    ValidateFuotaCampaignsAccess::new(Flag::Create, app.id),
+                    ValidateFuotaCampaignsAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::User(tenant_device_admin.id),
+                ok: true,
+            },
+            // tenant user can list
+            ValidatorTest {
+                validators: vec![ValidateFuotaCampaignsAccess::new(Flag::List, app.id)],
+                id: AuthID::User(tenant_user.id),
+                ok: true,
+            },
+            // tenant user can not create
+            ValidatorTest {
+                validators: vec![ValidateFuotaCampaignsAccess::new(Flag::Create, app.id)],
+                id: AuthID::User(tenant_user.id),
+                ok: false,
+            },
+            // other user can not create or list
+            ValidatorTest {
+                validators: vec![
+                    ValidateFuotaCampaignsAccess::new(Flag::Create, app.id),
+                    ValidateFuotaCampaignsAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::User(user_active.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // fuota-campaigns with api key
+        let tests = vec![
+            // admin api key can create and list
+            ValidatorTest {
+                validators: vec![
+                    ValidateFuotaCampaignsAccess::new(Flag::Create, app.id),
+                    ValidateFuotaCampaignsAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::Key(api_key_admin.id),
+                ok: true,
+            },
+            // tenant api key can create and list
+            ValidatorTest {
+                validators: vec![
+                    ValidateFuotaCampaignsAccess::new(Flag::Create, app.id),
+                    ValidateFuotaCampaignsAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::Key(api_key_tenant.id),
+                ok: true,
+            },
+            // tenant api key can not create or list for other tenant
+            ValidatorTest {
+                validators: vec![
+                    ValidateFuotaCampaignsAccess::new(Flag::Create, app.id),
+                    ValidateFuotaCampaignsAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::Key(api_key_other_tenant.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        let dp = device_profile::test::create_device_profile(api_key_tenant.tenant_id).await;
+        let fc = fuota::create(fuota::FuotaCampaign {
+            name: "test-fc".into(),
+            application_id: app.id,
+            device_profile_id: dp.id,
+            multicast_group_type: "C".into(),
+            fragment_size: 10,
+            payload: vec![1, 2, 3],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // fuota-campaign with user
+        let tests = vec![
+            // admin user can read, update and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateFuotaCampaignAccess::new(Flag::Read, fc.id),
+                    ValidateFuotaCampaignAccess::new(Flag::Update, fc.id),
+                    ValidateFuotaCampaignAccess::new(Flag::Delete, fc.id),
+                ],
+                id: AuthID::User(user_admin.id),
+                ok: true,
+            },
+            // tenant admin can read, update and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateFuotaCampaignAccess::new(Flag::Read, fc.id),
+                    ValidateFuotaCampaignAccess::new(Flag::Update, fc.id),
+                    ValidateFuotaCampaignAccess::new(Flag::Delete, fc.id),
+                ],
+                id: AuthID::User(tenant_admin.id),
+                ok: true,
+            },
+            // tenant device admin can read, update and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateFuotaCampaignAccess::new(Flag::Read, fc.id),
+                    ValidateFuotaCampaignAccess::new(Flag::Update, fc.id),
+                    ValidateFuotaCampaignAccess::new(Flag::Delete, fc.id),
+                ],
+                id: AuthID::User(tenant_device_admin.id),
+                ok: true,
+            },
+            // tenant user can read
+            ValidatorTest {
+                validators: vec![ValidateFuotaCampaignAccess::new(Flag::Read, fc.id)],
+                id: AuthID::User(tenant_user.id),
+                ok: true,
+            },
+            // tenant user can not update or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateFuotaCampaignAccess::new(Flag::Update, fc.id),
+                    ValidateFuotaCampaignAccess::new(Flag::Delete, fc.id),
+                ],
+                id: AuthID::User(tenant_user.id),
+                ok: false,
+            },
+            // other user can not read, update or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateFuotaCampaignAccess::new(Flag::Read, fc.id),
+                    ValidateFuotaCampaignAccess::new(Flag::Update, fc.id),
+                    ValidateFuotaCampaignAccess::new(Flag::Delete, fc.id),
+                ],
+                id: AuthID::User(user_active.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // fuota-campaign with api key
+        let tests = vec![
+            // admin api key can read, update and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateFuotaCampaignAccess::new(Flag::Read, fc.id),
+                    ValidateFuotaCampaignAccess::new(Flag::Update, fc.id),
+                    ValidateFuotaCampaignAccess::new(Flag::Delete, fc.id),
+                ],
+                id: AuthID::Key(api_key_admin.id),
+                ok: true,
+            },
+            // tenant api key can read, update and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateFuotaCampaignAccess::new(Flag::Read, fc.id),
+                    ValidateFuotaCampaignAccess::new(Flag::Update, fc.id),
+                    ValidateFuotaCampaignAccess::new(Flag::Delete, fc.id),
+                ],
+                id: AuthID::Key(api_key_tenant.id),
+                ok: true,
+            },
+            // other api key can not read, update or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateFuotaCampaignAccess::new(Flag::Read, fc.id),
+                    ValidateFuotaCampaignAccess::new(Flag::Update, fc.id),
+                    ValidateFuotaCampaignAccess::new(Flag::Delete, fc.id),
+                ],
+                id: AuthID::Key(api_key_other_tenant.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        let app_admin = user::User {
+            email: "app-admin@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+        let app_user = user::User {
+            email: "app-user@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+        for u in [&app_admin, &app_user] {
+            user::create(u.clone()).await.unwrap();
+        }
+        application::add_user(application::ApplicationUser {
+            application_id: app.id,
+            user_id: app_admin.id,
+            is_admin: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        application::add_user(application::ApplicationUser {
+            application_id: app.id,
+            user_id: app_user.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // fuota campaigns with application user
+        let tests = vec![
+            // application admin can create and list
+            ValidatorTest {
+                validators: vec![
+                    ValidateFuotaCampaignsAccess::new(Flag::Create, app.id),
+                    ValidateFuotaCampaignsAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::User(app_admin.id),
+                ok: true,
+            },
+            // application user can list
+            ValidatorTest {
+                validators: vec![ValidateFuotaCampaignsAccess::new(Flag::List, app.id)],
+                id: AuthID::User(app_user.id),
+                ok: true,
+            },
+            // application user can not create
+            ValidatorTest {
+                validators: vec![ValidateFuotaCampaignsAccess::new(Flag::Create, app.id)],
+                id: AuthID::User(app_user.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // fuota campaign with application user
+        let tests = vec![
+            // application admin can read, update and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateFuotaCampaignAccess::new(Flag::Read, fc.id),
+                    ValidateFuotaCampaignAccess::new(Flag::Update, fc.id),
+                    ValidateFuotaCampaignAccess::new(Flag::Delete, fc.id),
+                ],
+                id: AuthID::User(app_admin.id),
+                ok: true,
+            },
+            // application user can read
+            ValidatorTest {
+                validators: vec![ValidateFuotaCampaignAccess::new(Flag::Read, fc.id)],
+                id: AuthID::User(app_user.id),
+                ok: true,
+            },
+            // application user can not update or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateFuotaCampaignAccess::new(Flag::Update, fc.id),
+                    ValidateFuotaCampaignAccess::new(Flag::Delete, fc.id),
+                ],
+                id: AuthID::User(app_user.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+    }
+
+    #[tokio::test]
+    async fn api_key_scopes() {
+        let _guard = test::prepare().await;
+
+        let t = tenant::test::create_tenant().await;
+        let app = application::test::create_application(Some(t.id)).await;
+        let app_other = application::test::create_application(Some(t.id)).await;
+        let dp = device_profile::test::create_device_profile(Some(t.id)).await;
+        let dev = device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            Some(app.id),
+        )
+        .await;
+        let dev_other = device::test::create_device(
+            EUI64::from_be_bytes([2, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            Some(app_other.id),
+        )
+        .await;
+
+        let api_key_read_only = api_key::create(api_key::ApiKey {
+            name: "read-only".into(),
+            tenant_id: Some(t.id),
+            scope: ApiKeyScope::READ_ONLY,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let api_key_enqueue = api_key::create(api_key::ApiKey {
+            name: "enqueue".into(),
+            tenant_id: Some(t.id),
+            scope: ApiKeyScope::DEVICE_QUEUE_ENQUEUE,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let api_key_app = api_key::create(api_key::ApiKey {
+            name: "application".into(),
+            tenant_id: Some(t.id),
+            application_id: Some(app.id),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let api_key_expired = api_key::create(api_key::ApiKey {
+            name: "expired".into(),
+            tenant_id: Some(t.id),
+            expires_at: Some(chrono::Utc::now() - chrono::Duration::seconds(1)),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // device access
+        let tests = vec![
+            // read-only api key can read
+            ValidatorTest {
+                validators: vec![ValidateDeviceAccess::new(Flag::Read, dev.dev_eui)],
+                id: AuthID::Key(api_key_read_only.id),
+                ok: true,
+            },
+            // read-only api key can not update or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
+                ],
+                id: AuthID::Key(api_key_read_only.id),
+                ok: false,
+            },
+            // enqueue api key can not read, update or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateDeviceAccess::new(Flag::Read, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
+                ],
+                id: AuthID::Key(api_key_enqueue.id),
+                ok: false,
+            },
+            // application api key can read, update and delete devices of the application
+            ValidatorTest {
+                validators: vec![
+                    ValidateDeviceAccess::new(Flag::Read, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
+                ],
+                id: AuthID::Key(api_key_app.id),
+                ok: true,
+            },
+            // application api key can not read, update or delete devices of other applications
+            ValidatorTest {
+                validators: vec![
+                    ValidateDeviceAccess::new(Flag::Read, dev_other.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Update, dev_other.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Delete, dev_other.dev_eui),
+                ],
+                id: AuthID::Key(api_key_app.id),
+                ok: false,
+            },
+            // expired api key can not read
+            ValidatorTest {
+                validators: vec![ValidateDeviceAccess::new(Flag::Read, dev.dev_eui)],
+                id: AuthID::Key(api_key_expired.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // device-queue access
+        let tests = vec![
+            // enqueue api key can enqueue
+            ValidatorTest {
+                validators: vec![ValidateDeviceQueueAccess::new(Flag::Create, dev.dev_eui)],
+                id: AuthID::Key(api_key_enqueue.id),
+                ok: true,
+            },
+            // enqueue api key can not list or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateDeviceQueueAccess::new(Flag::List, dev.dev_eui),
+                    ValidateDeviceQueueAccess::new(Flag::Delete, dev.dev_eui),
+                ],
+                id: AuthID::Key(api_key_enqueue.id),
+                ok: false,
+            },
+            // read-only api key can list
+            ValidatorTest {
+                validators: vec![ValidateDeviceQueueAccess::new(Flag::List, dev.dev_eui)],
+                id: AuthID::Key(api_key_read_only.id),
+                ok: true,
+            },
+            // read-only api key can not enqueue or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateDeviceQueueAccess::new(Flag::Create, dev.dev_eui),
+                    ValidateDeviceQueueAccess::new(Flag::Delete, dev.dev_eui),
+                ],
+                id: AuthID::Key(api_key_read_only.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // tenant-level access
+        let tests = vec![
+            // read-only api key can list
+            ValidatorTest {
+                validators: vec![ValidateGatewaysAccess::new(Flag::List, t.id)],
+                id: AuthID::Key(api_key_read_only.id),
+                ok: true,
+            },
+            // application api key can not list
+            ValidatorTest {
+                validators: vec![ValidateGatewaysAccess::new(Flag::List, t.id)],
+                id: AuthID::Key(api_key_app.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // last used is set
+        let ak = api_key::get(&api_key_read_only.id).await.unwrap();
+        assert!(ak.last_used_at.is_some());
+        let ak = api_key::get(&api_key_expired.id).await.unwrap();
+        assert!(ak.last_used_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn application_user() {
+        let _guard = test::prepare().await;
+
+        let app_admin = user::User {
+            email: "app-admin@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+        let app_device_admin = user::User {
+            email: "app-device-admin@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+        let app_user = user::User {
+            email: "app-user@user".into(),
+            is_active: true,
+            ..Default::default()
+        };
+        for u in [&app_admin, &app_device_admin, &app_user] {
+            user::create(u.clone()).await.unwrap();
+        }
+
+        let t = tenant::test::create_tenant().await;
+        let app = application::test::create_application(Some(t.id)).await;
+        let app_other = application::test::create_application(Some(t.id)).await;
+        let dp = device_profile::test::create_device_profile(Some(t.id)).await;
+        let dev = device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            Some(app.id),
+        )
+        .await;
+        let dev_other = device::test::create_device(
+            EUI64::from_be_bytes([2, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            Some(app_other.id),
+        )
+        .await;
+
+        for (u, is_admin, is_device_admin) in [
+            (&app_admin, true, false),
+            (&app_device_admin, false, true),
+            (&app_user, false, false),
+        ] {
+            application::add_user(application::ApplicationUser {
+                application_id: app.id,
+                user_id: u.id,
+                is_admin,
+                is_device_admin,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        }
+
+        // applications
+        let tests = vec![
+            // application users can list applications of the tenant
+            ValidatorTest {
+                validators: vec![ValidateApplicationsAccess::new(Flag::List, t.id)],
+                id: AuthID::User(app_user.id),
+                ok: true,
+            },
+            // application users can not create applications
+            ValidatorTest {
+                validators: vec![ValidateApplicationsAccess::new(Flag::Create, t.id)],
+                id: AuthID::User(app_admin.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // application
+        let tests = vec![
+            // application admin and device admin can read, update and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateApplicationAccess::new(Flag::Read, app.id),
+                    ValidateApplicationAccess::new(Flag::Update, app.id),
+                    ValidateApplicationAccess::new(Flag::Delete, app.id),
+                ],
+                id: AuthID::User(app_admin.id),
+                ok: true,
+            },
+            ValidatorTest {
+                validators: vec![
+                    ValidateApplicationAccess::new(Flag::Read, app.id),
+                    ValidateApplicationAccess::new(Flag::Update, app.id),
+                    ValidateApplicationAccess::new(Flag::Delete, app.id),
+                ],
+                id: AuthID::User(app_device_admin.id),
+                ok: true,
+            },
+            // application user can read
+            ValidatorTest {
+                validators: vec![ValidateApplicationAccess::new(Flag::Read, app.id)],
+                id: AuthID::User(app_user.id),
+                ok: true,
+            },
+            // application user can not update or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateApplicationAccess::new(Flag::Update, app.id),
+                    ValidateApplicationAccess::new(Flag::Delete, app.id),
+                ],
+                id: AuthID::User(app_user.id),
+                ok: false,
+            },
+            // application admin can not read other applications
+            ValidatorTest {
+                validators: vec![ValidateApplicationAccess::new(Flag::Read, app_other.id)],
+                id: AuthID::User(app_admin.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // application users
+        let tests = vec![
+            // application admin can create and list
+            ValidatorTest {
+                validators: vec![
+                    ValidateApplicationUsersAccess::new(Flag::Create, app.id),
+                    ValidateApplicationUsersAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::User(app_admin.id),
+                ok: true,
+            },
+            // application user can list
+            ValidatorTest {
+                validators: vec![ValidateApplicationUsersAccess::new(Flag::List, app.id)],
+                id: AuthID::User(app_user.id),
+                ok: true,
+            },
+            // application device admin can not create
+            ValidatorTest {
+                validators: vec![ValidateApplicationUsersAccess::new(Flag::Create, app.id)],
+                id: AuthID::User(app_device_admin.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        let tests = vec![
+            // application admin can read, update and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateApplicationUserAccess::new(Flag::Read, app.id, app_user.id),
+                    ValidateApplicationUserAccess::new(Flag::Update, app.id, app_user.id),
+                    ValidateApplicationUserAccess::new(Flag::Delete, app.id, app_user.id),
+                ],
+                id: AuthID::User(app_admin.id),
+                ok: true,
+            },
+            // application user can read itself
+            ValidatorTest {
+                validators: vec![ValidateApplicationUserAccess::new(
+                    Flag::Read,
+                    app.id,
+                    app_user.id,
+                )],
+                id: AuthID::User(app_user.id),
+                ok: true,
+            },
+            // application user can not read others, or update itself
+            ValidatorTest {
+                validators: vec![
+                    ValidateApplicationUserAccess::new(Flag::Read, app.id, app_admin.id),
+                    ValidateApplicationUserAccess::new(Flag::Update, app.id, app_user.id),
+                ],
+                id: AuthID::User(app_user.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // devices
+        let tests = vec![
+            // application admin and device admin can create and list
+            ValidatorTest {
+                validators: vec![
+                    ValidateDevicesAccess::new(Flag::Create, app.id),
+                    ValidateDevicesAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::User(app_admin.id),
+                ok: true,
+            },
+            ValidatorTest {
+                validators: vec![
+                    ValidateDevicesAccess::new(Flag::Create, app.id),
+                    ValidateDevicesAccess::new(Flag::List, app.id),
+                ],
+                id: AuthID::User(app_device_admin.id),
+                ok: true,
+            },
+            // application user can list
+            ValidatorTest {
+                validators: vec![ValidateDevicesAccess::new(Flag::List, app.id)],
+                id: AuthID::User(app_user.id),
+                ok: true,
+            },
+            // application user can not create
+            ValidatorTest {
+                validators: vec![ValidateDevicesAccess::new(Flag::Create, app.id)],
+                id: AuthID::User(app_user.id),
+                ok: false,
+            },
+            // application device admin can not list devices of other applications
+            ValidatorTest {
+                validators: vec![ValidateDevicesAccess::new(Flag::List, app_other.id)],
+                id: AuthID::User(app_device_admin.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // device
+        let tests = vec![
+            // application device admin can read, update and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateDeviceAccess::new(Flag::Read, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
+                ],
+                id: AuthID::User(app_device_admin.id),
+                ok: true,
+            },
+            // application user can read
+            ValidatorTest {
+                validators: vec![ValidateDeviceAccess::new(Flag::Read, dev.dev_eui)],
+                id: AuthID::User(app_user.id),
+                ok: true,
+            },
+            // application user can not update or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateDeviceAccess::new(Flag::Update, dev.dev_eui),
+                    ValidateDeviceAccess::new(Flag::Delete, dev.dev_eui),
+                ],
+                id: AuthID::User(app_user.id),
+                ok: false,
+            },
+            // application admin can not read devices of other applications
+            ValidatorTest {
+                validators: vec![ValidateDeviceAccess::new(Flag::Read, dev_other.dev_eui)],
+                id: AuthID::User(app_admin.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // device queue
+        let tests = vec![
+            // application device admin can create, list and delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateDeviceQueueAccess::new(Flag::Create, dev.dev_eui),
+                    ValidateDeviceQueueAccess::new(Flag::List, dev.dev_eui),
+                    ValidateDeviceQueueAccess::new(Flag::Delete, dev.dev_eui),
+                ],
+                id: AuthID::User(app_device_admin.id),
+                ok: true,
+            },
+            // application user can list
+            ValidatorTest {
+                validators: vec![ValidateDeviceQueueAccess::new(Flag::List, dev.dev_eui)],
+                id: AuthID::User(app_user.id),
+                ok: true,
+            },
+            // application user can not create or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateDeviceQueueAccess::new(Flag::Create, dev.dev_eui),
+                    ValidateDeviceQueueAccess::new(Flag::Delete, dev.dev_eui),
+                ],
+                id: AuthID::User(app_user.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        // device-profiles
+        let tests = vec![
+            // application user can list
+            ValidatorTest {
+                validators: vec![ValidateDeviceProfilesAccess::new(Flag::List, t.id)],
+                id: AuthID::User(app_user.id),
+                ok: true,
+            },
+            // application admin can not create
+            ValidatorTest {
+                validators: vec![ValidateDeviceProfilesAccess::new(Flag::Create, t.id)],
+                id: AuthID::User(app_admin.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
+
+        let tests = vec![
+            // application user can read
+            ValidatorTest {
+                validators: vec![ValidateDeviceProfileAccess::new(Flag::Read, dp.id)],
+                id: AuthID::User(app_user.id),
+                ok: true,
+            },
+            // application admin can not update or delete
+            ValidatorTest {
+                validators: vec![
+                    ValidateDeviceProfileAccess::new(Flag::Update, dp.id),
+                    ValidateDeviceProfileAccess::new(Flag::Delete, dp.id),
+                ],
+                id: AuthID::User(app_admin.id),
+                ok: false,
+            },
+        ];
+        run_tests(tests).await;
     }
 }
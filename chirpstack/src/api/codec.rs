@@ -0,0 +1,852 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use chrono::Utc;
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+use chirpstack_api::api;
+use chirpstack_api::api::codec_service_server::CodecService;
+
+use super::auth::validator;
+use super::error::ToStatus;
+use super::helpers;
+use super::helpers::{FromProto, ToProto};
+use crate::codec as codec_runtime;
+use crate::storage::{audit_log, codec, fields};
+
+pub struct Codec {
+    validator: validator::RequestValidator,
+}
+
+impl Codec {
+    pub fn new(validator: validator::RequestValidator) -> Self {
+        Codec { validator }
+    }
+}
+
+#[tonic::async_trait]
+impl CodecService for Codec {
+    async fn create(
+        &self,
+        request: Request<api::CreateCodecRequest>,
+    ) -> Result<Response<api::CreateCodecResponse>, Status> {
+        let req_c = match &request.get_ref().codec {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("codec is missing"));
+            }
+        };
+        let tenant_id = Uuid::from_str(&req_c.tenant_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateCodecsAccess::new(validator::Flag::Create, tenant_id),
+            )
+            .await?;
+
+        let c = codec::create(codec::Codec {
+            tenant_id,
+            name: req_c.name.clone(),
+            description: req_c.description.clone(),
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::CreateCodecResponse {
+            id: c.id.to_string(),
+        });
+        resp.metadata_mut()
+            .insert("x-log-codec_id", c.id.to_string().parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn get(
+        &self,
+        request: Request<api::GetCodecRequest>,
+    ) -> Result<Response<api::GetCodecResponse>, Status> {
+        let req = request.get_ref();
+        let codec_id = Uuid::from_str(&req.id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateCodecAccess::new(validator::Flag::Read, codec_id),
+            )
+            .await?;
+
+        let c = codec::get(&codec_id).await.map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::GetCodecResponse {
+            codec: Some(api::Codec {
+                id: c.id.to_string(),
+                tenant_id: c.tenant_id.to_string(),
+                name: c.name,
+                description: c.description,
+            }),
+            created_at: Some(helpers::datetime_to_prost_timestamp(&c.created_at)),
+            updated_at: Some(helpers::datetime_to_prost_timestamp(&c.updated_at)),
+        });
+        resp.metadata_mut()
+            .insert("x-log-codec_id", req.id.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn update(
+        &self,
+        request: Request<api::UpdateCodecRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req_c = match &request.get_ref().codec {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("codec is missing"));
+            }
+        };
+        let codec_id = Uuid::from_str(&req_c.id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateCodecAccess::new(validator::Flag::Update, codec_id),
+            )
+            .await?;
+
+        let c_old = codec::get(&codec_id).await.map_err(|e| e.status())?;
+
+        let c = codec::update(codec::Codec {
+            id: codec_id,
+            name: req_c.name.clone(),
+            description: req_c.description.clone(),
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-codec_id", req_c.id.parse().unwrap());
+
+        resp.extensions_mut().insert(
+            audit_log::Changes::new()
+                .field("name", &c_old.name, &c.name)
+                .field("description", &c_old.description, &c.description),
+        );
+
+        Ok(resp)
+    }
+
+    async fn delete(
+        &self,
+        request: Request<api::DeleteCodecRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.get_ref();
+        let codec_id = Uuid::from_str(&req.id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateCodecAccess::new(validator::Flag::Delete, codec_id),
+            )
+            .await?;
+
+        let c = codec::get(&codec_id).await.map_err(|e| e.status())?;
+
+        codec::delete(&codec_id).await.map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-codec_id", req.id.parse().unwrap());
+        resp.metadata_mut()
+            .insert("x-log-tenant_id", c.tenant_id.to_string().parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn list(
+        &self,
+        request: Request<api::ListCodecsRequest>,
+    ) -> Result<Response<api::ListCodecsResponse>, Status> {
+        let req = request.get_ref();
+        let tenant_id = Uuid::from_str(&req.tenant_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateCodecsAccess::new(validator::Flag::List, tenant_id),
+            )
+            .await?;
+
+        let filters = codec::Filters {
+            tenant_id: Some(tenant_id),
+            search: if req.search.is_empty() {
+                None
+            } else {
+                Some(req.search.to_string())
+            },
+        };
+
+        let count = codec::get_count(&filters).await.map_err(|e| e.status())?;
+        let items = codec::list(req.limit as i64, req.offset as i64, &filters)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::ListCodecsResponse {
+            total_count: count as u32,
+            result: items
+                .iter()
+                .map(|c| api::CodecListItem {
+                    id: c.id.to_string(),
+                    created_at: Some(helpers::datetime_to_prost_timestamp(&c.created_at)),
+                    updated_at: Some(helpers::datetime_to_prost_timestamp(&c.updated_at)),
+                    name: c.name.clone(),
+                    latest_version: c.latest_version.unwrap_or_default() as u32,
+                })
+                .collect(),
+        });
+        resp.metadata_mut()
+            .insert("x-log-tenant_id", req.tenant_id.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn create_version(
+        &self,
+        request: Request<api::CreateCodecVersionRequest>,
+    ) -> Result<Response<api::CreateCodecVersionResponse>, Status> {
+        let req_cv = match &request.get_ref().codec_version {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("codec_version is missing"));
+            }
+        };
+        let codec_id = Uuid::from_str(&req_cv.codec_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateCodecAccess::new(validator::Flag::Update, codec_id),
+            )
+            .await?;
+
+        let runtime: codec_runtime::Codec = req_cv.runtime().from_proto();
+        let mut tests: Vec<fields::CodecTest> = Vec::new();
+
+        for t in &req_cv.tests {
+            if t.f_port == 0 || t.f_port > 255 {
+                return Err(Status::invalid_argument(format!(
+                    "Test '{}': f_port must be between 1 - 255",
+                    t.name
+                )));
+            }
+
+            let expected = struct_to_json(&t.object.clone().unwrap_or_default());
+            let decoded = codec_runtime::binary_to_struct(
+                runtime,
+                Utc::now(),
+                t.f_port as u8,
+                &HashMap::new(),
+                &Default::default(),
+                &req_cv.script,
+                &t.data,
+            )
+            .await
+            .map_err(|e| Status::invalid_argument(format!("Test '{}': {}", t.name, e)))?;
+
+            if !decoded.errors.is_empty() {
+                return Err(Status::invalid_argument(format!(
+                    "Test '{}': {}",
+                    t.name,
+                    decoded.errors.join(", ")
+                )));
+            }
+
+            let decoded = serde_json::to_value(decoded.object.unwrap_or_default())
+                .map_err(|e| Status::internal(format!("Test '{}': {}", t.name, e)))?;
+
+            if decoded != expected {
+                return Err(Status::invalid_argument(format!(
+                    "Test '{}': decoded object does not match expected object",
+                    t.name
+                )));
+            }
+
+            tests.push(fields::CodecTest {
+                name: t.name.clone(),
+                f_port: t.f_port as u8,
+                data: t.data.clone(),
+                object: expected,
+            });
+        }
+
+        let cv = codec::create_version(codec::CodecVersion {
+            codec_id,
+            runtime,
+            script: req_cv.script.clone(),
+            tests: fields::CodecTests::new(tests),
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::CreateCodecVersionResponse {
+            id: cv.id.to_string(),
+            version: cv.version as u32,
+        });
+        resp.metadata_mut()
+            .insert("x-log-codec_id", req_cv.codec_id.parse().unwrap());
+        resp.metadata_mut()
+            .insert("x-log-codec_version_id", cv.id.to_string().parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn get_version(
+        &self,
+        request: Request<api::GetCodecVersionRequest>,
+    ) -> Result<Response<api::GetCodecVersionResponse>, Status> {
+        let req = request.get_ref();
+        let cv_id = Uuid::from_str(&req.id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateCodecVersionAccess::new(validator::Flag::Read, cv_id),
+            )
+            .await?;
+
+        let cv = codec::get_version(&cv_id).await.map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::GetCodecVersionResponse {
+            codec_version: Some(api::CodecVersion {
+                id: cv.id.to_string(),
+                codec_id: cv.codec_id.to_string(),
+                version: cv.version as u32,
+                runtime: cv.runtime.to_proto().into(),
+                script: cv.script,
+                tests: cv
+                    .tests
+                    .iter()
+                    .map(|t| api::CodecTest {
+                        name: t.name.clone(),
+                        f_port: t.f_port as u32,
+                        data: t.data.clone(),
+                        object: Some(json_to_struct(&t.object)),
+                    })
+                    .collect(),
+            }),
+            created_at: Some(helpers::datetime_to_prost_timestamp(&cv.created_at)),
+        });
+        resp.metadata_mut()
+            .insert("x-log-codec_version_id", req.id.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn list_versions(
+        &self,
+        request: Request<api::ListCodecVersionsRequest>,
+    ) -> Result<Response<api::ListCodecVersionsResponse>, Status> {
+        let req = request.get_ref();
+        let codec_id = Uuid::from_str(&req.codec_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateCodecAccess::new(validator::Flag::Read, codec_id),
+            )
+            .await?;
+
+        let count = codec::get_version_count(&codec_id)
+            .await
+            .map_err(|e| e.status())?;
+        let items = codec::list_versions(req.limit as i64, req.offset as i64, &codec_id)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::ListCodecVersionsResponse {
+            total_count: count as u32,
+            result: items
+                .iter()
+                .map(|cv| api::CodecVersionListItem {
+                    id: cv.id.to_string(),
+                    created_at: Some(helpers::datetime_to_prost_timestamp(&cv.created_at)),
+                    version: cv.version as u32,
+                    runtime: cv.runtime.to_proto().into(),
+                    device_profile_count: cv.device_profile_count as u32,
+                })
+                .collect(),
+        });
+        resp.metadata_mut()
+            .insert("x-log-codec_id", req.codec_id.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn list_device_profiles(
+        &self,
+        request: Request<api::ListCodecDeviceProfilesRequest>,
+    ) -> Result<Response<api::ListCodecDeviceProfilesResponse>, Status> {
+        let req = request.get_ref();
+        let codec_id = Uuid::from_str(&req.codec_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateCodecAccess::new(validator::Flag::Read, codec_id),
+            )
+            .await?;
+
+        let count = codec::get_device_profile_count(&codec_id)
+            .await
+            .map_err(|e| e.status())?;
+        let items = codec::list_device_profiles(req.limit as i64, req.offset as i64, &codec_id)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::ListCodecDeviceProfilesResponse {
+            total_count: count as u32,
+            result: items
+                .iter()
+                .map(|dp| api::CodecDeviceProfileListItem {
+                    device_profile_id: dp.device_profile_id.to_string(),
+                    device_profile_name: dp.device_profile_name.clone(),
+                    codec_version_id: dp.codec_version_id.to_string(),
+                    version: dp.version as u32,
+                })
+                .collect(),
+        });
+        resp.metadata_mut()
+            .insert("x-log-codec_id", req.codec_id.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn roll_device_profiles(
+        &self,
+        request: Request<api::RollCodecDeviceProfilesRequest>,
+    ) -> Result<Response<api::RollCodecDeviceProfilesResponse>, Status> {
+        let req = request.get_ref();
+        let codec_id = Uuid::from_str(&req.codec_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateCodecAccess::new(validator::Flag::Update, codec_id),
+            )
+            .await?;
+
+        let version = i32::try_from(req.version)
+            .map_err(|_| Status::invalid_argument("version is out of range"))?;
+        let from_versions = req
+            .from_versions
+            .iter()
+            .map(|v| i32::try_from(*v))
+            .collect::<Result<Vec<i32>, _>>()
+            .map_err(|_| Status::invalid_argument("from_versions is out of range"))?;
+        let count = codec::roll_device_profiles(&codec_id, version, &from_versions)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::RollCodecDeviceProfilesResponse {
+            updated_count: count as u32,
+        });
+        resp.metadata_mut()
+            .insert("x-log-codec_id", req.codec_id.parse().unwrap());
+
+        Ok(resp)
+    }
+}
+
+fn struct_to_json(s: &prost_types::Struct) -> serde_json::Value {
+    serde_json::Value::Object(
+        s.fields
+            .iter()
+            .map(|(k, v)| (k.clone(), value_to_json(v)))
+            .collect(),
+    )
+}
+
+fn value_to_json(v: &prost_types::Value) -> serde_json::Value {
+    match &v.kind {
+        None | Some(prost_types::value::Kind::NullValue(_)) => serde_json::Value::Null,
+        Some(prost_types::value::Kind::BoolValue(v)) => serde_json::Value::Bool(*v),
+        Some(prost_types::value::Kind::NumberValue(v)) => serde_json::Number::from_f64(*v)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        Some(prost_types::value::Kind::StringValue(v)) => serde_json::Value::String(v.clone()),
+        Some(prost_types::value::Kind::ListValue(v)) => {
+            serde_json::Value::Array(v.values.iter().map(value_to_json).collect())
+        }
+        Some(prost_types::value::Kind::StructValue(v)) => struct_to_json(v),
+    }
+}
+
+fn json_to_struct(v: &serde_json::Value) -> prost_types::Struct {
+    match json_to_value(v).kind {
+        Some(prost_types::value::Kind::StructValue(v)) => v,
+        _ => Default::default(),
+    }
+}
+
+fn json_to_value(v: &serde_json::Value) -> prost_types::Value {
+    prost_types::Value {
+        kind: Some(match v {
+            serde_json::Value::Null => prost_types::value::Kind::NullValue(0),
+            serde_json::Value::Bool(v) => prost_types::value::Kind::BoolValue(*v),
+            serde_json::Value::Number(v) => {
+                prost_types::value::Kind::NumberValue(v.as_f64().unwrap_or_default())
+            }
+            serde_json::Value::String(v) => prost_types::value::Kind::StringValue(v.clone()),
+            serde_json::Value::Array(v) => {
+                prost_types::value::Kind::ListValue(prost_types::ListValue {
+                    values: v.iter().map(json_to_value).collect(),
+                })
+            }
+            serde_json::Value::Object(v) => {
+                prost_types::value::Kind::StructValue(prost_types::Struct {
+                    fields: v
+                        .iter()
+                        .map(|(k, v)| (k.clone(), json_to_value(v)))
+                        .collect(),
+                })
+            }
+        }),
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::api::auth::validator::RequestValidator;
+    use crate::api::auth::AuthID;
+    use crate::api::device_profile as device_profile_api;
+    use crate::storage::{device_profile, tenant, user};
+    use crate::test;
+    use chirpstack_api::api::device_profile_service_server::DeviceProfileService;
+
+    #[tokio::test]
+    async fn test_codec() {
+        let _guard = test::prepare().await;
+
+        // setup admin user
+        let u = user::User {
+            is_admin: true,
+            is_active: true,
+            email: "admin@admin".into(),
+            email_verified: true,
+            ..Default::default()
+        };
+        let u = user::create(u).await.unwrap();
+
+        // create tenant
+        let t = tenant::create(tenant::Tenant {
+            name: "test-tenant".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // setup the api
+        let service = Codec::new(RequestValidator::new());
+
+        // create
+        let create_req = get_request(
+            &u.id,
+            api::CreateCodecRequest {
+                codec: Some(api::Codec {
+                    tenant_id: t.id.to_string(),
+                    name: "test-codec".into(),
+                    ..Default::default()
+                }),
+            },
+        );
+        let create_resp = service.create(create_req).await.unwrap();
+        let codec_id = Uuid::from_str(&create_resp.get_ref().id).unwrap();
+
+        // get
+        let get_req = get_request(
+            &u.id,
+            api::GetCodecRequest {
+                id: codec_id.to_string(),
+            },
+        );
+        let get_resp = service.get(get_req).await.unwrap();
+        assert_eq!(
+            Some(api::Codec {
+                id: codec_id.to_string(),
+                tenant_id: t.id.to_string(),
+                name: "test-codec".into(),
+                ..Default::default()
+            }),
+            get_resp.get_ref().codec
+        );
+
+        // update
+        let update_req = get_request(
+            &u.id,
+            api::UpdateCodecRequest {
+                codec: Some(api::Codec {
+                    id: codec_id.to_string(),
+                    tenant_id: t.id.to_string(),
+                    name: "test-codec-updated".into(),
+                    description: "test description".into(),
+                }),
+            },
+        );
+        let _ = service.update(update_req).await.unwrap();
+
+        // list
+        let list_req = get_request(
+            &u.id,
+            api::ListCodecsRequest {
+                tenant_id: t.id.to_string(),
+                limit: 10,
+                search: "updated".into(),
+                ..Default::default()
+            },
+        );
+        let list_resp = service.list(list_req).await.unwrap();
+        let list_resp = list_resp.get_ref();
+        assert_eq!(1, list_resp.total_count);
+        assert_eq!(1, list_resp.result.len());
+        assert_eq!(codec_id.to_string(), list_resp.result[0].id);
+        assert_eq!(0, list_resp.result[0].latest_version);
+
+        // create version with failing test
+        let mut expected: prost_types::Struct = Default::default();
+        expected.fields.insert(
+            "temp".to_string(),
+            prost_types::Value {
+                kind: Some(prost_types::value::Kind::NumberValue(21.0)),
+            },
+        );
+        let script = r#"
+            function decodeUplink(input) {
+                return {
+                    data: {
+                        temp: input.bytes[0]
+                    }
+                };
+            }
+        "#
+        .to_string();
+
+        let create_version_req = get_request(
+            &u.id,
+            api::CreateCodecVersionRequest {
+                codec_version: Some(api::CodecVersion {
+                    codec_id: codec_id.to_string(),
+                    runtime: api::CodecRuntime::Js.into(),
+                    script: script.clone(),
+                    tests: vec![api::CodecTest {
+                        name: "temperature".into(),
+                        f_port: 10,
+                        data: vec![22],
+                        object: Some(expected.clone()),
+                    }],
+                    ..Default::default()
+                }),
+            },
+        );
+        let create_version_resp = service.create_version(create_version_req).await;
+        assert!(create_version_resp.is_err());
+
+        // create version
+        let create_version_req = get_request(
+            &u.id,
+            api::CreateCodecVersionRequest {
+                codec_version: Some(api::CodecVersion {
+                    codec_id: codec_id.to_string(),
+                    runtime: api::CodecRuntime::Js.into(),
+                    script: script.clone(),
+                    tests: vec![api::CodecTest {
+                        name: "temperature".into(),
+                        f_port: 10,
+                        data: vec![21],
+                        object: Some(expected.clone()),
+                    }],
+                    ..Default::default()
+                }),
+            },
+        );
+        let create_version_resp = service.create_version(create_version_req).await.unwrap();
+        let create_version_resp = create_version_resp.get_ref();
+        assert_eq!(1, create_version_resp.version);
+        let cv_id = create_version_resp.id.clone();
+
+        // get version
+        let get_version_req = get_request(&u.id, api::GetCodecVersionRequest { id: cv_id.clone() });
+        let get_version_resp = service.get_version(get_version_req).await.unwrap();
+        assert_eq!(
+            Some(api::CodecVersion {
+                id: cv_id.clone(),
+                codec_id: codec_id.to_string(),
+                version: 1,
+                runtime: api::CodecRuntime::Js.into(),
+                script: script.clone(),
+                tests: vec![api::CodecTest {
+                    name: "temperature".into(),
+                    f_port: 10,
+                    data: vec![21],
+                    object: Some(expected.clone()),
+                }],
+            }),
+            get_version_resp.get_ref().codec_version
+        );
+
+        // use version in device-profile
+        let dp = device_profile::create(device_profile::DeviceProfile {
+            tenant_id: t.id,
+            name: "test-dp".into(),
+            codec_version_id: Some(Uuid::from_str(&cv_id).unwrap()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert_eq!(script, dp.payload_codec_script);
+
+        // get device-profile returns the script of the codec version
+        let dp_service = device_profile_api::DeviceProfile::new(RequestValidator::new());
+        let get_dp_req = get_request(
+            &u.id,
+            api::GetDeviceProfileRequest {
+                id: dp.id.to_string(),
+            },
+        );
+        let get_dp_resp = dp_service.get(get_dp_req).await.unwrap();
+        let get_dp_resp = get_dp_resp.get_ref();
+        assert_eq!(
+            "",
+            get_dp_resp
+                .device_profile
+                .as_ref()
+                .unwrap()
+                .payload_codec_script
+        );
+        assert_eq!(script, get_dp_resp.codec_version_payload_codec_script);
+
+        // create second version
+        let create_version_req = get_request(
+            &u.id,
+            api::CreateCodecVersionRequest {
+                codec_version: Some(api::CodecVersion {
+                    codec_id: codec_id.to_string(),
+                    runtime: api::CodecRuntime::CayenneLpp.into(),
+                    ..Default::default()
+                }),
+            },
+        );
+        let create_version_resp = service.create_version(create_version_req).await.unwrap();
+        assert_eq!(2, create_version_resp.get_ref().version);
+
+        // list versions
+        let list_versions_req = get_request(
+            &u.id,
+            api::ListCodecVersionsRequest {
+                codec_id: codec_id.to_string(),
+                limit: 10,
+                ..Default::default()
+            },
+        );
+        let list_versions_resp = service.list_versions(list_versions_req).await.unwrap();
+        let list_versions_resp = list_versions_resp.get_ref();
+        assert_eq!(2, list_versions_resp.total_count);
+        assert_eq!(2, list_versions_resp.result[0].version);
+        assert_eq!(0, list_versions_resp.result[0].device_profile_count);
+        assert_eq!(1, list_versions_resp.result[1].version);
+        assert_eq!(1, list_versions_resp.result[1].device_profile_count);
+
+        // list device-profiles
+        let list_dps_req = get_request(
+            &u.id,
+            api::ListCodecDeviceProfilesRequest {
+                codec_id: codec_id.to_string(),
+                limit: 10,
+                ..Default::default()
+            },
+        );
+        let list_dps_resp = service.list_device_profiles(list_dps_req).await.unwrap();
+        let list_dps_resp = list_dps_resp.get_ref();
+        assert_eq!(1, list_dps_resp.total_count);
+        assert_eq!(
+            vec![api::CodecDeviceProfileListItem {
+                device_profile_id: dp.id.to_string(),
+                device_profile_name: "test-dp".into(),
+                codec_version_id: cv_id.clone(),
+                version: 1,
+            }],
+            list_dps_resp.result
+        );
+
+        // delete is not allowed while in use
+        let del_req = get_request(
+            &u.id,
+            api::DeleteCodecRequest {
+                id: codec_id.to_string(),
+            },
+        );
+        assert!(service.delete(del_req).await.is_err());
+
+        // roll with out of range version
+        let roll_req = get_request(
+            &u.id,
+            api::RollCodecDeviceProfilesRequest {
+                codec_id: codec_id.to_string(),
+                version: u32::MAX,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            tonic::Code::InvalidArgument,
+            service
+                .roll_device_profiles(roll_req)
+                .await
+                .unwrap_err()
+                .code()
+        );
+
+        // roll
+        let roll_req = get_request(
+            &u.id,
+            api::RollCodecDeviceProfilesRequest {
+                codec_id: codec_id.to_string(),
+                version: 2,
+                ..Default::default()
+            },
+        );
+        let roll_resp = service.roll_device_profiles(roll_req).await.unwrap();
+        assert_eq!(1, roll_resp.get_ref().updated_count);
+
+        let dp = device_profile::get(&dp.id).await.unwrap();
+        assert_eq!(codec_runtime::Codec::CAYENNE_LPP, dp.payload_codec_runtime);
+        assert_eq!("", dp.payload_codec_script);
+
+        // delete
+        device_profile::delete(&dp.id).await.unwrap();
+        let del_req = get_request(
+            &u.id,
+            api::DeleteCodecRequest {
+                id: codec_id.to_string(),
+            },
+        );
+        let _ = service.delete(del_req).await.unwrap();
+        let del_req = get_request(
+            &u.id,
+            api::DeleteCodecRequest {
+                id: codec_id.to_string(),
+            },
+        );
+        let del_resp = service.delete(del_req).await;
+        assert!(del_resp.is_err());
+    }
+
+    fn get_request<T>(user_id: &Uuid, req: T) -> Request<T> {
+        let mut req = Request::new(req);
+        req.extensions_mut().insert(AuthID::User(*user_id));
+        req
+    }
+}
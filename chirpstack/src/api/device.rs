@@ -16,12 +16,13 @@ use super::auth::validator;
 use super::error::ToStatus;
 use super::helpers::{self, FromProto, ToProto};
 use crate::storage::{
+    audit_log,
     device::{self, DeviceClass},
     device_keys, device_profile, device_queue,
     error::Error as StorageError,
-    fields, metrics,
+    fields, fragmentation_session, metrics,
 };
-use crate::{codec, devaddr::get_random_dev_addr};
+use crate::{adr, applayer, codec, devaddr::get_random_dev_addr, downlink};
 
 pub struct Device {
     validator: validator::RequestValidator,
@@ -189,7 +190,9 @@ impl DeviceService for Device {
             .await?;
 
         // update
-        let _ = device::update(device::Device {
+        let d_old = device::get(&dev_eui).await.map_err(|e| e.status())?;
+
+        let d = device::update(device::Device {
             dev_eui,
             application_id: app_id,
             device_profile_id: dp_id,
@@ -213,6 +216,27 @@ impl DeviceService for Device {
             req_d.is_disabled.to_string().parse().unwrap(),
         );
 
+        resp.extensions_mut().insert(
+            audit_log::Changes::new()
+                .field("application_id", &d_old.application_id, &d.application_id)
+                .field(
+                    "device_profile_id",
+                    &d_old.device_profile_id,
+                    &d.device_profile_id,
+                )
+                .field("name", &d_old.name, &d.name)
+                .field("description", &d_old.description, &d.description)
+                .field(
+                    "skip_fcnt_check",
+                    &d_old.skip_fcnt_check,
+                    &d.skip_fcnt_check,
+                )
+                .field("is_disabled", &d_old.is_disabled, &d.is_disabled)
+                .field("tags", &d_old.tags, &d.tags)
+                .field("variables", &d_old.variables, &d.variables)
+                .field("join_eui", &d_old.join_eui, &d.join_eui),
+        );
+
         Ok(resp)
     }
 
@@ -230,11 +254,17 @@ impl DeviceService for Device {
             )
             .await?;
 
+        let d = device::get(&dev_eui).await.map_err(|e| e.status())?;
+
         device::delete(&dev_eui).await.map_err(|e| e.status())?;
 
         let mut resp = Response::new(());
         resp.metadata_mut()
             .insert("x-log-dev_eui", req.dev_eui.parse().unwrap());
+        resp.metadata_mut().insert(
+            "x-log-application_id",
+            d.application_id.to_string().parse().unwrap(),
+        );
 
         Ok(resp)
     }
@@ -345,6 +375,11 @@ impl DeviceService for Device {
             } else {
                 AES128Key::null()
             },
+            gen_app_key: if !req_dk.gen_app_key.is_empty() {
+                AES128Key::from_str(&req_dk.gen_app_key).map_err(|e| e.status())?
+            } else {
+                AES128Key::null()
+            },
             ..Default::default()
         };
 
@@ -378,6 +413,7 @@ impl DeviceService for Device {
                 dev_eui: dk.dev_eui.to_string(),
                 nwk_key: dk.nwk_key.to_string(),
                 app_key: dk.app_key.to_string(),
+                gen_app_key: dk.gen_app_key.to_string(),
             }),
             created_at: Some(helpers::datetime_to_prost_timestamp(&dk.created_at)),
             updated_at: Some(helpers::datetime_to_prost_timestamp(&dk.updated_at)),
@@ -419,6 +455,11 @@ impl DeviceService for Device {
             } else {
                 AES128Key::null()
             },
+            gen_app_key: if !req_dk.gen_app_key.is_empty() {
+                AES128Key::from_str(&req_dk.gen_app_key).map_err(|e| e.status())?
+            } else {
+                AES128Key::null()
+            },
             ..Default::default()
         };
         let _ = device_keys::update(dk).await.map_err(|e| e.status())?;
@@ -1084,6 +1125,25 @@ impl DeviceService for Device {
             .map_err(|e| e.status())?;
         }
 
+        let expires_at: Option<DateTime<Utc>> = match (&req_qi.expires_at, &request.get_ref().ttl) {
+            (Some(_), Some(_)) => {
+                return Err(Status::invalid_argument(
+                    "expires_at and ttl can not be used together",
+                ));
+            }
+            (Some(v), None) => Some(SystemTime::try_from(*v).map_err(|e| e.status())?.into()),
+            (None, Some(v)) => {
+                let ttl = std::time::Duration::try_from(*v)
+                    .map_err(|e| Status::invalid_argument(e.to_string()))?;
+                Some(
+                    Utc::now()
+                        + chrono::Duration::from_std(ttl)
+                            .map_err(|e| Status::invalid_argument(e.to_string()))?,
+                )
+            }
+            (None, None) => None,
+        };
+
         let qi = device_queue::DeviceQueueItem {
             id: Uuid::new_v4(),
             dev_eui,
@@ -1096,16 +1156,60 @@ impl DeviceService for Device {
                 None
             },
             data,
+            expires_at,
+            priority: req_qi
+                .priority
+                .try_into()
+                .map_err(|_| Status::invalid_argument("priority is out of range"))?,
             ..Default::default()
         };
 
-        let qi = device_queue::enqueue_item(qi)
-            .await
-            .map_err(|e| e.status())?;
+        let resp = match &request.get_ref().fragmentation {
+            Some(frag) => {
+                if frag.frag_index > 3 {
+                    return Err(Status::invalid_argument(
+                        "frag_index must be between 0 and 3",
+                    ));
+                }
 
-        let mut resp = Response::new(api::EnqueueDeviceQueueItemResponse {
-            id: qi.id.to_string(),
-        });
+                let fs = fragmentation_session::FragmentationSession {
+                    frag_index: frag.frag_index as i16,
+                    fragment_size: frag
+                        .fragment_size
+                        .try_into()
+                        .map_err(|_| Status::invalid_argument("fragment_size is out of range"))?,
+                    redundancy: frag
+                        .redundancy
+                        .try_into()
+                        .map_err(|_| Status::invalid_argument("redundancy is out of range"))?,
+                    ..Default::default()
+                };
+
+                let (fs, items) = applayer::fragmentation::enqueue(fs, qi)
+                    .await
+                    .map_err(|e| e.status())?;
+
+                api::EnqueueDeviceQueueItemResponse {
+                    id: items
+                        .first()
+                        .map(|qi| qi.id.to_string())
+                        .unwrap_or_default(),
+                    fragmentation_session_id: fs.id.to_string(),
+                }
+            }
+            None => {
+                let qi = device_queue::enqueue_item(qi)
+                    .await
+                    .map_err(|e| e.status())?;
+
+                api::EnqueueDeviceQueueItemResponse {
+                    id: qi.id.to_string(),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let mut resp = Response::new(resp);
         resp.metadata_mut()
             .insert("x-log-dev_eui", req_qi.dev_eui.parse().unwrap());
 
@@ -1169,6 +1273,64 @@ impl DeviceService for Device {
                     is_pending: qi.is_pending,
                     f_cnt_down: qi.f_cnt_down.unwrap_or(0) as u32,
                     is_encrypted: qi.is_encrypted,
+                    expires_at: qi
+                        .expires_at
+                        .as_ref()
+                        .map(helpers::datetime_to_prost_timestamp),
+                    priority: qi.priority as u32,
+                })
+                .collect(),
+        });
+        resp.metadata_mut()
+            .insert("x-log-dev_eui", req.dev_eui.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn get_queue_forecast(
+        &self,
+        request: Request<api::GetDeviceQueueForecastRequest>,
+    ) -> Result<Response<api::GetDeviceQueueForecastResponse>, Status> {
+        let req = request.get_ref();
+        let dev_eui = EUI64::from_str(&req.dev_eui).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateDeviceQueueAccess::new(validator::Flag::List, dev_eui),
+            )
+            .await?;
+
+        let ping_slot_count = match req.ping_slot_count {
+            0 => 10,
+            v => cmp::min(v, 100),
+        } as usize;
+
+        let forecast = downlink::forecast::get_for_dev_eui(&dev_eui, ping_slot_count)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::GetDeviceQueueForecastResponse {
+            class_enabled: forecast.enabled_class.to_proto().into(),
+            scheduler_run_after: forecast
+                .scheduler_run_after
+                .as_ref()
+                .map(helpers::datetime_to_prost_timestamp),
+            scheduler_locked: forecast.scheduler_locked,
+            ping_slots: forecast
+                .ping_slots
+                .iter()
+                .map(helpers::datetime_to_prost_timestamp)
+                .collect(),
+            queue_items: forecast
+                .queue_items
+                .iter()
+                .map(|qi| api::DeviceQueueItemForecast {
+                    id: qi.id.to_string(),
+                    scheduled_at: qi
+                        .scheduled_at
+                        .as_ref()
+                        .map(helpers::datetime_to_prost_timestamp),
                 })
                 .collect(),
         });
@@ -1214,6 +1376,209 @@ impl DeviceService for Device {
 
         Ok(resp)
     }
+
+    async fn get_fragmentation_session(
+        &self,
+        request: Request<api::GetDeviceFragmentationSessionRequest>,
+    ) -> Result<Response<api::GetDeviceFragmentationSessionResponse>, Status> {
+        let req = request.get_ref();
+        let dev_eui = EUI64::from_str(&req.dev_eui).map_err(|e| e.status())?;
+        let id = Uuid::from_str(&req.id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateDeviceQueueAccess::new(validator::Flag::List, dev_eui),
+            )
+            .await?;
+
+        let fs = fragmentation_session::get(&id)
+            .await
+            .map_err(|e| e.status())?;
+        if fs.dev_eui != dev_eui {
+            return Err(StorageError::NotFound(id.to_string()).status());
+        }
+
+        let mut resp = Response::new(api::GetDeviceFragmentationSessionResponse {
+            id: fs.id.to_string(),
+            created_at: Some(helpers::datetime_to_prost_timestamp(&fs.created_at)),
+            updated_at: Some(helpers::datetime_to_prost_timestamp(&fs.updated_at)),
+            completed_at: fs
+                .completed_at
+                .as_ref()
+                .map(helpers::datetime_to_prost_timestamp),
+            status: fs.status.to_proto().into(),
+            frag_index: fs.frag_index as u32,
+            nb_frag: fs.nb_frag as u32,
+            fragment_size: fs.fragment_size as u32,
+            redundancy: fs.redundancy as u32,
+            padding: fs.padding as u32,
+            nb_frag_sent: fs.nb_frag_sent as u32,
+            nb_frag_received: fs.nb_frag_received.unwrap_or_default() as u32,
+            missing_frag: fs.missing_frag.unwrap_or_default() as u32,
+        });
+        resp.metadata_mut()
+            .insert("x-log-dev_eui", req.dev_eui.parse().unwrap());
+        resp.metadata_mut()
+            .insert("x-log-fragmentation_session_id", req.id.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn simulate_adr(
+        &self,
+        request: Request<api::SimulateDeviceAdrRequest>,
+    ) -> Result<Response<api::SimulateDeviceAdrResponse>, Status> {
+        let req = request.get_ref();
+        let dev_eui = EUI64::from_str(&req.dev_eui).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateDeviceAccess::new(validator::Flag::Read, dev_eui),
+            )
+            .await?;
+
+        let d = device::get(&dev_eui).await.map_err(|e| e.status())?;
+        let dp = device_profile::get(&d.device_profile_id)
+            .await
+            .map_err(|e| e.status())?;
+        let ds = d.get_device_session().map_err(|e| e.status())?;
+
+        let algorithm_id = if req.algorithm_id.is_empty() {
+            dp.adr_algorithm_id.clone()
+        } else {
+            req.algorithm_id.clone()
+        };
+        if !adr::get_algorithms().await.contains_key(&algorithm_id) {
+            return Err(Status::not_found(format!(
+                "ADR algorithm {} does not exist",
+                algorithm_id
+            )));
+        }
+
+        if ds.uplink_adr_history.is_empty() {
+            return Err(Status::failed_precondition(
+                "Device has no uplink history to simulate ADR",
+            ));
+        }
+
+        let overrides = req.overrides.unwrap_or_default();
+        let to_u8 = |field: &str, v: u32| -> Result<u8, Status> {
+            u8::try_from(v)
+                .map_err(|_| Status::invalid_argument(format!("Invalid {} value: {}", field, v)))
+        };
+
+        let dr = match overrides.dr {
+            Some(v) => to_u8("dr", v)?,
+            None => ds.dr as u8,
+        };
+        let mut adr_req =
+            adr::Request::new(&ds.region_config_id, &d, &dp, ds, dr).map_err(|e| e.status())?;
+        if let Some(v) = overrides.adr {
+            adr_req.adr = v;
+        }
+        if let Some(v) = overrides.tx_power_index {
+            adr_req.tx_power_index = to_u8("tx_power_index", v)?;
+        }
+        if let Some(v) = overrides.nb_trans {
+            adr_req.nb_trans = to_u8("nb_trans", v)?;
+        }
+        if let Some(v) = overrides.installation_margin {
+            adr_req.installation_margin = v;
+        }
+        if let Some(v) = overrides.min_dr {
+            adr_req.min_dr = to_u8("min_dr", v)?;
+        }
+        if let Some(v) = overrides.max_dr {
+            adr_req.max_dr = to_u8("max_dr", v)?;
+        }
+
+        let adr_resp = adr::handle(&algorithm_id, &adr_req).await;
+
+        let mut resp = Response::new(api::SimulateDeviceAdrResponse {
+            dr: adr_resp.dr.into(),
+            tx_power_index: adr_resp.tx_power_index.into(),
+            nb_trans: adr_resp.nb_trans.into(),
+            uplink_history_count: adr_req.uplink_history.len() as u32,
+            required_snr_for_dr: adr_req.required_snr_for_dr,
+            installation_margin: adr_req.installation_margin,
+            ..Default::default()
+        });
+
+        // The intermediate values are specific to the default algorithm, other algorithms
+        // (e.g. gateway diversity or LR-FHSS) use a different calculation.
+        if algorithm_id == "default" {
+            let a = adr::default::Algorithm::new();
+            let (snr_margin, nb_step) = a.get_snr_margin_and_nb_step(&adr_req);
+
+            let resp = resp.get_mut();
+            resp.max_snr = a.get_max_snr(&adr_req);
+            resp.snr_margin = snr_margin;
+            resp.nb_step = nb_step as i32;
+        }
+        resp.get_mut().algorithm_id = algorithm_id;
+        resp.metadata_mut()
+            .insert("x-log-dev_eui", req.dev_eui.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn force_clock_resync(
+        &self,
+        request: Request<api::ForceDeviceClockResyncRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.get_ref();
+        let dev_eui = EUI64::from_str(&req.dev_eui).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateDeviceAccess::new(validator::Flag::Update, dev_eui),
+            )
+            .await?;
+
+        let nb_transmissions = u8::try_from(req.nb_transmissions)
+            .map_err(|_| Status::invalid_argument("nb_transmissions must be between 1 and 7"))?;
+
+        applayer::clocksync::force_resync(&dev_eui, nb_transmissions)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-dev_eui", req.dev_eui.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn set_clock_sync_periodicity(
+        &self,
+        request: Request<api::SetDeviceClockSyncPeriodicityRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.get_ref();
+        let dev_eui = EUI64::from_str(&req.dev_eui).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateDeviceAccess::new(validator::Flag::Update, dev_eui),
+            )
+            .await?;
+
+        let periodicity = u8::try_from(req.periodicity)
+            .map_err(|_| Status::invalid_argument("periodicity must be between 0 and 15"))?;
+
+        applayer::clocksync::set_periodicity(&dev_eui, periodicity)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-dev_eui", req.dev_eui.parse().unwrap());
+
+        Ok(resp)
+    }
 }
 
 #[cfg(test)]
@@ -1221,7 +1586,7 @@ pub mod test {
     use super::*;
     use crate::api::auth::validator::RequestValidator;
     use crate::api::auth::AuthID;
-    use crate::storage::{application, device, tenant, user};
+    use crate::storage::{api_key, application, device, tenant, user};
     use crate::test;
     use lrwn::NetID;
 
@@ -1262,6 +1627,7 @@ pub mod test {
         let dp = device_profile::create(device_profile::DeviceProfile {
             name: "test-dp".into(),
             tenant_id: t.id,
+            adr_algorithm_id: "default".into(),
             ..Default::default()
         })
         .await
@@ -1364,6 +1730,7 @@ pub mod test {
                     dev_eui: "0102030405060708".into(),
                     nwk_key: "01020304050607080102030405060708".into(),
                     app_key: "02020304050607080202030405060708".into(),
+                    gen_app_key: "04020304050607080402030405060708".into(),
                 }),
             },
         );
@@ -1382,6 +1749,7 @@ pub mod test {
                 dev_eui: "0102030405060708".into(),
                 nwk_key: "01020304050607080102030405060708".into(),
                 app_key: "02020304050607080202030405060708".into(),
+                gen_app_key: "04020304050607080402030405060708".into(),
             }),
             get_keys_resp.get_ref().device_keys
         );
@@ -1394,6 +1762,7 @@ pub mod test {
                     dev_eui: "0102030405060708".into(),
                     nwk_key: "01020304050607080102030405060708".into(),
                     app_key: "03020304050607080302030405060708".into(),
+                    gen_app_key: "04020304050607080402030405060708".into(),
                 }),
             },
         );
@@ -1412,6 +1781,7 @@ pub mod test {
                 dev_eui: "0102030405060708".into(),
                 nwk_key: "01020304050607080102030405060708".into(),
                 app_key: "03020304050607080302030405060708".into(),
+                gen_app_key: "04020304050607080402030405060708".into(),
             }),
             get_keys_resp.get_ref().device_keys
         );
@@ -1517,6 +1887,160 @@ pub mod test {
             .unwrap();
         assert_eq!(1, get_next_f_cnt_resp.get_ref().f_cnt_down);
 
+        // simulate adr
+        let mut ds = dev.get_device_session().unwrap().clone();
+        ds.region_config_id = "eu868".into();
+        ds.adr = true;
+        ds.uplink_adr_history = (0..20)
+            .map(|i| internal::UplinkAdrHistory {
+                f_cnt: i,
+                max_snr: 10.0,
+                max_rssi: -50,
+                tx_power_index: 0,
+                gateway_count: 1,
+            })
+            .collect();
+        device::partial_update(
+            dev.dev_eui,
+            &device::DeviceChangeset {
+                device_session: Some(Some(ds)),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let simulate_adr_req = get_request(
+            &u.id,
+            api::SimulateDeviceAdrRequest {
+                dev_eui: "0102030405060708".into(),
+                ..Default::default()
+            },
+        );
+        let simulate_adr_resp = service.simulate_adr(simulate_adr_req).await.unwrap();
+        assert_eq!(
+            api::SimulateDeviceAdrResponse {
+                algorithm_id: "default".into(),
+                dr: 5,
+                tx_power_index: 1,
+                nb_trans: 1,
+                uplink_history_count: 20,
+                max_snr: 10.0,
+                required_snr_for_dr: -20.0,
+                installation_margin: 10.0,
+                snr_margin: 20.0,
+                nb_step: 6,
+            },
+            *simulate_adr_resp.get_ref()
+        );
+
+        // simulate adr with overrides
+        let simulate_adr_req = get_request(
+            &u.id,
+            api::SimulateDeviceAdrRequest {
+                dev_eui: "0102030405060708".into(),
+                algorithm_id: "default".into(),
+                overrides: Some(api::SimulateDeviceAdrOverrides {
+                    installation_margin: Some(25.0),
+                    ..Default::default()
+                }),
+            },
+        );
+        let simulate_adr_resp = service.simulate_adr(simulate_adr_req).await.unwrap();
+        assert_eq!(1, simulate_adr_resp.get_ref().dr);
+        assert_eq!(0, simulate_adr_resp.get_ref().tx_power_index);
+        assert_eq!(1, simulate_adr_resp.get_ref().nb_step);
+
+        // simulate adr with the ADR bit overridden
+        let simulate_adr_req = get_request(
+            &u.id,
+            api::SimulateDeviceAdrRequest {
+                dev_eui: "0102030405060708".into(),
+                algorithm_id: "default".into(),
+                overrides: Some(api::SimulateDeviceAdrOverrides {
+                    adr: Some(false),
+                    ..Default::default()
+                }),
+            },
+        );
+        let simulate_adr_resp = service.simulate_adr(simulate_adr_req).await.unwrap();
+        assert_eq!(0, simulate_adr_resp.get_ref().dr);
+        assert_eq!(0, simulate_adr_resp.get_ref().tx_power_index);
+
+        // simulate adr with an other algorithm, the default algorithm values are not set
+        let simulate_adr_req = get_request(
+            &u.id,
+            api::SimulateDeviceAdrRequest {
+                dev_eui: "0102030405060708".into(),
+                algorithm_id: "gateway_diversity".into(),
+                ..Default::default()
+            },
+        );
+        let simulate_adr_resp = service.simulate_adr(simulate_adr_req).await.unwrap();
+        let simulate_adr_resp = simulate_adr_resp.get_ref();
+        assert_eq!("gateway_diversity", simulate_adr_resp.algorithm_id);
+        assert_eq!(20, simulate_adr_resp.uplink_history_count);
+        assert_eq!(0.0, simulate_adr_resp.max_snr);
+        assert_eq!(0.0, simulate_adr_resp.snr_margin);
+        assert_eq!(0, simulate_adr_resp.nb_step);
+
+        // simulate adr with unknown algorithm
+        let simulate_adr_req = get_request(
+            &u.id,
+            api::SimulateDeviceAdrRequest {
+                dev_eui: "0102030405060708".into(),
+                algorithm_id: "unknown".into(),
+                ..Default::default()
+            },
+        );
+        let simulate_adr_resp = service.simulate_adr(simulate_adr_req).await;
+        assert_eq!(tonic::Code::NotFound, simulate_adr_resp.unwrap_err().code());
+
+        // simulate adr with an out of range override
+        let simulate_adr_req = get_request(
+            &u.id,
+            api::SimulateDeviceAdrRequest {
+                dev_eui: "0102030405060708".into(),
+                overrides: Some(api::SimulateDeviceAdrOverrides {
+                    nb_trans: Some(256),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+        let simulate_adr_resp = service.simulate_adr(simulate_adr_req).await;
+        assert_eq!(
+            tonic::Code::InvalidArgument,
+            simulate_adr_resp.unwrap_err().code()
+        );
+
+        // simulate adr without uplink history
+        let mut ds = dev.get_device_session().unwrap().clone();
+        ds.region_config_id = "eu868".into();
+        ds.uplink_adr_history = vec![];
+        device::partial_update(
+            dev.dev_eui,
+            &device::DeviceChangeset {
+                device_session: Some(Some(ds)),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let simulate_adr_req = get_request(
+            &u.id,
+            api::SimulateDeviceAdrRequest {
+                dev_eui: "0102030405060708".into(),
+                ..Default::default()
+            },
+        );
+        let simulate_adr_resp = service.simulate_adr(simulate_adr_req).await;
+        assert_eq!(
+            tonic::Code::FailedPrecondition,
+            simulate_adr_resp.unwrap_err().code()
+        );
+
         // deactivate
         let deactivate_req = get_request(
             &u.id,
@@ -1570,6 +2094,7 @@ pub mod test {
             &device::DeviceChangeset {
                 device_session: Some(Some(internal::DeviceSession {
                     dev_addr: vec![1, 2, 3, 4],
+                    region_config_id: "eu868".into(),
                     app_s_key: Some(common::KeyEnvelope {
                         kek_label: "test-key".into(),
                         aes_key: vec![8, 7, 6, 5, 4, 3, 2, 1, 8, 7, 6, 5, 4, 3, 2, 1],
@@ -1627,6 +2152,8 @@ pub mod test {
                     data: vec![3, 2, 1],
                     ..Default::default()
                 }),
+                ttl: None,
+                fragmentation: None,
             },
         );
         let _ = service.enqueue(enqueue_req).await.unwrap();
@@ -1643,6 +2170,8 @@ pub mod test {
                     is_encrypted: true,
                     ..Default::default()
                 }),
+                ttl: None,
+                fragmentation: None,
             },
         );
         let _ = service.enqueue(enqueue_req).await.unwrap();
@@ -1679,6 +2208,62 @@ pub mod test {
             .unwrap();
         assert_eq!(11, get_next_f_cnt_resp.get_ref().f_cnt_down);
 
+        // enqueue with priority and ttl
+        let enqueue_req = get_request(
+            &u.id,
+            api::EnqueueDeviceQueueItemRequest {
+                queue_item: Some(api::DeviceQueueItem {
+                    dev_eui: "0102030405060708".into(),
+                    f_port: 3,
+                    data: vec![4, 5, 6],
+                    priority: 10,
+                    ..Default::default()
+                }),
+                ttl: Some(prost_types::Duration {
+                    seconds: 60,
+                    nanos: 0,
+                }),
+                fragmentation: None,
+            },
+        );
+        let _ = service.enqueue(enqueue_req).await.unwrap();
+
+        let get_queue_req = get_request(
+            &u.id,
+            api::GetDeviceQueueItemsRequest {
+                dev_eui: "0102030405060708".into(),
+                count_only: false,
+            },
+        );
+        let get_queue_resp = service.get_queue(get_queue_req).await.unwrap();
+        let get_queue_resp = get_queue_resp.get_ref();
+        assert_eq!(3, get_queue_resp.total_count);
+        assert_eq!(vec![4, 5, 6], get_queue_resp.result[0].data);
+        assert_eq!(10, get_queue_resp.result[0].priority);
+        assert!(get_queue_resp.result[0].expires_at.is_some());
+        assert_eq!(vec![3, 2, 1], get_queue_resp.result[1].data);
+        assert!(get_queue_resp.result[1].expires_at.is_none());
+
+        // enqueue with expires_at and ttl
+        let enqueue_req = get_request(
+            &u.id,
+            api::EnqueueDeviceQueueItemRequest {
+                queue_item: Some(api::DeviceQueueItem {
+                    dev_eui: "0102030405060708".into(),
+                    f_port: 3,
+                    data: vec![4, 5, 6],
+                    expires_at: Some(helpers::datetime_to_prost_timestamp(&Utc::now())),
+                    ..Default::default()
+                }),
+                ttl: Some(prost_types::Duration {
+                    seconds: 60,
+                    nanos: 0,
+                }),
+                fragmentation: None,
+            },
+        );
+        assert!(service.enqueue(enqueue_req).await.is_err());
+
         // flush queue
         let flush_queue_req = get_request(
             &u.id,
@@ -1699,6 +2284,258 @@ pub mod test {
         assert_eq!(0, get_queue_resp.total_count);
         assert_eq!(0, get_queue_resp.result.len());
 
+        // enqueue fragmented
+        let enqueue_req = get_request(
+            &u.id,
+            api::EnqueueDeviceQueueItemRequest {
+                queue_item: Some(api::DeviceQueueItem {
+                    dev_eui: "0102030405060708".into(),
+                    f_port: 3,
+                    data: (0..25).collect(),
+                    ..Default::default()
+                }),
+                ttl: None,
+                fragmentation: Some(api::DeviceQueueItemFragmentation {
+                    fragment_size: 10,
+                    redundancy: 2,
+                    frag_index: 1,
+                }),
+            },
+        );
+        let enqueue_resp = service.enqueue(enqueue_req).await.unwrap();
+        let enqueue_resp = enqueue_resp.get_ref();
+
+        let get_queue_req = get_request(
+            &u.id,
+            api::GetDeviceQueueItemsRequest {
+                dev_eui: "0102030405060708".into(),
+                count_only: false,
+            },
+        );
+        let get_queue_resp = service.get_queue(get_queue_req).await.unwrap();
+        let get_queue_resp = get_queue_resp.get_ref();
+        // setup + 3 fragments + 2 redundancy fragments + status
+        assert_eq!(7, get_queue_resp.total_count);
+        assert_eq!(enqueue_resp.id, get_queue_resp.result[0].id);
+        assert_eq!(201, get_queue_resp.result[0].f_port);
+        assert_eq!(
+            vec![0x02, 0x10, 0x03, 0x00, 0x0a, 0x00, 0x05, 0x00, 0x00, 0x00, 0x00],
+            get_queue_resp.result[0].data
+        );
+        assert_eq!(
+            vec![0x08, 0x01, 0x40, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+            get_queue_resp.result[1].data
+        );
+        assert_eq!(vec![0x01, 0x03], get_queue_resp.result[6].data);
+
+        // get queue forecast (Class-A, transmit time is unknown)
+        let get_forecast_req = get_request(
+            &u.id,
+            api::GetDeviceQueueForecastRequest {
+                dev_eui: "0102030405060708".into(),
+                ping_slot_count: 0,
+            },
+        );
+        let get_forecast_resp = service.get_queue_forecast(get_forecast_req).await.unwrap();
+        let get_forecast_resp = get_forecast_resp.get_ref();
+        assert_eq!(
+            common::DeviceClass::ClassA,
+            get_forecast_resp.class_enabled()
+        );
+        assert!(!get_forecast_resp.scheduler_locked);
+        assert!(get_forecast_resp.ping_slots.is_empty());
+        assert_eq!(7, get_forecast_resp.queue_items.len());
+        assert_eq!(enqueue_resp.id, get_forecast_resp.queue_items[0].id);
+        assert!(get_forecast_resp
+            .queue_items
+            .iter()
+            .all(|qi| qi.scheduled_at.is_none()));
+
+        let get_fs_req = get_request(
+            &u.id,
+            api::GetDeviceFragmentationSessionRequest {
+                dev_eui: "0102030405060708".into(),
+                id: enqueue_resp.fragmentation_session_id.clone(),
+            },
+        );
+        let get_fs_resp = service.get_fragmentation_session(get_fs_req).await.unwrap();
+        let get_fs_resp = get_fs_resp.get_ref();
+        assert_eq!(
+            api::FragmentationSessionStatus::FragmentationSessionPending,
+            get_fs_resp.status()
+        );
+        assert_eq!(1, get_fs_resp.frag_index);
+        assert_eq!(3, get_fs_resp.nb_frag);
+        assert_eq!(5, get_fs_resp.padding);
+        assert_eq!(0, get_fs_resp.nb_frag_sent);
+
+        // enqueue fragmented with active frag_index
+        let enqueue_req = get_request(
+            &u.id,
+            api::EnqueueDeviceQueueItemRequest {
+                queue_item: Some(api::DeviceQueueItem {
+                    dev_eui: "0102030405060708".into(),
+                    f_port: 3,
+                    data: vec![1, 2, 3],
+                    ..Default::default()
+                }),
+                ttl: None,
+                fragmentation: Some(api::DeviceQueueItemFragmentation {
+                    fragment_size: 10,
+                    redundancy: 0,
+                    frag_index: 1,
+                }),
+            },
+        );
+        assert!(service.enqueue(enqueue_req).await.is_err());
+
+        // enqueue fragmented with out of range frag_index
+        let enqueue_req = get_request(
+            &u.id,
+            api::EnqueueDeviceQueueItemRequest {
+                queue_item: Some(api::DeviceQueueItem {
+                    dev_eui: "0102030405060708".into(),
+                    f_port: 3,
+                    data: vec![1, 2, 3],
+                    ..Default::default()
+                }),
+                ttl: None,
+                fragmentation: Some(api::DeviceQueueItemFragmentation {
+                    fragment_size: 10,
+                    redundancy: 0,
+                    frag_index: 257,
+                }),
+            },
+        );
+        assert_eq!(
+            tonic::Code::InvalidArgument,
+            service.enqueue(enqueue_req).await.unwrap_err().code()
+        );
+
+        // force clock resync
+        let force_resync_req = get_request(
+            &u.id,
+            api::ForceDeviceClockResyncRequest {
+                dev_eui: "0102030405060708".into(),
+                nb_transmissions: 2,
+            },
+        );
+        let _ = service.force_clock_resync(force_resync_req).await.unwrap();
+
+        let force_resync_req = get_request(
+            &u.id,
+            api::ForceDeviceClockResyncRequest {
+                dev_eui: "0102030405060708".into(),
+                nb_transmissions: 8,
+            },
+        );
+        assert!(service.force_clock_resync(force_resync_req).await.is_err());
+
+        // nb_transmissions which would wrap around to a valid u8 value
+        let force_resync_req = get_request(
+            &u.id,
+            api::ForceDeviceClockResyncRequest {
+                dev_eui: "0102030405060708".into(),
+                nb_transmissions: 258,
+            },
+        );
+        assert_eq!(
+            tonic::Code::InvalidArgument,
+            service
+                .force_clock_resync(force_resync_req)
+                .await
+                .unwrap_err()
+                .code()
+        );
+
+        // set clock sync periodicity
+        let set_periodicity_req = get_request(
+            &u.id,
+            api::SetDeviceClockSyncPeriodicityRequest {
+                dev_eui: "0102030405060708".into(),
+                periodicity: 4,
+            },
+        );
+        let _ = service
+            .set_clock_sync_periodicity(set_periodicity_req)
+            .await
+            .unwrap();
+
+        // a key restricted to enqueueing can not change the clock sync configuration
+        let ak_enqueue = api_key::create(api_key::ApiKey {
+            name: "enqueue-only".into(),
+            tenant_id: Some(t.id),
+            scope: fields::ApiKeyScope::DEVICE_QUEUE_ENQUEUE,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let mut force_resync_req = Request::new(api::ForceDeviceClockResyncRequest {
+            dev_eui: "0102030405060708".into(),
+            nb_transmissions: 2,
+        });
+        force_resync_req
+            .extensions_mut()
+            .insert(AuthID::Key(ak_enqueue.id));
+        assert_eq!(
+            tonic::Code::Unauthenticated,
+            service
+                .force_clock_resync(force_resync_req)
+                .await
+                .unwrap_err()
+                .code()
+        );
+        let mut set_periodicity_req = Request::new(api::SetDeviceClockSyncPeriodicityRequest {
+            dev_eui: "0102030405060708".into(),
+            periodicity: 4,
+        });
+        set_periodicity_req
+            .extensions_mut()
+            .insert(AuthID::Key(ak_enqueue.id));
+        assert_eq!(
+            tonic::Code::Unauthenticated,
+            service
+                .set_clock_sync_periodicity(set_periodicity_req)
+                .await
+                .unwrap_err()
+                .code()
+        );
+
+        // periodicity which would wrap around to a valid u8 value
+        let set_periodicity_req = get_request(
+            &u.id,
+            api::SetDeviceClockSyncPeriodicityRequest {
+                dev_eui: "0102030405060708".into(),
+                periodicity: 260,
+            },
+        );
+        assert_eq!(
+            tonic::Code::InvalidArgument,
+            service
+                .set_clock_sync_periodicity(set_periodicity_req)
+                .await
+                .unwrap_err()
+                .code()
+        );
+
+        let get_queue_req = get_request(
+            &u.id,
+            api::GetDeviceQueueItemsRequest {
+                dev_eui: "0102030405060708".into(),
+                count_only: false,
+            },
+        );
+        let get_queue_resp = service.get_queue(get_queue_req).await.unwrap();
+        let get_queue_resp = get_queue_resp.get_ref();
+        let clock_sync_items: Vec<_> = get_queue_resp
+            .result
+            .iter()
+            .filter(|qi| qi.f_port == lrwn::LA_FPORT_CLOCK_SYNC as u32)
+            .collect();
+        assert_eq!(2, clock_sync_items.len());
+        assert_eq!(vec![0x03, 0x02], clock_sync_items[0].data);
+        assert_eq!(vec![0x02, 0x04], clock_sync_items[1].data);
+
         // delete
         let del_req = get_request(
             &u.id,
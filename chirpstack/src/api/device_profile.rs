@@ -11,7 +11,7 @@ use super::error::ToStatus;
 use super::helpers;
 use super::helpers::{FromProto, ToProto};
 use crate::adr;
-use crate::storage::{device_profile, fields};
+use crate::storage::{audit_log, device_profile, fields};
 
 pub struct DeviceProfile {
     validator: validator::RequestValidator,
@@ -44,6 +44,18 @@ impl DeviceProfileService for DeviceProfile {
             )
             .await?;
 
+        let codec_version_id = if req_dp.codec_version_id.is_empty() {
+            None
+        } else {
+            if !req_dp.payload_codec_script.is_empty() {
+                return Err(Status::invalid_argument(
+                    "codec_version_id and payload_codec_script can not both be set",
+                ));
+            }
+
+            Some(Uuid::from_str(&req_dp.codec_version_id).map_err(|e| e.status())?)
+        };
+
         let mut dp = device_profile::DeviceProfile {
             tenant_id,
             name: req_dp.name.clone(),
@@ -114,6 +126,9 @@ impl DeviceProfileService for DeviceProfile {
             relay_overall_limit_bucket_size: req_dp.relay_overall_limit_bucket_size as i16,
             allow_roaming: req_dp.allow_roaming,
             rx1_delay: req_dp.rx1_delay as i16,
+            codec_version_id,
+            gateway_selection_strategy: req_dp.gateway_selection_strategy().from_proto(),
+            supports_clock_sync: req_dp.supports_clock_sync,
             ..Default::default()
         };
 
@@ -145,6 +160,11 @@ impl DeviceProfileService for DeviceProfile {
             .await?;
 
         let dp = device_profile::get(&dp_id).await.map_err(|e| e.status())?;
+        let codec_version_payload_codec_script = if dp.codec_version_id.is_some() {
+            dp.payload_codec_script.clone()
+        } else {
+            "".into()
+        };
 
         let mut resp = Response::new(api::GetDeviceProfileResponse {
             device_profile: Some(api::DeviceProfile {
@@ -157,7 +177,12 @@ impl DeviceProfileService for DeviceProfile {
                 reg_params_revision: dp.reg_params_revision.to_proto().into(),
                 adr_algorithm_id: dp.adr_algorithm_id,
                 payload_codec_runtime: dp.payload_codec_runtime.to_proto().into(),
-                payload_codec_script: dp.payload_codec_script,
+                // The script of a codec version must not be set on update.
+                payload_codec_script: if dp.codec_version_id.is_some() {
+                    "".into()
+                } else {
+                    dp.payload_codec_script
+                },
                 flush_queue_on_activate: dp.flush_queue_on_activate,
                 uplink_interval: dp.uplink_interval as u32,
                 device_status_req_interval: dp.device_status_req_interval as u32,
@@ -216,9 +241,16 @@ impl DeviceProfileService for DeviceProfile {
                 relay_overall_limit_bucket_size: dp.relay_overall_limit_bucket_size as u32,
                 allow_roaming: dp.allow_roaming,
                 rx1_delay: dp.rx1_delay as u32,
+                codec_version_id: dp
+                    .codec_version_id
+                    .map(|v| v.to_string())
+                    .unwrap_or_default(),
+                gateway_selection_strategy: dp.gateway_selection_strategy.to_proto().into(),
+                supports_clock_sync: dp.supports_clock_sync,
             }),
             created_at: Some(helpers::datetime_to_prost_timestamp(&dp.created_at)),
             updated_at: Some(helpers::datetime_to_prost_timestamp(&dp.updated_at)),
+            codec_version_payload_codec_script,
         });
         resp.metadata_mut()
             .insert("x-log-device_profile_id", req.id.parse().unwrap());
@@ -245,8 +277,22 @@ impl DeviceProfileService for DeviceProfile {
             )
             .await?;
 
+        let codec_version_id = if req_dp.codec_version_id.is_empty() {
+            None
+        } else {
+            if !req_dp.payload_codec_script.is_empty() {
+                return Err(Status::invalid_argument(
+                    "codec_version_id and payload_codec_script can not both be set",
+                ));
+            }
+
+            Some(Uuid::from_str(&req_dp.codec_version_id).map_err(|e| e.status())?)
+        };
+
+        let dp_old = device_profile::get(&dp_id).await.map_err(|e| e.status())?;
+
         // update
-        let _ = device_profile::update(device_profile::DeviceProfile {
+        let dp = device_profile::update(device_profile::DeviceProfile {
             id: dp_id,
             name: req_dp.name.clone(),
             description: req_dp.description.clone(),
@@ -316,6 +362,9 @@ impl DeviceProfileService for DeviceProfile {
             relay_overall_limit_bucket_size: req_dp.relay_overall_limit_bucket_size as i16,
             allow_roaming: req_dp.allow_roaming,
             rx1_delay: req_dp.rx1_delay as i16,
+            codec_version_id,
+            gateway_selection_strategy: req_dp.gateway_selection_strategy().from_proto(),
+            supports_clock_sync: req_dp.supports_clock_sync,
             ..Default::default()
         })
         .await
@@ -325,6 +374,79 @@ impl DeviceProfileService for DeviceProfile {
         resp.metadata_mut()
             .insert("x-log-device_profile_id", req_dp.id.parse().unwrap());
 
+        resp.extensions_mut().insert(
+            audit_log::Changes::new()
+                .field("name", &dp_old.name, &dp.name)
+                .field("description", &dp_old.description, &dp.description)
+                .field("region", &dp_old.region.to_string(), &dp.region.to_string())
+                .field(
+                    "region_config_id",
+                    &dp_old.region_config_id,
+                    &dp.region_config_id,
+                )
+                .field(
+                    "mac_version",
+                    &dp_old.mac_version.to_string(),
+                    &dp.mac_version.to_string(),
+                )
+                .field(
+                    "reg_params_revision",
+                    &dp_old.reg_params_revision.to_string(),
+                    &dp.reg_params_revision.to_string(),
+                )
+                .field(
+                    "adr_algorithm_id",
+                    &dp_old.adr_algorithm_id,
+                    &dp.adr_algorithm_id,
+                )
+                .field(
+                    "payload_codec_runtime",
+                    &dp_old.payload_codec_runtime.to_string(),
+                    &dp.payload_codec_runtime.to_string(),
+                )
+                .field(
+                    "payload_codec_script",
+                    &dp_old.payload_codec_script,
+                    &dp.payload_codec_script,
+                )
+                .field(
+                    "codec_version_id",
+                    &dp_old.codec_version_id,
+                    &dp.codec_version_id,
+                )
+                .field(
+                    "uplink_interval",
+                    &dp_old.uplink_interval,
+                    &dp.uplink_interval,
+                )
+                .field("supports_otaa", &dp_old.supports_otaa, &dp.supports_otaa)
+                .field(
+                    "supports_class_b",
+                    &dp_old.supports_class_b,
+                    &dp.supports_class_b,
+                )
+                .field(
+                    "supports_class_c",
+                    &dp_old.supports_class_c,
+                    &dp.supports_class_c,
+                )
+                .field(
+                    "supports_clock_sync",
+                    &dp_old.supports_clock_sync,
+                    &dp.supports_clock_sync,
+                )
+                .field("is_relay", &dp_old.is_relay, &dp.is_relay)
+                .field("is_relay_ed", &dp_old.is_relay_ed, &dp.is_relay_ed)
+                .field("allow_roaming", &dp_old.allow_roaming, &dp.allow_roaming)
+                .field("rx1_delay", &dp_old.rx1_delay, &dp.rx1_delay)
+                .field(
+                    "gateway_selection_strategy",
+                    &dp_old.gateway_selection_strategy.map(|v| v.to_string()),
+                    &dp.gateway_selection_strategy.map(|v| v.to_string()),
+                )
+                .field("tags", &dp_old.tags, &dp.tags),
+        );
+
         Ok(resp)
     }
 
@@ -342,6 +464,8 @@ impl DeviceProfileService for DeviceProfile {
             )
             .await?;
 
+        let dp = device_profile::get(&dp_id).await.map_err(|e| e.status())?;
+
         device_profile::delete(&dp_id)
             .await
             .map_err(|e| e.status())?;
@@ -349,6 +473,8 @@ impl DeviceProfileService for DeviceProfile {
         let mut resp = Response::new(());
         resp.metadata_mut()
             .insert("x-log-device_profile_id", req.id.parse().unwrap());
+        resp.metadata_mut()
+            .insert("x-log-tenant_id", dp.tenant_id.to_string().parse().unwrap());
 
         Ok(resp)
     }
@@ -489,6 +615,28 @@ pub mod test {
         let create_resp = service.create(create_req).await.unwrap();
         let dp_id = Uuid::from_str(&create_resp.get_ref().id).unwrap();
 
+        // create with both a codec version and codec script
+        let create_req = get_request(
+            &u.id,
+            api::CreateDeviceProfileRequest {
+                device_profile: Some(api::DeviceProfile {
+                    tenant_id: t.id.to_string(),
+                    name: "test-dp-codec".into(),
+                    region: common::Region::Eu868.into(),
+                    mac_version: common::MacVersion::Lorawan103.into(),
+                    reg_params_revision: common::RegParamsRevision::A.into(),
+                    adr_algorithm_id: "default".into(),
+                    codec_version_id: Uuid::new_v4().to_string(),
+                    payload_codec_script: "function decodeUplink(input) {}".into(),
+                    ..Default::default()
+                }),
+            },
+        );
+        assert_eq!(
+            tonic::Code::InvalidArgument,
+            service.create(create_req).await.unwrap_err().code()
+        );
+
         // get
         let get_req = get_request(
             &u.id,
@@ -523,11 +671,44 @@ pub mod test {
                     mac_version: common::MacVersion::Lorawan103.into(),
                     reg_params_revision: common::RegParamsRevision::A.into(),
                     adr_algorithm_id: "default".into(),
+                    gateway_selection_strategy: api::GatewaySelectionStrategy::BestSnr.into(),
+                    ..Default::default()
+                }),
+            },
+        );
+        let up_resp = service.update(update_req).await.unwrap();
+        assert_eq!(
+            Some(
+                &audit_log::Changes::new()
+                    .field("name", &"test-dp", &"test-dp-updated")
+                    .field("region", &"EU868", &"US915")
+                    .field("gateway_selection_strategy", &None, &Some("BEST_SNR"))
+            ),
+            up_resp.extensions().get::<audit_log::Changes>()
+        );
+
+        // update with both a codec version and codec script
+        let update_req = get_request(
+            &u.id,
+            api::UpdateDeviceProfileRequest {
+                device_profile: Some(api::DeviceProfile {
+                    id: dp_id.to_string(),
+                    tenant_id: t.id.to_string(),
+                    name: "test-dp-updated".into(),
+                    region: common::Region::Us915.into(),
+                    mac_version: common::MacVersion::Lorawan103.into(),
+                    reg_params_revision: common::RegParamsRevision::A.into(),
+                    adr_algorithm_id: "default".into(),
+                    codec_version_id: Uuid::new_v4().to_string(),
+                    payload_codec_script: "function decodeUplink(input) {}".into(),
                     ..Default::default()
                 }),
             },
         );
-        let _ = service.update(update_req).await.unwrap();
+        assert_eq!(
+            tonic::Code::InvalidArgument,
+            service.update(update_req).await.unwrap_err().code()
+        );
 
         // get
         let get_req = get_request(
@@ -546,6 +727,7 @@ pub mod test {
                 mac_version: common::MacVersion::Lorawan103.into(),
                 reg_params_revision: common::RegParamsRevision::A.into(),
                 adr_algorithm_id: "default".into(),
+                gateway_selection_strategy: api::GatewaySelectionStrategy::BestSnr.into(),
                 ..Default::default()
             }),
             get_resp.get_ref().device_profile
@@ -591,11 +773,12 @@ pub mod test {
             .await
             .unwrap();
         let list_adr_algs_resp = list_adr_algs_resp.get_ref();
-        assert_eq!(3, list_adr_algs_resp.total_count);
-        assert_eq!(3, list_adr_algs_resp.result.len());
+        assert_eq!(4, list_adr_algs_resp.total_count);
+        assert_eq!(4, list_adr_algs_resp.result.len());
         assert_eq!("default", list_adr_algs_resp.result[0].id);
-        assert_eq!("lr_fhss", list_adr_algs_resp.result[1].id);
-        assert_eq!("lora_lr_fhss", list_adr_algs_resp.result[2].id);
+        assert_eq!("gateway_diversity", list_adr_algs_resp.result[1].id);
+        assert_eq!("lr_fhss", list_adr_algs_resp.result[2].id);
+        assert_eq!("lora_lr_fhss", list_adr_algs_resp.result[3].id);
     }
 
     fn get_request<T>(user_id: &Uuid, req: T) -> Request<T> {
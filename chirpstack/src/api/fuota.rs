@@ -0,0 +1,745 @@
+use std::str::FromStr;
+
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+use chirpstack_api::api;
+use chirpstack_api::api::fuota_service_server::FuotaService;
+use lrwn::EUI64;
+
+use super::auth::validator;
+use super::error::ToStatus;
+use super::helpers::{self, FromProto, ToProto};
+use crate::applayer;
+use crate::storage::{audit_log, fuota};
+
+pub struct Fuota {
+    validator: validator::RequestValidator,
+}
+
+impl Fuota {
+    pub fn new(validator: validator::RequestValidator) -> Self {
+        Fuota { validator }
+    }
+}
+
+#[tonic::async_trait]
+impl FuotaService for Fuota {
+    async fn create(
+        &self,
+        request: Request<api::CreateFuotaCampaignRequest>,
+    ) -> Result<Response<api::CreateFuotaCampaignResponse>, Status> {
+        let req_fc = match &request.get_ref().fuota_campaign {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("fuota_campaign is missing"));
+            }
+        };
+
+        let app_id = Uuid::from_str(&req_fc.application_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateFuotaCampaignsAccess::new(validator::Flag::Create, app_id),
+            )
+            .await?;
+
+        let fc = fuota::FuotaCampaign {
+            application_id: app_id,
+            device_profile_id: Uuid::from_str(&req_fc.device_profile_id).map_err(|e| e.status())?,
+            name: req_fc.name.clone(),
+            multicast_group_type: match req_fc.multicast_group_type() {
+                api::MulticastGroupType::ClassB => "B",
+                api::MulticastGroupType::ClassC => "C",
+            }
+            .to_string(),
+            multicast_class_c_scheduling_type: req_fc
+                .multicast_class_c_scheduling_type()
+                .from_proto(),
+            multicast_dr: req_fc.multicast_dr as i16,
+            multicast_class_b_ping_slot_nb_k: req_fc.multicast_class_b_ping_slot_nb_k as i16,
+            multicast_frequency: req_fc.multicast_frequency as i64,
+            multicast_timeout: req_fc.multicast_timeout as i16,
+            unicast_timeout: req_fc.unicast_timeout as i32,
+            fragment_size: req_fc.fragment_size as i16,
+            redundancy: req_fc.redundancy as i32,
+            payload: req_fc.payload.clone(),
+            ..Default::default()
+        };
+        applayer::fuota::validate_fragment_size(&fc)
+            .await
+            .map_err(|e| e.status())?;
+        let fc = fuota::create(fc).await.map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::CreateFuotaCampaignResponse {
+            id: fc.id.to_string(),
+        });
+        resp.metadata_mut().insert(
+            "x-log-fuota_campaign_id",
+            fc.id.to_string().parse().unwrap(),
+        );
+
+        Ok(resp)
+    }
+
+    async fn get(
+        &self,
+        request: Request<api::GetFuotaCampaignRequest>,
+    ) -> Result<Response<api::GetFuotaCampaignResponse>, Status> {
+        let req = request.get_ref();
+        let fc_id = Uuid::from_str(&req.id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateFuotaCampaignAccess::new(validator::Flag::Read, fc_id),
+            )
+            .await?;
+
+        let fc = fuota::get(&fc_id).await.map_err(|e| e.status())?;
+        let counts = fuota::get_device_counts(&fc_id)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::GetFuotaCampaignResponse {
+            fuota_campaign: Some(api::FuotaCampaign {
+                id: fc.id.to_string(),
+                name: fc.name.clone(),
+                application_id: fc.application_id.to_string(),
+                device_profile_id: fc.device_profile_id.to_string(),
+                multicast_group_type: match fc.multicast_group_type.as_ref() {
+                    "B" => api::MulticastGroupType::ClassB,
+                    "C" => api::MulticastGroupType::ClassC,
+                    _ => {
+                        return Err(Status::invalid_argument("Invalid multicast_group_type"));
+                    }
+                }
+                .into(),
+                multicast_class_c_scheduling_type: fc
+                    .multicast_class_c_scheduling_type
+                    .to_proto()
+                    .into(),
+                multicast_dr: fc.multicast_dr as u32,
+                multicast_class_b_ping_slot_nb_k: fc.multicast_class_b_ping_slot_nb_k as u32,
+                multicast_frequency: fc.multicast_frequency as u32,
+                multicast_timeout: fc.multicast_timeout as u32,
+                unicast_timeout: fc.unicast_timeout as u32,
+                fragment_size: fc.fragment_size as u32,
+                redundancy: fc.redundancy as u32,
+                payload: fc.payload.clone(),
+            }),
+            created_at: Some(helpers::datetime_to_prost_timestamp(&fc.created_at)),
+            updated_at: Some(helpers::datetime_to_prost_timestamp(&fc.updated_at)),
+            started_at: fc
+                .started_at
+                .as_ref()
+                .map(helpers::datetime_to_prost_timestamp),
+            completed_at: fc
+                .completed_at
+                .as_ref()
+                .map(helpers::datetime_to_prost_timestamp),
+            step: fc.step.to_proto().into(),
+            session_start_at: fc
+                .session_start_at
+                .as_ref()
+                .map(helpers::datetime_to_prost_timestamp),
+            multicast_group_id: fc
+                .multicast_group_id
+                .map(|v| v.to_string())
+                .unwrap_or_default(),
+            device_count: counts.total as u32,
+            completed_count: counts.completed as u32,
+            error_count: counts.error as u32,
+        });
+        resp.metadata_mut()
+            .insert("x-log-fuota_campaign_id", req.id.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn update(
+        &self,
+        request: Request<api::UpdateFuotaCampaignRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req_fc = match &request.get_ref().fuota_campaign {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("fuota_campaign is missing"));
+            }
+        };
+        let fc_id = Uuid::from_str(&req_fc.id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateFuotaCampaignAccess::new(validator::Flag::Update, fc_id),
+            )
+            .await?;
+
+        let fc_old = fuota::get(&fc_id).await.map_err(|e| e.status())?;
+        if fc_old.started_at.is_some() {
+            return Err(Status::failed_precondition(
+                "FUOTA campaign has already been started",
+            ));
+        }
+
+        let fc = fuota::FuotaCampaign {
+            name: req_fc.name.clone(),
+            multicast_group_type: match req_fc.multicast_group_type() {
+                api::MulticastGroupType::ClassB => "B",
+                api::MulticastGroupType::ClassC => "C",
+            }
+            .to_string(),
+            multicast_class_c_scheduling_type: req_fc
+                .multicast_class_c_scheduling_type()
+                .from_proto(),
+            multicast_dr: req_fc.multicast_dr as i16,
+            multicast_class_b_ping_slot_nb_k: req_fc.multicast_class_b_ping_slot_nb_k as i16,
+            multicast_frequency: req_fc.multicast_frequency as i64,
+            multicast_timeout: req_fc.multicast_timeout as i16,
+            unicast_timeout: req_fc.unicast_timeout as i32,
+            fragment_size: req_fc.fragment_size as i16,
+            redundancy: req_fc.redundancy as i32,
+            payload: req_fc.payload.clone(),
+            ..fc_old.clone()
+        };
+        applayer::fuota::validate_fragment_size(&fc)
+            .await
+            .map_err(|e| e.status())?;
+        let fc = fuota::update(fc).await.map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-fuota_campaign_id", req_fc.id.parse().unwrap());
+
+        resp.extensions_mut().insert(
+            audit_log::Changes::new()
+                .field("name", &fc_old.name, &fc.name)
+                .field(
+                    "multicast_group_type",
+                    &fc_old.multicast_group_type,
+                    &fc.multicast_group_type,
+                )
+                .field("multicast_dr", &fc_old.multicast_dr, &fc.multicast_dr)
+                .field(
+                    "multicast_frequency",
+                    &fc_old.multicast_frequency,
+                    &fc.multicast_frequency,
+                )
+                .field(
+                    "multicast_timeout",
+                    &fc_old.multicast_timeout,
+                    &fc.multicast_timeout,
+                )
+                .field(
+                    "unicast_timeout",
+                    &fc_old.unicast_timeout,
+                    &fc.unicast_timeout,
+                )
+                .field("fragment_size", &fc_old.fragment_size, &fc.fragment_size)
+                .field("redundancy", &fc_old.redundancy, &fc.redundancy),
+        );
+
+        Ok(resp)
+    }
+
+    async fn delete(
+        &self,
+        request: Request<api::DeleteFuotaCampaignRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.get_ref();
+        let fc_id = Uuid::from_str(&req.id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateFuotaCampaignAccess::new(validator::Flag::Delete, fc_id),
+            )
+            .await?;
+
+        let fc = fuota::get(&fc_id).await.map_err(|e| e.status())?;
+
+        fuota::delete(&fc_id).await.map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-fuota_campaign_id", req.id.parse().unwrap());
+        resp.metadata_mut().insert(
+            "x-log-application_id",
+            fc.application_id.to_string().parse().unwrap(),
+        );
+
+        Ok(resp)
+    }
+
+    async fn list(
+        &self,
+        request: Request<api::ListFuotaCampaignsRequest>,
+    ) -> Result<Response<api::ListFuotaCampaignsResponse>, Status> {
+        let req = request.get_ref();
+        let app_id = Uuid::from_str(&req.application_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateFuotaCampaignsAccess::new(validator::Flag::List, app_id),
+            )
+            .await?;
+
+        let filters = fuota::Filters {
+            application_id: Some(app_id),
+            search: if req.search.is_empty() {
+                None
+            } else {
+                Some(req.search.to_string())
+            },
+        };
+
+        let count = fuota::get_count(&filters).await.map_err(|e| e.status())?;
+        let items = fuota::list(req.limit as i64, req.offset as i64, &filters)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::ListFuotaCampaignsResponse {
+            total_count: count as u32,
+            result: items
+                .iter()
+                .map(|fc| api::FuotaCampaignListItem {
+                    id: fc.id.to_string(),
+                    created_at: Some(helpers::datetime_to_prost_timestamp(&fc.created_at)),
+                    updated_at: Some(helpers::datetime_to_prost_timestamp(&fc.updated_at)),
+                    started_at: fc
+                        .started_at
+                        .as_ref()
+                        .map(helpers::datetime_to_prost_timestamp),
+                    completed_at: fc
+                        .completed_at
+                        .as_ref()
+                        .map(helpers::datetime_to_prost_timestamp),
+                    name: fc.name.clone(),
+                    step: fc.step.to_proto().into(),
+                })
+                .collect(),
+        });
+        resp.metadata_mut()
+            .insert("x-log-application_id", req.application_id.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn add_device(
+        &self,
+        request: Request<api::AddDeviceToFuotaCampaignRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.get_ref();
+        let fc_id = Uuid::from_str(&req.fuota_campaign_id).map_err(|e| e.status())?;
+        let dev_eui = EUI64::from_str(&req.dev_eui).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateFuotaCampaignAccess::new(validator::Flag::Update, fc_id),
+            )
+            .await?;
+
+        fuota::add_device(&fc_id, &dev_eui)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut().insert(
+            "x-log-fuota_campaign_id",
+            req.fuota_campaign_id.parse().unwrap(),
+        );
+        resp.metadata_mut()
+            .insert("x-log-dev_eui", req.dev_eui.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn remove_device(
+        &self,
+        request: Request<api::RemoveDeviceFromFuotaCampaignRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.get_ref();
+        let fc_id = Uuid::from_str(&req.fuota_campaign_id).map_err(|e| e.status())?;
+        let dev_eui = EUI64::from_str(&req.dev_eui).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateFuotaCampaignAccess::new(validator::Flag::Update, fc_id),
+            )
+            .await?;
+
+        fuota::remove_device(&fc_id, &dev_eui)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut().insert(
+            "x-log-fuota_campaign_id",
+            req.fuota_campaign_id.parse().unwrap(),
+        );
+        resp.metadata_mut()
+            .insert("x-log-dev_eui", req.dev_eui.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn list_devices(
+        &self,
+        request: Request<api::ListFuotaCampaignDevicesRequest>,
+    ) -> Result<Response<api::ListFuotaCampaignDevicesResponse>, Status> {
+        let req = request.get_ref();
+        let fc_id = Uuid::from_str(&req.fuota_campaign_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateFuotaCampaignAccess::new(validator::Flag::Read, fc_id),
+            )
+            .await?;
+
+        let count = fuota::get_device_count(&fc_id)
+            .await
+            .map_err(|e| e.status())?;
+        let items = fuota::get_devices(&fc_id, req.limit as i64, req.offset as i64)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::ListFuotaCampaignDevicesResponse {
+            total_count: count as u32,
+            result: items
+                .iter()
+                .map(|d| api::FuotaCampaignDeviceListItem {
+                    dev_eui: d.dev_eui.to_string(),
+                    created_at: Some(helpers::datetime_to_prost_timestamp(&d.created_at)),
+                    updated_at: Some(helpers::datetime_to_prost_timestamp(&d.updated_at)),
+                    mc_group_setup_completed_at: d
+                        .mc_group_setup_completed_at
+                        .as_ref()
+                        .map(helpers::datetime_to_prost_timestamp),
+                    frag_session_setup_completed_at: d
+                        .frag_session_setup_completed_at
+                        .as_ref()
+                        .map(helpers::datetime_to_prost_timestamp),
+                    mc_session_completed_at: d
+                        .mc_session_completed_at
+                        .as_ref()
+                        .map(helpers::datetime_to_prost_timestamp),
+                    frag_status_completed_at: d
+                        .frag_status_completed_at
+                        .as_ref()
+                        .map(helpers::datetime_to_prost_timestamp),
+                    nb_frag_received: d.nb_frag_received.unwrap_or_default() as u32,
+                    missing_frag: d.missing_frag.unwrap_or_default() as u32,
+                    error_msg: d.error_msg.clone(),
+                })
+                .collect(),
+        });
+        resp.metadata_mut().insert(
+            "x-log-fuota_campaign_id",
+            req.fuota_campaign_id.parse().unwrap(),
+        );
+
+        Ok(resp)
+    }
+
+    async fn start(
+        &self,
+        request: Request<api::StartFuotaCampaignRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.get_ref();
+        let fc_id = Uuid::from_str(&req.id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateFuotaCampaignAccess::new(validator::Flag::Update, fc_id),
+            )
+            .await?;
+
+        applayer::fuota::start(&fc_id)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-fuota_campaign_id", req.id.parse().unwrap());
+
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::api::auth::validator::RequestValidator;
+    use crate::api::auth::AuthID;
+    use crate::storage::{application, device, device_profile, tenant, user};
+    use crate::test;
+
+    #[tokio::test]
+    async fn test_fuota_campaign() {
+        let _guard = test::prepare().await;
+
+        // setup admin user
+        let u = user::User {
+            is_admin: true,
+            is_active: true,
+            email: "admin@admin".into(),
+            email_verified: true,
+            ..Default::default()
+        };
+        let u = user::create(u).await.unwrap();
+
+        // create tenant
+        let t = tenant::create(tenant::Tenant {
+            name: "test-tenant".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // create application
+        let app = application::create(application::Application {
+            name: "test-app".into(),
+            tenant_id: t.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // create device-profile
+        let dp = device_profile::create(device_profile::DeviceProfile {
+            name: "test-dp".into(),
+            tenant_id: t.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // create device
+        let d = device::create(device::Device {
+            application_id: app.id,
+            device_profile_id: dp.id,
+            dev_eui: EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            name: "test-dev".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // setup api
+        let service = Fuota::new(RequestValidator::new());
+
+        // create
+        let create_req = get_request(
+            &u.id,
+            api::CreateFuotaCampaignRequest {
+                fuota_campaign: Some(api::FuotaCampaign {
+                    name: "test-fuota".into(),
+                    application_id: app.id.to_string(),
+                    device_profile_id: dp.id.to_string(),
+                    multicast_group_type: api::MulticastGroupType::ClassC.into(),
+                    multicast_dr: 5,
+                    multicast_frequency: 868100000,
+                    multicast_timeout: 6,
+                    unicast_timeout: 60,
+                    fragment_size: 10,
+                    redundancy: 2,
+                    payload: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12],
+                    ..Default::default()
+                }),
+            },
+        );
+        let create_resp = service.create(create_req).await.unwrap();
+        let create_resp = create_resp.get_ref();
+
+        // get
+        let get_req = get_request(
+            &u.id,
+            api::GetFuotaCampaignRequest {
+                id: create_resp.id.clone(),
+            },
+        );
+        let get_resp = service.get(get_req).await.unwrap();
+        let get_resp = get_resp.get_ref();
+        assert_eq!(
+            Some(api::FuotaCampaign {
+                id: create_resp.id.clone(),
+                name: "test-fuota".into(),
+                application_id: app.id.to_string(),
+                device_profile_id: dp.id.to_string(),
+                multicast_group_type: api::MulticastGroupType::ClassC.into(),
+                multicast_class_c_scheduling_type: api::MulticastGroupSchedulingType::Delay.into(),
+                multicast_dr: 5,
+                multicast_class_b_ping_slot_nb_k: 0,
+                multicast_frequency: 868100000,
+                multicast_timeout: 6,
+                unicast_timeout: 60,
+                fragment_size: 10,
+                redundancy: 2,
+                payload: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12],
+            }),
+            get_resp.fuota_campaign
+        );
+        assert_eq!(api::FuotaCampaignStep::Created as i32, get_resp.step);
+        assert!(get_resp.started_at.is_none());
+
+        // update
+        let update_req = get_request(
+            &u.id,
+            api::UpdateFuotaCampaignRequest {
+                fuota_campaign: Some(api::FuotaCampaign {
+                    id: create_resp.id.clone(),
+                    name: "test-fuota-updated".into(),
+                    application_id: app.id.to_string(),
+                    device_profile_id: dp.id.to_string(),
+                    multicast_group_type: api::MulticastGroupType::ClassB.into(),
+                    multicast_dr: 3,
+                    multicast_class_b_ping_slot_nb_k: 2,
+                    multicast_frequency: 869525000,
+                    multicast_timeout: 2,
+                    unicast_timeout: 120,
+                    fragment_size: 10,
+                    redundancy: 2,
+                    payload: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12],
+                    ..Default::default()
+                }),
+            },
+        );
+        let _ = service.update(update_req).await.unwrap();
+        let fc = fuota::get(&Uuid::from_str(&create_resp.id).unwrap())
+            .await
+            .unwrap();
+        assert_eq!("test-fuota-updated", fc.name);
+        assert_eq!("B", fc.multicast_group_type);
+
+        // list
+        let list_req = get_request(
+            &u.id,
+            api::ListFuotaCampaignsRequest {
+                search: "fuota".into(),
+                application_id: app.id.to_string(),
+                limit: 10,
+                offset: 0,
+            },
+        );
+        let list_resp = service.list(list_req).await.unwrap();
+        let list_resp = list_resp.get_ref();
+        assert_eq!(1, list_resp.total_count);
+        assert_eq!(1, list_resp.result.len());
+        assert_eq!(create_resp.id, list_resp.result[0].id);
+
+        // start without devices
+        let start_req = get_request(
+            &u.id,
+            api::StartFuotaCampaignRequest {
+                id: create_resp.id.clone(),
+            },
+        );
+        assert!(service.start(start_req).await.is_err());
+
+        // add device
+        let add_dev_req = get_request(
+            &u.id,
+            api::AddDeviceToFuotaCampaignRequest {
+                fuota_campaign_id: create_resp.id.clone(),
+                dev_eui: d.dev_eui.to_string(),
+            },
+        );
+        let _ = service.add_device(add_dev_req).await.unwrap();
+
+        // list devices
+        let list_dev_req = get_request(
+            &u.id,
+            api::ListFuotaCampaignDevicesRequest {
+                fuota_campaign_id: create_resp.id.clone(),
+                limit: 10,
+                offset: 0,
+            },
+        );
+        let list_dev_resp = service.list_devices(list_dev_req).await.unwrap();
+        let list_dev_resp = list_dev_resp.get_ref();
+        assert_eq!(1, list_dev_resp.total_count);
+        assert_eq!(d.dev_eui.to_string(), list_dev_resp.result[0].dev_eui);
+
+        // remove device
+        let remove_dev_req = get_request(
+            &u.id,
+            api::RemoveDeviceFromFuotaCampaignRequest {
+                fuota_campaign_id: create_resp.id.clone(),
+                dev_eui: d.dev_eui.to_string(),
+            },
+        );
+        let _ = service.remove_device(remove_dev_req).await.unwrap();
+
+        // add device again and start
+        let add_dev_req = get_request(
+            &u.id,
+            api::AddDeviceToFuotaCampaignRequest {
+                fuota_campaign_id: create_resp.id.clone(),
+                dev_eui: d.dev_eui.to_string(),
+            },
+        );
+        let _ = service.add_device(add_dev_req).await.unwrap();
+        let start_req = get_request(
+            &u.id,
+            api::StartFuotaCampaignRequest {
+                id: create_resp.id.clone(),
+            },
+        );
+        let _ = service.start(start_req).await.unwrap();
+
+        let get_req = get_request(
+            &u.id,
+            api::GetFuotaCampaignRequest {
+                id: create_resp.id.clone(),
+            },
+        );
+        let get_resp = service.get(get_req).await.unwrap();
+        let get_resp = get_resp.get_ref();
+        assert_eq!(api::FuotaCampaignStep::McGroupSetup as i32, get_resp.step);
+        assert!(get_resp.started_at.is_some());
+        assert_eq!(1, get_resp.device_count);
+
+        // update after start
+        let update_req = get_request(
+            &u.id,
+            api::UpdateFuotaCampaignRequest {
+                fuota_campaign: Some(api::FuotaCampaign {
+                    id: create_resp.id.clone(),
+                    name: "test-fuota".into(),
+                    ..Default::default()
+                }),
+            },
+        );
+        assert!(service.update(update_req).await.is_err());
+
+        // delete
+        let del_req = get_request(
+            &u.id,
+            api::DeleteFuotaCampaignRequest {
+                id: create_resp.id.clone(),
+            },
+        );
+        let _ = service.delete(del_req).await.unwrap();
+        let del_req = get_request(
+            &u.id,
+            api::DeleteFuotaCampaignRequest {
+                id: create_resp.id.clone(),
+            },
+        );
+        let del_resp = service.delete(del_req).await;
+        assert!(del_resp.is_err());
+    }
+
+    fn get_request<T>(user_id: &Uuid, req: T) -> Request<T> {
+        let mut req = Request::new(req);
+        req.extensions_mut().insert(AuthID::User(*user_id));
+        req
+    }
+}
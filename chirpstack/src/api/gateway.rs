@@ -14,8 +14,9 @@ use super::auth::validator;
 use super::error::ToStatus;
 use super::helpers::{self, FromProto};
 use crate::certificate;
+use crate::gateway::{backend as gateway_backend, command_exec};
 use crate::storage::{
-    fields,
+    audit_log, fields,
     gateway::{self, RelayId},
     metrics,
 };
@@ -116,6 +117,7 @@ impl GatewayService for Gateway {
                 .last_seen_at
                 .as_ref()
                 .map(helpers::datetime_to_prost_timestamp),
+            state: get_gateway_state(gw.is_online, gw.last_seen_at, gw.stats_interval_secs).into(),
         });
         resp.metadata_mut()
             .insert("x-log-gateway_id", req.gateway_id.parse().unwrap());
@@ -148,7 +150,9 @@ impl GatewayService for Gateway {
         };
 
         // update
-        let _ = gateway::update(gateway::Gateway {
+        let gw_old = gateway::get(&gw_id).await.map_err(|e| e.status())?;
+
+        let gw = gateway::update(gateway::Gateway {
             gateway_id: gw_id,
             name: req_gw.name.clone(),
             description: req_gw.description.clone(),
@@ -166,6 +170,21 @@ impl GatewayService for Gateway {
         resp.metadata_mut()
             .insert("x-log-gateway_id", req_gw.gateway_id.parse().unwrap());
 
+        resp.extensions_mut().insert(
+            audit_log::Changes::new()
+                .field("name", &gw_old.name, &gw.name)
+                .field("description", &gw_old.description, &gw.description)
+                .field("latitude", &gw_old.latitude, &gw.latitude)
+                .field("longitude", &gw_old.longitude, &gw.longitude)
+                .field("altitude", &gw_old.altitude, &gw.altitude)
+                .field(
+                    "stats_interval_secs",
+                    &gw_old.stats_interval_secs,
+                    &gw.stats_interval_secs,
+                )
+                .field("tags", &gw_old.tags, &gw.tags),
+        );
+
         Ok(resp)
     }
 
@@ -183,11 +202,15 @@ impl GatewayService for Gateway {
             )
             .await?;
 
+        let gw = gateway::get(&gw_id).await.map_err(|e| e.status())?;
+
         gateway::delete(&gw_id).await.map_err(|e| e.status())?;
 
         let mut resp = Response::new(());
         resp.metadata_mut()
             .insert("x-log-gateway_id", req.gateway_id.parse().unwrap());
+        resp.metadata_mut()
+            .insert("x-log-tenant_id", gw.tenant_id.to_string().parse().unwrap());
 
         Ok(resp)
     }
@@ -264,21 +287,8 @@ impl GatewayService for Gateway {
                         .last_seen_at
                         .as_ref()
                         .map(helpers::datetime_to_prost_timestamp),
-                    state: {
-                        if let Some(ts) = gw.last_seen_at {
-                            if (Utc::now() - ts)
-                                > Duration::try_seconds((gw.stats_interval_secs * 2).into())
-                                    .unwrap_or_default()
-                            {
-                                api::GatewayState::Offline
-                            } else {
-                                api::GatewayState::Online
-                            }
-                        } else {
-                            api::GatewayState::NeverSeen
-                        }
-                    }
-                    .into(),
+                    state: get_gateway_state(gw.is_online, gw.last_seen_at, gw.stats_interval_secs)
+                        .into(),
                 })
                 .collect(),
         });
@@ -842,11 +852,14 @@ impl GatewayService for Gateway {
         let tenant_id = Uuid::from_str(&req_relay.tenant_id).map_err(|e| e.status())?;
         let relay_id = RelayId::from_str(&req_relay.relay_id).map_err(|e| e.status())?;
 
-        // The tenant_id is part of the relay PK.
         self.validator
             .validate(
                 request.extensions(),
-                validator::ValidateGatewaysAccess::new(validator::Flag::List, tenant_id),
+                validator::ValidateRelayGatewayAccess::new(
+                    validator::Flag::Update,
+                    tenant_id,
+                    relay_id,
+                ),
             )
             .await?;
 
@@ -879,11 +892,14 @@ impl GatewayService for Gateway {
         let tenant_id = Uuid::from_str(&req.tenant_id).map_err(|e| e.status())?;
         let relay_id = RelayId::from_str(&req.relay_id).map_err(|e| e.status())?;
 
-        // The tenant_id is part of the relay PK.
         self.validator
             .validate(
                 request.extensions(),
-                validator::ValidateGatewaysAccess::new(validator::Flag::List, tenant_id),
+                validator::ValidateRelayGatewayAccess::new(
+                    validator::Flag::Delete,
+                    tenant_id,
+                    relay_id,
+                ),
             )
             .await?;
 
@@ -971,6 +987,147 @@ impl GatewayService for Gateway {
 
         Ok(resp)
     }
+
+    async fn list_state_changes(
+        &self,
+        request: Request<api::ListGatewayStateChangesRequest>,
+    ) -> Result<Response<api::ListGatewayStateChangesResponse>, Status> {
+        let req = request.get_ref();
+        let gw_id = EUI64::from_str(&req.gateway_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateGatewayAccess::new(validator::Flag::Read, gw_id),
+            )
+            .await?;
+
+        let count = gateway::get_state_change_count(&gw_id)
+            .await
+            .map_err(|e| e.status())?;
+        let result = gateway::list_state_changes(&gw_id, req.limit as i64, req.offset as i64)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::ListGatewayStateChangesResponse {
+            total_count: count as u32,
+            result: result
+                .iter()
+                .map(|sc| api::GatewayStateChange {
+                    time: Some(helpers::datetime_to_prost_timestamp(&sc.created_at)),
+                    state: match sc.is_online {
+                        true => api::GatewayState::Online,
+                        false => api::GatewayState::Offline,
+                    }
+                    .into(),
+                })
+                .collect(),
+        });
+        resp.metadata_mut()
+            .insert("x-log-gateway_id", req.gateway_id.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn exec_command(
+        &self,
+        request: Request<api::ExecGatewayCommandRequest>,
+    ) -> Result<Response<api::ExecGatewayCommandResponse>, Status> {
+        let req = request.get_ref();
+        let gw_id = EUI64::from_str(&req.gateway_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateGatewayAccess::new(validator::Flag::Update, gw_id),
+            )
+            .await?;
+
+        if req.command.is_empty() {
+            return Err(Status::invalid_argument("command is missing"));
+        }
+
+        let exec_resp =
+            command_exec::exec(gw_id, &req.command, &req.stdin, req.environment.clone())
+                .await
+                .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::ExecGatewayCommandResponse {
+            stdout: exec_resp.stdout,
+            stderr: exec_resp.stderr,
+            error: exec_resp.error,
+        });
+        resp.metadata_mut()
+            .insert("x-log-gateway_id", req.gateway_id.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn send_raw_packet_forwarder_command(
+        &self,
+        request: Request<api::SendRawPacketForwarderCommandRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.get_ref();
+        let gw_id = EUI64::from_str(&req.gateway_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateGatewayAccess::new(validator::Flag::Update, gw_id),
+            )
+            .await?;
+
+        let gw = gateway::get(&gw_id).await.map_err(|e| e.status())?;
+        let region_config_id = gw.properties.get("region_config_id").ok_or_else(|| {
+            Status::failed_precondition(
+                "Gateway region_config_id is unknown, no stats received yet",
+            )
+        })?;
+
+        gateway_backend::send_raw_packet_forwarder_command(
+            region_config_id,
+            &chirpstack_api::gw::RawPacketForwarderCommand {
+                gateway_id: gw_id.to_string(),
+                payload: req.payload.clone(),
+                ..Default::default()
+            },
+        )
+        .await
+        .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-gateway_id", req.gateway_id.parse().unwrap());
+
+        Ok(resp)
+    }
+}
+
+// Returns the gateway state. When the gateway (bridge) reports its connection state, this state
+// is used. Else, the state is derived from the last seen timestamp and the stats interval.
+fn get_gateway_state(
+    is_online: Option<bool>,
+    last_seen_at: Option<DateTime<Utc>>,
+    stats_interval_secs: i32,
+) -> api::GatewayState {
+    if let Some(is_online) = is_online {
+        return match is_online {
+            true => api::GatewayState::Online,
+            false => api::GatewayState::Offline,
+        };
+    }
+
+    if let Some(ts) = last_seen_at {
+        if (Utc::now() - ts)
+            > Duration::try_seconds((stats_interval_secs * 2).into()).unwrap_or_default()
+        {
+            api::GatewayState::Offline
+        } else {
+            api::GatewayState::Online
+        }
+    } else {
+        api::GatewayState::NeverSeen
+    }
 }
 
 #[cfg(test)]
@@ -982,7 +1139,7 @@ pub mod test {
     use crate::api::auth::validator::RequestValidator;
     use crate::api::auth::AuthID;
     use crate::storage::metrics;
-    use crate::storage::{tenant, user};
+    use crate::storage::{api_key, tenant, user};
     use crate::test;
 
     #[tokio::test]
@@ -1109,6 +1266,91 @@ pub mod test {
         let list_resp = service.list(list_req).await.unwrap();
         assert_eq!(1, list_resp.get_ref().total_count);
         assert_eq!(1, list_resp.get_ref().result.len());
+        assert_eq!(
+            api::GatewayState::NeverSeen as i32,
+            list_resp.get_ref().result[0].state
+        );
+
+        // connection state
+        let gw_id = EUI64::from_str("0102030405060708").unwrap();
+        gateway::set_conn_state(gw_id, true).await.unwrap();
+        gateway::set_conn_state(gw_id, false).await.unwrap();
+
+        let get_req = api::GetGatewayRequest {
+            gateway_id: "0102030405060708".into(),
+        };
+        let mut get_req = Request::new(get_req);
+        get_req.extensions_mut().insert(AuthID::User(u.id));
+        let get_resp = service.get(get_req).await.unwrap();
+        assert_eq!(api::GatewayState::Offline as i32, get_resp.get_ref().state);
+
+        // list state changes
+        let list_req = api::ListGatewayStateChangesRequest {
+            gateway_id: "0102030405060708".into(),
+            limit: 10,
+            offset: 0,
+        };
+        let mut list_req = Request::new(list_req);
+        list_req.extensions_mut().insert(AuthID::User(u.id));
+        let list_resp = service.list_state_changes(list_req).await.unwrap();
+        assert_eq!(2, list_resp.get_ref().total_count);
+        assert_eq!(
+            vec![
+                api::GatewayState::Offline as i32,
+                api::GatewayState::Online as i32
+            ],
+            list_resp
+                .get_ref()
+                .result
+                .iter()
+                .map(|sc| sc.state)
+                .collect::<Vec<i32>>()
+        );
+
+        // send raw packet-forwarder command
+        gateway_backend::set_backend("eu868", Box::new(gateway_backend::mock::Backend {})).await;
+        gateway_backend::mock::reset().await;
+
+        let raw_req = api::SendRawPacketForwarderCommandRequest {
+            gateway_id: "0102030405060708".into(),
+            payload: vec![1, 2, 3],
+        };
+        let mut raw_req = Request::new(raw_req);
+        raw_req.extensions_mut().insert(AuthID::User(u.id));
+        // no stats received yet
+        assert!(service
+            .send_raw_packet_forwarder_command(raw_req)
+            .await
+            .is_err());
+
+        gateway::partial_update(
+            gw_id,
+            &gateway::GatewayChangeset {
+                properties: Some(fields::KeyValue::new(
+                    [("region_config_id".to_string(), "eu868".to_string())]
+                        .into_iter()
+                        .collect(),
+                )),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let raw_req = api::SendRawPacketForwarderCommandRequest {
+            gateway_id: "0102030405060708".into(),
+            payload: vec![1, 2, 3],
+        };
+        let mut raw_req = Request::new(raw_req);
+        raw_req.extensions_mut().insert(AuthID::User(u.id));
+        let _ = service
+            .send_raw_packet_forwarder_command(raw_req)
+            .await
+            .unwrap();
+        let cmds = gateway_backend::mock::get_raw_packet_forwarder_commands().await;
+        assert_eq!(1, cmds.len());
+        assert_eq!("0102030405060708", cmds[0].gateway_id);
+        assert_eq!(vec![1, 2, 3], cmds[0].payload);
 
         // delete
         let del_req = api::DeleteGatewayRequest {
@@ -1412,6 +1654,53 @@ pub mod test {
             get_relay_resp.get_ref().relay_gateway
         );
 
+        // read-only api key can not update or delete
+        let ak_read_only = api_key::create(api_key::ApiKey {
+            name: "read-only".into(),
+            tenant_id: Some(t.id),
+            scope: fields::ApiKeyScope::READ_ONLY,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let mut up_relay_req = Request::new(api::UpdateRelayGatewayRequest {
+            relay_gateway: Some(api::RelayGateway {
+                tenant_id: t.id.to_string(),
+                relay_id: "01020304".into(),
+                name: "read-only-relay".into(),
+                region_config_id: "us915_0".into(),
+                ..Default::default()
+            }),
+        });
+        up_relay_req
+            .extensions_mut()
+            .insert(AuthID::Key(ak_read_only.id));
+        assert_eq!(
+            tonic::Code::Unauthenticated,
+            service
+                .update_relay_gateway(up_relay_req)
+                .await
+                .unwrap_err()
+                .code()
+        );
+
+        let mut del_relay_req = Request::new(api::DeleteRelayGatewayRequest {
+            tenant_id: t.id.to_string(),
+            relay_id: "01020304".into(),
+        });
+        del_relay_req
+            .extensions_mut()
+            .insert(AuthID::Key(ak_read_only.id));
+        assert_eq!(
+            tonic::Code::Unauthenticated,
+            service
+                .delete_relay_gateway(del_relay_req)
+                .await
+                .unwrap_err()
+                .code()
+        );
+
         // list
         let list_relay_req = api::ListRelayGatewaysRequest {
             tenant_id: t.id.to_string(),
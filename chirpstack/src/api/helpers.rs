@@ -1,7 +1,10 @@
 use chrono::{DateTime, Utc};
 
 use crate::codec::Codec;
-use crate::storage::fields::{MeasurementKind, MulticastGroupSchedulingType};
+use crate::storage::fields::{
+    ApiKeyScope, FragmentationSessionStatus, FuotaCampaignStep, GatewaySelectionStrategy,
+    MeasurementKind, MulticastGroupSchedulingType,
+};
 use crate::storage::{device::DeviceClass, metrics::Aggregation};
 use chirpstack_api::{api, common};
 use lrwn::region::{CommonName, MacVersion, Revision};
@@ -223,6 +226,59 @@ impl FromProto<MulticastGroupSchedulingType> for api::MulticastGroupSchedulingTy
     }
 }
 
+impl ToProto<api::FuotaCampaignStep> for FuotaCampaignStep {
+    fn to_proto(self) -> api::FuotaCampaignStep {
+        match self {
+            FuotaCampaignStep::CREATED => api::FuotaCampaignStep::Created,
+            FuotaCampaignStep::MC_GROUP_SETUP => api::FuotaCampaignStep::McGroupSetup,
+            FuotaCampaignStep::FRAG_SESSION_SETUP => api::FuotaCampaignStep::FragSessionSetup,
+            FuotaCampaignStep::MC_SESSION => api::FuotaCampaignStep::McSession,
+            FuotaCampaignStep::ENQUEUE => api::FuotaCampaignStep::Enqueue,
+            FuotaCampaignStep::FRAG_STATUS => api::FuotaCampaignStep::FragStatus,
+            FuotaCampaignStep::COMPLETED => api::FuotaCampaignStep::Completed,
+        }
+    }
+}
+
+impl ToProto<api::FragmentationSessionStatus> for FragmentationSessionStatus {
+    fn to_proto(self) -> api::FragmentationSessionStatus {
+        match self {
+            FragmentationSessionStatus::PENDING => {
+                api::FragmentationSessionStatus::FragmentationSessionPending
+            }
+            FragmentationSessionStatus::ACTIVE => {
+                api::FragmentationSessionStatus::FragmentationSessionActive
+            }
+            FragmentationSessionStatus::COMPLETED => {
+                api::FragmentationSessionStatus::FragmentationSessionCompleted
+            }
+            FragmentationSessionStatus::FAILED => {
+                api::FragmentationSessionStatus::FragmentationSessionFailed
+            }
+        }
+    }
+}
+
+impl ToProto<api::ApiKeyScope> for ApiKeyScope {
+    fn to_proto(self) -> api::ApiKeyScope {
+        match self {
+            ApiKeyScope::FULL => api::ApiKeyScope::Full,
+            ApiKeyScope::READ_ONLY => api::ApiKeyScope::ReadOnly,
+            ApiKeyScope::DEVICE_QUEUE_ENQUEUE => api::ApiKeyScope::DeviceQueueEnqueue,
+        }
+    }
+}
+
+impl FromProto<ApiKeyScope> for api::ApiKeyScope {
+    fn from_proto(self) -> ApiKeyScope {
+        match self {
+            api::ApiKeyScope::Full => ApiKeyScope::FULL,
+            api::ApiKeyScope::ReadOnly => ApiKeyScope::READ_ONLY,
+            api::ApiKeyScope::DeviceQueueEnqueue => ApiKeyScope::DEVICE_QUEUE_ENQUEUE,
+        }
+    }
+}
+
 impl ToProto<api::RelayModeActivation> for lrwn::RelayModeActivation {
     fn to_proto(self) -> api::RelayModeActivation {
         match self {
@@ -263,6 +319,44 @@ impl ToProto<common::DeviceClass> for DeviceClass {
     }
 }
 
+impl ToProto<api::GatewaySelectionStrategy> for Option<GatewaySelectionStrategy> {
+    fn to_proto(self) -> api::GatewaySelectionStrategy {
+        match self {
+            None => api::GatewaySelectionStrategy::RegionDefault,
+            Some(GatewaySelectionStrategy::RANDOM) => api::GatewaySelectionStrategy::Random,
+            Some(GatewaySelectionStrategy::BEST_SNR) => api::GatewaySelectionStrategy::BestSnr,
+            Some(GatewaySelectionStrategy::LEAST_LOADED) => {
+                api::GatewaySelectionStrategy::LeastLoaded
+            }
+            Some(GatewaySelectionStrategy::ROUND_ROBIN) => {
+                api::GatewaySelectionStrategy::RoundRobin
+            }
+            Some(GatewaySelectionStrategy::PREFER_TAGS) => {
+                api::GatewaySelectionStrategy::PreferTags
+            }
+        }
+    }
+}
+
+impl FromProto<Option<GatewaySelectionStrategy>> for api::GatewaySelectionStrategy {
+    fn from_proto(self) -> Option<GatewaySelectionStrategy> {
+        match self {
+            api::GatewaySelectionStrategy::RegionDefault => None,
+            api::GatewaySelectionStrategy::Random => Some(GatewaySelectionStrategy::RANDOM),
+            api::GatewaySelectionStrategy::BestSnr => Some(GatewaySelectionStrategy::BEST_SNR),
+            api::GatewaySelectionStrategy::LeastLoaded => {
+                Some(GatewaySelectionStrategy::LEAST_LOADED)
+            }
+            api::GatewaySelectionStrategy::RoundRobin => {
+                Some(GatewaySelectionStrategy::ROUND_ROBIN)
+            }
+            api::GatewaySelectionStrategy::PreferTags => {
+                Some(GatewaySelectionStrategy::PREFER_TAGS)
+            }
+        }
+    }
+}
+
 pub fn datetime_to_prost_timestamp(dt: &DateTime<Utc>) -> prost_types::Timestamp {
     let ts = dt.timestamp_nanos_opt().unwrap_or_default();
 
@@ -1,9 +1,10 @@
 use std::pin::Pin;
 use std::str::FromStr;
 use std::task::{Context, Poll};
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use anyhow::{Context as AnyhowContext, Result};
+use chrono::{DateTime, Utc};
 use futures::Stream;
 use reqwest::header::{HeaderMap, CONTENT_TYPE};
 use reqwest::Client;
@@ -17,10 +18,10 @@ use uuid::Uuid;
 use chirpstack_api::api;
 use chirpstack_api::api::internal_service_server::InternalService;
 
-use super::auth::claims;
+use super::auth::{claims, totp};
 use super::auth::{validator, AuthID};
 use super::error::ToStatus;
-use super::helpers::ToProto;
+use super::helpers::{FromProto, ToProto};
 use super::{helpers, oauth2, oidc};
 use crate::storage::{api_key, device, error::Error, gateway, redis_key, search, tenant, user};
 use crate::{config, region, stream};
@@ -39,6 +40,109 @@ impl Internal {
         }
     }
 
+    async fn is_totp_required(&self, u: &user::User) -> Result<bool, Status> {
+        let conf = config::get();
+        if conf.user_authentication.totp.required {
+            return Ok(true);
+        }
+
+        user::is_totp_required_by_tenant(&u.id)
+            .await
+            .map_err(|e| e.status())
+    }
+
+    // Returns the user for the given login token (see Login).
+    async fn get_login_token_user(&self, login_token: &str) -> Result<user::User, Status> {
+        let claim = claims::AuthClaim::decode(login_token, self.jwt_secret.as_ref())
+            .map_err(|e| Status::unauthenticated(e.to_string()))?;
+        if claim.typ != "login" {
+            return Err(Status::unauthenticated("invalid token typ"));
+        }
+        let id = Uuid::from_str(&claim.sub).map_err(|e| e.status())?;
+
+        let u = user::get(&id).await.map_err(|e| e.status())?;
+        if !u.is_active {
+            return Err(Status::unauthenticated("user is not active"));
+        }
+
+        Ok(u)
+    }
+
+    // Returns the user for TOTP enrollment, either by the login token (in case TOTP
+    // enrollment is required to complete the login) or the authenticated user.
+    async fn get_totp_user<T>(
+        &self,
+        request: &Request<T>,
+        login_token: &str,
+    ) -> Result<user::User, Status> {
+        if !login_token.is_empty() {
+            return self.get_login_token_user(login_token).await;
+        }
+
+        self.validator
+            .validate(request.extensions(), validator::ValidateActiveUser::new())
+            .await?;
+
+        let id = match request.extensions().get::<AuthID>().unwrap() {
+            AuthID::User(id) => id,
+            _ => {
+                return Err(Status::internal("no user id"));
+            }
+        };
+
+        user::get(id).await.map_err(|e| e.status())
+    }
+
+    // Verifies the given TOTP code (or recovery-code when allowed) of the user. Failed
+    // attempts are counted and after max_failed_attempts, the user is locked out for the
+    // configured lockout duration. A code is only accepted once.
+    async fn verify_totp_code(
+        &self,
+        u: &user::User,
+        secret: &str,
+        code: &str,
+        allow_recovery_code: bool,
+    ) -> Result<bool, Status> {
+        let conf = config::get();
+
+        if user::get_totp_failed_attempts(&u.id)
+            .await
+            .map_err(|e| e.status())?
+            >= conf.user_authentication.totp.max_failed_attempts
+        {
+            return Err(Status::resource_exhausted(
+                "too many failed TOTP attempts, please try again later",
+            ));
+        }
+
+        let valid = match totp::verify_code(secret, code, Utc::now()).map_err(|e| e.status())? {
+            Some(counter) => user::set_totp_last_counter(&u.id, counter)
+                .await
+                .map_err(|e| e.status())?,
+            None => {
+                allow_recovery_code
+                    && user::use_totp_recovery_code(&u.id, code)
+                        .await
+                        .map_err(|e| e.status())?
+            }
+        };
+
+        if valid {
+            user::reset_totp_failed_attempts(&u.id)
+                .await
+                .map_err(|e| e.status())?;
+        } else {
+            user::increment_totp_failed_attempts(
+                &u.id,
+                conf.user_authentication.totp.lockout_duration,
+            )
+            .await
+            .map_err(|e| e.status())?;
+        }
+
+        Ok(valid)
+    }
+
     async fn create_and_provision_user<S>(
         &self,
         external_id: &str,
@@ -151,11 +255,132 @@ impl InternalService for Internal {
             .await
             .map_err(|e| e.status())?;
 
+        // In case TOTP is enabled or required, a short-lived login token is returned which
+        // must be used to complete the login.
+        if u.totp_enabled || self.is_totp_required(&u).await? {
+            let login_token = claims::AuthClaim::new_for_login(&u.id)
+                .encode(self.jwt_secret.as_ref())
+                .map_err(|e| e.status())?;
+
+            return Ok(Response::new(api::LoginResponse {
+                totp_required: u.totp_enabled,
+                totp_enrollment_required: !u.totp_enabled,
+                login_token,
+                ..Default::default()
+            }));
+        }
+
         let token = claims::AuthClaim::new_for_user(&u.id)
             .encode(self.jwt_secret.as_ref())
             .map_err(|e| e.status())?;
 
-        Ok(Response::new(api::LoginResponse { jwt: token }))
+        Ok(Response::new(api::LoginResponse {
+            jwt: token,
+            ..Default::default()
+        }))
+    }
+
+    async fn login_totp(
+        &self,
+        request: Request<api::LoginTotpRequest>,
+    ) -> Result<Response<api::LoginResponse>, Status> {
+        let req = request.get_ref();
+        let u = self.get_login_token_user(&req.login_token).await?;
+
+        let secret = match (&u.totp_secret, u.totp_enabled) {
+            (Some(v), true) => v,
+            _ => {
+                return Err(Status::failed_precondition("TOTP is not enabled"));
+            }
+        };
+
+        if !self.verify_totp_code(&u, secret, &req.code, true).await? {
+            return Err(Status::unauthenticated("invalid TOTP code"));
+        }
+
+        let token = claims::AuthClaim::new_for_user(&u.id)
+            .encode(self.jwt_secret.as_ref())
+            .map_err(|e| e.status())?;
+
+        Ok(Response::new(api::LoginResponse {
+            jwt: token,
+            ..Default::default()
+        }))
+    }
+
+    async fn enroll_totp(
+        &self,
+        request: Request<api::EnrollTotpRequest>,
+    ) -> Result<Response<api::EnrollTotpResponse>, Status> {
+        let conf = config::get();
+        let req = request.get_ref();
+        let u = self.get_totp_user(&request, &req.login_token).await?;
+
+        // Re-enrolling must not be used to bypass an enabled TOTP. In case the user lost
+        // access to the authenticator app, an admin must disable TOTP first.
+        if u.totp_enabled {
+            return Err(Status::failed_precondition("TOTP is already enabled"));
+        }
+
+        let secret = totp::generate_secret();
+        let u = user::set_totp_secret(&u.id, &secret)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::EnrollTotpResponse {
+            uri: totp::get_uri(&conf.user_authentication.totp.issuer, &u.email, &secret),
+            secret,
+        });
+        resp.metadata_mut()
+            .insert("x-log-user_id", u.id.to_string().parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn activate_totp(
+        &self,
+        request: Request<api::ActivateTotpRequest>,
+    ) -> Result<Response<api::ActivateTotpResponse>, Status> {
+        let req = request.get_ref();
+        let u = self.get_totp_user(&request, &req.login_token).await?;
+
+        if u.totp_enabled {
+            return Err(Status::failed_precondition("TOTP is already enabled"));
+        }
+
+        let secret = match &u.totp_secret {
+            Some(v) => v,
+            None => {
+                return Err(Status::failed_precondition("TOTP has not been enrolled"));
+            }
+        };
+
+        if !self.verify_totp_code(&u, secret, &req.code, false).await? {
+            return Err(Status::invalid_argument("invalid TOTP code"));
+        }
+
+        let recovery_codes = totp::generate_recovery_codes();
+        let u = user::enable_totp(&u.id, &recovery_codes)
+            .await
+            .map_err(|e| e.status())?;
+
+        // In case of the login token, this completes the login.
+        let jwt = if req.login_token.is_empty() {
+            "".into()
+        } else {
+            claims::AuthClaim::new_for_user(&u.id)
+                .encode(self.jwt_secret.as_ref())
+                .map_err(|e| e.status())?
+        };
+
+        let mut resp = Response::new(api::ActivateTotpResponse {
+            recovery_codes,
+            jwt,
+        });
+        resp.metadata_mut()
+            .insert("x-log-user_id", u.id.to_string().parse().unwrap());
+
+        Ok(resp)
     }
 
     async fn profile(
@@ -198,6 +423,7 @@ impl InternalService for Internal {
                     is_gateway_admin: i.is_gateway_admin,
                 })
                 .collect(),
+            totp_enabled: u.totp_enabled,
         }))
     }
 
@@ -302,6 +528,17 @@ impl InternalService for Internal {
             ));
         }
 
+        let application_id = if req_key.application_id.is_empty() {
+            None
+        } else {
+            Some(Uuid::from_str(&req_key.application_id).map_err(|e| e.status())?)
+        };
+
+        let expires_at: Option<DateTime<Utc>> = match &req_key.expires_at {
+            Some(v) => Some(SystemTime::try_from(*v).map_err(|e| e.status())?.into()),
+            None => None,
+        };
+
         self.validator
             .validate(
                 request.extensions(),
@@ -313,6 +550,9 @@ impl InternalService for Internal {
             name: req_key.name.clone(),
             is_admin: req_key.is_admin,
             tenant_id,
+            expires_at,
+            scope: req_key.scope().from_proto(),
+            application_id,
             ..Default::default()
         };
 
@@ -321,10 +561,18 @@ impl InternalService for Internal {
             .encode(self.jwt_secret.as_ref())
             .map_err(|e| e.status())?;
 
-        Ok(Response::new(api::CreateApiKeyResponse {
+        let mut resp = Response::new(api::CreateApiKeyResponse {
             id: ak.id.to_string(),
             token,
-        }))
+        });
+        resp.metadata_mut()
+            .insert("x-log-api_key_id", ak.id.to_string().parse().unwrap());
+        if let Some(tenant_id) = &ak.tenant_id {
+            resp.metadata_mut()
+                .insert("x-log-tenant_id", tenant_id.to_string().parse().unwrap());
+        }
+
+        Ok(resp)
     }
 
     async fn delete_api_key(
@@ -341,8 +589,18 @@ impl InternalService for Internal {
             )
             .await?;
 
+        let ak = api_key::get(&api_key_id).await.map_err(|e| e.status())?;
         api_key::delete(&api_key_id).await.map_err(|e| e.status())?;
-        Ok(Response::new(()))
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-api_key_id", req.id.parse().unwrap());
+        if let Some(tenant_id) = &ak.tenant_id {
+            resp.metadata_mut()
+                .insert("x-log-tenant_id", tenant_id.to_string().parse().unwrap());
+        }
+
+        Ok(resp)
     }
 
     async fn list_api_keys(
@@ -398,6 +656,19 @@ impl InternalService for Internal {
                         Some(v) => v.to_string(),
                         None => "".to_string(),
                     },
+                    expires_at: ak
+                        .expires_at
+                        .as_ref()
+                        .map(helpers::datetime_to_prost_timestamp),
+                    last_used_at: ak
+                        .last_used_at
+                        .as_ref()
+                        .map(helpers::datetime_to_prost_timestamp),
+                    scope: ak.scope.to_proto().into(),
+                    application_id: match ak.application_id {
+                        Some(v) => v.to_string(),
+                        None => "".to_string(),
+                    },
                 })
                 .collect(),
         }))
@@ -979,3 +1250,192 @@ impl InternalService for Internal {
         }))
     }
 }
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::api::auth::totp::test::generate_code;
+    use crate::test;
+
+    #[tokio::test]
+    async fn test_login_totp() {
+        let _guard = test::prepare().await;
+
+        let u = user::test::create_user().await;
+        let service = Internal::new(
+            validator::RequestValidator::new(),
+            config::get().api.secret.clone(),
+        );
+
+        let login = || async {
+            service
+                .login(Request::new(api::LoginRequest {
+                    email: "test@example.com".into(),
+                    password: "password!".into(),
+                }))
+                .await
+                .unwrap()
+                .into_inner()
+        };
+
+        // TOTP not enabled
+        let resp = login().await;
+        assert!(!resp.jwt.is_empty());
+        assert!(!resp.totp_required);
+
+        // enroll using the user jwt
+        let mut enroll_req = Request::new(api::EnrollTotpRequest::default());
+        enroll_req.extensions_mut().insert(AuthID::User(u.id));
+        let enroll_resp = service.enroll_totp(enroll_req).await.unwrap();
+        let secret = enroll_resp.get_ref().secret.clone();
+
+        // activate with invalid code
+        let mut activate_req = Request::new(api::ActivateTotpRequest {
+            code: "000000".into(),
+            ..Default::default()
+        });
+        activate_req.extensions_mut().insert(AuthID::User(u.id));
+        assert!(service.activate_totp(activate_req).await.is_err());
+
+        // activate
+        let mut activate_req = Request::new(api::ActivateTotpRequest {
+            code: generate_code(&secret, Utc::now()).unwrap(),
+            ..Default::default()
+        });
+        activate_req.extensions_mut().insert(AuthID::User(u.id));
+        let activate_resp = service.activate_totp(activate_req).await.unwrap();
+        let recovery_codes = activate_resp.get_ref().recovery_codes.clone();
+        assert_eq!(10, recovery_codes.len());
+        assert!(activate_resp.get_ref().jwt.is_empty());
+
+        // login now requires TOTP
+        let resp = login().await;
+        assert!(resp.jwt.is_empty());
+        assert!(resp.totp_required);
+        assert!(!resp.login_token.is_empty());
+
+        // the login token can not be used as jwt
+        assert!(crate::api::auth::get_auth_id(&format!("Bearer {}", resp.login_token)).is_err());
+
+        // re-enrolling is not possible
+        let enroll_req = Request::new(api::EnrollTotpRequest {
+            login_token: resp.login_token.clone(),
+        });
+        assert!(service.enroll_totp(enroll_req).await.is_err());
+
+        // invalid code
+        let login_req = Request::new(api::LoginTotpRequest {
+            login_token: resp.login_token.clone(),
+            code: "000000".into(),
+        });
+        assert!(service.login_totp(login_req).await.is_err());
+
+        // valid code (the code of the current time-step has been used for activation)
+        let code = generate_code(
+            &secret,
+            Utc::now() + chrono::Duration::try_seconds(30).unwrap(),
+        )
+        .unwrap();
+        let login_req = Request::new(api::LoginTotpRequest {
+            login_token: resp.login_token.clone(),
+            code: code.clone(),
+        });
+        let login_resp = service.login_totp(login_req).await.unwrap();
+        assert!(!login_resp.get_ref().jwt.is_empty());
+
+        // the same code can not be used twice
+        let login_req = Request::new(api::LoginTotpRequest {
+            login_token: resp.login_token.clone(),
+            code,
+        });
+        assert!(service.login_totp(login_req).await.is_err());
+
+        // recovery code can be used once
+        let login_req = Request::new(api::LoginTotpRequest {
+            login_token: resp.login_token.clone(),
+            code: recovery_codes[0].clone(),
+        });
+        assert!(service.login_totp(login_req).await.is_ok());
+        let login_req = Request::new(api::LoginTotpRequest {
+            login_token: resp.login_token.clone(),
+            code: recovery_codes[0].clone(),
+        });
+        assert!(service.login_totp(login_req).await.is_err());
+
+        // lockout after max failed attempts
+        for _ in 0..4 {
+            let login_req = Request::new(api::LoginTotpRequest {
+                login_token: resp.login_token.clone(),
+                code: "000000".into(),
+            });
+            assert_eq!(
+                tonic::Code::Unauthenticated,
+                service.login_totp(login_req).await.unwrap_err().code()
+            );
+        }
+        let login_req = Request::new(api::LoginTotpRequest {
+            login_token: resp.login_token.clone(),
+            code: recovery_codes[1].clone(),
+        });
+        assert_eq!(
+            tonic::Code::ResourceExhausted,
+            service.login_totp(login_req).await.unwrap_err().code()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_login_totp_enrollment_required() {
+        let _guard = test::prepare().await;
+
+        let u = user::test::create_user().await;
+        let t = tenant::create(tenant::Tenant {
+            name: "test-tenant".into(),
+            require_totp: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        tenant::add_user(tenant::TenantUser {
+            tenant_id: t.id,
+            user_id: u.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let service = Internal::new(
+            validator::RequestValidator::new(),
+            config::get().api.secret.clone(),
+        );
+
+        // login requires enrollment
+        let resp = service
+            .login(Request::new(api::LoginRequest {
+                email: "test@example.com".into(),
+                password: "password!".into(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(resp.jwt.is_empty());
+        assert!(resp.totp_enrollment_required);
+
+        // enroll and activate using the login token
+        let enroll_resp = service
+            .enroll_totp(Request::new(api::EnrollTotpRequest {
+                login_token: resp.login_token.clone(),
+            }))
+            .await
+            .unwrap();
+        let secret = enroll_resp.get_ref().secret.clone();
+
+        let activate_resp = service
+            .activate_totp(Request::new(api::ActivateTotpRequest {
+                login_token: resp.login_token.clone(),
+                code: generate_code(&secret, Utc::now()).unwrap(),
+            }))
+            .await
+            .unwrap();
+        assert!(!activate_resp.get_ref().jwt.is_empty());
+    }
+}
@@ -29,10 +29,12 @@ use tower_http::trace::TraceLayer;
 use tracing::{error, info};
 
 use chirpstack_api::api::application_service_server::ApplicationServiceServer;
+use chirpstack_api::api::codec_service_server::CodecServiceServer;
 use chirpstack_api::api::device_config_store_service_server::DeviceConfigStoreServiceServer;
 use chirpstack_api::api::device_profile_service_server::DeviceProfileServiceServer;
 use chirpstack_api::api::device_profile_template_service_server::DeviceProfileTemplateServiceServer;
 use chirpstack_api::api::device_service_server::DeviceServiceServer;
+use chirpstack_api::api::fuota_service_server::FuotaServiceServer;
 use chirpstack_api::api::gateway_service_server::GatewayServiceServer;
 use chirpstack_api::api::internal_service_server::InternalServiceServer;
 use chirpstack_api::api::multicast_group_service_server::MulticastGroupServiceServer;
@@ -45,16 +47,20 @@ use super::config;
 use crate::api::auth::validator;
 use crate::helpers::errors::PrintFullError;
 use crate::monitoring::prometheus;
+use crate::storage::audit_log;
 use crate::stream;
 
 pub mod application;
+pub mod audit;
 pub mod auth;
 pub mod backend;
+pub mod codec;
 pub mod device;
 pub mod device_config_store;
 pub mod device_profile;
 pub mod device_profile_template;
 pub mod error;
+pub mod fuota;
 pub mod gateway;
 mod grpc_multiplex;
 pub mod helpers;
@@ -93,6 +99,15 @@ lazy_static! {
         );
         histogram
     };
+    static ref AUDIT_LOG_ERROR_COUNTER: Counter = {
+        let counter = Counter::default();
+        prometheus::register(
+            "api_audit_log_errors",
+            "Number of mutating API requests that could not be written to the audit log",
+            counter.clone(),
+        );
+        counter
+    };
 }
 
 #[derive(RustEmbed)]
@@ -146,6 +161,10 @@ pub async fn setup() -> Result<()> {
             application::Application::new(validator::RequestValidator::new()),
             auth::auth_interceptor,
         ))
+        .add_service(CodecServiceServer::with_interceptor(
+            codec::Codec::new(validator::RequestValidator::new()),
+            auth::auth_interceptor,
+        ))
         .add_service(DeviceProfileServiceServer::with_interceptor(
             device_profile::DeviceProfile::new(validator::RequestValidator::new()),
             auth::auth_interceptor,
@@ -178,6 +197,10 @@ pub async fn setup() -> Result<()> {
             multicast::MulticastGroup::new(validator::RequestValidator::new()),
             auth::auth_interceptor,
         ))
+        .add_service(FuotaServiceServer::with_interceptor(
+            fuota::Fuota::new(validator::RequestValidator::new()),
+            auth::auth_interceptor,
+        ))
         .add_service(RelayServiceServer::with_interceptor(
             relay::Relay::new(validator::RequestValidator::new()),
             auth::auth_interceptor,
@@ -275,9 +298,17 @@ where
         self.inner.poll_ready(cx)
     }
 
-    fn call(&mut self, request: http::Request<ReqBody>) -> Self::Future {
+    fn call(&mut self, mut request: http::Request<ReqBody>) -> Self::Future {
         let uri = request.uri().path().to_string();
         let uri_parts: Vec<&str> = uri.split('/').collect();
+        let audit_req = audit::Request::new(&request);
+
+        // Pass the decoded AuthID to the auth interceptor, so that the token is not decoded
+        // a second time.
+        if audit_req.auth_id != auth::AuthID::None {
+            request.extensions_mut().insert(audit_req.auth_id.clone());
+        }
+
         let future = self.inner.call(request);
         let start = Instant::now();
         ApiLoggerFuture {
@@ -285,17 +316,25 @@ where
             start,
             service: uri_parts.get(1).map(|v| v.to_string()).unwrap_or_default(),
             method: uri_parts.get(2).map(|v| v.to_string()).unwrap_or_default(),
+            audit_req,
+            audit_future: None,
+            result: None,
         }
     }
 }
 
 #[pin_project]
-struct ApiLoggerFuture<F> {
+struct ApiLoggerFuture<F: Future> {
     #[pin]
     future: F,
     start: Instant,
     service: String,
     method: String,
+    audit_req: audit::Request,
+    // In case the request must be written to the audit log, the response is held back until
+    // the audit log has been written.
+    audit_future: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+    result: Option<F::Output>,
 }
 
 impl<ResBody, F, E> Future for ApiLoggerFuture<F>
@@ -309,6 +348,16 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.project();
 
+        if let Some(audit_future) = this.audit_future {
+            return match audit_future.as_mut().poll(cx) {
+                Poll::Ready(()) => {
+                    *this.audit_future = None;
+                    Poll::Ready(this.result.take().expect("result must be set"))
+                }
+                Poll::Pending => Poll::Pending,
+            };
+        }
+
         match this.future.poll(cx) {
             Poll::Ready(result) => {
                 if let Ok(response) = &result {
@@ -352,11 +401,44 @@ where
                             .collect(),
                     };
 
-                    task::spawn(async move {
-                        if let Err(e) = stream::api_request::log_request(&req_log).await {
-                            error!(error = %e.full(), "Log request error");
+                    task::spawn({
+                        let req_log = req_log.clone();
+                        async move {
+                            if let Err(e) = stream::api_request::log_request(&req_log).await {
+                                error!(error = %e.full(), "Log request error");
+                            }
                         }
                     });
+
+                    // Log mutating API request to the audit log
+                    if status_code == Code::Ok && audit::is_mutating(this.service, this.method) {
+                        let audit_req = this.audit_req.clone();
+                        let changes = response.extensions().get::<audit_log::Changes>().cloned();
+
+                        let mut audit_future = Box::pin(async move {
+                            if let Err(e) = audit::log_request(
+                                &audit_req,
+                                &req_log.service,
+                                &req_log.method,
+                                &req_log.metadata,
+                                changes,
+                            )
+                            .await
+                            {
+                                // The mutation has already been applied at this point, therefore
+                                // the request does not fail. Instead, the error is logged and
+                                // counted in the api_audit_log_errors metric.
+                                error!(error = %e.full(), "Log audit error");
+                                AUDIT_LOG_ERROR_COUNTER.inc();
+                            }
+                        });
+
+                        if audit_future.as_mut().poll(cx).is_pending() {
+                            *this.audit_future = Some(audit_future);
+                            *this.result = Some(result);
+                            return Poll::Pending;
+                        }
+                    }
                 }
                 Poll::Ready(result)
             }
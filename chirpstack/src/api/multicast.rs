@@ -12,7 +12,7 @@ use super::auth::validator;
 use super::error::ToStatus;
 use super::helpers::{self, FromProto, ToProto};
 use crate::downlink;
-use crate::storage::multicast;
+use crate::storage::{audit_log, fields, multicast};
 
 pub struct MulticastGroup {
     validator: validator::RequestValidator,
@@ -38,6 +38,11 @@ impl MulticastGroupService for MulticastGroup {
         };
 
         let app_id = Uuid::from_str(&req_mg.application_id).map_err(|e| e.status())?;
+        let selector_dp_id: Option<Uuid> = if req_mg.selector_device_profile_id.is_empty() {
+            None
+        } else {
+            Some(Uuid::from_str(&req_mg.selector_device_profile_id).map_err(|e| e.status())?)
+        };
 
         self.validator
             .validate(
@@ -70,6 +75,9 @@ impl MulticastGroupService for MulticastGroup {
                 req_mg.class_b_ping_slot_nb_k
             } as i16,
             class_c_scheduling_type: req_mg.class_c_scheduling_type().from_proto(),
+            auto_membership: req_mg.auto_membership,
+            selector_device_profile_id: selector_dp_id,
+            selector_tags: fields::KeyValue::new(req_mg.selector_tags.clone()),
             ..Default::default()
         };
         let mg = multicast::create(mg).await.map_err(|e| e.status())?;
@@ -124,6 +132,12 @@ impl MulticastGroupService for MulticastGroup {
                 class_b_ping_slot_period: (1 << (mg.class_b_ping_slot_nb_k as u32)) * 32,
                 class_b_ping_slot_nb_k: mg.class_b_ping_slot_nb_k as u32,
                 class_c_scheduling_type: mg.class_c_scheduling_type.to_proto().into(),
+                auto_membership: mg.auto_membership,
+                selector_device_profile_id: mg
+                    .selector_device_profile_id
+                    .map(|v| v.to_string())
+                    .unwrap_or_default(),
+                selector_tags: mg.selector_tags.into_hashmap(),
             }),
             created_at: Some(helpers::datetime_to_prost_timestamp(&mg.created_at)),
             updated_at: Some(helpers::datetime_to_prost_timestamp(&mg.updated_at)),
@@ -145,6 +159,11 @@ impl MulticastGroupService for MulticastGroup {
             }
         };
         let mg_id = Uuid::from_str(&req_mg.id).map_err(|e| e.status())?;
+        let selector_dp_id: Option<Uuid> = if req_mg.selector_device_profile_id.is_empty() {
+            None
+        } else {
+            Some(Uuid::from_str(&req_mg.selector_device_profile_id).map_err(|e| e.status())?)
+        };
 
         self.validator
             .validate(
@@ -153,7 +172,9 @@ impl MulticastGroupService for MulticastGroup {
             )
             .await?;
 
-        let _ = multicast::update(multicast::MulticastGroup {
+        let mg_old = multicast::get(&mg_id).await.map_err(|e| e.status())?;
+
+        let mg = multicast::update(multicast::MulticastGroup {
             id: mg_id,
             name: req_mg.name.clone(),
             region: req_mg.region().from_proto(),
@@ -177,6 +198,9 @@ impl MulticastGroupService for MulticastGroup {
                 req_mg.class_b_ping_slot_nb_k
             } as i16,
             class_c_scheduling_type: req_mg.class_c_scheduling_type().from_proto(),
+            auto_membership: req_mg.auto_membership,
+            selector_device_profile_id: selector_dp_id,
+            selector_tags: fields::KeyValue::new(req_mg.selector_tags.clone()),
             ..Default::default()
         })
         .await
@@ -186,6 +210,36 @@ impl MulticastGroupService for MulticastGroup {
         resp.metadata_mut()
             .insert("x-log-multicast_group_id", req_mg.id.parse().unwrap());
 
+        resp.extensions_mut().insert(
+            audit_log::Changes::new()
+                .field("name", &mg_old.name, &mg.name)
+                .field("region", &mg_old.region, &mg.region)
+                .field("group_type", &mg_old.group_type, &mg.group_type)
+                .field("dr", &mg_old.dr, &mg.dr)
+                .field("frequency", &mg_old.frequency, &mg.frequency)
+                .field(
+                    "class_b_ping_slot_nb_k",
+                    &mg_old.class_b_ping_slot_nb_k,
+                    &mg.class_b_ping_slot_nb_k,
+                )
+                .field(
+                    "class_c_scheduling_type",
+                    &mg_old.class_c_scheduling_type,
+                    &mg.class_c_scheduling_type,
+                )
+                .field(
+                    "auto_membership",
+                    &mg_old.auto_membership,
+                    &mg.auto_membership,
+                )
+                .field(
+                    "selector_device_profile_id",
+                    &mg_old.selector_device_profile_id,
+                    &mg.selector_device_profile_id,
+                )
+                .field("selector_tags", &mg_old.selector_tags, &mg.selector_tags),
+        );
+
         Ok(resp)
     }
 
@@ -203,11 +257,17 @@ impl MulticastGroupService for MulticastGroup {
             )
             .await?;
 
+        let mg = multicast::get(&mg_id).await.map_err(|e| e.status())?;
+
         multicast::delete(&mg_id).await.map_err(|e| e.status())?;
 
         let mut resp = Response::new(());
         resp.metadata_mut()
             .insert("x-log-multicast_group_id", req.id.parse().unwrap());
+        resp.metadata_mut().insert(
+            "x-log-application_id",
+            mg.application_id.to_string().parse().unwrap(),
+        );
 
         Ok(resp)
     }
@@ -490,6 +550,55 @@ impl MulticastGroupService for MulticastGroup {
             req.multicast_group_id.parse().unwrap(),
         );
 
+        Ok(resp)
+    }
+    async fn get_queue_item_status(
+        &self,
+        request: Request<api::GetMulticastGroupQueueItemStatusRequest>,
+    ) -> Result<Response<api::GetMulticastGroupQueueItemStatusResponse>, Status> {
+        let req = request.get_ref();
+        let mg_id = Uuid::from_str(&req.multicast_group_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateMulticastGroupQueueAccess::new(validator::Flag::List, mg_id),
+            )
+            .await?;
+
+        let deliveries = multicast::get_queue_item_deliveries(&mg_id, req.f_cnt)
+            .await
+            .map_err(|e| e.status())?;
+        let coverage = downlink::multicast::get_delivery_coverage(&deliveries)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::GetMulticastGroupQueueItemStatusResponse {
+            created_at: deliveries
+                .first()
+                .map(|d| helpers::datetime_to_prost_timestamp(&d.created_at)),
+            gateways: deliveries
+                .iter()
+                .map(|d| api::MulticastGroupQueueItemGatewayStatus {
+                    gateway_id: d.gateway_id.to_string(),
+                    pending: d.tx_ack_status.is_none(),
+                    tx_ack_status: d.tx_ack_status.clone().unwrap_or_default(),
+                    device_count: coverage
+                        .gateway_device_count
+                        .get(&d.gateway_id)
+                        .cloned()
+                        .unwrap_or_default() as u32,
+                    updated_at: Some(helpers::datetime_to_prost_timestamp(&d.updated_at)),
+                })
+                .collect(),
+            device_count: coverage.device_count as u32,
+            covered_device_count: coverage.covered_device_count as u32,
+        });
+        resp.metadata_mut().insert(
+            "x-log-multicast_group_id",
+            req.multicast_group_id.parse().unwrap(),
+        );
+
         Ok(resp)
     }
 }
@@ -627,6 +736,7 @@ pub mod test {
                 class_b_ping_slot_nb_k: 1,
                 class_b_ping_slot_period: 64,
                 class_c_scheduling_type: api::MulticastGroupSchedulingType::GpsTime.into(),
+                ..Default::default()
             }),
             get_resp.get_ref().multicast_group
         );
@@ -650,6 +760,7 @@ pub mod test {
                     class_b_ping_slot_nb_k: 2,
                     class_b_ping_slot_period: 0,
                     class_c_scheduling_type: api::MulticastGroupSchedulingType::Delay.into(),
+                    ..Default::default()
                 }),
             },
         );
@@ -679,6 +790,7 @@ pub mod test {
                 class_b_ping_slot_nb_k: 2,
                 class_b_ping_slot_period: 128,
                 class_c_scheduling_type: api::MulticastGroupSchedulingType::Delay.into(),
+                ..Default::default()
             }),
             get_resp.get_ref().multicast_group
         );
@@ -1,4 +1,5 @@
 use std::str::FromStr;
+use std::time::SystemTime;
 
 use tonic::{Request, Response, Status};
 use uuid::Uuid;
@@ -9,7 +10,8 @@ use chirpstack_api::api::tenant_service_server::TenantService;
 use super::auth::{validator, AuthID};
 use super::error::ToStatus;
 use super::helpers;
-use crate::storage::{fields, tenant, user};
+use crate::codec;
+use crate::storage::{audit_log, fields, tenant, user};
 
 pub struct Tenant {
     validator: validator::RequestValidator,
@@ -49,6 +51,7 @@ impl TenantService for Tenant {
             max_gateway_count: req_tenant.max_gateway_count as i32,
             private_gateways_up: req_tenant.private_gateways_up,
             private_gateways_down: req_tenant.private_gateways_down,
+            require_totp: req_tenant.require_totp,
             tags: fields::KeyValue::new(req_tenant.tags.clone()),
             ..Default::default()
         };
@@ -90,6 +93,7 @@ impl TenantService for Tenant {
                 max_device_count: t.max_device_count as u32,
                 private_gateways_up: t.private_gateways_up,
                 private_gateways_down: t.private_gateways_down,
+                require_totp: t.require_totp,
                 tags: t.tags.into_hashmap(),
             }),
             created_at: Some(helpers::datetime_to_prost_timestamp(&t.created_at)),
@@ -121,7 +125,9 @@ impl TenantService for Tenant {
             .await?;
 
         // update
-        let _ = tenant::update(tenant::Tenant {
+        let t_old = tenant::get(&tenant_id).await.map_err(|e| e.status())?;
+
+        let t = tenant::update(tenant::Tenant {
             id: tenant_id,
             name: req_tenant.name.clone(),
             description: req_tenant.description.clone(),
@@ -130,6 +136,7 @@ impl TenantService for Tenant {
             max_gateway_count: req_tenant.max_gateway_count as i32,
             private_gateways_up: req_tenant.private_gateways_up,
             private_gateways_down: req_tenant.private_gateways_down,
+            require_totp: req_tenant.require_totp,
             tags: fields::KeyValue::new(req_tenant.tags.clone()),
             ..Default::default()
         })
@@ -140,6 +147,39 @@ impl TenantService for Tenant {
         resp.metadata_mut()
             .insert("x-log-tenant_id", req_tenant.id.parse().unwrap());
 
+        resp.extensions_mut().insert(
+            audit_log::Changes::new()
+                .field("name", &t_old.name, &t.name)
+                .field("description", &t_old.description, &t.description)
+                .field(
+                    "can_have_gateways",
+                    &t_old.can_have_gateways,
+                    &t.can_have_gateways,
+                )
+                .field(
+                    "max_device_count",
+                    &t_old.max_device_count,
+                    &t.max_device_count,
+                )
+                .field(
+                    "max_gateway_count",
+                    &t_old.max_gateway_count,
+                    &t.max_gateway_count,
+                )
+                .field(
+                    "private_gateways_up",
+                    &t_old.private_gateways_up,
+                    &t.private_gateways_up,
+                )
+                .field(
+                    "private_gateways_down",
+                    &t_old.private_gateways_down,
+                    &t.private_gateways_down,
+                )
+                .field("require_totp", &t_old.require_totp, &t.require_totp)
+                .field("tags", &t_old.tags, &t.tags),
+        );
+
         Ok(resp)
     }
 
@@ -444,6 +484,85 @@ impl TenantService for Tenant {
 
         Ok(resp)
     }
+
+    async fn list_audit_log(
+        &self,
+        request: Request<api::ListAuditLogRequest>,
+    ) -> Result<Response<api::ListAuditLogResponse>, Status> {
+        let req = request.get_ref();
+        let tenant_id = Uuid::from_str(&req.tenant_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantAuditLogAccess::new(validator::Flag::List, tenant_id),
+            )
+            .await?;
+
+        let filters = audit_log::Filters {
+            tenant_id: Some(tenant_id),
+            user_id: if req.user_id.is_empty() {
+                None
+            } else {
+                Some(Uuid::from_str(&req.user_id).map_err(|e| e.status())?)
+            },
+            api_key_id: if req.api_key_id.is_empty() {
+                None
+            } else {
+                Some(Uuid::from_str(&req.api_key_id).map_err(|e| e.status())?)
+            },
+            method: if req.method.is_empty() {
+                None
+            } else {
+                Some(req.method.clone())
+            },
+            target_id: if req.target_id.is_empty() {
+                None
+            } else {
+                Some(req.target_id.clone())
+            },
+            start: match &req.start {
+                Some(v) => Some(SystemTime::try_from(*v).map_err(|e| e.status())?.into()),
+                None => None,
+            },
+            end: match &req.end {
+                Some(v) => Some(SystemTime::try_from(*v).map_err(|e| e.status())?.into()),
+                None => None,
+            },
+        };
+
+        let count = audit_log::get_count(&filters)
+            .await
+            .map_err(|e| e.status())?;
+        let result = audit_log::list(req.limit as i64, req.offset as i64, &filters)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::ListAuditLogResponse {
+            total_count: count as u32,
+            result: result
+                .iter()
+                .map(|a| api::AuditLogItem {
+                    id: a.id.to_string(),
+                    created_at: Some(helpers::datetime_to_prost_timestamp(&a.created_at)),
+                    tenant_id: a.tenant_id.map(|v| v.to_string()).unwrap_or_default(),
+                    user_id: a.user_id.map(|v| v.to_string()).unwrap_or_default(),
+                    api_key_id: a.api_key_id.map(|v| v.to_string()).unwrap_or_default(),
+                    service: a.service.clone(),
+                    method: a.method.clone(),
+                    target_id: a.target_id.clone(),
+                    changes: serde_json::from_value::<pbjson_types::Struct>(a.changes.clone())
+                        .ok()
+                        .map(|v| codec::convert::pb_json_to_prost(&v)),
+                    source_ip: a.source_ip.clone(),
+                })
+                .collect(),
+        });
+        resp.metadata_mut()
+            .insert("x-log-tenant_id", req.tenant_id.parse().unwrap());
+
+        Ok(resp)
+    }
 }
 
 #[cfg(test)]
@@ -519,7 +638,11 @@ pub mod test {
         };
         let mut up_req = Request::new(up_req);
         up_req.extensions_mut().insert(AuthID::User(u.id));
-        let _ = service.update(up_req).await.unwrap();
+        let up_resp = service.update(up_req).await.unwrap();
+        assert_eq!(
+            Some(&audit_log::Changes::new().field("name", &"Test tenant", &"Test tenant updated")),
+            up_resp.extensions().get::<audit_log::Changes>()
+        );
 
         // get
         let get_req = api::GetTenantRequest {
@@ -570,4 +693,78 @@ pub mod test {
         let del_resp = service.delete(del_req).await;
         assert!(del_resp.is_err());
     }
+
+    #[tokio::test]
+    async fn test_list_audit_log() {
+        let _guard = test::prepare().await;
+
+        // setup admin user
+        let u = user::User {
+            is_admin: true,
+            is_active: true,
+            email: "admin@admin".into(),
+            email_verified: true,
+            ..Default::default()
+        };
+        let u = user::create(u).await.unwrap();
+
+        let t = tenant::test::create_tenant().await;
+        let t_other = tenant::test::create_tenant().await;
+
+        let a = audit_log::create(audit_log::AuditLog {
+            tenant_id: Some(t.id),
+            user_id: Some(u.id),
+            service: "api.DeviceService".into(),
+            method: "Delete".into(),
+            target_id: "0102030405060708".into(),
+            source_ip: "127.0.0.1".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        audit_log::create(audit_log::AuditLog {
+            tenant_id: Some(t_other.id),
+            user_id: Some(u.id),
+            service: "api.DeviceService".into(),
+            method: "Delete".into(),
+            target_id: "0202030405060708".into(),
+            source_ip: "127.0.0.1".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // setup api
+        let service = Tenant::new(RequestValidator::new());
+
+        // list
+        let list_req = api::ListAuditLogRequest {
+            tenant_id: t.id.to_string(),
+            limit: 10,
+            method: "Delete".into(),
+            ..Default::default()
+        };
+        let mut list_req = Request::new(list_req);
+        list_req.extensions_mut().insert(AuthID::User(u.id));
+        let list_resp = service.list_audit_log(list_req).await.unwrap();
+        assert_eq!(
+            api::ListAuditLogResponse {
+                total_count: 1,
+                result: vec![api::AuditLogItem {
+                    id: a.id.to_string(),
+                    created_at: Some(helpers::datetime_to_prost_timestamp(&a.created_at)),
+                    tenant_id: t.id.to_string(),
+                    user_id: u.id.to_string(),
+                    api_key_id: "".into(),
+                    service: "api.DeviceService".into(),
+                    method: "Delete".into(),
+                    target_id: "0102030405060708".into(),
+                    changes: Some(Default::default()),
+                    source_ip: "127.0.0.1".into(),
+                }],
+            },
+            list_resp.into_inner()
+        );
+    }
 }
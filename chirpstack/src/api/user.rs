@@ -10,7 +10,7 @@ use chirpstack_api::api::user_service_server::UserService;
 use super::auth::{validator, AuthID};
 use super::error::ToStatus;
 use super::helpers;
-use crate::storage::{tenant, user};
+use crate::storage::{audit_log, tenant, user};
 
 pub struct User {
     validator: validator::RequestValidator,
@@ -137,7 +137,9 @@ impl UserService for User {
             .await?;
 
         // update
-        let _ = user::update(user::User {
+        let u_old = user::get(&user_id).await.map_err(|e| e.status())?;
+
+        let u = user::update(user::User {
             id: user_id,
             is_admin: req_user.is_admin,
             is_active: req_user.is_active,
@@ -153,6 +155,14 @@ impl UserService for User {
         resp.metadata_mut()
             .insert("x-log-user_id", req_user.id.parse().unwrap());
 
+        resp.extensions_mut().insert(
+            audit_log::Changes::new()
+                .field("is_admin", &u_old.is_admin, &u.is_admin)
+                .field("is_active", &u_old.is_active, &u.is_active)
+                .field("email", &u_old.email, &u.email)
+                .field("note", &u_old.note, &u.note),
+        );
+
         Ok(resp)
     }
 
@@ -253,6 +263,28 @@ impl UserService for User {
 
         Ok(resp)
     }
+
+    async fn disable_totp(
+        &self,
+        request: Request<api::DisableUserTotpRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.get_ref();
+        let user_id = Uuid::from_str(&req.user_id).map_err(|e| e.status())?;
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateUserAccess::new(validator::Flag::Update, user_id),
+            )
+            .await?;
+
+        let _ = user::disable_totp(&user_id).await.map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-user_id", req.user_id.parse().unwrap());
+
+        Ok(resp)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,209 @@
+use anyhow::Result;
+use tracing::{debug, info, warn};
+
+use lrwn::applayer::clocksync;
+use lrwn::{EUI64, LA_FPORT_CLOCK_SYNC};
+
+use crate::gpstime::ToGpsTime;
+use crate::storage::error::Error;
+use crate::storage::{device, device_queue};
+use crate::uplink::helpers;
+use chirpstack_api::integration as integration_pb;
+
+/// Handles the clock sync requests sent by the device.
+pub async fn handle_uplink(dev: &device::Device, up: &integration_pb::UplinkEvent) -> Result<()> {
+    match clocksync::Payload::from_slice(true, &up.data)? {
+        clocksync::Payload::AppTimeReq(pl) => handle_app_time_req(dev, up, pl).await,
+        clocksync::Payload::DeviceAppTimePeriodicityAns(pl) => {
+            handle_device_app_time_periodicity_ans(dev, up, pl);
+            Ok(())
+        }
+        pl => {
+            debug!(dev_eui = %dev.dev_eui, cid = ?pl.cid(), "Ignoring clock sync payload");
+            Ok(())
+        }
+    }
+}
+
+async fn handle_app_time_req(
+    dev: &device::Device,
+    up: &integration_pb::UplinkEvent,
+    pl: clocksync::AppTimeReqPayload,
+) -> Result<()> {
+    let rx_time = helpers::get_rx_timestamp_chrono(&up.rx_info);
+    let gps_time = rx_time.to_gps_time().num_seconds() as u32;
+    let time_correction = gps_time.wrapping_sub(pl.device_time) as i32;
+
+    info!(dev_eui = %dev.dev_eui, device_time = pl.device_time, gps_time = gps_time, time_correction = time_correction, "AppTimeReq received");
+
+    // The device only expects an answer when it is out of sync, unless requested otherwise.
+    if time_correction == 0 && !pl.ans_required {
+        return Ok(());
+    }
+
+    enqueue(
+        &dev.dev_eui,
+        clocksync::Payload::AppTimeAns(clocksync::AppTimeAnsPayload {
+            time_correction,
+            token_ans: pl.token_req,
+        }),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Requests the device to re-synchronize its clock by sending the given number of AppTimeReq
+/// transmissions.
+pub async fn force_resync(dev_eui: &EUI64, nb_transmissions: u8) -> Result<(), Error> {
+    if !(1..=7).contains(&nb_transmissions) {
+        return Err(Error::Validation(
+            "nb_transmissions must be between 1 and 7".into(),
+        ));
+    }
+
+    enqueue(
+        dev_eui,
+        clocksync::Payload::ForceDeviceResyncReq(clocksync::ForceDeviceResyncReqPayload {
+            nb_transmissions,
+        }),
+    )
+    .await
+}
+
+/// Sets the periodicity by which the device sends AppTimeReq, which is 128 * 2^periodicity
+/// seconds.
+pub async fn set_periodicity(dev_eui: &EUI64, periodicity: u8) -> Result<(), Error> {
+    if periodicity > 15 {
+        return Err(Error::Validation(
+            "periodicity must be between 0 and 15".into(),
+        ));
+    }
+
+    enqueue(
+        dev_eui,
+        clocksync::Payload::DeviceAppTimePeriodicityReq(
+            clocksync::DeviceAppTimePeriodicityReqPayload { periodicity },
+        ),
+    )
+    .await
+}
+
+fn handle_device_app_time_periodicity_ans(
+    dev: &device::Device,
+    up: &integration_pb::UplinkEvent,
+    pl: clocksync::DeviceAppTimePeriodicityAnsPayload,
+) {
+    if pl.not_supported {
+        warn!(dev_eui = %dev.dev_eui, "Device does not support the requested AppTimeReq periodicity");
+        return;
+    }
+
+    let rx_time = helpers::get_rx_timestamp_chrono(&up.rx_info);
+    let gps_time = rx_time.to_gps_time().num_seconds() as u32;
+    info!(dev_eui = %dev.dev_eui, device_time = pl.time, gps_time = gps_time, time_correction = gps_time.wrapping_sub(pl.time) as i32, "DeviceAppTimePeriodicityAns received");
+}
+
+async fn enqueue(dev_eui: &EUI64, pl: clocksync::Payload) -> Result<(), Error> {
+    device_queue::enqueue_item(device_queue::DeviceQueueItem {
+        dev_eui: *dev_eui,
+        f_port: LA_FPORT_CLOCK_SYNC as i16,
+        data: pl.to_vec()?,
+        ..Default::default()
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage;
+    use crate::test;
+    use chirpstack_api::gw;
+    use chrono::Utc;
+
+    #[tokio::test]
+    async fn test_app_time_req() {
+        let _guard = test::prepare().await;
+
+        let dp = storage::device_profile::test::create_device_profile(None).await;
+        let dev = storage::device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            None,
+        )
+        .await;
+
+        let rx_time = Utc::now();
+        let gps_time = rx_time.to_gps_time().num_seconds() as u32;
+        let up = |device_time: u32, ans_required: bool| integration_pb::UplinkEvent {
+            f_port: LA_FPORT_CLOCK_SYNC as u32,
+            data: clocksync::Payload::AppTimeReq(clocksync::AppTimeReqPayload {
+                device_time,
+                ans_required,
+                token_req: 5,
+            })
+            .to_vec()
+            .unwrap(),
+            rx_info: vec![gw::UplinkRxInfo {
+                gw_time: Some(rx_time.into()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // In sync, no answer required.
+        handle_uplink(&dev, &up(gps_time, false)).await.unwrap();
+        assert!(device_queue::get_for_dev_eui(&dev.dev_eui)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Out of sync.
+        handle_uplink(&dev, &up(gps_time - 10, false))
+            .await
+            .unwrap();
+        let items = device_queue::get_for_dev_eui(&dev.dev_eui).await.unwrap();
+        assert_eq!(1, items.len());
+        assert_eq!(LA_FPORT_CLOCK_SYNC as i16, items[0].f_port);
+        assert_eq!(
+            clocksync::Payload::AppTimeAns(clocksync::AppTimeAnsPayload {
+                time_correction: 10,
+                token_ans: 5,
+            }),
+            clocksync::Payload::from_slice(false, &items[0].data).unwrap()
+        );
+
+        // In sync, answer required.
+        handle_uplink(&dev, &up(gps_time, true)).await.unwrap();
+        let items = device_queue::get_for_dev_eui(&dev.dev_eui).await.unwrap();
+        assert_eq!(2, items.len());
+    }
+
+    #[tokio::test]
+    async fn test_force_resync_and_periodicity() {
+        let _guard = test::prepare().await;
+
+        let dp = storage::device_profile::test::create_device_profile(None).await;
+        let dev = storage::device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            None,
+        )
+        .await;
+
+        assert!(force_resync(&dev.dev_eui, 0).await.is_err());
+        assert!(set_periodicity(&dev.dev_eui, 16).await.is_err());
+
+        force_resync(&dev.dev_eui, 3).await.unwrap();
+        set_periodicity(&dev.dev_eui, 9).await.unwrap();
+
+        let items = device_queue::get_for_dev_eui(&dev.dev_eui).await.unwrap();
+        assert_eq!(2, items.len());
+        assert_eq!(LA_FPORT_CLOCK_SYNC as i16, items[0].f_port);
+        assert_eq!(vec![0x03, 0x03], items[0].data);
+        assert_eq!(vec![0x02, 0x09], items[1].data);
+    }
+}
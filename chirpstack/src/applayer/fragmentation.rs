@@ -0,0 +1,798 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::Utc;
+use tracing::{debug, info, warn};
+
+use lrwn::applayer::fragmentation;
+use lrwn::{EUI64, LA_FPORT_FRAGMENTATION};
+
+use super::fuota;
+use crate::api::helpers::{FromProto, ToProto};
+use crate::integration;
+use crate::region;
+use crate::storage::error::Error;
+use crate::storage::fields::FragmentationSessionStatus;
+use crate::storage::fragmentation_session::{self, FragmentationSession};
+use crate::storage::helpers::get_all_device_data;
+use crate::storage::{device, device_profile, device_queue};
+use chirpstack_api::integration as integration_pb;
+
+// Size of the CID + IndexAndN fields of the DataFragment command.
+pub const DATA_FRAGMENT_OVERHEAD: usize = 3;
+
+/// Splits the data of the given queue-item into fragments and enqueues these, together with the
+/// FragSessionSetupReq and FragSessionStatusReq commands. The queue-item is used as template
+/// for the created queue-items. When the fragment_size of the session is 0, it is derived from
+/// the current data-rate of the device.
+pub async fn enqueue(
+    mut fs: FragmentationSession,
+    qi: device_queue::DeviceQueueItem,
+) -> Result<(FragmentationSession, Vec<device_queue::DeviceQueueItem>), Error> {
+    if qi.is_encrypted {
+        return Err(Error::Validation(
+            "Fragmentation can not be used with encrypted payloads".to_string(),
+        ));
+    }
+
+    if qi.data.is_empty() {
+        return Err(Error::Validation("data must not be empty".to_string()));
+    }
+
+    match fragmentation_session::get_active_for_dev_eui_and_frag_index(
+        &qi.dev_eui,
+        fs.frag_index as u8,
+    )
+    .await
+    {
+        Ok(_) => {
+            return Err(Error::Validation(format!(
+                "A fragmentation session with frag_index {} is already active",
+                fs.frag_index
+            )));
+        }
+        Err(Error::NotFound(_)) => {}
+        Err(e) => {
+            return Err(e);
+        }
+    }
+
+    if fuota::is_frag_index_in_use(&qi.dev_eui, fs.frag_index as u8).await? {
+        return Err(Error::Validation(format!(
+            "frag_index {} is in use by a running FUOTA campaign",
+            fs.frag_index
+        )));
+    }
+
+    let max_fragment_size = get_max_fragment_size(&qi.dev_eui).await?;
+    if fs.fragment_size == 0 {
+        fs.fragment_size = max_fragment_size
+            .ok_or_else(|| {
+                Error::Validation("Device is not activated, fragment_size must be set".to_string())
+            })?
+            .min(u8::MAX as usize) as i16;
+    }
+
+    if fs.fragment_size <= 0 || fs.fragment_size > u8::MAX as i16 {
+        return Err(Error::Validation(format!(
+            "fragment_size must be between 1 and {}",
+            u8::MAX
+        )));
+    }
+
+    if let Some(max_fragment_size) = max_fragment_size {
+        if fs.fragment_size as usize > max_fragment_size {
+            return Err(Error::Validation(format!(
+                "fragment_size {} exceeds the max. fragment size {} of the current data-rate",
+                fs.fragment_size, max_fragment_size
+            )));
+        }
+    }
+
+    let fragment_size: usize = fs.fragment_size as usize;
+    let padding = fragmentation::get_padding(qi.data.len(), fragment_size);
+    let mut data = qi.data.clone();
+    data.resize(data.len() + padding, 0);
+
+    fs.dev_eui = qi.dev_eui;
+    fs.padding = padding as i16;
+    fs.nb_frag = (data.len() / fragment_size) as i32;
+    fs.nb_frag_sent = 0;
+    fs.status = FragmentationSessionStatus::PENDING;
+
+    let frag_index = fs.frag_index as u8;
+    let mut payloads: Vec<fragmentation::Payload> =
+        vec![fragmentation::Payload::FragSessionSetupReq(
+            fragmentation::FragSessionSetupReqPayload {
+                frag_index,
+                mc_group_bit_mask: [false; 4],
+                nb_frag: fs.nb_frag as u16,
+                frag_size: fs.fragment_size as u8,
+                fragmentation_matrix: 0,
+                block_ack_delay: 0,
+                padding: fs.padding as u8,
+                descriptor: [0; 4],
+            },
+        )];
+
+    for (i, fragment) in fragmentation::encode(&data, fragment_size, fs.redundancy as usize)?
+        .into_iter()
+        .enumerate()
+    {
+        payloads.push(fragmentation::Payload::DataFragment(
+            fragmentation::DataFragmentPayload {
+                frag_index,
+                n: (i + 1) as u16,
+                data: fragment,
+            },
+        ));
+    }
+
+    payloads.push(fragmentation::Payload::FragSessionStatusReq(
+        fragmentation::FragSessionStatusReqPayload {
+            frag_index,
+            participants: true,
+        },
+    ));
+
+    let mut items = Vec::with_capacity(payloads.len());
+    for pl in payloads {
+        items.push(device_queue::DeviceQueueItem {
+            dev_eui: qi.dev_eui,
+            f_port: LA_FPORT_FRAGMENTATION as i16,
+            data: pl.to_vec()?,
+            expires_at: qi.expires_at,
+            priority: qi.priority,
+            ..Default::default()
+        });
+    }
+
+    // The session and its queue-items are created within a single transaction, such that a
+    // failure does not leave a session without (all of) its fragments.
+    let (fs, items) = fragmentation_session::create_and_enqueue(fs, items).await?;
+
+    info!(dev_eui = %fs.dev_eui, fragmentation_session_id = %fs.id, nb_frag = fs.nb_frag, redundancy = fs.redundancy, "Fragmentation-session enqueued");
+
+    Ok((fs, items))
+}
+
+/// Updates the number of sent fragments of the fragmentation-session in case the given
+/// queue-item contains a DataFragment.
+pub async fn handle_tx_ack(qi: &device_queue::DeviceQueueItem) -> Result<()> {
+    if qi.f_port != LA_FPORT_FRAGMENTATION as i16 || qi.is_encrypted {
+        return Ok(());
+    }
+
+    if let Ok(fragmentation::Payload::DataFragment(pl)) =
+        fragmentation::Payload::from_slice(false, &qi.data)
+    {
+        match fragmentation_session::get_active_for_dev_eui_and_frag_index(
+            &qi.dev_eui,
+            pl.frag_index,
+        )
+        .await
+        {
+            Ok(fs) => {
+                fragmentation_session::increment_nb_frag_sent(&fs.id).await?;
+            }
+            Err(Error::NotFound(_)) => {}
+            Err(e) => {
+                return Err(e.into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Handles the fragmentation answers sent by the device. Answers which are not related to a
+/// fragmentation-session of the device-queue are forwarded to the FUOTA campaign handler.
+pub async fn handle_uplink(dev: &device::Device, up: &integration_pb::UplinkEvent) -> Result<()> {
+    let pl = fragmentation::Payload::from_slice(true, &up.data)?;
+    let frag_index = match &pl {
+        fragmentation::Payload::FragSessionSetupAns(pl) => pl.frag_index,
+        fragmentation::Payload::FragSessionStatusAns(pl) => pl.frag_index,
+        _ => {
+            debug!(dev_eui = %dev.dev_eui, cid = ?pl.cid(), "Ignoring fragmentation payload");
+            return Ok(());
+        }
+    };
+
+    let fs = match fragmentation_session::get_active_for_dev_eui_and_frag_index(
+        &dev.dev_eui,
+        frag_index,
+    )
+    .await
+    {
+        Ok(v) => v,
+        Err(Error::NotFound(_)) => {
+            return fuota::handle_fragmentation_uplink(dev, pl).await;
+        }
+        Err(e) => {
+            return Err(e.into());
+        }
+    };
+
+    match pl {
+        fragmentation::Payload::FragSessionSetupAns(pl) => {
+            handle_frag_session_setup_ans(dev, up, fs, pl).await
+        }
+        fragmentation::Payload::FragSessionStatusAns(pl) => {
+            handle_frag_session_status_ans(dev, up, fs, pl).await
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn handle_frag_session_setup_ans(
+    dev: &device::Device,
+    up: &integration_pb::UplinkEvent,
+    mut fs: FragmentationSession,
+    pl: fragmentation::FragSessionSetupAnsPayload,
+) -> Result<()> {
+    if !pl.is_error() {
+        fs.status = FragmentationSessionStatus::ACTIVE;
+        fragmentation_session::update(fs).await?;
+        return Ok(());
+    }
+
+    warn!(dev_eui = %dev.dev_eui, fragmentation_session_id = %fs.id, "Fragmentation-session setup failed");
+
+    fs.status = FragmentationSessionStatus::FAILED;
+    let fs = fragmentation_session::update(fs).await?;
+    flush_queue(&dev.dev_eui, pl.frag_index, false).await?;
+
+    let mut context = session_context(&fs);
+    for (k, v) in [
+        ("wrong_descriptor", pl.wrong_descriptor),
+        (
+            "frag_session_index_not_supported",
+            pl.frag_session_index_not_supported,
+        ),
+        ("not_enough_memory", pl.not_enough_memory),
+        ("encoding_unsupported", pl.encoding_unsupported),
+    ] {
+        context.insert(k.to_string(), v.to_string());
+    }
+
+    send_log_event(
+        dev,
+        up.device_info.clone(),
+        "Fragmentation-session setup failed".to_string(),
+        context,
+    )
+    .await;
+    send_integration_event(
+        dev,
+        up.device_info.clone(),
+        up.deduplication_id.clone(),
+        &fs,
+        "failed",
+    )
+    .await;
+
+    Ok(())
+}
+
+async fn handle_frag_session_status_ans(
+    dev: &device::Device,
+    up: &integration_pb::UplinkEvent,
+    mut fs: FragmentationSession,
+    pl: fragmentation::FragSessionStatusAnsPayload,
+) -> Result<()> {
+    fs.nb_frag_received = Some(pl.nb_frag_received.into());
+    fs.missing_frag = Some(pl.missing_frag.into());
+
+    if pl.missing_frag == 0 && !pl.not_enough_matrix_memory {
+        fs.status = FragmentationSessionStatus::COMPLETED;
+        fs.completed_at = Some(Utc::now());
+        let fs = fragmentation_session::update(fs).await?;
+
+        info!(dev_eui = %dev.dev_eui, fragmentation_session_id = %fs.id, "Fragmentation-session completed");
+        send_integration_event(
+            dev,
+            up.device_info.clone(),
+            up.deduplication_id.clone(),
+            &fs,
+            "completed",
+        )
+        .await;
+    } else {
+        fs.status = FragmentationSessionStatus::FAILED;
+        let fs = fragmentation_session::update(fs).await?;
+
+        warn!(dev_eui = %dev.dev_eui, fragmentation_session_id = %fs.id, missing_frag = pl.missing_frag, "Fragmentation-session failed");
+
+        let mut context = session_context(&fs);
+        context.insert(
+            "not_enough_matrix_memory".to_string(),
+            pl.not_enough_matrix_memory.to_string(),
+        );
+
+        send_log_event(
+            dev,
+            up.device_info.clone(),
+            "Fragmentation-session failed, device reported missing fragments".to_string(),
+            context,
+        )
+        .await;
+        send_integration_event(
+            dev,
+            up.device_info.clone(),
+            up.deduplication_id.clone(),
+            &fs,
+            "failed",
+        )
+        .await;
+    }
+
+    Ok(())
+}
+
+/// Handles a fragmentation-session which has been marked as failed because it did not complete
+/// within the configured timeout. The remaining queue-items of the session are removed.
+pub async fn handle_timeout(fs: &FragmentationSession) -> Result<()> {
+    warn!(dev_eui = %fs.dev_eui, fragmentation_session_id = %fs.id, "Fragmentation-session timed out");
+
+    flush_queue(&fs.dev_eui, fs.frag_index as u8, true).await?;
+
+    let (dev, app, t, dp) = get_all_device_data(fs.dev_eui).await?;
+    let device_info = integration_pb::DeviceInfo {
+        tenant_id: t.id.to_string(),
+        tenant_name: t.name.clone(),
+        application_id: app.id.to_string(),
+        application_name: app.name.to_string(),
+        device_profile_id: dp.id.to_string(),
+        device_profile_name: dp.name.clone(),
+        device_name: dev.name.clone(),
+        device_class_enabled: dev.enabled_class.to_proto().into(),
+        dev_eui: dev.dev_eui.to_string(),
+        tags: {
+            let mut tags = (*app.tags).clone();
+            tags.extend((*dp.tags).clone());
+            tags.extend((*dev.tags).clone());
+            tags
+        },
+    };
+
+    send_log_event(
+        &dev,
+        Some(device_info.clone()),
+        "Fragmentation-session timed out".to_string(),
+        session_context(fs),
+    )
+    .await;
+    send_integration_event(&dev, Some(device_info), "".to_string(), fs, "failed").await;
+
+    Ok(())
+}
+
+// Returns the max. fragment size, such that each DataFragment fits within the max. payload
+// size of both the RX1 (taking the RX1 data-rate offset into account) and RX2 data-rate. In
+// case the device is not activated, None is returned.
+async fn get_max_fragment_size(dev_eui: &EUI64) -> Result<Option<usize>, Error> {
+    let dev = device::get(dev_eui).await?;
+    let ds = match dev.get_device_session() {
+        Ok(v) => v,
+        Err(_) => return Ok(None),
+    };
+    let dp = device_profile::get(&dev.device_profile_id).await?;
+    let region_conf = region::get(&ds.region_config_id)?;
+
+    let rx1_dr = region_conf.get_rx1_data_rate_index(ds.dr as u8, ds.rx1_dr_offset as usize)?;
+
+    let mut max_pl_size = usize::MAX;
+    for dr in [rx1_dr, ds.rx2_dr as u8] {
+        let pl_size = region_conf.get_max_payload_size(
+            ds.mac_version().from_proto(),
+            dp.reg_params_revision,
+            dr,
+        )?;
+        max_pl_size = max_pl_size.min(pl_size.n);
+    }
+
+    Ok(Some(max_pl_size.saturating_sub(DATA_FRAGMENT_OVERHEAD)))
+}
+
+// Removes the queue-items of the given session which have not yet been sent. The
+// FragSessionSetupReq is only removed when include_setup is set.
+async fn flush_queue(dev_eui: &EUI64, frag_index: u8, include_setup: bool) -> Result<()> {
+    for qi in device_queue::get_for_dev_eui(dev_eui).await? {
+        if qi.f_port != LA_FPORT_FRAGMENTATION as i16 || qi.is_pending {
+            continue;
+        }
+
+        let qi_frag_index = match fragmentation::Payload::from_slice(false, &qi.data) {
+            Ok(fragmentation::Payload::FragSessionSetupReq(pl)) if include_setup => pl.frag_index,
+            Ok(fragmentation::Payload::DataFragment(pl)) => pl.frag_index,
+            Ok(fragmentation::Payload::FragSessionStatusReq(pl)) => pl.frag_index,
+            _ => continue,
+        };
+
+        if qi_frag_index == frag_index {
+            device_queue::delete_item(&qi.id).await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn session_context(fs: &FragmentationSession) -> HashMap<String, String> {
+    [
+        ("fragmentation_session_id".to_string(), fs.id.to_string()),
+        ("frag_index".to_string(), fs.frag_index.to_string()),
+    ]
+    .iter()
+    .cloned()
+    .collect()
+}
+
+async fn send_log_event(
+    dev: &device::Device,
+    device_info: Option<integration_pb::DeviceInfo>,
+    description: String,
+    context: HashMap<String, String>,
+) {
+    integration::log_event(
+        dev.application_id,
+        &dev.variables,
+        &integration_pb::LogEvent {
+            time: Some(Utc::now().into()),
+            device_info,
+            level: integration_pb::LogLevel::Error.into(),
+            code: integration_pb::LogCode::FragmentationSession.into(),
+            description,
+            context,
+        },
+    )
+    .await;
+}
+
+async fn send_integration_event(
+    dev: &device::Device,
+    device_info: Option<integration_pb::DeviceInfo>,
+    deduplication_id: String,
+    fs: &FragmentationSession,
+    event_type: &str,
+) {
+    let mut object: pbjson_types::Struct = Default::default();
+    object.fields.insert(
+        "fragmentationSessionId".to_string(),
+        pbjson_types::Value {
+            kind: Some(pbjson_types::value::Kind::StringValue(fs.id.to_string())),
+        },
+    );
+    for (k, v) in [
+        ("fragIndex", Some(fs.frag_index.into())),
+        ("nbFrag", Some(fs.nb_frag)),
+        ("redundancy", Some(fs.redundancy)),
+        ("fragmentSize", Some(fs.fragment_size.into())),
+        ("nbFragSent", Some(fs.nb_frag_sent)),
+        ("nbFragReceived", fs.nb_frag_received),
+        ("missingFrag", fs.missing_frag),
+    ] {
+        if let Some(v) = v {
+            object.fields.insert(
+                k.to_string(),
+                pbjson_types::Value {
+                    kind: Some(pbjson_types::value::Kind::NumberValue(v as f64)),
+                },
+            );
+        }
+    }
+
+    integration::integration_event(
+        dev.application_id,
+        &dev.variables,
+        &integration_pb::IntegrationEvent {
+            deduplication_id,
+            time: Some(Utc::now().into()),
+            device_info,
+            integration_name: "fragmentation".to_string(),
+            event_type: event_type.to_string(),
+            object: Some(object),
+        },
+    )
+    .await;
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage;
+    use crate::test;
+    use chirpstack_api::internal;
+    use tokio::time::{sleep, Duration};
+
+    #[tokio::test]
+    async fn test_fragmentation_session() {
+        let _guard = test::prepare().await;
+        integration::set_mock().await;
+        integration::mock::reset().await;
+
+        let dp = storage::device_profile::test::create_device_profile(None).await;
+        let dev = storage::device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            None,
+        )
+        .await;
+
+        let qi = device_queue::DeviceQueueItem {
+            dev_eui: dev.dev_eui,
+            data: (0..25).collect(),
+            ..Default::default()
+        };
+
+        // fragment_size can not be derived, as the device is not activated
+        assert!(enqueue(
+            FragmentationSession {
+                frag_index: 1,
+                ..Default::default()
+            },
+            qi.clone()
+        )
+        .await
+        .is_err());
+
+        let (fs, items) = enqueue(
+            FragmentationSession {
+                frag_index: 1,
+                fragment_size: 10,
+                redundancy: 2,
+                ..Default::default()
+            },
+            qi.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(3, fs.nb_frag);
+        assert_eq!(5, fs.padding);
+        assert_eq!(7, items.len());
+
+        // tx ack of setup and data fragment
+        handle_tx_ack(&items[0]).await.unwrap();
+        handle_tx_ack(&items[1]).await.unwrap();
+        let fs_get = fragmentation_session::get(&fs.id).await.unwrap();
+        assert_eq!(1, fs_get.nb_frag_sent);
+
+        let up = integration_pb::UplinkEvent {
+            deduplication_id: "dedup".to_string(),
+            f_port: LA_FPORT_FRAGMENTATION as u32,
+            ..Default::default()
+        };
+
+        // setup ans
+        handle_uplink(
+            &dev,
+            &integration_pb::UplinkEvent {
+                data: fragmentation::Payload::FragSessionSetupAns(
+                    fragmentation::FragSessionSetupAnsPayload {
+                        frag_index: 1,
+                        wrong_descriptor: false,
+                        frag_session_index_not_supported: false,
+                        not_enough_memory: false,
+                        encoding_unsupported: false,
+                    },
+                )
+                .to_vec()
+                .unwrap(),
+                ..up.clone()
+            },
+        )
+        .await
+        .unwrap();
+        let fs_get = fragmentation_session::get(&fs.id).await.unwrap();
+        assert_eq!(FragmentationSessionStatus::ACTIVE, fs_get.status);
+
+        // status ans
+        handle_uplink(
+            &dev,
+            &integration_pb::UplinkEvent {
+                data: fragmentation::Payload::FragSessionStatusAns(
+                    fragmentation::FragSessionStatusAnsPayload {
+                        frag_index: 1,
+                        nb_frag_received: 4,
+                        missing_frag: 0,
+                        not_enough_matrix_memory: false,
+                    },
+                )
+                .to_vec()
+                .unwrap(),
+                ..up.clone()
+            },
+        )
+        .await
+        .unwrap();
+        let fs_get = fragmentation_session::get(&fs.id).await.unwrap();
+        assert_eq!(FragmentationSessionStatus::COMPLETED, fs_get.status);
+        assert_eq!(Some(4), fs_get.nb_frag_received);
+        assert!(fs_get.completed_at.is_some());
+
+        sleep(Duration::from_millis(100)).await;
+        let events = integration::mock::get_integration_events().await;
+        assert_eq!(1, events.len());
+        assert_eq!("fragmentation", events[0].integration_name);
+        assert_eq!("completed", events[0].event_type);
+        assert_eq!("dedup", events[0].deduplication_id);
+
+        // setup ans with error
+        let (fs, _) = enqueue(
+            FragmentationSession {
+                frag_index: 2,
+                fragment_size: 10,
+                ..Default::default()
+            },
+            qi.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            12,
+            device_queue::get_for_dev_eui(&dev.dev_eui)
+                .await
+                .unwrap()
+                .len()
+        );
+
+        handle_uplink(
+            &dev,
+            &integration_pb::UplinkEvent {
+                data: fragmentation::Payload::FragSessionSetupAns(
+                    fragmentation::FragSessionSetupAnsPayload {
+                        frag_index: 2,
+                        wrong_descriptor: false,
+                        frag_session_index_not_supported: false,
+                        not_enough_memory: true,
+                        encoding_unsupported: false,
+                    },
+                )
+                .to_vec()
+                .unwrap(),
+                ..up.clone()
+            },
+        )
+        .await
+        .unwrap();
+        let fs_get = fragmentation_session::get(&fs.id).await.unwrap();
+        assert_eq!(FragmentationSessionStatus::FAILED, fs_get.status);
+
+        // only the setup of the failed session remains
+        assert_eq!(
+            8,
+            device_queue::get_for_dev_eui(&dev.dev_eui)
+                .await
+                .unwrap()
+                .len()
+        );
+
+        sleep(Duration::from_millis(100)).await;
+        let events = integration::mock::get_log_events().await;
+        assert_eq!(1, events.len());
+        assert_eq!(
+            integration_pb::LogCode::FragmentationSession,
+            events[0].code()
+        );
+        assert_eq!("true", events[0].context["not_enough_memory"]);
+        let events = integration::mock::get_integration_events().await;
+        assert_eq!(1, events.len());
+        assert_eq!("failed", events[0].event_type);
+    }
+
+    #[tokio::test]
+    async fn test_fragment_size() {
+        let _guard = test::prepare().await;
+
+        let dp = storage::device_profile::test::create_device_profile(None).await;
+        let dev = storage::device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            None,
+        )
+        .await;
+
+        // DR5 with an RX1 data-rate offset of 3 results in RX1 DR2 (max. 51 bytes).
+        device::partial_update(
+            dev.dev_eui,
+            &device::DeviceChangeset {
+                device_session: Some(Some(internal::DeviceSession {
+                    region_config_id: "eu868".into(),
+                    dr: 5,
+                    rx1_dr_offset: 3,
+                    rx2_dr: 5,
+                    ..Default::default()
+                })),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let qi = device_queue::DeviceQueueItem {
+            dev_eui: dev.dev_eui,
+            data: (0..100).collect(),
+            ..Default::default()
+        };
+
+        // fragment_size exceeds the max. payload size at RX1
+        assert!(enqueue(
+            FragmentationSession {
+                frag_index: 1,
+                fragment_size: 100,
+                ..Default::default()
+            },
+            qi.clone()
+        )
+        .await
+        .is_err());
+        assert!(device_queue::get_for_dev_eui(&dev.dev_eui)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // fragment_size is derived
+        let (fs, _) = enqueue(
+            FragmentationSession {
+                frag_index: 1,
+                ..Default::default()
+            },
+            qi.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(48, fs.fragment_size);
+    }
+
+    #[tokio::test]
+    async fn test_handle_timeout() {
+        let _guard = test::prepare().await;
+        integration::set_mock().await;
+        integration::mock::reset().await;
+
+        let dp = storage::device_profile::test::create_device_profile(None).await;
+        let dev = storage::device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            None,
+        )
+        .await;
+
+        let (_, items) = enqueue(
+            FragmentationSession {
+                frag_index: 1,
+                fragment_size: 10,
+                ..Default::default()
+            },
+            device_queue::DeviceQueueItem {
+                dev_eui: dev.dev_eui,
+                data: (0..25).collect(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(5, items.len());
+
+        let sessions = fragmentation_session::set_timed_out_as_failed(chrono::Duration::zero())
+            .await
+            .unwrap();
+        assert_eq!(1, sessions.len());
+        handle_timeout(&sessions[0]).await.unwrap();
+
+        assert!(device_queue::get_for_dev_eui(&dev.dev_eui)
+            .await
+            .unwrap()
+            .is_empty());
+
+        sleep(Duration::from_millis(100)).await;
+        let events = integration::mock::get_log_events().await;
+        assert_eq!(1, events.len());
+        assert_eq!(
+            dev.dev_eui.to_string(),
+            events[0].device_info.as_ref().unwrap().dev_eui
+        );
+        let events = integration::mock::get_integration_events().await;
+        assert_eq!(1, events.len());
+        assert_eq!("failed", events[0].event_type);
+    }
+}
@@ -0,0 +1,903 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use lrwn::applayer::{fragmentation, multicastsetup};
+use lrwn::region::MacVersion;
+use lrwn::{AES128Key, DevAddr, EUI64, LA_FPORT_FRAGMENTATION, LA_FPORT_MULTICAST_SETUP};
+
+use super::fragmentation::DATA_FRAGMENT_OVERHEAD;
+use crate::gpstime::{ToDateTime, ToGpsTime};
+use crate::storage::error::Error;
+use crate::storage::fields::FuotaCampaignStep;
+use crate::storage::fuota::{self, FuotaCampaign, FuotaCampaignDevice};
+use crate::storage::{
+    device, device_keys, device_profile, device_queue, fragmentation_session, multicast,
+};
+use crate::{downlink, region};
+use chirpstack_api::integration as integration_pb;
+
+// The McGroupID and FragIndex which are used on the end-devices for FUOTA campaigns.
+const MC_GROUP_ID: u8 = 0;
+const FRAG_INDEX: u8 = 0;
+
+// Duration of a Class-B beacon period (seconds).
+const BEACON_PERIOD: i64 = 128;
+
+/// Validates that the DataFragment payloads of the given campaign fit within the max. payload
+/// size of the multicast data-rate.
+pub async fn validate_fragment_size(fc: &FuotaCampaign) -> Result<(), Error> {
+    let dp = device_profile::get(&fc.device_profile_id).await?;
+    let region_conf = region::get(&region::get_region_config_id(dp.region)?)?;
+
+    let dr = u8::try_from(fc.multicast_dr)
+        .map_err(|_| Error::Validation("multicast_dr is invalid".into()))?;
+    let max_pl_size = region_conf
+        .get_max_payload_size(dp.mac_version, dp.reg_params_revision, dr)
+        .map_err(|e| Error::Validation(format!("multicast_dr is invalid: {}", e)))?;
+
+    if fc.fragment_size as usize + DATA_FRAGMENT_OVERHEAD > max_pl_size.n {
+        return Err(Error::Validation(format!(
+            "fragment_size must not exceed {} bytes for multicast_dr {}",
+            max_pl_size.n.saturating_sub(DATA_FRAGMENT_OVERHEAD),
+            dr
+        )));
+    }
+
+    Ok(())
+}
+
+/// Returns true when the given FragIndex is used by a running FUOTA campaign of the device.
+pub async fn is_frag_index_in_use(dev_eui: &EUI64, frag_index: u8) -> Result<bool, Error> {
+    if frag_index != FRAG_INDEX {
+        return Ok(false);
+    }
+
+    match fuota::get_running_for_dev_eui(dev_eui).await {
+        Ok(_) => Ok(true),
+        Err(Error::NotFound(_)) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Starts the given FUOTA campaign. The steps of the campaign are executed by the FUOTA
+/// scheduler.
+pub async fn start(id: &Uuid) -> Result<FuotaCampaign, Error> {
+    let mut fc = fuota::get(id).await?;
+    if fc.started_at.is_some() {
+        return Err(Error::Validation(
+            "FUOTA campaign has already been started".into(),
+        ));
+    }
+
+    if fuota::get_device_count(id).await? == 0 {
+        return Err(Error::Validation(
+            "FUOTA campaign does not contain any devices".into(),
+        ));
+    }
+
+    // LoRaWAN 1.0.x devices derive the McRootKey from the GenAppKey, which is not part
+    // of the regular OTAA keys and must be set explicitly.
+    let dp = device_profile::get(&fc.device_profile_id).await?;
+    if !is_lorawan_1_1(dp.mac_version) {
+        for fcd in get_devices(id).await? {
+            let dk = match device_keys::get(&fcd.dev_eui).await {
+                Ok(v) => v,
+                Err(Error::NotFound(_)) => continue,
+                Err(e) => return Err(e),
+            };
+
+            if dk.gen_app_key == AES128Key::null() {
+                return Err(Error::Validation(format!(
+                    "Device {} has no GenAppKey configured",
+                    fcd.dev_eui
+                )));
+            }
+        }
+    }
+
+    // The FragIndex used by the campaign must not collide with an active (unicast)
+    // fragmentation-session of one of the devices.
+    for fcd in get_devices(id).await? {
+        match fragmentation_session::get_active_for_dev_eui_and_frag_index(&fcd.dev_eui, FRAG_INDEX)
+            .await
+        {
+            Ok(_) => {
+                return Err(Error::Validation(format!(
+                    "Device {} has an active fragmentation-session with frag_index {}",
+                    fcd.dev_eui, FRAG_INDEX
+                )));
+            }
+            Err(Error::NotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    fc.started_at = Some(Utc::now());
+    fc.step = FuotaCampaignStep::MC_GROUP_SETUP;
+    fc.scheduler_run_after = Utc::now();
+    fuota::update(fc).await
+}
+
+/// Executes the current step of the given campaign.
+pub async fn handle_schedule(fc: FuotaCampaign) -> Result<()> {
+    info!(fuota_campaign_id = %fc.id, step = %fc.step, "Handling FUOTA campaign step");
+
+    match fc.step {
+        FuotaCampaignStep::CREATED => Ok(()),
+        FuotaCampaignStep::MC_GROUP_SETUP => mc_group_setup(fc).await,
+        FuotaCampaignStep::FRAG_SESSION_SETUP => frag_session_setup(fc).await,
+        FuotaCampaignStep::MC_SESSION => mc_session(fc).await,
+        FuotaCampaignStep::ENQUEUE => enqueue(fc).await,
+        FuotaCampaignStep::FRAG_STATUS => frag_status(fc).await,
+        FuotaCampaignStep::COMPLETED => complete(fc).await,
+    }
+}
+
+/// Handles the Remote Multicast Setup answers sent by the device.
+pub async fn handle_multicast_setup_uplink(
+    dev: &device::Device,
+    up: &integration_pb::UplinkEvent,
+) -> Result<()> {
+    let pl = multicastsetup::Payload::from_slice(true, &up.data)?;
+    let mut fcd = match get_running_campaign_device(dev).await? {
+        Some(v) => v,
+        None => {
+            debug!(dev_eui = %dev.dev_eui, cid = ?pl.cid(), "No running FUOTA campaign for device, ignoring multicast setup payload");
+            return Ok(());
+        }
+    };
+
+    match pl {
+        multicastsetup::Payload::McGroupSetupAns(pl) => {
+            if pl.mc_group_id != MC_GROUP_ID || pl.id_error {
+                fcd.error_msg = "McGroupSetupReq failed: McGroupID error".into();
+            } else {
+                fcd.mc_group_setup_completed_at = Some(Utc::now());
+            }
+        }
+        multicastsetup::Payload::McClassCSessionAns(pl)
+        | multicastsetup::Payload::McClassBSessionAns(pl) => {
+            if pl.is_error() {
+                fcd.error_msg = format!(
+                    "McSessionReq failed: dr_error: {}, freq_error: {}, mc_group_undefined: {}",
+                    pl.dr_error, pl.freq_error, pl.mc_group_undefined
+                );
+            } else {
+                fcd.mc_session_completed_at = Some(Utc::now());
+            }
+        }
+        pl => {
+            debug!(dev_eui = %dev.dev_eui, cid = ?pl.cid(), "Ignoring multicast setup payload");
+            return Ok(());
+        }
+    }
+
+    update_device(fcd).await
+}
+
+/// Handles the fragmentation answers sent by the device, related to a FUOTA campaign.
+pub async fn handle_fragmentation_uplink(
+    dev: &device::Device,
+    pl: fragmentation::Payload,
+) -> Result<()> {
+    let mut fcd = match get_running_campaign_device(dev).await? {
+        Some(v) => v,
+        None => {
+            debug!(dev_eui = %dev.dev_eui, cid = ?pl.cid(), "No running FUOTA campaign for device, ignoring fragmentation payload");
+            return Ok(());
+        }
+    };
+
+    match pl {
+        fragmentation::Payload::FragSessionSetupAns(pl) => {
+            if pl.is_error() {
+                fcd.error_msg = format!(
+                    "FragSessionSetupReq failed: wrong_descriptor: {}, frag_session_index_not_supported: {}, not_enough_memory: {}, encoding_unsupported: {}",
+                    pl.wrong_descriptor,
+                    pl.frag_session_index_not_supported,
+                    pl.not_enough_memory,
+                    pl.encoding_unsupported
+                );
+            } else {
+                fcd.frag_session_setup_completed_at = Some(Utc::now());
+            }
+        }
+        fragmentation::Payload::FragSessionStatusAns(pl) => {
+            fcd.frag_status_completed_at = Some(Utc::now());
+            fcd.nb_frag_received = Some(pl.nb_frag_received.into());
+            fcd.missing_frag = Some(pl.missing_frag.into());
+
+            if pl.missing_frag != 0 || pl.not_enough_matrix_memory {
+                fcd.error_msg = format!(
+                    "FragSessionStatusReq failed: missing_frag: {}, not_enough_matrix_memory: {}",
+                    pl.missing_frag, pl.not_enough_matrix_memory
+                );
+            }
+        }
+        pl => {
+            debug!(dev_eui = %dev.dev_eui, cid = ?pl.cid(), "Ignoring fragmentation payload");
+            return Ok(());
+        }
+    }
+
+    update_device(fcd).await
+}
+
+// Creates the multicast-group of the campaign and sends the McGroupSetupReq to each device.
+// When this step is retried, the multicast-group created by the previous attempt is re-used
+// with a new McKey.
+async fn mc_group_setup(mut fc: FuotaCampaign) -> Result<()> {
+    let dp = device_profile::get(&fc.device_profile_id).await?;
+
+    let mc_key = AES128Key::from_bytes(rand::random());
+
+    let mg = match fc.multicast_group_id {
+        Some(multicast_group_id) => {
+            let mg = multicast::get(&multicast_group_id).await?;
+            multicast::update(multicast::MulticastGroup {
+                mc_nwk_s_key: multicastsetup::get_mc_net_s_key(&mc_key, &mg.mc_addr)?,
+                mc_app_s_key: multicastsetup::get_mc_app_s_key(&mc_key, &mg.mc_addr)?,
+                ..mg
+            })
+            .await?
+        }
+        None => {
+            let mc_addr = DevAddr::from_be_bytes(rand::random());
+            let mg = multicast::create(multicast::MulticastGroup {
+                application_id: fc.application_id,
+                name: format!("fuota-{}", fc.id),
+                region: dp.region,
+                mc_addr,
+                mc_nwk_s_key: multicastsetup::get_mc_net_s_key(&mc_key, &mc_addr)?,
+                mc_app_s_key: multicastsetup::get_mc_app_s_key(&mc_key, &mc_addr)?,
+                group_type: fc.multicast_group_type.clone(),
+                dr: fc.multicast_dr,
+                frequency: fc.multicast_frequency,
+                class_b_ping_slot_nb_k: fc.multicast_class_b_ping_slot_nb_k,
+                class_c_scheduling_type: fc.multicast_class_c_scheduling_type,
+                ..Default::default()
+            })
+            .await?;
+
+            // Store the multicast-group before enqueueing, such that a retry does not
+            // create a second multicast-group.
+            fc.multicast_group_id = Some(mg.id);
+            fc = fuota::update(fc).await?;
+            mg
+        }
+    };
+    let mc_addr = mg.mc_addr;
+
+    for mut fcd in get_devices(&fc.id).await? {
+        let dk = match device_keys::get(&fcd.dev_eui).await {
+            Ok(v) => v,
+            Err(Error::NotFound(_)) => {
+                fcd.error_msg = "Device has no root keys configured".into();
+                fuota::update_device(fcd).await?;
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let mc_root_key = if is_lorawan_1_1(dp.mac_version) {
+            multicastsetup::get_mc_root_key_for_app_key(&dk.app_key)?
+        } else {
+            if dk.gen_app_key == AES128Key::null() {
+                fcd.error_msg = "Device has no GenAppKey configured".into();
+                fuota::update_device(fcd).await?;
+                continue;
+            }
+            multicastsetup::get_mc_root_key_for_gen_app_key(&dk.gen_app_key)?
+        };
+        let mc_ke_key = multicastsetup::get_mc_ke_key(&mc_root_key)?;
+
+        enqueue_unicast(
+            &fcd.dev_eui,
+            LA_FPORT_MULTICAST_SETUP,
+            multicastsetup::Payload::McGroupSetupReq(multicastsetup::McGroupSetupReqPayload {
+                mc_group_id: MC_GROUP_ID,
+                mc_addr,
+                mc_key_encrypted: multicastsetup::encrypt_mc_key(&mc_ke_key, &mc_key),
+                min_mc_f_count: 0,
+                max_mc_f_count: u32::MAX,
+            })
+            .to_vec()?,
+        )
+        .await?;
+    }
+
+    next_step(fc, FuotaCampaignStep::FRAG_SESSION_SETUP, None).await
+}
+
+// Sends the FragSessionSetupReq to each device which has setup the multicast-group.
+async fn frag_session_setup(fc: FuotaCampaign) -> Result<()> {
+    let fragment_size = fc.fragment_size as usize;
+    let padding = fragmentation::get_padding(fc.payload.len(), fragment_size);
+    let nb_frag = (fc.payload.len() + padding) / fragment_size;
+
+    for mut fcd in get_devices(&fc.id).await? {
+        if !fcd.error_msg.is_empty() {
+            continue;
+        }
+
+        if fcd.mc_group_setup_completed_at.is_none() {
+            fcd.error_msg = "McGroupSetupReq timeout".into();
+            fuota::update_device(fcd).await?;
+            continue;
+        }
+
+        enqueue_unicast(
+            &fcd.dev_eui,
+            LA_FPORT_FRAGMENTATION,
+            fragmentation::Payload::FragSessionSetupReq(
+                fragmentation::FragSessionSetupReqPayload {
+                    frag_index: FRAG_INDEX,
+                    // McGroupID 0.
+                    mc_group_bit_mask: [true, false, false, false],
+                    nb_frag: nb_frag as u16,
+                    frag_size: fc.fragment_size as u8,
+                    fragmentation_matrix: 0,
+                    block_ack_delay: 0,
+                    padding: padding as u8,
+                    descriptor: [0; 4],
+                },
+            )
+            .to_vec()?,
+        )
+        .await?;
+    }
+
+    next_step(fc, FuotaCampaignStep::MC_SESSION, None).await
+}
+
+// Sends the McClassCSessionReq or McClassBSessionReq to each device which has setup the
+// fragmentation-session. The session starts after the unicast timeout, such that all devices
+// are able to receive the request before the start of the session.
+async fn mc_session(mut fc: FuotaCampaign) -> Result<()> {
+    let mut session_start_gps =
+        (Utc::now() + Duration::seconds(fc.unicast_timeout.into())).to_gps_time();
+
+    // For Class-B, the session must start at a beacon boundary.
+    if fc.multicast_group_type == "B" {
+        let secs = session_start_gps.num_seconds();
+        session_start_gps =
+            Duration::seconds(secs + (BEACON_PERIOD - secs % BEACON_PERIOD) % BEACON_PERIOD);
+    }
+    let session_time = (session_start_gps.num_seconds() % (1 << 32)) as u32;
+
+    let pl = if fc.multicast_group_type == "B" {
+        multicastsetup::Payload::McClassBSessionReq(multicastsetup::McClassBSessionReqPayload {
+            mc_group_id: MC_GROUP_ID,
+            session_time,
+            time_out: fc.multicast_timeout as u8,
+            periodicity: 7 - fc.multicast_class_b_ping_slot_nb_k as u8,
+            dl_frequency: fc.multicast_frequency as u32,
+            dr: fc.multicast_dr as u8,
+        })
+    } else {
+        multicastsetup::Payload::McClassCSessionReq(multicastsetup::McClassCSessionReqPayload {
+            mc_group_id: MC_GROUP_ID,
+            session_time,
+            session_time_out: fc.multicast_timeout as u8,
+            dl_frequency: fc.multicast_frequency as u32,
+            dr: fc.multicast_dr as u8,
+        })
+    }
+    .to_vec()?;
+
+    for mut fcd in get_devices(&fc.id).await? {
+        if !fcd.error_msg.is_empty() {
+            continue;
+        }
+
+        if fcd.frag_session_setup_completed_at.is_none() {
+            fcd.error_msg = "FragSessionSetupReq timeout".into();
+            fuota::update_device(fcd).await?;
+            continue;
+        }
+
+        enqueue_unicast(&fcd.dev_eui, LA_FPORT_MULTICAST_SETUP, pl.clone()).await?;
+    }
+
+    let session_start_at = session_start_gps.to_date_time();
+    fc.session_start_at = Some(session_start_at);
+    next_step(fc, FuotaCampaignStep::ENQUEUE, Some(session_start_at)).await
+}
+
+// Adds the devices which have setup the multicast-session to the multicast-group and enqueues
+// the fragments to the multicast-group queue.
+async fn enqueue(fc: FuotaCampaign) -> Result<()> {
+    let multicast_group_id = fc
+        .multicast_group_id
+        .ok_or_else(|| anyhow!("FUOTA campaign has no multicast-group"))?;
+
+    let mut device_count = 0;
+    for mut fcd in get_devices(&fc.id).await? {
+        if !fcd.error_msg.is_empty() {
+            continue;
+        }
+
+        if fcd.mc_session_completed_at.is_none() {
+            fcd.error_msg = "McSessionReq timeout".into();
+            fuota::update_device(fcd).await?;
+            continue;
+        }
+
+        multicast::add_device(&multicast_group_id, &fcd.dev_eui).await?;
+        device_count += 1;
+    }
+
+    if device_count == 0 {
+        warn!(fuota_campaign_id = %fc.id, "No devices left in FUOTA campaign, skipping enqueue");
+        return next_step(fc, FuotaCampaignStep::COMPLETED, Some(Utc::now())).await;
+    }
+
+    let fragment_size = fc.fragment_size as usize;
+    let padding = fragmentation::get_padding(fc.payload.len(), fragment_size);
+    let mut data = fc.payload.clone();
+    data.resize(data.len() + padding, 0);
+
+    for (i, fragment) in fragmentation::encode(&data, fragment_size, fc.redundancy as usize)?
+        .into_iter()
+        .enumerate()
+    {
+        downlink::multicast::enqueue(multicast::MulticastGroupQueueItem {
+            multicast_group_id,
+            f_port: LA_FPORT_FRAGMENTATION as i16,
+            data: fragmentation::Payload::DataFragment(fragmentation::DataFragmentPayload {
+                frag_index: FRAG_INDEX,
+                n: (i + 1) as u16,
+                data: fragment,
+            })
+            .to_vec()?,
+            ..Default::default()
+        })
+        .await?;
+    }
+
+    // Request the fragmentation-session status after the multicast-session has ended.
+    let session_timeout = if fc.multicast_group_type == "B" {
+        Duration::seconds((1 << fc.multicast_timeout) * BEACON_PERIOD)
+    } else {
+        Duration::seconds(1 << fc.multicast_timeout)
+    };
+    let session_end = fc.session_start_at.unwrap_or_else(Utc::now) + session_timeout;
+
+    next_step(
+        fc,
+        FuotaCampaignStep::FRAG_STATUS,
+        Some(session_end.max(Utc::now())),
+    )
+    .await
+}
+
+// Sends the FragSessionStatusReq to each device which took part in the multicast-session.
+async fn frag_status(fc: FuotaCampaign) -> Result<()> {
+    for fcd in get_devices(&fc.id).await? {
+        if !fcd.error_msg.is_empty() {
+            continue;
+        }
+
+        enqueue_unicast(
+            &fcd.dev_eui,
+            LA_FPORT_FRAGMENTATION,
+            fragmentation::Payload::FragSessionStatusReq(
+                fragmentation::FragSessionStatusReqPayload {
+                    frag_index: FRAG_INDEX,
+                    participants: true,
+                },
+            )
+            .to_vec()?,
+        )
+        .await?;
+    }
+
+    next_step(fc, FuotaCampaignStep::COMPLETED, None).await
+}
+
+// Marks the devices which did not report their status as failed and removes the
+// multicast-group of the campaign.
+async fn complete(mut fc: FuotaCampaign) -> Result<()> {
+    for mut fcd in get_devices(&fc.id).await? {
+        if fcd.error_msg.is_empty() && fcd.frag_status_completed_at.is_none() {
+            fcd.error_msg = "FragSessionStatusReq timeout".into();
+            fuota::update_device(fcd).await?;
+        }
+    }
+
+    if let Some(multicast_group_id) = fc.multicast_group_id.take() {
+        match multicast::delete(&multicast_group_id).await {
+            Ok(_) | Err(Error::NotFound(_)) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    fc.completed_at = Some(Utc::now());
+    let fc = fuota::update(fc).await?;
+    info!(fuota_campaign_id = %fc.id, "FUOTA campaign completed");
+
+    Ok(())
+}
+
+// Sets the next step of the campaign. When run_after is not set, the next step is executed
+// after the unicast timeout.
+async fn next_step(
+    mut fc: FuotaCampaign,
+    step: FuotaCampaignStep,
+    run_after: Option<DateTime<Utc>>,
+) -> Result<()> {
+    fc.step = step;
+    fc.scheduler_run_after =
+        run_after.unwrap_or_else(|| Utc::now() + Duration::seconds(fc.unicast_timeout.into()));
+    fuota::update(fc).await?;
+    Ok(())
+}
+
+fn is_lorawan_1_1(mac_version: MacVersion) -> bool {
+    matches!(mac_version, MacVersion::LORAWAN_1_1_0 | MacVersion::Latest)
+}
+
+async fn get_devices(campaign_id: &Uuid) -> Result<Vec<FuotaCampaignDevice>> {
+    let count = fuota::get_device_count(campaign_id).await?;
+    Ok(fuota::get_devices(campaign_id, count, 0).await?)
+}
+
+async fn enqueue_unicast(dev_eui: &lrwn::EUI64, f_port: u8, data: Vec<u8>) -> Result<()> {
+    device_queue::enqueue_item(device_queue::DeviceQueueItem {
+        dev_eui: *dev_eui,
+        f_port: f_port as i16,
+        data,
+        ..Default::default()
+    })
+    .await?;
+    Ok(())
+}
+
+async fn get_running_campaign_device(dev: &device::Device) -> Result<Option<FuotaCampaignDevice>> {
+    let fc = match fuota::get_running_for_dev_eui(&dev.dev_eui).await {
+        Ok(v) => v,
+        Err(Error::NotFound(_)) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(Some(fuota::get_device(&fc.id, &dev.dev_eui).await?))
+}
+
+async fn update_device(fcd: FuotaCampaignDevice) -> Result<()> {
+    if !fcd.error_msg.is_empty() {
+        warn!(fuota_campaign_id = %fcd.fuota_campaign_id, dev_eui = %fcd.dev_eui, error = %fcd.error_msg, "FUOTA campaign failed for device");
+    }
+    fuota::update_device(fcd).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage::{self, gateway};
+    use crate::test;
+    use lrwn::EUI64;
+
+    fn uplink(f_port: u8, data: Vec<u8>) -> integration_pb::UplinkEvent {
+        integration_pb::UplinkEvent {
+            f_port: f_port.into(),
+            data,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fuota_campaign() {
+        let _guard = test::prepare().await;
+
+        let dp = storage::device_profile::test::create_device_profile(None).await;
+        let dev = storage::device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            None,
+        )
+        .await;
+        let dev_no_keys = storage::device::test::create_device(
+            EUI64::from_be_bytes([2, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            Some(dev.application_id),
+        )
+        .await;
+        device_keys::create(device_keys::DeviceKeys {
+            dev_eui: dev.dev_eui,
+            app_key: AES128Key::from_bytes([
+                0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+                0x0f, 0x10,
+            ]),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let gw = gateway::create(gateway::Gateway {
+            gateway_id: EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            tenant_id: dp.tenant_id,
+            name: "test-gw".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let fc = fuota::create(FuotaCampaign {
+            name: "test-fuota".into(),
+            application_id: dev.application_id,
+            device_profile_id: dp.id,
+            multicast_group_type: "C".into(),
+            multicast_dr: 3,
+            multicast_frequency: 869525000,
+            multicast_timeout: 2,
+            unicast_timeout: 60,
+            fragment_size: 10,
+            redundancy: 2,
+            payload: (0..25).collect(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // DataFragment must fit within the max. payload size of the multicast data-rate.
+        validate_fragment_size(&fc).await.unwrap();
+        assert!(validate_fragment_size(&FuotaCampaign {
+            multicast_dr: 0,
+            fragment_size: 57,
+            ..fc.clone()
+        })
+        .await
+        .is_err());
+
+        // Campaign without devices can not be started.
+        assert!(start(&fc.id).await.is_err());
+
+        fuota::add_device(&fc.id, &dev.dev_eui).await.unwrap();
+        fuota::add_device(&fc.id, &dev_no_keys.dev_eui)
+            .await
+            .unwrap();
+
+        // LoRaWAN 1.0.x devices require the GenAppKey.
+        assert!(start(&fc.id).await.is_err());
+        let dk = device_keys::get(&dev.dev_eui).await.unwrap();
+        device_keys::update(device_keys::DeviceKeys {
+            gen_app_key: AES128Key::from_bytes([
+                0x02, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+                0x0f, 0x10,
+            ]),
+            ..dk
+        })
+        .await
+        .unwrap();
+
+        // The FragIndex of the campaign is used by an active fragmentation-session.
+        let (fs, _) = fragmentation_session::create_and_enqueue(
+            fragmentation_session::FragmentationSession {
+                dev_eui: dev.dev_eui,
+                frag_index: FRAG_INDEX.into(),
+                nb_frag: 10,
+                fragment_size: 10,
+                ..Default::default()
+            },
+            vec![],
+        )
+        .await
+        .unwrap();
+        assert!(start(&fc.id).await.is_err());
+        fragmentation_session::update(fragmentation_session::FragmentationSession {
+            status: crate::storage::fields::FragmentationSessionStatus::FAILED,
+            ..fs
+        })
+        .await
+        .unwrap();
+
+        let fc = start(&fc.id).await.unwrap();
+        assert!(start(&fc.id).await.is_err());
+        assert!(is_frag_index_in_use(&dev.dev_eui, FRAG_INDEX)
+            .await
+            .unwrap());
+        assert!(!is_frag_index_in_use(&dev.dev_eui, FRAG_INDEX + 1)
+            .await
+            .unwrap());
+
+        // McGroupSetup
+        handle_schedule(fc.clone()).await.unwrap();
+        let fc = fuota::get(&fc.id).await.unwrap();
+        assert_eq!(FuotaCampaignStep::FRAG_SESSION_SETUP, fc.step);
+        let mg = multicast::get(&fc.multicast_group_id.unwrap())
+            .await
+            .unwrap();
+
+        // McGroupSetup retry re-uses the multicast-group
+        device_queue::flush_for_dev_eui(&dev.dev_eui).await.unwrap();
+        handle_schedule(FuotaCampaign {
+            step: FuotaCampaignStep::MC_GROUP_SETUP,
+            ..fc.clone()
+        })
+        .await
+        .unwrap();
+        let fc = fuota::get(&fc.id).await.unwrap();
+        assert_eq!(Some(mg.id), fc.multicast_group_id);
+        assert_eq!(
+            1,
+            multicast::get_count(&multicast::Filters {
+                application_id: Some(dev.application_id),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+        );
+        let mg = multicast::get(&fc.multicast_group_id.unwrap())
+            .await
+            .unwrap();
+        multicast::add_gateway(&mg.id, &gw.gateway_id)
+            .await
+            .unwrap();
+
+        let items = device_queue::get_for_dev_eui(&dev.dev_eui).await.unwrap();
+        assert_eq!(1, items.len());
+        assert_eq!(LA_FPORT_MULTICAST_SETUP as i16, items[0].f_port);
+        match multicastsetup::Payload::from_slice(false, &items[0].data).unwrap() {
+            multicastsetup::Payload::McGroupSetupReq(pl) => {
+                assert_eq!(mg.mc_addr, pl.mc_addr);
+            }
+            _ => panic!("McGroupSetupReq expected"),
+        }
+
+        let fcd = fuota::get_device(&fc.id, &dev_no_keys.dev_eui)
+            .await
+            .unwrap();
+        assert_eq!("Device has no root keys configured", fcd.error_msg);
+
+        handle_multicast_setup_uplink(
+            &dev,
+            &uplink(
+                LA_FPORT_MULTICAST_SETUP,
+                multicastsetup::Payload::McGroupSetupAns(multicastsetup::McGroupSetupAnsPayload {
+                    mc_group_id: 0,
+                    id_error: false,
+                })
+                .to_vec()
+                .unwrap(),
+            ),
+        )
+        .await
+        .unwrap();
+        device_queue::flush_for_dev_eui(&dev.dev_eui).await.unwrap();
+
+        // FragSessionSetup
+        handle_schedule(fc.clone()).await.unwrap();
+        let fc = fuota::get(&fc.id).await.unwrap();
+        assert_eq!(FuotaCampaignStep::MC_SESSION, fc.step);
+
+        let items = device_queue::get_for_dev_eui(&dev.dev_eui).await.unwrap();
+        assert_eq!(1, items.len());
+        assert_eq!(
+            fragmentation::Payload::FragSessionSetupReq(
+                fragmentation::FragSessionSetupReqPayload {
+                    frag_index: 0,
+                    mc_group_bit_mask: [true, false, false, false],
+                    nb_frag: 3,
+                    frag_size: 10,
+                    fragmentation_matrix: 0,
+                    block_ack_delay: 0,
+                    padding: 5,
+                    descriptor: [0; 4],
+                }
+            ),
+            fragmentation::Payload::from_slice(false, &items[0].data).unwrap()
+        );
+
+        handle_fragmentation_uplink(
+            &dev,
+            fragmentation::Payload::FragSessionSetupAns(
+                fragmentation::FragSessionSetupAnsPayload {
+                    frag_index: 0,
+                    wrong_descriptor: false,
+                    frag_session_index_not_supported: false,
+                    not_enough_memory: false,
+                    encoding_unsupported: false,
+                },
+            ),
+        )
+        .await
+        .unwrap();
+        device_queue::flush_for_dev_eui(&dev.dev_eui).await.unwrap();
+
+        // McSession
+        handle_schedule(fc.clone()).await.unwrap();
+        let fc = fuota::get(&fc.id).await.unwrap();
+        assert_eq!(FuotaCampaignStep::ENQUEUE, fc.step);
+        assert_eq!(fc.session_start_at, Some(fc.scheduler_run_after));
+
+        let items = device_queue::get_for_dev_eui(&dev.dev_eui).await.unwrap();
+        assert_eq!(1, items.len());
+        match multicastsetup::Payload::from_slice(false, &items[0].data).unwrap() {
+            multicastsetup::Payload::McClassCSessionReq(pl) => {
+                assert_eq!(2, pl.session_time_out);
+                assert_eq!(869525000, pl.dl_frequency);
+                assert_eq!(3, pl.dr);
+            }
+            _ => panic!("McClassCSessionReq expected"),
+        }
+
+        handle_multicast_setup_uplink(
+            &dev,
+            &uplink(
+                LA_FPORT_MULTICAST_SETUP,
+                multicastsetup::Payload::McClassCSessionAns(multicastsetup::McSessionAnsPayload {
+                    mc_group_id: 0,
+                    dr_error: false,
+                    freq_error: false,
+                    mc_group_undefined: false,
+                    time_to_start: Some(60),
+                })
+                .to_vec()
+                .unwrap(),
+            ),
+        )
+        .await
+        .unwrap();
+        device_queue::flush_for_dev_eui(&dev.dev_eui).await.unwrap();
+
+        // Enqueue
+        handle_schedule(fc.clone()).await.unwrap();
+        let fc = fuota::get(&fc.id).await.unwrap();
+        assert_eq!(FuotaCampaignStep::FRAG_STATUS, fc.step);
+        assert_eq!(
+            vec![dev.dev_eui],
+            multicast::get_dev_euis(&mg.id).await.unwrap()
+        );
+        let queue = multicast::get_queue(&mg.id).await.unwrap();
+        assert_eq!(5, queue.len());
+        assert_eq!(LA_FPORT_FRAGMENTATION as i16, queue[0].f_port);
+
+        // FragStatus
+        handle_schedule(fc.clone()).await.unwrap();
+        let fc = fuota::get(&fc.id).await.unwrap();
+        assert_eq!(FuotaCampaignStep::COMPLETED, fc.step);
+
+        let items = device_queue::get_for_dev_eui(&dev.dev_eui).await.unwrap();
+        assert_eq!(1, items.len());
+        assert_eq!(
+            fragmentation::Payload::FragSessionStatusReq(
+                fragmentation::FragSessionStatusReqPayload {
+                    frag_index: 0,
+                    participants: true,
+                }
+            ),
+            fragmentation::Payload::from_slice(false, &items[0].data).unwrap()
+        );
+
+        handle_fragmentation_uplink(
+            &dev,
+            fragmentation::Payload::FragSessionStatusAns(
+                fragmentation::FragSessionStatusAnsPayload {
+                    frag_index: 0,
+                    nb_frag_received: 5,
+                    missing_frag: 0,
+                    not_enough_matrix_memory: false,
+                },
+            ),
+        )
+        .await
+        .unwrap();
+
+        // Complete
+        handle_schedule(fc.clone()).await.unwrap();
+        let fc = fuota::get(&fc.id).await.unwrap();
+        assert!(fc.completed_at.is_some());
+        assert!(fc.multicast_group_id.is_none());
+        assert!(multicast::get(&mg.id).await.is_err());
+
+        let fcd = fuota::get_device(&fc.id, &dev.dev_eui).await.unwrap();
+        assert_eq!("", fcd.error_msg);
+        assert_eq!(Some(5), fcd.nb_frag_received);
+        assert!(fcd.frag_status_completed_at.is_some());
+
+        // Campaign is no longer running.
+        assert!(get_running_campaign_device(&dev).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,85 @@
+use anyhow::Result;
+
+use crate::storage::{device, device_profile};
+use chirpstack_api::integration as integration_pb;
+
+pub mod clocksync;
+pub mod fragmentation;
+pub mod fuota;
+
+/// Handles the application-layer payloads sent by the device on the standardized f_ports.
+/// Clock synchronization is only handled when enabled in the device-profile, as devices might
+/// use this f_port for their own payloads.
+pub async fn handle_uplink(
+    dev: &device::Device,
+    dp: &device_profile::DeviceProfile,
+    up: &integration_pb::UplinkEvent,
+) -> Result<()> {
+    match up.f_port as u8 {
+        lrwn::LA_FPORT_MULTICAST_SETUP => fuota::handle_multicast_setup_uplink(dev, up).await,
+        lrwn::LA_FPORT_FRAGMENTATION => fragmentation::handle_uplink(dev, up).await,
+        lrwn::LA_FPORT_CLOCK_SYNC if dp.supports_clock_sync => {
+            clocksync::handle_uplink(dev, up).await
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::gpstime::ToGpsTime;
+    use crate::storage::device_queue;
+    use crate::test;
+    use chirpstack_api::gw;
+    use chrono::Utc;
+    use lrwn::applayer::clocksync as clocksync_pl;
+
+    #[tokio::test]
+    async fn test_handle_uplink_clock_sync_disabled() {
+        let _guard = test::prepare().await;
+
+        let mut dp = device_profile::test::create_device_profile(None).await;
+        let dev = device::test::create_device(
+            lrwn::EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            None,
+        )
+        .await;
+
+        let rx_time = Utc::now();
+        let up = integration_pb::UplinkEvent {
+            f_port: lrwn::LA_FPORT_CLOCK_SYNC as u32,
+            data: clocksync_pl::Payload::AppTimeReq(clocksync_pl::AppTimeReqPayload {
+                device_time: rx_time.to_gps_time().num_seconds() as u32 - 10,
+                ans_required: false,
+                token_req: 1,
+            })
+            .to_vec()
+            .unwrap(),
+            rx_info: vec![gw::UplinkRxInfo {
+                gw_time: Some(rx_time.into()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // Clock sync is not enabled in the device-profile.
+        handle_uplink(&dev, &dp, &up).await.unwrap();
+        assert!(device_queue::get_for_dev_eui(&dev.dev_eui)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Clock sync is enabled in the device-profile.
+        dp.supports_clock_sync = true;
+        handle_uplink(&dev, &dp, &up).await.unwrap();
+        assert_eq!(
+            1,
+            device_queue::get_for_dev_eui(&dev.dev_eui)
+                .await
+                .unwrap()
+                .len()
+        );
+    }
+}
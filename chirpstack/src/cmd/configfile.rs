@@ -1,9 +1,9 @@
+use anyhow::Result;
 use handlebars::{no_escape, Handlebars};
 
 use super::super::config;
 
-pub fn run() {
-    let template = r#"
+const TEMPLATE: &str = r#"
 # Logging configuration
 [logging]
 
@@ -109,6 +109,19 @@ pub fn run() {
   #   openssl rand -base64 32
   secret="{{ api.secret }}"
 
+  # Trusted proxies.
+  #
+  # List of IP addresses or networks (CIDR notation) of trusted reverse-proxies
+  # in front of the API. Only for connections originating from one of these
+  # addresses, the X-Forwarded-For and X-Real-IP headers are used to determine
+  # the source IP that is written to the audit log. Example:
+  #   trusted_proxies=["127.0.0.1", "10.0.0.0/8"]
+  trusted_proxies=[
+    {{#each api.trusted_proxies}}
+    "{{this}}",
+    {{/each}}
+  ]
+
 
 # Global gateway configuration.
 # Please note that backend configuration can be found in the per-region
@@ -138,6 +151,12 @@ pub fn run() {
   # ChirpStack will be allowed.
   allow_unknown_gateways={{ gateway.allow_unknown_gateways }}
 
+  # Command execution timeout.
+  #
+  # This defines how long ChirpStack waits for the gateway to return the
+  # result of a command execution request (see ExecCommand API method).
+  command_exec_timeout="{{ gateway.command_exec_timeout }}"
+
 
 # Network related configuration.
 [network]
@@ -209,6 +228,13 @@ pub fn run() {
   # Mac-commands disabled.
   mac_commands_disabled={{ network.mac_commands_disabled }}
 
+  # Fragmentation-session timeout.
+  #
+  # Fragmentation-sessions (see the fragmentation option of the device
+  # queue-item) that did not make any progress within this duration are
+  # marked as failed and their remaining queue-items are removed.
+  fragmentation_session_timeout="{{ network.fragmentation_session_timeout }}"
+
   # Custom ADR plugins.
   #
   # The custom ADR plugin must be implemented in JavaScript. For an example
@@ -220,6 +246,49 @@ pub fn run() {
     {{/each}}
   ]
 
+  # Custom ADR plugins reload interval.
+  #
+  # When set, the custom ADR plugin files are checked for changes at this
+  # interval. A changed plugin is validated and replaces the loaded plugin
+  # without restarting ChirpStack. In case the changed plugin fails to load,
+  # the previously loaded plugin is kept. Set this to 0s to disable.
+  adr_plugins_reload_interval="{{ network.adr_plugins_reload_interval }}"
+
+  # External ADR plugins.
+  #
+  # External ADR plugins are implemented as a service, e.g. to implement
+  # ADR algorithms in a different language than JavaScript. The service must
+  # implement the AdrPluginService as defined in:
+  # https://github.com/chirpstack/chirpstack/blob/master/api/proto/api/adr.proto
+  #
+  # The ID and name of the algorithm are requested once on start. In case the
+  # service is not available, this is retried in the background until it
+  # succeeds. The ID must not be in use by one of the included algorithms or
+  # an other plugin. In case the service does not respond to an ADR request
+  # within the configured timeout, the included algorithm matching the enabled
+  # data-rates of the device is used (LoRa, LR-FHSS or both).
+  #
+  # Example:
+  # [[network.adr_external_plugins]]
+  #
+  #   # Protocol (grpc or http).
+  #   #
+  #   # When set to http, the Protobuf encoded request is posted to the server
+  #   # URL, with the RPC method (GetInfo or Handle) as method URL parameter.
+  #   protocol="grpc"
+  #
+  #   # Server.
+  #   server="http://localhost:50051"
+  #
+  #   # Timeout.
+  #   timeout="100ms"
+  {{#each network.adr_external_plugins}}
+  [[network.adr_external_plugins]]
+    protocol="{{this.protocol}}"
+    server="{{this.server}}"
+    timeout="{{this.timeout}}"
+  {{/each}}
+
 
   # Scheduler settings.
   [network.scheduler]
@@ -227,7 +296,8 @@ pub fn run() {
     # Scheduler interval.
     #
     # The interval in which the downlink scheduler for multicast, Class-B and
-    # Class-C runs.
+    # Class-C runs. This is also the interval in which expired device
+    # queue-items are deleted.
     interval="{{ network.scheduler.interval }}"
 
     # Class-A lock duration.
@@ -259,6 +329,20 @@ pub fn run() {
     # scheduler interval.
     multicast_class_b_margin="{{ network.scheduler.multicast_class_b_margin }}"
 
+    # Multicast delivery timeout.
+    #
+    # Multicast-group queue items which have not been transmitted, or of which
+    # the gateway did not report its tx acknowledgement, within this duration
+    # after their scheduled time are discarded. Their delivery is reported with
+    # the DISCARDED status.
+    multicast_delivery_timeout="{{ network.scheduler.multicast_delivery_timeout }}"
+
+    # Multicast delivery retention.
+    #
+    # This defines how long the delivery status of multicast-group queue items
+    # is kept after it has been reported.
+    multicast_delivery_retention="{{ network.scheduler.multicast_delivery_retention }}"
+
 
 # Monitoring related configuration.
 [monitoring]
@@ -290,6 +374,20 @@ pub fn run() {
   # Setting this value to 0 disables this feature.
   gateway_frame_log_max_history={{ monitoring.gateway_frame_log_max_history }}
 
+  # Gateway state-log max history.
+  #
+  # This defines the max number of gateway state-change records that will be persisted
+  # in Redis Streams. This stream contains the ONLINE / OFFLINE state changes of all
+  # gateways, as reported by the gateway connection-state messages.
+  # Setting this value to 0 disables this feature.
+  #
+  # Gateway state-changes are not published as integration events. This stream
+  # (key: gw:stream:state, prefixed by the Redis key_prefix, field: state, encoded
+  # as stream.GatewayStateChange Protobuf message) is the only interface for
+  # consuming state-changes as they happen. The state-change history of a gateway can be retrieved using the
+  # GatewayService.ListStateChanges API method.
+  gateway_state_log_max_history={{ monitoring.gateway_state_log_max_history }}
+
   # Device frame-log max history.
   #
   # This defines the max number of frame-log records that will be persisted in Redis Streams.
@@ -362,6 +460,26 @@ pub fn run() {
     # This is the topic on which the MQTT subscribes for receiving (enqueue) commands.
     command_topic="{{ integration.mqtt.command_topic }}"
 
+    # Gateway event topic template.
+    #
+    # This is the topic on which gateway events (e.g. raw packet-forwarder
+    # events) are published, the gateway_id and event variables can be used.
+    # When empty, gateway events are not published. Gateway events are only
+    # published by the MQTT integration, which must be enabled when this is
+    # set.
+    gateway_event_topic="{{ integration.mqtt.gateway_event_topic }}"
+
+    # Multicast-group event topic template.
+    #
+    # This is the topic on which multicast-group events (e.g. delivery events)
+    # are published, the application_id, multicast_group_id and event variables
+    # can be used. When empty (default), multicast-group events are not
+    # published. Like gateway events, multicast-group events are only
+    # published by the MQTT integration, which must be enabled when this is
+    # set. Example:
+    #   multicast_group_event_topic="application/\{{application_id}}/multicast-group/\{{multicast_group_id}}/event/\{{event}}"
+    multicast_group_event_topic="{{ integration.mqtt.multicast_group_event_topic }}"
+
     # Use JSON encoding instead of Protobuf (binary).
     json={{ integration.mqtt.json }}
 
@@ -539,6 +657,20 @@ pub fn run() {
     # Maximum execution time.
     max_execution_time="{{ codec.js.max_execution_time }}"
 
+    # Device codec context TTL.
+    #
+    # The decodeUplink function can store a per-device context object
+    # (input.context), which is passed to the next decodeUplink call of the
+    # same device. The TTL is refreshed each time the context is stored.
+    # The context is removed when the device (re-)joins.
+    context_ttl="{{ codec.js.context_ttl }}"
+
+    # Max. size of the device codec context (bytes).
+    #
+    # Contexts exceeding this size are not stored and a warning is emitted
+    # as codec log event.
+    max_context_size={{ codec.js.max_context_size }}
+
 
 # User authentication configuration.
 [user_authentication]
@@ -715,6 +847,38 @@ pub fn run() {
       {{/each}}
     ]
 
+  # TOTP based two-factor authentication.
+  #
+  # This applies to the internal authentication backend only. Users can enroll
+  # an authenticator app from their profile.
+  [user_authentication.totp]
+
+    # Required.
+    #
+    # If set to true, all users must use two-factor authentication. Users that
+    # did not yet enroll an authenticator app will be asked to do so on login.
+    # Note that it is also possible to require two-factor authentication for
+    # the users of a tenant only (tenant setting).
+    required={{ user_authentication.totp.required }}
+
+    # Issuer.
+    #
+    # This is the issuer name which is displayed by the authenticator app.
+    issuer="{{ user_authentication.totp.issuer }}"
+
+    # Max. failed attempts.
+    #
+    # After this number of failed TOTP code attempts, the user is locked out
+    # from completing the login using a TOTP code until the lockout duration
+    # has expired.
+    max_failed_attempts={{ user_authentication.totp.max_failed_attempts }}
+
+    # Lockout duration.
+    #
+    # This is the duration (counted from the last failed attempt) for which
+    # the failed attempts are remembered.
+    lockout_duration="{{ user_authentication.totp.lockout_duration }}"
+
 
 # Join Server configuration.
 [join_server]
@@ -957,15 +1121,39 @@ pub fn run() {
   # This configures the map attribution. The default attribution relates to the
   # default tileserver_url (OSM). If you configure a different tile-server, you
   # might need to update the map_attribution.
-  map_attribution="{{ui.map_attribution}}"
+  map_attribution='{{ui.map_attribution}}'
 "#;
 
+pub fn run() {
+    let conf = config::get();
+    println!("{}", render(&conf).expect("render configfile error"));
+}
+
+fn render(conf: &config::Configuration) -> Result<String> {
     let mut reg = Handlebars::new();
     reg.register_escape_fn(no_escape);
-    let conf = config::get();
-    println!(
-        "{}",
-        reg.render_template(template, &conf)
-            .expect("render configfile error")
-    );
+    Ok(reg.render_template(TEMPLATE, conf)?)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut conf = config::Configuration::default();
+        conf.network.adr_plugins = vec!["plugin.js".into()];
+        conf.network.adr_plugins_reload_interval = Duration::from_secs(30);
+
+        let out = render(&conf).unwrap();
+        let conf: config::Configuration = toml::from_str(&out).unwrap();
+
+        assert_eq!(vec!["plugin.js".to_string()], conf.network.adr_plugins);
+        assert_eq!(
+            Duration::from_secs(30),
+            conf.network.adr_plugins_reload_interval
+        );
+    }
 }
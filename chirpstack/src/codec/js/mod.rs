@@ -5,7 +5,7 @@ use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use rquickjs::{CatchResultExt, IntoJs};
 
-use super::convert;
+use super::{convert, Decoded};
 use crate::config;
 
 mod vendor_base64_js;
@@ -16,9 +16,10 @@ pub async fn decode(
     recv_time: DateTime<Utc>,
     f_port: u8,
     variables: &HashMap<String, String>,
+    context: &pbjson_types::Struct,
     decode_config: &str,
     b: &[u8],
-) -> Result<pbjson_types::Struct> {
+) -> Result<Decoded> {
     let conf = config::get();
     let max_run_ts = SystemTime::now() + conf.codec.js.max_execution_time;
 
@@ -47,7 +48,7 @@ pub async fn decode(
     );
     let b = b.to_vec();
 
-    let out = ctx.with(|ctx| -> Result<pbjson_types::Struct> {
+    ctx.with(|ctx| -> Result<Decoded> {
         // We need to export the Buffer class, as eval / eval_with_options
         // does not allow using import statement.
         let buff = rquickjs::Module::declare(
@@ -71,9 +72,13 @@ pub async fn decode(
         input.set("fPort", f_port.into_js(&ctx)?)?;
         input.set("recvTime", recv_time.into_js(&ctx)?)?;
         input.set("variables", variables.into_js(&ctx)?)?;
+        input.set(
+            "context",
+            convert::struct_to_rquickjs(&ctx, &convert::pb_json_to_prost(context)),
+        )?;
 
         let globals = ctx.globals();
-        globals.set("chirpstack_input", input)?;
+        globals.set("chirpstack_input", input.clone())?;
         globals.set("Buffer", buff)?;
 
         let mut eval_options = rquickjs::context::EvalOptions::default();
@@ -84,25 +89,32 @@ pub async fn decode(
             .catch(&ctx)
             .map_err(|e| anyhow!("JS error: {}", e))?;
 
-        let errors: Result<Vec<String>, rquickjs::Error> = res.get("errors");
-        if let Ok(errors) = errors {
-            if !errors.is_empty() {
-                return Err(anyhow!(
-                    "decodeUplink returned errors: {}",
-                    errors.join(", ")
-                ));
-            }
-        }
+        // The context might have been modified or replaced by the decoder.
+        let context: rquickjs::Value = input.get("context")?;
+        let context = convert::rquickjs_to_struct(&context);
 
-        Ok(convert::rquickjs_to_struct(&res))
-    })?;
+        let warnings: Vec<String> = res.get("warnings").unwrap_or_default();
+        let errors: Vec<String> = res.get("errors").unwrap_or_default();
 
-    let data = out.fields.get("data").cloned().unwrap_or_default();
-    if let Some(pbjson_types::value::Kind::StructValue(v)) = data.kind {
-        return Ok(v);
-    }
+        let out = convert::rquickjs_to_struct(&res);
+        let data = out.fields.get("data").cloned().unwrap_or_default();
+        let object = match data.kind {
+            Some(pbjson_types::value::Kind::StructValue(v)) => Some(v),
+            _ => None,
+        };
+
+        // In case of errors, the returned data might be partial or missing.
+        if object.is_none() && errors.is_empty() {
+            return Err(anyhow!("decodeUplink did not return 'data'"));
+        }
 
-    Err(anyhow!("decodeUplink did not return 'data'"))
+        Ok(Decoded {
+            object,
+            warnings,
+            errors,
+            context: Some(context),
+        })
+    })
 }
 
 pub async fn encode(
@@ -210,7 +222,15 @@ pub mod test {
         .to_string();
 
         let vars: HashMap<String, String> = HashMap::new();
-        let out = decode(Utc::now(), 10, &vars, &decoder, &[0x01, 0x02, 0x03]).await;
+        let out = decode(
+            Utc::now(),
+            10,
+            &vars,
+            &Default::default(),
+            &decoder,
+            &[0x01, 0x02, 0x03],
+        )
+        .await;
         assert!(out.is_err());
     }
 
@@ -224,7 +244,15 @@ pub mod test {
         .to_string();
 
         let vars: HashMap<String, String> = HashMap::new();
-        let out = decode(Utc::now(), 10, &vars, &decoder, &[0x01, 0x02, 0x03]).await;
+        let out = decode(
+            Utc::now(),
+            10,
+            &vars,
+            &Default::default(),
+            &decoder,
+            &[0x01, 0x02, 0x03],
+        )
+        .await;
 
         assert_eq!(
             "JS error: Error:4:24 'foo' is not defined\n    at decodeUplink (eval_script:4:24)\n    at <eval> (eval_script:8:9)\n",
@@ -256,9 +284,16 @@ pub mod test {
         let mut vars: HashMap<String, String> = HashMap::new();
         vars.insert("foo".into(), "bar".into());
 
-        let out = decode(recv_time, 10, &vars, &decoder, &[0x01, 0x02, 0x03])
-            .await
-            .unwrap();
+        let out = decode(
+            recv_time,
+            10,
+            &vars,
+            &Default::default(),
+            &decoder,
+            &[0x01, 0x02, 0x03],
+        )
+        .await
+        .unwrap();
 
         let expected = pbjson_types::Struct {
             fields: [
@@ -328,7 +363,183 @@ pub mod test {
             .collect(),
         };
 
-        assert_eq!(expected, out);
+        assert_eq!(
+            Decoded {
+                object: Some(expected),
+                context: Some(Default::default()),
+                ..Default::default()
+            },
+            out
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_decode_warnings_and_errors() {
+        let decoder = r#"
+            function decodeUplink(input) {
+                return {
+                    data: {
+                        temp: input.bytes[0]
+                    },
+                    warnings: ["low battery"],
+                    errors: ["humidity sensor failure"]
+                };
+            }
+        "#
+        .to_string();
+
+        let vars: HashMap<String, String> = HashMap::new();
+        let out = decode(
+            Utc::now(),
+            10,
+            &vars,
+            &Default::default(),
+            &decoder,
+            &[0x01],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            Decoded {
+                object: Some(pbjson_types::Struct {
+                    fields: [(
+                        "temp".to_string(),
+                        pbjson_types::Value {
+                            kind: Some(pbjson_types::value::Kind::NumberValue(1.0)),
+                        },
+                    )]
+                    .iter()
+                    .cloned()
+                    .collect(),
+                }),
+                warnings: vec!["low battery".into()],
+                errors: vec!["humidity sensor failure".into()],
+                context: Some(Default::default()),
+            },
+            out
+        );
+
+        // errors without data
+        let decoder = r#"
+            function decodeUplink(input) {
+                return {
+                    errors: ["unknown payload type"]
+                };
+            }
+        "#
+        .to_string();
+
+        let out = decode(
+            Utc::now(),
+            10,
+            &vars,
+            &Default::default(),
+            &decoder,
+            &[0x01],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            Decoded {
+                object: None,
+                warnings: vec![],
+                errors: vec!["unknown payload type".into()],
+                context: Some(Default::default()),
+            },
+            out
+        );
+
+        // no data and no errors
+        let decoder = r#"
+            function decodeUplink(input) {
+                return {};
+            }
+        "#
+        .to_string();
+
+        let out = decode(
+            Utc::now(),
+            10,
+            &vars,
+            &Default::default(),
+            &decoder,
+            &[0x01],
+        )
+        .await;
+        assert_eq!(
+            "decodeUplink did not return 'data'",
+            out.err().unwrap().to_string()
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_decode_context() {
+        let decoder = r#"
+            function decodeUplink(input) {
+                var counter = (input.context.counter || 0) + 1;
+                input.context.counter = counter;
+
+                return {
+                    data: {
+                        counter: counter
+                    }
+                };
+            }
+        "#
+        .to_string();
+
+        let vars: HashMap<String, String> = HashMap::new();
+        let counter = |v: f64| pbjson_types::Struct {
+            fields: [(
+                "counter".to_string(),
+                pbjson_types::Value {
+                    kind: Some(pbjson_types::value::Kind::NumberValue(v)),
+                },
+            )]
+            .iter()
+            .cloned()
+            .collect(),
+        };
+
+        // empty context
+        let out = decode(
+            Utc::now(),
+            10,
+            &vars,
+            &Default::default(),
+            &decoder,
+            &[0x01],
+        )
+        .await
+        .unwrap();
+        assert_eq!(Some(counter(1.0)), out.object);
+        assert_eq!(Some(counter(1.0)), out.context);
+
+        // previous context
+        let out = decode(Utc::now(), 10, &vars, &counter(1.0), &decoder, &[0x01])
+            .await
+            .unwrap();
+        assert_eq!(Some(counter(2.0)), out.object);
+        assert_eq!(Some(counter(2.0)), out.context);
+
+        // replaced context
+        let decoder = r#"
+            function decodeUplink(input) {
+                input.context = {};
+
+                return {
+                    data: {}
+                };
+            }
+        "#
+        .to_string();
+
+        let out = decode(Utc::now(), 10, &vars, &counter(2.0), &decoder, &[0x01])
+            .await
+            .unwrap();
+        assert_eq!(Some(pbjson_types::Struct::default()), out.context);
     }
 
     #[tokio::test]
@@ -23,6 +23,19 @@ pub enum Codec {
     JS,
 }
 
+// Decoded uplink payload. Besides the decoded object, this contains the
+// warnings and errors returned by the codec. In case errors are returned,
+// the object contains the (partial) data that could be decoded, if any.
+// The context is only set by codecs supporting a per-device context and
+// must be persisted for the next decode call.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Decoded {
+    pub object: Option<pbjson_types::Struct>,
+    pub warnings: Vec<String>,
+    pub errors: Vec<String>,
+    pub context: Option<pbjson_types::Struct>,
+}
+
 impl fmt::Display for Codec {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}", self)
@@ -69,13 +82,17 @@ pub async fn binary_to_struct(
     recv_time: DateTime<Utc>,
     f_port: u8,
     variables: &HashMap<String, String>,
+    context: &pbjson_types::Struct,
     decoder_config: &str,
     b: &[u8],
-) -> Result<Option<pbjson_types::Struct>> {
+) -> Result<Decoded> {
     Ok(match codec {
-        Codec::NONE => None,
-        Codec::CAYENNE_LPP => Some(cayenne_lpp::decode(b).context("CayenneLpp decode")?),
-        Codec::JS => Some(js::decode(recv_time, f_port, variables, decoder_config, b).await?),
+        Codec::NONE => Decoded::default(),
+        Codec::CAYENNE_LPP => Decoded {
+            object: Some(cayenne_lpp::decode(b).context("CayenneLpp decode")?),
+            ..Default::default()
+        },
+        Codec::JS => js::decode(recv_time, f_port, variables, context, decoder_config, b).await?,
     })
 }
 
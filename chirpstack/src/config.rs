@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
@@ -9,6 +10,8 @@ use serde::{Deserialize, Serialize};
 use lrwn::region::CommonName;
 use lrwn::{AES128Key, DevAddrPrefix, EUI64Prefix, NetID};
 
+use crate::storage::fields::GatewaySelectionStrategy;
+
 lazy_static! {
     static ref CONFIG: Mutex<Arc<Configuration>> = Mutex::new(Arc::new(Default::default()));
 }
@@ -95,6 +98,7 @@ impl Default for Redis {
 pub struct Api {
     pub bind: String,
     pub secret: String,
+    pub trusted_proxies: Vec<String>,
 }
 
 impl Default for Api {
@@ -102,6 +106,7 @@ impl Default for Api {
         Api {
             bind: "0.0.0.0:8080".into(),
             secret: "".into(),
+            trusted_proxies: vec![],
         }
     }
 }
@@ -114,6 +119,8 @@ pub struct Gateway {
     pub ca_cert: String,
     pub ca_key: String,
     pub allow_unknown_gateways: bool,
+    #[serde(with = "humantime_serde")]
+    pub command_exec_timeout: Duration,
 }
 
 impl Default for Gateway {
@@ -123,6 +130,7 @@ impl Default for Gateway {
             ca_cert: "".to_string(),
             ca_key: "".to_string(),
             allow_unknown_gateways: false,
+            command_exec_timeout: Duration::from_secs(10),
         }
     }
 }
@@ -141,7 +149,12 @@ pub struct Network {
     #[serde(with = "humantime_serde")]
     pub get_downlink_data_delay: Duration,
     pub mac_commands_disabled: bool,
+    #[serde(with = "humantime_serde")]
+    pub fragmentation_session_timeout: Duration,
     pub adr_plugins: Vec<String>,
+    #[serde(with = "humantime_serde")]
+    pub adr_plugins_reload_interval: Duration,
+    pub adr_external_plugins: Vec<AdrExternalPlugin>,
     pub scheduler: Scheduler,
 }
 
@@ -156,12 +169,34 @@ impl Default for Network {
             deduplication_delay: Duration::from_millis(200),
             get_downlink_data_delay: Duration::from_millis(100),
             mac_commands_disabled: false,
+            fragmentation_session_timeout: Duration::from_secs(60 * 60 * 24),
             adr_plugins: vec![],
+            adr_plugins_reload_interval: Duration::from_secs(0),
+            adr_external_plugins: vec![],
             scheduler: Default::default(),
         }
     }
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AdrExternalPlugin {
+    pub protocol: String,
+    pub server: String,
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+}
+
+impl Default for AdrExternalPlugin {
+    fn default() -> Self {
+        AdrExternalPlugin {
+            protocol: "grpc".into(),
+            server: "".into(),
+            timeout: Duration::from_millis(100),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(default)]
 pub struct Scheduler {
@@ -176,6 +211,10 @@ pub struct Scheduler {
     pub multicast_class_c_margin: Duration,
     #[serde(with = "humantime_serde")]
     pub multicast_class_b_margin: Duration,
+    #[serde(with = "humantime_serde")]
+    pub multicast_delivery_timeout: Duration,
+    #[serde(with = "humantime_serde")]
+    pub multicast_delivery_retention: Duration,
 }
 
 impl Default for Scheduler {
@@ -187,6 +226,8 @@ impl Default for Scheduler {
             class_c_lock_duration: Duration::from_secs(5),
             multicast_class_c_margin: Duration::from_secs(5),
             multicast_class_b_margin: Duration::from_secs(5),
+            multicast_delivery_timeout: Duration::from_secs(60 * 60),
+            multicast_delivery_retention: Duration::from_secs(60 * 60 * 24 * 7),
         }
     }
 }
@@ -199,6 +240,7 @@ pub struct Monitoring {
     pub backend_interfaces_log_max_history: usize,
     pub meta_log_max_history: usize,
     pub gateway_frame_log_max_history: usize,
+    pub gateway_state_log_max_history: usize,
     pub device_frame_log_max_history: usize,
     pub device_event_log_max_history: usize,
     pub per_gateway_frame_log_max_history: usize,
@@ -220,6 +262,7 @@ impl Default for Monitoring {
             backend_interfaces_log_max_history: 10,
             meta_log_max_history: 10,
             gateway_frame_log_max_history: 10,
+            gateway_state_log_max_history: 10,
             device_frame_log_max_history: 10,
             device_event_log_max_history: 10,
             per_gateway_frame_log_max_history: 10,
@@ -248,6 +291,8 @@ pub struct MqttIntegration {
     pub client: MqttIntegrationClient,
     pub event_topic: String,
     pub command_topic: String,
+    pub gateway_event_topic: String,
+    pub multicast_group_event_topic: String,
     pub json: bool,
     pub server: String,
     pub username: String,
@@ -270,6 +315,8 @@ impl Default for MqttIntegration {
             event_topic: "application/{{application_id}}/device/{{dev_eui}}/event/{{event}}".into(),
             command_topic: "application/{{application_id}}/device/{{dev_eui}}/command/{{command}}"
                 .into(),
+            gateway_event_topic: "".into(),
+            multicast_group_event_topic: "".into(),
             json: true,
             server: "tcp://127.0.0.1:1883/".into(),
             username: "".into(),
@@ -382,12 +429,17 @@ pub struct Codec {
 pub struct CodecJs {
     #[serde(with = "humantime_serde")]
     pub max_execution_time: Duration,
+    #[serde(with = "humantime_serde")]
+    pub context_ttl: Duration,
+    pub max_context_size: usize,
 }
 
 impl Default for CodecJs {
     fn default() -> Self {
         CodecJs {
             max_execution_time: Duration::from_millis(100),
+            context_ttl: Duration::from_secs(60 * 60 * 24 * 31),
+            max_context_size: 4096,
         }
     }
 }
@@ -398,6 +450,7 @@ pub struct UserAuthentication {
     pub enabled: String,
     pub openid_connect: OpenIdConnect,
     pub oauth2: OAuth2,
+    pub totp: Totp,
 }
 
 impl Default for UserAuthentication {
@@ -406,6 +459,7 @@ impl Default for UserAuthentication {
             enabled: "internal".into(),
             openid_connect: Default::default(),
             oauth2: Default::default(),
+            totp: Default::default(),
         }
     }
 }
@@ -484,6 +538,27 @@ impl Default for OAuth2 {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Totp {
+    pub required: bool,
+    pub issuer: String,
+    pub max_failed_attempts: u32,
+    #[serde(with = "humantime_serde")]
+    pub lockout_duration: Duration,
+}
+
+impl Default for Totp {
+    fn default() -> Self {
+        Totp {
+            required: false,
+            issuer: "ChirpStack".into(),
+            max_failed_attempts: 5,
+            lockout_duration: Duration::from_secs(60 * 15),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Default, Clone)]
 #[serde(default)]
 pub struct JoinServer {
@@ -600,6 +675,8 @@ pub struct RegionNetwork {
     pub rx2_prefer_on_rx1_dr_lt: u8,
     pub rx2_prefer_on_link_budget: bool,
     pub gateway_prefer_min_margin: f32,
+    pub gateway_selection_strategy: GatewaySelectionStrategy,
+    pub gateway_prefer_tags: HashMap<String, String>,
     pub downlink_tx_power: i32,
     pub adr_disabled: bool,
     pub min_dr: u8,
@@ -627,6 +704,8 @@ impl Default for RegionNetwork {
             rx2_prefer_on_rx1_dr_lt: 0,
             rx2_prefer_on_link_budget: false,
             gateway_prefer_min_margin: 10.0,
+            gateway_selection_strategy: GatewaySelectionStrategy::RANDOM,
+            gateway_prefer_tags: HashMap::new(),
             downlink_tx_power: -1,
             adr_disabled: false,
             min_dr: 0,
@@ -672,6 +751,7 @@ pub struct ExtraChannel {
 pub struct RegionGateway {
     pub force_gws_private: bool,
     pub backend: GatewayBackend,
+    pub extra_backends: Vec<GatewayBackend>,
     pub channels: Vec<GatewayChannel>,
 }
 
@@ -680,6 +760,8 @@ pub struct RegionGateway {
 pub struct GatewayBackend {
     pub enabled: String,
     pub mqtt: GatewayBackendMqtt,
+    pub semtech_udp: GatewayBackendSemtechUdp,
+    pub basics_station: GatewayBackendBasicsStation,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -687,6 +769,7 @@ pub struct GatewayBackend {
 pub struct GatewayBackendMqtt {
     pub topic_prefix: String,
     pub event_topic: String,
+    pub state_topic: String,
     pub command_topic: String,
     pub server: String,
     pub username: String,
@@ -708,6 +791,7 @@ impl Default for GatewayBackendMqtt {
         GatewayBackendMqtt {
             topic_prefix: "".into(),
             event_topic: "".into(),
+            state_topic: "".into(),
             command_topic: "".into(),
             server: "tcp://127.0.0.1:1883/".into(),
             username: "".into(),
@@ -725,6 +809,41 @@ impl Default for GatewayBackendMqtt {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct GatewayBackendSemtechUdp {
+    pub bind: String,
+    #[serde(with = "humantime_serde")]
+    pub cleanup_duration: Duration,
+}
+
+impl Default for GatewayBackendSemtechUdp {
+    fn default() -> Self {
+        GatewayBackendSemtechUdp {
+            bind: "0.0.0.0:1700".into(),
+            cleanup_duration: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct GatewayBackendBasicsStation {
+    pub bind: String,
+    pub tls_cert: String,
+    pub tls_key: String,
+}
+
+impl Default for GatewayBackendBasicsStation {
+    fn default() -> Self {
+        GatewayBackendBasicsStation {
+            bind: "0.0.0.0:3001".into(),
+            tls_cert: "".into(),
+            tls_key: "".into(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Hash)]
 #[allow(non_camel_case_types)]
 #[allow(clippy::upper_case_acronyms)]
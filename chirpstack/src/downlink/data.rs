@@ -219,7 +219,7 @@ impl Data {
             more_device_queue_items: false,
         };
 
-        ctx.select_downlink_gateway()?;
+        ctx.select_downlink_gateway().await?;
         ctx.set_tx_info()?;
         ctx.get_next_device_queue_item().await?;
         ctx.set_mac_commands().await?;
@@ -303,7 +303,7 @@ impl Data {
             more_device_queue_items: false,
         };
 
-        ctx.select_downlink_gateway()?;
+        ctx.select_downlink_gateway().await?;
         ctx.set_tx_info_relayed()?;
         ctx.get_next_device_queue_item().await?;
         ctx.set_mac_commands().await?;
@@ -371,7 +371,7 @@ impl Data {
             more_device_queue_items: false,
         };
 
-        ctx.select_downlink_gateway()?;
+        ctx.select_downlink_gateway().await?;
         if ctx._is_class_c() {
             ctx.class_c_update_scheduler_run_after().await?;
             ctx.check_for_first_uplink()?;
@@ -396,15 +396,20 @@ impl Data {
         Ok(())
     }
 
-    fn select_downlink_gateway(&mut self) -> Result<()> {
+    async fn select_downlink_gateway(&mut self) -> Result<()> {
         trace!("Selecting downlink gateway");
 
         let gw_down = helpers::select_downlink_gateway(
             Some(self.tenant.id),
             &self.device.get_device_session()?.region_config_id,
             self.network_conf.gateway_prefer_min_margin,
+            self.device_profile
+                .gateway_selection_strategy
+                .unwrap_or(self.network_conf.gateway_selection_strategy),
+            &self.network_conf.gateway_prefer_tags,
             self.device_gateway_rx_info.as_mut().unwrap(),
-        )?;
+        )
+        .await?;
 
         self.downlink_frame.gateway_id = hex::encode(&gw_down.gateway_id);
         self.downlink_gateway = Some(gw_down);
@@ -501,11 +506,13 @@ impl Data {
             // The queue item:
             // * should fit within the max payload size
             // * should not be pending
+            // * should not be expired
             // * in case encrypted, should have a valid FCntDown
             if qi.data.len() <= max_payload_size
-                && !qi.is_pending
-                && !(qi.is_encrypted
-                    && (qi.f_cnt_down.unwrap_or_default() as u32) < ds.get_a_f_cnt_down())
+                && !(qi.is_pending
+                    || qi.is_expired()
+                    || (qi.is_encrypted
+                        && (qi.f_cnt_down.unwrap_or_default() as u32) < ds.get_a_f_cnt_down()))
             {
                 trace!(id = %qi.id, more_in_queue = more_in_queue, "Found device queue-item for downlink");
                 self.device_queue_item = Some(qi);
@@ -562,6 +569,19 @@ impl Data {
                 continue;
             }
 
+            // Handle expired queue-item.
+            if qi.is_expired() {
+                device_queue::delete_item(&qi.id)
+                    .await
+                    .context("Delete device queue-item")?;
+
+                let pl = queue_item_expired_log_event(device_info.clone(), &qi);
+                integration::log_event(self.application.id, &self.device.variables, &pl).await;
+                warn!(dev_eui = %self.device.dev_eui, device_queue_item_id = %qi.id, "Device queue-item discarded because it has expired");
+
+                continue;
+            }
+
             // Handle payload size.
             if qi.data.len() > max_payload_size {
                 device_queue::delete_item(&qi.id)
@@ -1282,47 +1302,15 @@ impl Data {
             return Ok(());
         }
 
-        let dr = self
-            .region_conf
-            .get_data_rate(self.uplink_frame_set.as_ref().unwrap().dr)?;
-
         let ufs = self.uplink_frame_set.as_ref().unwrap();
         let ds = self.device.get_device_session()?;
-
-        let req = adr::Request {
-            region_config_id: ufs.region_config_id.clone(),
-            region_common_name: ufs.region_common_name,
-            dev_eui: self.device.dev_eui,
-            mac_version: self.device_profile.mac_version,
-            reg_params_revision: self.device_profile.reg_params_revision,
-            adr: ds.adr,
-            dr: self.uplink_frame_set.as_ref().unwrap().dr,
-            tx_power_index: ds.tx_power_index as u8,
-            nb_trans: ds.nb_trans as u8,
-            max_tx_power_index: if ds.max_supported_tx_power_index != 0 {
-                ds.max_supported_tx_power_index as u8
-            } else {
-                let mut max_tx_power_index: u8 = 0;
-                for n in 0..16 {
-                    if self.region_conf.get_tx_power_offset(n).is_ok() {
-                        max_tx_power_index = n as u8;
-                    }
-                }
-                max_tx_power_index
-            },
-            required_snr_for_dr: match dr {
-                lrwn::region::DataRateModulation::Lora(params) => {
-                    config::get_required_snr_for_sf(params.spreading_factor)?
-                }
-                _ => 0.0,
-            },
-            installation_margin: self.network_conf.installation_margin,
-            min_dr: self.network_conf.min_dr,
-            max_dr: self.network_conf.max_dr,
-            uplink_history: ds.uplink_adr_history.clone(),
-            skip_f_cnt_check: ds.skip_f_cnt_check,
-            device_variables: self.device.variables.into_hashmap(),
-        };
+        let req = adr::Request::new(
+            &ufs.region_config_id,
+            &self.device,
+            &self.device_profile,
+            ds,
+            ufs.dr,
+        )?;
 
         let resp = adr::handle(&self.device_profile.adr_algorithm_id, &req).await;
 
@@ -2680,6 +2668,61 @@ impl Data {
     }
 }
 
+/// It logs the DeviceQueueItemExpired event for the given queue-item, which has been deleted
+/// by the expired queue-items sweep.
+pub async fn log_expired_queue_item(qi: &device_queue::DeviceQueueItem) -> Result<()> {
+    let (dev, app, t, dp) = get_all_device_data(qi.dev_eui)
+        .await
+        .context("Get all device data")?;
+
+    let device_info = integration_pb::DeviceInfo {
+        tenant_id: t.id.to_string(),
+        tenant_name: t.name.clone(),
+        application_id: app.id.to_string(),
+        application_name: app.name.to_string(),
+        device_profile_id: dp.id.to_string(),
+        device_profile_name: dp.name.clone(),
+        device_name: dev.name.clone(),
+        device_class_enabled: dev.enabled_class.to_proto().into(),
+        dev_eui: dev.dev_eui.to_string(),
+        tags: {
+            let mut tags = (*app.tags).clone();
+            tags.extend((*dp.tags).clone());
+            tags.extend((*dev.tags).clone());
+            tags
+        },
+    };
+
+    let pl = queue_item_expired_log_event(device_info, qi);
+    integration::log_event(app.id, &dev.variables, &pl).await;
+    warn!(dev_eui = %dev.dev_eui, device_queue_item_id = %qi.id, "Device queue-item discarded because it has expired");
+
+    Ok(())
+}
+
+fn queue_item_expired_log_event(
+    device_info: integration_pb::DeviceInfo,
+    qi: &device_queue::DeviceQueueItem,
+) -> integration_pb::LogEvent {
+    integration_pb::LogEvent {
+        time: Some(Utc::now().into()),
+        device_info: Some(device_info),
+        level: integration_pb::LogLevel::Warning.into(),
+        code: integration_pb::LogCode::DeviceQueueItemExpired.into(),
+        description: "Device queue-item discarded because it has expired".to_string(),
+        context: [
+            (
+                "expires_at".to_string(),
+                qi.expires_at.unwrap_or_default().to_rfc3339(),
+            ),
+            ("queue_item_id".to_string(), qi.id.to_string()),
+        ]
+        .iter()
+        .cloned()
+        .collect(),
+    }
+}
+
 fn filter_mac_commands(
     device_session: &internal::DeviceSession,
     mac_commands: &[lrwn::MACCommandSet],
@@ -2787,6 +2830,8 @@ mod test {
         }
 
         let qi_id = Uuid::new_v4();
+        let qi_id_2 = Uuid::new_v4();
+        let expires_at: DateTime<Utc> = "2024-01-01T00:00:00Z".parse().unwrap();
 
         let tests = vec![
             Test {
@@ -2902,6 +2947,57 @@ mod test {
                     ..Default::default()
                 }),
             },
+            Test {
+                name: "expired".into(),
+                max_payload_size: 10,
+                queue_items: vec![
+                    device_queue::DeviceQueueItem {
+                        id: qi_id,
+                        dev_eui: d.dev_eui,
+                        f_port: 1,
+                        data: vec![1, 2, 3],
+                        expires_at: Some(expires_at),
+                        priority: 1,
+                        ..Default::default()
+                    },
+                    device_queue::DeviceQueueItem {
+                        id: qi_id_2,
+                        dev_eui: d.dev_eui,
+                        f_port: 1,
+                        data: vec![1, 2, 3],
+                        ..Default::default()
+                    },
+                ],
+                expected_queue_item: Some(device_queue::DeviceQueueItem {
+                    id: qi_id_2,
+                    ..Default::default()
+                }),
+                expected_ack_event: None,
+                expected_log_event: Some(integration_pb::LogEvent {
+                    device_info: Some(integration_pb::DeviceInfo {
+                        tenant_id: t.id.to_string(),
+                        tenant_name: t.name.clone(),
+                        application_id: app.id.to_string(),
+                        application_name: app.name.clone(),
+                        device_profile_id: dp.id.to_string(),
+                        device_profile_name: dp.name.clone(),
+                        device_name: d.name.clone(),
+                        dev_eui: d.dev_eui.to_string(),
+                        ..Default::default()
+                    }),
+                    level: integration_pb::LogLevel::Warning.into(),
+                    code: integration_pb::LogCode::DeviceQueueItemExpired.into(),
+                    description: "Device queue-item discarded because it has expired".into(),
+                    context: [
+                        ("queue_item_id".to_string(), qi_id.to_string()),
+                        ("expires_at".to_string(), expires_at.to_rfc3339()),
+                    ]
+                    .iter()
+                    .cloned()
+                    .collect(),
+                    ..Default::default()
+                }),
+            },
             Test {
                 name: "valid payload".into(),
                 max_payload_size: 10,
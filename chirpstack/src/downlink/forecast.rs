@@ -0,0 +1,367 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use super::classb;
+use crate::config;
+use crate::gpstime::{ToDateTime, ToGpsTime};
+use crate::storage::device::{self, DeviceClass};
+use crate::storage::{device_profile, device_queue};
+use lrwn::{DevAddr, EUI64};
+
+pub struct Forecast {
+    pub enabled_class: DeviceClass,
+    pub scheduler_run_after: Option<DateTime<Utc>>,
+    pub scheduler_locked: bool,
+    pub ping_slots: Vec<DateTime<Utc>>,
+    pub queue_items: Vec<QueueItemForecast>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct QueueItemForecast {
+    pub id: Uuid,
+    pub scheduled_at: Option<DateTime<Utc>>,
+}
+
+struct ClassBParameters {
+    dev_addr: DevAddr,
+    ping_nb: usize,
+}
+
+// Returns the downlink scheduling forecast for the given device. Note that the
+// returned timestamps are estimates, e.g. the Class-B / Class-C scheduler runs
+// at a fixed interval and a Class-A uplink might lock the scheduler for the
+// device.
+pub async fn get_for_dev_eui(dev_eui: &EUI64, ping_slot_count: usize) -> Result<Forecast> {
+    let conf = config::get();
+    let dev = device::get(dev_eui).await?;
+    let dp = device_profile::get(&dev.device_profile_id).await?;
+    let items = device_queue::get_for_dev_eui(dev_eui).await?;
+    let now = Utc::now();
+
+    let class_b_params = match (dev.enabled_class, dev.get_device_session(), dev.dev_addr) {
+        (DeviceClass::B, Ok(ds), Some(dev_addr)) if ds.class_b_ping_slot_nb != 0 => {
+            Some(ClassBParameters {
+                dev_addr,
+                ping_nb: ds.class_b_ping_slot_nb as usize,
+            })
+        }
+        _ => None,
+    };
+
+    let ping_slots = match &class_b_params {
+        Some(p) => get_ping_slots(now, p, ping_slot_count)?,
+        None => vec![],
+    };
+
+    let queue_items = forecast_queue_items(
+        now,
+        dev.enabled_class,
+        dev.scheduler_run_after,
+        &items,
+        class_b_params.as_ref(),
+        Duration::from_std(conf.network.scheduler.class_c_lock_duration)?,
+        Duration::try_seconds(dp.class_c_timeout as i64).unwrap_or_default(),
+    )?;
+
+    Ok(Forecast {
+        enabled_class: dev.enabled_class,
+        scheduler_run_after: dev.scheduler_run_after,
+        scheduler_locked: dev.scheduler_run_after.map(|v| v > now).unwrap_or(false),
+        ping_slots,
+        queue_items,
+    })
+}
+
+fn get_ping_slots(
+    after: DateTime<Utc>,
+    p: &ClassBParameters,
+    count: usize,
+) -> Result<Vec<DateTime<Utc>>> {
+    let mut out = Vec::with_capacity(count);
+    let mut after_gps_ts = after.to_gps_time();
+
+    for _ in 0..count {
+        let ping_slot_ts = classb::get_next_ping_slot_after(after_gps_ts, &p.dev_addr, p.ping_nb)?;
+        out.push(ping_slot_ts.to_date_time());
+        after_gps_ts = ping_slot_ts;
+    }
+
+    Ok(out)
+}
+
+fn forecast_queue_items(
+    now: DateTime<Utc>,
+    enabled_class: DeviceClass,
+    scheduler_run_after: Option<DateTime<Utc>>,
+    items: &[device_queue::DeviceQueueItem],
+    class_b_params: Option<&ClassBParameters>,
+    class_c_lock_duration: Duration,
+    class_c_timeout: Duration,
+) -> Result<Vec<QueueItemForecast>> {
+    let mut out = Vec::with_capacity(items.len());
+
+    // Timestamp after which the next queue-item can be scheduled. None in case
+    // this can't be determined (e.g. Class-A or waiting for an acknowledgement).
+    let mut next_ts: Option<DateTime<Utc>> = match enabled_class {
+        DeviceClass::A => None,
+        DeviceClass::B if class_b_params.is_none() => None,
+        DeviceClass::B | DeviceClass::C => Some(match scheduler_run_after {
+            Some(v) if v > now => v,
+            _ => now,
+        }),
+    };
+
+    for qi in items {
+        // Pending items are awaiting an acknowledgement and will not be
+        // re-transmitted. The next item is scheduled after the timeout.
+        if qi.is_pending {
+            next_ts = match (next_ts, qi.timeout_after) {
+                (Some(ts), Some(timeout_after)) => Some(ts.max(timeout_after)),
+                _ => None,
+            };
+
+            out.push(QueueItemForecast {
+                id: qi.id,
+                scheduled_at: None,
+            });
+            continue;
+        }
+
+        let ts = match next_ts {
+            Some(v) => v,
+            None => {
+                out.push(QueueItemForecast {
+                    id: qi.id,
+                    scheduled_at: None,
+                });
+                continue;
+            }
+        };
+
+        let scheduled_at = match enabled_class {
+            DeviceClass::B => {
+                let p = class_b_params.unwrap();
+                // The scheduler requests the next ping-slot, at least one second
+                // in the future.
+                classb::get_next_ping_slot_after(
+                    ts.to_gps_time() + Duration::try_seconds(1).unwrap(),
+                    &p.dev_addr,
+                    p.ping_nb,
+                )?
+                .to_date_time()
+            }
+            _ => ts,
+        };
+
+        // Expired items are discarded by the scheduler.
+        if let Some(expires_at) = qi.expires_at {
+            if expires_at <= scheduled_at {
+                out.push(QueueItemForecast {
+                    id: qi.id,
+                    scheduled_at: None,
+                });
+                continue;
+            }
+        }
+
+        next_ts = match (enabled_class, qi.confirmed) {
+            // Class-B confirmed downlinks do not time out, the next item is
+            // scheduled after the acknowledgement.
+            (DeviceClass::B, true) => None,
+            (DeviceClass::B, false) => Some(scheduled_at),
+            (_, true) => Some(scheduled_at + class_c_lock_duration.max(class_c_timeout)),
+            (_, false) => Some(scheduled_at + class_c_lock_duration),
+        };
+
+        out.push(QueueItemForecast {
+            id: qi.id,
+            scheduled_at: Some(scheduled_at),
+        });
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn test_forecast_queue_items_class_c() {
+        let now = Utc::now();
+        let lock = Duration::try_seconds(5).unwrap();
+        let timeout = Duration::try_seconds(10).unwrap();
+
+        let qi_a = device_queue::DeviceQueueItem::default();
+        let qi_b = device_queue::DeviceQueueItem {
+            confirmed: true,
+            ..Default::default()
+        };
+        let qi_c = device_queue::DeviceQueueItem::default();
+
+        // scheduler locked
+        let out = forecast_queue_items(
+            now,
+            DeviceClass::C,
+            Some(now + Duration::try_seconds(3).unwrap()),
+            &[qi_a.clone(), qi_b.clone(), qi_c.clone()],
+            None,
+            lock,
+            timeout,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                QueueItemForecast {
+                    id: qi_a.id,
+                    scheduled_at: Some(now + Duration::try_seconds(3).unwrap()),
+                },
+                QueueItemForecast {
+                    id: qi_b.id,
+                    scheduled_at: Some(now + Duration::try_seconds(8).unwrap()),
+                },
+                QueueItemForecast {
+                    id: qi_c.id,
+                    scheduled_at: Some(now + Duration::try_seconds(18).unwrap()),
+                },
+            ],
+            out
+        );
+
+        // pending item
+        let qi_pending = device_queue::DeviceQueueItem {
+            is_pending: true,
+            timeout_after: Some(now + Duration::try_seconds(20).unwrap()),
+            ..Default::default()
+        };
+        let out = forecast_queue_items(
+            now,
+            DeviceClass::C,
+            None,
+            &[qi_pending.clone(), qi_a.clone()],
+            None,
+            lock,
+            timeout,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                QueueItemForecast {
+                    id: qi_pending.id,
+                    scheduled_at: None,
+                },
+                QueueItemForecast {
+                    id: qi_a.id,
+                    scheduled_at: Some(now + Duration::try_seconds(20).unwrap()),
+                },
+            ],
+            out
+        );
+
+        // expired item
+        let qi_expired = device_queue::DeviceQueueItem {
+            expires_at: Some(now + Duration::try_seconds(1).unwrap()),
+            ..Default::default()
+        };
+        let out = forecast_queue_items(
+            now,
+            DeviceClass::C,
+            Some(now + Duration::try_seconds(3).unwrap()),
+            &[qi_expired.clone(), qi_a.clone()],
+            None,
+            lock,
+            timeout,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                QueueItemForecast {
+                    id: qi_expired.id,
+                    scheduled_at: None,
+                },
+                QueueItemForecast {
+                    id: qi_a.id,
+                    scheduled_at: Some(now + Duration::try_seconds(3).unwrap()),
+                },
+            ],
+            out
+        );
+    }
+
+    #[test]
+    fn test_forecast_queue_items_class_a() {
+        let qi = device_queue::DeviceQueueItem::default();
+        let out = forecast_queue_items(
+            Utc::now(),
+            DeviceClass::A,
+            None,
+            &[qi.clone()],
+            None,
+            Duration::try_seconds(5).unwrap(),
+            Duration::try_seconds(10).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            vec![QueueItemForecast {
+                id: qi.id,
+                scheduled_at: None,
+            }],
+            out
+        );
+    }
+
+    #[test]
+    fn test_forecast_queue_items_class_b() {
+        let now = Utc::now();
+        let p = ClassBParameters {
+            dev_addr: DevAddr::from_be_bytes([1, 2, 3, 4]),
+            ping_nb: 1,
+        };
+
+        let ping_slots = get_ping_slots(now, &p, 2).unwrap();
+        assert_eq!(2, ping_slots.len());
+        assert!(ping_slots[0] > now);
+        assert!(ping_slots[1] > ping_slots[0]);
+
+        let qi_a = device_queue::DeviceQueueItem {
+            confirmed: true,
+            ..Default::default()
+        };
+        let qi_b = device_queue::DeviceQueueItem::default();
+
+        let out = forecast_queue_items(
+            now,
+            DeviceClass::B,
+            None,
+            &[qi_a.clone(), qi_b.clone()],
+            Some(&p),
+            Duration::try_seconds(5).unwrap(),
+            Duration::try_seconds(10).unwrap(),
+        )
+        .unwrap();
+
+        let expected_ts = classb::get_next_ping_slot_after(
+            now.to_gps_time() + Duration::try_seconds(1).unwrap(),
+            &p.dev_addr,
+            p.ping_nb,
+        )
+        .unwrap()
+        .to_date_time();
+
+        assert_eq!(
+            vec![
+                QueueItemForecast {
+                    id: qi_a.id,
+                    scheduled_at: Some(expected_ts),
+                },
+                // Scheduled after the acknowledgement of the confirmed item.
+                QueueItemForecast {
+                    id: qi_b.id,
+                    scheduled_at: None,
+                },
+            ],
+            out
+        );
+    }
+}
@@ -1,26 +1,36 @@
+use std::collections::HashMap;
 use std::str::FromStr;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::{Duration, Local};
 use rand::seq::SliceRandom;
+use tracing::warn;
 use uuid::Uuid;
 
 use chirpstack_api::{gw, internal};
 use lrwn::region::DataRateModulation;
+use lrwn::EUI64;
 
 use crate::config;
+use crate::helpers::errors::PrintFullError;
 use crate::region;
+use crate::storage::fields::GatewaySelectionStrategy;
+use crate::storage::{gateway, get_async_redis_conn, metrics, redis_key};
 
 // Returns the gateway to use for downlink.
 // It will filter out private gateways (gateways from a different tenant ID,
 // that do not allow downlinks). The result will be sorted based on SNR / RSSI.
 // The returned value is:
-//  * A random item from the elements with an SNR > minSNR
+//  * The item selected by the given strategy from the elements with an
+//    SNR > minSNR
 //  * The first item of the sorted slice (failing the above)
 //  * An error in case no gateways are available
-pub fn select_downlink_gateway(
+pub async fn select_downlink_gateway(
     tenant_id: Option<Uuid>,
     region_config_id: &str,
     min_snr_margin: f32,
+    strategy: GatewaySelectionStrategy,
+    prefer_tags: &HashMap<String, String>,
     rx_info: &mut internal::DeviceGatewayRxInfo,
 ) -> Result<internal::DeviceGatewayRxInfoItem> {
     rx_info.items.retain(|rx_info| {
@@ -71,15 +81,174 @@ pub fn select_downlink_gateway(
         }
     }
 
-    // Return a random item from the new_items slice (filtered by min_snr_margin).
-    // If new_items is empty, then choose will return None and we return the first item from
-    // rx_info.item.
-    Ok(match new_items.choose(&mut rand::thread_rng()) {
-        Some(v) => v.clone(),
-        None => rx_info.items[0].clone(),
+    // In case none of the items is within the min_snr_margin, we return the first
+    // item from rx_info.items (best SNR).
+    if new_items.is_empty() {
+        return Ok(rx_info.items[0].clone());
+    }
+
+    let res = match strategy {
+        GatewaySelectionStrategy::RANDOM => Ok(new_items
+            .choose(&mut rand::thread_rng())
+            .cloned()
+            .unwrap_or_else(|| rx_info.items[0].clone())),
+        GatewaySelectionStrategy::BEST_SNR => Ok(rx_info.items[0].clone()),
+        GatewaySelectionStrategy::LEAST_LOADED => select_least_loaded(new_items).await,
+        GatewaySelectionStrategy::ROUND_ROBIN => {
+            select_round_robin(&rx_info.dev_eui, new_items).await
+        }
+        GatewaySelectionStrategy::PREFER_TAGS => select_prefer_tags(prefer_tags, new_items).await,
+    };
+
+    // A failing strategy (e.g. a Redis or database error) must not fail the downlink, in which
+    // case we fall back to the item with the best SNR.
+    Ok(match res {
+        Ok(v) => v,
+        Err(e) => {
+            warn!(strategy = %strategy, error = %e.full(), "Gateway selection failed, falling back to best SNR");
+            rx_info.items[0].clone()
+        }
     })
 }
 
+// Returns the item of which the gateway has the lowest duty-cycle usage, based on
+// the most recent duty-cycle stats reported by the gateway. As their load is
+// unknown, gateways without duty-cycle stats are ranked after the gateways with
+// duty-cycle stats. In case of equal load, the item with the best SNR is returned.
+async fn select_least_loaded(
+    items: Vec<internal::DeviceGatewayRxInfoItem>,
+) -> Result<internal::DeviceGatewayRxInfoItem> {
+    let gateway_ids = items
+        .iter()
+        .map(|v| EUI64::from_slice(&v.gateway_id))
+        .collect::<Result<Vec<EUI64>, _>>()?;
+    let loads = get_duty_cycle_loads(&gateway_ids).await?;
+
+    let mut out: Option<(Option<f64>, internal::DeviceGatewayRxInfoItem)> = None;
+
+    for (load, item) in loads.into_iter().zip(items) {
+        let is_better = match (&out, load) {
+            (None, _) => true,
+            (Some((None, _)), Some(_)) => true,
+            (Some((Some(l), _)), Some(load)) => load < *l,
+            (Some(_), None) => false,
+        };
+
+        if is_better {
+            out = Some((load, item));
+        }
+    }
+
+    out.map(|(_, item)| item)
+        .ok_or_else(|| anyhow!("No downlink gateway available"))
+}
+
+// Returns for each gateway the duty-cycle usage (percentage of the max. load) of
+// the most loaded band, from the most recent duty-cycle stats of the last 10
+// minutes. None is returned for gateways without duty-cycle stats.
+async fn get_duty_cycle_loads(gateway_ids: &[EUI64]) -> Result<Vec<Option<f64>>> {
+    let end = Local::now();
+    let start = end - Duration::try_minutes(10).unwrap();
+
+    let records = metrics::get_many(
+        &gateway_ids
+            .iter()
+            .map(|v| format!("gw:dc:{}", v))
+            .collect::<Vec<String>>(),
+        metrics::Kind::COUNTER,
+        metrics::Aggregation::MINUTE,
+        start,
+        end,
+    )
+    .await
+    .context("Get gateway duty-cycle stats")?;
+
+    Ok(records
+        .iter()
+        .map(|records| {
+            records
+                .iter()
+                .rev()
+                .find(|r| !r.metrics.is_empty())
+                .map(|r| {
+                    r.metrics
+                        .iter()
+                        .filter(|(k, _)| k.starts_with("max_load_perc_"))
+                        .map(|(_, v)| *v)
+                        .fold(0.0, f64::max)
+                })
+        })
+        .collect())
+}
+
+// Returns the next item in a round-robin fashion. The items are ordered by
+// gateway ID such that the order does not depend on the SNR of the uplink. The
+// round-robin counter is stored per device.
+async fn select_round_robin(
+    dev_eui: &[u8],
+    mut items: Vec<internal::DeviceGatewayRxInfoItem>,
+) -> Result<internal::DeviceGatewayRxInfoItem> {
+    items.sort_by(|a, b| a.gateway_id.cmp(&b.gateway_id));
+
+    let key = redis_key(format!("device:{{{}}}:gw:rr", hex::encode(dev_eui)));
+    let (counter,): (u64,) = redis::pipe()
+        .atomic()
+        .cmd("INCR")
+        .arg(&key)
+        .cmd("PEXPIRE")
+        .arg(&key)
+        .arg(Duration::try_days(1).unwrap().num_milliseconds())
+        .ignore()
+        .query_async(&mut get_async_redis_conn().await?)
+        .await
+        .context("Increment gateway round-robin counter")?;
+
+    Ok(items[(counter as usize - 1) % items.len()].clone())
+}
+
+// Returns a random item from the items of which the gateway has all the given
+// tags. In case none of the gateways has all the given tags, a random item is
+// returned.
+async fn select_prefer_tags(
+    prefer_tags: &HashMap<String, String>,
+    items: Vec<internal::DeviceGatewayRxInfoItem>,
+) -> Result<internal::DeviceGatewayRxInfoItem> {
+    let mut preferred = Vec::new();
+
+    if !prefer_tags.is_empty() {
+        let gateway_ids = items
+            .iter()
+            .map(|v| EUI64::from_slice(&v.gateway_id))
+            .collect::<Result<Vec<EUI64>, _>>()?;
+        let gw_tags = gateway::get_tags_for_gateway_ids(&gateway_ids).await?;
+
+        for (gateway_id, item) in gateway_ids.iter().zip(&items) {
+            let tags = match gw_tags.get(gateway_id) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            if prefer_tags
+                .iter()
+                .all(|(k, v)| tags.get(k).map(|gw_v| gw_v == v).unwrap_or(false))
+            {
+                preferred.push(item.clone());
+            }
+        }
+    }
+
+    let items = if preferred.is_empty() {
+        items
+    } else {
+        preferred
+    };
+
+    items
+        .choose(&mut rand::thread_rng())
+        .cloned()
+        .ok_or_else(|| anyhow!("No downlink gateway available"))
+}
+
 pub fn set_tx_info_data_rate(
     tx_info: &mut chirpstack_api::gw::DownlinkTxInfo,
     dr: &DataRateModulation,
@@ -118,10 +287,8 @@ pub fn set_tx_info_data_rate(
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
-
     use super::*;
-    use crate::storage::tenant;
+    use crate::storage::{fields, tenant};
     use crate::test;
 
     struct Test {
@@ -321,8 +488,11 @@ mod tests {
                     test.tenant_id,
                     "eu868",
                     test.min_snr_margin,
+                    GatewaySelectionStrategy::RANDOM,
+                    &HashMap::new(),
                     &mut rx_info,
                 )
+                .await
                 .unwrap();
                 gw_map.insert(out.gateway_id, ());
             }
@@ -336,4 +506,194 @@ mod tests {
             );
         }
     }
+
+    #[tokio::test]
+    async fn test_select_downlink_gateway_strategy() {
+        let _guard = test::prepare().await;
+
+        let t = tenant::create(tenant::Tenant {
+            name: "test-tenant".into(),
+            can_have_gateways: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let gw_1 = gateway::create(gateway::Gateway {
+            gateway_id: EUI64::from_be_bytes([1, 1, 1, 1, 1, 1, 1, 1]),
+            tenant_id: t.id,
+            name: "gw-1".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let gw_2 = gateway::create(gateway::Gateway {
+            gateway_id: EUI64::from_be_bytes([2, 2, 2, 2, 2, 2, 2, 2]),
+            tenant_id: t.id,
+            name: "gw-2".into(),
+            tags: fields::KeyValue::new(
+                [("site".to_string(), "rooftop".to_string())]
+                    .iter()
+                    .cloned()
+                    .collect(),
+            ),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // gw_1 has the best SNR, both gateways are within the margin.
+        let rx_info = internal::DeviceGatewayRxInfo {
+            dev_eui: vec![1, 2, 3, 4, 5, 6, 7, 8],
+            dr: 2, // -15 is required
+            items: vec![
+                internal::DeviceGatewayRxInfoItem {
+                    lora_snr: -5.0,
+                    gateway_id: gw_2.gateway_id.to_vec(),
+                    ..Default::default()
+                },
+                internal::DeviceGatewayRxInfoItem {
+                    lora_snr: 0.0,
+                    gateway_id: gw_1.gateway_id.to_vec(),
+                    ..Default::default()
+                },
+            ],
+        };
+
+        let prefer_tags: HashMap<String, String> = [("site".to_string(), "rooftop".to_string())]
+            .iter()
+            .cloned()
+            .collect();
+
+        // best snr
+        for _ in 0..10 {
+            let out = select_downlink_gateway(
+                None,
+                "eu868",
+                5.0,
+                GatewaySelectionStrategy::BEST_SNR,
+                &HashMap::new(),
+                &mut rx_info.clone(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(gw_1.gateway_id.to_vec(), out.gateway_id);
+        }
+
+        // prefer tags
+        for _ in 0..10 {
+            let out = select_downlink_gateway(
+                None,
+                "eu868",
+                5.0,
+                GatewaySelectionStrategy::PREFER_TAGS,
+                &prefer_tags,
+                &mut rx_info.clone(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(gw_2.gateway_id.to_vec(), out.gateway_id);
+        }
+
+        // round robin
+        let mut out = Vec::new();
+        for _ in 0..4 {
+            out.push(
+                select_downlink_gateway(
+                    None,
+                    "eu868",
+                    5.0,
+                    GatewaySelectionStrategy::ROUND_ROBIN,
+                    &HashMap::new(),
+                    &mut rx_info.clone(),
+                )
+                .await
+                .unwrap()
+                .gateway_id,
+            );
+        }
+        assert_ne!(out[0], out[1]);
+        assert_eq!(out[0], out[2]);
+        assert_eq!(out[1], out[3]);
+
+        // least loaded, gw_1 has no duty-cycle stats and is ranked after gw_2
+        metrics::save(
+            &format!("gw:dc:{}", gw_2.gateway_id),
+            &metrics::Record {
+                time: Local::now(),
+                kind: metrics::Kind::COUNTER,
+                metrics: [("max_load_perc_L_868000000_868600000_10".to_string(), 20.0)]
+                    .iter()
+                    .cloned()
+                    .collect(),
+            },
+            &[metrics::Aggregation::MINUTE],
+        )
+        .await
+        .unwrap();
+
+        for _ in 0..10 {
+            let out = select_downlink_gateway(
+                None,
+                "eu868",
+                5.0,
+                GatewaySelectionStrategy::LEAST_LOADED,
+                &HashMap::new(),
+                &mut rx_info.clone(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(gw_2.gateway_id.to_vec(), out.gateway_id);
+        }
+
+        // least loaded, both gateways have duty-cycle stats
+        metrics::save(
+            &format!("gw:dc:{}", gw_1.gateway_id),
+            &metrics::Record {
+                time: Local::now(),
+                kind: metrics::Kind::COUNTER,
+                metrics: [("max_load_perc_L_868000000_868600000_10".to_string(), 80.0)]
+                    .iter()
+                    .cloned()
+                    .collect(),
+            },
+            &[metrics::Aggregation::MINUTE],
+        )
+        .await
+        .unwrap();
+        for _ in 0..10 {
+            let out = select_downlink_gateway(
+                None,
+                "eu868",
+                5.0,
+                GatewaySelectionStrategy::LEAST_LOADED,
+                &HashMap::new(),
+                &mut rx_info.clone(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(gw_2.gateway_id.to_vec(), out.gateway_id);
+        }
+
+        // a failing strategy falls back to the best snr (the invalid gateway ID fails the lookup)
+        let mut rx_info_invalid = rx_info.clone();
+        rx_info_invalid.items[0].gateway_id = vec![2, 2, 2];
+        for strategy in [
+            GatewaySelectionStrategy::LEAST_LOADED,
+            GatewaySelectionStrategy::PREFER_TAGS,
+        ] {
+            let out = select_downlink_gateway(
+                None,
+                "eu868",
+                5.0,
+                strategy,
+                &prefer_tags,
+                &mut rx_info_invalid.clone(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(gw_1.gateway_id.to_vec(), out.gateway_id);
+        }
+    }
 }
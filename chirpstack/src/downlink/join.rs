@@ -11,7 +11,7 @@ use lrwn::{PhyPayload, EUI64};
 use super::helpers;
 use crate::api::helpers::FromProto;
 use crate::gateway::backend::send_downlink;
-use crate::storage::{device, downlink_frame, tenant};
+use crate::storage::{device, device_profile, downlink_frame, tenant};
 use crate::uplink::{RelayContext, UplinkFrameSet};
 use crate::{config, region};
 use chirpstack_api::{gw, internal};
@@ -21,6 +21,7 @@ pub struct JoinAccept<'a> {
     relay_context: Option<&'a RelayContext>,
     tenant: &'a tenant::Tenant,
     device: &'a device::Device,
+    device_profile: &'a device_profile::DeviceProfile,
     join_accept: &'a PhyPayload,
     network_conf: config::RegionNetwork,
     region_conf: Arc<Box<dyn lrwn::region::Region + Sync + Send>>,
@@ -35,12 +36,20 @@ impl JoinAccept<'_> {
         ufs: &UplinkFrameSet,
         tenant: &tenant::Tenant,
         device: &device::Device,
+        device_profile: &device_profile::DeviceProfile,
         join_accept: &PhyPayload,
     ) -> Result<()> {
         let downlink_id: u32 = rand::thread_rng().gen();
         let span = span!(Level::INFO, "join_accept", downlink_id = downlink_id);
 
-        let fut = JoinAccept::_handle(downlink_id, ufs, tenant, device, join_accept);
+        let fut = JoinAccept::_handle(
+            downlink_id,
+            ufs,
+            tenant,
+            device,
+            device_profile,
+            join_accept,
+        );
         fut.instrument(span).await
     }
 
@@ -49,6 +58,7 @@ impl JoinAccept<'_> {
         ufs: &UplinkFrameSet,
         tenant: &tenant::Tenant,
         device: &device::Device,
+        device_profile: &device_profile::DeviceProfile,
         join_accept: &PhyPayload,
     ) -> Result<()> {
         let downlink_id: u32 = rand::thread_rng().gen();
@@ -58,8 +68,15 @@ impl JoinAccept<'_> {
             downlink_id = downlink_id
         );
 
-        let fut =
-            JoinAccept::_handle_relayed(downlink_id, relay_ctx, ufs, tenant, device, join_accept);
+        let fut = JoinAccept::_handle_relayed(
+            downlink_id,
+            relay_ctx,
+            ufs,
+            tenant,
+            device,
+            device_profile,
+            join_accept,
+        );
         fut.instrument(span).await
     }
 
@@ -68,6 +85,7 @@ impl JoinAccept<'_> {
         ufs: &UplinkFrameSet,
         tenant: &tenant::Tenant,
         device: &device::Device,
+        device_profile: &device_profile::DeviceProfile,
         join_accept: &PhyPayload,
     ) -> Result<()> {
         let mut ctx = JoinAccept {
@@ -75,6 +93,7 @@ impl JoinAccept<'_> {
             relay_context: None,
             tenant,
             device,
+            device_profile,
             join_accept,
             network_conf: config::get_region_network(&ufs.region_config_id)?,
             region_conf: region::get(&ufs.region_config_id)?,
@@ -88,7 +107,7 @@ impl JoinAccept<'_> {
         };
 
         ctx.set_device_gateway_rx_info()?;
-        ctx.select_downlink_gateway()?;
+        ctx.select_downlink_gateway().await?;
         ctx.set_tx_info()?;
         ctx.set_downlink_frame()?;
         ctx.save_downlink_frame().await?;
@@ -103,6 +122,7 @@ impl JoinAccept<'_> {
         ufs: &UplinkFrameSet,
         tenant: &tenant::Tenant,
         device: &device::Device,
+        device_profile: &device_profile::DeviceProfile,
         join_accept: &PhyPayload,
     ) -> Result<()> {
         let mut ctx = JoinAccept {
@@ -110,6 +130,7 @@ impl JoinAccept<'_> {
             relay_context: Some(relay_ctx),
             tenant,
             device,
+            device_profile,
             join_accept,
             network_conf: config::get_region_network(&ufs.region_config_id)?,
             region_conf: region::get(&ufs.region_config_id)?,
@@ -123,7 +144,7 @@ impl JoinAccept<'_> {
         };
 
         ctx.set_device_gateway_rx_info()?;
-        ctx.select_downlink_gateway()?;
+        ctx.select_downlink_gateway().await?;
         ctx.set_tx_info_relayed()?;
         ctx.set_downlink_frame_relayed()?;
         ctx.send_join_accept_response().await?;
@@ -178,15 +199,20 @@ impl JoinAccept<'_> {
         Ok(())
     }
 
-    fn select_downlink_gateway(&mut self) -> Result<()> {
+    async fn select_downlink_gateway(&mut self) -> Result<()> {
         trace!("Select downlink gateway");
 
         let gw_down = helpers::select_downlink_gateway(
             Some(self.tenant.id),
             &self.uplink_frame_set.region_config_id,
             self.network_conf.gateway_prefer_min_margin,
+            self.device_profile
+                .gateway_selection_strategy
+                .unwrap_or(self.network_conf.gateway_selection_strategy),
+            &self.network_conf.gateway_prefer_tags,
             self.device_gateway_rx_info.as_mut().unwrap(),
-        )?;
+        )
+        .await?;
 
         self.downlink_frame.gateway_id = hex::encode(&gw_down.gateway_id);
         self.downlink_gateway = Some(gw_down);
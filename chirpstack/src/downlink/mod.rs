@@ -4,6 +4,7 @@ pub mod classb;
 pub mod data;
 pub mod data_fns;
 pub mod error;
+pub mod forecast;
 mod helpers;
 pub mod join;
 pub mod multicast;
@@ -17,8 +18,28 @@ pub async fn setup() {
         scheduler::class_b_c_scheduler_loop().await;
     });
 
+    info!("Setting up device-queue expiry loop");
+    tokio::spawn(async move {
+        scheduler::device_queue_expiry_loop().await;
+    });
+
+    info!("Setting up fragmentation-session timeout loop");
+    tokio::spawn(async move {
+        scheduler::fragmentation_session_timeout_loop().await;
+    });
+
     info!("Setting up multicast scheduler loop");
     tokio::spawn(async move {
         scheduler::multicast_group_queue_scheduler_loop().await;
     });
+
+    info!("Setting up multicast-group delivery cleanup loop");
+    tokio::spawn(async move {
+        scheduler::multicast_group_delivery_cleanup_loop().await;
+    });
+
+    info!("Setting up FUOTA campaign scheduler loop");
+    tokio::spawn(async move {
+        scheduler::fuota_campaign_scheduler_loop().await;
+    });
 }
@@ -1,6 +1,7 @@
 use std::collections::{HashMap, HashSet};
 
 use anyhow::{Context, Result};
+use chrono::Utc;
 use petgraph::algo::min_spanning_tree;
 use petgraph::data::FromElements;
 use petgraph::graph::{DefaultIx, Graph, NodeIndex, UnGraph};
@@ -10,8 +11,8 @@ use tracing::{span, trace, warn, Instrument, Level};
 use crate::downlink::helpers;
 use crate::gateway::backend as gateway_backend;
 use crate::storage::{device_gateway, downlink_frame, gateway, multicast};
-use crate::{config, region};
-use chirpstack_api::{gw, internal};
+use crate::{config, integration, region};
+use chirpstack_api::{gw, integration as integration_pb, internal};
 use lrwn::EUI64;
 
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
@@ -20,6 +21,12 @@ enum Node {
     Device(EUI64),
 }
 
+pub struct DeliveryCoverage {
+    pub device_count: usize,
+    pub covered_device_count: usize,
+    pub gateway_device_count: HashMap<EUI64, usize>,
+}
+
 pub struct Multicast {
     multicast_group_queue_item: multicast::MulticastGroupQueueItem,
     downlink_frame: gw::DownlinkFrame,
@@ -108,7 +115,11 @@ impl Multicast {
                 pl_size = self.multicast_group_queue_item.data.len(),
                 "Discarding multicast-group queue item because it exceeds max. payload size"
             );
-            multicast::delete_queue_item(&self.multicast_group_queue_item.id).await?;
+            if let Some(deliveries) =
+                multicast::delete_queue_item(&self.multicast_group_queue_item.id).await?
+            {
+                send_delivery_event(&deliveries).await?;
+            }
             return Err(anyhow!(
                 "Queue item exceeds max payload and has been discarded"
             ));
@@ -253,6 +264,88 @@ pub async fn enqueue(qi: multicast::MulticastGroupQueueItem) -> Result<u32> {
     Ok(f_cnt)
 }
 
+/// Sends the multicast-group delivery event for the given (resolved) delivery records of a
+/// multicast-group queue item.
+pub async fn send_delivery_event(
+    deliveries: &[multicast::MulticastGroupQueueItemDelivery],
+) -> Result<()> {
+    let d = match deliveries.first() {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+
+    trace!("Sending multicast-group delivery event");
+
+    let mg = multicast::get(&d.multicast_group_id).await?;
+    let coverage = get_delivery_coverage(deliveries).await?;
+
+    let pl = integration_pb::MulticastGroupDeliveryEvent {
+        time: Some(Utc::now().into()),
+        application_id: mg.application_id.to_string(),
+        multicast_group_id: mg.id.to_string(),
+        multicast_group_name: mg.name.clone(),
+        f_cnt: d.f_cnt as u32,
+        gateways: deliveries
+            .iter()
+            .map(|d| integration_pb::MulticastGroupGatewayDelivery {
+                gateway_id: d.gateway_id.to_string(),
+                status: d
+                    .tx_ack_status
+                    .as_deref()
+                    .and_then(gw::TxAckStatus::from_str_name)
+                    .unwrap_or_default()
+                    .into(),
+                device_count: coverage
+                    .gateway_device_count
+                    .get(&d.gateway_id)
+                    .cloned()
+                    .unwrap_or_default() as u32,
+                discarded: d.tx_ack_status.as_deref() == Some(multicast::DELIVERY_STATUS_DISCARDED),
+            })
+            .collect(),
+        device_count: coverage.device_count as u32,
+        covered_device_count: coverage.covered_device_count as u32,
+    };
+
+    integration::multicast_group_delivery_event(mg.application_id, &HashMap::new(), &pl).await;
+
+    Ok(())
+}
+
+// Returns the estimated device coverage of the given multicast-group queue item
+// delivery records. This is based on the gateways that received the last uplink
+// of each device. Only gateways that acknowledged the transmission are taken
+// into account for the covered device count.
+pub async fn get_delivery_coverage(
+    deliveries: &[multicast::MulticastGroupQueueItemDelivery],
+) -> Result<DeliveryCoverage> {
+    let multicast_group_id = match deliveries.first() {
+        Some(v) => v.multicast_group_id,
+        None => {
+            return Ok(DeliveryCoverage {
+                device_count: 0,
+                covered_device_count: 0,
+                gateway_device_count: HashMap::new(),
+            });
+        }
+    };
+
+    let dev_euis = multicast::get_dev_euis(&multicast_group_id).await?;
+    let dev_gw_set = device_gateway::get_rx_info_for_dev_euis(&dev_euis).await?;
+
+    let acked_gateway_ids: HashSet<EUI64> = deliveries
+        .iter()
+        .filter(|d| d.tx_ack_status.as_deref() == Some(gw::TxAckStatus::Ok.as_str_name()))
+        .map(|d| d.gateway_id)
+        .collect();
+
+    Ok(DeliveryCoverage {
+        device_count: dev_euis.len(),
+        covered_device_count: get_covered_device_count(&dev_gw_set, &acked_gateway_ids)?,
+        gateway_device_count: get_gateway_device_count_map(&dev_gw_set)?,
+    })
+}
+
 fn get_minimum_gateway_set(dev_gw_set: &[internal::DeviceGatewayRxInfo]) -> Result<Vec<EUI64>> {
     if dev_gw_set.is_empty() {
         return Ok(vec![]);
@@ -358,10 +451,89 @@ fn get_gateway_device_count_map(
     Ok(out)
 }
 
+fn get_covered_device_count(
+    dev_gw_set: &[internal::DeviceGatewayRxInfo],
+    gateway_ids: &HashSet<EUI64>,
+) -> Result<usize> {
+    let mut count = 0;
+
+    for dev_gw in dev_gw_set {
+        for gw_rx_info in &dev_gw.items {
+            let gateway_id = EUI64::from_slice(&gw_rx_info.gateway_id)?;
+            if gateway_ids.contains(&gateway_id) {
+                count += 1;
+                break;
+            }
+        }
+    }
+
+    Ok(count)
+}
+
 #[cfg(test)]
 pub mod test {
     use super::*;
 
+    #[test]
+    fn test_covered_device_count() {
+        let dev_gw_set = vec![
+            internal::DeviceGatewayRxInfo {
+                dev_eui: vec![1, 1, 1, 1, 1, 1, 1, 1],
+                items: vec![
+                    internal::DeviceGatewayRxInfoItem {
+                        gateway_id: vec![2, 2, 2, 2, 2, 2, 2, 1],
+                        ..Default::default()
+                    },
+                    internal::DeviceGatewayRxInfoItem {
+                        gateway_id: vec![2, 2, 2, 2, 2, 2, 2, 2],
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            },
+            internal::DeviceGatewayRxInfo {
+                dev_eui: vec![1, 1, 1, 1, 1, 1, 1, 2],
+                items: vec![internal::DeviceGatewayRxInfoItem {
+                    gateway_id: vec![2, 2, 2, 2, 2, 2, 2, 2],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            internal::DeviceGatewayRxInfo {
+                dev_eui: vec![1, 1, 1, 1, 1, 1, 1, 3],
+                items: vec![internal::DeviceGatewayRxInfoItem {
+                    gateway_id: vec![2, 2, 2, 2, 2, 2, 2, 3],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        ];
+
+        let gateway_ids: HashSet<EUI64> = [
+            EUI64::from_be_bytes([2, 2, 2, 2, 2, 2, 2, 1]),
+            EUI64::from_be_bytes([2, 2, 2, 2, 2, 2, 2, 2]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            2,
+            get_covered_device_count(&dev_gw_set, &gateway_ids).unwrap()
+        );
+
+        let gateway_ids: HashSet<EUI64> = [EUI64::from_be_bytes([2, 2, 2, 2, 2, 2, 2, 3])]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            1,
+            get_covered_device_count(&dev_gw_set, &gateway_ids).unwrap()
+        );
+
+        assert_eq!(
+            0,
+            get_covered_device_count(&dev_gw_set, &HashSet::new()).unwrap()
+        );
+    }
+
     #[test]
     fn test_minimum_gateway_set() {
         struct Test {
@@ -49,7 +49,7 @@ impl PassiveRoamingDownlink {
             downlink_gateway: None,
         };
 
-        ctx.select_downlink_gateway()?;
+        ctx.select_downlink_gateway().await?;
         ctx.set_downlink_frame()?;
         ctx.save_downlink_frame().await?;
         ctx.send_downlink_frame().await?;
@@ -57,7 +57,7 @@ impl PassiveRoamingDownlink {
         Ok(())
     }
 
-    fn select_downlink_gateway(&mut self) -> Result<()> {
+    async fn select_downlink_gateway(&mut self) -> Result<()> {
         trace!("Selecting downlink gateway");
 
         let mut dev_gw_rx_info = internal::DeviceGatewayRxInfo {
@@ -104,8 +104,11 @@ impl PassiveRoamingDownlink {
             None,
             &self.uplink_frame_set.region_config_id,
             self.network_conf.gateway_prefer_min_margin,
+            self.network_conf.gateway_selection_strategy,
+            &self.network_conf.gateway_prefer_tags,
             &mut dev_gw_rx_info,
-        )?;
+        )
+        .await?;
 
         self.downlink_frame.gateway_id = hex::encode(&gw_down.gateway_id);
         self.downlink_gateway = Some(gw_down);
@@ -1,12 +1,14 @@
 use anyhow::Result;
+use chrono::Utc;
 use tokio::time::sleep;
 use tracing::{error, trace};
 
 use super::data;
 use super::multicast as mcast;
+use crate::applayer;
 use crate::config;
 use crate::helpers::errors::PrintFullError;
-use crate::storage::{device, multicast};
+use crate::storage::{device, device_queue, fragmentation_session, fuota, multicast};
 
 pub async fn class_b_c_scheduler_loop() {
     let conf = config::get();
@@ -42,6 +44,73 @@ pub async fn multicast_group_queue_scheduler_loop() {
     }
 }
 
+pub async fn fuota_campaign_scheduler_loop() {
+    let conf = config::get();
+
+    loop {
+        trace!("Starting FUOTA campaign scheduler loop run");
+
+        if let Err(err) = schedule_fuota_campaign_batch(conf.network.scheduler.batch_size).await {
+            error!(error = %err, "Scheduling FUOTA campaign batch failed");
+        } else {
+            trace!("FUOTA campaign scheduler run completed successfully");
+        }
+
+        sleep(conf.network.scheduler.interval).await;
+    }
+}
+
+pub async fn device_queue_expiry_loop() {
+    let conf = config::get();
+
+    loop {
+        trace!("Starting device-queue expiry loop run");
+
+        if let Err(err) = delete_expired_device_queue_batch(conf.network.scheduler.batch_size).await
+        {
+            error!(error = %err, "Deleting expired device-queue batch failed");
+        } else {
+            trace!("Device-queue expiry loop run completed successfully");
+        }
+
+        sleep(conf.network.scheduler.interval).await;
+    }
+}
+
+pub async fn fragmentation_session_timeout_loop() {
+    let conf = config::get();
+
+    loop {
+        trace!("Starting fragmentation-session timeout loop run");
+
+        if let Err(err) = handle_timed_out_fragmentation_sessions().await {
+            error!(error = %err, "Handling timed out fragmentation-sessions failed");
+        } else {
+            trace!("Fragmentation-session timeout loop run completed successfully");
+        }
+
+        sleep(conf.network.scheduler.interval).await;
+    }
+}
+
+pub async fn multicast_group_delivery_cleanup_loop() {
+    let conf = config::get();
+
+    loop {
+        trace!("Starting multicast-group delivery cleanup loop run");
+
+        if let Err(err) =
+            cleanup_multicast_group_deliveries(conf.network.scheduler.batch_size).await
+        {
+            error!(error = %err, "Multicast-group delivery cleanup failed");
+        } else {
+            trace!("Multicast-group delivery cleanup loop run completed successfully");
+        }
+
+        sleep(conf.network.scheduler.interval).await;
+    }
+}
+
 pub async fn schedule_device_queue_batch(size: usize) -> Result<()> {
     trace!("Getting devices that have schedulable queue-items");
     let devices = device::get_with_class_b_c_queue_items(size).await?;
@@ -89,3 +158,88 @@ pub async fn schedule_multicast_group_queue_batch(size: usize) -> Result<()> {
     futures::future::join_all(handles).await;
     Ok(())
 }
+
+pub async fn schedule_fuota_campaign_batch(size: usize) -> Result<()> {
+    trace!("Getting schedulable FUOTA campaigns");
+    let campaigns = fuota::get_schedulable_campaigns(size).await?;
+    trace!(
+        count = campaigns.len(),
+        "Got this number of schedulable FUOTA campaigns"
+    );
+
+    let mut handles = vec![];
+
+    for fc in campaigns {
+        let handle = tokio::spawn(async move {
+            if let Err(e) = applayer::fuota::handle_schedule(fc).await {
+                error!(error = %e.full(), "Handle FUOTA campaign step failed");
+            }
+        });
+        handles.push(handle);
+    }
+
+    futures::future::join_all(handles).await;
+    Ok(())
+}
+
+pub async fn delete_expired_device_queue_batch(size: usize) -> Result<()> {
+    trace!("Deleting expired device-queue items");
+    let items = device_queue::delete_expired(size).await?;
+    trace!(
+        count = items.len(),
+        "Deleted this number of expired device-queue items"
+    );
+
+    for qi in items {
+        if let Err(e) = data::log_expired_queue_item(&qi).await {
+            error!(error = %e.full(), "Log expired device queue-item failed");
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn handle_timed_out_fragmentation_sessions() -> Result<()> {
+    let conf = config::get();
+    let timeout = chrono::Duration::from_std(conf.network.fragmentation_session_timeout)?;
+
+    trace!("Marking timed out fragmentation-sessions as failed");
+    let sessions = fragmentation_session::set_timed_out_as_failed(timeout).await?;
+    trace!(
+        count = sessions.len(),
+        "Marked this number of fragmentation-sessions as failed"
+    );
+
+    for fs in sessions {
+        if let Err(e) = applayer::fragmentation::handle_timeout(&fs).await {
+            error!(error = %e.full(), "Handle fragmentation-session timeout failed");
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn cleanup_multicast_group_deliveries(size: usize) -> Result<()> {
+    let conf = config::get();
+    let now = Utc::now();
+
+    trace!("Discarding timed out multicast-group queue item deliveries");
+    let items = multicast::discard_timed_out_queue_item_deliveries(
+        now - chrono::Duration::from_std(conf.network.scheduler.multicast_delivery_timeout)?,
+        size,
+    )
+    .await?;
+
+    for deliveries in items {
+        if let Err(e) = mcast::send_delivery_event(&deliveries).await {
+            error!(error = %e.full(), "Send multicast-group delivery event failed");
+        }
+    }
+
+    multicast::delete_queue_item_deliveries_before(
+        now - chrono::Duration::from_std(conf.network.scheduler.multicast_delivery_retention)?,
+    )
+    .await?;
+
+    Ok(())
+}
@@ -1,11 +1,12 @@
 use anyhow::Result;
 use chrono::{Duration, Utc};
-use tracing::{error, info, span, trace, Instrument, Level};
+use tracing::{error, info, span, trace, warn, Instrument, Level};
 use uuid::Uuid;
 
 use lrwn::{AES128Key, MType, Payload, PhyPayload, EUI64};
 
 use crate::api::helpers::ToProto;
+use crate::helpers::errors::PrintFullError;
 use crate::storage::{
     application,
     device::{self, DeviceClass},
@@ -13,7 +14,7 @@ use crate::storage::{
     helpers::get_all_device_data,
     multicast, tenant,
 };
-use crate::{integration, stream};
+use crate::{applayer, downlink, integration, stream};
 use chirpstack_api::{common, gw, integration as integration_pb, internal, stream as stream_pb};
 
 pub struct TxAck {
@@ -91,6 +92,7 @@ impl TxAck {
             }
 
             if ctx.is_multicast_downlink() {
+                ctx.handle_multicast_group_queue_item_delivery().await?;
                 ctx.delete_multicast_group_queue_item().await?;
             }
         } else {
@@ -110,6 +112,7 @@ impl TxAck {
 
                     ctx.increment_a_f_cnt_down()?;
                     ctx.send_tx_ack_event().await?;
+                    ctx.handle_fragmentation_tx_ack().await?;
                 }
 
                 if ctx.is_mac_only_downlink() {
@@ -120,6 +123,7 @@ impl TxAck {
             }
 
             if ctx.is_multicast_downlink() {
+                ctx.handle_multicast_group_queue_item_delivery().await?;
                 ctx.delete_multicast_group_queue_item().await?;
             }
 
@@ -165,6 +169,7 @@ impl TxAck {
                 // Log tx ack event.
                 self.get_device_data_relayed().await?;
                 self.send_tx_ack_event_relayed().await?;
+                self.handle_fragmentation_tx_ack().await?;
             } else if self.is_mac_only_downlink_relayed() {
                 self.increment_n_f_cnt_down_relayed()?;
                 self.save_device_session_relayed().await?;
@@ -272,6 +277,38 @@ impl TxAck {
         Ok(())
     }
 
+    async fn handle_multicast_group_queue_item_delivery(&self) -> Result<()> {
+        trace!("Handling multicast-group queue item delivery");
+        let qi_id = Uuid::from_slice(
+            &self
+                .downlink_frame
+                .as_ref()
+                .unwrap()
+                .multicast_group_queue_item_id,
+        )?;
+
+        if let Err(e) = self.send_multicast_group_delivery_event(&qi_id).await {
+            warn!(multicast_group_queue_item_id = %qi_id, error = %e.full(), "Handle multicast-group queue item delivery error");
+        }
+
+        Ok(())
+    }
+
+    async fn send_multicast_group_delivery_event(&self, qi_id: &Uuid) -> Result<()> {
+        let deliveries = match multicast::set_queue_item_delivery_tx_ack_status(
+            qi_id,
+            self.downlink_tx_ack_status.as_str_name(),
+        )
+        .await?
+        {
+            Some(v) => v,
+            // Not all gateways have reported their tx ack yet.
+            None => return Ok(()),
+        };
+
+        downlink::multicast::send_delivery_event(&deliveries).await
+    }
+
     async fn set_device_queue_item_pending(&mut self) -> Result<()> {
         trace!("Setting device queue-item pending");
 
@@ -488,6 +525,15 @@ impl TxAck {
         Ok(())
     }
 
+    async fn handle_fragmentation_tx_ack(&self) -> Result<()> {
+        let qi = self.device_queue_item.as_ref().unwrap();
+        if let Err(e) = applayer::fragmentation::handle_tx_ack(qi).await {
+            warn!(dev_eui = %qi.dev_eui, error = %e.full(), "Handle fragmentation tx ack error");
+        }
+
+        Ok(())
+    }
+
     async fn send_tx_ack_event_relayed(&self) -> Result<()> {
         trace!("Sending relayed tx ack event");
 
@@ -0,0 +1,831 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path,
+    },
+    http::{header, HeaderMap, StatusCode},
+    middleware::AddExtension,
+    response::{IntoResponse, Response},
+    routing::get,
+    Extension, Router,
+};
+use axum_server::{
+    accept::Accept,
+    tls_rustls::{RustlsAcceptor, RustlsConfig},
+};
+use chrono::Utc;
+use futures::future::BoxFuture;
+use futures::{SinkExt, StreamExt};
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use rustls::{
+    server::{NoClientAuth, WebPkiClientVerifier},
+    ServerConfig,
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{mpsc, RwLock};
+use tokio_rustls::server::TlsStream;
+use tower::Layer;
+use tracing::{debug, error, info, trace, warn};
+
+use super::GatewayBackend;
+use crate::config::{self, GatewayBackendBasicsStation};
+use crate::gpstime::ToGpsTime;
+use crate::helpers::errors::PrintFullError;
+use crate::helpers::tls::{get_root_certs, load_cert, load_key};
+use crate::monitoring::prometheus;
+use crate::{downlink, region, uplink};
+use chirpstack_api::gw;
+use lrwn::region::CommonName;
+use lrwn::EUI64;
+use structs::{
+    get_gateway_configuration, get_uplink_frame, DownlinkMessage, DownlinkTransmitted, JoinRequest,
+    MessageType, ProprietaryDataFrame, RouterConfig, RouterInfoRequest, RouterInfoResponse,
+    TimeSync, UplinkDataFrame, Version,
+};
+
+mod structs;
+
+// Pending downlinks for which no dntxed message has been received within this duration are
+// removed.
+const PENDING_DOWNLINK_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Hash, PartialEq, Eq, EncodeLabelSet, Debug)]
+struct MessageLabels {
+    msgtype: String,
+}
+
+lazy_static! {
+    static ref EVENT_COUNTER: Family<MessageLabels, Counter> = {
+        let counter = Family::<MessageLabels, Counter>::default();
+        prometheus::register(
+            "gateway_backend_basics_station_events",
+            "Number of messages received",
+            counter.clone(),
+        );
+        counter
+    };
+    static ref COMMAND_COUNTER: Family<MessageLabels, Counter> = {
+        let counter = Family::<MessageLabels, Counter>::default();
+        prometheus::register(
+            "gateway_backend_basics_station_commands",
+            "Number of messages sent",
+            counter.clone(),
+        );
+        counter
+    };
+}
+
+pub struct BasicsStationBackend {
+    state: Arc<State>,
+    #[cfg(test)]
+    local_addr: std::net::SocketAddr,
+}
+
+struct State {
+    region_config_id: String,
+    backend_id: usize,
+    region_common_name: CommonName,
+    tls: bool,
+    gateways: RwLock<HashMap<EUI64, GatewayConnection>>,
+    downlinks: RwLock<HashMap<u32, PendingDownlink>>,
+}
+
+// GatewayConnection contains the sender of the WebSocket connection of the gateway. The id is
+// used to detect if the connection has been replaced by a newer connection of the same gateway.
+#[derive(Clone)]
+struct GatewayConnection {
+    id: u64,
+    sender: mpsc::UnboundedSender<String>,
+}
+
+// PendingDownlink contains a downlink frame for which a dntxed message is expected.
+struct PendingDownlink {
+    frame: gw::DownlinkFrame,
+    created_at: Instant,
+}
+
+impl PendingDownlink {
+    fn tx_ack(&self) -> gw::DownlinkTxAck {
+        gw::DownlinkTxAck {
+            gateway_id: self.frame.gateway_id.clone(),
+            downlink_id: self.frame.downlink_id,
+            items: self
+                .frame
+                .items
+                .iter()
+                .enumerate()
+                .map(|(i, _)| gw::DownlinkTxAckItem {
+                    status: if i == 0 {
+                        gw::TxAckStatus::Ok
+                    } else {
+                        gw::TxAckStatus::Ignored
+                    }
+                    .into(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+// ClientCommonName contains the CN of the client certificate presented by the gateway. When the
+// gateway client certificates are issued by ChirpStack, this is the Gateway ID.
+#[derive(Clone)]
+struct ClientCommonName(Option<String>);
+
+#[derive(Clone)]
+struct ClientCertAcceptor {
+    inner: RustlsAcceptor,
+}
+
+impl<I, S> Accept<I, S> for ClientCertAcceptor
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = TlsStream<I>;
+    type Service = AddExtension<S, ClientCommonName>;
+    type Future = BoxFuture<'static, io::Result<(Self::Stream, Self::Service)>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let acceptor = self.inner.clone();
+
+        Box::pin(async move {
+            let (stream, service) = acceptor.accept(stream, service).await?;
+            let cn = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .and_then(|cert| x509_parser::parse_x509_certificate(cert).ok())
+                .and_then(|(_, cert)| {
+                    cert.subject()
+                        .iter_common_name()
+                        .next()
+                        .and_then(|cn| cn.as_str().ok())
+                        .map(|cn| cn.to_string())
+                });
+
+            Ok((stream, Extension(ClientCommonName(cn)).layer(service)))
+        })
+    }
+}
+
+impl BasicsStationBackend {
+    pub async fn new(
+        region_config_id: &str,
+        backend_id: usize,
+        region_common_name: CommonName,
+        conf: &GatewayBackendBasicsStation,
+    ) -> Result<BasicsStationBackend> {
+        let tls = !conf.tls_cert.is_empty() || !conf.tls_key.is_empty();
+
+        let listener = std::net::TcpListener::bind(&conf.bind).context("Bind TCP listener")?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+
+        info!(region_id = %region_config_id, bind = %local_addr, tls = tls, "Basics Station gateway backend listening");
+
+        let state = Arc::new(State {
+            region_config_id: region_config_id.to_string(),
+            backend_id,
+            region_common_name,
+            tls,
+            gateways: RwLock::new(HashMap::new()),
+            downlinks: RwLock::new(HashMap::new()),
+        });
+
+        let app = Router::new()
+            .route("/router-info", get(handle_router_info))
+            .route("/gateway/:gateway_id", get(handle_gateway))
+            .with_state(state.clone());
+
+        if tls {
+            // The gateway client certificates are signed by the gateway CA certificate.
+            let ca_cert = config::get().gateway.ca_cert.clone();
+            let server_config = ServerConfig::builder()
+                .with_client_cert_verifier(if ca_cert.is_empty() {
+                    Arc::new(NoClientAuth)
+                } else {
+                    let root_certs = get_root_certs(Some(ca_cert))?;
+                    WebPkiClientVerifier::builder(root_certs.into()).build()?
+                })
+                .with_single_cert(
+                    load_cert(&conf.tls_cert).await?,
+                    load_key(&conf.tls_key).await?,
+                )?;
+
+            let acceptor = ClientCertAcceptor {
+                inner: RustlsAcceptor::new(RustlsConfig::from_config(Arc::new(server_config))),
+            };
+
+            tokio::spawn({
+                let region_config_id = region_config_id.to_string();
+
+                async move {
+                    if let Err(e) = axum_server::from_tcp(listener)
+                        .acceptor(acceptor)
+                        .serve(app.into_make_service())
+                        .await
+                    {
+                        error!(region_id = %region_config_id, error = %e, "Basics Station server error");
+                    }
+                }
+            });
+        } else {
+            tokio::spawn({
+                let region_config_id = region_config_id.to_string();
+
+                async move {
+                    if let Err(e) = axum_server::from_tcp(listener)
+                        .serve(app.into_make_service())
+                        .await
+                    {
+                        error!(region_id = %region_config_id, error = %e, "Basics Station server error");
+                    }
+                }
+            });
+        }
+
+        Ok(BasicsStationBackend {
+            state,
+            #[cfg(test)]
+            local_addr,
+        })
+    }
+}
+
+#[async_trait]
+impl GatewayBackend for BasicsStationBackend {
+    async fn send_downlink(&self, df: &gw::DownlinkFrame) -> Result<()> {
+        let gateway_id: EUI64 = df.gateway_id.parse().context("Gateway ID")?;
+        let region_conf = region::get(&self.state.region_config_id)?;
+        let dm = DownlinkMessage::from_downlink_frame(region_conf.as_ref().as_ref(), df)?;
+
+        info!(region_id = %self.state.region_config_id, gateway_id = %gateway_id, downlink_id = df.downlink_id, "Sending downlink frame");
+
+        {
+            let mut downlinks = self.state.downlinks.write().await;
+            downlinks.retain(|_, pending| pending.created_at.elapsed() <= PENDING_DOWNLINK_TTL);
+            downlinks.insert(
+                df.downlink_id,
+                PendingDownlink {
+                    frame: df.clone(),
+                    created_at: Instant::now(),
+                },
+            );
+        }
+
+        if let Err(e) = send_message(&self.state, gateway_id, "dnmsg", &dm).await {
+            self.state.downlinks.write().await.remove(&df.downlink_id);
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    async fn send_configuration(&self, gw_conf: &gw::GatewayConfiguration) -> Result<()> {
+        send_configuration(&self.state, gw_conf).await
+    }
+
+    async fn send_command_exec(&self, _pl: &gw::GatewayCommandExecRequest) -> Result<()> {
+        Err(anyhow!(
+            "Command execution is not supported by the Basics Station backend"
+        ))
+    }
+
+    async fn send_raw_packet_forwarder_command(
+        &self,
+        _pl: &gw::RawPacketForwarderCommand,
+    ) -> Result<()> {
+        Err(anyhow!(
+            "Raw packet-forwarder commands are not supported by the Basics Station backend"
+        ))
+    }
+}
+
+async fn handle_router_info(
+    axum::extract::State(state): axum::extract::State<Arc<State>>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+
+    ws.on_upgrade(move |mut socket| async move {
+        let resp = match socket.recv().await {
+            Some(Ok(Message::Text(msg))) => {
+                match serde_json::from_str::<RouterInfoRequest>(&msg)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|req| req.router.to_eui64())
+                {
+                    Ok(gateway_id) => {
+                        info!(region_id = %state.region_config_id, gateway_id = %gateway_id, "Router-info request received");
+
+                        RouterInfoResponse {
+                            router: gateway_id.to_string(),
+                            muxs: "muxs-::0".into(),
+                            uri: format!(
+                                "{}://{}/gateway/{}",
+                                if state.tls { "wss" } else { "ws" },
+                                host,
+                                gateway_id
+                            ),
+                            error: "".into(),
+                        }
+                    }
+                    Err(e) => {
+                        warn!(region_id = %state.region_config_id, error = %e.full(), "Parse router-info request error");
+
+                        RouterInfoResponse {
+                            router: "".into(),
+                            muxs: "muxs-::0".into(),
+                            uri: "".into(),
+                            error: e.to_string(),
+                        }
+                    }
+                }
+            }
+            _ => return,
+        };
+
+        match serde_json::to_string(&resp) {
+            Ok(v) => {
+                let _ = socket.send(Message::Text(v)).await;
+            }
+            Err(e) => {
+                error!(error = %e, "Encode router-info response error");
+            }
+        }
+    })
+}
+
+async fn handle_gateway(
+    axum::extract::State(state): axum::extract::State<Arc<State>>,
+    Path(gateway_id): Path<String>,
+    client_cn: Option<Extension<ClientCommonName>>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let gateway_id: EUI64 = match gateway_id.parse() {
+        Ok(v) => v,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    if let Some(Extension(ClientCommonName(Some(cn)))) = client_cn {
+        if cn != gateway_id.to_string() {
+            warn!(region_id = %state.region_config_id, gateway_id = %gateway_id, common_name = %cn, "Client certificate CN does not match the Gateway ID");
+            return (
+                StatusCode::FORBIDDEN,
+                "Client certificate CN does not match the Gateway ID",
+            )
+                .into_response();
+        }
+    }
+
+    ws.on_upgrade(move |socket| handle_gateway_socket(state, gateway_id, socket))
+}
+
+async fn handle_gateway_socket(state: Arc<State>, gateway_id: EUI64, socket: WebSocket) {
+    let (mut ws_sender, mut ws_receiver) = socket.split();
+    let (sender, mut receiver) = mpsc::unbounded_channel::<String>();
+    let conn_id: u64 = rand::random();
+
+    state.gateways.write().await.insert(
+        gateway_id,
+        GatewayConnection {
+            id: conn_id,
+            sender: sender.clone(),
+        },
+    );
+
+    info!(region_id = %state.region_config_id, gateway_id = %gateway_id, "Gateway connected");
+    tokio::spawn(uplink::conn_state::ConnState::handle(gw::ConnState {
+        gateway_id: gateway_id.to_string(),
+        state: gw::conn_state::State::Online.into(),
+        ..Default::default()
+    }));
+
+    // Send loop
+    let send_task = tokio::spawn(async move {
+        while let Some(msg) = receiver.recv().await {
+            if ws_sender.send(Message::Text(msg)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    // Receive loop
+    while let Some(msg) = ws_receiver.next().await {
+        match msg {
+            Ok(Message::Text(msg)) => {
+                if let Err(e) = handle_message(&state, gateway_id, &sender, &msg).await {
+                    error!(region_id = %state.region_config_id, gateway_id = %gateway_id, error = %e.full(), "Handle message error");
+                }
+            }
+            Ok(Message::Close(_)) => break,
+            Ok(_) => {}
+            Err(e) => {
+                warn!(region_id = %state.region_config_id, gateway_id = %gateway_id, error = %e, "Receive message error");
+                break;
+            }
+        }
+    }
+
+    send_task.abort();
+
+    // The connection is only removed in case it has not been replaced by a newer connection.
+    let removed = {
+        let mut gateways = state.gateways.write().await;
+        if gateways.get(&gateway_id).map(|c| c.id) == Some(conn_id) {
+            gateways.remove(&gateway_id);
+            true
+        } else {
+            false
+        }
+    };
+
+    if removed {
+        info!(region_id = %state.region_config_id, gateway_id = %gateway_id, "Gateway disconnected");
+        tokio::spawn(uplink::conn_state::ConnState::handle(gw::ConnState {
+            gateway_id: gateway_id.to_string(),
+            state: gw::conn_state::State::Offline.into(),
+            ..Default::default()
+        }));
+    }
+}
+
+async fn handle_message(
+    state: &State,
+    gateway_id: EUI64,
+    sender: &mpsc::UnboundedSender<String>,
+    msg: &str,
+) -> Result<()> {
+    let msg_type: MessageType = serde_json::from_str(msg).context("Parse msgtype")?;
+
+    EVENT_COUNTER
+        .get_or_create(&MessageLabels {
+            msgtype: msg_type.msgtype.clone(),
+        })
+        .inc();
+
+    match msg_type.msgtype.as_ref() {
+        "version" => handle_version(state, gateway_id, serde_json::from_str(msg)?).await,
+        "updf" => {
+            let pl: UplinkDataFrame = serde_json::from_str(msg)?;
+            handle_uplink(
+                state,
+                gateway_id,
+                pl.phy_payload()?,
+                pl.dr,
+                pl.freq,
+                &pl.upinfo,
+            )
+            .await
+        }
+        "jreq" => {
+            let pl: JoinRequest = serde_json::from_str(msg)?;
+            handle_uplink(
+                state,
+                gateway_id,
+                pl.phy_payload()?,
+                pl.dr,
+                pl.freq,
+                &pl.upinfo,
+            )
+            .await
+        }
+        "propdf" => {
+            let pl: ProprietaryDataFrame = serde_json::from_str(msg)?;
+            let phy_payload = hex::decode(&pl.frm_payload).context("Decode FRMPayload")?;
+            handle_uplink(state, gateway_id, phy_payload, pl.dr, pl.freq, &pl.upinfo).await
+        }
+        "dntxed" => handle_dntxed(state, gateway_id, serde_json::from_str(msg)?).await,
+        "timesync" => handle_timesync(gateway_id, sender, serde_json::from_str(msg)?),
+        _ => {
+            debug!(gateway_id = %gateway_id, msgtype = %msg_type.msgtype, "Ignoring unsupported message");
+            Ok(())
+        }
+    }
+}
+
+async fn handle_version(state: &State, gateway_id: EUI64, pl: Version) -> Result<()> {
+    info!(region_id = %state.region_config_id, gateway_id = %gateway_id, station = %pl.station, firmware = %pl.firmware, package = %pl.package, model = %pl.model, protocol = pl.protocol, features = %pl.features, "Version received from gateway");
+
+    // The Basics Station expects the router_config as response to the version message.
+    let gateway_conf = config::get_region_gateway(&state.region_config_id)?;
+    send_configuration(state, &get_gateway_configuration(gateway_id, &gateway_conf)).await
+}
+
+async fn handle_uplink(
+    state: &State,
+    gateway_id: EUI64,
+    phy_payload: Vec<u8>,
+    dr: u8,
+    freq: u32,
+    upinfo: &structs::UpInfo,
+) -> Result<()> {
+    let region_conf = region::get(&state.region_config_id)?;
+    let mut event = get_uplink_frame(
+        region_conf.as_ref().as_ref(),
+        gateway_id,
+        phy_payload,
+        dr,
+        freq,
+        upinfo,
+    )?;
+
+    if let Some(rx_info) = &mut event.rx_info {
+        rx_info.ns_time = Some(Utc::now().into());
+        rx_info.metadata.insert(
+            "region_config_id".to_string(),
+            state.region_config_id.clone(),
+        );
+        rx_info.metadata.insert(
+            "region_common_name".to_string(),
+            state.region_common_name.to_string(),
+        );
+    }
+
+    super::set_gateway_backend(
+        state.region_config_id.clone(),
+        gateway_id.to_string(),
+        state.backend_id,
+    )
+    .await;
+    tokio::spawn(uplink::deduplicate_uplink(event));
+
+    Ok(())
+}
+
+async fn handle_dntxed(state: &State, gateway_id: EUI64, pl: DownlinkTransmitted) -> Result<()> {
+    info!(region_id = %state.region_config_id, gateway_id = %gateway_id, diid = pl.diid, "Downlink transmitted by gateway");
+
+    let pending = state
+        .downlinks
+        .write()
+        .await
+        .remove(&(pl.diid as u32))
+        .ok_or_else(|| anyhow!("No pending downlink for diid {}", pl.diid))?;
+
+    tokio::spawn(downlink::tx_ack::TxAck::handle(pending.tx_ack()));
+
+    Ok(())
+}
+
+fn handle_timesync(
+    gateway_id: EUI64,
+    sender: &mpsc::UnboundedSender<String>,
+    pl: TimeSync,
+) -> Result<()> {
+    trace!(gateway_id = %gateway_id, "Timesync request received");
+
+    let resp = TimeSync {
+        msgtype: "timesync".into(),
+        txtime: pl.txtime,
+        gpstime: Utc::now().to_gps_time().num_microseconds(),
+    };
+
+    sender
+        .send(serde_json::to_string(&resp)?)
+        .map_err(|_| anyhow!("Gateway connection closed"))?;
+
+    Ok(())
+}
+
+async fn send_configuration(state: &State, gw_conf: &gw::GatewayConfiguration) -> Result<()> {
+    let gateway_id: EUI64 = gw_conf.gateway_id.parse().context("Gateway ID")?;
+    let region_conf = region::get(&state.region_config_id)?;
+    let rc = RouterConfig::from_gateway_configuration(region_conf.as_ref().as_ref(), gw_conf)?;
+
+    info!(region_id = %state.region_config_id, gateway_id = %gateway_id, "Sending router_config to gateway");
+
+    send_message(state, gateway_id, "router_config", &rc).await
+}
+
+async fn send_message<T: serde::Serialize>(
+    state: &State,
+    gateway_id: EUI64,
+    msgtype: &str,
+    pl: &T,
+) -> Result<()> {
+    let conn = state
+        .gateways
+        .read()
+        .await
+        .get(&gateway_id)
+        .cloned()
+        .ok_or_else(|| anyhow!("Gateway {} is not connected", gateway_id))?;
+
+    COMMAND_COUNTER
+        .get_or_create(&MessageLabels {
+            msgtype: msgtype.to_string(),
+        })
+        .inc();
+
+    conn.sender
+        .send(serde_json::to_string(pl)?)
+        .map_err(|_| anyhow!("Gateway {} connection closed", gateway_id))?;
+    trace!(msgtype = %msgtype, "Message sent");
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::test;
+    use tokio_tungstenite::tungstenite;
+
+    async fn recv<S>(ws: &mut S) -> serde_json::Value
+    where
+        S: futures::Stream<Item = Result<tungstenite::Message, tungstenite::Error>> + Unpin,
+    {
+        let msg = tokio::time::timeout(Duration::from_secs(1), ws.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        serde_json::from_str(msg.to_text().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_basics_station() {
+        let _guard = test::prepare().await;
+
+        let mut conf = (*config::get()).clone();
+        conf.regions[0].gateway.channels = [868100000, 868300000, 868500000]
+            .iter()
+            .map(|f| config::GatewayChannel {
+                frequency: *f,
+                bandwidth: 125000,
+                modulation: config::GatewayChannelModulation::LORA,
+                spreading_factors: vec![7, 8, 9, 10, 11, 12],
+                ..Default::default()
+            })
+            .collect();
+        config::set(conf);
+
+        let backend = BasicsStationBackend::new(
+            "eu868",
+            0,
+            CommonName::EU868,
+            &GatewayBackendBasicsStation {
+                bind: "127.0.0.1:0".into(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let gateway_id = EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]);
+
+        // router-info
+        let (mut ws, _) =
+            tokio_tungstenite::connect_async(format!("ws://{}/router-info", backend.local_addr))
+                .await
+                .unwrap();
+        ws.send(tungstenite::Message::Text(
+            r#"{"router":"102:304:506:708"}"#.into(),
+        ))
+        .await
+        .unwrap();
+        let resp = recv(&mut ws).await;
+        assert_eq!(
+            format!("ws://{}/gateway/0102030405060708", backend.local_addr),
+            resp["uri"]
+        );
+
+        // gateway connection
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!(
+            "ws://{}/gateway/0102030405060708",
+            backend.local_addr
+        ))
+        .await
+        .unwrap();
+        ws.send(tungstenite::Message::Text(
+            r#"{"msgtype":"version","station":"2.0.6","protocol":2}"#.into(),
+        ))
+        .await
+        .unwrap();
+
+        let rc = recv(&mut ws).await;
+        assert_eq!("router_config", rc["msgtype"]);
+        assert_eq!("EU863", rc["region"]);
+        assert_eq!(868300000, rc["sx1301_conf"][0]["radio_0"]["freq"]);
+        assert_eq!(-200000, rc["sx1301_conf"][0]["chan_multiSF_0"]["if"]);
+
+        // timesync
+        ws.send(tungstenite::Message::Text(
+            r#"{"msgtype":"timesync","txtime":123.5}"#.into(),
+        ))
+        .await
+        .unwrap();
+        let resp = recv(&mut ws).await;
+        assert_eq!(123.5, resp["txtime"]);
+        assert!(resp["gpstime"].as_i64().unwrap() > 0);
+
+        // downlink
+        let mut context = 1000i64.to_be_bytes().to_vec();
+        context.extend_from_slice(&2i64.to_be_bytes());
+        backend
+            .send_downlink(&gw::DownlinkFrame {
+                gateway_id: gateway_id.to_string(),
+                downlink_id: 123,
+                items: vec![gw::DownlinkFrameItem {
+                    phy_payload: vec![1, 2, 3],
+                    tx_info: Some(gw::DownlinkTxInfo {
+                        frequency: 868100000,
+                        power: 14,
+                        modulation: Some(gw::Modulation {
+                            parameters: Some(gw::modulation::Parameters::Lora(
+                                gw::LoraModulationInfo {
+                                    bandwidth: 125000,
+                                    spreading_factor: 12,
+                                    code_rate: gw::CodeRate::Cr45.into(),
+                                    polarization_inversion: true,
+                                    ..Default::default()
+                                },
+                            )),
+                        }),
+                        timing: Some(gw::Timing {
+                            parameters: Some(gw::timing::Parameters::Delay(gw::DelayTimingInfo {
+                                delay: Some(Duration::from_secs(1).into()),
+                            })),
+                        }),
+                        context,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let dm = recv(&mut ws).await;
+        assert_eq!("dnmsg", dm["msgtype"]);
+        assert_eq!(123, dm["diid"]);
+        assert_eq!("010203", dm["pdu"]);
+        assert_eq!(1, dm["RxDelay"]);
+        assert_eq!(0, dm["RX1DR"]);
+        assert_eq!(1000, dm["xtime"]);
+        assert_eq!(2, dm["rctx"]);
+
+        // dntxed
+        ws.send(tungstenite::Message::Text(
+            r#"{"msgtype":"dntxed","diid":123}"#.into(),
+        ))
+        .await
+        .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(backend.state.downlinks.read().await.is_empty());
+
+        // disconnect
+        ws.close(None).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(backend.state.gateways.read().await.is_empty());
+    }
+
+    #[test]
+    fn test_pending_downlink() {
+        let pending = PendingDownlink {
+            frame: gw::DownlinkFrame {
+                gateway_id: "0102030405060708".into(),
+                downlink_id: 123,
+                items: vec![Default::default(), Default::default()],
+                ..Default::default()
+            },
+            created_at: Instant::now(),
+        };
+
+        assert_eq!(
+            gw::DownlinkTxAck {
+                gateway_id: "0102030405060708".into(),
+                downlink_id: 123,
+                items: vec![
+                    gw::DownlinkTxAckItem {
+                        status: gw::TxAckStatus::Ok.into(),
+                    },
+                    gw::DownlinkTxAckItem {
+                        status: gw::TxAckStatus::Ignored.into(),
+                    },
+                ],
+                ..Default::default()
+            },
+            pending.tx_ack()
+        );
+    }
+}
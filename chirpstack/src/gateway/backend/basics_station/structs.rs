@@ -0,0 +1,957 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::config;
+use chirpstack_api::gw;
+use lrwn::region::{CommonName, DataRateModulation, Region};
+use lrwn::EUI64;
+
+// The IF bandwidth of a SX1257 radio.
+const RADIO_BANDWIDTH: u32 = 925_000;
+
+// Number of data-rates in the router_config DRs table.
+const DATA_RATES: u8 = 16;
+
+#[derive(Deserialize)]
+pub struct MessageType {
+    pub msgtype: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum RouterId {
+    Int(u64),
+    Str(String),
+}
+
+impl RouterId {
+    // Returns the router ID as EUI64. The router ID can be formatted as integer, as EUI string
+    // (with or without - separators) or in the ID6 format (e.g. b827:ebff:fe61:51a9).
+    pub fn to_eui64(&self) -> Result<EUI64> {
+        match self {
+            RouterId::Int(v) => Ok(EUI64::from_be_bytes(v.to_be_bytes())),
+            RouterId::Str(v) => {
+                if v.contains(':') {
+                    let (head, tail) = match v.split_once("::") {
+                        Some((head, tail)) => (head, tail),
+                        None => (v.as_str(), ""),
+                    };
+                    let head: Vec<&str> = head.split(':').filter(|s| !s.is_empty()).collect();
+                    let tail: Vec<&str> = tail.split(':').filter(|s| !s.is_empty()).collect();
+                    if head.len() + tail.len() > 4 {
+                        return Err(anyhow!("Invalid ID6: {}", v));
+                    }
+
+                    let mut groups: Vec<u16> = Vec::with_capacity(4);
+                    for g in &head {
+                        groups.push(u16::from_str_radix(g, 16).context("Parse ID6")?);
+                    }
+                    groups.resize(4 - tail.len(), 0);
+                    for g in &tail {
+                        groups.push(u16::from_str_radix(g, 16).context("Parse ID6")?);
+                    }
+
+                    let mut b = [0; 8];
+                    for (i, g) in groups.iter().enumerate() {
+                        b[i * 2..i * 2 + 2].copy_from_slice(&g.to_be_bytes());
+                    }
+                    Ok(EUI64::from_be_bytes(b))
+                } else {
+                    Ok(EUI64::from_str(&v.replace('-', ""))?)
+                }
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RouterInfoRequest {
+    pub router: RouterId,
+}
+
+#[derive(Serialize)]
+pub struct RouterInfoResponse {
+    pub router: String,
+    pub muxs: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub uri: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub error: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Version {
+    pub station: String,
+    pub firmware: String,
+    pub package: String,
+    pub model: String,
+    pub protocol: u32,
+    pub features: String,
+}
+
+#[derive(Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct UpInfo {
+    pub rctx: i64,
+    pub xtime: i64,
+    pub gpstime: i64,
+    pub fts: Option<i64>,
+    pub rssi: f32,
+    pub snr: f32,
+    pub rxtime: f64,
+}
+
+#[derive(Deserialize)]
+pub struct UplinkDataFrame {
+    #[serde(rename = "MHdr")]
+    pub mhdr: u8,
+    #[serde(rename = "DevAddr")]
+    pub dev_addr: i64,
+    #[serde(rename = "FCtrl")]
+    pub f_ctrl: u8,
+    #[serde(rename = "FCnt")]
+    pub f_cnt: u16,
+    #[serde(rename = "FOpts")]
+    pub f_opts: String,
+    #[serde(rename = "FPort")]
+    pub f_port: i16,
+    #[serde(rename = "FRMPayload")]
+    pub frm_payload: String,
+    #[serde(rename = "MIC")]
+    pub mic: i32,
+    #[serde(rename = "DR")]
+    pub dr: u8,
+    #[serde(rename = "Freq")]
+    pub freq: u32,
+    pub upinfo: UpInfo,
+}
+
+impl UplinkDataFrame {
+    pub fn phy_payload(&self) -> Result<Vec<u8>> {
+        let mut b = vec![self.mhdr];
+        b.extend_from_slice(&(self.dev_addr as u32).to_le_bytes());
+        b.push(self.f_ctrl);
+        b.extend_from_slice(&self.f_cnt.to_le_bytes());
+        b.extend_from_slice(&hex::decode(&self.f_opts).context("Decode FOpts")?);
+        if self.f_port >= 0 {
+            b.push(self.f_port as u8);
+            b.extend_from_slice(&hex::decode(&self.frm_payload).context("Decode FRMPayload")?);
+        }
+        b.extend_from_slice(&self.mic.to_le_bytes());
+        Ok(b)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct JoinRequest {
+    #[serde(rename = "MHdr")]
+    pub mhdr: u8,
+    #[serde(rename = "JoinEui")]
+    pub join_eui: String,
+    #[serde(rename = "DevEui")]
+    pub dev_eui: String,
+    #[serde(rename = "DevNonce")]
+    pub dev_nonce: u16,
+    #[serde(rename = "MIC")]
+    pub mic: i32,
+    #[serde(rename = "DR")]
+    pub dr: u8,
+    #[serde(rename = "Freq")]
+    pub freq: u32,
+    pub upinfo: UpInfo,
+}
+
+impl JoinRequest {
+    pub fn phy_payload(&self) -> Result<Vec<u8>> {
+        let join_eui = EUI64::from_str(&self.join_eui.replace('-', ""))?;
+        let dev_eui = EUI64::from_str(&self.dev_eui.replace('-', ""))?;
+
+        let mut b = vec![self.mhdr];
+        b.extend_from_slice(&join_eui.to_le_bytes());
+        b.extend_from_slice(&dev_eui.to_le_bytes());
+        b.extend_from_slice(&self.dev_nonce.to_le_bytes());
+        b.extend_from_slice(&self.mic.to_le_bytes());
+        Ok(b)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ProprietaryDataFrame {
+    #[serde(rename = "FRMPayload")]
+    pub frm_payload: String,
+    #[serde(rename = "DR")]
+    pub dr: u8,
+    #[serde(rename = "Freq")]
+    pub freq: u32,
+    pub upinfo: UpInfo,
+}
+
+#[derive(Deserialize)]
+pub struct DownlinkTransmitted {
+    pub diid: i64,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct TimeSync {
+    pub msgtype: String,
+    pub txtime: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gpstime: Option<i64>,
+}
+
+// Returns the uplink frame given the PHYPayload, data-rate, frequency and upinfo.
+pub fn get_uplink_frame(
+    region_conf: &dyn Region,
+    gateway_id: EUI64,
+    phy_payload: Vec<u8>,
+    dr: u8,
+    freq: u32,
+    upinfo: &UpInfo,
+) -> Result<gw::UplinkFrame> {
+    let mut context = upinfo.xtime.to_be_bytes().to_vec();
+    context.extend_from_slice(&upinfo.rctx.to_be_bytes());
+
+    Ok(gw::UplinkFrame {
+        phy_payload,
+        tx_info: Some(gw::UplinkTxInfo {
+            frequency: freq,
+            modulation: Some(get_modulation(region_conf.get_data_rate(dr)?)?),
+        }),
+        rx_info: Some(gw::UplinkRxInfo {
+            gateway_id: gateway_id.to_string(),
+            uplink_id: rand::random(),
+            gw_time: if upinfo.rxtime > 0.0 {
+                DateTime::<Utc>::from_timestamp(
+                    upinfo.rxtime.trunc() as i64,
+                    (upinfo.rxtime.fract() * 1_000_000_000.0) as u32,
+                )
+                .map(|v| v.into())
+            } else {
+                None
+            },
+            time_since_gps_epoch: if upinfo.gpstime > 0 {
+                Some(Duration::from_micros(upinfo.gpstime as u64).into())
+            } else {
+                None
+            },
+            // The fine-timestamp is -1 when not available.
+            fine_time_since_gps_epoch: match upinfo.fts {
+                Some(fts) if upinfo.gpstime > 0 && fts >= 0 => Some(
+                    Duration::new(
+                        Duration::from_micros(upinfo.gpstime as u64).as_secs(),
+                        fts as u32,
+                    )
+                    .into(),
+                ),
+                _ => None,
+            },
+            rssi: upinfo.rssi as i32,
+            snr: upinfo.snr,
+            context,
+            crc_status: gw::CrcStatus::CrcOk.into(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
+
+fn get_modulation(dr: DataRateModulation) -> Result<gw::Modulation> {
+    Ok(gw::Modulation {
+        parameters: Some(match dr {
+            DataRateModulation::Lora(v) => {
+                gw::modulation::Parameters::Lora(gw::LoraModulationInfo {
+                    bandwidth: v.bandwidth,
+                    spreading_factor: v.spreading_factor.into(),
+                    code_rate: gw::CodeRate::from_str(&v.coding_rate)
+                        .map_err(|e| anyhow!("{}", e))?
+                        .into(),
+                    ..Default::default()
+                })
+            }
+            DataRateModulation::Fsk(v) => gw::modulation::Parameters::Fsk(gw::FskModulationInfo {
+                datarate: v.bitrate,
+                ..Default::default()
+            }),
+            DataRateModulation::LrFhss(v) => {
+                gw::modulation::Parameters::LrFhss(gw::LrFhssModulationInfo {
+                    operating_channel_width: v.occupied_channel_width,
+                    code_rate: gw::CodeRate::from_str(&v.coding_rate)
+                        .map_err(|e| anyhow!("{}", e))?
+                        .into(),
+                    ..Default::default()
+                })
+            }
+        }),
+    })
+}
+
+fn get_data_rate_modulation(modulation: &Option<gw::Modulation>) -> Result<DataRateModulation> {
+    Ok(
+        match modulation
+            .as_ref()
+            .and_then(|v| v.parameters.as_ref())
+            .ok_or_else(|| anyhow!("modulation parameters must not be None"))?
+        {
+            gw::modulation::Parameters::Lora(v) => {
+                DataRateModulation::Lora(lrwn::region::LoraDataRate {
+                    spreading_factor: v.spreading_factor as u8,
+                    bandwidth: v.bandwidth,
+                    coding_rate: v.code_rate().into(),
+                })
+            }
+            gw::modulation::Parameters::Fsk(v) => {
+                DataRateModulation::Fsk(lrwn::region::FskDataRate {
+                    bitrate: v.datarate,
+                })
+            }
+            gw::modulation::Parameters::LrFhss(_) => {
+                return Err(anyhow!("LR-FHSS modulation is not supported for downlink"));
+            }
+        },
+    )
+}
+
+#[derive(Serialize, Default, Debug, PartialEq)]
+pub struct DownlinkMessage {
+    pub msgtype: String,
+    #[serde(rename = "DevEui")]
+    pub dev_eui: String,
+    #[serde(rename = "dC")]
+    pub device_class: u8,
+    pub diid: i64,
+    pub pdu: String,
+    #[serde(rename = "RxDelay", skip_serializing_if = "Option::is_none")]
+    pub rx_delay: Option<u64>,
+    #[serde(rename = "RX1DR", skip_serializing_if = "Option::is_none")]
+    pub rx1_dr: Option<u8>,
+    #[serde(rename = "RX1Freq", skip_serializing_if = "Option::is_none")]
+    pub rx1_freq: Option<u32>,
+    #[serde(rename = "RX2DR", skip_serializing_if = "Option::is_none")]
+    pub rx2_dr: Option<u8>,
+    #[serde(rename = "RX2Freq", skip_serializing_if = "Option::is_none")]
+    pub rx2_freq: Option<u32>,
+    #[serde(rename = "DR", skip_serializing_if = "Option::is_none")]
+    pub dr: Option<u8>,
+    #[serde(rename = "Freq", skip_serializing_if = "Option::is_none")]
+    pub freq: Option<u32>,
+    pub priority: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub xtime: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rctx: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gpstime: Option<i64>,
+    #[serde(rename = "MuxTime")]
+    pub mux_time: f64,
+}
+
+impl DownlinkMessage {
+    pub fn from_downlink_frame(region_conf: &dyn Region, df: &gw::DownlinkFrame) -> Result<Self> {
+        let item = df
+            .items
+            .first()
+            .ok_or_else(|| anyhow!("Downlink frame does not contain any items"))?;
+        let tx_info = item
+            .tx_info
+            .as_ref()
+            .ok_or_else(|| anyhow!("tx_info must not be None"))?;
+
+        let mut dm = DownlinkMessage {
+            msgtype: "dnmsg".into(),
+            dev_eui: "00-00-00-00-00-00-00-00".into(),
+            diid: df.downlink_id.into(),
+            pdu: hex::encode(&item.phy_payload),
+            mux_time: Utc::now().timestamp_micros() as f64 / 1_000_000.0,
+            ..Default::default()
+        };
+
+        // The context contains the xtime and rctx of the uplink.
+        let (xtime, rctx) = if tx_info.context.len() == 16 {
+            (
+                Some(i64::from_be_bytes(tx_info.context[0..8].try_into()?)),
+                Some(i64::from_be_bytes(tx_info.context[8..16].try_into()?)),
+            )
+        } else {
+            (None, None)
+        };
+
+        let dr = region_conf
+            .get_data_rate_index(false, &get_data_rate_modulation(&tx_info.modulation)?)?;
+
+        match tx_info
+            .timing
+            .as_ref()
+            .and_then(|v| v.parameters.as_ref())
+            .ok_or_else(|| anyhow!("timing parameters must not be None"))?
+        {
+            gw::timing::Parameters::Immediately(_) => {
+                dm.device_class = 2;
+                dm.rx2_dr = Some(dr);
+                dm.rx2_freq = Some(tx_info.frequency);
+                dm.rctx = rctx;
+            }
+            gw::timing::Parameters::Delay(v) => {
+                let delay: Duration = v
+                    .delay
+                    .ok_or_else(|| anyhow!("delay must not be None"))?
+                    .try_into()?;
+
+                dm.device_class = 0;
+                dm.rx_delay = Some(delay.as_secs());
+                dm.rx1_dr = Some(dr);
+                dm.rx1_freq = Some(tx_info.frequency);
+                dm.xtime = Some(xtime.ok_or_else(|| anyhow!("context must be 16 bytes"))?);
+                dm.rctx = rctx;
+
+                if let Some(tx_info) = df.items.get(1).and_then(|v| v.tx_info.as_ref()) {
+                    dm.rx2_dr = Some(region_conf.get_data_rate_index(
+                        false,
+                        &get_data_rate_modulation(&tx_info.modulation)?,
+                    )?);
+                    dm.rx2_freq = Some(tx_info.frequency);
+                }
+            }
+            gw::timing::Parameters::GpsEpoch(v) => {
+                let gps_time: Duration = v
+                    .time_since_gps_epoch
+                    .ok_or_else(|| anyhow!("time_since_gps_epoch must not be None"))?
+                    .try_into()?;
+
+                dm.device_class = 1;
+                dm.dr = Some(dr);
+                dm.freq = Some(tx_info.frequency);
+                dm.gpstime = Some(gps_time.as_micros() as i64);
+                dm.rctx = rctx;
+            }
+        }
+
+        Ok(dm)
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct RouterConfig {
+    pub msgtype: String,
+    #[serde(rename = "NetID")]
+    pub net_id: Option<Vec<u32>>,
+    #[serde(rename = "JoinEui")]
+    pub join_eui: Option<Vec<[u64; 2]>>,
+    pub region: String,
+    pub hwspec: String,
+    pub freq_range: [u32; 2],
+    #[serde(rename = "DRs")]
+    pub drs: Vec<[i32; 3]>,
+    pub sx1301_conf: Vec<serde_json::Value>,
+    pub nocca: bool,
+    pub nodc: bool,
+    pub nodwell: bool,
+}
+
+impl RouterConfig {
+    pub fn from_gateway_configuration(
+        region_conf: &dyn Region,
+        gw_conf: &gw::GatewayConfiguration,
+    ) -> Result<Self> {
+        let (region, freq_range) = get_region_and_freq_range(region_conf.get_name())?;
+
+        Ok(RouterConfig {
+            msgtype: "router_config".into(),
+            net_id: None,
+            join_eui: None,
+            region: region.to_string(),
+            hwspec: "sx1301/1".into(),
+            freq_range,
+            drs: (0..DATA_RATES)
+                .map(|dr| match region_conf.get_data_rate(dr) {
+                    Ok(v) => {
+                        let dn_only = match region_conf.get_data_rate_index(true, &v) {
+                            Ok(i) => i != dr,
+                            Err(_) => true,
+                        } as i32;
+
+                        match v {
+                            DataRateModulation::Lora(v) => [
+                                v.spreading_factor.into(),
+                                (v.bandwidth / 1000) as i32,
+                                dn_only,
+                            ],
+                            DataRateModulation::Fsk(_) => [0, 0, dn_only],
+                            DataRateModulation::LrFhss(_) => [-1, 0, 0],
+                        }
+                    }
+                    Err(_) => [-1, 0, 0],
+                })
+                .collect(),
+            sx1301_conf: vec![get_sx1301_conf(&gw_conf.channels)?],
+            nocca: true,
+            nodc: true,
+            nodwell: true,
+        })
+    }
+}
+
+// Returns the Basics Station region name and frequency range.
+fn get_region_and_freq_range(common_name: CommonName) -> Result<(&'static str, [u32; 2])> {
+    Ok(match common_name {
+        CommonName::EU868 => ("EU863", [863_000_000, 870_000_000]),
+        CommonName::EU433 => ("EU433", [433_050_000, 434_900_000]),
+        CommonName::US915 => ("US902", [902_000_000, 928_000_000]),
+        CommonName::AU915 => ("AU915", [915_000_000, 928_000_000]),
+        CommonName::CN470 => ("CN470", [470_000_000, 510_000_000]),
+        CommonName::AS923 => ("AS923-1", [915_000_000, 928_000_000]),
+        CommonName::AS923_2 => ("AS923-2", [915_000_000, 928_000_000]),
+        CommonName::AS923_3 => ("AS923-3", [915_000_000, 928_000_000]),
+        CommonName::AS923_4 => ("AS923-4", [915_000_000, 928_000_000]),
+        CommonName::KR920 => ("KR920", [920_900_000, 923_300_000]),
+        CommonName::IN865 => ("IN865", [865_000_000, 867_000_000]),
+        _ => {
+            return Err(anyhow!(
+                "Region {} is not supported by the Basics Station backend",
+                common_name
+            ))
+        }
+    })
+}
+
+// Returns the sx1301_conf for the given channels. The channels are assigned to the two radios,
+// the radio center frequencies are chosen such that all channels fit within the radio
+// bandwidth.
+fn get_sx1301_conf(channels: &[gw::ChannelConfiguration]) -> Result<serde_json::Value> {
+    if channels.is_empty() {
+        return Err(anyhow!("Gateway channels are not configured"));
+    }
+
+    let mut channels: Vec<(u32, u32, &gw::ChannelConfiguration)> = channels
+        .iter()
+        .map(|c| {
+            let bandwidth = match &c.modulation_config {
+                Some(gw::channel_configuration::ModulationConfig::LoraModulationConfig(v)) => {
+                    v.bandwidth
+                }
+                Some(gw::channel_configuration::ModulationConfig::FskModulationConfig(v)) => {
+                    v.bandwidth
+                }
+                None => 0,
+            };
+            (c.frequency, bandwidth, c)
+        })
+        .collect();
+    channels.sort_by_key(|(freq, _, _)| *freq);
+
+    // (min, max) edges of the channels assigned to each radio.
+    let mut radios: [Option<(u32, u32)>; 2] = [None, None];
+    let mut assigned: Vec<(usize, &gw::ChannelConfiguration)> = Vec::new();
+
+    for (freq, bandwidth, c) in &channels {
+        let (min, max) = (freq - bandwidth / 2, freq + bandwidth / 2);
+
+        let radio = radios.iter().position(|r| match r {
+            Some((r_min, r_max)) => (*r_max).max(max) - (*r_min).min(min) <= RADIO_BANDWIDTH,
+            None => true,
+        });
+
+        let radio = radio.ok_or_else(|| {
+            anyhow!(
+                "Channel {} does not fit within the bandwidth of the radios",
+                freq
+            )
+        })?;
+
+        radios[radio] = Some(match radios[radio] {
+            Some((r_min, r_max)) => (r_min.min(min), r_max.max(max)),
+            None => (min, max),
+        });
+        assigned.push((radio, c));
+    }
+
+    let radio_freq: Vec<u32> = radios
+        .iter()
+        .map(|r| r.map(|(min, max)| (min + max) / 2).unwrap_or_default())
+        .collect();
+
+    let mut conf = serde_json::Map::new();
+    for (i, freq) in radio_freq.iter().enumerate() {
+        conf.insert(
+            format!("radio_{}", i),
+            json!({
+                "enable": *freq != 0,
+                "freq": freq,
+            }),
+        );
+    }
+
+    let mut multi_sf = 0;
+    for (radio, c) in assigned {
+        let if_freq = c.frequency as i64 - radio_freq[radio] as i64;
+
+        match &c.modulation_config {
+            Some(gw::channel_configuration::ModulationConfig::LoraModulationConfig(v))
+                if v.spreading_factors.len() == 1 =>
+            {
+                if conf.contains_key("chan_Lora_std") {
+                    return Err(anyhow!("Only one single-SF LoRa channel is supported"));
+                }
+                conf.insert(
+                    "chan_Lora_std".into(),
+                    json!({
+                        "enable": true,
+                        "radio": radio,
+                        "if": if_freq,
+                        "bandwidth": v.bandwidth,
+                        "spread_factor": v.spreading_factors[0],
+                    }),
+                );
+            }
+            Some(gw::channel_configuration::ModulationConfig::LoraModulationConfig(_)) => {
+                if multi_sf == 8 {
+                    return Err(anyhow!("At most 8 multi-SF LoRa channels are supported"));
+                }
+                conf.insert(
+                    format!("chan_multiSF_{}", multi_sf),
+                    json!({
+                        "enable": true,
+                        "radio": radio,
+                        "if": if_freq,
+                    }),
+                );
+                multi_sf += 1;
+            }
+            Some(gw::channel_configuration::ModulationConfig::FskModulationConfig(_)) => {
+                if conf.contains_key("chan_FSK") {
+                    return Err(anyhow!("Only one FSK channel is supported"));
+                }
+                conf.insert(
+                    "chan_FSK".into(),
+                    json!({
+                        "enable": true,
+                        "radio": radio,
+                        "if": if_freq,
+                    }),
+                );
+            }
+            None => {
+                return Err(anyhow!("Channel {} has no modulation", c.frequency));
+            }
+        }
+    }
+
+    Ok(serde_json::Value::Object(conf))
+}
+
+// Returns the gateway configuration for the given region gateway configuration, this is used
+// when a Basics Station connects, as it expects the router_config before it starts operating.
+pub fn get_gateway_configuration(
+    gateway_id: EUI64,
+    gateway_conf: &config::RegionGateway,
+) -> gw::GatewayConfiguration {
+    gw::GatewayConfiguration {
+        gateway_id: gateway_id.to_string(),
+        channels: gateway_conf
+            .channels
+            .iter()
+            .map(|c| gw::ChannelConfiguration {
+                frequency: c.frequency,
+                modulation_config: Some(match c.modulation {
+                    config::GatewayChannelModulation::LORA => {
+                        gw::channel_configuration::ModulationConfig::LoraModulationConfig(
+                            gw::LoraModulationConfig {
+                                bandwidth: c.bandwidth,
+                                spreading_factors: c.spreading_factors.clone(),
+                                ..Default::default()
+                            },
+                        )
+                    }
+                    config::GatewayChannelModulation::FSK => {
+                        gw::channel_configuration::ModulationConfig::FskModulationConfig(
+                            gw::FskModulationConfig {
+                                bandwidth: c.bandwidth,
+                                bitrate: c.datarate,
+                                ..Default::default()
+                            },
+                        )
+                    }
+                }),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn test_router_id_to_eui64() {
+        let gateway_id = EUI64::from_be_bytes([0xb8, 0x27, 0xeb, 0xff, 0xfe, 0x61, 0x51, 0xa9]);
+
+        let tests = vec![
+            r#"{"router":"b827:ebff:fe61:51a9"}"#,
+            r#"{"router":"b8-27-eb-ff-fe-61-51-a9"}"#,
+            r#"{"router":"b827ebfffe6151a9"}"#,
+            r#"{"router":13269834311787434409}"#,
+        ];
+        for tst in tests {
+            let req: RouterInfoRequest = serde_json::from_str(tst).unwrap();
+            assert_eq!(gateway_id, req.router.to_eui64().unwrap(), "{}", tst);
+        }
+
+        let req: RouterInfoRequest = serde_json::from_str(r#"{"router":"::1"}"#).unwrap();
+        assert_eq!(
+            EUI64::from_be_bytes([0, 0, 0, 0, 0, 0, 0, 1]),
+            req.router.to_eui64().unwrap()
+        );
+
+        let req: RouterInfoRequest = serde_json::from_str(r#"{"router":"1:2:3:4:5"}"#).unwrap();
+        assert!(req.router.to_eui64().is_err());
+    }
+
+    #[test]
+    fn test_uplink_data_frame() {
+        let pl: UplinkDataFrame = serde_json::from_str(
+            r#"{"msgtype":"updf","MHdr":64,"DevAddr":16909060,"FCtrl":128,"FCnt":10,"FOpts":"","FPort":1,"FRMPayload":"0102","MIC":-1,"DR":5,"Freq":868100000,"upinfo":{"rctx":0,"xtime":1000,"gpstime":0,"rssi":-50,"snr":9.5,"rxtime":1700000000.5}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                0x40, 0x04, 0x03, 0x02, 0x01, 0x80, 0x0a, 0x00, 0x01, 0x01, 0x02, 0xff, 0xff, 0xff,
+                0xff
+            ],
+            pl.phy_payload().unwrap()
+        );
+
+        let region_conf = lrwn::region::get(CommonName::EU868, false, false);
+        let gateway_id = EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]);
+        let uf = get_uplink_frame(
+            &*region_conf,
+            gateway_id,
+            pl.phy_payload().unwrap(),
+            pl.dr,
+            pl.freq,
+            &pl.upinfo,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some(gw::UplinkTxInfo {
+                frequency: 868100000,
+                modulation: Some(gw::Modulation {
+                    parameters: Some(gw::modulation::Parameters::Lora(gw::LoraModulationInfo {
+                        bandwidth: 125000,
+                        spreading_factor: 7,
+                        code_rate: gw::CodeRate::Cr45.into(),
+                        ..Default::default()
+                    })),
+                }),
+            }),
+            uf.tx_info
+        );
+
+        let rx_info = uf.rx_info.unwrap();
+        assert_eq!(-50, rx_info.rssi);
+        assert_eq!(9.5, rx_info.snr);
+        assert_eq!(None, rx_info.time_since_gps_epoch);
+        assert_eq!(
+            Some(
+                DateTime::<Utc>::from_timestamp(1700000000, 500_000_000)
+                    .unwrap()
+                    .into()
+            ),
+            rx_info.gw_time
+        );
+        let mut context = 1000i64.to_be_bytes().to_vec();
+        context.extend_from_slice(&0i64.to_be_bytes());
+        assert_eq!(context, rx_info.context);
+    }
+
+    #[test]
+    fn test_join_request() {
+        let pl: JoinRequest = serde_json::from_str(
+            r#"{"msgtype":"jreq","MHdr":0,"JoinEui":"01-02-03-04-05-06-07-08","DevEui":"08-07-06-05-04-03-02-01","DevNonce":258,"MIC":16909060,"DR":0,"Freq":868100000,"upinfo":{}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                0x00, 0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01, 0x01, 0x02, 0x03, 0x04, 0x05,
+                0x06, 0x07, 0x08, 0x02, 0x01, 0x04, 0x03, 0x02, 0x01
+            ],
+            pl.phy_payload().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_downlink_message() {
+        let region_conf = lrwn::region::get(CommonName::EU868, false, false);
+        let tx_info = |freq: u32, sf: u32, timing: gw::timing::Parameters| gw::DownlinkTxInfo {
+            frequency: freq,
+            modulation: Some(gw::Modulation {
+                parameters: Some(gw::modulation::Parameters::Lora(gw::LoraModulationInfo {
+                    bandwidth: 125000,
+                    spreading_factor: sf,
+                    code_rate: gw::CodeRate::Cr45.into(),
+                    ..Default::default()
+                })),
+            }),
+            timing: Some(gw::Timing {
+                parameters: Some(timing),
+            }),
+            context: vec![0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2],
+            ..Default::default()
+        };
+
+        let mut dm = DownlinkMessage::from_downlink_frame(
+            &*region_conf,
+            &gw::DownlinkFrame {
+                downlink_id: 123,
+                items: vec![
+                    gw::DownlinkFrameItem {
+                        phy_payload: vec![1, 2, 3],
+                        tx_info: Some(tx_info(
+                            868100000,
+                            7,
+                            gw::timing::Parameters::Delay(gw::DelayTimingInfo {
+                                delay: Some(Duration::from_secs(5).into()),
+                            }),
+                        )),
+                        ..Default::default()
+                    },
+                    gw::DownlinkFrameItem {
+                        phy_payload: vec![1, 2, 3],
+                        tx_info: Some(tx_info(
+                            869525000,
+                            12,
+                            gw::timing::Parameters::Delay(gw::DelayTimingInfo {
+                                delay: Some(Duration::from_secs(6).into()),
+                            }),
+                        )),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        dm.mux_time = 0.0;
+
+        assert_eq!(
+            DownlinkMessage {
+                msgtype: "dnmsg".into(),
+                dev_eui: "00-00-00-00-00-00-00-00".into(),
+                device_class: 0,
+                diid: 123,
+                pdu: "010203".into(),
+                rx_delay: Some(5),
+                rx1_dr: Some(5),
+                rx1_freq: Some(868100000),
+                rx2_dr: Some(0),
+                rx2_freq: Some(869525000),
+                xtime: Some(1),
+                rctx: Some(2),
+                ..Default::default()
+            },
+            dm
+        );
+
+        let mut dm = DownlinkMessage::from_downlink_frame(
+            &*region_conf,
+            &gw::DownlinkFrame {
+                downlink_id: 124,
+                items: vec![gw::DownlinkFrameItem {
+                    phy_payload: vec![1, 2, 3],
+                    tx_info: Some(tx_info(
+                        869525000,
+                        12,
+                        gw::timing::Parameters::Immediately(gw::ImmediatelyTimingInfo {}),
+                    )),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        dm.mux_time = 0.0;
+
+        assert_eq!(
+            DownlinkMessage {
+                msgtype: "dnmsg".into(),
+                dev_eui: "00-00-00-00-00-00-00-00".into(),
+                device_class: 2,
+                diid: 124,
+                pdu: "010203".into(),
+                rx2_dr: Some(0),
+                rx2_freq: Some(869525000),
+                rctx: Some(2),
+                ..Default::default()
+            },
+            dm
+        );
+    }
+
+    #[test]
+    fn test_router_config() {
+        let region_conf = lrwn::region::get(CommonName::EU868, false, false);
+        let gw_conf = get_gateway_configuration(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            &config::RegionGateway {
+                channels: vec![
+                    config::GatewayChannel {
+                        frequency: 868100000,
+                        bandwidth: 125000,
+                        modulation: config::GatewayChannelModulation::LORA,
+                        spreading_factors: vec![7, 8, 9, 10, 11, 12],
+                        ..Default::default()
+                    },
+                    config::GatewayChannel {
+                        frequency: 868300000,
+                        bandwidth: 250000,
+                        modulation: config::GatewayChannelModulation::LORA,
+                        spreading_factors: vec![7],
+                        ..Default::default()
+                    },
+                    config::GatewayChannel {
+                        frequency: 868800000,
+                        bandwidth: 125000,
+                        modulation: config::GatewayChannelModulation::FSK,
+                        datarate: 50000,
+                        ..Default::default()
+                    },
+                    config::GatewayChannel {
+                        frequency: 867100000,
+                        bandwidth: 125000,
+                        modulation: config::GatewayChannelModulation::LORA,
+                        spreading_factors: vec![7, 8, 9, 10, 11, 12],
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            },
+        );
+
+        let rc = RouterConfig::from_gateway_configuration(&*region_conf, &gw_conf).unwrap();
+        assert_eq!("EU863", rc.region);
+        assert_eq!([863_000_000, 870_000_000], rc.freq_range);
+        assert_eq!(16, rc.drs.len());
+        assert_eq!([12, 125, 0], rc.drs[0]);
+        assert_eq!([7, 250, 0], rc.drs[6]);
+        assert_eq!([0, 0, 0], rc.drs[7]);
+        assert_eq!([-1, 0, 0], rc.drs[15]);
+        assert_eq!(
+            json!({
+                "radio_0": {"enable": true, "freq": 867100000},
+                "radio_1": {"enable": true, "freq": 868450000},
+                "chan_multiSF_0": {"enable": true, "radio": 0, "if": 0},
+                "chan_multiSF_1": {"enable": true, "radio": 1, "if": -350000},
+                "chan_Lora_std": {"enable": true, "radio": 1, "if": -150000, "bandwidth": 250000, "spread_factor": 7},
+                "chan_FSK": {"enable": true, "radio": 1, "if": 350000},
+            }),
+            rc.sx1301_conf[0]
+        );
+    }
+}
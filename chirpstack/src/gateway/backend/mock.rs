@@ -10,11 +10,17 @@ lazy_static! {
     static ref DOWNLINK_FRAMES: RwLock<Vec<gw::DownlinkFrame>> = RwLock::new(Vec::new());
     static ref GATEWAY_CONFIGURATIONS: RwLock<Vec<gw::GatewayConfiguration>> =
         RwLock::new(Vec::new());
+    static ref COMMAND_EXEC_REQUESTS: RwLock<Vec<gw::GatewayCommandExecRequest>> =
+        RwLock::new(Vec::new());
+    static ref RAW_PACKET_FORWARDER_COMMANDS: RwLock<Vec<gw::RawPacketForwarderCommand>> =
+        RwLock::new(Vec::new());
 }
 
 pub async fn reset() {
     DOWNLINK_FRAMES.write().await.drain(..);
     GATEWAY_CONFIGURATIONS.write().await.drain(..);
+    COMMAND_EXEC_REQUESTS.write().await.drain(..);
+    RAW_PACKET_FORWARDER_COMMANDS.write().await.drain(..);
 }
 
 pub struct Backend {}
@@ -33,6 +39,22 @@ impl GatewayBackend for Backend {
         GATEWAY_CONFIGURATIONS.write().await.push(gw_conf.clone());
         Ok(())
     }
+
+    async fn send_command_exec(
+        &self,
+        pl: &chirpstack_api::gw::GatewayCommandExecRequest,
+    ) -> Result<()> {
+        COMMAND_EXEC_REQUESTS.write().await.push(pl.clone());
+        Ok(())
+    }
+
+    async fn send_raw_packet_forwarder_command(
+        &self,
+        pl: &chirpstack_api::gw::RawPacketForwarderCommand,
+    ) -> Result<()> {
+        RAW_PACKET_FORWARDER_COMMANDS.write().await.push(pl.clone());
+        Ok(())
+    }
 }
 
 pub async fn get_downlink_frames() -> Vec<gw::DownlinkFrame> {
@@ -42,3 +64,15 @@ pub async fn get_downlink_frames() -> Vec<gw::DownlinkFrame> {
 pub async fn get_gateway_configurations() -> Vec<gw::GatewayConfiguration> {
     GATEWAY_CONFIGURATIONS.write().await.drain(..).collect()
 }
+
+pub async fn get_command_exec_requests() -> Vec<gw::GatewayCommandExecRequest> {
+    COMMAND_EXEC_REQUESTS.write().await.drain(..).collect()
+}
+
+pub async fn get_raw_packet_forwarder_commands() -> Vec<gw::RawPacketForwarderCommand> {
+    RAW_PACKET_FORWARDER_COMMANDS
+        .write()
+        .await
+        .drain(..)
+        .collect()
+}
@@ -1,19 +1,36 @@
 use std::collections::HashMap;
+use std::sync::RwLock as SyncRwLock;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+use futures::future::BoxFuture;
 use tokio::sync::RwLock;
-use tracing::info;
+use tracing::{error, info, warn};
 
 use crate::config;
+use crate::helpers::errors::PrintFullError;
+use crate::storage::{get_async_redis_conn, redis_key};
 
+mod basics_station;
 #[cfg(test)]
 pub mod mock;
 mod mqtt;
+mod semtech_udp;
+
+// Duration for which the backend through which the last uplink or stats message of a gateway
+// was received is stored.
+const GATEWAY_BACKEND_TTL: Duration = Duration::from_secs(60 * 60 * 24);
+
+// Duration before the stored gateway backend expires, in which it is refreshed. Until then,
+// the stored gateway backend is only updated when it changes.
+const GATEWAY_BACKEND_REFRESH: Duration = Duration::from_secs(60 * 60);
 
 lazy_static! {
-    static ref BACKENDS: RwLock<HashMap<String, Box<dyn GatewayBackend + Sync + Send>>> =
+    static ref BACKENDS: RwLock<HashMap<String, Vec<Box<dyn GatewayBackend + Sync + Send>>>> =
         RwLock::new(HashMap::new());
+    static ref GATEWAY_BACKEND_STORED: SyncRwLock<HashMap<String, (usize, Instant)>> =
+        SyncRwLock::new(HashMap::new());
 }
 
 #[async_trait]
@@ -23,6 +40,14 @@ pub trait GatewayBackend {
         &self,
         gw_conf: &chirpstack_api::gw::GatewayConfiguration,
     ) -> Result<()>;
+    async fn send_command_exec(
+        &self,
+        pl: &chirpstack_api::gw::GatewayCommandExecRequest,
+    ) -> Result<()>;
+    async fn send_raw_packet_forwarder_command(
+        &self,
+        pl: &chirpstack_api::gw::RawPacketForwarderCommand,
+    ) -> Result<()>;
 }
 
 pub async fn setup() -> Result<()> {
@@ -37,55 +62,386 @@ pub async fn setup() -> Result<()> {
         info!(
             region_id = %region.id,
             region_common_name = %region.common_name,
+            extra_backends = region.gateway.extra_backends.len(),
             "Setting up gateway backend for region"
         );
 
-        let backend =
-            mqtt::MqttBackend::new(&region.id, region.common_name, &region.gateway.backend.mqtt)
-                .await
-                .context("New MQTT gateway backend error")?;
+        let mut backends: Vec<Box<dyn GatewayBackend + Sync + Send>> = Vec::new();
 
-        set_backend(&region.id, Box::new(backend)).await;
+        for (backend_id, backend_conf) in std::iter::once(&region.gateway.backend)
+            .chain(region.gateway.extra_backends.iter())
+            .enumerate()
+        {
+            backends.push(new_backend(region, backend_id, backend_conf).await?);
+        }
+
+        set_backends(&region.id, backends).await;
     }
 
     Ok(())
 }
 
+async fn new_backend(
+    region: &config::Region,
+    backend_id: usize,
+    conf: &config::GatewayBackend,
+) -> Result<Box<dyn GatewayBackend + Sync + Send>> {
+    Ok(match conf.enabled.as_ref() {
+        "mqtt" | "" => Box::new(
+            mqtt::MqttBackend::new(&region.id, backend_id, region.common_name, &conf.mqtt)
+                .await
+                .context("New MQTT gateway backend error")?,
+        ),
+        "semtech_udp" => Box::new(
+            semtech_udp::SemtechUdpBackend::new(
+                &region.id,
+                backend_id,
+                region.common_name,
+                &conf.semtech_udp,
+            )
+            .await
+            .context("New Semtech UDP gateway backend error")?,
+        ),
+        "basics_station" => Box::new(
+            basics_station::BasicsStationBackend::new(
+                &region.id,
+                backend_id,
+                region.common_name,
+                &conf.basics_station,
+            )
+            .await
+            .context("New Basics Station gateway backend error")?,
+        ),
+        _ => {
+            return Err(anyhow!("Unexpected gateway backend: {}", conf.enabled));
+        }
+    })
+}
+
+#[cfg(test)]
 pub async fn set_backend(region_config_id: &str, b: Box<dyn GatewayBackend + Sync + Send>) {
+    set_backends(region_config_id, vec![b]).await;
+}
+
+pub async fn set_backends(region_config_id: &str, b: Vec<Box<dyn GatewayBackend + Sync + Send>>) {
     let mut b_w = BACKENDS.write().await;
     b_w.insert(region_config_id.to_string(), b);
 }
 
-pub async fn send_downlink(
+// Stores the backend through which an uplink or stats message of the given gateway was
+// received. Downlinks for this gateway will be sent through the same backend. This is a no-op
+// for regions with a single backend.
+pub async fn set_gateway_backend(region_config_id: String, gateway_id: String, backend_id: usize) {
+    let backend_count = BACKENDS
+        .read()
+        .await
+        .get(&region_config_id)
+        .map(|v| v.len())
+        .unwrap_or_default();
+    if backend_count < 2 {
+        return;
+    }
+
+    if let Err(e) = _set_gateway_backend(&region_config_id, &gateway_id, backend_id).await {
+        error!(region_id = %region_config_id, gateway_id = %gateway_id, error = %e.full(), "Storing gateway backend failed");
+    }
+}
+
+async fn _set_gateway_backend(
     region_config_id: &str,
-    df: &chirpstack_api::gw::DownlinkFrame,
+    gateway_id: &str,
+    backend_id: usize,
 ) -> Result<()> {
+    let key = redis_key(format!(
+        "gw:{{{}}}:backend:{}",
+        gateway_id, region_config_id
+    ));
+
+    // Only store the backend when it has changed or when the stored value is about to expire.
+    if let Some((stored_backend_id, stored_at)) = GATEWAY_BACKEND_STORED.read().unwrap().get(&key) {
+        if *stored_backend_id == backend_id
+            && stored_at.elapsed() < GATEWAY_BACKEND_TTL - GATEWAY_BACKEND_REFRESH
+        {
+            return Ok(());
+        }
+    }
+
+    redis::cmd("PSETEX")
+        .arg(&key)
+        .arg(GATEWAY_BACKEND_TTL.as_millis() as u64)
+        .arg(backend_id)
+        .query_async::<()>(&mut get_async_redis_conn().await?)
+        .await?;
+
+    GATEWAY_BACKEND_STORED
+        .write()
+        .unwrap()
+        .insert(key, (backend_id, Instant::now()));
+
+    Ok(())
+}
+
+// Returns the order in which the backends must be tried for the given gateway. The backend
+// through which the last uplink was received is tried first, then the other backends.
+async fn get_backend_order(region_config_id: &str, gateway_id: &str, count: usize) -> Vec<usize> {
+    // There is nothing to route with a single backend.
+    if count < 2 {
+        return (0..count).collect();
+    }
+
+    let first = match get_gateway_backend(region_config_id, gateway_id).await {
+        Ok(v) => v.filter(|v| *v < count).unwrap_or_default(),
+        Err(e) => {
+            warn!(region_id = %region_config_id, gateway_id = %gateway_id, error = %e.full(), "Getting gateway backend failed");
+            0
+        }
+    };
+
+    std::iter::once(first)
+        .chain((0..count).filter(|v| *v != first))
+        .collect()
+}
+
+async fn get_gateway_backend(region_config_id: &str, gateway_id: &str) -> Result<Option<usize>> {
+    let key = redis_key(format!(
+        "gw:{{{}}}:backend:{}",
+        gateway_id, region_config_id
+    ));
+
+    let backend_id: Option<usize> = redis::cmd("GET")
+        .arg(key)
+        .query_async(&mut get_async_redis_conn().await?)
+        .await?;
+
+    Ok(backend_id)
+}
+
+// Sends the given payload through the backends of the region, in the order returned by
+// get_backend_order. In case sending fails, the next backend is tried. Note that the backend
+// used for the failover is not stored, as the route is only learned from uplinks and stats.
+async fn send<P, F>(region_config_id: &str, gateway_id: &str, typ: &str, pl: &P, f: F) -> Result<()>
+where
+    P: Sync + ?Sized,
+    F: for<'a> Fn(&'a (dyn GatewayBackend + Sync + Send), &'a P) -> BoxFuture<'a, Result<()>>,
+{
     let b_r = BACKENDS.read().await;
-    let b = b_r.get(region_config_id).ok_or_else(|| {
+    let backends = b_r.get(region_config_id).ok_or_else(|| {
         anyhow!(
             "region_config_id '{}' does not exist in BACKENDS",
             region_config_id
         )
     })?;
 
-    b.send_downlink(df).await?;
+    let mut err = anyhow!("region_config_id '{}' has no backends", region_config_id);
+    for backend_id in get_backend_order(region_config_id, gateway_id, backends.len()).await {
+        match f(backends[backend_id].as_ref(), pl).await {
+            Ok(_) => {
+                return Ok(());
+            }
+            Err(e) => {
+                warn!(region_id = %region_config_id, gateway_id = %gateway_id, backend_id = backend_id, error = %e, "Sending {} failed, trying next backend", typ);
+                err = e;
+            }
+        }
+    }
 
-    Ok(())
+    Err(err)
+}
+
+pub async fn send_downlink(
+    region_config_id: &str,
+    df: &chirpstack_api::gw::DownlinkFrame,
+) -> Result<()> {
+    send(region_config_id, &df.gateway_id, "downlink", df, |b, df| {
+        b.send_downlink(df)
+    })
+    .await
 }
 
 pub async fn send_configuration(
     region_config_id: &str,
     gw_conf: &chirpstack_api::gw::GatewayConfiguration,
 ) -> Result<()> {
-    let b_r = BACKENDS.read().await;
-    let b = b_r.get(region_config_id).ok_or_else(|| {
-        anyhow!(
-            "region_config_id '{}' does not exist in BACKENDS",
-            region_config_id
+    send(
+        region_config_id,
+        &gw_conf.gateway_id,
+        "gateway configuration",
+        gw_conf,
+        |b, gw_conf| b.send_configuration(gw_conf),
+    )
+    .await
+}
+
+pub async fn send_command_exec(
+    region_config_id: &str,
+    pl: &chirpstack_api::gw::GatewayCommandExecRequest,
+) -> Result<()> {
+    send(
+        region_config_id,
+        &pl.gateway_id,
+        "command execution request",
+        pl,
+        |b, pl| b.send_command_exec(pl),
+    )
+    .await
+}
+
+pub async fn send_raw_packet_forwarder_command(
+    region_config_id: &str,
+    pl: &chirpstack_api::gw::RawPacketForwarderCommand,
+) -> Result<()> {
+    send(
+        region_config_id,
+        &pl.gateway_id,
+        "raw packet-forwarder command",
+        pl,
+        |b, pl| b.send_raw_packet_forwarder_command(pl),
+    )
+    .await
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::test;
+    use chirpstack_api::gw;
+
+    // Backend which records the downlinks it was able to send.
+    struct TestBackend {
+        fail: bool,
+        downlinks: Arc<RwLock<Vec<u32>>>,
+    }
+
+    #[async_trait]
+    impl GatewayBackend for TestBackend {
+        async fn send_downlink(&self, df: &gw::DownlinkFrame) -> Result<()> {
+            if self.fail {
+                return Err(anyhow!("Publish error"));
+            }
+            self.downlinks.write().await.push(df.downlink_id);
+            Ok(())
+        }
+
+        async fn send_configuration(&self, _gw_conf: &gw::GatewayConfiguration) -> Result<()> {
+            Ok(())
+        }
+
+        async fn send_command_exec(&self, _pl: &gw::GatewayCommandExecRequest) -> Result<()> {
+            Ok(())
+        }
+
+        async fn send_raw_packet_forwarder_command(
+            &self,
+            _pl: &gw::RawPacketForwarderCommand,
+        ) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backend_routing() {
+        let _guard = test::prepare().await;
+
+        let downlinks: Vec<Arc<RwLock<Vec<u32>>>> =
+            (0..3).map(|_| Arc::new(RwLock::new(Vec::new()))).collect();
+        set_backends(
+            "test_routing",
+            vec![
+                Box::new(TestBackend {
+                    fail: false,
+                    downlinks: downlinks[0].clone(),
+                }),
+                Box::new(TestBackend {
+                    fail: false,
+                    downlinks: downlinks[1].clone(),
+                }),
+                Box::new(TestBackend {
+                    fail: true,
+                    downlinks: downlinks[2].clone(),
+                }),
+            ],
         )
-    })?;
+        .await;
 
-    b.send_configuration(gw_conf).await?;
+        let df = |downlink_id: u32| gw::DownlinkFrame {
+            gateway_id: "0102030405060708".into(),
+            downlink_id,
+            ..Default::default()
+        };
 
-    Ok(())
+        // unknown gateway, the first backend is used
+        send_downlink("test_routing", &df(1)).await.unwrap();
+
+        // uplink was received through the second backend
+        set_gateway_backend("test_routing".into(), "0102030405060708".into(), 1).await;
+        send_downlink("test_routing", &df(2)).await.unwrap();
+
+        // uplink was received through the third backend, which fails to publish
+        set_gateway_backend("test_routing".into(), "0102030405060708".into(), 2).await;
+        send_downlink("test_routing", &df(3)).await.unwrap();
+
+        assert_eq!(vec![1, 3], *downlinks[0].read().await);
+        assert_eq!(vec![2], *downlinks[1].read().await);
+        assert!(downlinks[2].read().await.is_empty());
+
+        // the backend which was used for the failover does not overwrite the route
+        assert_eq!(
+            vec![2, 0, 1],
+            get_backend_order("test_routing", "0102030405060708", 3).await
+        );
+
+        // unknown gateway
+        assert_eq!(
+            vec![0, 1, 2],
+            get_backend_order("test_routing", "0807060504030201", 3).await
+        );
+
+        // the stored backend is only updated when it changes
+        let key = redis_key("gw:{0102030405060708}:backend:test_routing".to_string());
+        redis::cmd("DEL")
+            .arg(&key)
+            .query_async::<()>(&mut get_async_redis_conn().await.unwrap())
+            .await
+            .unwrap();
+        set_gateway_backend("test_routing".into(), "0102030405060708".into(), 2).await;
+        assert_eq!(
+            None,
+            get_gateway_backend("test_routing", "0102030405060708")
+                .await
+                .unwrap()
+        );
+        set_gateway_backend("test_routing".into(), "0102030405060708".into(), 1).await;
+        assert_eq!(
+            Some(1),
+            get_gateway_backend("test_routing", "0102030405060708")
+                .await
+                .unwrap()
+        );
+
+        // all backends fail
+        set_backends(
+            "test_routing",
+            vec![Box::new(TestBackend {
+                fail: true,
+                downlinks: downlinks[2].clone(),
+            })],
+        )
+        .await;
+        assert!(send_downlink("test_routing", &df(4)).await.is_err());
+
+        // the backend is not stored for a region with a single backend
+        set_gateway_backend("test_routing".into(), "0807060504030201".into(), 0).await;
+        assert_eq!(
+            None,
+            get_gateway_backend("test_routing", "0807060504030201")
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            vec![0],
+            get_backend_order("test_routing", "0807060504030201", 1).await
+        );
+    }
 }
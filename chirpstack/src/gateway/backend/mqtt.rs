@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::io::Cursor;
-use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
 use anyhow::Result;
@@ -23,6 +24,7 @@ use tracing::{error, info, trace};
 
 use super::GatewayBackend;
 use crate::config::GatewayBackendMqtt;
+use crate::gateway::command_exec;
 use crate::helpers::tls22::{get_root_certs, load_cert, load_key};
 use crate::monitoring::prometheus;
 use crate::{downlink, uplink};
@@ -66,6 +68,7 @@ pub struct MqttBackend<'a> {
     qos: QoS,
     v4_migrate: bool,
     region_config_id: String,
+    connected: Arc<AtomicBool>,
 }
 
 #[derive(Serialize)]
@@ -77,6 +80,7 @@ struct CommandTopicContext {
 impl<'a> MqttBackend<'a> {
     pub async fn new(
         region_config_id: &str,
+        backend_id: usize,
         region_common_name: CommonName,
         conf: &GatewayBackendMqtt,
     ) -> Result<MqttBackend<'a>> {
@@ -165,6 +169,7 @@ impl<'a> MqttBackend<'a> {
             templates,
             v4_migrate: conf.v4_migrate,
             region_config_id: region_config_id.to_string(),
+            connected: Arc::new(AtomicBool::new(false)),
         };
 
         // connect
@@ -185,6 +190,16 @@ impl<'a> MqttBackend<'a> {
             } else {
                 conf.event_topic.clone()
             };
+            let state_topic = if conf.state_topic.is_empty() {
+                let state_topic = "gateway/+/state/+".to_string();
+                if conf.topic_prefix.is_empty() {
+                    state_topic
+                } else {
+                    format!("{}/{}", conf.topic_prefix, state_topic)
+                }
+            } else {
+                conf.state_topic.clone()
+            };
             let share_name = conf.share_name.clone();
 
             async move {
@@ -199,6 +214,14 @@ impl<'a> MqttBackend<'a> {
                     if let Err(e) = client.subscribe(&event_topic, qos).await {
                         error!(region_id = %region_config_id, event_topic = %event_topic, error = %e, "MQTT subscribe error");
                     }
+
+                    // The state messages are published as retained messages. As retained
+                    // messages are not delivered to shared subscriptions, this topic is always
+                    // subscribed to without a share name.
+                    info!(region_id = %region_config_id, state_topic = %state_topic, "Subscribing to gateway state topic");
+                    if let Err(e) = client.subscribe(&state_topic, qos).await {
+                        error!(region_id = %region_config_id, state_topic = %state_topic, error = %e, "MQTT subscribe error");
+                    }
                 }
             }
         });
@@ -207,6 +230,7 @@ impl<'a> MqttBackend<'a> {
         tokio::spawn({
             let region_config_id = region_config_id.to_string();
             let v4_migrate = conf.v4_migrate;
+            let connected = b.connected.clone();
 
             async move {
                 info!("Starting MQTT event loop");
@@ -221,6 +245,7 @@ impl<'a> MqttBackend<'a> {
                                     message_callback(
                                         v4_migrate,
                                         &region_config_id,
+                                        backend_id,
                                         region_common_name,
                                         p,
                                     )
@@ -228,6 +253,8 @@ impl<'a> MqttBackend<'a> {
                                 }
                                 Event::Incoming(Incoming::ConnAck(v)) => {
                                     if v.code == ConnectReturnCode::Success {
+                                        connected.store(true, Ordering::Relaxed);
+
                                         // Per specification:
                                         // A value of 1 means Shared Subscriptions are supported. If not present, then Shared Subscriptions are supported.
                                         let shared_sub_support = v
@@ -251,6 +278,7 @@ impl<'a> MqttBackend<'a> {
                             }
                         }
                         Err(e) => {
+                            connected.store(false, Ordering::Relaxed);
                             error!(error = %e, "MQTT error");
                             sleep(Duration::from_secs(1)).await
                         }
@@ -263,6 +291,16 @@ impl<'a> MqttBackend<'a> {
         Ok(b)
     }
 
+    // Returns an error when the client is not connected to the MQTT broker. Publishing while
+    // disconnected would queue the message, which prevents failing over to another backend.
+    fn ensure_connected(&self) -> Result<()> {
+        if !self.connected.load(Ordering::Relaxed) {
+            return Err(anyhow!("Not connected to MQTT broker"));
+        }
+
+        Ok(())
+    }
+
     fn get_command_topic(&self, gateway_id: &str, command: &str) -> Result<String> {
         Ok(self.templates.render(
             "command_topic",
@@ -296,6 +334,7 @@ impl GatewayBackend for MqttBackend<'_> {
         };
 
         info!(region_id = %self.region_config_id, gateway_id = %df.gateway_id, topic = %topic, json = json, "Sending downlink frame");
+        self.ensure_connected()?;
         self.client.publish(topic, self.qos, false, b).await?;
         trace!("Message published");
 
@@ -319,6 +358,67 @@ impl GatewayBackend for MqttBackend<'_> {
         };
 
         info!(region_id = %self.region_config_id, gateway_id = %gw_conf.gateway_id, topic = %topic, json = json, "Sending gateway configuration");
+        self.ensure_connected()?;
+        self.client.publish(topic, self.qos, false, b).await?;
+        trace!("Message published");
+
+        Ok(())
+    }
+
+    async fn send_command_exec(
+        &self,
+        pl: &chirpstack_api::gw::GatewayCommandExecRequest,
+    ) -> Result<()> {
+        COMMAND_COUNTER
+            .get_or_create(&CommandLabels {
+                command: "exec".to_string(),
+            })
+            .inc();
+        let topic = self.get_command_topic(&pl.gateway_id, "exec")?;
+        let mut pl = pl.clone();
+
+        if self.v4_migrate {
+            pl.v4_migrate();
+        }
+
+        let json = gateway_is_json(&pl.gateway_id);
+        let b = match json {
+            true => serde_json::to_vec(&pl)?,
+            false => pl.encode_to_vec(),
+        };
+
+        info!(region_id = %self.region_config_id, gateway_id = %pl.gateway_id, exec_id = pl.exec_id, topic = %topic, json = json, "Sending gateway command execution request");
+        self.ensure_connected()?;
+        self.client.publish(topic, self.qos, false, b).await?;
+        trace!("Message published");
+
+        Ok(())
+    }
+
+    async fn send_raw_packet_forwarder_command(
+        &self,
+        pl: &chirpstack_api::gw::RawPacketForwarderCommand,
+    ) -> Result<()> {
+        COMMAND_COUNTER
+            .get_or_create(&CommandLabels {
+                command: "raw".to_string(),
+            })
+            .inc();
+        let topic = self.get_command_topic(&pl.gateway_id, "raw")?;
+        let mut pl = pl.clone();
+
+        if self.v4_migrate {
+            pl.v4_migrate();
+        }
+
+        let json = gateway_is_json(&pl.gateway_id);
+        let b = match json {
+            true => serde_json::to_vec(&pl)?,
+            false => pl.encode_to_vec(),
+        };
+
+        info!(region_id = %self.region_config_id, gateway_id = %pl.gateway_id, topic = %topic, json = json, "Sending raw packet-forwarder command");
+        self.ensure_connected()?;
         self.client.publish(topic, self.qos, false, b).await?;
         trace!("Message published");
 
@@ -329,6 +429,7 @@ impl GatewayBackend for MqttBackend<'_> {
 async fn message_callback(
     v4_migrate: bool,
     region_config_id: &str,
+    backend_id: usize,
     region_common_name: CommonName,
     p: Publish,
 ) {
@@ -362,6 +463,11 @@ async fn message_callback(
 
             if let Some(rx_info) = &mut event.rx_info {
                 set_gateway_json(&rx_info.gateway_id, json);
+                tokio::spawn(super::set_gateway_backend(
+                    region_config_id.to_string(),
+                    rx_info.gateway_id.clone(),
+                    backend_id,
+                ));
                 rx_info.ns_time = Some(Utc::now().into());
                 rx_info
                     .metadata
@@ -396,6 +502,11 @@ async fn message_callback(
                 region_common_name.to_string(),
             );
             set_gateway_json(&event.gateway_id, json);
+            tokio::spawn(super::set_gateway_backend(
+                region_config_id.to_string(),
+                event.gateway_id.clone(),
+                backend_id,
+            ));
             tokio::spawn(uplink::stats::Stats::handle(event));
         } else if topic.ends_with("/ack") {
             EVENT_COUNTER
@@ -426,6 +537,66 @@ async fn message_callback(
             };
 
             tokio::spawn(uplink::mesh::MeshHeartbeat::handle(event));
+        } else if topic.ends_with("/exec") {
+            EVENT_COUNTER
+                .get_or_create(&EventLabels {
+                    event: "exec".to_string(),
+                })
+                .inc();
+            let mut event = match json {
+                true => serde_json::from_slice(&p.payload)?,
+                false => chirpstack_api::gw::GatewayCommandExecResponse::decode(&mut Cursor::new(
+                    &p.payload,
+                ))?,
+            };
+
+            if v4_migrate {
+                event.v4_migrate();
+            }
+
+            set_gateway_json(&event.gateway_id, json);
+            tokio::spawn(command_exec::handle_response(event));
+        } else if topic.ends_with("/raw") {
+            EVENT_COUNTER
+                .get_or_create(&EventLabels {
+                    event: "raw".to_string(),
+                })
+                .inc();
+            let mut event = match json {
+                true => serde_json::from_slice(&p.payload)?,
+                false => chirpstack_api::gw::RawPacketForwarderEvent::decode(&mut Cursor::new(
+                    &p.payload,
+                ))?,
+            };
+
+            if v4_migrate {
+                event.v4_migrate();
+            }
+
+            set_gateway_json(&event.gateway_id, json);
+            tokio::spawn(uplink::raw::RawPacketForwarderEvent::handle(event));
+        } else if topic.ends_with("/conn") {
+            // An empty payload is used to remove the retained message.
+            if p.payload.is_empty() {
+                return Ok(());
+            }
+
+            EVENT_COUNTER
+                .get_or_create(&EventLabels {
+                    event: "conn".to_string(),
+                })
+                .inc();
+            let mut event = match json {
+                true => serde_json::from_slice(&p.payload)?,
+                false => chirpstack_api::gw::ConnState::decode(&mut Cursor::new(&p.payload))?,
+            };
+
+            if v4_migrate {
+                event.v4_migrate();
+            }
+
+            set_gateway_json(&event.gateway_id, json);
+            tokio::spawn(uplink::conn_state::ConnState::handle(event));
         } else {
             return Err(anyhow!("Unknown event type"));
         }
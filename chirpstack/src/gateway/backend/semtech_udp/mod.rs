@@ -0,0 +1,666 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use tokio::net::UdpSocket;
+use tokio::sync::RwLock;
+use tokio::time::sleep;
+use tracing::{debug, error, info, trace, warn};
+
+use super::GatewayBackend;
+use crate::config::GatewayBackendSemtechUdp;
+use crate::helpers::errors::PrintFullError;
+use crate::monitoring::prometheus;
+use crate::{downlink, uplink};
+use chirpstack_api::gw;
+use lrwn::region::CommonName;
+use lrwn::EUI64;
+use structs::{
+    get_gateway_id, Header, PacketType, PullRespPayload, PushDataPayload, TxAckPayload, TxPk,
+    PROTOCOL_VERSION_1,
+};
+
+mod structs;
+
+#[derive(Clone, Hash, PartialEq, Eq, EncodeLabelSet, Debug)]
+struct PacketLabels {
+    packet_type: String,
+}
+
+lazy_static! {
+    static ref EVENT_COUNTER: Family<PacketLabels, Counter> = {
+        let counter = Family::<PacketLabels, Counter>::default();
+        prometheus::register(
+            "gateway_backend_semtech_udp_events",
+            "Number of packets received",
+            counter.clone(),
+        );
+        counter
+    };
+    static ref COMMAND_COUNTER: Family<PacketLabels, Counter> = {
+        let counter = Family::<PacketLabels, Counter>::default();
+        prometheus::register(
+            "gateway_backend_semtech_udp_commands",
+            "Number of packets sent",
+            counter.clone(),
+        );
+        counter
+    };
+}
+
+pub struct SemtechUdpBackend {
+    state: Arc<State>,
+}
+
+struct State {
+    region_config_id: String,
+    backend_id: usize,
+    region_common_name: CommonName,
+    socket: UdpSocket,
+    gateways: RwLock<HashMap<EUI64, GatewayConnection>>,
+    downlinks: RwLock<HashMap<(EUI64, u16), PendingDownlink>>,
+}
+
+// GatewayConnection contains the address to which PULL_RESP packets must be sent. This is the
+// address from which the gateway sent its last PULL_DATA packet.
+#[derive(Clone)]
+struct GatewayConnection {
+    addr: SocketAddr,
+    protocol_version: u8,
+    last_seen: Instant,
+}
+
+// PendingDownlink contains a downlink frame for which a TX_ACK is expected. In case the gateway
+// rejects an item, the next item (e.g. RX2) is sent to the gateway.
+struct PendingDownlink {
+    frame: gw::DownlinkFrame,
+    index: usize,
+    acks: Vec<gw::TxAckStatus>,
+    created_at: Instant,
+}
+
+impl PendingDownlink {
+    fn new(frame: gw::DownlinkFrame) -> Self {
+        PendingDownlink {
+            acks: vec![gw::TxAckStatus::Ignored; frame.items.len()],
+            frame,
+            index: 0,
+            created_at: Instant::now(),
+        }
+    }
+
+    // Sets the status of the current item and returns the next item to send in case the
+    // current item was not accepted by the gateway.
+    fn ack(&mut self, status: gw::TxAckStatus) -> Option<&gw::DownlinkFrameItem> {
+        self.acks[self.index] = status;
+
+        if status != gw::TxAckStatus::Ok && self.index + 1 < self.frame.items.len() {
+            self.index += 1;
+            return self.frame.items.get(self.index);
+        }
+
+        None
+    }
+
+    fn tx_ack(&self) -> gw::DownlinkTxAck {
+        gw::DownlinkTxAck {
+            gateway_id: self.frame.gateway_id.clone(),
+            downlink_id: self.frame.downlink_id,
+            items: self
+                .acks
+                .iter()
+                .map(|s| gw::DownlinkTxAckItem {
+                    status: (*s).into(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl SemtechUdpBackend {
+    pub async fn new(
+        region_config_id: &str,
+        backend_id: usize,
+        region_common_name: CommonName,
+        conf: &GatewayBackendSemtechUdp,
+    ) -> Result<SemtechUdpBackend> {
+        let socket = UdpSocket::bind(&conf.bind)
+            .await
+            .context("Bind UDP socket")?;
+
+        info!(region_id = %region_config_id, bind = %conf.bind, "Semtech UDP gateway backend listening");
+
+        let state = Arc::new(State {
+            region_config_id: region_config_id.to_string(),
+            backend_id,
+            region_common_name,
+            socket,
+            gateways: RwLock::new(HashMap::new()),
+            downlinks: RwLock::new(HashMap::new()),
+        });
+
+        // Receive loop
+        tokio::spawn({
+            let state = state.clone();
+
+            async move {
+                let mut buf = vec![0; 65535];
+
+                loop {
+                    match state.socket.recv_from(&mut buf).await {
+                        Ok((size, addr)) => {
+                            if let Err(e) = handle_packet(&state, addr, &buf[..size]).await {
+                                error!(region_id = %state.region_config_id, addr = %addr, error = %e.full(), "Handle UDP packet error");
+                            }
+                        }
+                        Err(e) => {
+                            error!(region_id = %state.region_config_id, error = %e, "Receive UDP packet error");
+                            sleep(Duration::from_secs(1)).await;
+                        }
+                    }
+                }
+            }
+        });
+
+        // Cleanup loop
+        tokio::spawn({
+            let state = state.clone();
+            let cleanup_duration = conf.cleanup_duration;
+
+            async move {
+                loop {
+                    sleep(cleanup_duration).await;
+                    cleanup(&state, cleanup_duration).await;
+                }
+            }
+        });
+
+        Ok(SemtechUdpBackend { state })
+    }
+
+    #[cfg(test)]
+    fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.state.socket.local_addr()?)
+    }
+}
+
+#[async_trait]
+impl GatewayBackend for SemtechUdpBackend {
+    async fn send_downlink(&self, df: &gw::DownlinkFrame) -> Result<()> {
+        let gateway_id: EUI64 = df.gateway_id.parse().context("Gateway ID")?;
+        let conn = self
+            .state
+            .gateways
+            .read()
+            .await
+            .get(&gateway_id)
+            .cloned()
+            .ok_or_else(|| {
+                anyhow!(
+                    "Gateway {} is not connected, no PULL_DATA received",
+                    gateway_id
+                )
+            })?;
+        let item = df
+            .items
+            .first()
+            .ok_or_else(|| anyhow!("Downlink frame does not contain any items"))?;
+        let token: u16 = rand::random();
+
+        info!(region_id = %self.state.region_config_id, gateway_id = %gateway_id, downlink_id = df.downlink_id, addr = %conn.addr, "Sending downlink frame");
+
+        // Protocol version 1 does not implement the TX_ACK packet.
+        if conn.protocol_version == PROTOCOL_VERSION_1 {
+            send_pull_resp(&self.state, &conn, token, item).await?;
+
+            let mut pending = PendingDownlink::new(df.clone());
+            pending.ack(gw::TxAckStatus::Ok);
+            tokio::spawn(downlink::tx_ack::TxAck::handle(pending.tx_ack()));
+
+            return Ok(());
+        }
+
+        // The pending downlink is stored before sending, as the TX_ACK could be received
+        // before send_pull_resp returns.
+        self.state
+            .downlinks
+            .write()
+            .await
+            .insert((gateway_id, token), PendingDownlink::new(df.clone()));
+
+        if let Err(e) = send_pull_resp(&self.state, &conn, token, item).await {
+            self.state
+                .downlinks
+                .write()
+                .await
+                .remove(&(gateway_id, token));
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    async fn send_configuration(&self, gw_conf: &gw::GatewayConfiguration) -> Result<()> {
+        // The channel-plan of the Semtech UDP packet-forwarder is configured on the gateway.
+        debug!(region_id = %self.state.region_config_id, gateway_id = %gw_conf.gateway_id, "Gateway configuration is not supported by the Semtech UDP backend, skipping");
+        Ok(())
+    }
+
+    async fn send_command_exec(&self, _pl: &gw::GatewayCommandExecRequest) -> Result<()> {
+        Err(anyhow!(
+            "Command execution is not supported by the Semtech UDP backend"
+        ))
+    }
+
+    async fn send_raw_packet_forwarder_command(
+        &self,
+        _pl: &gw::RawPacketForwarderCommand,
+    ) -> Result<()> {
+        Err(anyhow!(
+            "Raw packet-forwarder commands are not supported by the Semtech UDP backend"
+        ))
+    }
+}
+
+async fn handle_packet(state: &State, addr: SocketAddr, b: &[u8]) -> Result<()> {
+    let header = Header::from_slice(b)?;
+
+    EVENT_COUNTER
+        .get_or_create(&PacketLabels {
+            packet_type: format!("{:?}", header.packet_type),
+        })
+        .inc();
+
+    match header.packet_type {
+        PacketType::PushData => handle_push_data(state, addr, &header, b).await,
+        PacketType::PullData => handle_pull_data(state, addr, &header, b).await,
+        PacketType::TxAck => handle_tx_ack(state, &header, b).await,
+        _ => Err(anyhow!("Unexpected packet type: {:?}", header.packet_type)),
+    }
+}
+
+async fn handle_push_data(
+    state: &State,
+    addr: SocketAddr,
+    header: &Header,
+    b: &[u8],
+) -> Result<()> {
+    let gateway_id = get_gateway_id(b)?;
+
+    info!(region_id = %state.region_config_id, gateway_id = %gateway_id, addr = %addr, "PUSH_DATA received from gateway");
+
+    send_ack(state, addr, header, PacketType::PushAck).await?;
+
+    let pl = PushDataPayload::from_slice(b)?;
+
+    if !pl.rxpk.is_empty() || pl.stat.is_some() {
+        super::set_gateway_backend(
+            state.region_config_id.clone(),
+            gateway_id.to_string(),
+            state.backend_id,
+        )
+        .await;
+    }
+
+    for rxpk in &pl.rxpk {
+        if rxpk.stat != 1 {
+            debug!(gateway_id = %gateway_id, "Skipping uplink without valid CRC");
+            continue;
+        }
+
+        let mut event = match rxpk.to_uplink_frame(gateway_id) {
+            Ok(v) => v,
+            Err(e) => {
+                warn!(gateway_id = %gateway_id, error = %e.full(), "Decode rxpk error");
+                continue;
+            }
+        };
+
+        if let Some(rx_info) = &mut event.rx_info {
+            rx_info.ns_time = Some(Utc::now().into());
+            rx_info.metadata.insert(
+                "region_config_id".to_string(),
+                state.region_config_id.clone(),
+            );
+            rx_info.metadata.insert(
+                "region_common_name".to_string(),
+                state.region_common_name.to_string(),
+            );
+        }
+
+        tokio::spawn(uplink::deduplicate_uplink(event));
+    }
+
+    if let Some(stat) = &pl.stat {
+        let mut event = stat.to_gateway_stats(gateway_id)?;
+        event.metadata.insert(
+            "region_config_id".to_string(),
+            state.region_config_id.clone(),
+        );
+        event.metadata.insert(
+            "region_common_name".to_string(),
+            state.region_common_name.to_string(),
+        );
+
+        tokio::spawn(uplink::stats::Stats::handle(event));
+    }
+
+    Ok(())
+}
+
+async fn handle_pull_data(
+    state: &State,
+    addr: SocketAddr,
+    header: &Header,
+    b: &[u8],
+) -> Result<()> {
+    let gateway_id = get_gateway_id(b)?;
+
+    trace!(region_id = %state.region_config_id, gateway_id = %gateway_id, addr = %addr, "PULL_DATA received from gateway");
+
+    send_ack(state, addr, header, PacketType::PullAck).await?;
+
+    let prev = state.gateways.write().await.insert(
+        gateway_id,
+        GatewayConnection {
+            addr,
+            protocol_version: header.protocol_version,
+            last_seen: Instant::now(),
+        },
+    );
+
+    if prev.is_none() {
+        info!(region_id = %state.region_config_id, gateway_id = %gateway_id, addr = %addr, "Gateway connected");
+        tokio::spawn(uplink::conn_state::ConnState::handle(gw::ConnState {
+            gateway_id: gateway_id.to_string(),
+            state: gw::conn_state::State::Online.into(),
+            ..Default::default()
+        }));
+    }
+
+    Ok(())
+}
+
+async fn handle_tx_ack(state: &State, header: &Header, b: &[u8]) -> Result<()> {
+    let gateway_id = get_gateway_id(b)?;
+    let status = TxAckPayload::from_slice(b)?.get_status();
+
+    info!(region_id = %state.region_config_id, gateway_id = %gateway_id, status = ?status, "TX_ACK received from gateway");
+
+    let key = (gateway_id, header.random_token);
+    let mut pending = state
+        .downlinks
+        .write()
+        .await
+        .remove(&key)
+        .ok_or_else(|| anyhow!("No pending downlink for token {}", header.random_token))?;
+
+    if let Some(item) = pending.ack(status).cloned() {
+        let conn = state
+            .gateways
+            .read()
+            .await
+            .get(&gateway_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Gateway {} is not connected", gateway_id))?;
+
+        info!(gateway_id = %gateway_id, downlink_id = pending.frame.downlink_id, "Downlink item rejected, sending next item");
+
+        state.downlinks.write().await.insert(key, pending);
+        send_pull_resp(state, &conn, header.random_token, &item).await?;
+
+        return Ok(());
+    }
+
+    tokio::spawn(downlink::tx_ack::TxAck::handle(pending.tx_ack()));
+
+    Ok(())
+}
+
+async fn send_ack(
+    state: &State,
+    addr: SocketAddr,
+    header: &Header,
+    packet_type: PacketType,
+) -> Result<()> {
+    let b = Header {
+        protocol_version: header.protocol_version,
+        random_token: header.random_token,
+        packet_type,
+    }
+    .to_vec();
+
+    state.socket.send_to(&b, addr).await?;
+
+    Ok(())
+}
+
+async fn send_pull_resp(
+    state: &State,
+    conn: &GatewayConnection,
+    token: u16,
+    item: &gw::DownlinkFrameItem,
+) -> Result<()> {
+    COMMAND_COUNTER
+        .get_or_create(&PacketLabels {
+            packet_type: format!("{:?}", PacketType::PullResp),
+        })
+        .inc();
+
+    let mut b = Header {
+        protocol_version: conn.protocol_version,
+        random_token: token,
+        packet_type: PacketType::PullResp,
+    }
+    .to_vec();
+    b.extend_from_slice(&serde_json::to_vec(&PullRespPayload {
+        txpk: TxPk::from_downlink_frame_item(item)?,
+    })?);
+
+    state.socket.send_to(&b, conn.addr).await?;
+    trace!("PULL_RESP sent");
+
+    Ok(())
+}
+
+// Removes the gateways from which no PULL_DATA has been received and the downlinks for which no
+// TX_ACK has been received within the cleanup duration.
+async fn cleanup(state: &State, cleanup_duration: Duration) {
+    let mut removed: Vec<EUI64> = Vec::new();
+
+    state.gateways.write().await.retain(|gateway_id, conn| {
+        if conn.last_seen.elapsed() > cleanup_duration {
+            removed.push(*gateway_id);
+            return false;
+        }
+        true
+    });
+
+    state
+        .downlinks
+        .write()
+        .await
+        .retain(|_, pending| pending.created_at.elapsed() <= cleanup_duration);
+
+    for gateway_id in removed {
+        info!(region_id = %state.region_config_id, gateway_id = %gateway_id, "Gateway disconnected");
+        tokio::spawn(uplink::conn_state::ConnState::handle(gw::ConnState {
+            gateway_id: gateway_id.to_string(),
+            state: gw::conn_state::State::Offline.into(),
+            ..Default::default()
+        }));
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::test;
+
+    async fn recv(socket: &UdpSocket) -> Vec<u8> {
+        let mut buf = vec![0; 65535];
+        let size = tokio::time::timeout(Duration::from_secs(1), socket.recv(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        buf.truncate(size);
+        buf
+    }
+
+    #[tokio::test]
+    async fn test_semtech_udp() {
+        let _guard = test::prepare().await;
+
+        let backend = SemtechUdpBackend::new(
+            "eu868",
+            0,
+            CommonName::EU868,
+            &GatewayBackendSemtechUdp {
+                bind: "127.0.0.1:0".into(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let gateway_id = EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]);
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        socket.connect(backend.local_addr().unwrap()).await.unwrap();
+
+        let df = gw::DownlinkFrame {
+            gateway_id: gateway_id.to_string(),
+            downlink_id: 123,
+            items: [868100000, 869525000]
+                .iter()
+                .map(|f| gw::DownlinkFrameItem {
+                    phy_payload: vec![1, 2, 3],
+                    tx_info: Some(gw::DownlinkTxInfo {
+                        frequency: *f,
+                        power: 14,
+                        modulation: Some(gw::Modulation {
+                            parameters: Some(gw::modulation::Parameters::Lora(
+                                gw::LoraModulationInfo {
+                                    bandwidth: 125000,
+                                    spreading_factor: 12,
+                                    code_rate: gw::CodeRate::Cr45.into(),
+                                    polarization_inversion: true,
+                                    ..Default::default()
+                                },
+                            )),
+                        }),
+                        timing: Some(gw::Timing {
+                            parameters: Some(gw::timing::Parameters::Immediately(
+                                gw::ImmediatelyTimingInfo {},
+                            )),
+                        }),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        // the gateway did not send a PULL_DATA yet
+        assert!(backend.send_downlink(&df).await.is_err());
+
+        // PULL_DATA
+        let mut b = vec![0x02, 0x01, 0x02, 0x02];
+        b.extend_from_slice(&gateway_id.to_be_bytes());
+        socket.send(&b).await.unwrap();
+        assert_eq!(vec![0x02, 0x01, 0x02, 0x04], recv(&socket).await);
+
+        // PUSH_DATA
+        let mut b = vec![0x02, 0x03, 0x04, 0x00];
+        b.extend_from_slice(&gateway_id.to_be_bytes());
+        b.extend_from_slice(b"{}");
+        socket.send(&b).await.unwrap();
+        assert_eq!(vec![0x02, 0x03, 0x04, 0x01], recv(&socket).await);
+
+        // downlink
+        backend.send_downlink(&df).await.unwrap();
+        let b = recv(&socket).await;
+        let header = Header::from_slice(&b).unwrap();
+        assert_eq!(PacketType::PullResp, header.packet_type);
+        let pl: serde_json::Value = serde_json::from_slice(&b[4..]).unwrap();
+        assert_eq!(868.1, pl["txpk"]["freq"]);
+        assert_eq!(true, pl["txpk"]["imme"]);
+
+        // the first item is rejected, the second item must be sent using the same token
+        let mut b = Header {
+            protocol_version: 0x02,
+            random_token: header.random_token,
+            packet_type: PacketType::TxAck,
+        }
+        .to_vec();
+        b.extend_from_slice(&gateway_id.to_be_bytes());
+        b.extend_from_slice(br#"{"txpk_ack":{"error":"TX_FREQ"}}"#);
+        socket.send(&b).await.unwrap();
+
+        let b = recv(&socket).await;
+        let header_2 = Header::from_slice(&b).unwrap();
+        assert_eq!(PacketType::PullResp, header_2.packet_type);
+        assert_eq!(header.random_token, header_2.random_token);
+        let pl: serde_json::Value = serde_json::from_slice(&b[4..]).unwrap();
+        assert_eq!(869.525, pl["txpk"]["freq"]);
+
+        {
+            let downlinks = backend.state.downlinks.read().await;
+            let pending = downlinks.get(&(gateway_id, header.random_token)).unwrap();
+            assert_eq!(
+                vec![gw::TxAckStatus::TxFreq, gw::TxAckStatus::Ignored],
+                pending.acks
+            );
+        }
+
+        // the second item is accepted
+        let mut b = Header {
+            protocol_version: 0x02,
+            random_token: header.random_token,
+            packet_type: PacketType::TxAck,
+        }
+        .to_vec();
+        b.extend_from_slice(&gateway_id.to_be_bytes());
+        socket.send(&b).await.unwrap();
+
+        sleep(Duration::from_millis(100)).await;
+        assert!(backend.state.downlinks.read().await.is_empty());
+    }
+
+    #[test]
+    fn test_pending_downlink() {
+        let mut pending = PendingDownlink::new(gw::DownlinkFrame {
+            gateway_id: "0102030405060708".into(),
+            downlink_id: 123,
+            items: vec![Default::default(), Default::default()],
+            ..Default::default()
+        });
+
+        assert!(pending.ack(gw::TxAckStatus::TooLate).is_some());
+        assert!(pending.ack(gw::TxAckStatus::TooLate).is_none());
+        assert_eq!(
+            gw::DownlinkTxAck {
+                gateway_id: "0102030405060708".into(),
+                downlink_id: 123,
+                items: vec![
+                    gw::DownlinkTxAckItem {
+                        status: gw::TxAckStatus::TooLate.into(),
+                    },
+                    gw::DownlinkTxAckItem {
+                        status: gw::TxAckStatus::TooLate.into(),
+                    },
+                ],
+                ..Default::default()
+            },
+            pending.tx_ack()
+        );
+    }
+}
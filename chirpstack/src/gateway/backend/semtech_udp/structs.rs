@@ -0,0 +1,634 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use chirpstack_api::{common, gw};
+use lrwn::EUI64;
+
+pub const PROTOCOL_VERSION_1: u8 = 0x01;
+pub const PROTOCOL_VERSION_2: u8 = 0x02;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketType {
+    PushData,
+    PushAck,
+    PullData,
+    PullResp,
+    PullAck,
+    TxAck,
+}
+
+impl TryFrom<u8> for PacketType {
+    type Error = anyhow::Error;
+
+    fn try_from(v: u8) -> Result<Self> {
+        Ok(match v {
+            0x00 => PacketType::PushData,
+            0x01 => PacketType::PushAck,
+            0x02 => PacketType::PullData,
+            0x03 => PacketType::PullResp,
+            0x04 => PacketType::PullAck,
+            0x05 => PacketType::TxAck,
+            _ => return Err(anyhow!("Invalid packet type: {}", v)),
+        })
+    }
+}
+
+impl From<PacketType> for u8 {
+    fn from(v: PacketType) -> u8 {
+        match v {
+            PacketType::PushData => 0x00,
+            PacketType::PushAck => 0x01,
+            PacketType::PullData => 0x02,
+            PacketType::PullResp => 0x03,
+            PacketType::PullAck => 0x04,
+            PacketType::TxAck => 0x05,
+        }
+    }
+}
+
+// Header contains the fields which are common to all packet types.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub protocol_version: u8,
+    pub random_token: u16,
+    pub packet_type: PacketType,
+}
+
+impl Header {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        if b.len() < 4 {
+            return Err(anyhow!("At least 4 bytes are expected"));
+        }
+
+        if b[0] != PROTOCOL_VERSION_1 && b[0] != PROTOCOL_VERSION_2 {
+            return Err(anyhow!("Unexpected protocol version: {}", b[0]));
+        }
+
+        Ok(Header {
+            protocol_version: b[0],
+            random_token: u16::from_be_bytes([b[1], b[2]]),
+            packet_type: PacketType::try_from(b[3])?,
+        })
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut b = vec![self.protocol_version];
+        b.extend_from_slice(&self.random_token.to_be_bytes());
+        b.push(self.packet_type.into());
+        b
+    }
+}
+
+// Returns the gateway ID of PUSH_DATA, PULL_DATA and TX_ACK packets.
+pub fn get_gateway_id(b: &[u8]) -> Result<EUI64> {
+    if b.len() < 12 {
+        return Err(anyhow!("At least 12 bytes are expected"));
+    }
+
+    Ok(EUI64::from_slice(&b[4..12])?)
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct PushDataPayload {
+    pub rxpk: Vec<RxPk>,
+    pub stat: Option<Stat>,
+}
+
+impl PushDataPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        if b.len() <= 12 {
+            return Ok(Default::default());
+        }
+
+        serde_json::from_slice(&b[12..]).context("Decode PUSH_DATA payload")
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum DataRate {
+    Lora(String),
+    Fsk(u32),
+}
+
+impl Default for DataRate {
+    fn default() -> Self {
+        DataRate::Lora("".into())
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct RxPk {
+    pub time: Option<String>,
+    pub tmms: Option<u64>,
+    pub tmst: u32,
+    pub chan: u32,
+    pub rfch: u32,
+    pub brd: u32,
+    pub ant: u32,
+    pub freq: f64,
+    pub stat: i8,
+    pub modu: String,
+    pub datr: DataRate,
+    pub codr: String,
+    pub rssi: i32,
+    pub lsnr: f32,
+    pub size: usize,
+    pub data: String,
+}
+
+impl RxPk {
+    pub fn to_uplink_frame(&self, gateway_id: EUI64) -> Result<gw::UplinkFrame> {
+        let phy_payload = general_purpose::STANDARD
+            .decode(&self.data)
+            .context("Decode rxpk data")?;
+
+        Ok(gw::UplinkFrame {
+            phy_payload,
+            tx_info: Some(gw::UplinkTxInfo {
+                frequency: mhz_to_hz(self.freq),
+                modulation: Some(get_modulation(&self.modu, &self.datr, &self.codr, false)?),
+            }),
+            rx_info: Some(gw::UplinkRxInfo {
+                gateway_id: gateway_id.to_string(),
+                uplink_id: rand::random(),
+                gw_time: match &self.time {
+                    Some(v) => Some(
+                        DateTime::parse_from_rfc3339(v)
+                            .context("Parse rxpk time")?
+                            .with_timezone(&Utc)
+                            .into(),
+                    ),
+                    None => None,
+                },
+                time_since_gps_epoch: self.tmms.map(|v| Duration::from_millis(v).into()),
+                rssi: self.rssi,
+                snr: self.lsnr,
+                channel: self.chan,
+                rf_chain: self.rfch,
+                board: self.brd,
+                antenna: self.ant,
+                context: self.tmst.to_be_bytes().to_vec(),
+                crc_status: match self.stat {
+                    1 => gw::CrcStatus::CrcOk,
+                    -1 => gw::CrcStatus::BadCrc,
+                    _ => gw::CrcStatus::NoCrc,
+                }
+                .into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Stat {
+    pub time: String,
+    pub lati: Option<f64>,
+    pub long: Option<f64>,
+    pub alti: Option<i32>,
+    pub rxnb: u32,
+    pub rxok: u32,
+    pub rxfw: u32,
+    pub ackr: f32,
+    pub dwnb: u32,
+    pub txnb: u32,
+}
+
+impl Stat {
+    pub fn to_gateway_stats(&self, gateway_id: EUI64) -> Result<gw::GatewayStats> {
+        // The packet-forwarder uses the "2014-01-12 08:59:28 GMT" format.
+        let time =
+            NaiveDateTime::parse_from_str(self.time.trim_end_matches(" GMT"), "%Y-%m-%d %H:%M:%S")
+                .context("Parse stat time")?
+                .and_utc();
+
+        Ok(gw::GatewayStats {
+            gateway_id: gateway_id.to_string(),
+            time: Some(time.into()),
+            location: match (self.lati, self.long) {
+                (Some(lat), Some(lon)) if lat != 0.0 || lon != 0.0 => Some(common::Location {
+                    latitude: lat,
+                    longitude: lon,
+                    altitude: self.alti.unwrap_or_default().into(),
+                    source: common::LocationSource::Gps.into(),
+                    ..Default::default()
+                }),
+                _ => None,
+            },
+            rx_packets_received: self.rxnb,
+            rx_packets_received_ok: self.rxok,
+            tx_packets_received: self.dwnb,
+            tx_packets_emitted: self.txnb,
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Serialize, Default, Debug, PartialEq)]
+pub struct PullRespPayload {
+    pub txpk: TxPk,
+}
+
+#[derive(Serialize, Default, Debug, PartialEq)]
+pub struct TxPk {
+    pub imme: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tmst: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tmms: Option<u64>,
+    pub freq: f64,
+    pub rfch: u32,
+    pub powe: i32,
+    pub brd: u32,
+    pub ant: u32,
+    pub modu: String,
+    pub datr: DataRate,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub codr: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fdev: Option<u32>,
+    pub ipol: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prea: Option<u32>,
+    pub ncrc: bool,
+    pub size: usize,
+    pub data: String,
+}
+
+impl TxPk {
+    pub fn from_downlink_frame_item(item: &gw::DownlinkFrameItem) -> Result<Self> {
+        let tx_info = item
+            .tx_info
+            .as_ref()
+            .ok_or_else(|| anyhow!("tx_info must not be None"))?;
+
+        let mut txpk = TxPk {
+            freq: tx_info.frequency as f64 / 1_000_000.0,
+            powe: tx_info.power,
+            brd: tx_info.board,
+            ant: tx_info.antenna,
+            size: item.phy_payload.len(),
+            data: general_purpose::STANDARD.encode(&item.phy_payload),
+            ..Default::default()
+        };
+
+        match tx_info
+            .modulation
+            .as_ref()
+            .and_then(|v| v.parameters.as_ref())
+            .ok_or_else(|| anyhow!("modulation parameters must not be None"))?
+        {
+            gw::modulation::Parameters::Lora(v) => {
+                txpk.modu = "LORA".into();
+                txpk.datr =
+                    DataRate::Lora(format!("SF{}BW{}", v.spreading_factor, v.bandwidth / 1000));
+                txpk.codr = v.code_rate().into();
+                txpk.ipol = v.polarization_inversion;
+                txpk.ncrc = v.no_crc;
+                if v.preamble != 0 {
+                    txpk.prea = Some(v.preamble);
+                }
+            }
+            gw::modulation::Parameters::Fsk(v) => {
+                txpk.modu = "FSK".into();
+                txpk.datr = DataRate::Fsk(v.datarate);
+                txpk.fdev = Some(v.frequency_deviation);
+            }
+            gw::modulation::Parameters::LrFhss(_) => {
+                return Err(anyhow!("LR-FHSS modulation is not supported for downlink"));
+            }
+        }
+
+        match tx_info
+            .timing
+            .as_ref()
+            .and_then(|v| v.parameters.as_ref())
+            .ok_or_else(|| anyhow!("timing parameters must not be None"))?
+        {
+            gw::timing::Parameters::Immediately(_) => {
+                txpk.imme = true;
+            }
+            gw::timing::Parameters::Delay(v) => {
+                // The context contains the internal concentrator counter (tmst) of the uplink.
+                let tmst: [u8; 4] = tx_info
+                    .context
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| anyhow!("context must be exactly 4 bytes"))?;
+                let delay: Duration = v
+                    .delay
+                    .ok_or_else(|| anyhow!("delay must not be None"))?
+                    .try_into()?;
+
+                txpk.tmst = Some(u32::from_be_bytes(tmst).wrapping_add(delay.as_micros() as u32));
+            }
+            gw::timing::Parameters::GpsEpoch(v) => {
+                let gps_time: Duration = v
+                    .time_since_gps_epoch
+                    .ok_or_else(|| anyhow!("time_since_gps_epoch must not be None"))?
+                    .try_into()?;
+
+                txpk.tmms = Some(gps_time.as_millis() as u64);
+            }
+        }
+
+        Ok(txpk)
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct TxAckPayload {
+    pub txpk_ack: TxPkAck,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct TxPkAck {
+    pub error: String,
+}
+
+impl TxAckPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        // Older packet-forwarders do not send a payload in case of no error.
+        match b.get(12..) {
+            Some(v) if !v.iter().all(|c| c.is_ascii_whitespace() || *c == 0) => {
+                serde_json::from_slice(v.strip_suffix(&[0]).unwrap_or(v))
+                    .context("Decode TX_ACK payload")
+            }
+            _ => Ok(Default::default()),
+        }
+    }
+
+    pub fn get_status(&self) -> gw::TxAckStatus {
+        match self.txpk_ack.error.as_ref() {
+            "" | "NONE" => gw::TxAckStatus::Ok,
+            "TOO_LATE" => gw::TxAckStatus::TooLate,
+            "TOO_EARLY" => gw::TxAckStatus::TooEarly,
+            "COLLISION_PACKET" => gw::TxAckStatus::CollisionPacket,
+            "COLLISION_BEACON" => gw::TxAckStatus::CollisionBeacon,
+            "TX_FREQ" => gw::TxAckStatus::TxFreq,
+            "TX_POWER" => gw::TxAckStatus::TxPower,
+            "GPS_UNLOCKED" => gw::TxAckStatus::GpsUnlocked,
+            _ => gw::TxAckStatus::InternalError,
+        }
+    }
+}
+
+fn mhz_to_hz(v: f64) -> u32 {
+    (v * 1_000_000.0).round() as u32
+}
+
+fn get_modulation(
+    modu: &str,
+    datr: &DataRate,
+    codr: &str,
+    polarization_inversion: bool,
+) -> Result<gw::Modulation> {
+    Ok(gw::Modulation {
+        parameters: Some(match (modu, datr) {
+            ("LORA", DataRate::Lora(v)) => {
+                let (sf, bw) = v
+                    .strip_prefix("SF")
+                    .and_then(|v| v.split_once("BW"))
+                    .ok_or_else(|| anyhow!("Invalid LoRa data-rate: {}", v))?;
+
+                gw::modulation::Parameters::Lora(gw::LoraModulationInfo {
+                    spreading_factor: sf.parse().context("Parse spreading-factor")?,
+                    bandwidth: bw.parse::<u32>().context("Parse bandwidth")? * 1000,
+                    code_rate: gw::CodeRate::from_str(codr)
+                        .map_err(|e| anyhow!("{}", e))?
+                        .into(),
+                    polarization_inversion,
+                    ..Default::default()
+                })
+            }
+            ("FSK", DataRate::Fsk(v)) => gw::modulation::Parameters::Fsk(gw::FskModulationInfo {
+                datarate: *v,
+                ..Default::default()
+            }),
+            _ => {
+                return Err(anyhow!(
+                    "Unsupported modulation: {} (data-rate: {:?})",
+                    modu,
+                    datr
+                ))
+            }
+        }),
+    })
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn test_header() {
+        let b = vec![0x02, 0x01, 0x02, 0x00];
+        let h = Header::from_slice(&b).unwrap();
+        assert_eq!(
+            Header {
+                protocol_version: PROTOCOL_VERSION_2,
+                random_token: 0x0102,
+                packet_type: PacketType::PushData,
+            },
+            h
+        );
+        assert_eq!(b, h.to_vec());
+
+        assert!(Header::from_slice(&[0x03, 0x01, 0x02, 0x00]).is_err());
+        assert!(Header::from_slice(&[0x02, 0x01, 0x02, 0x06]).is_err());
+        assert!(Header::from_slice(&[0x02, 0x01]).is_err());
+    }
+
+    #[test]
+    fn test_rxpk_to_uplink_frame() {
+        let pl: PushDataPayload = serde_json::from_str(
+            r#"{"rxpk":[{"time":"2013-03-31T16:21:17.528002Z","tmst":3512348611,"tmms":1048000000000,"chan":2,"rfch":0,"freq":866.349812,"stat":1,"modu":"LORA","datr":"SF7BW125","codr":"4/6","rssi":-35,"lsnr":5.1,"size":4,"data":"AQIDBA=="}]}"#,
+        )
+        .unwrap();
+        assert!(pl.stat.is_none());
+
+        let gateway_id = EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]);
+        let mut uf = pl.rxpk[0].to_uplink_frame(gateway_id).unwrap();
+        assert_ne!(0, uf.rx_info.as_ref().unwrap().uplink_id);
+        uf.rx_info.as_mut().unwrap().uplink_id = 0;
+
+        assert_eq!(
+            gw::UplinkFrame {
+                phy_payload: vec![1, 2, 3, 4],
+                tx_info: Some(gw::UplinkTxInfo {
+                    frequency: 866349812,
+                    modulation: Some(gw::Modulation {
+                        parameters: Some(gw::modulation::Parameters::Lora(
+                            gw::LoraModulationInfo {
+                                bandwidth: 125000,
+                                spreading_factor: 7,
+                                code_rate: gw::CodeRate::Cr46.into(),
+                                ..Default::default()
+                            }
+                        )),
+                    }),
+                }),
+                rx_info: Some(gw::UplinkRxInfo {
+                    gateway_id: "0102030405060708".into(),
+                    gw_time: Some(
+                        DateTime::parse_from_rfc3339("2013-03-31T16:21:17.528002Z")
+                            .unwrap()
+                            .with_timezone(&Utc)
+                            .into()
+                    ),
+                    time_since_gps_epoch: Some(Duration::from_millis(1048000000000).into()),
+                    rssi: -35,
+                    snr: 5.1,
+                    channel: 2,
+                    context: 3512348611_u32.to_be_bytes().to_vec(),
+                    crc_status: gw::CrcStatus::CrcOk.into(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            uf
+        );
+    }
+
+    #[test]
+    fn test_rxpk_fsk() {
+        let pl: PushDataPayload = serde_json::from_str(
+            r#"{"rxpk":[{"tmst":3512348514,"chan":9,"rfch":1,"freq":869.1,"stat":1,"modu":"FSK","datr":50000,"rssi":-75,"size":4,"data":"AQIDBA=="}]}"#,
+        )
+        .unwrap();
+
+        let uf = pl.rxpk[0]
+            .to_uplink_frame(EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]))
+            .unwrap();
+        assert_eq!(
+            Some(gw::UplinkTxInfo {
+                frequency: 869100000,
+                modulation: Some(gw::Modulation {
+                    parameters: Some(gw::modulation::Parameters::Fsk(gw::FskModulationInfo {
+                        datarate: 50000,
+                        ..Default::default()
+                    })),
+                }),
+            }),
+            uf.tx_info
+        );
+    }
+
+    #[test]
+    fn test_stat_to_gateway_stats() {
+        let pl: PushDataPayload = serde_json::from_str(
+            r#"{"stat":{"time":"2014-01-12 08:59:28 GMT","lati":46.24000,"long":3.25230,"alti":145,"rxnb":2,"rxok":1,"rxfw":1,"ackr":100.0,"dwnb":3,"txnb":2}}"#,
+        )
+        .unwrap();
+
+        let stats = pl
+            .stat
+            .unwrap()
+            .to_gateway_stats(EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]))
+            .unwrap();
+        assert_eq!(
+            gw::GatewayStats {
+                gateway_id: "0102030405060708".into(),
+                time: Some(
+                    DateTime::parse_from_rfc3339("2014-01-12T08:59:28Z")
+                        .unwrap()
+                        .with_timezone(&Utc)
+                        .into()
+                ),
+                location: Some(common::Location {
+                    latitude: 46.24,
+                    longitude: 3.2523,
+                    altitude: 145.0,
+                    source: common::LocationSource::Gps.into(),
+                    ..Default::default()
+                }),
+                rx_packets_received: 2,
+                rx_packets_received_ok: 1,
+                tx_packets_received: 3,
+                tx_packets_emitted: 2,
+                ..Default::default()
+            },
+            stats
+        );
+    }
+
+    #[test]
+    fn test_txpk_from_downlink_frame_item() {
+        let item = gw::DownlinkFrameItem {
+            phy_payload: vec![1, 2, 3, 4],
+            tx_info: Some(gw::DownlinkTxInfo {
+                frequency: 868100000,
+                power: 14,
+                modulation: Some(gw::Modulation {
+                    parameters: Some(gw::modulation::Parameters::Lora(gw::LoraModulationInfo {
+                        bandwidth: 125000,
+                        spreading_factor: 7,
+                        code_rate: gw::CodeRate::Cr45.into(),
+                        polarization_inversion: true,
+                        ..Default::default()
+                    })),
+                }),
+                timing: Some(gw::Timing {
+                    parameters: Some(gw::timing::Parameters::Delay(gw::DelayTimingInfo {
+                        delay: Some(Duration::from_secs(1).into()),
+                    })),
+                }),
+                context: u32::MAX.to_be_bytes().to_vec(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let txpk = TxPk::from_downlink_frame_item(&item).unwrap();
+        assert_eq!(
+            TxPk {
+                imme: false,
+                tmst: Some(999999),
+                freq: 868.1,
+                powe: 14,
+                modu: "LORA".into(),
+                datr: DataRate::Lora("SF7BW125".into()),
+                codr: "4/5".into(),
+                ipol: true,
+                size: 4,
+                data: "AQIDBA==".into(),
+                ..Default::default()
+            },
+            txpk
+        );
+
+        let b = serde_json::to_string(&PullRespPayload { txpk }).unwrap();
+        assert_eq!(
+            r#"{"txpk":{"imme":false,"tmst":999999,"freq":868.1,"rfch":0,"powe":14,"brd":0,"ant":0,"modu":"LORA","datr":"SF7BW125","codr":"4/5","ipol":true,"ncrc":false,"size":4,"data":"AQIDBA=="}}"#,
+            b
+        );
+    }
+
+    #[test]
+    fn test_tx_ack_payload() {
+        let mut b = vec![0x02, 0x01, 0x02, 0x05, 1, 2, 3, 4, 5, 6, 7, 8];
+        assert_eq!(
+            gw::TxAckStatus::Ok,
+            TxAckPayload::from_slice(&b).unwrap().get_status()
+        );
+
+        b.extend_from_slice(br#"{"txpk_ack":{"error":"TOO_LATE"}}"#);
+        b.push(0);
+        assert_eq!(
+            gw::TxAckStatus::TooLate,
+            TxAckPayload::from_slice(&b).unwrap().get_status()
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use prost::Message;
+use rand::Rng;
+use redis::streams::StreamReadReply;
+use tokio::sync::oneshot;
+use tokio::task;
+use tracing::{error, info};
+
+use crate::config;
+use crate::gateway::backend as gateway_backend;
+use crate::helpers::errors::PrintFullError;
+use crate::storage::{gateway, get_async_redis_conn, redis_key};
+use chirpstack_api::gw;
+use lrwn::EUI64;
+
+// Executes the given command on the gateway and waits for the gateway to return the execution
+// response. As the response might be received by a different instance than the instance that
+// sent the request, the response is correlated by exec_id through Redis.
+pub async fn exec(
+    gateway_id: EUI64,
+    command: &str,
+    stdin: &[u8],
+    environment: HashMap<String, String>,
+) -> Result<gw::GatewayCommandExecResponse> {
+    let conf = config::get();
+
+    let gw = gateway::get(&gateway_id).await?;
+    let region_config_id = gw
+        .properties
+        .get("region_config_id")
+        .cloned()
+        .ok_or_else(|| anyhow!("Gateway region_config_id is unknown, no stats received yet"))?;
+
+    let exec_id: u32 = rand::thread_rng().gen();
+    let rx = get_response_receiver(gateway_id, exec_id, conf.gateway.command_exec_timeout).await?;
+
+    let pl = gw::GatewayCommandExecRequest {
+        gateway_id: gateway_id.to_string(),
+        command: command.to_string(),
+        exec_id,
+        stdin: stdin.to_vec(),
+        environment,
+        ..Default::default()
+    };
+
+    info!(gateway_id = %gateway_id, exec_id = exec_id, command = %command, "Sending gateway command execution request");
+    gateway_backend::send_command_exec(&region_config_id, &pl)
+        .await
+        .context("Send command execution request")?;
+
+    let b = rx
+        .await
+        .map_err(|_| anyhow!("Gateway command execution timeout"))?;
+    let resp = gw::GatewayCommandExecResponse::decode(&mut Cursor::new(b))?;
+
+    Ok(resp)
+}
+
+// Handles the command execution response received from the gateway, by publishing it to the
+// Redis Stream on which the requesting instance is waiting.
+pub async fn handle_response(pl: gw::GatewayCommandExecResponse) {
+    if let Err(e) = _handle_response(&pl).await {
+        error!(gateway_id = %pl.gateway_id, exec_id = pl.exec_id, error = %e.full(), "Handle gateway command execution response error");
+    }
+}
+
+async fn _handle_response(pl: &gw::GatewayCommandExecResponse) -> Result<()> {
+    let conf = config::get();
+    let gateway_id: EUI64 = pl.gateway_id.parse().context("Gateway ID")?;
+
+    info!(gateway_id = %gateway_id, exec_id = pl.exec_id, "Gateway command execution response received");
+
+    let key = redis_key(format!("gw:{{{}}}:exec:{}", gateway_id, pl.exec_id));
+    let b = pl.encode_to_vec();
+
+    redis::pipe()
+        .atomic()
+        .cmd("XADD")
+        .arg(&key)
+        .arg("MAXLEN")
+        .arg(1_i64)
+        .arg("*")
+        .arg("pl")
+        .arg(b)
+        .ignore()
+        .cmd("PEXPIRE")
+        .arg(&key)
+        .arg(conf.gateway.command_exec_timeout.as_millis() as u64)
+        .ignore()
+        .query_async(&mut get_async_redis_conn().await?)
+        .await?;
+
+    Ok(())
+}
+
+async fn get_response_receiver(
+    gateway_id: EUI64,
+    exec_id: u32,
+    timeout: Duration,
+) -> Result<oneshot::Receiver<Vec<u8>>> {
+    let (tx, rx) = oneshot::channel();
+
+    task::spawn(async move {
+        let mut c = match get_async_redis_conn().await {
+            Ok(v) => v,
+            Err(e) => {
+                error!(error = %e, "Get Redis connection error");
+                return;
+            }
+        };
+        let key = redis_key(format!("gw:{{{}}}:exec:{}", gateway_id, exec_id));
+
+        let srr: StreamReadReply = match redis::cmd("XREAD")
+            .arg("BLOCK")
+            .arg(timeout.as_millis() as u64)
+            .arg("COUNT")
+            .arg(1_u64)
+            .arg("STREAMS")
+            .arg(&key)
+            .arg("0")
+            .query_async(&mut c)
+            .await
+        {
+            Ok(v) => v,
+            Err(e) => {
+                error!(error = %e, "Read from Redis Stream error");
+                return;
+            }
+        };
+
+        for stream_key in &srr.keys {
+            for stream_id in &stream_key.ids {
+                for (k, v) in &stream_id.map {
+                    match k.as_ref() {
+                        "pl" => {
+                            if let redis::Value::BulkString(b) = v {
+                                let _ = tx.send(b.to_vec());
+                                return;
+                            }
+                        }
+                        _ => {
+                            error!(
+                                gateway_id = %gateway_id,
+                                exec_id = exec_id,
+                                key = %key,
+                                "Unexpected key in command execution stream"
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(rx)
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage::fields;
+    use crate::test;
+
+    #[tokio::test]
+    async fn test_exec() {
+        let _guard = test::prepare().await;
+
+        gateway_backend::set_backend("eu868", Box::new(gateway_backend::mock::Backend {})).await;
+        gateway_backend::mock::reset().await;
+
+        let gw =
+            gateway::test::create_gateway(EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8])).await;
+
+        // no stats received yet
+        assert!(exec(gw.gateway_id, "reboot", &[], HashMap::new())
+            .await
+            .is_err());
+
+        gateway::partial_update(
+            gw.gateway_id,
+            &gateway::GatewayChangeset {
+                properties: Some(fields::KeyValue::new(
+                    [("region_config_id".to_string(), "eu868".to_string())]
+                        .into_iter()
+                        .collect(),
+                )),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        // respond to the request once it has been sent to the gateway
+        task::spawn(async move {
+            loop {
+                let reqs = gateway_backend::mock::get_command_exec_requests().await;
+                if let Some(req) = reqs.first() {
+                    handle_response(gw::GatewayCommandExecResponse {
+                        gateway_id: req.gateway_id.clone(),
+                        exec_id: req.exec_id,
+                        stdout: req.stdin.clone(),
+                        ..Default::default()
+                    })
+                    .await;
+                    return;
+                }
+
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        });
+
+        let resp = exec(
+            gw.gateway_id,
+            "echo",
+            b"hello",
+            [("FOO".to_string(), "bar".to_string())]
+                .into_iter()
+                .collect(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(b"hello".to_vec(), resp.stdout);
+        assert_eq!("", resp.error);
+    }
+
+    #[tokio::test]
+    async fn test_exec_timeout() {
+        let _guard = test::prepare().await;
+
+        let mut conf = (*config::get()).clone();
+        conf.gateway.command_exec_timeout = Duration::from_millis(100);
+        config::set(conf);
+
+        gateway_backend::set_backend("eu868", Box::new(gateway_backend::mock::Backend {})).await;
+        gateway_backend::mock::reset().await;
+
+        let gw =
+            gateway::test::create_gateway(EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8])).await;
+        gateway::partial_update(
+            gw.gateway_id,
+            &gateway::GatewayChangeset {
+                properties: Some(fields::KeyValue::new(
+                    [("region_config_id".to_string(), "eu868".to_string())]
+                        .into_iter()
+                        .collect(),
+                )),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert!(exec(gw.gateway_id, "reboot", &[], HashMap::new())
+            .await
+            .is_err());
+
+        let reqs = gateway_backend::mock::get_command_exec_requests().await;
+        assert_eq!(1, reqs.len());
+        assert_eq!("reboot", reqs[0].command);
+    }
+}
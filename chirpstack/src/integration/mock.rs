@@ -18,6 +18,10 @@ lazy_static! {
     static ref LOCATION_EVENTS: RwLock<Vec<integration::LocationEvent>> = RwLock::new(Vec::new());
     static ref INTEGRATION_EVENTS: RwLock<Vec<integration::IntegrationEvent>> =
         RwLock::new(Vec::new());
+    static ref RAW_PACKET_FORWARDER_EVENTS: RwLock<Vec<integration::RawPacketForwarderEvent>> =
+        RwLock::new(Vec::new());
+    static ref MULTICAST_GROUP_DELIVERY_EVENTS: RwLock<Vec<integration::MulticastGroupDeliveryEvent>> =
+        RwLock::new(Vec::new());
 }
 
 pub async fn reset() {
@@ -29,6 +33,8 @@ pub async fn reset() {
     STATUS_EVENTS.write().await.drain(..);
     LOCATION_EVENTS.write().await.drain(..);
     INTEGRATION_EVENTS.write().await.drain(..);
+    RAW_PACKET_FORWARDER_EVENTS.write().await.drain(..);
+    MULTICAST_GROUP_DELIVERY_EVENTS.write().await.drain(..);
 }
 
 pub struct Integration {}
@@ -106,6 +112,27 @@ impl IntegrationTrait for Integration {
         INTEGRATION_EVENTS.write().await.push(pl.clone());
         Ok(())
     }
+
+    async fn raw_packet_forwarder_event(
+        &self,
+        _vars: &HashMap<String, String>,
+        pl: &integration::RawPacketForwarderEvent,
+    ) -> Result<()> {
+        RAW_PACKET_FORWARDER_EVENTS.write().await.push(pl.clone());
+        Ok(())
+    }
+
+    async fn multicast_group_delivery_event(
+        &self,
+        _vars: &HashMap<String, String>,
+        pl: &integration::MulticastGroupDeliveryEvent,
+    ) -> Result<()> {
+        MULTICAST_GROUP_DELIVERY_EVENTS
+            .write()
+            .await
+            .push(pl.clone());
+        Ok(())
+    }
 }
 
 pub async fn get_join_event() -> Option<integration::JoinEvent> {
@@ -191,3 +218,20 @@ pub async fn get_location_events() -> Vec<integration::LocationEvent> {
 pub async fn get_integration_events() -> Vec<integration::IntegrationEvent> {
     INTEGRATION_EVENTS.write().await.drain(..).collect()
 }
+
+pub async fn get_raw_packet_forwarder_events() -> Vec<integration::RawPacketForwarderEvent> {
+    RAW_PACKET_FORWARDER_EVENTS
+        .write()
+        .await
+        .drain(..)
+        .collect()
+}
+
+pub async fn get_multicast_group_delivery_events() -> Vec<integration::MulticastGroupDeliveryEvent>
+{
+    MULTICAST_GROUP_DELIVERY_EVENTS
+        .write()
+        .await
+        .drain(..)
+        .collect()
+}
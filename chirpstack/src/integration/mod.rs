@@ -74,6 +74,19 @@ pub async fn setup() -> Result<()> {
         }
     }
 
+    // Gateway and multicast-group events are only published by the MQTT integration.
+    let mqtt_enabled = conf.integration.enabled.iter().any(|v| v == "mqtt");
+    if !conf.integration.mqtt.gateway_event_topic.is_empty() && !mqtt_enabled {
+        return Err(anyhow!(
+            "gateway_event_topic is configured, but the MQTT integration is not enabled"
+        ));
+    }
+    if !conf.integration.mqtt.multicast_group_event_topic.is_empty() && !mqtt_enabled {
+        return Err(anyhow!(
+            "multicast_group_event_topic is configured, but the MQTT integration is not enabled"
+        ));
+    }
+
     Ok(())
 }
 
@@ -132,6 +145,29 @@ pub trait Integration {
         vars: &HashMap<String, String>,
         pl: &integration::IntegrationEvent,
     ) -> Result<()>;
+
+    // Gateway events are not related to an application and are therefore only published to the
+    // global integrations. Only the MQTT integration implements this method (when a gateway event
+    // topic has been configured), the other integrations ignore these events.
+    async fn raw_packet_forwarder_event(
+        &self,
+        _vars: &HashMap<String, String>,
+        _pl: &integration::RawPacketForwarderEvent,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    // Multicast-group events are not related to a device and are therefore, like gateway events,
+    // only published to the global integrations. Only the MQTT integration implements this method
+    // (when a multicast-group event topic has been configured), the other integrations ignore
+    // these events.
+    async fn multicast_group_delivery_event(
+        &self,
+        _vars: &HashMap<String, String>,
+        _pl: &integration::MulticastGroupDeliveryEvent,
+    ) -> Result<()> {
+        Ok(())
+    }
 }
 
 // Returns a Vec of integrations for the given Application ID.
@@ -524,6 +560,95 @@ async fn _integration_event(
     Ok(())
 }
 
+pub async fn multicast_group_delivery_event(
+    application_id: Uuid,
+    vars: &HashMap<String, String>,
+    pl: &integration::MulticastGroupDeliveryEvent,
+) {
+    tokio::spawn({
+        let vars = vars.clone();
+        let pl = pl.clone();
+
+        async move {
+            if let Err(err) = _multicast_group_delivery_event(&vars, &pl).await {
+                warn!(application_id = %application_id, error = %err.full(), "Multicast-group delivery event error");
+            }
+        }
+    });
+}
+
+async fn _multicast_group_delivery_event(
+    vars: &HashMap<String, String>,
+    pl: &integration::MulticastGroupDeliveryEvent,
+) -> Result<()> {
+    #[cfg(test)]
+    {
+        let m = MOCK_INTEGRATION.read().await;
+        if *m {
+            return mock::Integration {}
+                .multicast_group_delivery_event(vars, pl)
+                .await;
+        }
+    }
+
+    let global_ints = GLOBAL_INTEGRATIONS.read().await;
+    let mut futures = Vec::new();
+
+    for (i, _) in global_ints.iter().enumerate() {
+        futures.push(global_ints[i].multicast_group_delivery_event(vars, pl));
+    }
+
+    for e in join_all(futures).await {
+        e?;
+    }
+
+    Ok(())
+}
+
+pub async fn raw_packet_forwarder_event(
+    vars: &HashMap<String, String>,
+    pl: &integration::RawPacketForwarderEvent,
+) {
+    tokio::spawn({
+        let vars = vars.clone();
+        let pl = pl.clone();
+
+        async move {
+            if let Err(err) = _raw_packet_forwarder_event(&vars, &pl).await {
+                warn!(gateway_id = %pl.gateway_id, error = %err.full(), "Raw packet-forwarder event error");
+            }
+        }
+    });
+}
+
+async fn _raw_packet_forwarder_event(
+    vars: &HashMap<String, String>,
+    pl: &integration::RawPacketForwarderEvent,
+) -> Result<()> {
+    #[cfg(test)]
+    {
+        let m = MOCK_INTEGRATION.read().await;
+        if *m {
+            return mock::Integration {}
+                .raw_packet_forwarder_event(vars, pl)
+                .await;
+        }
+    }
+
+    let global_ints = GLOBAL_INTEGRATIONS.read().await;
+    let mut futures = Vec::new();
+
+    for (i, _) in global_ints.iter().enumerate() {
+        futures.push(global_ints[i].raw_packet_forwarder_event(vars, pl));
+    }
+
+    for e in join_all(futures).await {
+        e?;
+    }
+
+    Ok(())
+}
+
 async fn handle_down_command(application_id: String, pl: integration::DownlinkCommand) {
     let err = async {
         info!(dev_eui = %pl.dev_eui, "Handling downlink command for device");
@@ -28,6 +28,8 @@ pub struct Integration<'a> {
     json: bool,
     qos: QoS,
     command_regex: Regex,
+    gateway_events: bool,
+    multicast_group_events: bool,
 }
 
 #[derive(Serialize)]
@@ -37,6 +39,19 @@ struct EventTopicContext {
     pub event: String,
 }
 
+#[derive(Serialize)]
+struct GatewayEventTopicContext {
+    pub gateway_id: String,
+    pub event: String,
+}
+
+#[derive(Serialize)]
+struct MulticastGroupEventTopicContext {
+    pub application_id: String,
+    pub multicast_group_id: String,
+    pub event: String,
+}
+
 #[derive(Serialize)]
 struct CommandTopicContext {
     pub application_id: String,
@@ -53,6 +68,11 @@ impl<'a> Integration<'a> {
         templates.register_escape_fn(handlebars::no_escape);
         templates.register_template_string("event_topic", &conf.event_topic)?;
         templates.register_template_string("command_topic", &conf.command_topic)?;
+        templates.register_template_string("gateway_event_topic", &conf.gateway_event_topic)?;
+        templates.register_template_string(
+            "multicast_group_event_topic",
+            &conf.multicast_group_event_topic,
+        )?;
 
         let command_topic = templates.render(
             "command_topic",
@@ -137,6 +157,8 @@ impl<'a> Integration<'a> {
             )?)?,
             qos,
             json: conf.json,
+            gateway_events: !conf.gateway_event_topic.is_empty(),
+            multicast_group_events: !conf.multicast_group_event_topic.is_empty(),
             client,
             templates,
         };
@@ -234,6 +256,32 @@ impl<'a> Integration<'a> {
         )?)
     }
 
+    fn get_gateway_event_topic(&self, gateway_id: &str, event: &str) -> Result<String> {
+        Ok(self.templates.render(
+            "gateway_event_topic",
+            &GatewayEventTopicContext {
+                gateway_id: gateway_id.to_string(),
+                event: event.to_string(),
+            },
+        )?)
+    }
+
+    fn get_multicast_group_event_topic(
+        &self,
+        application_id: &str,
+        multicast_group_id: &str,
+        event: &str,
+    ) -> Result<String> {
+        Ok(self.templates.render(
+            "multicast_group_event_topic",
+            &MulticastGroupEventTopicContext {
+                application_id: application_id.to_string(),
+                multicast_group_id: multicast_group_id.to_string(),
+                event: event.to_string(),
+            },
+        )?)
+    }
+
     async fn publish_event(&self, topic: &str, b: Vec<u8>) -> Result<()> {
         info!(topic = %topic, "Publishing event");
         self.client.publish(topic, self.qos, false, b).await?;
@@ -396,6 +444,49 @@ impl IntegrationTrait for Integration<'_> {
 
         self.publish_event(&topic, b).await
     }
+
+    async fn raw_packet_forwarder_event(
+        &self,
+        _vars: &HashMap<String, String>,
+        pl: &integration::RawPacketForwarderEvent,
+    ) -> Result<()> {
+        // Gateway events are only published when a gateway event topic has been configured.
+        if !self.gateway_events {
+            return Ok(());
+        }
+
+        let topic = self.get_gateway_event_topic(&pl.gateway_id, "raw")?;
+        let b = match self.json {
+            true => serde_json::to_vec(&pl)?,
+            false => pl.encode_to_vec(),
+        };
+
+        self.publish_event(&topic, b).await
+    }
+
+    async fn multicast_group_delivery_event(
+        &self,
+        _vars: &HashMap<String, String>,
+        pl: &integration::MulticastGroupDeliveryEvent,
+    ) -> Result<()> {
+        // Multicast-group events are only published when a multicast-group event topic has been
+        // configured.
+        if !self.multicast_group_events {
+            return Ok(());
+        }
+
+        let topic = self.get_multicast_group_event_topic(
+            &pl.application_id,
+            &pl.multicast_group_id,
+            "delivery",
+        )?;
+        let b = match self.json {
+            true => serde_json::to_vec(&pl)?,
+            false => pl.encode_to_vec(),
+        };
+
+        self.publish_event(&topic, b).await
+    }
 }
 
 async fn message_callback(
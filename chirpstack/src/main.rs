@@ -21,6 +21,7 @@ use lrwn::EUI64;
 
 mod adr;
 mod api;
+mod applayer;
 mod backend;
 mod certificate;
 mod cmd;
@@ -7,10 +7,13 @@ use tracing::info;
 use uuid::Uuid;
 
 use super::error::Error;
-use super::schema::api_key;
+use super::fields::ApiKeyScope;
+use super::schema::{api_key, application};
 use super::{error, get_async_db_conn};
 
-#[derive(Queryable, Insertable, PartialEq, Eq, Debug)]
+pub const LAST_USED_UPDATE_INTERVAL: chrono::Duration = chrono::Duration::minutes(1);
+
+#[derive(Queryable, Insertable, PartialEq, Eq, Debug, Clone)]
 #[diesel(table_name = api_key)]
 pub struct ApiKey {
     pub id: Uuid,
@@ -18,6 +21,10 @@ pub struct ApiKey {
     pub name: String,
     pub is_admin: bool,
     pub tenant_id: Option<Uuid>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub scope: ApiKeyScope,
+    pub application_id: Option<Uuid>,
 }
 
 impl ApiKey {
@@ -26,8 +33,21 @@ impl ApiKey {
             return Err(Error::Validation("name is not set".into()));
         }
 
+        if self.application_id.is_some() && self.tenant_id.is_none() {
+            return Err(Error::Validation(
+                "application_id can only be set for tenant api keys".into(),
+            ));
+        }
+
         Ok(())
     }
+
+    pub fn is_expired(&self) -> bool {
+        match self.expires_at {
+            Some(v) => v <= Utc::now(),
+            None => false,
+        }
+    }
 }
 
 impl Default for ApiKey {
@@ -38,6 +58,10 @@ impl Default for ApiKey {
             name: "".into(),
             is_admin: false,
             tenant_id: None,
+            expires_at: None,
+            last_used_at: None,
+            scope: ApiKeyScope::FULL,
+            application_id: None,
         }
     }
 }
@@ -51,6 +75,21 @@ pub struct Filters {
 pub async fn create(ak: ApiKey) -> Result<ApiKey, Error> {
     ak.validate()?;
 
+    if let Some(application_id) = &ak.application_id {
+        let tenant_id: Uuid = application::dsl::application
+            .select(application::dsl::tenant_id)
+            .find(application_id)
+            .first(&mut get_async_db_conn().await?)
+            .await
+            .map_err(|e| error::Error::from_diesel(e, application_id.to_string()))?;
+
+        if ak.tenant_id != Some(tenant_id) {
+            return Err(Error::Validation(
+                "application_id does not belong to the tenant of the api key".into(),
+            ));
+        }
+    }
+
     let ak: ApiKey = diesel::insert_into(api_key::table)
         .values(&ak)
         .get_result(&mut get_async_db_conn().await?)
@@ -60,6 +99,32 @@ pub async fn create(ak: ApiKey) -> Result<ApiKey, Error> {
     Ok(ak)
 }
 
+pub async fn get(id: &Uuid) -> Result<ApiKey, Error> {
+    api_key::dsl::api_key
+        .find(&id)
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| error::Error::from_diesel(e, id.to_string()))
+}
+
+// Updates the last-used timestamp of the API key. To avoid a database write on every request,
+// this is a no-op when the key has been used within the last minute.
+pub async fn update_last_used(id: &Uuid) -> Result<(), Error> {
+    let now = Utc::now();
+    diesel::update(
+        api_key::dsl::api_key.find(&id).filter(
+            api_key::dsl::last_used_at
+                .is_null()
+                .or(api_key::dsl::last_used_at.lt(now - LAST_USED_UPDATE_INTERVAL)),
+        ),
+    )
+    .set(api_key::last_used_at.eq(Some(now)))
+    .execute(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| error::Error::from_diesel(e, id.to_string()))?;
+    Ok(())
+}
+
 pub async fn delete(id: &Uuid) -> Result<(), Error> {
     let ra = diesel::delete(api_key::dsl::api_key.find(&id))
         .execute(&mut get_async_db_conn().await?)
@@ -105,7 +170,7 @@ pub async fn list(limit: i64, offset: i64, filters: &Filters) -> Result<Vec<ApiK
 #[cfg(test)]
 pub mod test {
     use super::*;
-    use crate::storage::tenant;
+    use crate::storage::{application, tenant};
     use crate::test;
 
     struct FilterTest<'a> {
@@ -116,14 +181,6 @@ pub mod test {
         offset: i64,
     }
 
-    pub async fn get(id: &Uuid) -> Result<ApiKey, Error> {
-        api_key::dsl::api_key
-            .find(&id)
-            .first(&mut get_async_db_conn().await?)
-            .await
-            .map_err(|e| error::Error::from_diesel(e, id.to_string()))
-    }
-
     pub async fn create_api_key(is_admin: bool, is_tenant: bool) -> ApiKey {
         let ak = ApiKey {
             name: "test api key".into(),
@@ -147,6 +204,19 @@ pub mod test {
         // get
         let ak_get = get(&ak_admin.id).await.unwrap();
         assert_eq!(ak_admin, ak_get);
+        assert!(!ak_get.is_expired());
+
+        // update last used
+        update_last_used(&ak_admin.id).await.unwrap();
+        let ak_get = get(&ak_admin.id).await.unwrap();
+        assert!(ak_get.last_used_at.is_some());
+
+        // update last used within the update interval is a no-op
+        update_last_used(&ak_admin.id).await.unwrap();
+        assert_eq!(
+            ak_get.last_used_at,
+            get(&ak_admin.id).await.unwrap().last_used_at
+        );
 
         // get count and list
         let tests = vec![
@@ -193,4 +263,48 @@ pub mod test {
         delete(&ak_admin.id).await.unwrap();
         assert!(delete(&ak_admin.id).await.is_err());
     }
+
+    #[tokio::test]
+    async fn api_key_application() {
+        let _guard = test::prepare().await;
+        let app = application::test::create_application(None).await;
+        let app_other = application::test::create_application(None).await;
+
+        // application of an other tenant
+        let res = create(ApiKey {
+            name: "test api key".into(),
+            tenant_id: Some(app.tenant_id),
+            application_id: Some(app_other.id),
+            ..Default::default()
+        })
+        .await;
+        assert!(res.is_err());
+
+        // admin key can not be restricted to an application
+        let res = create(ApiKey {
+            name: "test api key".into(),
+            is_admin: true,
+            application_id: Some(app.id),
+            ..Default::default()
+        })
+        .await;
+        assert!(res.is_err());
+
+        // valid
+        let ak = create(ApiKey {
+            name: "test api key".into(),
+            tenant_id: Some(app.tenant_id),
+            application_id: Some(app.id),
+            scope: ApiKeyScope::READ_ONLY,
+            expires_at: Some(Utc::now() - chrono::Duration::seconds(1)),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert!(ak.is_expired());
+
+        // deleting the application deletes the api key
+        application::delete(&app.id).await.unwrap();
+        assert!(get(&ak.id).await.is_err());
+    }
 }
@@ -18,7 +18,7 @@ use tracing::info;
 use uuid::Uuid;
 
 use super::error::Error;
-use super::schema::{application, application_integration};
+use super::schema::{application, application_integration, application_user, tenant_user, user};
 use super::{fields, get_async_db_conn};
 
 #[derive(Clone, Queryable, Insertable, PartialEq, Eq, Debug)]
@@ -60,10 +60,50 @@ impl Default for Application {
     }
 }
 
+#[derive(Queryable, Insertable, PartialEq, Eq, Debug)]
+#[diesel(table_name = application_user)]
+pub struct ApplicationUser {
+    pub application_id: Uuid,
+    pub user_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub is_admin: bool,
+    pub is_device_admin: bool,
+}
+
+impl Default for ApplicationUser {
+    fn default() -> Self {
+        let now = Utc::now();
+
+        ApplicationUser {
+            application_id: Uuid::nil(),
+            user_id: Uuid::nil(),
+            created_at: now,
+            updated_at: now,
+            is_admin: false,
+            is_device_admin: false,
+        }
+    }
+}
+
+#[derive(Queryable, PartialEq, Eq, Debug)]
+pub struct ApplicationUserListItem {
+    pub application_id: Uuid,
+    pub user_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub email: String,
+    pub is_admin: bool,
+    pub is_device_admin: bool,
+}
+
 #[derive(Default, Clone)]
 pub struct Filters {
     pub tenant_id: Option<Uuid>,
     pub search: Option<String>,
+    // Only return applications to which the user has access, either as
+    // tenant user or as application user.
+    pub user_id: Option<Uuid>,
 }
 
 #[derive(Queryable, PartialEq, Eq, Debug)]
@@ -378,6 +418,25 @@ pub async fn get_count(filters: &Filters) -> Result<i64, Error> {
         q = q.filter(application::dsl::name.ilike(format!("%{}%", search)));
     }
 
+    if let Some(user_id) = &filters.user_id {
+        q = q.filter(
+            dsl::exists(
+                tenant_user::dsl::tenant_user.filter(
+                    tenant_user::dsl::tenant_id
+                        .eq(application::dsl::tenant_id)
+                        .and(tenant_user::dsl::user_id.eq(user_id)),
+                ),
+            )
+            .or(dsl::exists(
+                application_user::dsl::application_user.filter(
+                    application_user::dsl::application_id
+                        .eq(application::dsl::id)
+                        .and(application_user::dsl::user_id.eq(user_id)),
+                ),
+            )),
+        );
+    }
+
     Ok(q.first(&mut get_async_db_conn().await?).await?)
 }
 
@@ -404,6 +463,25 @@ pub async fn list(
         q = q.filter(application::dsl::name.ilike(format!("%{}%", search)));
     }
 
+    if let Some(user_id) = &filters.user_id {
+        q = q.filter(
+            dsl::exists(
+                tenant_user::dsl::tenant_user.filter(
+                    tenant_user::dsl::tenant_id
+                        .eq(application::dsl::tenant_id)
+                        .and(tenant_user::dsl::user_id.eq(user_id)),
+                ),
+            )
+            .or(dsl::exists(
+                application_user::dsl::application_user.filter(
+                    application_user::dsl::application_id
+                        .eq(application::dsl::id)
+                        .and(application_user::dsl::user_id.eq(user_id)),
+                ),
+            )),
+        );
+    }
+
     let items = q
         .order_by(application::dsl::name)
         .limit(limit)
@@ -413,6 +491,106 @@ pub async fn list(
     Ok(items)
 }
 
+pub async fn add_user(au: ApplicationUser) -> Result<ApplicationUser, Error> {
+    let au: ApplicationUser = diesel::insert_into(application_user::table)
+        .values(&au)
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, au.user_id.to_string()))?;
+    info!(
+        application_id = %au.application_id,
+        user_id = %au.user_id,
+        "Application user added"
+    );
+    Ok(au)
+}
+
+pub async fn update_user(au: ApplicationUser) -> Result<ApplicationUser, Error> {
+    let au: ApplicationUser = diesel::update(
+        application_user::dsl::application_user
+            .filter(application_user::dsl::application_id.eq(&au.application_id))
+            .filter(application_user::dsl::user_id.eq(&au.user_id)),
+    )
+    .set((
+        application_user::updated_at.eq(Utc::now()),
+        application_user::is_admin.eq(au.is_admin),
+        application_user::is_device_admin.eq(au.is_device_admin),
+    ))
+    .get_result(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| Error::from_diesel(e, au.user_id.to_string()))?;
+    info!(
+        application_id = %au.application_id,
+        user_id = %au.user_id,
+        "Application user updated"
+    );
+    Ok(au)
+}
+
+pub async fn get_user(application_id: &Uuid, user_id: &Uuid) -> Result<ApplicationUser, Error> {
+    let au: ApplicationUser = application_user::dsl::application_user
+        .filter(application_user::dsl::application_id.eq(&application_id))
+        .filter(application_user::dsl::user_id.eq(&user_id))
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, user_id.to_string()))?;
+    Ok(au)
+}
+
+pub async fn get_user_count(application_id: &Uuid) -> Result<i64, Error> {
+    let count = application_user::dsl::application_user
+        .select(dsl::count_star())
+        .filter(application_user::dsl::application_id.eq(&application_id))
+        .first(&mut get_async_db_conn().await?)
+        .await?;
+    Ok(count)
+}
+
+pub async fn get_users(
+    application_id: &Uuid,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<ApplicationUserListItem>, Error> {
+    let items = application_user::dsl::application_user
+        .inner_join(user::table)
+        .select((
+            application_user::dsl::application_id,
+            application_user::dsl::user_id,
+            application_user::dsl::created_at,
+            application_user::dsl::updated_at,
+            user::dsl::email,
+            application_user::dsl::is_admin,
+            application_user::dsl::is_device_admin,
+        ))
+        .filter(application_user::dsl::application_id.eq(&application_id))
+        .order_by(user::dsl::email)
+        .limit(limit)
+        .offset(offset)
+        .load(&mut get_async_db_conn().await?)
+        .await?;
+
+    Ok(items)
+}
+
+pub async fn delete_user(application_id: &Uuid, user_id: &Uuid) -> Result<(), Error> {
+    let ra = diesel::delete(
+        application_user::dsl::application_user
+            .filter(application_user::dsl::application_id.eq(&application_id))
+            .filter(application_user::dsl::user_id.eq(&user_id)),
+    )
+    .execute(&mut get_async_db_conn().await?)
+    .await?;
+    if ra == 0 {
+        return Err(Error::NotFound(user_id.to_string()));
+    }
+    info!(
+        application_id = %application_id,
+        user_id = %user_id,
+        "Application user deleted"
+    );
+    Ok(())
+}
+
 pub async fn create_integration(i: Integration) -> Result<Integration, Error> {
     let i: Integration = diesel::insert_into(application_integration::table)
         .values(&i)
@@ -585,6 +763,7 @@ pub mod test {
                 filters: Filters {
                     tenant_id: None,
                     search: None,
+                    user_id: None,
                 },
                 apps: vec![&app],
                 count: 1,
@@ -595,6 +774,7 @@ pub mod test {
                 filters: Filters {
                     tenant_id: None,
                     search: Some("aap".into()),
+                    user_id: None,
                 },
                 apps: vec![],
                 count: 0,
@@ -605,6 +785,7 @@ pub mod test {
                 filters: Filters {
                     tenant_id: None,
                     search: Some("app".into()),
+                    user_id: None,
                 },
                 apps: vec![&app],
                 count: 1,
@@ -615,6 +796,7 @@ pub mod test {
                 filters: Filters {
                     tenant_id: None,
                     search: Some("app".into()),
+                    user_id: None,
                 },
                 apps: vec![],
                 count: 1,
@@ -625,6 +807,7 @@ pub mod test {
                 filters: Filters {
                     tenant_id: Some(app.tenant_id),
                     search: None,
+                    user_id: None,
                 },
                 apps: vec![&app],
                 count: 1,
@@ -635,6 +818,7 @@ pub mod test {
                 filters: Filters {
                     tenant_id: Some(Uuid::new_v4()),
                     search: None,
+                    user_id: None,
                 },
                 apps: vec![],
                 count: 0,
@@ -664,4 +848,71 @@ pub mod test {
         delete(&app.id).await.unwrap();
         assert!(delete(&app.id).await.is_err());
     }
+
+    #[tokio::test]
+    async fn test_application_user() {
+        let _guard = test::prepare().await;
+
+        let app = create_application(None).await;
+        let _app_other = create_application(Some(app.tenant_id)).await;
+        let user = storage::user::test::create_user().await;
+
+        let au = ApplicationUser {
+            application_id: app.id,
+            user_id: user.id,
+            is_device_admin: true,
+            ..Default::default()
+        };
+
+        // add user
+        let au = add_user(au).await.unwrap();
+
+        // get
+        let au_get = get_user(&app.id, &user.id).await.unwrap();
+        assert_eq!(au, au_get);
+
+        // update
+        let au = update_user(ApplicationUser {
+            application_id: app.id,
+            user_id: user.id,
+            is_admin: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert!(au.is_admin);
+        assert!(!au.is_device_admin);
+
+        // get count and list
+        let count = get_user_count(&app.id).await.unwrap();
+        assert_eq!(1, count);
+
+        // get users
+        let users = get_users(&app.id, 10, 0).await.unwrap();
+        assert_eq!(user.id, users[0].user_id);
+
+        // applications filtered by user
+        let filters = Filters {
+            tenant_id: Some(app.tenant_id),
+            search: None,
+            user_id: Some(user.id),
+        };
+        assert_eq!(1, get_count(&filters).await.unwrap());
+        let items = list(10, 0, &filters).await.unwrap();
+        assert_eq!(app.id, items[0].id);
+
+        // tenant users have access to all applications
+        storage::tenant::add_user(storage::tenant::TenantUser {
+            tenant_id: app.tenant_id,
+            user_id: user.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert_eq!(2, get_count(&filters).await.unwrap());
+
+        // delete
+        delete_user(&app.id, &user.id).await.unwrap();
+        assert!(delete_user(&app.id, &user.id).await.is_err());
+    }
 }
@@ -0,0 +1,384 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use diesel::dsl;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+use tracing::info;
+use uuid::Uuid;
+
+use super::error::Error;
+use super::get_async_db_conn;
+use super::schema::audit_log;
+
+#[derive(Queryable, Insertable, PartialEq, Debug, Clone)]
+#[diesel(table_name = audit_log)]
+pub struct AuditLog {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub tenant_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub api_key_id: Option<Uuid>,
+    pub service: String,
+    pub method: String,
+    pub target_id: String,
+    pub changes: serde_json::Value,
+    pub source_ip: String,
+}
+
+impl AuditLog {
+    fn validate(&self) -> Result<(), Error> {
+        if self.service.is_empty() {
+            return Err(Error::Validation("service is not set".into()));
+        }
+
+        if self.method.is_empty() {
+            return Err(Error::Validation("method is not set".into()));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        AuditLog {
+            id: Uuid::new_v4(),
+            created_at: Utc::now(),
+            tenant_id: None,
+            user_id: None,
+            api_key_id: None,
+            service: "".into(),
+            method: "".into(),
+            target_id: "".into(),
+            changes: serde_json::Value::Object(Default::default()),
+            source_ip: "".into(),
+        }
+    }
+}
+
+// Changes contains the old and new values of the fields that were modified by an API call.
+// Only the fields of which the value has changed are included.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Changes(serde_json::Map<String, serde_json::Value>);
+
+impl Changes {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn field<T: Serialize + PartialEq>(mut self, name: &str, old: &T, new: &T) -> Self {
+        if old != new {
+            self.0.insert(
+                name.to_string(),
+                serde_json::json!({
+                    "old": old,
+                    "new": new,
+                }),
+            );
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Changes> for serde_json::Value {
+    fn from(c: Changes) -> Self {
+        serde_json::Value::Object(c.0)
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct Filters {
+    pub tenant_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub api_key_id: Option<Uuid>,
+    pub method: Option<String>,
+    pub target_id: Option<String>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+pub async fn create(a: AuditLog) -> Result<AuditLog, Error> {
+    a.validate()?;
+
+    let a: AuditLog = diesel::insert_into(audit_log::table)
+        .values(&a)
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, a.id.to_string()))?;
+
+    info!(
+        id = %a.id,
+        service = %a.service,
+        method = %a.method,
+        target_id = %a.target_id,
+        "Audit log created"
+    );
+    Ok(a)
+}
+
+pub async fn get_count(filters: &Filters) -> Result<i64, Error> {
+    let mut q = audit_log::dsl::audit_log
+        .select(dsl::count_star())
+        .into_boxed();
+
+    if let Some(tenant_id) = &filters.tenant_id {
+        q = q.filter(audit_log::dsl::tenant_id.eq(tenant_id));
+    }
+
+    if let Some(user_id) = &filters.user_id {
+        q = q.filter(audit_log::dsl::user_id.eq(user_id));
+    }
+
+    if let Some(api_key_id) = &filters.api_key_id {
+        q = q.filter(audit_log::dsl::api_key_id.eq(api_key_id));
+    }
+
+    if let Some(method) = &filters.method {
+        q = q.filter(audit_log::dsl::method.eq(method));
+    }
+
+    if let Some(target_id) = &filters.target_id {
+        q = q.filter(audit_log::dsl::target_id.eq(target_id));
+    }
+
+    if let Some(start) = &filters.start {
+        q = q.filter(audit_log::dsl::created_at.ge(start));
+    }
+
+    if let Some(end) = &filters.end {
+        q = q.filter(audit_log::dsl::created_at.lt(end));
+    }
+
+    Ok(q.first(&mut get_async_db_conn().await?).await?)
+}
+
+pub async fn list(limit: i64, offset: i64, filters: &Filters) -> Result<Vec<AuditLog>, Error> {
+    let mut q = audit_log::dsl::audit_log.into_boxed();
+
+    if let Some(tenant_id) = &filters.tenant_id {
+        q = q.filter(audit_log::dsl::tenant_id.eq(tenant_id));
+    }
+
+    if let Some(user_id) = &filters.user_id {
+        q = q.filter(audit_log::dsl::user_id.eq(user_id));
+    }
+
+    if let Some(api_key_id) = &filters.api_key_id {
+        q = q.filter(audit_log::dsl::api_key_id.eq(api_key_id));
+    }
+
+    if let Some(method) = &filters.method {
+        q = q.filter(audit_log::dsl::method.eq(method));
+    }
+
+    if let Some(target_id) = &filters.target_id {
+        q = q.filter(audit_log::dsl::target_id.eq(target_id));
+    }
+
+    if let Some(start) = &filters.start {
+        q = q.filter(audit_log::dsl::created_at.ge(start));
+    }
+
+    if let Some(end) = &filters.end {
+        q = q.filter(audit_log::dsl::created_at.lt(end));
+    }
+
+    let items = q
+        .order_by(audit_log::dsl::created_at.desc())
+        .limit(limit)
+        .offset(offset)
+        .load(&mut get_async_db_conn().await?)
+        .await?;
+    Ok(items)
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::test;
+    use chrono::Duration;
+
+    struct FilterTest<'a> {
+        filters: Filters,
+        logs: Vec<&'a AuditLog>,
+        count: usize,
+        limit: i64,
+        offset: i64,
+    }
+
+    #[test]
+    fn test_changes() {
+        let changes = Changes::new()
+            .field("name", &"old-name", &"new-name")
+            .field("description", &"same", &"same")
+            .field("is_disabled", &false, &true);
+
+        assert_eq!(
+            serde_json::json!({
+                "name": {
+                    "old": "old-name",
+                    "new": "new-name",
+                },
+                "is_disabled": {
+                    "old": false,
+                    "new": true,
+                },
+            }),
+            serde_json::Value::from(changes)
+        );
+
+        assert!(Changes::new().field("name", &"a", &"a").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_audit_log() {
+        let _guard = test::prepare().await;
+
+        let tenant_id = Uuid::new_v4();
+        let user_id = Uuid::new_v4();
+        let api_key_id = Uuid::new_v4();
+
+        let a1 = create(AuditLog {
+            created_at: Utc::now() - Duration::try_minutes(10).unwrap(),
+            tenant_id: Some(tenant_id),
+            user_id: Some(user_id),
+            service: "api.DeviceService".into(),
+            method: "Update".into(),
+            target_id: "0102030405060708".into(),
+            changes: Changes::new().field("name", &"a", &"b").into(),
+            source_ip: "127.0.0.1".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let a2 = create(AuditLog {
+            tenant_id: Some(tenant_id),
+            api_key_id: Some(api_key_id),
+            service: "api.DeviceService".into(),
+            method: "Delete".into(),
+            target_id: "0102030405060708".into(),
+            source_ip: "127.0.0.1".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let a3 = create(AuditLog {
+            user_id: Some(user_id),
+            service: "api.UserService".into(),
+            method: "Create".into(),
+            target_id: Uuid::new_v4().to_string(),
+            source_ip: "127.0.0.1".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // service must be set
+        assert!(create(AuditLog {
+            method: "Create".into(),
+            ..Default::default()
+        })
+        .await
+        .is_err());
+
+        let tests = vec![
+            FilterTest {
+                filters: Filters::default(),
+                logs: vec![&a3, &a2, &a1],
+                count: 3,
+                limit: 10,
+                offset: 0,
+            },
+            FilterTest {
+                filters: Filters {
+                    tenant_id: Some(tenant_id),
+                    ..Default::default()
+                },
+                logs: vec![&a2, &a1],
+                count: 2,
+                limit: 10,
+                offset: 0,
+            },
+            FilterTest {
+                filters: Filters {
+                    tenant_id: Some(tenant_id),
+                    ..Default::default()
+                },
+                logs: vec![&a1],
+                count: 2,
+                limit: 10,
+                offset: 1,
+            },
+            FilterTest {
+                filters: Filters {
+                    user_id: Some(user_id),
+                    ..Default::default()
+                },
+                logs: vec![&a3, &a1],
+                count: 2,
+                limit: 10,
+                offset: 0,
+            },
+            FilterTest {
+                filters: Filters {
+                    api_key_id: Some(api_key_id),
+                    ..Default::default()
+                },
+                logs: vec![&a2],
+                count: 1,
+                limit: 10,
+                offset: 0,
+            },
+            FilterTest {
+                filters: Filters {
+                    tenant_id: Some(tenant_id),
+                    method: Some("Delete".into()),
+                    target_id: Some("0102030405060708".into()),
+                    ..Default::default()
+                },
+                logs: vec![&a2],
+                count: 1,
+                limit: 10,
+                offset: 0,
+            },
+            FilterTest {
+                filters: Filters {
+                    end: Some(Utc::now() - Duration::try_minutes(5).unwrap()),
+                    ..Default::default()
+                },
+                logs: vec![&a1],
+                count: 1,
+                limit: 10,
+                offset: 0,
+            },
+            FilterTest {
+                filters: Filters {
+                    start: Some(Utc::now() - Duration::try_minutes(5).unwrap()),
+                    ..Default::default()
+                },
+                logs: vec![&a3, &a2],
+                count: 2,
+                limit: 10,
+                offset: 0,
+            },
+        ];
+
+        for tst in tests {
+            let count = get_count(&tst.filters).await.unwrap() as usize;
+            assert_eq!(tst.count, count);
+
+            let items = list(tst.limit, tst.offset, &tst.filters).await.unwrap();
+            assert_eq!(
+                tst.logs.iter().map(|l| l.id).collect::<Vec<Uuid>>(),
+                items.iter().map(|l| l.id).collect::<Vec<Uuid>>()
+            );
+        }
+    }
+}
@@ -0,0 +1,607 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use diesel::{dsl, prelude::*};
+use diesel_async::RunQueryDsl;
+use tracing::info;
+use uuid::Uuid;
+
+use super::error::Error;
+use super::schema::{codec, codec_version, device_profile};
+use super::{fields, get_async_db_conn};
+
+#[derive(Clone, Queryable, Insertable, PartialEq, Eq, Debug)]
+#[diesel(table_name = codec)]
+pub struct Codec {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub name: String,
+    pub description: String,
+}
+
+impl Codec {
+    fn validate(&self) -> Result<(), Error> {
+        if self.name.is_empty() {
+            return Err(Error::Validation("name is not set".into()));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Codec {
+    fn default() -> Self {
+        let now = Utc::now();
+
+        Codec {
+            id: Uuid::new_v4(),
+            tenant_id: Uuid::nil(),
+            created_at: now,
+            updated_at: now,
+            name: "".into(),
+            description: "".into(),
+        }
+    }
+}
+
+#[derive(Clone, Queryable, Insertable, PartialEq, Eq, Debug)]
+#[diesel(table_name = codec_version)]
+pub struct CodecVersion {
+    pub id: Uuid,
+    pub codec_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub version: i32,
+    pub runtime: crate::codec::Codec,
+    pub script: String,
+    pub tests: fields::CodecTests,
+}
+
+impl Default for CodecVersion {
+    fn default() -> Self {
+        CodecVersion {
+            id: Uuid::new_v4(),
+            codec_id: Uuid::nil(),
+            created_at: Utc::now(),
+            version: 0,
+            runtime: crate::codec::Codec::NONE,
+            script: "".into(),
+            tests: fields::CodecTests::default(),
+        }
+    }
+}
+
+#[derive(Queryable, PartialEq, Eq, Debug)]
+pub struct CodecListItem {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub name: String,
+    pub latest_version: Option<i32>,
+}
+
+#[derive(Queryable, PartialEq, Eq, Debug)]
+pub struct CodecVersionListItem {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub version: i32,
+    pub runtime: crate::codec::Codec,
+    pub device_profile_count: i64,
+}
+
+#[derive(Queryable, PartialEq, Eq, Debug)]
+pub struct CodecDeviceProfileListItem {
+    pub device_profile_id: Uuid,
+    pub device_profile_name: String,
+    pub codec_version_id: Uuid,
+    pub version: i32,
+}
+
+#[derive(Default, Clone)]
+pub struct Filters {
+    pub tenant_id: Option<Uuid>,
+    pub search: Option<String>,
+}
+
+pub async fn create(c: Codec) -> Result<Codec, Error> {
+    c.validate()?;
+
+    let c: Codec = diesel::insert_into(codec::table)
+        .values(&c)
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, c.id.to_string()))?;
+    info!(id = %c.id, "Codec created");
+    Ok(c)
+}
+
+pub async fn get(id: &Uuid) -> Result<Codec, Error> {
+    let c = codec::dsl::codec
+        .find(&id)
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    Ok(c)
+}
+
+pub async fn update(c: Codec) -> Result<Codec, Error> {
+    c.validate()?;
+
+    let c: Codec = diesel::update(codec::dsl::codec.find(&c.id))
+        .set((
+            codec::updated_at.eq(Utc::now()),
+            codec::name.eq(&c.name),
+            codec::description.eq(&c.description),
+        ))
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, c.id.to_string()))?;
+    info!(id = %c.id, "Codec updated");
+    Ok(c)
+}
+
+pub async fn delete(id: &Uuid) -> Result<(), Error> {
+    let mut c = get_async_db_conn().await?;
+    let id = *id;
+    c.build_transaction()
+        .run::<(), Error, _>(|c| {
+            Box::pin(async move {
+                let dp_count: i64 = device_profile::dsl::device_profile
+                    .select(dsl::count_star())
+                    .inner_join(codec_version::table)
+                    .filter(codec_version::dsl::codec_id.eq(&id))
+                    .first(c)
+                    .await?;
+                if dp_count != 0 {
+                    return Err(Error::NotAllowed(
+                        "Codec is used by one or multiple device-profiles".into(),
+                    ));
+                }
+
+                let ra = diesel::delete(codec::dsl::codec.find(&id))
+                    .execute(c)
+                    .await?;
+                if ra == 0 {
+                    return Err(Error::NotFound(id.to_string()));
+                }
+
+                Ok(())
+            })
+        })
+        .await?;
+    info!(id = %id, "Codec deleted");
+    Ok(())
+}
+
+pub async fn get_count(filters: &Filters) -> Result<i64, Error> {
+    let mut q = codec::dsl::codec.select(dsl::count_star()).into_boxed();
+
+    if let Some(tenant_id) = &filters.tenant_id {
+        q = q.filter(codec::dsl::tenant_id.eq(tenant_id));
+    }
+
+    if let Some(search) = &filters.search {
+        q = q.filter(codec::dsl::name.ilike(format!("%{}%", search)));
+    }
+
+    Ok(q.first(&mut get_async_db_conn().await?).await?)
+}
+
+pub async fn list(limit: i64, offset: i64, filters: &Filters) -> Result<Vec<CodecListItem>, Error> {
+    let mut q = codec::dsl::codec
+        .select((
+            codec::id,
+            codec::created_at,
+            codec::updated_at,
+            codec::name,
+            codec_version::table
+                .select(dsl::max(codec_version::version))
+                .filter(codec_version::dsl::codec_id.eq(codec::dsl::id))
+                .single_value(),
+        ))
+        .into_boxed();
+
+    if let Some(tenant_id) = &filters.tenant_id {
+        q = q.filter(codec::dsl::tenant_id.eq(tenant_id));
+    }
+
+    if let Some(search) = &filters.search {
+        q = q.filter(codec::dsl::name.ilike(format!("%{}%", search)));
+    }
+
+    let items = q
+        .order_by(codec::dsl::name)
+        .limit(limit)
+        .offset(offset)
+        .load(&mut get_async_db_conn().await?)
+        .await?;
+    Ok(items)
+}
+
+// Create a new codec version. The version number is assigned automatically,
+// based on the latest version of the codec.
+pub async fn create_version(cv: CodecVersion) -> Result<CodecVersion, Error> {
+    let mut c = get_async_db_conn().await?;
+    let cv: CodecVersion = c
+        .build_transaction()
+        .run::<CodecVersion, Error, _>(|c| {
+            Box::pin(async move {
+                // use for update to lock the codec
+                let _: Uuid = codec::dsl::codec
+                    .select(codec::dsl::id)
+                    .find(&cv.codec_id)
+                    .for_update()
+                    .first(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, cv.codec_id.to_string()))?;
+
+                let latest_version: Option<i32> = codec_version::dsl::codec_version
+                    .select(dsl::max(codec_version::dsl::version))
+                    .filter(codec_version::dsl::codec_id.eq(&cv.codec_id))
+                    .first(c)
+                    .await?;
+
+                let cv = CodecVersion {
+                    version: latest_version.unwrap_or_default() + 1,
+                    ..cv
+                };
+
+                diesel::insert_into(codec_version::table)
+                    .values(&cv)
+                    .get_result(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, cv.id.to_string()))
+            })
+        })
+        .await?;
+    info!(id = %cv.id, codec_id = %cv.codec_id, version = cv.version, "Codec version created");
+    Ok(cv)
+}
+
+pub async fn get_version(id: &Uuid) -> Result<CodecVersion, Error> {
+    let cv = codec_version::dsl::codec_version
+        .find(&id)
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    Ok(cv)
+}
+
+pub async fn get_version_by_number(codec_id: &Uuid, version: i32) -> Result<CodecVersion, Error> {
+    let cv = codec_version::dsl::codec_version
+        .filter(codec_version::dsl::codec_id.eq(&codec_id))
+        .filter(codec_version::dsl::version.eq(version))
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, format!("{} (version: {})", codec_id, version)))?;
+    Ok(cv)
+}
+
+pub async fn get_version_count(codec_id: &Uuid) -> Result<i64, Error> {
+    let count = codec_version::dsl::codec_version
+        .select(dsl::count_star())
+        .filter(codec_version::dsl::codec_id.eq(&codec_id))
+        .first(&mut get_async_db_conn().await?)
+        .await?;
+    Ok(count)
+}
+
+pub async fn list_versions(
+    limit: i64,
+    offset: i64,
+    codec_id: &Uuid,
+) -> Result<Vec<CodecVersionListItem>, Error> {
+    let items = codec_version::dsl::codec_version
+        .select((
+            codec_version::id,
+            codec_version::created_at,
+            codec_version::version,
+            codec_version::runtime,
+            device_profile::table
+                .select(dsl::count_star())
+                .filter(device_profile::dsl::codec_version_id.eq(codec_version::dsl::id.nullable()))
+                .single_value()
+                .assume_not_null(),
+        ))
+        .filter(codec_version::dsl::codec_id.eq(&codec_id))
+        .order_by(codec_version::dsl::version.desc())
+        .limit(limit)
+        .offset(offset)
+        .load(&mut get_async_db_conn().await?)
+        .await?;
+    Ok(items)
+}
+
+pub async fn get_device_profile_count(codec_id: &Uuid) -> Result<i64, Error> {
+    let count = device_profile::dsl::device_profile
+        .select(dsl::count_star())
+        .inner_join(codec_version::table)
+        .filter(codec_version::dsl::codec_id.eq(&codec_id))
+        .first(&mut get_async_db_conn().await?)
+        .await?;
+    Ok(count)
+}
+
+pub async fn list_device_profiles(
+    limit: i64,
+    offset: i64,
+    codec_id: &Uuid,
+) -> Result<Vec<CodecDeviceProfileListItem>, Error> {
+    let items = device_profile::dsl::device_profile
+        .inner_join(codec_version::table)
+        .select((
+            device_profile::id,
+            device_profile::name,
+            codec_version::id,
+            codec_version::version,
+        ))
+        .filter(codec_version::dsl::codec_id.eq(&codec_id))
+        .order_by(device_profile::dsl::name)
+        .limit(limit)
+        .offset(offset)
+        .load(&mut get_async_db_conn().await?)
+        .await?;
+    Ok(items)
+}
+
+// Roll the device-profiles using a version of the given codec to the given
+// version. When from_versions is not empty, only the device-profiles using
+// one of these versions are updated. It returns the number of updated
+// device-profiles.
+pub async fn roll_device_profiles(
+    codec_id: &Uuid,
+    version: i32,
+    from_versions: &[i32],
+) -> Result<usize, Error> {
+    let cv = get_version_by_number(codec_id, version).await?;
+    let from_versions = from_versions.to_vec();
+
+    let mut c = get_async_db_conn().await?;
+    let count = c
+        .build_transaction()
+        .run::<usize, Error, _>(|c| {
+            Box::pin(async move {
+                let mut q = codec_version::dsl::codec_version
+                    .select(codec_version::dsl::id)
+                    .filter(codec_version::dsl::codec_id.eq(&cv.codec_id))
+                    .filter(codec_version::dsl::id.ne(&cv.id))
+                    .into_boxed();
+
+                if !from_versions.is_empty() {
+                    q = q.filter(codec_version::dsl::version.eq_any(from_versions));
+                }
+
+                let version_ids: Vec<Uuid> = q.load(c).await?;
+
+                diesel::update(
+                    device_profile::dsl::device_profile
+                        .filter(device_profile::dsl::codec_version_id.eq_any(version_ids)),
+                )
+                .set((
+                    device_profile::updated_at.eq(Utc::now()),
+                    device_profile::codec_version_id.eq(&cv.id),
+                    device_profile::payload_codec_runtime.eq(&cv.runtime),
+                    device_profile::payload_codec_script.eq(&cv.script),
+                ))
+                .execute(c)
+                .await
+                .map_err(Error::Diesel)
+            })
+        })
+        .await?;
+    info!(codec_id = %codec_id, version = version, updated_count = count, "Device-profiles rolled to codec version");
+    Ok(count)
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage;
+    use crate::test;
+
+    struct FilterTest<'a> {
+        filters: Filters,
+        codecs: Vec<&'a Codec>,
+        count: usize,
+        limit: i64,
+        offset: i64,
+    }
+
+    pub async fn create_codec(tenant_id: Option<Uuid>) -> Codec {
+        let tenant_id = match tenant_id {
+            Some(v) => v,
+            None => {
+                let t = storage::tenant::test::create_tenant().await;
+                t.id
+            }
+        };
+
+        let c = Codec {
+            tenant_id,
+            name: "test codec".into(),
+            ..Default::default()
+        };
+
+        create(c).await.unwrap()
+    }
+
+    pub async fn create_codec_version(codec_id: Uuid) -> CodecVersion {
+        create_version(CodecVersion {
+            codec_id,
+            runtime: crate::codec::Codec::JS,
+            script: r#"
+                function decodeUplink(input) {
+                    return {
+                        data: {
+                            bytes: input.bytes
+                        }
+                    };
+                }
+            "#
+            .into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_codec() {
+        let _guard = test::prepare().await;
+        let mut c = create_codec(None).await;
+
+        // get
+        let c_get = get(&c.id).await.unwrap();
+        assert_eq!(c, c_get);
+
+        // update
+        c.name = "updated codec".into();
+        c = update(c).await.unwrap();
+        let c_get = get(&c.id).await.unwrap();
+        assert_eq!(c, c_get);
+
+        // get count and list
+        let tests = vec![
+            FilterTest {
+                filters: Filters {
+                    tenant_id: None,
+                    search: None,
+                },
+                codecs: vec![&c],
+                count: 1,
+                limit: 10,
+                offset: 0,
+            },
+            FilterTest {
+                filters: Filters {
+                    tenant_id: None,
+                    search: Some("foo".into()),
+                },
+                codecs: vec![],
+                count: 0,
+                limit: 10,
+                offset: 0,
+            },
+            FilterTest {
+                filters: Filters {
+                    tenant_id: None,
+                    search: Some("cod".into()),
+                },
+                codecs: vec![&c],
+                count: 1,
+                limit: 10,
+                offset: 0,
+            },
+            FilterTest {
+                filters: Filters {
+                    tenant_id: Some(c.tenant_id),
+                    search: None,
+                },
+                codecs: vec![&c],
+                count: 1,
+                limit: 10,
+                offset: 0,
+            },
+            FilterTest {
+                filters: Filters {
+                    tenant_id: Some(Uuid::new_v4()),
+                    search: None,
+                },
+                codecs: vec![],
+                count: 0,
+                limit: 10,
+                offset: 0,
+            },
+        ];
+
+        for tst in tests {
+            let count = get_count(&tst.filters).await.unwrap() as usize;
+            assert_eq!(tst.count, count);
+
+            let items = list(tst.limit, tst.offset, &tst.filters).await.unwrap();
+            assert_eq!(
+                tst.codecs
+                    .iter()
+                    .map(|c| { c.id.to_string() })
+                    .collect::<String>(),
+                items
+                    .iter()
+                    .map(|c| { c.id.to_string() })
+                    .collect::<String>()
+            );
+        }
+
+        // delete
+        delete(&c.id).await.unwrap();
+        assert!(delete(&c.id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_codec_version() {
+        let _guard = test::prepare().await;
+        let c = create_codec(None).await;
+
+        // create versions
+        let cv1 = create_codec_version(c.id).await;
+        assert_eq!(1, cv1.version);
+        let cv2 = create_codec_version(c.id).await;
+        assert_eq!(2, cv2.version);
+
+        // get
+        assert_eq!(cv1, get_version(&cv1.id).await.unwrap());
+        assert_eq!(cv2, get_version_by_number(&c.id, 2).await.unwrap());
+        assert!(get_version_by_number(&c.id, 3).await.is_err());
+
+        // latest version
+        let items = list(10, 0, &Filters::default()).await.unwrap();
+        assert_eq!(Some(2), items[0].latest_version);
+
+        // assign device-profiles
+        let mut dp1 = storage::device_profile::test::create_device_profile(Some(c.tenant_id)).await;
+        dp1.codec_version_id = Some(cv1.id);
+        let dp1 = storage::device_profile::update(dp1).await.unwrap();
+        assert_eq!(cv1.script, dp1.payload_codec_script);
+
+        let mut dp2 = storage::device_profile::test::create_device_profile(Some(c.tenant_id)).await;
+        dp2.codec_version_id = Some(cv2.id);
+        storage::device_profile::update(dp2).await.unwrap();
+
+        // device-profile of other tenant
+        let mut dp3 = storage::device_profile::test::create_device_profile(None).await;
+        dp3.codec_version_id = Some(cv1.id);
+        assert!(storage::device_profile::update(dp3).await.is_err());
+
+        // list versions
+        assert_eq!(2, get_version_count(&c.id).await.unwrap());
+        let items = list_versions(10, 0, &c.id).await.unwrap();
+        assert_eq!(
+            vec![(2, 1), (1, 1)],
+            items
+                .iter()
+                .map(|v| (v.version, v.device_profile_count))
+                .collect::<Vec<(i32, i64)>>()
+        );
+
+        // list device-profiles
+        assert_eq!(2, get_device_profile_count(&c.id).await.unwrap());
+        let items = list_device_profiles(10, 0, &c.id).await.unwrap();
+        assert_eq!(2, items.len());
+
+        // delete is not allowed while in use
+        assert!(delete(&c.id).await.is_err());
+
+        // roll from version 1 to 3
+        let cv3 = create_codec_version(c.id).await;
+        assert_eq!(1, roll_device_profiles(&c.id, 3, &[1]).await.unwrap());
+        let dp1_get = storage::device_profile::get(&dp1.id).await.unwrap();
+        assert_eq!(Some(cv3.id), dp1_get.codec_version_id);
+
+        // roll all to version 3
+        assert_eq!(1, roll_device_profiles(&c.id, 3, &[]).await.unwrap());
+        let items = list_versions(10, 0, &c.id).await.unwrap();
+        assert_eq!(2, items[0].device_profile_count);
+
+        // roll to non-existing version
+        assert!(roll_device_profiles(&c.id, 4, &[]).await.is_err());
+    }
+}
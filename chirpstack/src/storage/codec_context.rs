@@ -0,0 +1,117 @@
+use std::io::Cursor;
+
+use anyhow::{Context, Result};
+use prost::Message;
+use tracing::info;
+
+use super::{get_async_redis_conn, redis_key};
+use crate::config;
+use lrwn::EUI64;
+
+pub async fn get(dev_eui: &EUI64) -> Result<pbjson_types::Struct> {
+    let key = redis_key(format!("device:{{{}}}:codec:context", dev_eui));
+
+    let b: Vec<u8> = redis::cmd("GET")
+        .arg(key)
+        .query_async(&mut get_async_redis_conn().await?)
+        .await
+        .context("Get codec context")?;
+    if b.is_empty() {
+        return Ok(Default::default());
+    }
+
+    pbjson_types::Struct::decode(&mut Cursor::new(b)).context("Decode codec context")
+}
+
+pub async fn set(dev_eui: &EUI64, context: &pbjson_types::Struct) -> Result<()> {
+    let conf = config::get();
+    let key = redis_key(format!("device:{{{}}}:codec:context", dev_eui));
+    let ttl = conf.codec.js.context_ttl.as_millis() as usize;
+    let b = context.encode_to_vec();
+
+    if b.len() > conf.codec.js.max_context_size {
+        return Err(anyhow!(
+            "Codec context size exceeds max. context size ({} > {} bytes)",
+            b.len(),
+            conf.codec.js.max_context_size
+        ));
+    }
+
+    redis::cmd("PSETEX")
+        .arg(key)
+        .arg(ttl)
+        .arg(b)
+        .query_async(&mut get_async_redis_conn().await?)
+        .await?;
+
+    info!(dev_eui = %dev_eui, "Codec context saved");
+    Ok(())
+}
+
+pub async fn delete(dev_eui: &EUI64) -> Result<()> {
+    let key = redis_key(format!("device:{{{}}}:codec:context", dev_eui));
+
+    redis::cmd("DEL")
+        .arg(key)
+        .query_async(&mut get_async_redis_conn().await?)
+        .await?;
+
+    info!(dev_eui = %dev_eui, "Codec context deleted");
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::test;
+
+    #[tokio::test]
+    async fn test_codec_context() {
+        let _guard = test::prepare().await;
+
+        let dev_eui = EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]);
+
+        // get empty
+        let ctx = get(&dev_eui).await.unwrap();
+        assert_eq!(pbjson_types::Struct::default(), ctx);
+
+        // set
+        let ctx = pbjson_types::Struct {
+            fields: [(
+                "counter".to_string(),
+                pbjson_types::Value {
+                    kind: Some(pbjson_types::value::Kind::NumberValue(3.0)),
+                },
+            )]
+            .iter()
+            .cloned()
+            .collect(),
+        };
+        set(&dev_eui, &ctx).await.unwrap();
+
+        // get
+        let ctx_get = get(&dev_eui).await.unwrap();
+        assert_eq!(ctx, ctx_get);
+
+        // set exceeding max size
+        let ctx_large = pbjson_types::Struct {
+            fields: [(
+                "data".to_string(),
+                pbjson_types::Value {
+                    kind: Some(pbjson_types::value::Kind::StringValue("x".repeat(5000))),
+                },
+            )]
+            .iter()
+            .cloned()
+            .collect(),
+        };
+        assert!(set(&dev_eui, &ctx_large).await.is_err());
+        let ctx_get = get(&dev_eui).await.unwrap();
+        assert_eq!(ctx, ctx_get);
+
+        // delete
+        delete(&dev_eui).await.unwrap();
+        let ctx_get = get(&dev_eui).await.unwrap();
+        assert_eq!(pbjson_types::Struct::default(), ctx_get);
+    }
+}
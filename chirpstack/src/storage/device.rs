@@ -241,6 +241,7 @@ pub async fn create(d: Device) -> Result<Device, Error> {
                         tenant::dsl::private_gateways_up,
                         tenant::dsl::private_gateways_down,
                         tenant::dsl::tags,
+                        tenant::dsl::require_totp,
                     ))
                     .inner_join(application::table)
                     .filter(application::dsl::id.eq(&d.application_id))
@@ -261,15 +262,20 @@ pub async fn create(d: Device) -> Result<Device, Error> {
                     ));
                 }
 
-                diesel::insert_into(device::table)
+                let d: Device = diesel::insert_into(device::table)
                     .values(&d)
                     .get_result(c)
                     .await
-                    .map_err(|e| Error::from_diesel(e, d.dev_eui.to_string()))
+                    .map_err(|e| Error::from_diesel(e, d.dev_eui.to_string()))?;
+
+                super::multicast::sync_device_memberships(c, &d.dev_eui).await?;
+
+                Ok(d)
             })
         })
         .await?;
     info!(dev_eui = %d.dev_eui, "Device created");
+
     Ok(d)
 }
 
@@ -509,23 +515,36 @@ pub async fn get_for_phypayload(
 pub async fn update(d: Device) -> Result<Device, Error> {
     d.validate()?;
 
-    let d: Device = diesel::update(device::dsl::device.find(&d.dev_eui))
-        .set((
-            device::updated_at.eq(Utc::now()),
-            device::application_id.eq(&d.application_id),
-            device::device_profile_id.eq(&d.device_profile_id),
-            device::name.eq(&d.name),
-            device::description.eq(&d.description),
-            device::skip_fcnt_check.eq(&d.skip_fcnt_check),
-            device::is_disabled.eq(&d.is_disabled),
-            device::tags.eq(&d.tags),
-            device::variables.eq(&d.variables),
-            device::join_eui.eq(&d.join_eui),
-        ))
-        .get_result(&mut get_async_db_conn().await?)
-        .await
-        .map_err(|e| Error::from_diesel(e, d.dev_eui.to_string()))?;
+    let mut c = get_async_db_conn().await?;
+    let d: Device = c
+        .build_transaction()
+        .run::<Device, Error, _>(|c| {
+            Box::pin(async move {
+                let d: Device = diesel::update(device::dsl::device.find(&d.dev_eui))
+                    .set((
+                        device::updated_at.eq(Utc::now()),
+                        device::application_id.eq(&d.application_id),
+                        device::device_profile_id.eq(&d.device_profile_id),
+                        device::name.eq(&d.name),
+                        device::description.eq(&d.description),
+                        device::skip_fcnt_check.eq(&d.skip_fcnt_check),
+                        device::is_disabled.eq(&d.is_disabled),
+                        device::tags.eq(&d.tags),
+                        device::variables.eq(&d.variables),
+                        device::join_eui.eq(&d.join_eui),
+                    ))
+                    .get_result(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, d.dev_eui.to_string()))?;
+
+                super::multicast::sync_device_memberships(c, &d.dev_eui).await?;
+
+                Ok(d)
+            })
+        })
+        .await?;
     info!(dev_eui = %d.dev_eui, "Device updated");
+
     Ok(d)
 }
 
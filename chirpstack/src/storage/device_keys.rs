@@ -20,6 +20,7 @@ pub struct DeviceKeys {
     pub app_key: AES128Key,
     pub dev_nonces: Vec<Option<i32>>,
     pub join_nonce: i32,
+    pub gen_app_key: AES128Key,
 }
 
 impl Default for DeviceKeys {
@@ -40,6 +41,10 @@ impl Default for DeviceKeys {
             ]),
             dev_nonces: Vec::new(),
             join_nonce: 0,
+            gen_app_key: AES128Key::from_bytes([
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00,
+            ]),
         }
     }
 }
@@ -10,10 +10,11 @@ use uuid::Uuid;
 use lrwn::region::{CommonName, MacVersion, Revision};
 
 use super::error::Error;
-use super::schema::device_profile;
-use super::{error, fields, get_async_db_conn};
+use super::schema::{device_profile, multicast_group};
+use super::{codec, error, fields, get_async_db_conn};
 use crate::api::helpers::ToProto;
 use crate::codec::Codec;
+use crate::config;
 use chirpstack_api::internal;
 
 #[derive(Clone, Queryable, Insertable, Debug, PartialEq, Eq)]
@@ -74,6 +75,9 @@ pub struct DeviceProfile {
     pub relay_overall_limit_bucket_size: i16,
     pub allow_roaming: bool,
     pub rx1_delay: i16,
+    pub codec_version_id: Option<Uuid>,
+    pub gateway_selection_strategy: Option<fields::GatewaySelectionStrategy>,
+    pub supports_clock_sync: bool,
 }
 
 impl DeviceProfile {
@@ -86,6 +90,25 @@ impl DeviceProfile {
             return Err(Error::Validation("RX1 Delay must be between 0 - 15".into()));
         }
 
+        // The preferred gateway tags are configured per region, therefore the PREFER_TAGS
+        // strategy can only be used when all the matching regions have these configured.
+        if self.gateway_selection_strategy == Some(fields::GatewaySelectionStrategy::PREFER_TAGS) {
+            let conf = config::get();
+            if conf
+                .regions
+                .iter()
+                .filter(|r| match &self.region_config_id {
+                    Some(region_config_id) => &r.id == region_config_id,
+                    None => r.common_name == self.region,
+                })
+                .any(|r| r.network.gateway_prefer_tags.is_empty())
+            {
+                return Err(Error::Validation(
+                    "PREFER_TAGS gateway selection strategy requires gateway_prefer_tags in the region configuration".into(),
+                ));
+            }
+        }
+
         Ok(())
     }
 }
@@ -150,6 +173,9 @@ impl Default for DeviceProfile {
             relay_overall_limit_bucket_size: 0,
             allow_roaming: false,
             rx1_delay: 0,
+            codec_version_id: None,
+            gateway_selection_strategy: None,
+            supports_clock_sync: false,
         }
     }
 }
@@ -203,8 +229,10 @@ pub struct Filters {
     pub search: Option<String>,
 }
 
-pub async fn create(dp: DeviceProfile) -> Result<DeviceProfile, Error> {
+pub async fn create(mut dp: DeviceProfile) -> Result<DeviceProfile, Error> {
     dp.validate()?;
+    let tenant_id = dp.tenant_id;
+    set_codec_from_version(&mut dp, &tenant_id).await?;
 
     let dp: DeviceProfile = diesel::insert_into(device_profile::table)
         .values(&dp)
@@ -224,8 +252,13 @@ pub async fn get(id: &Uuid) -> Result<DeviceProfile, Error> {
     Ok(dp)
 }
 
-pub async fn update(dp: DeviceProfile) -> Result<DeviceProfile, Error> {
+pub async fn update(mut dp: DeviceProfile) -> Result<DeviceProfile, Error> {
     dp.validate()?;
+    if dp.codec_version_id.is_some() {
+        // The tenant_id is not updated, therefore we must use the stored value.
+        let tenant_id = get(&dp.id).await?.tenant_id;
+        set_codec_from_version(&mut dp, &tenant_id).await?;
+    }
 
     let dp: DeviceProfile = diesel::update(device_profile::dsl::device_profile.find(&dp.id))
         .set((
@@ -287,6 +320,9 @@ pub async fn update(dp: DeviceProfile) -> Result<DeviceProfile, Error> {
             device_profile::relay_overall_limit_bucket_size.eq(&dp.relay_overall_limit_bucket_size),
             device_profile::allow_roaming.eq(&dp.allow_roaming),
             device_profile::rx1_delay.eq(&dp.rx1_delay),
+            device_profile::codec_version_id.eq(&dp.codec_version_id),
+            device_profile::gateway_selection_strategy.eq(&dp.gateway_selection_strategy),
+            device_profile::supports_clock_sync.eq(&dp.supports_clock_sync),
         ))
         .get_result(&mut get_async_db_conn().await?)
         .await
@@ -307,12 +343,35 @@ pub async fn set_measurements(id: Uuid, m: &fields::Measurements) -> Result<Devi
 }
 
 pub async fn delete(id: &Uuid) -> Result<(), Error> {
-    let ra = diesel::delete(device_profile::dsl::device_profile.find(&id))
-        .execute(&mut get_async_db_conn().await?)
+    let mut c = get_async_db_conn().await?;
+    c.build_transaction()
+        .run::<(), Error, _>(|c| {
+            Box::pin(async move {
+                // Disable the automatic membership of multicast-groups using this
+                // device-profile as selector. Else the foreign-key would set the selector
+                // to null, matching all the devices of the application.
+                diesel::update(
+                    multicast_group::dsl::multicast_group
+                        .filter(multicast_group::dsl::selector_device_profile_id.eq(&id)),
+                )
+                .set((
+                    multicast_group::dsl::auto_membership.eq(false),
+                    multicast_group::dsl::selector_device_profile_id.eq(None::<Uuid>),
+                ))
+                .execute(c)
+                .await?;
+
+                let ra = diesel::delete(device_profile::dsl::device_profile.find(&id))
+                    .execute(c)
+                    .await?;
+                if ra == 0 {
+                    return Err(error::Error::NotFound(id.to_string()));
+                }
+
+                Ok(())
+            })
+        })
         .await?;
-    if ra == 0 {
-        return Err(error::Error::NotFound(id.to_string()));
-    }
     info!(id = %id, "Device-profile deleted");
     Ok(())
 }
@@ -370,6 +429,26 @@ pub async fn list(
     Ok(items)
 }
 
+// In case the device-profile refers to a codec library version, this copies the codec runtime
+// and script of this version into the device-profile, such that the uplink and downlink
+// handling does not need to resolve the codec version.
+async fn set_codec_from_version(dp: &mut DeviceProfile, tenant_id: &Uuid) -> Result<(), Error> {
+    if let Some(codec_version_id) = &dp.codec_version_id {
+        let cv = codec::get_version(codec_version_id).await?;
+        let c = codec::get(&cv.codec_id).await?;
+        if c.tenant_id != *tenant_id {
+            return Err(Error::Validation(
+                "codec version belongs to a different tenant".into(),
+            ));
+        }
+
+        dp.payload_codec_runtime = cv.runtime;
+        dp.payload_codec_script = cv.script;
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 pub mod test {
     use super::*;
@@ -428,6 +507,28 @@ pub mod test {
         let dp_get = get(&dp.id).await.unwrap();
         assert_eq!(dp, dp_get);
 
+        // PREFER_TAGS requires prefer tags in the region configuration
+        assert!(update(DeviceProfile {
+            gateway_selection_strategy: Some(fields::GatewaySelectionStrategy::PREFER_TAGS),
+            ..dp.clone()
+        })
+        .await
+        .is_err());
+
+        let mut conf = (*config::get()).clone();
+        conf.regions[0]
+            .network
+            .gateway_prefer_tags
+            .insert("site".into(), "a".into());
+        config::set(conf);
+
+        dp = update(DeviceProfile {
+            gateway_selection_strategy: Some(fields::GatewaySelectionStrategy::PREFER_TAGS),
+            ..dp
+        })
+        .await
+        .unwrap();
+
         // get count and list
         let tests = vec![
             FilterTest {
@@ -23,10 +23,12 @@ pub struct DeviceQueueItem {
     pub f_cnt_down: Option<i64>,
     pub timeout_after: Option<DateTime<Utc>>,
     pub is_encrypted: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub priority: i32,
 }
 
 impl DeviceQueueItem {
-    fn validate(&self) -> Result<(), Error> {
+    pub fn validate(&self) -> Result<(), Error> {
         if self.f_port == 0 || self.f_port > 255 {
             return Err(Error::Validation(
                 "FPort must be between 1 - 255".to_string(),
@@ -39,8 +41,29 @@ impl DeviceQueueItem {
             ));
         }
 
+        if self.priority < 0 {
+            return Err(Error::Validation(
+                "Priority must be equal or greater than 0".to_string(),
+            ));
+        }
+
+        // Encrypted queue-items must be sent in the order of their frame-counter.
+        if self.is_encrypted && self.priority != 0 {
+            return Err(Error::Validation(
+                "Priority can not be set for encrypted queue-items".to_string(),
+            ));
+        }
+
         Ok(())
     }
+
+    // Returns true when the queue-item has expired.
+    pub fn is_expired(&self) -> bool {
+        match &self.expires_at {
+            Some(v) => v <= &Utc::now(),
+            None => false,
+        }
+    }
 }
 
 impl Default for DeviceQueueItem {
@@ -58,6 +81,8 @@ impl Default for DeviceQueueItem {
             f_cnt_down: None,
             timeout_after: None,
             is_encrypted: false,
+            expires_at: None,
+            priority: 0,
         }
     }
 }
@@ -110,10 +135,16 @@ pub async fn delete_item(id: &Uuid) -> Result<(), Error> {
 }
 
 /// It returns the device queue-item and a bool indicating if there are more items in the queue.
+/// A pending queue-item is always returned first, then the queue-items are ordered by priority
+/// (highest first) and creation time.
 pub async fn get_next_for_dev_eui(dev_eui: &EUI64) -> Result<(DeviceQueueItem, bool), Error> {
     let items: Vec<DeviceQueueItem> = device_queue_item::dsl::device_queue_item
         .filter(device_queue_item::dev_eui.eq(&dev_eui))
-        .order_by(device_queue_item::created_at)
+        .order_by((
+            device_queue_item::is_pending.desc(),
+            device_queue_item::priority.desc(),
+            device_queue_item::created_at,
+        ))
         .limit(2)
         .load(&mut get_async_db_conn().await?)
         .await
@@ -141,13 +172,50 @@ pub async fn get_next_for_dev_eui(dev_eui: &EUI64) -> Result<(DeviceQueueItem, b
 pub async fn get_for_dev_eui(dev_eui: &EUI64) -> Result<Vec<DeviceQueueItem>, Error> {
     let items = device_queue_item::dsl::device_queue_item
         .filter(device_queue_item::dev_eui.eq(&dev_eui))
-        .order_by(device_queue_item::created_at)
+        .order_by((
+            device_queue_item::is_pending.desc(),
+            device_queue_item::priority.desc(),
+            device_queue_item::created_at,
+        ))
         .load(&mut get_async_db_conn().await?)
         .await
         .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))?;
     Ok(items)
 }
 
+/// It deletes and returns (at most limit) queue-items that have expired. Pending queue-items
+/// are not returned, as these are handled by the downlink (timeout) flow.
+pub async fn delete_expired(limit: usize) -> Result<Vec<DeviceQueueItem>, Error> {
+    let mut c = get_async_db_conn().await?;
+    c.build_transaction()
+        .run::<Vec<DeviceQueueItem>, Error, _>(|c| {
+            Box::pin(async move {
+                // Lock the records with skip locked such that other ChirpStack instances
+                // are able to delete the remaining items.
+                let ids: Vec<Uuid> = device_queue_item::dsl::device_queue_item
+                    .select(device_queue_item::dsl::id)
+                    .filter(device_queue_item::dsl::is_pending.eq(false))
+                    .filter(device_queue_item::dsl::expires_at.le(Utc::now()))
+                    .order_by(device_queue_item::dsl::expires_at)
+                    .limit(limit as i64)
+                    .for_update()
+                    .skip_locked()
+                    .load(c)
+                    .await?;
+
+                let items: Vec<DeviceQueueItem> = diesel::delete(
+                    device_queue_item::dsl::device_queue_item
+                        .filter(device_queue_item::dsl::id.eq_any(&ids)),
+                )
+                .get_results(c)
+                .await?;
+
+                Ok(items)
+            })
+        })
+        .await
+}
+
 pub async fn flush_for_dev_eui(dev_eui: &EUI64) -> Result<(), Error> {
     let count: usize = diesel::delete(
         device_queue_item::dsl::device_queue_item.filter(device_queue_item::dev_eui.eq(&dev_eui)),
@@ -214,6 +282,28 @@ pub mod test {
         };
         assert!(enqueue_item(qi).await.is_err());
 
+        // invalid priority
+        let qi = DeviceQueueItem {
+            dev_eui: d.dev_eui,
+            f_port: 10,
+            priority: -1,
+            data: vec![0x01, 0x02, 0x03],
+            ..Default::default()
+        };
+        assert!(enqueue_item(qi).await.is_err());
+
+        // priority for encrypted item
+        let qi = DeviceQueueItem {
+            dev_eui: d.dev_eui,
+            f_port: 10,
+            priority: 10,
+            is_encrypted: true,
+            f_cnt_down: Some(10),
+            data: vec![0x01, 0x02, 0x03],
+            ..Default::default()
+        };
+        assert!(enqueue_item(qi).await.is_err());
+
         // create
         let mut qi = DeviceQueueItem {
             dev_eui: d.dev_eui,
@@ -301,4 +391,100 @@ pub mod test {
         let max_f_cnt = get_max_f_cnt_down(d.dev_eui).await.unwrap();
         assert_eq!(Some(10), max_f_cnt);
     }
+
+    #[tokio::test]
+    async fn test_queue_priority() {
+        let _guard = test::prepare().await;
+        let dp = storage::device_profile::test::create_device_profile(None).await;
+        let d = storage::device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            None,
+        )
+        .await;
+
+        let mut items: Vec<DeviceQueueItem> = Vec::new();
+        for priority in [0, 0, 10] {
+            items.push(
+                enqueue_item(DeviceQueueItem {
+                    dev_eui: d.dev_eui,
+                    f_port: 10,
+                    priority,
+                    ..Default::default()
+                })
+                .await
+                .unwrap(),
+            );
+        }
+
+        // highest priority first, then by creation time
+        let queue = get_for_dev_eui(&d.dev_eui).await.unwrap();
+        assert_eq!(
+            vec![items[2].id, items[0].id, items[1].id],
+            queue.iter().map(|qi| qi.id).collect::<Vec<Uuid>>()
+        );
+        let resp = get_next_for_dev_eui(&d.dev_eui).await.unwrap();
+        assert_eq!(items[2].id, resp.0.id);
+        assert!(resp.1);
+
+        // a pending item is returned first, regardless its priority
+        let mut qi = items[1].clone();
+        qi.is_pending = true;
+        qi.timeout_after = Some(Utc::now() - chrono::Duration::seconds(1));
+        update_item(qi).await.unwrap();
+        let resp = get_next_for_dev_eui(&d.dev_eui).await.unwrap();
+        assert_eq!(items[1].id, resp.0.id);
+    }
+
+    #[tokio::test]
+    async fn test_delete_expired() {
+        let _guard = test::prepare().await;
+        let dp = storage::device_profile::test::create_device_profile(None).await;
+        let d = storage::device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            None,
+        )
+        .await;
+
+        let mut items: Vec<DeviceQueueItem> = Vec::new();
+        for (expires_at, is_pending) in [
+            (None, false),
+            (Some(Utc::now() + chrono::Duration::seconds(10)), false),
+            (Some(Utc::now() - chrono::Duration::seconds(10)), false),
+            (Some(Utc::now() - chrono::Duration::seconds(10)), true),
+        ] {
+            items.push(
+                enqueue_item(DeviceQueueItem {
+                    dev_eui: d.dev_eui,
+                    f_port: 10,
+                    expires_at,
+                    is_pending,
+                    ..Default::default()
+                })
+                .await
+                .unwrap(),
+            );
+        }
+
+        // only the expired and not pending item is deleted
+        let deleted = delete_expired(10).await.unwrap();
+        assert_eq!(vec![items[2].clone()], deleted);
+        assert!(get_item(&items[2].id).await.is_err());
+        assert_eq!(3, get_for_dev_eui(&d.dev_eui).await.unwrap().len());
+
+        assert!(delete_expired(10).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_is_expired() {
+        let mut qi = DeviceQueueItem::default();
+        assert!(!qi.is_expired());
+
+        qi.expires_at = Some(Utc::now() + chrono::Duration::seconds(10));
+        assert!(!qi.is_expired());
+
+        qi.expires_at = Some(Utc::now() - chrono::Duration::seconds(10));
+        assert!(qi.is_expired());
+    }
 }
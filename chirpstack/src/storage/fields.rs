@@ -9,7 +9,7 @@ use diesel::sql_types::{Jsonb, Text};
 use diesel::{deserialize, serialize};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, AsExpression, FromSqlRow)]
 #[diesel(sql_type = Jsonb)]
 pub struct KeyValue(HashMap<String, String>);
 
@@ -175,3 +175,315 @@ impl FromStr for MulticastGroupSchedulingType {
         })
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, AsExpression, FromSqlRow)]
+#[allow(clippy::upper_case_acronyms)]
+#[allow(non_camel_case_types)]
+#[diesel(sql_type = diesel::sql_types::Text)]
+pub enum ApiKeyScope {
+    // Full access.
+    FULL,
+    // Read and list access only.
+    READ_ONLY,
+    // Enqueue device-queue items only.
+    DEVICE_QUEUE_ENQUEUE,
+}
+
+impl fmt::Display for ApiKeyScope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl<DB> deserialize::FromSql<Text, DB> for ApiKeyScope
+where
+    DB: Backend,
+    *const str: deserialize::FromSql<Text, DB>,
+{
+    fn from_sql(value: <DB as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
+        let string = <*const str>::from_sql(value)?;
+        Ok(Self::from_str(unsafe { &*string })?)
+    }
+}
+
+impl serialize::ToSql<Text, diesel::pg::Pg> for ApiKeyScope
+where
+    str: serialize::ToSql<Text, diesel::pg::Pg>,
+{
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut serialize::Output<'b, '_, diesel::pg::Pg>,
+    ) -> serialize::Result {
+        <str as serialize::ToSql<Text, diesel::pg::Pg>>::to_sql(
+            &self.to_string(),
+            &mut out.reborrow(),
+        )
+    }
+}
+
+impl FromStr for ApiKeyScope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s {
+            "FULL" => ApiKeyScope::FULL,
+            "READ_ONLY" => ApiKeyScope::READ_ONLY,
+            "DEVICE_QUEUE_ENQUEUE" => ApiKeyScope::DEVICE_QUEUE_ENQUEUE,
+            _ => {
+                return Err(anyhow!("Unexpected ApiKeyScope: {}", s));
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, AsExpression, FromSqlRow)]
+#[allow(clippy::upper_case_acronyms)]
+#[allow(non_camel_case_types)]
+#[diesel(sql_type = diesel::sql_types::Text)]
+pub enum FragmentationSessionStatus {
+    // Waiting for the FragSessionSetupAns.
+    PENDING,
+    // Session has been setup by the device.
+    ACTIVE,
+    // Device reported that all fragments have been received.
+    COMPLETED,
+    // Session setup failed or the device reported missing fragments.
+    FAILED,
+}
+
+impl fmt::Display for FragmentationSessionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl<DB> deserialize::FromSql<Text, DB> for FragmentationSessionStatus
+where
+    DB: Backend,
+    *const str: deserialize::FromSql<Text, DB>,
+{
+    fn from_sql(value: <DB as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
+        let string = <*const str>::from_sql(value)?;
+        Ok(Self::from_str(unsafe { &*string })?)
+    }
+}
+
+impl serialize::ToSql<Text, diesel::pg::Pg> for FragmentationSessionStatus
+where
+    str: serialize::ToSql<Text, diesel::pg::Pg>,
+{
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut serialize::Output<'b, '_, diesel::pg::Pg>,
+    ) -> serialize::Result {
+        <str as serialize::ToSql<Text, diesel::pg::Pg>>::to_sql(
+            &self.to_string(),
+            &mut out.reborrow(),
+        )
+    }
+}
+
+impl FromStr for FragmentationSessionStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s {
+            "PENDING" => FragmentationSessionStatus::PENDING,
+            "ACTIVE" => FragmentationSessionStatus::ACTIVE,
+            "COMPLETED" => FragmentationSessionStatus::COMPLETED,
+            "FAILED" => FragmentationSessionStatus::FAILED,
+            _ => {
+                return Err(anyhow!("Unexpected FragmentationSessionStatus: {}", s));
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, AsExpression, FromSqlRow, PartialEq, Eq)]
+#[diesel(sql_type = Jsonb)]
+pub struct CodecTests(Vec<CodecTest>);
+
+impl CodecTests {
+    pub fn new(t: Vec<CodecTest>) -> Self {
+        CodecTests(t)
+    }
+}
+
+impl Deref for CodecTests {
+    type Target = Vec<CodecTest>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for CodecTests {
+    fn deref_mut(&mut self) -> &mut Vec<CodecTest> {
+        &mut self.0
+    }
+}
+
+impl deserialize::FromSql<Jsonb, Pg> for CodecTests {
+    fn from_sql(value: <Pg as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
+        let value = <serde_json::Value as deserialize::FromSql<Jsonb, Pg>>::from_sql(value)?;
+        let t: Vec<CodecTest> = serde_json::from_value(value)?;
+        Ok(CodecTests::new(t))
+    }
+}
+
+impl serialize::ToSql<Jsonb, Pg> for CodecTests {
+    fn to_sql(&self, out: &mut serialize::Output<'_, '_, Pg>) -> serialize::Result {
+        let value = serde_json::to_value(&self.0)?;
+        <serde_json::Value as serialize::ToSql<Jsonb, Pg>>::to_sql(&value, &mut out.reborrow())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct CodecTest {
+    pub name: String,
+    pub f_port: u8,
+    pub data: Vec<u8>,
+    // Expected decoded object.
+    pub object: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, AsExpression, FromSqlRow)]
+#[allow(clippy::upper_case_acronyms)]
+#[allow(non_camel_case_types)]
+#[diesel(sql_type = diesel::sql_types::Text)]
+pub enum FuotaCampaignStep {
+    // Campaign has been created, but has not yet been started.
+    CREATED,
+    // Setup the multicast-group on the devices (McGroupSetupReq).
+    MC_GROUP_SETUP,
+    // Setup the fragmentation-session on the devices (FragSessionSetupReq).
+    FRAG_SESSION_SETUP,
+    // Setup the multicast-session on the devices (McClassB/CSessionReq).
+    MC_SESSION,
+    // Enqueue the fragments to the multicast-group queue.
+    ENQUEUE,
+    // Request the fragmentation-session status (FragSessionStatusReq).
+    FRAG_STATUS,
+    // Campaign has been completed.
+    COMPLETED,
+}
+
+impl fmt::Display for FuotaCampaignStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl<DB> deserialize::FromSql<Text, DB> for FuotaCampaignStep
+where
+    DB: Backend,
+    *const str: deserialize::FromSql<Text, DB>,
+{
+    fn from_sql(value: <DB as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
+        let string = <*const str>::from_sql(value)?;
+        Ok(Self::from_str(unsafe { &*string })?)
+    }
+}
+
+impl serialize::ToSql<Text, diesel::pg::Pg> for FuotaCampaignStep
+where
+    str: serialize::ToSql<Text, diesel::pg::Pg>,
+{
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut serialize::Output<'b, '_, diesel::pg::Pg>,
+    ) -> serialize::Result {
+        <str as serialize::ToSql<Text, diesel::pg::Pg>>::to_sql(
+            &self.to_string(),
+            &mut out.reborrow(),
+        )
+    }
+}
+
+impl FromStr for FuotaCampaignStep {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s {
+            "CREATED" => FuotaCampaignStep::CREATED,
+            "MC_GROUP_SETUP" => FuotaCampaignStep::MC_GROUP_SETUP,
+            "FRAG_SESSION_SETUP" => FuotaCampaignStep::FRAG_SESSION_SETUP,
+            "MC_SESSION" => FuotaCampaignStep::MC_SESSION,
+            "ENQUEUE" => FuotaCampaignStep::ENQUEUE,
+            "FRAG_STATUS" => FuotaCampaignStep::FRAG_STATUS,
+            "COMPLETED" => FuotaCampaignStep::COMPLETED,
+            _ => {
+                return Err(anyhow!("Unexpected FuotaCampaignStep: {}", s));
+            }
+        })
+    }
+}
+
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, AsExpression, FromSqlRow,
+)]
+#[allow(clippy::upper_case_acronyms)]
+#[allow(non_camel_case_types)]
+#[diesel(sql_type = diesel::sql_types::Text)]
+pub enum GatewaySelectionStrategy {
+    // Random gateway within the min. SNR margin.
+    #[default]
+    RANDOM,
+    // Gateway with the best SNR.
+    BEST_SNR,
+    // Gateway within the min. SNR margin with the lowest duty-cycle usage.
+    LEAST_LOADED,
+    // Round-robin between the gateways within the min. SNR margin.
+    ROUND_ROBIN,
+    // Gateway within the min. SNR margin having the preferred tags.
+    PREFER_TAGS,
+}
+
+impl fmt::Display for GatewaySelectionStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl<DB> deserialize::FromSql<Text, DB> for GatewaySelectionStrategy
+where
+    DB: Backend,
+    *const str: deserialize::FromSql<Text, DB>,
+{
+    fn from_sql(value: <DB as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
+        let string = <*const str>::from_sql(value)?;
+        Ok(Self::from_str(unsafe { &*string })?)
+    }
+}
+
+impl serialize::ToSql<Text, diesel::pg::Pg> for GatewaySelectionStrategy
+where
+    str: serialize::ToSql<Text, diesel::pg::Pg>,
+{
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut serialize::Output<'b, '_, diesel::pg::Pg>,
+    ) -> serialize::Result {
+        <str as serialize::ToSql<Text, diesel::pg::Pg>>::to_sql(
+            &self.to_string(),
+            &mut out.reborrow(),
+        )
+    }
+}
+
+impl FromStr for GatewaySelectionStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s {
+            "RANDOM" => GatewaySelectionStrategy::RANDOM,
+            "BEST_SNR" => GatewaySelectionStrategy::BEST_SNR,
+            "LEAST_LOADED" => GatewaySelectionStrategy::LEAST_LOADED,
+            "ROUND_ROBIN" => GatewaySelectionStrategy::ROUND_ROBIN,
+            "PREFER_TAGS" => GatewaySelectionStrategy::PREFER_TAGS,
+            _ => {
+                return Err(anyhow!("Unexpected GatewaySelectionStrategy: {}", s));
+            }
+        })
+    }
+}
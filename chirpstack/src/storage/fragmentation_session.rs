@@ -0,0 +1,381 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use tracing::info;
+use uuid::Uuid;
+
+use lrwn::EUI64;
+
+use super::device_queue::DeviceQueueItem;
+use super::error::Error;
+use super::fields::FragmentationSessionStatus;
+use super::get_async_db_conn;
+use super::schema::{device_queue_item, fragmentation_session};
+
+#[derive(Queryable, Insertable, AsChangeset, PartialEq, Eq, Debug, Clone)]
+#[diesel(table_name = fragmentation_session)]
+pub struct FragmentationSession {
+    pub id: Uuid,
+    pub dev_eui: EUI64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub frag_index: i16,
+    pub nb_frag: i32,
+    pub fragment_size: i16,
+    pub redundancy: i32,
+    pub padding: i16,
+    pub nb_frag_sent: i32,
+    pub nb_frag_received: Option<i32>,
+    pub missing_frag: Option<i32>,
+    pub status: FragmentationSessionStatus,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl FragmentationSession {
+    fn validate(&self) -> Result<(), Error> {
+        if !(0..=3).contains(&self.frag_index) {
+            return Err(Error::Validation(
+                "frag_index must be between 0 and 3".to_string(),
+            ));
+        }
+
+        if self.fragment_size < 1 || self.fragment_size > 255 {
+            return Err(Error::Validation(
+                "fragment_size must be between 1 and 255".to_string(),
+            ));
+        }
+
+        if self.nb_frag < 1 || self.nb_frag + self.redundancy > 16383 {
+            return Err(Error::Validation(
+                "The total number of fragments must be between 1 and 16383".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for FragmentationSession {
+    fn default() -> Self {
+        let now = Utc::now();
+
+        FragmentationSession {
+            id: Uuid::new_v4(),
+            dev_eui: EUI64::from_be_bytes([0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
+            created_at: now,
+            updated_at: now,
+            frag_index: 0,
+            nb_frag: 0,
+            fragment_size: 0,
+            redundancy: 0,
+            padding: 0,
+            nb_frag_sent: 0,
+            nb_frag_received: None,
+            missing_frag: None,
+            status: FragmentationSessionStatus::PENDING,
+            completed_at: None,
+        }
+    }
+}
+
+/// Creates the fragmentation-session and enqueues the given queue-items (containing the
+/// fragmentation payloads) within a single transaction.
+pub async fn create_and_enqueue(
+    fs: FragmentationSession,
+    items: Vec<DeviceQueueItem>,
+) -> Result<(FragmentationSession, Vec<DeviceQueueItem>), Error> {
+    fs.validate()?;
+    for qi in &items {
+        qi.validate()?;
+    }
+
+    let mut c = get_async_db_conn().await?;
+    let (fs, items) = c
+        .build_transaction()
+        .run::<(FragmentationSession, Vec<DeviceQueueItem>), Error, _>(|c| {
+            Box::pin(async move {
+                let fs: FragmentationSession = diesel::insert_into(fragmentation_session::table)
+                    .values(&fs)
+                    .get_result(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, fs.id.to_string()))?;
+
+                if items.is_empty() {
+                    return Ok((fs, items));
+                }
+
+                let items: Vec<DeviceQueueItem> = diesel::insert_into(device_queue_item::table)
+                    .values(&items)
+                    .get_results(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, fs.dev_eui.to_string()))?;
+
+                Ok((fs, items))
+            })
+        })
+        .await?;
+    info!(id = %fs.id, dev_eui = %fs.dev_eui, frag_index = fs.frag_index, queue_item_count = items.len(), "Fragmentation-session created");
+    Ok((fs, items))
+}
+
+pub async fn get(id: &Uuid) -> Result<FragmentationSession, Error> {
+    let fs = fragmentation_session::dsl::fragmentation_session
+        .find(id)
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    Ok(fs)
+}
+
+pub async fn update(fs: FragmentationSession) -> Result<FragmentationSession, Error> {
+    let fs: FragmentationSession =
+        diesel::update(fragmentation_session::dsl::fragmentation_session.find(&fs.id))
+            .set((
+                fragmentation_session::updated_at.eq(Utc::now()),
+                fragmentation_session::nb_frag_received.eq(&fs.nb_frag_received),
+                fragmentation_session::missing_frag.eq(&fs.missing_frag),
+                fragmentation_session::status.eq(&fs.status),
+                fragmentation_session::completed_at.eq(&fs.completed_at),
+            ))
+            .get_result(&mut get_async_db_conn().await?)
+            .await
+            .map_err(|e| Error::from_diesel(e, fs.id.to_string()))?;
+    info!(id = %fs.id, status = %fs.status, "Fragmentation-session updated");
+    Ok(fs)
+}
+
+// Returns the most recent active (pending or setup) session for the given DevEUI and
+// FragIndex.
+pub async fn get_active_for_dev_eui_and_frag_index(
+    dev_eui: &EUI64,
+    frag_index: u8,
+) -> Result<FragmentationSession, Error> {
+    let fs = fragmentation_session::dsl::fragmentation_session
+        .filter(fragmentation_session::dsl::dev_eui.eq(dev_eui))
+        .filter(fragmentation_session::dsl::frag_index.eq(frag_index as i16))
+        .filter(fragmentation_session::dsl::status.eq_any(&[
+            FragmentationSessionStatus::PENDING,
+            FragmentationSessionStatus::ACTIVE,
+        ]))
+        .order_by(fragmentation_session::dsl::created_at.desc())
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, format!("{}/{}", dev_eui, frag_index)))?;
+    Ok(fs)
+}
+
+pub async fn increment_nb_frag_sent(id: &Uuid) -> Result<FragmentationSession, Error> {
+    let fs: FragmentationSession =
+        diesel::update(fragmentation_session::dsl::fragmentation_session.find(id))
+            .set((
+                fragmentation_session::updated_at.eq(Utc::now()),
+                fragmentation_session::nb_frag_sent.eq(fragmentation_session::nb_frag_sent + 1),
+            ))
+            .get_result(&mut get_async_db_conn().await?)
+            .await
+            .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    Ok(fs)
+}
+
+// Sets the status of the active (pending or setup) sessions which have not been updated
+// within the given timeout to FAILED and returns these.
+pub async fn set_timed_out_as_failed(
+    timeout: Duration,
+) -> Result<Vec<FragmentationSession>, Error> {
+    let items: Vec<FragmentationSession> = diesel::update(
+        fragmentation_session::dsl::fragmentation_session
+            .filter(fragmentation_session::dsl::status.eq_any(&[
+                FragmentationSessionStatus::PENDING,
+                FragmentationSessionStatus::ACTIVE,
+            ]))
+            .filter(fragmentation_session::dsl::updated_at.lt(Utc::now() - timeout)),
+    )
+    .set((
+        fragmentation_session::updated_at.eq(Utc::now()),
+        fragmentation_session::status.eq(FragmentationSessionStatus::FAILED),
+    ))
+    .get_results(&mut get_async_db_conn().await?)
+    .await?;
+    Ok(items)
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage;
+    use crate::test;
+
+    #[tokio::test]
+    async fn test_fragmentation_session() {
+        let _guard = test::prepare().await;
+        let dp = storage::device_profile::test::create_device_profile(None).await;
+        let d = storage::device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            None,
+        )
+        .await;
+
+        // invalid frag_index
+        let fs = FragmentationSession {
+            dev_eui: d.dev_eui,
+            frag_index: 4,
+            nb_frag: 10,
+            fragment_size: 50,
+            ..Default::default()
+        };
+        assert!(create_and_enqueue(fs, vec![]).await.is_err());
+
+        // invalid nb_frag
+        let fs = FragmentationSession {
+            dev_eui: d.dev_eui,
+            nb_frag: 16000,
+            redundancy: 1000,
+            fragment_size: 50,
+            ..Default::default()
+        };
+        assert!(create_and_enqueue(fs, vec![]).await.is_err());
+
+        // create
+        let (mut fs, _) = create_and_enqueue(
+            FragmentationSession {
+                dev_eui: d.dev_eui,
+                frag_index: 1,
+                nb_frag: 10,
+                fragment_size: 50,
+                redundancy: 2,
+                ..Default::default()
+            },
+            vec![],
+        )
+        .await
+        .unwrap();
+
+        // get
+        let fs_get = get(&fs.id).await.unwrap();
+        assert_eq!(fs, fs_get);
+
+        // get active
+        let fs_get = get_active_for_dev_eui_and_frag_index(&d.dev_eui, 1)
+            .await
+            .unwrap();
+        assert_eq!(fs.id, fs_get.id);
+        assert!(get_active_for_dev_eui_and_frag_index(&d.dev_eui, 0)
+            .await
+            .is_err());
+
+        // increment nb_frag_sent
+        let fs_get = increment_nb_frag_sent(&fs.id).await.unwrap();
+        assert_eq!(1, fs_get.nb_frag_sent);
+
+        // update
+        fs.nb_frag_received = Some(12);
+        fs.missing_frag = Some(0);
+        fs.status = FragmentationSessionStatus::COMPLETED;
+        fs.completed_at = Some(Utc::now());
+        let fs_up = update(fs.clone()).await.unwrap();
+        assert_eq!(Some(12), fs_up.nb_frag_received);
+        assert_eq!(FragmentationSessionStatus::COMPLETED, fs_up.status);
+        assert_eq!(1, fs_up.nb_frag_sent);
+
+        // no longer active
+        assert!(get_active_for_dev_eui_and_frag_index(&d.dev_eui, 1)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_create_and_enqueue() {
+        let _guard = test::prepare().await;
+        let dp = storage::device_profile::test::create_device_profile(None).await;
+        let d = storage::device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            None,
+        )
+        .await;
+
+        let fs = FragmentationSession {
+            dev_eui: d.dev_eui,
+            nb_frag: 2,
+            fragment_size: 50,
+            ..Default::default()
+        };
+
+        // the queue-item refers to an unknown device, nothing is created
+        assert!(create_and_enqueue(
+            fs.clone(),
+            vec![DeviceQueueItem {
+                dev_eui: EUI64::from_be_bytes([8, 7, 6, 5, 4, 3, 2, 1]),
+                f_port: 201,
+                ..Default::default()
+            }],
+        )
+        .await
+        .is_err());
+        assert!(get(&fs.id).await.is_err());
+
+        let (fs, items) = create_and_enqueue(
+            fs,
+            (0..3)
+                .map(|_| DeviceQueueItem {
+                    dev_eui: d.dev_eui,
+                    f_port: 201,
+                    ..Default::default()
+                })
+                .collect(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(fs, get(&fs.id).await.unwrap());
+        assert_eq!(
+            items,
+            storage::device_queue::get_for_dev_eui(&d.dev_eui)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_timed_out_as_failed() {
+        let _guard = test::prepare().await;
+        let dp = storage::device_profile::test::create_device_profile(None).await;
+        let d = storage::device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id,
+            None,
+        )
+        .await;
+
+        let (fs, _) = create_and_enqueue(
+            FragmentationSession {
+                dev_eui: d.dev_eui,
+                nb_frag: 10,
+                fragment_size: 50,
+                updated_at: Utc::now() - Duration::hours(2),
+                ..Default::default()
+            },
+            vec![],
+        )
+        .await
+        .unwrap();
+
+        // not yet timed out
+        assert!(set_timed_out_as_failed(Duration::hours(3))
+            .await
+            .unwrap()
+            .is_empty());
+
+        let items = set_timed_out_as_failed(Duration::hours(1)).await.unwrap();
+        assert_eq!(1, items.len());
+        assert_eq!(fs.id, items[0].id);
+        assert_eq!(FragmentationSessionStatus::FAILED, items[0].status);
+
+        // failed sessions are not returned again
+        assert!(set_timed_out_as_failed(Duration::hours(1))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
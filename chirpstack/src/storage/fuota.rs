@@ -0,0 +1,638 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use diesel::{dsl, prelude::*};
+use diesel_async::RunQueryDsl;
+use tracing::info;
+use uuid::Uuid;
+
+use lrwn::EUI64;
+
+use super::error::Error;
+use super::schema::{device, fuota_campaign, fuota_campaign_device};
+use super::{fields, get_async_db_conn};
+use crate::config;
+
+#[derive(Clone, Queryable, QueryableByName, Insertable, AsChangeset, Debug, PartialEq, Eq)]
+#[diesel(table_name = fuota_campaign, treat_none_as_null = true)]
+pub struct FuotaCampaign {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub name: String,
+    pub application_id: Uuid,
+    pub device_profile_id: Uuid,
+    pub multicast_group_id: Option<Uuid>,
+    pub multicast_group_type: String,
+    pub multicast_class_c_scheduling_type: fields::MulticastGroupSchedulingType,
+    pub multicast_dr: i16,
+    pub multicast_class_b_ping_slot_nb_k: i16,
+    pub multicast_frequency: i64,
+    pub multicast_timeout: i16,
+    pub unicast_timeout: i32,
+    pub fragment_size: i16,
+    pub redundancy: i32,
+    pub payload: Vec<u8>,
+    pub step: fields::FuotaCampaignStep,
+    pub scheduler_run_after: DateTime<Utc>,
+    pub session_start_at: Option<DateTime<Utc>>,
+}
+
+impl FuotaCampaign {
+    fn validate(&self) -> Result<(), Error> {
+        if self.name.is_empty() {
+            return Err(Error::Validation("name is not set".into()));
+        }
+
+        if self.multicast_group_type != "B" && self.multicast_group_type != "C" {
+            return Err(Error::Validation(
+                "multicast_group_type must be B or C".into(),
+            ));
+        }
+
+        if !(0..=15).contains(&self.multicast_timeout) {
+            return Err(Error::Validation(
+                "multicast_timeout must be between 0 and 15".into(),
+            ));
+        }
+
+        if !(0..=7).contains(&self.multicast_class_b_ping_slot_nb_k) {
+            return Err(Error::Validation(
+                "multicast_class_b_ping_slot_nb_k must be between 0 and 7".into(),
+            ));
+        }
+
+        if self.fragment_size < 1 || self.fragment_size > 255 {
+            return Err(Error::Validation(
+                "fragment_size must be between 1 and 255".into(),
+            ));
+        }
+
+        if self.payload.is_empty() {
+            return Err(Error::Validation("payload must not be empty".into()));
+        }
+
+        let nb_frag = self.payload.len().div_ceil(self.fragment_size as usize);
+        if self.redundancy < 0 || nb_frag + self.redundancy as usize > 16383 {
+            return Err(Error::Validation(
+                "The total number of fragments must be between 1 and 16383".into(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for FuotaCampaign {
+    fn default() -> Self {
+        let now = Utc::now();
+
+        FuotaCampaign {
+            id: Uuid::new_v4(),
+            created_at: now,
+            updated_at: now,
+            started_at: None,
+            completed_at: None,
+            name: "".into(),
+            application_id: Uuid::nil(),
+            device_profile_id: Uuid::nil(),
+            multicast_group_id: None,
+            multicast_group_type: "".into(),
+            multicast_class_c_scheduling_type: fields::MulticastGroupSchedulingType::DELAY,
+            multicast_dr: 0,
+            multicast_class_b_ping_slot_nb_k: 0,
+            multicast_frequency: 0,
+            multicast_timeout: 0,
+            unicast_timeout: 0,
+            fragment_size: 0,
+            redundancy: 0,
+            payload: vec![],
+            step: fields::FuotaCampaignStep::CREATED,
+            scheduler_run_after: now,
+            session_start_at: None,
+        }
+    }
+}
+
+#[derive(Queryable, PartialEq, Eq, Debug)]
+pub struct FuotaCampaignListItem {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub name: String,
+    pub step: fields::FuotaCampaignStep,
+}
+
+#[derive(Default, Clone)]
+pub struct Filters {
+    pub application_id: Option<Uuid>,
+    pub search: Option<String>,
+}
+
+#[derive(Default, Debug, PartialEq, Eq)]
+pub struct DeviceCounts {
+    pub total: i64,
+    pub completed: i64,
+    pub error: i64,
+}
+
+#[derive(Clone, Queryable, Insertable, AsChangeset, Debug, PartialEq, Eq)]
+#[diesel(table_name = fuota_campaign_device, treat_none_as_null = true)]
+#[diesel(primary_key(fuota_campaign_id, dev_eui))]
+pub struct FuotaCampaignDevice {
+    pub fuota_campaign_id: Uuid,
+    pub dev_eui: EUI64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub mc_group_setup_completed_at: Option<DateTime<Utc>>,
+    pub frag_session_setup_completed_at: Option<DateTime<Utc>>,
+    pub mc_session_completed_at: Option<DateTime<Utc>>,
+    pub frag_status_completed_at: Option<DateTime<Utc>>,
+    pub nb_frag_received: Option<i32>,
+    pub missing_frag: Option<i32>,
+    pub error_msg: String,
+}
+
+impl Default for FuotaCampaignDevice {
+    fn default() -> Self {
+        let now = Utc::now();
+
+        FuotaCampaignDevice {
+            fuota_campaign_id: Uuid::nil(),
+            dev_eui: Default::default(),
+            created_at: now,
+            updated_at: now,
+            mc_group_setup_completed_at: None,
+            frag_session_setup_completed_at: None,
+            mc_session_completed_at: None,
+            frag_status_completed_at: None,
+            nb_frag_received: None,
+            missing_frag: None,
+            error_msg: "".into(),
+        }
+    }
+}
+
+pub async fn create(fc: FuotaCampaign) -> Result<FuotaCampaign, Error> {
+    fc.validate()?;
+
+    let fc: FuotaCampaign = diesel::insert_into(fuota_campaign::table)
+        .values(&fc)
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, fc.id.to_string()))?;
+    info!(id = %fc.id, "FUOTA campaign created");
+    Ok(fc)
+}
+
+pub async fn get(id: &Uuid) -> Result<FuotaCampaign, Error> {
+    fuota_campaign::dsl::fuota_campaign
+        .find(&id)
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))
+}
+
+pub async fn update(mut fc: FuotaCampaign) -> Result<FuotaCampaign, Error> {
+    fc.validate()?;
+    fc.updated_at = Utc::now();
+
+    let fc: FuotaCampaign = diesel::update(fuota_campaign::dsl::fuota_campaign.find(&fc.id))
+        .set(&fc)
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, fc.id.to_string()))?;
+    info!(id = %fc.id, step = %fc.step, "FUOTA campaign updated");
+    Ok(fc)
+}
+
+pub async fn delete(id: &Uuid) -> Result<(), Error> {
+    let ra = diesel::delete(fuota_campaign::dsl::fuota_campaign.find(&id))
+        .execute(&mut get_async_db_conn().await?)
+        .await?;
+    if ra == 0 {
+        return Err(Error::NotFound(id.to_string()));
+    }
+    info!(id = %id, "FUOTA campaign deleted");
+    Ok(())
+}
+
+pub async fn get_count(filters: &Filters) -> Result<i64, Error> {
+    let mut q = fuota_campaign::dsl::fuota_campaign
+        .select(dsl::count_star())
+        .into_boxed();
+
+    if let Some(application_id) = &filters.application_id {
+        q = q.filter(fuota_campaign::dsl::application_id.eq(application_id));
+    }
+
+    if let Some(search) = &filters.search {
+        q = q.filter(fuota_campaign::dsl::name.ilike(format!("%{}%", search)));
+    }
+
+    q.first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, "".into()))
+}
+
+pub async fn list(
+    limit: i64,
+    offset: i64,
+    filters: &Filters,
+) -> Result<Vec<FuotaCampaignListItem>, Error> {
+    let mut q = fuota_campaign::dsl::fuota_campaign
+        .select((
+            fuota_campaign::id,
+            fuota_campaign::created_at,
+            fuota_campaign::updated_at,
+            fuota_campaign::started_at,
+            fuota_campaign::completed_at,
+            fuota_campaign::name,
+            fuota_campaign::step,
+        ))
+        .into_boxed();
+
+    if let Some(application_id) = &filters.application_id {
+        q = q.filter(fuota_campaign::dsl::application_id.eq(application_id));
+    }
+
+    if let Some(search) = &filters.search {
+        q = q.filter(fuota_campaign::dsl::name.ilike(format!("%{}%", search)));
+    }
+
+    q.order_by(fuota_campaign::dsl::created_at.desc())
+        .limit(limit)
+        .offset(offset)
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, "".into()))
+}
+
+// Devices can only be added to a campaign which has not yet been started and they must be
+// part of the same application and use the device-profile of the campaign.
+pub async fn add_device(campaign_id: &Uuid, dev_eui: &EUI64) -> Result<(), Error> {
+    let mut c = get_async_db_conn().await?;
+    c.build_transaction()
+        .run::<(), Error, _>(|c| {
+            Box::pin(async move {
+                let d: super::device::Device = device::dsl::device
+                    .find(&dev_eui)
+                    .get_result(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))?;
+
+                let fc: FuotaCampaign = fuota_campaign::dsl::fuota_campaign
+                    .find(&campaign_id)
+                    .for_update()
+                    .get_result(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, campaign_id.to_string()))?;
+
+                if d.application_id != fc.application_id {
+                    // Device not found within the same application.
+                    return Err(Error::NotFound(dev_eui.to_string()));
+                }
+
+                if d.device_profile_id != fc.device_profile_id {
+                    return Err(Error::Validation(format!(
+                        "Device {} does not use the device-profile of the FUOTA campaign",
+                        dev_eui
+                    )));
+                }
+
+                if fc.started_at.is_some() {
+                    return Err(Error::Validation(
+                        "FUOTA campaign has already been started".into(),
+                    ));
+                }
+
+                let _ = diesel::insert_into(fuota_campaign_device::table)
+                    .values(&FuotaCampaignDevice {
+                        fuota_campaign_id: *campaign_id,
+                        dev_eui: *dev_eui,
+                        ..Default::default()
+                    })
+                    .execute(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))?;
+                Ok(())
+            })
+        })
+        .await?;
+    info!(fuota_campaign_id = %campaign_id, dev_eui = %dev_eui, "Device added to FUOTA campaign");
+    Ok(())
+}
+
+pub async fn remove_device(campaign_id: &Uuid, dev_eui: &EUI64) -> Result<(), Error> {
+    let ra = diesel::delete(
+        fuota_campaign_device::dsl::fuota_campaign_device
+            .filter(fuota_campaign_device::fuota_campaign_id.eq(&campaign_id))
+            .filter(fuota_campaign_device::dev_eui.eq(&dev_eui)),
+    )
+    .execute(&mut get_async_db_conn().await?)
+    .await?;
+    if ra == 0 {
+        return Err(Error::NotFound(format!(
+            "fuota-campaign: {}, device: {}",
+            campaign_id, dev_eui
+        )));
+    }
+    info!(fuota_campaign_id = %campaign_id, dev_eui = %dev_eui, "Device removed from FUOTA campaign");
+    Ok(())
+}
+
+pub async fn get_device(campaign_id: &Uuid, dev_eui: &EUI64) -> Result<FuotaCampaignDevice, Error> {
+    fuota_campaign_device::dsl::fuota_campaign_device
+        .find((&campaign_id, &dev_eui))
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))
+}
+
+pub async fn update_device(mut d: FuotaCampaignDevice) -> Result<FuotaCampaignDevice, Error> {
+    d.updated_at = Utc::now();
+
+    let d: FuotaCampaignDevice = diesel::update(
+        fuota_campaign_device::dsl::fuota_campaign_device.find((&d.fuota_campaign_id, &d.dev_eui)),
+    )
+    .set(&d)
+    .get_result(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| Error::from_diesel(e, d.dev_eui.to_string()))?;
+    Ok(d)
+}
+
+pub async fn get_device_count(campaign_id: &Uuid) -> Result<i64, Error> {
+    fuota_campaign_device::dsl::fuota_campaign_device
+        .select(dsl::count_star())
+        .filter(fuota_campaign_device::dsl::fuota_campaign_id.eq(&campaign_id))
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, "".into()))
+}
+
+// Returns the total, completed and failed number of devices of the campaign.
+pub async fn get_device_counts(campaign_id: &Uuid) -> Result<DeviceCounts, Error> {
+    let (total, completed, error): (i64, i64, i64) =
+        fuota_campaign_device::dsl::fuota_campaign_device
+            .select((
+                dsl::count_star(),
+                dsl::sql::<diesel::sql_types::BigInt>(
+                    "count(*) filter (where frag_status_completed_at is not null and error_msg = '')",
+                ),
+                dsl::sql::<diesel::sql_types::BigInt>("count(*) filter (where error_msg != '')"),
+            ))
+            .filter(fuota_campaign_device::dsl::fuota_campaign_id.eq(&campaign_id))
+            .first(&mut get_async_db_conn().await?)
+            .await
+            .map_err(|e| Error::from_diesel(e, "".into()))?;
+
+    Ok(DeviceCounts {
+        total,
+        completed,
+        error,
+    })
+}
+
+pub async fn get_devices(
+    campaign_id: &Uuid,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<FuotaCampaignDevice>, Error> {
+    fuota_campaign_device::dsl::fuota_campaign_device
+        .filter(fuota_campaign_device::dsl::fuota_campaign_id.eq(&campaign_id))
+        .order_by(fuota_campaign_device::dsl::dev_eui)
+        .limit(limit)
+        .offset(offset)
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, "".into()))
+}
+
+// Returns the campaign which is currently running for the given device.
+pub async fn get_running_for_dev_eui(dev_eui: &EUI64) -> Result<FuotaCampaign, Error> {
+    fuota_campaign::dsl::fuota_campaign
+        .select(fuota_campaign::all_columns)
+        .inner_join(fuota_campaign_device::table)
+        .filter(fuota_campaign_device::dsl::dev_eui.eq(&dev_eui))
+        .filter(fuota_campaign::dsl::started_at.is_not_null())
+        .filter(fuota_campaign::dsl::completed_at.is_null())
+        .order_by(fuota_campaign::dsl::started_at.desc())
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))
+}
+
+pub async fn get_schedulable_campaigns(limit: usize) -> Result<Vec<FuotaCampaign>> {
+    let mut c = get_async_db_conn().await?;
+    c.build_transaction()
+        .run::<Vec<FuotaCampaign>, Error, _>(|c| {
+            Box::pin(async move {
+                let conf = config::get();
+
+                // The scheduler_run_after is bumped such that other ChirpStack instances do
+                // not process the same campaign step concurrently. The campaign step handler
+                // sets the scheduler_run_after of the next step.
+                diesel::sql_query(
+                    r#"
+                        update
+                            fuota_campaign
+                        set
+                            scheduler_run_after = $3
+                        where
+                            id in (
+                                select
+                                    id
+                                from
+                                    fuota_campaign
+                                where
+                                    started_at is not null
+                                    and completed_at is null
+                                    and scheduler_run_after <= $2
+                                order by
+                                    scheduler_run_after
+                                limit $1
+                                for update skip locked
+                            )
+                        returning *
+                    "#,
+                )
+                .bind::<diesel::sql_types::Integer, _>(limit as i32)
+                .bind::<diesel::sql_types::Timestamptz, _>(Utc::now())
+                .bind::<diesel::sql_types::Timestamptz, _>(
+                    Utc::now() + Duration::from_std(2 * conf.network.scheduler.interval).unwrap(),
+                )
+                .load(c)
+                .await
+                .map_err(|e| Error::from_diesel(e, "".into()))
+            })
+        })
+        .await
+        .context("Get schedulable FUOTA campaigns")
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage::{self, application, device_profile, tenant};
+    use crate::test;
+
+    #[tokio::test]
+    async fn test_fuota_campaign() {
+        let _guard = test::prepare().await;
+
+        let t = tenant::create(tenant::Tenant {
+            name: "test-tenant".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let app = application::create(application::Application {
+            name: "test-app".into(),
+            tenant_id: t.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let dp = device_profile::create(device_profile::DeviceProfile {
+            name: "test-dp".into(),
+            tenant_id: t.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let dp_other = device_profile::create(device_profile::DeviceProfile {
+            name: "test-dp-other".into(),
+            tenant_id: t.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let d = storage::device::create(storage::device::Device {
+            name: "test-dev".into(),
+            dev_eui: EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            application_id: app.id,
+            device_profile_id: dp.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let d_other = storage::device::create(storage::device::Device {
+            name: "test-dev-other".into(),
+            dev_eui: EUI64::from_be_bytes([2, 2, 3, 4, 5, 6, 7, 8]),
+            application_id: app.id,
+            device_profile_id: dp_other.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // invalid
+        assert!(create(FuotaCampaign {
+            name: "test-campaign".into(),
+            application_id: app.id,
+            device_profile_id: dp.id,
+            multicast_group_type: "A".into(),
+            fragment_size: 50,
+            payload: vec![1, 2, 3],
+            ..Default::default()
+        })
+        .await
+        .is_err());
+
+        // create
+        let mut fc = create(FuotaCampaign {
+            name: "test-campaign".into(),
+            application_id: app.id,
+            device_profile_id: dp.id,
+            multicast_group_type: "C".into(),
+            multicast_frequency: 868100000,
+            multicast_timeout: 8,
+            unicast_timeout: 60,
+            fragment_size: 50,
+            redundancy: 5,
+            payload: vec![1, 2, 3],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // get
+        let fc_get = get(&fc.id).await.unwrap();
+        assert_eq!(fc, fc_get);
+
+        // count and list
+        let filters = Filters {
+            application_id: Some(app.id),
+            search: Some("camp".into()),
+        };
+        assert_eq!(1, get_count(&filters).await.unwrap());
+        let items = list(10, 0, &filters).await.unwrap();
+        assert_eq!(1, items.len());
+        assert_eq!(fc.id, items[0].id);
+        assert_eq!(fields::FuotaCampaignStep::CREATED, items[0].step);
+
+        // add devices
+        add_device(&fc.id, &d.dev_eui).await.unwrap();
+        assert!(add_device(&fc.id, &d_other.dev_eui).await.is_err());
+        assert_eq!(1, get_device_count(&fc.id).await.unwrap());
+
+        // not yet running
+        assert!(get_running_for_dev_eui(&d.dev_eui).await.is_err());
+        assert!(get_schedulable_campaigns(10).await.unwrap().is_empty());
+
+        // start
+        fc.started_at = Some(Utc::now());
+        fc.step = fields::FuotaCampaignStep::MC_GROUP_SETUP;
+        fc.scheduler_run_after = Utc::now();
+        let fc = update(fc).await.unwrap();
+
+        // devices can not be added after start
+        assert!(add_device(&fc.id, &d_other.dev_eui).await.is_err());
+
+        let fc_get = get_running_for_dev_eui(&d.dev_eui).await.unwrap();
+        assert_eq!(fc.id, fc_get.id);
+
+        // schedulable, this also bumps the scheduler_run_after
+        let items = get_schedulable_campaigns(10).await.unwrap();
+        assert_eq!(1, items.len());
+        assert!(get_schedulable_campaigns(10).await.unwrap().is_empty());
+
+        // update device
+        let mut fcd = get_device(&fc.id, &d.dev_eui).await.unwrap();
+        fcd.mc_group_setup_completed_at = Some(Utc::now());
+        update_device(fcd).await.unwrap();
+        let devices = get_devices(&fc.id, 10, 0).await.unwrap();
+        assert_eq!(1, devices.len());
+        assert!(devices[0].mc_group_setup_completed_at.is_some());
+
+        // device counts
+        let mut fcd = devices[0].clone();
+        fcd.frag_status_completed_at = Some(Utc::now());
+        update_device(fcd).await.unwrap();
+        assert_eq!(
+            DeviceCounts {
+                total: 1,
+                completed: 1,
+                error: 0,
+            },
+            get_device_counts(&fc.id).await.unwrap()
+        );
+
+        // remove device
+        remove_device(&fc.id, &d.dev_eui).await.unwrap();
+        assert!(remove_device(&fc.id, &d.dev_eui).await.is_err());
+
+        // delete
+        delete(&fc.id).await.unwrap();
+        assert!(delete(&fc.id).await.is_err());
+    }
+}
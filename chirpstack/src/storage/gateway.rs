@@ -9,7 +9,9 @@ use uuid::Uuid;
 
 use lrwn::{DevAddr, EUI64};
 
-use super::schema::{gateway, multicast_group_gateway, relay_gateway, tenant};
+use super::schema::{
+    gateway, gateway_state_change, multicast_group_gateway, relay_gateway, tenant,
+};
 use super::{error::Error, fields, get_async_db_conn};
 
 pub type RelayId = DevAddr;
@@ -31,6 +33,7 @@ pub struct Gateway {
     pub tls_certificate: Option<Vec<u8>>,
     pub tags: fields::KeyValue,
     pub properties: fields::KeyValue,
+    pub is_online: Option<bool>,
 }
 
 impl Gateway {
@@ -61,6 +64,7 @@ impl Default for Gateway {
             stats_interval_secs: 30,
             tags: fields::KeyValue::new(HashMap::new()),
             properties: fields::KeyValue::new(HashMap::new()),
+            is_online: None,
         }
     }
 }
@@ -90,6 +94,7 @@ pub struct GatewayListItem {
     pub altitude: f32,
     pub properties: fields::KeyValue,
     pub stats_interval_secs: i32,
+    pub is_online: Option<bool>,
 }
 
 #[derive(Queryable, PartialEq, Debug)]
@@ -120,6 +125,26 @@ pub struct GatewayCountsByState {
     pub offline_count: i64,
 }
 
+#[derive(Queryable, Insertable, PartialEq, Debug, Clone)]
+#[diesel(table_name = gateway_state_change)]
+pub struct GatewayStateChange {
+    pub id: Uuid,
+    pub gateway_id: EUI64,
+    pub created_at: DateTime<Utc>,
+    pub is_online: bool,
+}
+
+impl Default for GatewayStateChange {
+    fn default() -> Self {
+        GatewayStateChange {
+            id: Uuid::new_v4(),
+            gateway_id: EUI64::from_be_bytes([0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
+            created_at: Utc::now(),
+            is_online: false,
+        }
+    }
+}
+
 #[derive(Queryable, Insertable, PartialEq, Debug)]
 #[diesel(table_name = relay_gateway)]
 pub struct RelayGateway {
@@ -225,6 +250,19 @@ pub async fn get(gateway_id: &EUI64) -> Result<Gateway, Error> {
     Ok(gw)
 }
 
+// Returns the tags of the given gateways, using a single query. Gateways that do not exist are
+// not included in the returned map.
+pub async fn get_tags_for_gateway_ids(
+    gateway_ids: &[EUI64],
+) -> Result<HashMap<EUI64, fields::KeyValue>, Error> {
+    let items: Vec<(EUI64, fields::KeyValue)> = gateway::dsl::gateway
+        .select((gateway::dsl::gateway_id, gateway::dsl::tags))
+        .filter(gateway::dsl::gateway_id.eq_any(gateway_ids))
+        .load(&mut get_async_db_conn().await?)
+        .await?;
+    Ok(items.into_iter().collect())
+}
+
 pub async fn update(gw: Gateway) -> Result<Gateway, Error> {
     gw.validate()?;
 
@@ -316,6 +354,7 @@ pub async fn list(
             gateway::altitude,
             gateway::properties,
             gateway::stats_interval_secs,
+            gateway::is_online,
         ))
         .distinct()
         .into_boxed();
@@ -363,9 +402,9 @@ pub async fn get_meta(gateway_id: &EUI64) -> Result<GatewayMeta, Error> {
 pub async fn get_counts_by_state(tenant_id: &Option<Uuid>) -> Result<GatewayCountsByState, Error> {
     let counts: GatewayCountsByState = diesel::sql_query(r#"
         select
-            coalesce(sum(case when last_seen_at is null then 1 end), 0) as never_seen_count,
-            coalesce(sum(case when (now() - make_interval(secs => stats_interval_secs * 2)) > last_seen_at then 1 end), 0) as offline_count,
-            coalesce(sum(case when (now() - make_interval(secs => stats_interval_secs * 2)) <= last_seen_at then 1 end), 0) as online_count
+            coalesce(sum(case when is_online is null and last_seen_at is null then 1 end), 0) as never_seen_count,
+            coalesce(sum(case when is_online = false or (is_online is null and (now() - make_interval(secs => stats_interval_secs * 2)) > last_seen_at) then 1 end), 0) as offline_count,
+            coalesce(sum(case when is_online = true or (is_online is null and (now() - make_interval(secs => stats_interval_secs * 2)) <= last_seen_at) then 1 end), 0) as online_count
         from
             gateway
         where
@@ -374,6 +413,94 @@ pub async fn get_counts_by_state(tenant_id: &Option<Uuid>) -> Result<GatewayCoun
     Ok(counts)
 }
 
+// Sets the connection state of the gateway, as reported by the gateway (bridge). When the state
+// differs from the current state, the gateway is updated and the state change is stored and
+// returned. If the state is unchanged (e.g. when receiving the same retained message after a
+// re-connect), None is returned.
+pub async fn set_conn_state(
+    gateway_id: EUI64,
+    is_online: bool,
+) -> Result<Option<GatewayStateChange>, Error> {
+    let mut c = get_async_db_conn().await?;
+    let sc = c
+        .build_transaction()
+        .run::<Option<GatewayStateChange>, Error, _>(|c| {
+            Box::pin(async move {
+                // use for_update to avoid storing the same state change twice in case multiple
+                // instances receive the same connection state.
+                let gw: Gateway = gateway::dsl::gateway
+                    .find(&gateway_id)
+                    .for_update()
+                    .get_result(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, gateway_id.to_string()))?;
+
+                if gw.is_online == Some(is_online) {
+                    return Ok(None);
+                }
+
+                let sc = GatewayStateChange {
+                    gateway_id,
+                    is_online,
+                    ..Default::default()
+                };
+
+                if is_online {
+                    diesel::update(gateway::dsl::gateway.find(&gateway_id))
+                        .set((
+                            gateway::is_online.eq(Some(true)),
+                            gateway::last_seen_at.eq(Some(sc.created_at)),
+                        ))
+                        .execute(c)
+                        .await?;
+                } else {
+                    diesel::update(gateway::dsl::gateway.find(&gateway_id))
+                        .set(gateway::is_online.eq(Some(false)))
+                        .execute(c)
+                        .await?;
+                }
+
+                diesel::insert_into(gateway_state_change::table)
+                    .values(&sc)
+                    .get_result(c)
+                    .await
+                    .map(Some)
+                    .map_err(|e| Error::from_diesel(e, sc.id.to_string()))
+            })
+        })
+        .await?;
+
+    if let Some(sc) = &sc {
+        info!(gateway_id = %gateway_id, is_online = sc.is_online, "Gateway connection state changed");
+    }
+
+    Ok(sc)
+}
+
+pub async fn get_state_change_count(gateway_id: &EUI64) -> Result<i64, Error> {
+    let count = gateway_state_change::dsl::gateway_state_change
+        .select(dsl::count_star())
+        .filter(gateway_state_change::dsl::gateway_id.eq(&gateway_id))
+        .first(&mut get_async_db_conn().await?)
+        .await?;
+    Ok(count)
+}
+
+pub async fn list_state_changes(
+    gateway_id: &EUI64,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<GatewayStateChange>, Error> {
+    let items = gateway_state_change::dsl::gateway_state_change
+        .filter(gateway_state_change::dsl::gateway_id.eq(&gateway_id))
+        .order_by(gateway_state_change::dsl::created_at.desc())
+        .limit(limit)
+        .offset(offset)
+        .load(&mut get_async_db_conn().await?)
+        .await?;
+    Ok(items)
+}
+
 pub async fn create_relay_gateway(relay: RelayGateway) -> Result<RelayGateway, Error> {
     let relay: RelayGateway = diesel::insert_into(relay_gateway::table)
         .values(&relay)
@@ -657,6 +784,58 @@ pub mod test {
         assert!(delete(&gw.gateway_id).await.is_err());
     }
 
+    #[tokio::test]
+    async fn test_conn_state() {
+        let _guard = test::prepare().await;
+        let gw = create_gateway(EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8])).await;
+        assert_eq!(None, gw.is_online);
+
+        let counts = get_counts_by_state(&Some(gw.tenant_id)).await.unwrap();
+        assert_eq!(1, counts.never_seen_count);
+
+        // online
+        let sc = set_conn_state(gw.gateway_id, true).await.unwrap().unwrap();
+        assert!(sc.is_online);
+
+        let gw_get = get(&gw.gateway_id).await.unwrap();
+        assert_eq!(Some(true), gw_get.is_online);
+        assert!(gw_get.last_seen_at.is_some());
+
+        let counts = get_counts_by_state(&Some(gw.tenant_id)).await.unwrap();
+        assert_eq!(1, counts.online_count);
+
+        // same state (e.g. retained message after re-connect)
+        assert!(set_conn_state(gw.gateway_id, true).await.unwrap().is_none());
+
+        // offline
+        let sc = set_conn_state(gw.gateway_id, false).await.unwrap().unwrap();
+        assert!(!sc.is_online);
+
+        let gw_get = get(&gw.gateway_id).await.unwrap();
+        assert_eq!(Some(false), gw_get.is_online);
+
+        let counts = get_counts_by_state(&Some(gw.tenant_id)).await.unwrap();
+        assert_eq!(1, counts.offline_count);
+
+        // history
+        assert_eq!(2, get_state_change_count(&gw.gateway_id).await.unwrap());
+        let items = list_state_changes(&gw.gateway_id, 10, 0).await.unwrap();
+        assert_eq!(
+            vec![false, true],
+            items.iter().map(|sc| sc.is_online).collect::<Vec<bool>>()
+        );
+
+        let items = list_state_changes(&gw.gateway_id, 10, 1).await.unwrap();
+        assert_eq!(1, items.len());
+
+        // unknown gateway
+        assert!(
+            set_conn_state(EUI64::from_be_bytes([8, 7, 6, 5, 4, 3, 2, 1]), true)
+                .await
+                .is_err()
+        );
+    }
+
     #[tokio::test]
     async fn test_relay_gateway() {
         let _guard = test::prepare().await;
@@ -182,7 +182,48 @@ pub async fn get(
     start: DateTime<Local>,
     end: DateTime<Local>,
 ) -> Result<Vec<Record>> {
-    let mut keys: Vec<String> = Vec::new();
+    Ok(get_many(&[name.to_string()], kind, a, start, end)
+        .await?
+        .pop()
+        .unwrap_or_default())
+}
+
+// Returns the records for each of the given metric names (in the same order), using a single
+// Redis pipeline.
+pub async fn get_many(
+    names: &[String],
+    kind: Kind,
+    a: Aggregation,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+) -> Result<Vec<Vec<Record>>> {
+    let timestamps = get_timestamps(a, start, end)?;
+    if timestamps.is_empty() || names.is_empty() {
+        return Ok(names.iter().map(|_| Vec::new()).collect());
+    }
+
+    let mut pipe = redis::pipe();
+
+    for name in names {
+        for ts in &timestamps {
+            pipe.cmd("HGETALL").arg(get_key(name, a, *ts));
+        }
+    }
+
+    let res: Vec<HashMap<String, f64>> =
+        pipe.query_async(&mut get_async_redis_conn().await?).await?;
+
+    Ok(res
+        .chunks(timestamps.len())
+        .map(|res| get_records(kind, &timestamps, res))
+        .collect())
+}
+
+fn get_timestamps(
+    a: Aggregation,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+) -> Result<Vec<NaiveDateTime>> {
     let mut timestamps: Vec<NaiveDateTime> = Vec::new();
 
     match a {
@@ -198,7 +239,6 @@ pub async fn get(
 
             while ts.le(&end) {
                 timestamps.push(ts);
-                keys.push(get_key(name, a, ts));
                 ts += ChronoDuration::minutes(1);
             }
         }
@@ -214,7 +254,6 @@ pub async fn get(
 
             while ts.le(&end) {
                 timestamps.push(ts);
-                keys.push(get_key(name, a, ts));
                 ts += ChronoDuration::hours(1);
             }
         }
@@ -230,7 +269,6 @@ pub async fn get(
 
             while ts.le(&end) {
                 timestamps.push(ts);
-                keys.push(get_key(name, a, ts));
                 ts += ChronoDuration::days(1);
             }
         }
@@ -246,7 +284,6 @@ pub async fn get(
 
             while ts.le(&end) {
                 timestamps.push(ts);
-                keys.push(get_key(name, a, ts));
                 ts = ts
                     .checked_add_months(Months::new(1))
                     .ok_or_else(|| anyhow!("Add month error"))?;
@@ -254,18 +291,14 @@ pub async fn get(
         }
     }
 
-    if keys.is_empty() {
-        return Ok(Vec::new());
-    }
-
-    let mut pipe = redis::pipe();
-
-    for k in &keys {
-        pipe.cmd("HGETALL").arg(k);
-    }
+    Ok(timestamps)
+}
 
-    let res: Vec<HashMap<String, f64>> =
-        pipe.query_async(&mut get_async_redis_conn().await?).await?;
+fn get_records(
+    kind: Kind,
+    timestamps: &[NaiveDateTime],
+    res: &[HashMap<String, f64>],
+) -> Vec<Record> {
     let mut out: Vec<Record> = Vec::new();
 
     for (i, r) in res.iter().enumerate() {
@@ -304,7 +337,7 @@ pub async fn get(
         });
     }
 
-    Ok(out)
+    out
 }
 
 #[cfg(test)]
@@ -20,6 +20,9 @@ use crate::config;
 
 pub mod api_key;
 pub mod application;
+pub mod audit_log;
+pub mod codec;
+pub mod codec_context;
 pub mod device;
 pub mod device_config_store;
 pub mod device_gateway;
@@ -31,6 +34,8 @@ pub mod device_session;
 pub mod downlink_frame;
 pub mod error;
 pub mod fields;
+pub mod fragmentation_session;
+pub mod fuota;
 pub mod gateway;
 pub mod helpers;
 pub mod mac_command;
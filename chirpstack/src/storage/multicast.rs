@@ -1,7 +1,9 @@
+use std::collections::HashMap;
+
 use anyhow::{Context, Result};
 use chrono::{DateTime, Duration, Utc};
 use diesel::{dsl, prelude::*};
-use diesel_async::RunQueryDsl;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use tracing::info;
 use uuid::Uuid;
 
@@ -11,12 +13,16 @@ use lrwn::{AES128Key, DevAddr, EUI64};
 use super::error::Error;
 use super::schema::{
     application, device, gateway, multicast_group, multicast_group_device, multicast_group_gateway,
-    multicast_group_queue_item,
+    multicast_group_queue_item, multicast_group_queue_item_delivery,
 };
 use super::{fields, get_async_db_conn};
 use crate::downlink::classb;
 use crate::{config, gpstime::ToDateTime, gpstime::ToGpsTime};
 
+/// Tx ack status of a delivery record which has been resolved without receiving the tx ack of
+/// the gateway, e.g. because the queue item was discarded or the tx ack timed out.
+pub const DELIVERY_STATUS_DISCARDED: &str = "DISCARDED";
+
 #[derive(Clone, Queryable, Insertable, Debug, PartialEq, Eq)]
 #[diesel(table_name = multicast_group)]
 pub struct MulticastGroup {
@@ -35,6 +41,9 @@ pub struct MulticastGroup {
     pub frequency: i64,
     pub class_b_ping_slot_nb_k: i16,
     pub class_c_scheduling_type: fields::MulticastGroupSchedulingType,
+    pub auto_membership: bool,
+    pub selector_device_profile_id: Option<Uuid>,
+    pub selector_tags: fields::KeyValue,
 }
 
 impl MulticastGroup {
@@ -66,6 +75,9 @@ impl Default for MulticastGroup {
             frequency: 0,
             class_b_ping_slot_nb_k: 0,
             class_c_scheduling_type: fields::MulticastGroupSchedulingType::DELAY,
+            auto_membership: false,
+            selector_device_profile_id: None,
+            selector_tags: fields::KeyValue::new(HashMap::new()),
         }
     }
 }
@@ -130,15 +142,56 @@ impl Default for MulticastGroupQueueItem {
     }
 }
 
+#[derive(Clone, Queryable, QueryableByName, Insertable, Debug, PartialEq, Eq)]
+#[diesel(table_name = multicast_group_queue_item_delivery)]
+pub struct MulticastGroupQueueItemDelivery {
+    pub multicast_group_queue_item_id: Uuid,
+    pub multicast_group_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub f_cnt: i64,
+    pub gateway_id: EUI64,
+    pub tx_ack_status: Option<String>,
+}
+
+impl From<&MulticastGroupQueueItem> for MulticastGroupQueueItemDelivery {
+    fn from(qi: &MulticastGroupQueueItem) -> Self {
+        MulticastGroupQueueItemDelivery {
+            multicast_group_queue_item_id: qi.id,
+            multicast_group_id: qi.multicast_group_id,
+            created_at: qi.created_at,
+            updated_at: qi.created_at,
+            f_cnt: qi.f_cnt,
+            gateway_id: qi.gateway_id,
+            tx_ack_status: None,
+        }
+    }
+}
+
 pub async fn create(mg: MulticastGroup) -> Result<MulticastGroup, Error> {
     mg.validate()?;
 
-    let mg: MulticastGroup = diesel::insert_into(multicast_group::table)
-        .values(&mg)
-        .get_result(&mut get_async_db_conn().await?)
-        .await
-        .map_err(|e| Error::from_diesel(e, mg.id.to_string()))?;
+    let mut c = get_async_db_conn().await?;
+    let mg: MulticastGroup = c
+        .build_transaction()
+        .run::<MulticastGroup, Error, _>(|c| {
+            Box::pin(async move {
+                let mg: MulticastGroup = diesel::insert_into(multicast_group::table)
+                    .values(&mg)
+                    .get_result(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, mg.id.to_string()))?;
+
+                if mg.auto_membership {
+                    sync_group_memberships(c, &mg.id).await?;
+                }
+
+                Ok(mg)
+            })
+        })
+        .await?;
     info!(id = %mg.id, "Multicast-group created");
+
     Ok(mg)
 }
 
@@ -153,25 +206,46 @@ pub async fn get(id: &Uuid) -> Result<MulticastGroup, Error> {
 pub async fn update(mg: MulticastGroup) -> Result<MulticastGroup, Error> {
     mg.validate()?;
 
-    let mg: MulticastGroup = diesel::update(multicast_group::dsl::multicast_group.find(&mg.id))
-        .set((
-            multicast_group::updated_at.eq(Utc::now()),
-            multicast_group::name.eq(&mg.name),
-            multicast_group::region.eq(&mg.region),
-            multicast_group::mc_addr.eq(&mg.mc_addr),
-            multicast_group::mc_nwk_s_key.eq(&mg.mc_nwk_s_key),
-            multicast_group::mc_app_s_key.eq(&mg.mc_app_s_key),
-            multicast_group::f_cnt.eq(&mg.f_cnt),
-            multicast_group::group_type.eq(&mg.group_type),
-            multicast_group::dr.eq(&mg.dr),
-            multicast_group::frequency.eq(&mg.frequency),
-            multicast_group::class_b_ping_slot_nb_k.eq(&mg.class_b_ping_slot_nb_k),
-            multicast_group::class_c_scheduling_type.eq(&mg.class_c_scheduling_type),
-        ))
-        .get_result(&mut get_async_db_conn().await?)
-        .await
-        .map_err(|e| Error::from_diesel(e, mg.id.to_string()))?;
+    let mut c = get_async_db_conn().await?;
+    let mg: MulticastGroup = c
+        .build_transaction()
+        .run::<MulticastGroup, Error, _>(|c| {
+            Box::pin(async move {
+                let mg: MulticastGroup =
+                    diesel::update(multicast_group::dsl::multicast_group.find(&mg.id))
+                        .set((
+                            multicast_group::updated_at.eq(Utc::now()),
+                            multicast_group::name.eq(&mg.name),
+                            multicast_group::region.eq(&mg.region),
+                            multicast_group::mc_addr.eq(&mg.mc_addr),
+                            multicast_group::mc_nwk_s_key.eq(&mg.mc_nwk_s_key),
+                            multicast_group::mc_app_s_key.eq(&mg.mc_app_s_key),
+                            multicast_group::f_cnt.eq(&mg.f_cnt),
+                            multicast_group::group_type.eq(&mg.group_type),
+                            multicast_group::dr.eq(&mg.dr),
+                            multicast_group::frequency.eq(&mg.frequency),
+                            multicast_group::class_b_ping_slot_nb_k.eq(&mg.class_b_ping_slot_nb_k),
+                            multicast_group::class_c_scheduling_type
+                                .eq(&mg.class_c_scheduling_type),
+                            multicast_group::auto_membership.eq(&mg.auto_membership),
+                            multicast_group::selector_device_profile_id
+                                .eq(&mg.selector_device_profile_id),
+                            multicast_group::selector_tags.eq(&mg.selector_tags),
+                        ))
+                        .get_result(c)
+                        .await
+                        .map_err(|e| Error::from_diesel(e, mg.id.to_string()))?;
+
+                if mg.auto_membership {
+                    sync_group_memberships(c, &mg.id).await?;
+                }
+
+                Ok(mg)
+            })
+        })
+        .await?;
     info!(id = %mg.id, "Multicast-group updated");
+
     Ok(mg)
 }
 
@@ -260,6 +334,12 @@ pub async fn add_device(group_id: &Uuid, dev_eui: &EUI64) -> Result<(), Error> {
                     return Err(Error::NotFound(dev_eui.to_string()));
                 }
 
+                if mg.auto_membership {
+                    return Err(Error::Validation(
+                        "Devices can not be added manually to a multicast-group with automatic membership".into(),
+                    ));
+                }
+
                 let _ = diesel::insert_into(multicast_group_device::table)
                     .values((
                         multicast_group_device::multicast_group_id.eq(&group_id),
@@ -278,6 +358,14 @@ pub async fn add_device(group_id: &Uuid, dev_eui: &EUI64) -> Result<(), Error> {
 }
 
 pub async fn remove_device(group_id: &Uuid, dev_eui: &EUI64) -> Result<(), Error> {
+    let mg = get(group_id).await?;
+    if mg.auto_membership {
+        return Err(Error::Validation(
+            "Devices can not be removed manually from a multicast-group with automatic membership"
+                .into(),
+        ));
+    }
+
     let ra = diesel::delete(
         multicast_group_device::dsl::multicast_group_device
             .filter(multicast_group_device::multicast_group_id.eq(&group_id))
@@ -295,6 +383,129 @@ pub async fn remove_device(group_id: &Uuid, dev_eui: &EUI64) -> Result<(), Error
     Ok(())
 }
 
+// Re-evaluates the device membership of the given multicast-group, in case
+// automatic membership is enabled. Devices matching the selector (device-profile
+// and tags) are added, devices no longer matching the selector are removed.
+// This must be called within the transaction that creates or updates the group.
+pub(super) async fn sync_group_memberships(
+    c: &mut AsyncPgConnection,
+    group_id: &Uuid,
+) -> Result<(), Error> {
+    diesel::sql_query(
+        r#"
+        delete from
+            multicast_group_device mgd
+        using
+            multicast_group mg,
+            device d
+        where
+            mgd.multicast_group_id = mg.id
+            and mgd.dev_eui = d.dev_eui
+            and mg.id = $1
+            and mg.auto_membership = true
+            and not (
+                (mg.selector_device_profile_id is null or d.device_profile_id = mg.selector_device_profile_id)
+                and d.tags @> mg.selector_tags
+            )
+    "#,
+    )
+    .bind::<diesel::sql_types::Uuid, _>(group_id)
+    .execute(c)
+    .await?;
+
+    diesel::sql_query(
+        r#"
+        insert into multicast_group_device (
+            multicast_group_id,
+            dev_eui,
+            created_at
+        )
+        select
+            mg.id,
+            d.dev_eui,
+            $2
+        from
+            multicast_group mg
+        inner join device d
+            on d.application_id = mg.application_id
+        where
+            mg.id = $1
+            and mg.auto_membership = true
+            and (mg.selector_device_profile_id is null or d.device_profile_id = mg.selector_device_profile_id)
+            and d.tags @> mg.selector_tags
+        on conflict do nothing
+    "#,
+    )
+    .bind::<diesel::sql_types::Uuid, _>(group_id)
+    .bind::<diesel::sql_types::Timestamptz, _>(Utc::now())
+    .execute(c)
+    .await?;
+
+    info!(multicast_group_id = %group_id, "Multicast-group memberships synced");
+    Ok(())
+}
+
+// Re-evaluates the membership of the given device for all the multicast-groups
+// of its application which have automatic membership enabled. This must be called
+// within the transaction that creates or updates the device.
+pub(super) async fn sync_device_memberships(
+    c: &mut AsyncPgConnection,
+    dev_eui: &EUI64,
+) -> Result<(), Error> {
+    diesel::sql_query(
+        r#"
+        delete from
+            multicast_group_device mgd
+        using
+            multicast_group mg,
+            device d
+        where
+            mgd.multicast_group_id = mg.id
+            and mgd.dev_eui = d.dev_eui
+            and d.dev_eui = $1
+            and mg.auto_membership = true
+            and not (
+                (mg.selector_device_profile_id is null or d.device_profile_id = mg.selector_device_profile_id)
+                and d.tags @> mg.selector_tags
+            )
+    "#,
+    )
+    .bind::<diesel::sql_types::Binary, _>(dev_eui)
+    .execute(c)
+    .await?;
+
+    diesel::sql_query(
+        r#"
+        insert into multicast_group_device (
+            multicast_group_id,
+            dev_eui,
+            created_at
+        )
+        select
+            mg.id,
+            d.dev_eui,
+            $2
+        from
+            multicast_group mg
+        inner join device d
+            on d.application_id = mg.application_id
+        where
+            d.dev_eui = $1
+            and mg.auto_membership = true
+            and (mg.selector_device_profile_id is null or d.device_profile_id = mg.selector_device_profile_id)
+            and d.tags @> mg.selector_tags
+        on conflict do nothing
+    "#,
+    )
+    .bind::<diesel::sql_types::Binary, _>(dev_eui)
+    .bind::<diesel::sql_types::Timestamptz, _>(Utc::now())
+    .execute(c)
+    .await?;
+
+    info!(dev_eui = %dev_eui, "Device multicast-group memberships synced");
+    Ok(())
+}
+
 pub async fn add_gateway(group_id: &Uuid, gateway_id: &EUI64) -> Result<(), Error> {
     let mut c = get_async_db_conn().await?;
     c.build_transaction()
@@ -461,6 +672,12 @@ pub async fn enqueue(
                                     .await
                                     .map_err(|e| Error::from_diesel(e, mg.id.to_string()))?;
                             ids.push(qi.id);
+
+                            diesel::insert_into(multicast_group_queue_item_delivery::table)
+                                .values(&MulticastGroupQueueItemDelivery::from(&qi))
+                                .execute(c)
+                                .await
+                                .map_err(|e| Error::from_diesel(e, mg.id.to_string()))?;
                         }
                     }
                     "C" => {
@@ -520,6 +737,12 @@ pub async fn enqueue(
                                     .map_err(|e| Error::from_diesel(e, mg.id.to_string()))?;
                             ids.push(qi.id);
 
+                            diesel::insert_into(multicast_group_queue_item_delivery::table)
+                                .values(&MulticastGroupQueueItemDelivery::from(&qi))
+                                .execute(c)
+                                .await
+                                .map_err(|e| Error::from_diesel(e, mg.id.to_string()))?;
+
                             if mg.class_c_scheduling_type
                                 == fields::MulticastGroupSchedulingType::DELAY
                             {
@@ -554,15 +777,32 @@ pub async fn enqueue(
     Ok((ids, f_cnt))
 }
 
-pub async fn delete_queue_item(id: &Uuid) -> Result<(), Error> {
-    let ra = diesel::delete(multicast_group_queue_item::dsl::multicast_group_queue_item.find(&id))
-        .execute(&mut get_async_db_conn().await?)
+// Deletes the given queue item. In case its delivery record is still pending, it is set to
+// DISCARDED. When this was the last pending delivery record of the multicast-group queue item,
+// then all its delivery records are returned.
+pub async fn delete_queue_item(
+    id: &Uuid,
+) -> Result<Option<Vec<MulticastGroupQueueItemDelivery>>, Error> {
+    let mut c = get_async_db_conn().await?;
+    let out = c
+        .build_transaction()
+        .run::<Option<Vec<MulticastGroupQueueItemDelivery>>, Error, _>(|c| {
+            Box::pin(async move {
+                let ra = diesel::delete(
+                    multicast_group_queue_item::dsl::multicast_group_queue_item.find(&id),
+                )
+                .execute(c)
+                .await?;
+                if ra == 0 {
+                    return Err(Error::NotFound(id.to_string()));
+                }
+
+                _set_queue_item_delivery_tx_ack_status(c, id, DELIVERY_STATUS_DISCARDED, true).await
+            })
+        })
         .await?;
-    if ra == 0 {
-        return Err(Error::NotFound(id.to_string()));
-    }
     info!(id = %id, "Multicast-group queue item deleted");
-    Ok(())
+    Ok(out)
 }
 
 pub async fn flush_queue(multicast_group_id: &Uuid) -> Result<(), Error> {
@@ -573,6 +813,17 @@ pub async fn flush_queue(multicast_group_id: &Uuid) -> Result<(), Error> {
     .execute(&mut get_async_db_conn().await?)
     .await
     .map_err(|e| Error::from_diesel(e, multicast_group_id.to_string()))?;
+
+    // The flushed queue items will never be transmitted, remove their pending
+    // delivery records.
+    let _ = diesel::delete(
+        multicast_group_queue_item_delivery::dsl::multicast_group_queue_item_delivery
+            .filter(multicast_group_queue_item_delivery::multicast_group_id.eq(&multicast_group_id))
+            .filter(multicast_group_queue_item_delivery::tx_ack_status.is_null()),
+    )
+    .execute(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| Error::from_diesel(e, multicast_group_id.to_string()))?;
     info!(multicast_group_id = %multicast_group_id, "Multicast-group queue flushed");
     Ok(())
 }
@@ -586,6 +837,196 @@ pub async fn get_queue(multicast_group_id: &Uuid) -> Result<Vec<MulticastGroupQu
         .map_err(|e| Error::from_diesel(e, multicast_group_id.to_string()))
 }
 
+pub async fn get_queue_item_deliveries(
+    multicast_group_id: &Uuid,
+    f_cnt: u32,
+) -> Result<Vec<MulticastGroupQueueItemDelivery>, Error> {
+    let items: Vec<MulticastGroupQueueItemDelivery> =
+        multicast_group_queue_item_delivery::dsl::multicast_group_queue_item_delivery
+            .filter(multicast_group_queue_item_delivery::multicast_group_id.eq(&multicast_group_id))
+            .filter(multicast_group_queue_item_delivery::f_cnt.eq(f_cnt as i64))
+            .order_by(multicast_group_queue_item_delivery::created_at)
+            .load(&mut get_async_db_conn().await?)
+            .await
+            .map_err(|e| Error::from_diesel(e, multicast_group_id.to_string()))?;
+
+    if items.is_empty() {
+        return Err(Error::NotFound(format!(
+            "multicast-group: {}, f_cnt: {}",
+            multicast_group_id, f_cnt
+        )));
+    }
+
+    Ok(items)
+}
+
+// Sets the tx ack status of the delivery record of the given queue item. In case
+// this was the last pending delivery record of the multicast-group queue item
+// (all gateways reported their tx ack), then all its delivery records are returned.
+pub async fn set_queue_item_delivery_tx_ack_status(
+    queue_item_id: &Uuid,
+    tx_ack_status: &str,
+) -> Result<Option<Vec<MulticastGroupQueueItemDelivery>>, Error> {
+    let mut c = get_async_db_conn().await?;
+    let out = c
+        .build_transaction()
+        .run::<Option<Vec<MulticastGroupQueueItemDelivery>>, Error, _>(|c| {
+            Box::pin(async move {
+                _set_queue_item_delivery_tx_ack_status(c, queue_item_id, tx_ack_status, false).await
+            })
+        })
+        .await?;
+    info!(multicast_group_queue_item_id = %queue_item_id, tx_ack_status = %tx_ack_status, "Multicast-group queue item delivery updated");
+    Ok(out)
+}
+
+// Discards the pending delivery records of which the queue item has not been transmitted, or
+// of which the gateway did not report its tx ack, before the given timestamp. The related
+// queue items are deleted. For every multicast-group queue item of which this resolved the
+// last pending delivery record, all its delivery records are returned.
+pub async fn discard_timed_out_queue_item_deliveries(
+    before: DateTime<Utc>,
+    limit: usize,
+) -> Result<Vec<Vec<MulticastGroupQueueItemDelivery>>, Error> {
+    let items: Vec<MulticastGroupQueueItemDelivery> = diesel::sql_query(
+        r#"
+            select
+                d.*
+            from
+                multicast_group_queue_item_delivery d
+            left join multicast_group_queue_item qi
+                on qi.id = d.multicast_group_queue_item_id
+            where
+                d.tx_ack_status is null
+                and d.updated_at < $1
+                and (qi.id is null or qi.scheduler_run_after < $1)
+            order by
+                d.updated_at
+            limit $2
+        "#,
+    )
+    .bind::<diesel::sql_types::Timestamptz, _>(before)
+    .bind::<diesel::sql_types::Integer, _>(limit as i32)
+    .load(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| Error::from_diesel(e, "".into()))?;
+
+    let mut out = Vec::new();
+    for item in items {
+        let mut c = get_async_db_conn().await?;
+        let id = item.multicast_group_queue_item_id;
+        let deliveries = c
+            .build_transaction()
+            .run::<Option<Vec<MulticastGroupQueueItemDelivery>>, Error, _>(|c| {
+                Box::pin(async move {
+                    diesel::delete(
+                        multicast_group_queue_item::dsl::multicast_group_queue_item.find(&id),
+                    )
+                    .execute(c)
+                    .await?;
+
+                    _set_queue_item_delivery_tx_ack_status(c, &id, DELIVERY_STATUS_DISCARDED, true)
+                        .await
+                })
+            })
+            .await?;
+        info!(multicast_group_queue_item_id = %id, "Multicast-group queue item delivery timed out");
+
+        if let Some(v) = deliveries {
+            out.push(v);
+        }
+    }
+
+    Ok(out)
+}
+
+// Deletes the (no longer pending) delivery records which have been updated before the given
+// timestamp.
+pub async fn delete_queue_item_deliveries_before(before: DateTime<Utc>) -> Result<usize, Error> {
+    let ra = diesel::delete(
+        multicast_group_queue_item_delivery::dsl::multicast_group_queue_item_delivery
+            .filter(multicast_group_queue_item_delivery::tx_ack_status.is_not_null())
+            .filter(multicast_group_queue_item_delivery::updated_at.lt(before)),
+    )
+    .execute(&mut get_async_db_conn().await?)
+    .await?;
+    info!(count = ra, "Multicast-group queue item deliveries deleted");
+    Ok(ra)
+}
+
+async fn _set_queue_item_delivery_tx_ack_status(
+    c: &mut AsyncPgConnection,
+    queue_item_id: &Uuid,
+    tx_ack_status: &str,
+    pending_only: bool,
+) -> Result<Option<Vec<MulticastGroupQueueItemDelivery>>, Error> {
+    let d: Option<MulticastGroupQueueItemDelivery> =
+        multicast_group_queue_item_delivery::dsl::multicast_group_queue_item_delivery
+            .find(&queue_item_id)
+            .get_result(c)
+            .await
+            .optional()
+            .map_err(|e| Error::from_diesel(e, queue_item_id.to_string()))?;
+    let d = match d {
+        Some(v) => v,
+        None if pending_only => return Ok(None),
+        None => return Err(Error::NotFound(queue_item_id.to_string())),
+    };
+
+    // Lock all the delivery records of the same multicast-group queue item,
+    // such that only the last tx ack completes the delivery.
+    let items: Vec<MulticastGroupQueueItemDelivery> =
+        multicast_group_queue_item_delivery::dsl::multicast_group_queue_item_delivery
+            .filter(
+                multicast_group_queue_item_delivery::multicast_group_id.eq(&d.multicast_group_id),
+            )
+            .filter(multicast_group_queue_item_delivery::f_cnt.eq(d.f_cnt))
+            .order_by(multicast_group_queue_item_delivery::multicast_group_queue_item_id)
+            .for_update()
+            .load(c)
+            .await
+            .map_err(|e| Error::from_diesel(e, queue_item_id.to_string()))?;
+
+    let was_pending = items.iter().any(|v| {
+        v.multicast_group_queue_item_id == d.multicast_group_queue_item_id
+            && v.tx_ack_status.is_none()
+    });
+
+    // The delivery record was already resolved (e.g. by the tx ack of the gateway).
+    if pending_only && !was_pending {
+        return Ok(None);
+    }
+
+    let d: MulticastGroupQueueItemDelivery = diesel::update(
+        multicast_group_queue_item_delivery::dsl::multicast_group_queue_item_delivery
+            .find(&queue_item_id),
+    )
+    .set((
+        multicast_group_queue_item_delivery::updated_at.eq(Utc::now()),
+        multicast_group_queue_item_delivery::tx_ack_status.eq(tx_ack_status),
+    ))
+    .get_result(c)
+    .await
+    .map_err(|e| Error::from_diesel(e, queue_item_id.to_string()))?;
+
+    let items: Vec<MulticastGroupQueueItemDelivery> = items
+        .into_iter()
+        .map(|v| {
+            if v.multicast_group_queue_item_id == d.multicast_group_queue_item_id {
+                d.clone()
+            } else {
+                v
+            }
+        })
+        .collect();
+
+    if was_pending && items.iter().all(|v| v.tx_ack_status.is_some()) {
+        Ok(Some(items))
+    } else {
+        Ok(None)
+    }
+}
+
 pub async fn get_schedulable_queue_items(limit: usize) -> Result<Vec<MulticastGroupQueueItem>> {
     let mut c = get_async_db_conn().await?;
     c.build_transaction()
@@ -840,6 +1281,159 @@ pub mod test {
         assert!(dev_euis.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_auto_membership() {
+        let _guard = test::prepare().await;
+
+        let t = tenant::create(tenant::Tenant {
+            name: "test-tenant".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let app = application::create(application::Application {
+            name: "test-app".into(),
+            tenant_id: t.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let dp = device_profile::create(device_profile::DeviceProfile {
+            tenant_id: t.id,
+            name: "test-dp".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let dp_other = device_profile::create(device_profile::DeviceProfile {
+            tenant_id: t.id,
+            name: "test-dp-other".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let mut d1 = device::create(device::Device {
+            application_id: app.id,
+            device_profile_id: dp.id,
+            name: "test-device-1".into(),
+            dev_eui: EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 1]),
+            tags: fields::KeyValue::new(
+                [("type".to_string(), "street-light".to_string())]
+                    .iter()
+                    .cloned()
+                    .collect(),
+            ),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let d2 = device::create(device::Device {
+            application_id: app.id,
+            device_profile_id: dp_other.id,
+            name: "test-device-2".into(),
+            dev_eui: EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 2]),
+            tags: fields::KeyValue::new(
+                [("type".to_string(), "street-light".to_string())]
+                    .iter()
+                    .cloned()
+                    .collect(),
+            ),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let mut mg = create(MulticastGroup {
+            application_id: app.id,
+            name: "test-mg".into(),
+            region: CommonName::EU868,
+            mc_addr: DevAddr::from_be_bytes([1, 2, 3, 4]),
+            group_type: "C".into(),
+            auto_membership: true,
+            selector_device_profile_id: Some(dp.id),
+            selector_tags: fields::KeyValue::new(
+                [("type".to_string(), "street-light".to_string())]
+                    .iter()
+                    .cloned()
+                    .collect(),
+            ),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // only the device matching both device-profile and tags is member
+        let dev_euis = get_dev_euis(&mg.id).await.unwrap();
+        assert_eq!(vec![d1.dev_eui], dev_euis);
+
+        // manual membership changes are not allowed
+        assert!(add_device(&mg.id, &d2.dev_eui).await.is_err());
+        assert!(remove_device(&mg.id, &d1.dev_eui).await.is_err());
+
+        // a new matching device is added on create
+        let d3 = device::create(device::Device {
+            application_id: app.id,
+            device_profile_id: dp.id,
+            name: "test-device-3".into(),
+            dev_eui: EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 3]),
+            tags: fields::KeyValue::new(
+                [
+                    ("type".to_string(), "street-light".to_string()),
+                    ("street".to_string(), "main".to_string()),
+                ]
+                .iter()
+                .cloned()
+                .collect(),
+            ),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let mut dev_euis = get_dev_euis(&mg.id).await.unwrap();
+        dev_euis.sort_by_key(|v| v.to_be_bytes());
+        assert_eq!(vec![d1.dev_eui, d3.dev_eui], dev_euis);
+
+        // a device no longer matching is removed on update
+        d1.tags = fields::KeyValue::new(HashMap::new());
+        let d1 = device::update(d1).await.unwrap();
+        let dev_euis = get_dev_euis(&mg.id).await.unwrap();
+        assert_eq!(vec![d3.dev_eui], dev_euis);
+
+        // updating the selector re-evaluates the membership
+        mg.selector_device_profile_id = None;
+        mg.selector_tags = fields::KeyValue::new(HashMap::new());
+        let mut mg = update(mg).await.unwrap();
+        let mut dev_euis = get_dev_euis(&mg.id).await.unwrap();
+        dev_euis.sort_by_key(|v| v.to_be_bytes());
+        assert_eq!(vec![d1.dev_eui, d2.dev_eui, d3.dev_eui], dev_euis);
+
+        // deleting the selector device-profile disables the automatic membership
+        let dp_empty = device_profile::create(device_profile::DeviceProfile {
+            tenant_id: t.id,
+            name: "test-dp-empty".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        mg.selector_device_profile_id = Some(dp_empty.id);
+        let mg = update(mg).await.unwrap();
+        assert!(get_dev_euis(&mg.id).await.unwrap().is_empty());
+
+        device_profile::delete(&dp_empty.id).await.unwrap();
+        let mg = get(&mg.id).await.unwrap();
+        assert!(!mg.auto_membership);
+        assert_eq!(None, mg.selector_device_profile_id);
+
+        // the device is not added, as the group no longer has automatic membership
+        device::update(d1).await.unwrap();
+        assert!(get_dev_euis(&mg.id).await.unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_gateway() {
         let _guard = test::prepare().await;
@@ -999,8 +1593,13 @@ pub mod test {
         assert_eq!(10, qi_get.f_cnt);
         assert_eq!(vec![3, 2, 1], qi_get.data);
 
-        // delete
-        delete_queue_item(&ids[0]).await.unwrap();
+        // delete, the pending delivery record is discarded
+        let deliveries = delete_queue_item(&ids[0]).await.unwrap().unwrap();
+        assert_eq!(1, deliveries.len());
+        assert_eq!(
+            Some(DELIVERY_STATUS_DISCARDED.to_string()),
+            deliveries[0].tx_ack_status
+        );
         assert!(delete_queue_item(&ids[0]).await.is_err());
 
         // Enqueue (Class-C) (GPS time)
@@ -8,6 +8,11 @@ diesel::table! {
         name -> Varchar,
         is_admin -> Bool,
         tenant_id -> Nullable<Uuid>,
+        expires_at -> Nullable<Timestamptz>,
+        last_used_at -> Nullable<Timestamptz>,
+        #[max_length = 30]
+        scope -> Varchar,
+        application_id -> Nullable<Uuid>,
     }
 }
 
@@ -36,6 +41,61 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    application_user (application_id, user_id) {
+        application_id -> Uuid,
+        user_id -> Uuid,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        is_admin -> Bool,
+        is_device_admin -> Bool,
+    }
+}
+
+diesel::table! {
+    audit_log (id) {
+        id -> Uuid,
+        created_at -> Timestamptz,
+        tenant_id -> Nullable<Uuid>,
+        user_id -> Nullable<Uuid>,
+        api_key_id -> Nullable<Uuid>,
+        #[max_length = 100]
+        service -> Varchar,
+        #[max_length = 100]
+        method -> Varchar,
+        #[max_length = 100]
+        target_id -> Varchar,
+        changes -> Jsonb,
+        #[max_length = 45]
+        source_ip -> Varchar,
+    }
+}
+
+diesel::table! {
+    codec (id) {
+        id -> Uuid,
+        tenant_id -> Uuid,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        #[max_length = 100]
+        name -> Varchar,
+        description -> Text,
+    }
+}
+
+diesel::table! {
+    codec_version (id) {
+        id -> Uuid,
+        codec_id -> Uuid,
+        created_at -> Timestamptz,
+        version -> Int4,
+        #[max_length = 20]
+        runtime -> Varchar,
+        script -> Text,
+        tests -> Jsonb,
+    }
+}
+
 diesel::table! {
     device (dev_eui) {
         dev_eui -> Bytea,
@@ -86,6 +146,7 @@ diesel::table! {
         app_key -> Bytea,
         dev_nonces -> Array<Nullable<Int4>>,
         join_nonce -> Int4,
+        gen_app_key -> Bytea,
     }
 }
 
@@ -153,6 +214,10 @@ diesel::table! {
         relay_overall_limit_bucket_size -> Int2,
         allow_roaming -> Bool,
         rx1_delay -> Int2,
+        codec_version_id -> Nullable<Uuid>,
+        #[max_length = 20]
+        gateway_selection_strategy -> Nullable<Varchar>,
+        supports_clock_sync -> Bool,
     }
 }
 
@@ -212,6 +277,75 @@ diesel::table! {
         f_cnt_down -> Nullable<Int8>,
         timeout_after -> Nullable<Timestamptz>,
         is_encrypted -> Bool,
+        expires_at -> Nullable<Timestamptz>,
+        priority -> Int4,
+    }
+}
+
+diesel::table! {
+    fragmentation_session (id) {
+        id -> Uuid,
+        dev_eui -> Bytea,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        frag_index -> Int2,
+        nb_frag -> Int4,
+        fragment_size -> Int2,
+        redundancy -> Int4,
+        padding -> Int2,
+        nb_frag_sent -> Int4,
+        nb_frag_received -> Nullable<Int4>,
+        missing_frag -> Nullable<Int4>,
+        #[max_length = 20]
+        status -> Varchar,
+        completed_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    fuota_campaign (id) {
+        id -> Uuid,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        started_at -> Nullable<Timestamptz>,
+        completed_at -> Nullable<Timestamptz>,
+        #[max_length = 100]
+        name -> Varchar,
+        application_id -> Uuid,
+        device_profile_id -> Uuid,
+        multicast_group_id -> Nullable<Uuid>,
+        #[max_length = 1]
+        multicast_group_type -> Bpchar,
+        #[max_length = 20]
+        multicast_class_c_scheduling_type -> Varchar,
+        multicast_dr -> Int2,
+        multicast_class_b_ping_slot_nb_k -> Int2,
+        multicast_frequency -> Int8,
+        multicast_timeout -> Int2,
+        unicast_timeout -> Int4,
+        fragment_size -> Int2,
+        redundancy -> Int4,
+        payload -> Bytea,
+        #[max_length = 20]
+        step -> Varchar,
+        scheduler_run_after -> Timestamptz,
+        session_start_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    fuota_campaign_device (fuota_campaign_id, dev_eui) {
+        fuota_campaign_id -> Uuid,
+        dev_eui -> Bytea,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        mc_group_setup_completed_at -> Nullable<Timestamptz>,
+        frag_session_setup_completed_at -> Nullable<Timestamptz>,
+        mc_session_completed_at -> Nullable<Timestamptz>,
+        frag_status_completed_at -> Nullable<Timestamptz>,
+        nb_frag_received -> Nullable<Int4>,
+        missing_frag -> Nullable<Int4>,
+        error_msg -> Text,
     }
 }
 
@@ -232,6 +366,16 @@ diesel::table! {
         tls_certificate -> Nullable<Bytea>,
         tags -> Jsonb,
         properties -> Jsonb,
+        is_online -> Nullable<Bool>,
+    }
+}
+
+diesel::table! {
+    gateway_state_change (id) {
+        id -> Uuid,
+        gateway_id -> Bytea,
+        created_at -> Timestamptz,
+        is_online -> Bool,
     }
 }
 
@@ -256,6 +400,9 @@ diesel::table! {
         class_b_ping_slot_nb_k -> Int2,
         #[max_length = 20]
         class_c_scheduling_type -> Varchar,
+        auto_membership -> Bool,
+        selector_device_profile_id -> Nullable<Uuid>,
+        selector_tags -> Jsonb,
     }
 }
 
@@ -289,6 +436,19 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    multicast_group_queue_item_delivery (multicast_group_queue_item_id) {
+        multicast_group_queue_item_id -> Uuid,
+        multicast_group_id -> Uuid,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        f_cnt -> Int8,
+        gateway_id -> Bytea,
+        #[max_length = 30]
+        tx_ack_status -> Nullable<Varchar>,
+    }
+}
+
 diesel::table! {
     relay_device (relay_dev_eui, dev_eui) {
         relay_dev_eui -> Bytea,
@@ -327,6 +487,7 @@ diesel::table! {
         private_gateways_up -> Bool,
         private_gateways_down -> Bool,
         tags -> Jsonb,
+        require_totp -> Bool,
     }
 }
 
@@ -355,48 +516,87 @@ diesel::table! {
         #[max_length = 200]
         password_hash -> Varchar,
         note -> Text,
+        #[max_length = 64]
+        totp_secret -> Nullable<Varchar>,
+        totp_enabled -> Bool,
+        totp_last_counter -> Int8,
+    }
+}
+
+diesel::table! {
+    user_totp_recovery_code (user_id, code_hash) {
+        user_id -> Uuid,
+        #[max_length = 64]
+        code_hash -> Varchar,
+        created_at -> Timestamptz,
     }
 }
 
+diesel::joinable!(api_key -> application (application_id));
 diesel::joinable!(api_key -> tenant (tenant_id));
 diesel::joinable!(application -> tenant (tenant_id));
 diesel::joinable!(application_integration -> application (application_id));
+diesel::joinable!(application_user -> application (application_id));
+diesel::joinable!(application_user -> user (user_id));
+diesel::joinable!(codec -> tenant (tenant_id));
+diesel::joinable!(codec_version -> codec (codec_id));
 diesel::joinable!(device -> application (application_id));
 diesel::joinable!(device -> device_profile (device_profile_id));
 diesel::joinable!(device_config_store -> device (dev_eui));
 diesel::joinable!(device_keys -> device (dev_eui));
+diesel::joinable!(device_profile -> codec_version (codec_version_id));
 diesel::joinable!(device_profile -> tenant (tenant_id));
 diesel::joinable!(device_queue_item -> device (dev_eui));
+diesel::joinable!(fragmentation_session -> device (dev_eui));
+diesel::joinable!(fuota_campaign -> application (application_id));
+diesel::joinable!(fuota_campaign -> device_profile (device_profile_id));
+diesel::joinable!(fuota_campaign -> multicast_group (multicast_group_id));
+diesel::joinable!(fuota_campaign_device -> device (dev_eui));
+diesel::joinable!(fuota_campaign_device -> fuota_campaign (fuota_campaign_id));
 diesel::joinable!(gateway -> tenant (tenant_id));
+diesel::joinable!(gateway_state_change -> gateway (gateway_id));
 diesel::joinable!(multicast_group -> application (application_id));
+diesel::joinable!(multicast_group -> device_profile (selector_device_profile_id));
 diesel::joinable!(multicast_group_device -> device (dev_eui));
 diesel::joinable!(multicast_group_device -> multicast_group (multicast_group_id));
 diesel::joinable!(multicast_group_gateway -> gateway (gateway_id));
 diesel::joinable!(multicast_group_gateway -> multicast_group (multicast_group_id));
 diesel::joinable!(multicast_group_queue_item -> gateway (gateway_id));
 diesel::joinable!(multicast_group_queue_item -> multicast_group (multicast_group_id));
+diesel::joinable!(multicast_group_queue_item_delivery -> multicast_group (multicast_group_id));
 diesel::joinable!(relay_gateway -> tenant (tenant_id));
 diesel::joinable!(tenant_user -> tenant (tenant_id));
 diesel::joinable!(tenant_user -> user (user_id));
+diesel::joinable!(user_totp_recovery_code -> user (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     api_key,
     application,
     application_integration,
+    application_user,
+    audit_log,
+    codec,
+    codec_version,
     device,
     device_config_store,
     device_keys,
     device_profile,
     device_profile_template,
     device_queue_item,
+    fragmentation_session,
+    fuota_campaign,
+    fuota_campaign_device,
     gateway,
+    gateway_state_change,
     multicast_group,
     multicast_group_device,
     multicast_group_gateway,
     multicast_group_queue_item,
+    multicast_group_queue_item_delivery,
     relay_device,
     relay_gateway,
     tenant,
     tenant_user,
     user,
+    user_totp_recovery_code,
 );
@@ -25,6 +25,7 @@ pub struct Tenant {
     pub private_gateways_up: bool,
     pub private_gateways_down: bool,
     pub tags: fields::KeyValue,
+    pub require_totp: bool,
 }
 
 impl Tenant {
@@ -52,6 +53,7 @@ impl Default for Tenant {
             private_gateways_up: false,
             private_gateways_down: false,
             tags: fields::KeyValue::new(HashMap::new()),
+            require_totp: false,
         }
     }
 }
@@ -137,6 +139,7 @@ pub async fn update(t: Tenant) -> Result<Tenant, Error> {
             tenant::private_gateways_up.eq(&t.private_gateways_up),
             tenant::private_gateways_down.eq(&t.private_gateways_down),
             tenant::tags.eq(&t.tags),
+            tenant::require_totp.eq(&t.require_totp),
         ))
         .get_result(&mut get_async_db_conn().await?)
         .await
@@ -335,6 +338,7 @@ pub mod test {
             private_gateways_up: true,
             private_gateways_down: true,
             tags: fields::KeyValue::new(HashMap::new()),
+            require_totp: false,
         };
         create(t).await.unwrap()
     }
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use diesel::{dsl, prelude::*};
@@ -8,12 +10,15 @@ use pbkdf2::{
     Algorithm, Pbkdf2,
 };
 use rand_core::OsRng;
+use sha2::{Digest, Sha256};
 use tracing::info;
 use uuid::Uuid;
 
 use super::error::Error;
-use super::get_async_db_conn;
-use super::schema::user;
+use super::schema::{
+    application, application_user, tenant, tenant_user, user, user_totp_recovery_code,
+};
+use super::{get_async_db_conn, get_async_redis_conn, redis_key};
 
 #[derive(Queryable, Insertable, PartialEq, Eq, Debug, Clone)]
 #[diesel(table_name = user)]
@@ -28,6 +33,9 @@ pub struct User {
     pub email_verified: bool,
     pub password_hash: String,
     pub note: String,
+    pub totp_secret: Option<String>,
+    pub totp_enabled: bool,
+    pub totp_last_counter: i64,
 }
 
 impl Default for User {
@@ -45,6 +53,9 @@ impl Default for User {
             email_verified: false,
             password_hash: "".into(),
             note: "".into(),
+            totp_secret: None,
+            totp_enabled: false,
+            totp_last_counter: 0,
         }
     }
 }
@@ -156,6 +167,199 @@ pub async fn set_password_hash(id: &Uuid, hash: &str) -> Result<User, Error> {
     Ok(u)
 }
 
+// Sets the TOTP secret of the user. This disables TOTP until it is enabled again using
+// enable_totp, which must happen after the user has confirmed the secret with a valid code.
+pub async fn set_totp_secret(id: &Uuid, secret: &str) -> Result<User, Error> {
+    let u: User = diesel::update(user::dsl::user.find(&id))
+        .set((
+            user::updated_at.eq(Utc::now()),
+            user::totp_secret.eq(secret),
+            user::totp_enabled.eq(false),
+            user::totp_last_counter.eq(0),
+        ))
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    info!(id = %id, "TOTP secret set");
+    Ok(u)
+}
+
+// Enables TOTP for the user and replaces the recovery-codes with the given codes.
+pub async fn enable_totp(id: &Uuid, recovery_codes: &[String]) -> Result<User, Error> {
+    let mut c = get_async_db_conn().await?;
+    let u: User = c
+        .build_transaction()
+        .run::<User, Error, _>(|c| {
+            Box::pin(async move {
+                let u: User = diesel::update(user::dsl::user.find(&id))
+                    .set((user::updated_at.eq(Utc::now()), user::totp_enabled.eq(true)))
+                    .filter(user::dsl::totp_secret.is_not_null())
+                    .get_result(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+
+                diesel::delete(
+                    user_totp_recovery_code::dsl::user_totp_recovery_code
+                        .filter(user_totp_recovery_code::dsl::user_id.eq(&id)),
+                )
+                .execute(c)
+                .await?;
+
+                let now = Utc::now();
+                diesel::insert_into(user_totp_recovery_code::table)
+                    .values(
+                        recovery_codes
+                            .iter()
+                            .map(|code| {
+                                (
+                                    user_totp_recovery_code::user_id.eq(id),
+                                    user_totp_recovery_code::code_hash.eq(hash_recovery_code(code)),
+                                    user_totp_recovery_code::created_at.eq(now),
+                                )
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                    .execute(c)
+                    .await?;
+
+                Ok(u)
+            })
+        })
+        .await?;
+    info!(id = %id, "TOTP enabled");
+    Ok(u)
+}
+
+// Disables TOTP for the user and removes the TOTP secret and recovery-codes.
+pub async fn disable_totp(id: &Uuid) -> Result<User, Error> {
+    let mut c = get_async_db_conn().await?;
+    let u: User = c
+        .build_transaction()
+        .run::<User, Error, _>(|c| {
+            Box::pin(async move {
+                diesel::delete(
+                    user_totp_recovery_code::dsl::user_totp_recovery_code
+                        .filter(user_totp_recovery_code::dsl::user_id.eq(&id)),
+                )
+                .execute(c)
+                .await?;
+
+                diesel::update(user::dsl::user.find(&id))
+                    .set((
+                        user::updated_at.eq(Utc::now()),
+                        user::totp_secret.eq(None::<String>),
+                        user::totp_enabled.eq(false),
+                        user::totp_last_counter.eq(0),
+                    ))
+                    .get_result(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, id.to_string()))
+            })
+        })
+        .await?;
+    info!(id = %id, "TOTP disabled");
+    Ok(u)
+}
+
+// Sets the last accepted TOTP time-step of the user. It returns false when the given
+// time-step is not greater than the last accepted time-step, in which case the code must
+// be rejected as it has already been used.
+pub async fn set_totp_last_counter(id: &Uuid, counter: i64) -> Result<bool, Error> {
+    let ra = diesel::update(
+        user::dsl::user
+            .find(&id)
+            .filter(user::dsl::totp_last_counter.lt(counter)),
+    )
+    .set(user::totp_last_counter.eq(counter))
+    .execute(&mut get_async_db_conn().await?)
+    .await?;
+    Ok(ra != 0)
+}
+
+// Returns the number of failed TOTP attempts of the user within the lockout duration.
+pub async fn get_totp_failed_attempts(id: &Uuid) -> Result<u32, Error> {
+    let key = redis_key(format!("user:{{{}}}:totp:failed", id));
+    let count: Option<u32> = redis::cmd("GET")
+        .arg(&key)
+        .query_async(&mut get_async_redis_conn().await?)
+        .await?;
+    Ok(count.unwrap_or_default())
+}
+
+// Increments the number of failed TOTP attempts of the user and returns the new value.
+// The counter expires after the given lockout duration, counted from the last failed
+// attempt.
+pub async fn increment_totp_failed_attempts(id: &Uuid, ttl: Duration) -> Result<u32, Error> {
+    let key = redis_key(format!("user:{{{}}}:totp:failed", id));
+    let (count,): (u32,) = redis::pipe()
+        .atomic()
+        .cmd("INCR")
+        .arg(&key)
+        .cmd("PEXPIRE")
+        .arg(&key)
+        .arg(ttl.as_millis() as usize)
+        .ignore()
+        .query_async(&mut get_async_redis_conn().await?)
+        .await?;
+    Ok(count)
+}
+
+// Resets the number of failed TOTP attempts of the user.
+pub async fn reset_totp_failed_attempts(id: &Uuid) -> Result<(), Error> {
+    let key = redis_key(format!("user:{{{}}}:totp:failed", id));
+    redis::cmd("DEL")
+        .arg(&key)
+        .query_async(&mut get_async_redis_conn().await?)
+        .await?;
+    Ok(())
+}
+
+// Consumes the given recovery-code. It returns true when the code was valid, in which case it
+// can not be used again.
+pub async fn use_totp_recovery_code(id: &Uuid, code: &str) -> Result<bool, Error> {
+    let ra = diesel::delete(
+        user_totp_recovery_code::dsl::user_totp_recovery_code
+            .filter(user_totp_recovery_code::dsl::user_id.eq(&id))
+            .filter(user_totp_recovery_code::dsl::code_hash.eq(hash_recovery_code(code))),
+    )
+    .execute(&mut get_async_db_conn().await?)
+    .await?;
+
+    if ra != 0 {
+        info!(id = %id, "TOTP recovery-code used");
+    }
+    Ok(ra != 0)
+}
+
+// Returns true if the user is a member of a tenant which requires TOTP, either
+// directly or through one of the tenant applications.
+pub async fn is_totp_required_by_tenant(id: &Uuid) -> Result<bool, Error> {
+    let count: i64 = tenant::dsl::tenant
+        .select(dsl::count_star())
+        .filter(tenant::dsl::require_totp.eq(true))
+        .filter(
+            dsl::exists(
+                tenant_user::dsl::tenant_user.filter(
+                    tenant_user::dsl::tenant_id
+                        .eq(tenant::dsl::id)
+                        .and(tenant_user::dsl::user_id.eq(&id)),
+                ),
+            )
+            .or(dsl::exists(
+                application_user::dsl::application_user
+                    .inner_join(application::table)
+                    .filter(
+                        application::dsl::tenant_id
+                            .eq(tenant::dsl::id)
+                            .and(application_user::dsl::user_id.eq(&id)),
+                    ),
+            )),
+        )
+        .first(&mut get_async_db_conn().await?)
+        .await?;
+    Ok(count != 0)
+}
+
 pub async fn delete(id: &Uuid) -> Result<(), Error> {
     let ra = diesel::delete(user::dsl::user.find(&id))
         .execute(&mut get_async_db_conn().await?)
@@ -208,6 +412,11 @@ fn hash_password(pw: &str, rounds: u32) -> Result<String, Error> {
     }
 }
 
+// Recovery-codes are random and long enough to not require a (slow) password hash.
+fn hash_recovery_code(code: &str) -> String {
+    hex::encode(Sha256::digest(code.trim().to_lowercase().as_bytes()))
+}
+
 fn verify_password(pw: &str, hash: &str) -> bool {
     let parsed = match PasswordHash::new(hash) {
         Ok(v) => v,
@@ -224,6 +433,15 @@ pub mod test {
     use super::*;
     use crate::test;
 
+    pub async fn get_totp_recovery_code_count(id: &Uuid) -> i64 {
+        user_totp_recovery_code::dsl::user_totp_recovery_code
+            .select(dsl::count_star())
+            .filter(user_totp_recovery_code::dsl::user_id.eq(&id))
+            .first(&mut get_async_db_conn().await.unwrap())
+            .await
+            .unwrap()
+    }
+
     pub async fn create_user() -> User {
         let mut user = User {
             is_admin: true,
@@ -278,4 +496,110 @@ pub mod test {
         delete(&user.id).await.unwrap();
         assert!(delete(&user.id).await.is_err());
     }
+
+    #[tokio::test]
+    async fn test_totp() {
+        let _guard = test::prepare().await;
+        let user = create_user().await;
+        assert!(!is_totp_required_by_tenant(&user.id).await.unwrap());
+
+        // TOTP can not be enabled without secret
+        assert!(enable_totp(&user.id, &[]).await.is_err());
+
+        // set secret
+        let user = set_totp_secret(&user.id, "JBSWY3DPEHPK3PXP").await.unwrap();
+        assert_eq!(Some("JBSWY3DPEHPK3PXP".to_string()), user.totp_secret);
+        assert!(!user.totp_enabled);
+
+        // enable
+        let user = enable_totp(&user.id, &["aaaaa-bbbbb".into(), "ccccc-ddddd".into()])
+            .await
+            .unwrap();
+        assert!(user.totp_enabled);
+        assert_eq!(2, get_totp_recovery_code_count(&user.id).await);
+
+        // use recovery-code
+        assert!(!use_totp_recovery_code(&user.id, "eeeee-fffff")
+            .await
+            .unwrap());
+        assert!(use_totp_recovery_code(&user.id, "AAAAA-BBBBB")
+            .await
+            .unwrap());
+        assert!(!use_totp_recovery_code(&user.id, "aaaaa-bbbbb")
+            .await
+            .unwrap());
+        assert_eq!(1, get_totp_recovery_code_count(&user.id).await);
+
+        // last counter
+        assert!(set_totp_last_counter(&user.id, 10).await.unwrap());
+        assert!(!set_totp_last_counter(&user.id, 10).await.unwrap());
+        assert!(!set_totp_last_counter(&user.id, 9).await.unwrap());
+        assert!(set_totp_last_counter(&user.id, 11).await.unwrap());
+
+        // failed attempts
+        let ttl = Duration::from_secs(60);
+        assert_eq!(0, get_totp_failed_attempts(&user.id).await.unwrap());
+        assert_eq!(
+            1,
+            increment_totp_failed_attempts(&user.id, ttl).await.unwrap()
+        );
+        assert_eq!(
+            2,
+            increment_totp_failed_attempts(&user.id, ttl).await.unwrap()
+        );
+        assert_eq!(2, get_totp_failed_attempts(&user.id).await.unwrap());
+        reset_totp_failed_attempts(&user.id).await.unwrap();
+        assert_eq!(0, get_totp_failed_attempts(&user.id).await.unwrap());
+
+        // disable
+        let user = disable_totp(&user.id).await.unwrap();
+        assert!(!user.totp_enabled);
+        assert_eq!(None, user.totp_secret);
+        assert_eq!(0, get_totp_recovery_code_count(&user.id).await);
+
+        // required by tenant through application user
+        let t = crate::storage::tenant::create(crate::storage::tenant::Tenant {
+            name: "test-tenant-app".into(),
+            require_totp: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let app = crate::storage::application::create(crate::storage::application::Application {
+            tenant_id: t.id,
+            name: "test-app".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        crate::storage::application::add_user(crate::storage::application::ApplicationUser {
+            application_id: app.id,
+            user_id: user.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert!(is_totp_required_by_tenant(&user.id).await.unwrap());
+        crate::storage::application::delete_user(&app.id, &user.id)
+            .await
+            .unwrap();
+        assert!(!is_totp_required_by_tenant(&user.id).await.unwrap());
+
+        // required by tenant
+        let t = crate::storage::tenant::create(crate::storage::tenant::Tenant {
+            name: "test-tenant".into(),
+            require_totp: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        crate::storage::tenant::add_user(crate::storage::tenant::TenantUser {
+            tenant_id: t.id,
+            user_id: user.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert!(is_totp_required_by_tenant(&user.id).await.unwrap());
+    }
 }
@@ -124,6 +124,55 @@ pub async fn log_downlink_for_gateway(dfl: &stream::DownlinkFrameLog) -> Result<
     Ok(())
 }
 
+pub async fn log_raw_packet_forwarder_event_for_gateway(
+    rfl: &stream::RawPacketForwarderEventLog,
+) -> Result<()> {
+    if rfl.gateway_id.is_empty() {
+        return Err(anyhow!("gateway_id must be set"));
+    }
+
+    let conf = config::get();
+
+    let b = rfl.encode_to_vec();
+
+    // per gateway stream
+    if conf.monitoring.per_gateway_frame_log_max_history > 0 {
+        let key = redis_key(format!("gw:{{{}}}:stream:frame", rfl.gateway_id));
+        redis::pipe()
+            .atomic()
+            .cmd("XADD")
+            .arg(&key)
+            .arg("MAXLEN")
+            .arg(conf.monitoring.per_gateway_frame_log_max_history)
+            .arg("*")
+            .arg("raw")
+            .arg(&b)
+            .ignore()
+            .cmd("PEXPIRE")
+            .arg(&key)
+            .arg(conf.monitoring.per_gateway_frame_log_ttl.as_millis() as usize)
+            .ignore()
+            .query_async(&mut get_async_redis_conn().await?)
+            .await?;
+    }
+
+    // global gateway stream
+    if conf.monitoring.gateway_frame_log_max_history > 0 {
+        let key = redis_key("gw:stream:frame".to_string());
+        redis::cmd("XADD")
+            .arg(&key)
+            .arg("MAXLEN")
+            .arg(conf.monitoring.gateway_frame_log_max_history)
+            .arg("*")
+            .arg("raw")
+            .arg(&b)
+            .query_async(&mut get_async_redis_conn().await?)
+            .await?;
+    }
+
+    Ok(())
+}
+
 pub async fn log_uplink_for_device(ufl: &stream::UplinkFrameLog) -> Result<()> {
     if ufl.dev_eui.is_empty() {
         return Err(anyhow!("dev_eui must be set"));
@@ -359,6 +408,31 @@ async fn handle_stream(
                 channel.send(pl).await?;
             }
         }
+        "raw" => {
+            trace!(key = %k, id = %stream_id, "frame-log received from stream");
+            if let redis::Value::BulkString(b) = v {
+                let pl = stream::RawPacketForwarderEventLog::decode(&mut Cursor::new(b))?;
+
+                let pl = api::LogItem {
+                    id: stream_id.to_string(),
+                    time: pl.time.as_ref().map(|t| prost_types::Timestamp {
+                        seconds: t.seconds,
+                        nanos: t.nanos,
+                    }),
+                    description: "RawPacketForwarderEvent".into(),
+                    body: json!({
+                        "payload": hex::encode(&pl.payload),
+                    })
+                    .to_string(),
+                    properties: [("Gateway ID".to_string(), pl.gateway_id)]
+                        .iter()
+                        .cloned()
+                        .collect(),
+                };
+
+                channel.send(pl).await?;
+            }
+        }
         _ => {
             error!(key = %k, "Unexpected key in frame-log stream");
         }
@@ -0,0 +1,27 @@
+use anyhow::Result;
+use prost::Message;
+
+use crate::config;
+use crate::storage::{get_async_redis_conn, redis_key};
+use chirpstack_api::stream;
+
+pub async fn log_state_change(sc: &stream::GatewayStateChange) -> Result<()> {
+    let conf = config::get();
+
+    if conf.monitoring.gateway_state_log_max_history > 0 {
+        let key = redis_key("gw:stream:state".to_string());
+        let b = sc.encode_to_vec();
+
+        redis::cmd("XADD")
+            .arg(&key)
+            .arg("MAXLEN")
+            .arg(conf.monitoring.gateway_state_log_max_history)
+            .arg("*")
+            .arg("state")
+            .arg(&b)
+            .query_async(&mut get_async_redis_conn().await?)
+            .await?;
+    }
+
+    Ok(())
+}
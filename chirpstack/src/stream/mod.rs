@@ -2,4 +2,5 @@ pub mod api_request;
 pub mod backend_interfaces;
 pub mod event;
 pub mod frame;
+pub mod gateway;
 pub mod meta;
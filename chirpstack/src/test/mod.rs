@@ -71,7 +71,9 @@ pub async fn prepare<'a>() -> std::sync::MutexGuard<'a, ()> {
                     topic_prefix: "eu868".into(),
                     ..Default::default()
                 },
+                ..Default::default()
             },
+            extra_backends: vec![],
         },
     }];
     config::set(conf);
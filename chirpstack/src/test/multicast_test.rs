@@ -5,7 +5,7 @@ use crate::storage::{
     application, device, device_gateway, device_profile, fields, gateway, multicast, tenant,
 };
 use crate::{downlink, gateway::backend as gateway_backend, integration, test};
-use chirpstack_api::{gw, internal};
+use chirpstack_api::{gw, integration as integration_pb, internal};
 use lrwn::{AES128Key, DevAddr, EUI64};
 
 struct MulticastTest {
@@ -228,6 +228,226 @@ async fn test_multicast() {
     }
 }
 
+#[tokio::test]
+async fn test_multicast_delivery() {
+    let _guard = test::prepare().await;
+    integration::set_mock().await;
+    integration::mock::reset().await;
+    gateway_backend::set_backend("eu868", Box::new(gateway_backend::mock::Backend {})).await;
+    gateway_backend::mock::reset().await;
+
+    let t = tenant::create(tenant::Tenant {
+        name: "test-tenant".into(),
+        can_have_gateways: true,
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let gw = gateway::create(gateway::Gateway {
+        tenant_id: t.id,
+        gateway_id: EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+        name: "test-gw".into(),
+        properties: fields::KeyValue::new(
+            [("region_config_id".to_string(), "eu868".to_string())]
+                .iter()
+                .cloned()
+                .collect(),
+        ),
+        stats_interval_secs: 30,
+        last_seen_at: Some(Utc::now()),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let app = application::create(application::Application {
+        name: "test-app".into(),
+        tenant_id: t.id,
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let dp = device_profile::create(device_profile::DeviceProfile {
+        name: "test-dp".into(),
+        tenant_id: t.id,
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let d = device::create(device::Device {
+        name: "test-dev".into(),
+        application_id: app.id,
+        device_profile_id: dp.id,
+        dev_eui: EUI64::from_be_bytes([8, 7, 6, 5, 4, 3, 2, 1]),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let mg = multicast::create(multicast::MulticastGroup {
+        application_id: app.id,
+        name: "test-mg".into(),
+        mc_addr: DevAddr::from_be_bytes([1, 2, 3, 4]),
+        f_cnt: 10,
+        group_type: "C".into(),
+        dr: 3,
+        frequency: 868300000,
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    multicast::add_device(&mg.id, &d.dev_eui).await.unwrap();
+
+    device_gateway::save_rx_info(&internal::DeviceGatewayRxInfo {
+        dev_eui: d.dev_eui.to_vec(),
+        items: vec![internal::DeviceGatewayRxInfoItem {
+            gateway_id: gw.gateway_id.to_vec(),
+            ..Default::default()
+        }],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let f_cnt = downlink::multicast::enqueue(multicast::MulticastGroupQueueItem {
+        multicast_group_id: mg.id,
+        f_port: 5,
+        data: vec![1, 2, 3],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    // Pending before the tx ack.
+    let deliveries = multicast::get_queue_item_deliveries(&mg.id, f_cnt)
+        .await
+        .unwrap();
+    assert_eq!(1, deliveries.len());
+    assert_eq!(gw.gateway_id, deliveries[0].gateway_id);
+    assert_eq!(None, deliveries[0].tx_ack_status);
+
+    downlink::scheduler::schedule_multicast_group_queue_batch(1)
+        .await
+        .unwrap();
+    let downlink_frames = gateway_backend::mock::get_downlink_frames().await;
+    assert_eq!(1, downlink_frames.len());
+
+    downlink::tx_ack::TxAck::handle(gw::DownlinkTxAck {
+        gateway_id: gw.gateway_id.to_string(),
+        downlink_id: downlink_frames[0].downlink_id,
+        items: vec![gw::DownlinkTxAckItem {
+            status: gw::TxAckStatus::Ok.into(),
+        }],
+        ..Default::default()
+    })
+    .await;
+
+    // Delivered after the tx ack.
+    let deliveries = multicast::get_queue_item_deliveries(&mg.id, f_cnt)
+        .await
+        .unwrap();
+    assert_eq!(Some("OK".to_string()), deliveries[0].tx_ack_status);
+
+    let coverage = downlink::multicast::get_delivery_coverage(&deliveries)
+        .await
+        .unwrap();
+    assert_eq!(1, coverage.device_count);
+    assert_eq!(1, coverage.covered_device_count);
+
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    let events = integration::mock::get_multicast_group_delivery_events().await;
+    assert_eq!(1, events.len());
+    assert_eq!(mg.id.to_string(), events[0].multicast_group_id);
+    assert_eq!(f_cnt, events[0].f_cnt);
+    assert_eq!(1, events[0].covered_device_count);
+    assert_eq!(
+        vec![integration_pb::MulticastGroupGatewayDelivery {
+            gateway_id: gw.gateway_id.to_string(),
+            status: gw::TxAckStatus::Ok.into(),
+            device_count: 1,
+            discarded: false,
+        }],
+        events[0].gateways
+    );
+
+    // Discarded because the payload exceeds the max. payload size.
+    integration::mock::reset().await;
+    let f_cnt = downlink::multicast::enqueue(multicast::MulticastGroupQueueItem {
+        multicast_group_id: mg.id,
+        f_port: 5,
+        data: vec![0; 250],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    downlink::scheduler::schedule_multicast_group_queue_batch(1)
+        .await
+        .unwrap();
+
+    let deliveries = multicast::get_queue_item_deliveries(&mg.id, f_cnt)
+        .await
+        .unwrap();
+    assert_eq!(
+        Some(multicast::DELIVERY_STATUS_DISCARDED.to_string()),
+        deliveries[0].tx_ack_status
+    );
+
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    let events = integration::mock::get_multicast_group_delivery_events().await;
+    assert_eq!(1, events.len());
+    assert_eq!(f_cnt, events[0].f_cnt);
+    assert_eq!(0, events[0].covered_device_count);
+    assert!(events[0].gateways[0].discarded);
+
+    // Discarded because the tx ack timed out.
+    integration::mock::reset().await;
+    let f_cnt = downlink::multicast::enqueue(multicast::MulticastGroupQueueItem {
+        multicast_group_id: mg.id,
+        f_port: 5,
+        data: vec![1, 2, 3],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let items = multicast::discard_timed_out_queue_item_deliveries(
+        Utc::now() - chrono::Duration::hours(1),
+        10,
+    )
+    .await
+    .unwrap();
+    assert!(items.is_empty());
+
+    let items = multicast::discard_timed_out_queue_item_deliveries(
+        Utc::now() + chrono::Duration::hours(1),
+        10,
+    )
+    .await
+    .unwrap();
+    assert_eq!(1, items.len());
+    assert_eq!(f_cnt as i64, items[0][0].f_cnt);
+    assert!(multicast::get_queue(&mg.id).await.unwrap().is_empty());
+
+    downlink::multicast::send_delivery_event(&items[0])
+        .await
+        .unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    let events = integration::mock::get_multicast_group_delivery_events().await;
+    assert_eq!(1, events.len());
+    assert!(events[0].gateways[0].discarded);
+
+    // Retention cleanup.
+    multicast::delete_queue_item_deliveries_before(Utc::now() + chrono::Duration::hours(1))
+        .await
+        .unwrap();
+    assert!(multicast::get_queue_item_deliveries(&mg.id, f_cnt)
+        .await
+        .is_err());
+}
+
 async fn run_scheduler_test(t: &MulticastTest) {
     println!("> {}", t.name);
 
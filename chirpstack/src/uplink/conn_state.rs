@@ -0,0 +1,166 @@
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use tracing::{error, span, trace, warn, Instrument, Level};
+
+use crate::config;
+use crate::helpers::errors::PrintFullError;
+use crate::storage::{error::Error, gateway};
+use crate::stream;
+use chirpstack_api::{gw, stream as stream_pb};
+use lrwn::EUI64;
+
+pub struct ConnState {
+    gateway_id: EUI64,
+    conn_state: gw::ConnState,
+}
+
+impl ConnState {
+    pub async fn handle(s: gw::ConnState) {
+        let gateway_id = match if !s.gateway_id.is_empty() {
+            EUI64::from_str(&s.gateway_id).context("Gateway ID")
+        } else {
+            EUI64::from_slice(&s.gateway_id_legacy).context("Legacy Gateway ID")
+        } {
+            Ok(v) => v,
+            Err(e) => {
+                warn!(error = %e.full(), "Decode conn-state gateway_id error");
+                return;
+            }
+        };
+
+        let span = span!(Level::INFO, "conn_state", gateway_id = %gateway_id);
+
+        if let Err(e) = ConnState::_handle(gateway_id, s).instrument(span).await {
+            match e.downcast_ref::<Error>() {
+                Some(Error::NotFound(_)) => {
+                    let conf = config::get();
+                    if !conf.gateway.allow_unknown_gateways {
+                        error!(error = %e.full(), "Handle gateway conn-state error");
+                    }
+                }
+                Some(_) | None => {
+                    error!(error = %e.full(), "Handle gateway conn-state error");
+                }
+            }
+        }
+    }
+
+    async fn _handle(gateway_id: EUI64, s: gw::ConnState) -> Result<()> {
+        let ctx = ConnState {
+            gateway_id,
+            conn_state: s,
+        };
+
+        ctx.update_gateway_state().await?;
+
+        Ok(())
+    }
+
+    async fn update_gateway_state(&self) -> Result<()> {
+        trace!(state = ?self.conn_state.state(), "Update gateway connection state");
+
+        let is_online = self.conn_state.state() == gw::conn_state::State::Online;
+        let sc = match gateway::set_conn_state(self.gateway_id, is_online)
+            .await
+            .context("Set gateway connection state")?
+        {
+            Some(v) => v,
+            None => {
+                trace!("Gateway connection state is unchanged");
+                return Ok(());
+            }
+        };
+
+        stream::gateway::log_state_change(&stream_pb::GatewayStateChange {
+            gateway_id: self.gateway_id.to_string(),
+            time: Some(sc.created_at.into()),
+            state: self.conn_state.state,
+        })
+        .await
+        .context("Log gateway state change")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage::{get_async_redis_conn, redis_key};
+    use crate::test;
+    use prost::Message;
+    use redis::streams::StreamReadReply;
+    use std::io::Cursor;
+
+    #[tokio::test]
+    async fn test_conn_state() {
+        let _guard = test::prepare().await;
+        let gw =
+            gateway::test::create_gateway(EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8])).await;
+
+        ConnState::handle(gw::ConnState {
+            gateway_id: gw.gateway_id.to_string(),
+            state: gw::conn_state::State::Online.into(),
+            ..Default::default()
+        })
+        .await;
+
+        // duplicate (e.g. retained message)
+        ConnState::handle(gw::ConnState {
+            gateway_id: gw.gateway_id.to_string(),
+            state: gw::conn_state::State::Online.into(),
+            ..Default::default()
+        })
+        .await;
+
+        ConnState::handle(gw::ConnState {
+            gateway_id_legacy: gw.gateway_id.to_vec(),
+            state: gw::conn_state::State::Offline.into(),
+            ..Default::default()
+        })
+        .await;
+
+        let gw_get = gateway::get(&gw.gateway_id).await.unwrap();
+        assert_eq!(Some(false), gw_get.is_online);
+        assert_eq!(
+            2,
+            gateway::get_state_change_count(&gw.gateway_id)
+                .await
+                .unwrap()
+        );
+
+        let srr: StreamReadReply = redis::cmd("XREAD")
+            .arg("STREAMS")
+            .arg(redis_key("gw:stream:state".to_string()))
+            .arg("0")
+            .query_async(&mut get_async_redis_conn().await.unwrap())
+            .await
+            .unwrap();
+
+        let mut states: Vec<i32> = Vec::new();
+        for stream_key in &srr.keys {
+            for stream_id in &stream_key.ids {
+                for (k, v) in &stream_id.map {
+                    assert_eq!("state", k);
+                    if let redis::Value::BulkString(b) = v {
+                        let pl =
+                            stream_pb::GatewayStateChange::decode(&mut Cursor::new(b)).unwrap();
+                        assert_eq!(gw.gateway_id.to_string(), pl.gateway_id);
+                        states.push(pl.state);
+                    } else {
+                        panic!("Invalid payload");
+                    }
+                }
+            }
+        }
+
+        assert_eq!(
+            vec![
+                gw::conn_state::State::Online as i32,
+                gw::conn_state::State::Offline as i32
+            ],
+            states
+        );
+    }
+}
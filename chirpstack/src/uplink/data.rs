@@ -15,13 +15,13 @@ use crate::backend::roaming;
 use crate::helpers::errors::PrintFullError;
 use crate::storage::error::Error as StorageError;
 use crate::storage::{
-    application,
+    application, codec_context,
     device::{self, DeviceClass},
     device_gateway, device_profile, device_queue, fields,
     helpers::get_all_device_data,
     metrics, tenant,
 };
-use crate::{codec, config, downlink, integration, maccommand, region, stream};
+use crate::{applayer, codec, config, downlink, integration, maccommand, region, stream};
 use chirpstack_api::{common, integration as integration_pb, internal, stream as stream_pb};
 use lrwn::{AES128Key, EUI64};
 
@@ -145,6 +145,7 @@ impl Data {
         ctx.append_meta_data_to_uplink_history()?;
         ctx.send_uplink_event().await?;
         ctx.detect_and_save_measurements().await?;
+        ctx.handle_app_layer_payload().await?;
         ctx.sync_uplink_f_cnt()?;
         ctx.set_region_config_id()?;
         ctx.update_device().await?;
@@ -200,6 +201,7 @@ impl Data {
         ctx.append_meta_data_to_uplink_history_relayed()?;
         ctx.send_uplink_event().await?;
         ctx.detect_and_save_measurements().await?;
+        ctx.handle_app_layer_payload().await?;
         ctx.sync_uplink_f_cnt()?;
         ctx.set_region_config_id()?;
         ctx.update_device().await?;
@@ -961,40 +963,80 @@ impl Data {
         };
 
         if !self._is_end_to_end_encrypted() {
+            let mut codec_logs: Vec<(integration_pb::LogLevel, String)> = Vec::new();
+
+            // Only the JS codec supports a per-device context. A failure to load the context
+            // must not fail the uplink handling, in which case an empty context is used.
+            let codec_ctx = if dp.payload_codec_runtime == codec::Codec::JS {
+                match codec_context::get(&dev.dev_eui).await {
+                    Ok(v) => v,
+                    Err(e) => {
+                        warn!(dev_eui = %dev.dev_eui, error = %e.full(), "Get codec context error, using empty context");
+                        Default::default()
+                    }
+                }
+            } else {
+                Default::default()
+            };
+
             pl.object = match codec::binary_to_struct(
                 dp.payload_codec_runtime,
                 ts,
                 mac.f_port.unwrap_or(0),
                 &dev.variables,
+                &codec_ctx,
                 &dp.payload_codec_script,
                 &pl.data,
             )
             .await
             {
-                Ok(v) => v,
+                Ok(v) => {
+                    for w in v.warnings {
+                        codec_logs.push((integration_pb::LogLevel::Warning, w));
+                    }
+                    for e in v.errors {
+                        codec_logs.push((integration_pb::LogLevel::Error, e));
+                    }
+
+                    if let Some(ctx) = v.context {
+                        if !ctx.fields.is_empty() {
+                            if let Err(e) = codec_context::set(&dev.dev_eui, &ctx).await {
+                                codec_logs
+                                    .push((integration_pb::LogLevel::Warning, format!("{:#}", e)));
+                            }
+                        } else if !codec_ctx.fields.is_empty() {
+                            if let Err(e) = codec_context::delete(&dev.dev_eui).await {
+                                warn!(dev_eui = %dev.dev_eui, error = %e.full(), "Delete codec context error");
+                            }
+                        }
+                    }
+
+                    v.object
+                }
                 Err(e) => {
-                    integration::log_event(
-                        app.id,
-                        &dev.variables,
-                        &integration_pb::LogEvent {
-                            time: Some(Utc::now().into()),
-                            device_info: self.device_info.clone(),
-                            level: integration_pb::LogLevel::Error.into(),
-                            code: integration_pb::LogCode::UplinkCodec.into(),
-                            description: format!("{:#}", e),
-                            context: [(
-                                "deduplication_id".to_string(),
-                                pl.deduplication_id.clone(),
-                            )]
-                            .iter()
-                            .cloned()
-                            .collect(),
-                        },
-                    )
-                    .await;
+                    codec_logs.push((integration_pb::LogLevel::Error, format!("{:#}", e)));
                     None
                 }
             };
+
+            for (level, description) in codec_logs {
+                integration::log_event(
+                    app.id,
+                    &dev.variables,
+                    &integration_pb::LogEvent {
+                        time: Some(Utc::now().into()),
+                        device_info: self.device_info.clone(),
+                        level: level.into(),
+                        code: integration_pb::LogCode::UplinkCodec.into(),
+                        description,
+                        context: [("deduplication_id".to_string(), pl.deduplication_id.clone())]
+                            .iter()
+                            .cloned()
+                            .collect(),
+                    },
+                )
+                .await;
+            }
         }
 
         integration::uplink_event(app.id, &dev.variables, &pl).await;
@@ -1119,6 +1161,23 @@ impl Data {
         Ok(())
     }
 
+    async fn handle_app_layer_payload(&self) -> Result<()> {
+        if self._is_end_to_end_encrypted() {
+            return Ok(());
+        }
+
+        trace!("Handling application-layer payload");
+
+        let up_event = self.uplink_event.as_ref().unwrap();
+        let dev = self.device.as_ref().unwrap();
+        let dp = self.device_profile.as_ref().unwrap();
+        if let Err(e) = applayer::handle_uplink(dev, dp, up_event).await {
+            warn!(dev_eui = %dev.dev_eui, error = %e.full(), "Handle application-layer payload error");
+        }
+
+        Ok(())
+    }
+
     async fn handle_uplink_ack(&self) -> Result<()> {
         let mac = if let lrwn::Payload::MACPayload(pl) = &self.phy_payload.payload {
             pl
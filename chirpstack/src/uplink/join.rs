@@ -21,7 +21,7 @@ use crate::api::{backend::get_async_receiver, helpers::ToProto};
 use crate::backend::{joinserver, keywrap, roaming};
 use crate::helpers::errors::PrintFullError;
 use crate::storage::{
-    application,
+    application, codec_context,
     device::{self, DeviceClass},
     device_keys, device_profile, device_queue,
     error::Error as StorageError,
@@ -138,6 +138,7 @@ impl JoinRequest {
         ctx.log_uplink_meta().await?;
         ctx.set_device_session().await?;
         ctx.flush_device_queue().await?;
+        ctx.reset_codec_context().await?;
         ctx.set_device_mode().await?;
         ctx.update_device().await?;
         ctx.start_downlink_join_accept_flow().await?;
@@ -187,6 +188,7 @@ impl JoinRequest {
         }
         ctx.set_device_session().await?;
         ctx.flush_device_queue().await?;
+        ctx.reset_codec_context().await?;
         ctx.set_device_mode().await?;
         ctx.update_device().await?;
         ctx.start_downlink_join_accept_flow_relayed().await?;
@@ -858,6 +860,15 @@ impl JoinRequest {
         Ok(())
     }
 
+    async fn reset_codec_context(&self) -> Result<()> {
+        trace!("Resetting codec context");
+        let dev = self.device.as_ref().unwrap();
+        if let Err(e) = codec_context::delete(&dev.dev_eui).await {
+            warn!(dev_eui = %dev.dev_eui, error = %e.full(), "Reset codec context error");
+        }
+        Ok(())
+    }
+
     async fn set_device_mode(&mut self) -> Result<()> {
         let dp = self.device_profile.as_ref().unwrap();
         let d = self.device.as_mut().unwrap();
@@ -900,6 +911,7 @@ impl JoinRequest {
             &self.uplink_frame_set,
             self.tenant.as_ref().unwrap(),
             self.device.as_ref().unwrap(),
+            self.device_profile.as_ref().unwrap(),
             self.join_accept.as_ref().unwrap(),
         )
         .await?;
@@ -913,6 +925,7 @@ impl JoinRequest {
             &self.uplink_frame_set,
             self.tenant.as_ref().unwrap(),
             self.device.as_ref().unwrap(),
+            self.device_profile.as_ref().unwrap(),
             self.join_accept.as_ref().unwrap(),
         )
         .await?;
@@ -2,13 +2,14 @@ use std::sync::Arc;
 
 use anyhow::{Context, Result};
 use chrono::{DateTime, Local, Utc};
-use tracing::{span, trace, Instrument, Level};
+use tracing::{span, trace, warn, Instrument, Level};
 
 use super::{error::Error, helpers, UplinkFrameSet};
 use crate::api::helpers::ToProto;
 use crate::backend::{joinserver, keywrap, roaming};
+use crate::helpers::errors::PrintFullError;
 use crate::storage::{
-    application,
+    application, codec_context,
     device::{self, DeviceClass},
     device_keys, device_profile, device_queue,
     error::Error as StorageError,
@@ -99,6 +100,7 @@ impl JoinRequest {
         ctx.log_uplink_meta().await?;
         ctx.set_device_session().await?;
         ctx.flush_device_queue().await?;
+        ctx.reset_codec_context().await?;
         ctx.update_device().await?;
         ctx.send_join_event().await?;
         ctx.set_pr_start_ans_payload()?;
@@ -640,6 +642,15 @@ impl JoinRequest {
         Ok(())
     }
 
+    async fn reset_codec_context(&self) -> Result<()> {
+        trace!("Resetting codec context");
+        let dev = self.device.as_ref().unwrap();
+        if let Err(e) = codec_context::delete(&dev.dev_eui).await {
+            warn!(dev_eui = %dev.dev_eui, error = %e.full(), "Reset codec context error");
+        }
+        Ok(())
+    }
+
     async fn update_device(&mut self) -> Result<()> {
         trace!("Updating device");
         let dp = self.device_profile.as_ref().unwrap();
@@ -25,6 +25,7 @@ use chirpstack_api::{common, gw, stream as stream_pb};
 use lrwn::region::CommonName;
 use lrwn::{ForwardUplinkReq, MType, PhyPayload, EUI64};
 
+pub mod conn_state;
 mod data;
 mod data_fns;
 pub mod data_sns;
@@ -34,6 +35,7 @@ pub mod join;
 pub mod join_fns;
 pub mod join_sns;
 pub mod mesh;
+pub mod raw;
 pub mod stats;
 
 #[derive(Clone, Hash, PartialEq, Eq, EncodeLabelSet, Debug)]
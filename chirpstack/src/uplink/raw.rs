@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use tracing::{error, span, trace, warn, Instrument, Level};
+
+use crate::helpers::errors::PrintFullError;
+use crate::storage::{error::Error, gateway};
+use crate::{config, integration, stream};
+use chirpstack_api::{gw, integration as integration_pb, stream as stream_pb};
+use lrwn::EUI64;
+
+pub struct RawPacketForwarderEvent {
+    gateway_id: EUI64,
+    event: gw::RawPacketForwarderEvent,
+    gateway: Option<gateway::Gateway>,
+}
+
+impl RawPacketForwarderEvent {
+    pub async fn handle(e: gw::RawPacketForwarderEvent) {
+        let gateway_id = match if !e.gateway_id.is_empty() {
+            EUI64::from_str(&e.gateway_id).context("Gateway ID")
+        } else {
+            EUI64::from_slice(&e.gateway_id_legacy).context("Legacy Gateway ID")
+        } {
+            Ok(v) => v,
+            Err(e) => {
+                warn!(error = %e.full(), "Decode raw packet-forwarder event gateway_id error");
+                return;
+            }
+        };
+
+        let span = span!(Level::INFO, "raw", gateway_id = %gateway_id);
+
+        if let Err(e) = RawPacketForwarderEvent::_handle(gateway_id, e)
+            .instrument(span)
+            .await
+        {
+            match e.downcast_ref::<Error>() {
+                Some(Error::NotFound(_)) => {
+                    let conf = config::get();
+                    if !conf.gateway.allow_unknown_gateways {
+                        error!(error = %e.full(), "Handle raw packet-forwarder event error");
+                    }
+                }
+                Some(_) | None => {
+                    error!(error = %e.full(), "Handle raw packet-forwarder event error");
+                }
+            }
+        }
+    }
+
+    async fn _handle(gateway_id: EUI64, e: gw::RawPacketForwarderEvent) -> Result<()> {
+        let mut ctx = RawPacketForwarderEvent {
+            gateway_id,
+            event: e,
+            gateway: None,
+        };
+
+        ctx.get_gateway().await?;
+        ctx.log_event().await?;
+        ctx.send_integration_event().await?;
+
+        Ok(())
+    }
+
+    async fn get_gateway(&mut self) -> Result<()> {
+        trace!("Get gateway");
+        self.gateway = Some(gateway::get(&self.gateway_id).await?);
+        Ok(())
+    }
+
+    async fn log_event(&self) -> Result<()> {
+        trace!("Log raw packet-forwarder event");
+
+        stream::frame::log_raw_packet_forwarder_event_for_gateway(
+            &stream_pb::RawPacketForwarderEventLog {
+                gateway_id: self.gateway_id.to_string(),
+                time: Some(Utc::now().into()),
+                payload: self.event.payload.clone(),
+            },
+        )
+        .await
+        .context("Log raw packet-forwarder event")?;
+
+        Ok(())
+    }
+
+    async fn send_integration_event(&self) -> Result<()> {
+        trace!("Send raw packet-forwarder integration event");
+
+        let gw = self.gateway.as_ref().unwrap();
+
+        integration::raw_packet_forwarder_event(
+            &HashMap::new(),
+            &integration_pb::RawPacketForwarderEvent {
+                gateway_id: self.gateway_id.to_string(),
+                time: Some(Utc::now().into()),
+                tenant_id: gw.tenant_id.to_string(),
+                payload: self.event.payload.clone(),
+            },
+        )
+        .await;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage::{get_async_redis_conn, redis_key};
+    use crate::test;
+    use prost::Message;
+    use redis::streams::StreamReadReply;
+    use std::io::Cursor;
+    use std::time::Duration;
+    use tokio::time::sleep;
+
+    #[tokio::test]
+    async fn test_raw_packet_forwarder_event() {
+        let _guard = test::prepare().await;
+        integration::set_mock().await;
+        integration::mock::reset().await;
+
+        let gw =
+            gateway::test::create_gateway(EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8])).await;
+
+        RawPacketForwarderEvent::handle(gw::RawPacketForwarderEvent {
+            gateway_id: gw.gateway_id.to_string(),
+            payload: vec![1, 2, 3],
+            ..Default::default()
+        })
+        .await;
+
+        let srr: StreamReadReply = redis::cmd("XREAD")
+            .arg("STREAMS")
+            .arg(redis_key(format!("gw:{{{}}}:stream:frame", gw.gateway_id)))
+            .arg("0")
+            .query_async(&mut get_async_redis_conn().await.unwrap())
+            .await
+            .unwrap();
+
+        let mut payloads: Vec<Vec<u8>> = Vec::new();
+        for stream_key in &srr.keys {
+            for stream_id in &stream_key.ids {
+                for (k, v) in &stream_id.map {
+                    assert_eq!("raw", k);
+                    if let redis::Value::BulkString(b) = v {
+                        let pl = stream_pb::RawPacketForwarderEventLog::decode(&mut Cursor::new(b))
+                            .unwrap();
+                        assert_eq!(gw.gateway_id.to_string(), pl.gateway_id);
+                        payloads.push(pl.payload);
+                    } else {
+                        panic!("Invalid payload");
+                    }
+                }
+            }
+        }
+        assert_eq!(vec![vec![1, 2, 3]], payloads);
+
+        // the integration event is sent async
+        sleep(Duration::from_millis(100)).await;
+        let events = integration::mock::get_raw_packet_forwarder_events().await;
+        assert_eq!(1, events.len());
+        assert_eq!(gw.gateway_id.to_string(), events[0].gateway_id);
+        assert_eq!(gw.tenant_id.to_string(), events[0].tenant_id);
+        assert_eq!(vec![1, 2, 3], events[0].payload);
+    }
+}
@@ -0,0 +1,372 @@
+//! Application Layer Clock Synchronization (TS003) v1.0.0.
+use anyhow::Result;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Cid {
+    PackageVersionReq,
+    PackageVersionAns,
+    AppTimeReq,
+    AppTimeAns,
+    DeviceAppTimePeriodicityReq,
+    DeviceAppTimePeriodicityAns,
+    ForceDeviceResyncReq,
+}
+
+impl Cid {
+    pub fn from_u8(uplink: bool, v: u8) -> Result<Self> {
+        Ok(match (uplink, v) {
+            (false, 0x00) => Cid::PackageVersionReq,
+            (true, 0x00) => Cid::PackageVersionAns,
+            (true, 0x01) => Cid::AppTimeReq,
+            (false, 0x01) => Cid::AppTimeAns,
+            (false, 0x02) => Cid::DeviceAppTimePeriodicityReq,
+            (true, 0x02) => Cid::DeviceAppTimePeriodicityAns,
+            (false, 0x03) => Cid::ForceDeviceResyncReq,
+            _ => return Err(anyhow!("Invalid CID: {}", v)),
+        })
+    }
+
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            Cid::PackageVersionReq | Cid::PackageVersionAns => 0x00,
+            Cid::AppTimeReq | Cid::AppTimeAns => 0x01,
+            Cid::DeviceAppTimePeriodicityReq | Cid::DeviceAppTimePeriodicityAns => 0x02,
+            Cid::ForceDeviceResyncReq => 0x03,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Payload {
+    PackageVersionReq,
+    PackageVersionAns(PackageVersionAnsPayload),
+    AppTimeReq(AppTimeReqPayload),
+    AppTimeAns(AppTimeAnsPayload),
+    DeviceAppTimePeriodicityReq(DeviceAppTimePeriodicityReqPayload),
+    DeviceAppTimePeriodicityAns(DeviceAppTimePeriodicityAnsPayload),
+    ForceDeviceResyncReq(ForceDeviceResyncReqPayload),
+}
+
+impl Payload {
+    pub fn cid(&self) -> Cid {
+        match self {
+            Payload::PackageVersionReq => Cid::PackageVersionReq,
+            Payload::PackageVersionAns(_) => Cid::PackageVersionAns,
+            Payload::AppTimeReq(_) => Cid::AppTimeReq,
+            Payload::AppTimeAns(_) => Cid::AppTimeAns,
+            Payload::DeviceAppTimePeriodicityReq(_) => Cid::DeviceAppTimePeriodicityReq,
+            Payload::DeviceAppTimePeriodicityAns(_) => Cid::DeviceAppTimePeriodicityAns,
+            Payload::ForceDeviceResyncReq(_) => Cid::ForceDeviceResyncReq,
+        }
+    }
+
+    pub fn from_slice(uplink: bool, b: &[u8]) -> Result<Self> {
+        if b.is_empty() {
+            return Err(anyhow!("at least 1 byte is expected"));
+        }
+
+        let cid = Cid::from_u8(uplink, b[0])?;
+        let b = &b[1..];
+
+        Ok(match cid {
+            Cid::PackageVersionReq => Payload::PackageVersionReq,
+            Cid::PackageVersionAns => {
+                Payload::PackageVersionAns(PackageVersionAnsPayload::from_slice(b)?)
+            }
+            Cid::AppTimeReq => Payload::AppTimeReq(AppTimeReqPayload::from_slice(b)?),
+            Cid::AppTimeAns => Payload::AppTimeAns(AppTimeAnsPayload::from_slice(b)?),
+            Cid::DeviceAppTimePeriodicityReq => Payload::DeviceAppTimePeriodicityReq(
+                DeviceAppTimePeriodicityReqPayload::from_slice(b)?,
+            ),
+            Cid::DeviceAppTimePeriodicityAns => Payload::DeviceAppTimePeriodicityAns(
+                DeviceAppTimePeriodicityAnsPayload::from_slice(b)?,
+            ),
+            Cid::ForceDeviceResyncReq => {
+                Payload::ForceDeviceResyncReq(ForceDeviceResyncReqPayload::from_slice(b)?)
+            }
+        })
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        let mut out = vec![self.cid().to_u8()];
+
+        match self {
+            Payload::PackageVersionReq => {}
+            Payload::PackageVersionAns(pl) => out.extend_from_slice(&pl.to_bytes()),
+            Payload::AppTimeReq(pl) => out.extend_from_slice(&pl.to_bytes()?),
+            Payload::AppTimeAns(pl) => out.extend_from_slice(&pl.to_bytes()?),
+            Payload::DeviceAppTimePeriodicityReq(pl) => out.extend_from_slice(&pl.to_bytes()?),
+            Payload::DeviceAppTimePeriodicityAns(pl) => out.extend_from_slice(&pl.to_bytes()),
+            Payload::ForceDeviceResyncReq(pl) => out.extend_from_slice(&pl.to_bytes()?),
+        }
+
+        Ok(out)
+    }
+}
+
+fn check_len(b: &[u8], n: usize) -> Result<()> {
+    if b.len() != n {
+        return Err(anyhow!("{} bytes are expected", n));
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PackageVersionAnsPayload {
+    pub package_identifier: u8,
+    pub package_version: u8,
+}
+
+impl PackageVersionAnsPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 2)?;
+        Ok(PackageVersionAnsPayload {
+            package_identifier: b[0],
+            package_version: b[1],
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 2] {
+        [self.package_identifier, self.package_version]
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct AppTimeReqPayload {
+    /// Device time, in seconds since GPS epoch (modulo 2^32).
+    pub device_time: u32,
+    pub ans_required: bool,
+    pub token_req: u8,
+}
+
+impl AppTimeReqPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 5)?;
+        Ok(AppTimeReqPayload {
+            device_time: u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            ans_required: b[4] & 0x10 != 0,
+            token_req: b[4] & 0x0f,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; 5]> {
+        if self.token_req > 15 {
+            return Err(anyhow!("max token_req value is 15"));
+        }
+
+        let device_time = self.device_time.to_le_bytes();
+        Ok([
+            device_time[0],
+            device_time[1],
+            device_time[2],
+            device_time[3],
+            (self.ans_required as u8) << 4 | self.token_req,
+        ])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct AppTimeAnsPayload {
+    /// Correction (in seconds) which must be applied to the device clock.
+    pub time_correction: i32,
+    pub token_ans: u8,
+}
+
+impl AppTimeAnsPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 5)?;
+        Ok(AppTimeAnsPayload {
+            time_correction: i32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            token_ans: b[4] & 0x0f,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; 5]> {
+        if self.token_ans > 15 {
+            return Err(anyhow!("max token_ans value is 15"));
+        }
+
+        let time_correction = self.time_correction.to_le_bytes();
+        Ok([
+            time_correction[0],
+            time_correction[1],
+            time_correction[2],
+            time_correction[3],
+            self.token_ans,
+        ])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DeviceAppTimePeriodicityReqPayload {
+    /// The device sends an AppTimeReq every 128 * 2^periodicity seconds.
+    pub periodicity: u8,
+}
+
+impl DeviceAppTimePeriodicityReqPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 1)?;
+        Ok(DeviceAppTimePeriodicityReqPayload {
+            periodicity: b[0] & 0x0f,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; 1]> {
+        if self.periodicity > 15 {
+            return Err(anyhow!("max periodicity value is 15"));
+        }
+
+        Ok([self.periodicity])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DeviceAppTimePeriodicityAnsPayload {
+    pub not_supported: bool,
+    /// Device time, in seconds since GPS epoch (modulo 2^32).
+    pub time: u32,
+}
+
+impl DeviceAppTimePeriodicityAnsPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 5)?;
+        Ok(DeviceAppTimePeriodicityAnsPayload {
+            not_supported: b[0] & 0x01 != 0,
+            time: u32::from_le_bytes([b[1], b[2], b[3], b[4]]),
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 5] {
+        let time = self.time.to_le_bytes();
+        [self.not_supported as u8, time[0], time[1], time[2], time[3]]
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ForceDeviceResyncReqPayload {
+    /// Number of AppTimeReq transmissions the device must send to resync its clock.
+    pub nb_transmissions: u8,
+}
+
+impl ForceDeviceResyncReqPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 1)?;
+        Ok(ForceDeviceResyncReqPayload {
+            nb_transmissions: b[0] & 0x07,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; 1]> {
+        if self.nb_transmissions > 7 {
+            return Err(anyhow!("max nb_transmissions value is 7"));
+        }
+
+        Ok([self.nb_transmissions])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct PayloadTest {
+        uplink: bool,
+        pl: Payload,
+        bytes: Vec<u8>,
+    }
+
+    #[test]
+    fn test_payloads() {
+        let tests = vec![
+            PayloadTest {
+                uplink: false,
+                pl: Payload::PackageVersionReq,
+                bytes: vec![0x00],
+            },
+            PayloadTest {
+                uplink: true,
+                pl: Payload::PackageVersionAns(PackageVersionAnsPayload {
+                    package_identifier: 1,
+                    package_version: 1,
+                }),
+                bytes: vec![0x00, 0x01, 0x01],
+            },
+            PayloadTest {
+                uplink: true,
+                pl: Payload::AppTimeReq(AppTimeReqPayload {
+                    device_time: 1_000_000,
+                    ans_required: true,
+                    token_req: 3,
+                }),
+                bytes: vec![0x01, 0x40, 0x42, 0x0f, 0x00, 0x13],
+            },
+            PayloadTest {
+                uplink: false,
+                pl: Payload::AppTimeAns(AppTimeAnsPayload {
+                    time_correction: -2,
+                    token_ans: 3,
+                }),
+                bytes: vec![0x01, 0xfe, 0xff, 0xff, 0xff, 0x03],
+            },
+            PayloadTest {
+                uplink: false,
+                pl: Payload::DeviceAppTimePeriodicityReq(DeviceAppTimePeriodicityReqPayload {
+                    periodicity: 9,
+                }),
+                bytes: vec![0x02, 0x09],
+            },
+            PayloadTest {
+                uplink: true,
+                pl: Payload::DeviceAppTimePeriodicityAns(DeviceAppTimePeriodicityAnsPayload {
+                    not_supported: true,
+                    time: 1_000_000,
+                }),
+                bytes: vec![0x02, 0x01, 0x40, 0x42, 0x0f, 0x00],
+            },
+            PayloadTest {
+                uplink: false,
+                pl: Payload::ForceDeviceResyncReq(ForceDeviceResyncReqPayload {
+                    nb_transmissions: 3,
+                }),
+                bytes: vec![0x03, 0x03],
+            },
+        ];
+
+        for tst in tests {
+            assert_eq!(tst.bytes, tst.pl.to_vec().unwrap());
+            assert_eq!(tst.pl, Payload::from_slice(tst.uplink, &tst.bytes).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_payload_errors() {
+        assert!(Payload::from_slice(true, &[]).is_err());
+        assert!(Payload::from_slice(true, &[0x01, 0x00]).is_err());
+        assert!(Payload::AppTimeAns(AppTimeAnsPayload {
+            time_correction: 0,
+            token_ans: 16,
+        })
+        .to_vec()
+        .is_err());
+        assert!(Payload::ForceDeviceResyncReq(ForceDeviceResyncReqPayload {
+            nb_transmissions: 8,
+        })
+        .to_vec()
+        .is_err());
+        assert!(
+            Payload::DeviceAppTimePeriodicityReq(DeviceAppTimePeriodicityReqPayload {
+                periodicity: 16,
+            })
+            .to_vec()
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,633 @@
+//! Fragmented Data Block Transport (TS004) v1.0.0.
+use anyhow::Result;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Cid {
+    PackageVersionReq,
+    PackageVersionAns,
+    FragSessionStatusReq,
+    FragSessionStatusAns,
+    FragSessionSetupReq,
+    FragSessionSetupAns,
+    FragSessionDeleteReq,
+    FragSessionDeleteAns,
+    DataFragment,
+}
+
+impl Cid {
+    pub fn from_u8(uplink: bool, v: u8) -> Result<Self> {
+        Ok(match (uplink, v) {
+            (false, 0x00) => Cid::PackageVersionReq,
+            (true, 0x00) => Cid::PackageVersionAns,
+            (false, 0x01) => Cid::FragSessionStatusReq,
+            (true, 0x01) => Cid::FragSessionStatusAns,
+            (false, 0x02) => Cid::FragSessionSetupReq,
+            (true, 0x02) => Cid::FragSessionSetupAns,
+            (false, 0x03) => Cid::FragSessionDeleteReq,
+            (true, 0x03) => Cid::FragSessionDeleteAns,
+            (false, 0x08) => Cid::DataFragment,
+            _ => return Err(anyhow!("Invalid CID: {}", v)),
+        })
+    }
+
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            Cid::PackageVersionReq | Cid::PackageVersionAns => 0x00,
+            Cid::FragSessionStatusReq | Cid::FragSessionStatusAns => 0x01,
+            Cid::FragSessionSetupReq | Cid::FragSessionSetupAns => 0x02,
+            Cid::FragSessionDeleteReq | Cid::FragSessionDeleteAns => 0x03,
+            Cid::DataFragment => 0x08,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Payload {
+    PackageVersionReq,
+    PackageVersionAns(PackageVersionAnsPayload),
+    FragSessionStatusReq(FragSessionStatusReqPayload),
+    FragSessionStatusAns(FragSessionStatusAnsPayload),
+    FragSessionSetupReq(FragSessionSetupReqPayload),
+    FragSessionSetupAns(FragSessionSetupAnsPayload),
+    FragSessionDeleteReq(FragSessionDeleteReqPayload),
+    FragSessionDeleteAns(FragSessionDeleteAnsPayload),
+    DataFragment(DataFragmentPayload),
+}
+
+impl Payload {
+    pub fn cid(&self) -> Cid {
+        match self {
+            Payload::PackageVersionReq => Cid::PackageVersionReq,
+            Payload::PackageVersionAns(_) => Cid::PackageVersionAns,
+            Payload::FragSessionStatusReq(_) => Cid::FragSessionStatusReq,
+            Payload::FragSessionStatusAns(_) => Cid::FragSessionStatusAns,
+            Payload::FragSessionSetupReq(_) => Cid::FragSessionSetupReq,
+            Payload::FragSessionSetupAns(_) => Cid::FragSessionSetupAns,
+            Payload::FragSessionDeleteReq(_) => Cid::FragSessionDeleteReq,
+            Payload::FragSessionDeleteAns(_) => Cid::FragSessionDeleteAns,
+            Payload::DataFragment(_) => Cid::DataFragment,
+        }
+    }
+
+    pub fn from_slice(uplink: bool, b: &[u8]) -> Result<Self> {
+        if b.is_empty() {
+            return Err(anyhow!("at least 1 byte is expected"));
+        }
+
+        let cid = Cid::from_u8(uplink, b[0])?;
+        let b = &b[1..];
+
+        Ok(match cid {
+            Cid::PackageVersionReq => Payload::PackageVersionReq,
+            Cid::PackageVersionAns => {
+                Payload::PackageVersionAns(PackageVersionAnsPayload::from_slice(b)?)
+            }
+            Cid::FragSessionStatusReq => {
+                Payload::FragSessionStatusReq(FragSessionStatusReqPayload::from_slice(b)?)
+            }
+            Cid::FragSessionStatusAns => {
+                Payload::FragSessionStatusAns(FragSessionStatusAnsPayload::from_slice(b)?)
+            }
+            Cid::FragSessionSetupReq => {
+                Payload::FragSessionSetupReq(FragSessionSetupReqPayload::from_slice(b)?)
+            }
+            Cid::FragSessionSetupAns => {
+                Payload::FragSessionSetupAns(FragSessionSetupAnsPayload::from_slice(b)?)
+            }
+            Cid::FragSessionDeleteReq => {
+                Payload::FragSessionDeleteReq(FragSessionDeleteReqPayload::from_slice(b)?)
+            }
+            Cid::FragSessionDeleteAns => {
+                Payload::FragSessionDeleteAns(FragSessionDeleteAnsPayload::from_slice(b)?)
+            }
+            Cid::DataFragment => Payload::DataFragment(DataFragmentPayload::from_slice(b)?),
+        })
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        let mut out = vec![self.cid().to_u8()];
+
+        match self {
+            Payload::PackageVersionReq => {}
+            Payload::PackageVersionAns(pl) => out.extend_from_slice(&pl.to_bytes()),
+            Payload::FragSessionStatusReq(pl) => out.extend_from_slice(&pl.to_bytes()?),
+            Payload::FragSessionStatusAns(pl) => out.extend_from_slice(&pl.to_bytes()?),
+            Payload::FragSessionSetupReq(pl) => out.extend_from_slice(&pl.to_bytes()?),
+            Payload::FragSessionSetupAns(pl) => out.extend_from_slice(&pl.to_bytes()?),
+            Payload::FragSessionDeleteReq(pl) => out.extend_from_slice(&pl.to_bytes()?),
+            Payload::FragSessionDeleteAns(pl) => out.extend_from_slice(&pl.to_bytes()?),
+            Payload::DataFragment(pl) => out.extend_from_slice(&pl.to_vec()?),
+        }
+
+        Ok(out)
+    }
+}
+
+fn check_len(b: &[u8], n: usize) -> Result<()> {
+    if b.len() != n {
+        return Err(anyhow!("{} bytes are expected", n));
+    }
+    Ok(())
+}
+
+fn check_frag_index(frag_index: u8) -> Result<()> {
+    if frag_index > 3 {
+        return Err(anyhow!("max frag_index value is 3"));
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PackageVersionAnsPayload {
+    pub package_identifier: u8,
+    pub package_version: u8,
+}
+
+impl PackageVersionAnsPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 2)?;
+        Ok(PackageVersionAnsPayload {
+            package_identifier: b[0],
+            package_version: b[1],
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 2] {
+        [self.package_identifier, self.package_version]
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FragSessionStatusReqPayload {
+    pub frag_index: u8,
+    pub participants: bool,
+}
+
+impl FragSessionStatusReqPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 1)?;
+        Ok(FragSessionStatusReqPayload {
+            frag_index: (b[0] >> 1) & 0x03,
+            participants: b[0] & 0x01 != 0,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; 1]> {
+        check_frag_index(self.frag_index)?;
+        Ok([self.frag_index << 1 | self.participants as u8])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FragSessionStatusAnsPayload {
+    pub frag_index: u8,
+    pub nb_frag_received: u16,
+    pub missing_frag: u8,
+    pub not_enough_matrix_memory: bool,
+}
+
+impl FragSessionStatusAnsPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 4)?;
+        let received_and_index = u16::from_le_bytes([b[0], b[1]]);
+
+        Ok(FragSessionStatusAnsPayload {
+            frag_index: (received_and_index >> 14) as u8,
+            nb_frag_received: received_and_index & 0x3fff,
+            missing_frag: b[2],
+            not_enough_matrix_memory: b[3] & 0x01 != 0,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; 4]> {
+        check_frag_index(self.frag_index)?;
+        if self.nb_frag_received > 0x3fff {
+            return Err(anyhow!("max nb_frag_received value is 16383"));
+        }
+
+        let received_and_index = (self.frag_index as u16) << 14 | self.nb_frag_received;
+        let b = received_and_index.to_le_bytes();
+
+        Ok([
+            b[0],
+            b[1],
+            self.missing_frag,
+            self.not_enough_matrix_memory as u8,
+        ])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FragSessionSetupReqPayload {
+    pub frag_index: u8,
+    pub mc_group_bit_mask: [bool; 4],
+    pub nb_frag: u16,
+    pub frag_size: u8,
+    pub fragmentation_matrix: u8,
+    pub block_ack_delay: u8,
+    pub padding: u8,
+    pub descriptor: [u8; 4],
+}
+
+impl FragSessionSetupReqPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 10)?;
+
+        Ok(FragSessionSetupReqPayload {
+            frag_index: (b[0] >> 4) & 0x03,
+            mc_group_bit_mask: [
+                b[0] & 0x01 != 0,
+                b[0] & 0x02 != 0,
+                b[0] & 0x04 != 0,
+                b[0] & 0x08 != 0,
+            ],
+            nb_frag: u16::from_le_bytes([b[1], b[2]]),
+            frag_size: b[3],
+            fragmentation_matrix: (b[4] >> 3) & 0x07,
+            block_ack_delay: b[4] & 0x07,
+            padding: b[5],
+            descriptor: [b[6], b[7], b[8], b[9]],
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; 10]> {
+        check_frag_index(self.frag_index)?;
+        if self.fragmentation_matrix > 7 {
+            return Err(anyhow!("max fragmentation_matrix value is 7"));
+        }
+        if self.block_ack_delay > 7 {
+            return Err(anyhow!("max block_ack_delay value is 7"));
+        }
+
+        let mut frag_session = self.frag_index << 4;
+        for (i, v) in self.mc_group_bit_mask.iter().enumerate() {
+            if *v {
+                frag_session |= 1 << i;
+            }
+        }
+        let nb_frag = self.nb_frag.to_le_bytes();
+
+        Ok([
+            frag_session,
+            nb_frag[0],
+            nb_frag[1],
+            self.frag_size,
+            self.fragmentation_matrix << 3 | self.block_ack_delay,
+            self.padding,
+            self.descriptor[0],
+            self.descriptor[1],
+            self.descriptor[2],
+            self.descriptor[3],
+        ])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FragSessionSetupAnsPayload {
+    pub frag_index: u8,
+    pub wrong_descriptor: bool,
+    pub frag_session_index_not_supported: bool,
+    pub not_enough_memory: bool,
+    pub encoding_unsupported: bool,
+}
+
+impl FragSessionSetupAnsPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 1)?;
+
+        Ok(FragSessionSetupAnsPayload {
+            frag_index: b[0] >> 6,
+            wrong_descriptor: b[0] & 0x08 != 0,
+            frag_session_index_not_supported: b[0] & 0x04 != 0,
+            not_enough_memory: b[0] & 0x02 != 0,
+            encoding_unsupported: b[0] & 0x01 != 0,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; 1]> {
+        check_frag_index(self.frag_index)?;
+
+        Ok([self.frag_index << 6
+            | (self.wrong_descriptor as u8) << 3
+            | (self.frag_session_index_not_supported as u8) << 2
+            | (self.not_enough_memory as u8) << 1
+            | self.encoding_unsupported as u8])
+    }
+
+    /// Returns true when the device reported an error.
+    pub fn is_error(&self) -> bool {
+        self.wrong_descriptor
+            || self.frag_session_index_not_supported
+            || self.not_enough_memory
+            || self.encoding_unsupported
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FragSessionDeleteReqPayload {
+    pub frag_index: u8,
+}
+
+impl FragSessionDeleteReqPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 1)?;
+        Ok(FragSessionDeleteReqPayload {
+            frag_index: b[0] & 0x03,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; 1]> {
+        check_frag_index(self.frag_index)?;
+        Ok([self.frag_index])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FragSessionDeleteAnsPayload {
+    pub frag_index: u8,
+    pub session_does_not_exist: bool,
+}
+
+impl FragSessionDeleteAnsPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 1)?;
+        Ok(FragSessionDeleteAnsPayload {
+            frag_index: b[0] & 0x03,
+            session_does_not_exist: b[0] & 0x04 != 0,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; 1]> {
+        check_frag_index(self.frag_index)?;
+        Ok([self.frag_index | (self.session_does_not_exist as u8) << 2])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DataFragmentPayload {
+    pub frag_index: u8,
+    /// Fragment number (starting at 1).
+    pub n: u16,
+    pub data: Vec<u8>,
+}
+
+impl DataFragmentPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        if b.len() < 2 {
+            return Err(anyhow!("at least 2 bytes are expected"));
+        }
+        let index_and_n = u16::from_le_bytes([b[0], b[1]]);
+
+        Ok(DataFragmentPayload {
+            frag_index: (index_and_n >> 14) as u8,
+            n: index_and_n & 0x3fff,
+            data: b[2..].to_vec(),
+        })
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        check_frag_index(self.frag_index)?;
+        if self.n > 0x3fff {
+            return Err(anyhow!("max n value is 16383"));
+        }
+
+        let index_and_n = (self.frag_index as u16) << 14 | self.n;
+        let mut b = index_and_n.to_le_bytes().to_vec();
+        b.extend_from_slice(&self.data);
+        Ok(b)
+    }
+}
+
+/// Splits the data into fragments of the given size and appends the given number of redundancy
+/// fragments, using the parity check matrix as described by the specification. The length of
+/// the data must be a multiple of the fragment size (see padding).
+pub fn encode(data: &[u8], fragment_size: usize, redundancy: usize) -> Result<Vec<Vec<u8>>> {
+    if fragment_size == 0 {
+        return Err(anyhow!("fragment_size must be greater than 0"));
+    }
+    if data.len() % fragment_size != 0 {
+        return Err(anyhow!(
+            "length of data must be a multiple of fragment_size"
+        ));
+    }
+
+    let mut out: Vec<Vec<u8>> = data.chunks(fragment_size).map(|c| c.to_vec()).collect();
+    let m = out.len();
+
+    for n in 1..=redundancy {
+        let mut fragment = vec![0; fragment_size];
+        for (i, v) in matrix_line(n, m).iter().enumerate() {
+            if *v {
+                for (j, b) in out[i].iter().enumerate() {
+                    fragment[j] ^= b;
+                }
+            }
+        }
+        out.push(fragment);
+    }
+
+    Ok(out)
+}
+
+/// Returns the padding which must be appended to the data, to make the length of the data a
+/// multiple of the fragment size.
+pub fn get_padding(data_len: usize, fragment_size: usize) -> usize {
+    match data_len % fragment_size {
+        0 => 0,
+        v => fragment_size - v,
+    }
+}
+
+// Pseudo-random binary sequence generator (23 bit).
+fn prbs23(x: usize) -> usize {
+    let b0 = x & 1;
+    let b1 = (x & 32) >> 5;
+    (x >> 1) + ((b0 ^ b1) << 22)
+}
+
+// Returns the line n (starting at 1) of the parity check matrix for m uncoded fragments.
+fn matrix_line(n: usize, m: usize) -> Vec<bool> {
+    let mut line = vec![false; m];
+    let mm = if m.is_power_of_two() { 1 } else { 0 };
+
+    let mut x = 1 + 1001 * n;
+    for _ in 0..(m / 2) {
+        let mut r = 1 << 16;
+        while r >= m {
+            x = prbs23(x);
+            r = x % (m + mm);
+        }
+        line[r] = true;
+    }
+
+    line
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct PayloadTest {
+        uplink: bool,
+        pl: Payload,
+        bytes: Vec<u8>,
+    }
+
+    #[test]
+    fn test_payloads() {
+        let tests = vec![
+            PayloadTest {
+                uplink: false,
+                pl: Payload::PackageVersionReq,
+                bytes: vec![0x00],
+            },
+            PayloadTest {
+                uplink: true,
+                pl: Payload::PackageVersionAns(PackageVersionAnsPayload {
+                    package_identifier: 3,
+                    package_version: 1,
+                }),
+                bytes: vec![0x00, 0x03, 0x01],
+            },
+            PayloadTest {
+                uplink: false,
+                pl: Payload::FragSessionStatusReq(FragSessionStatusReqPayload {
+                    frag_index: 2,
+                    participants: true,
+                }),
+                bytes: vec![0x01, 0x05],
+            },
+            PayloadTest {
+                uplink: true,
+                pl: Payload::FragSessionStatusAns(FragSessionStatusAnsPayload {
+                    frag_index: 1,
+                    nb_frag_received: 10,
+                    missing_frag: 2,
+                    not_enough_matrix_memory: true,
+                }),
+                bytes: vec![0x01, 0x0a, 0x40, 0x02, 0x01],
+            },
+            PayloadTest {
+                uplink: false,
+                pl: Payload::FragSessionSetupReq(FragSessionSetupReqPayload {
+                    frag_index: 1,
+                    mc_group_bit_mask: [true, false, false, false],
+                    nb_frag: 258,
+                    frag_size: 50,
+                    fragmentation_matrix: 0,
+                    block_ack_delay: 1,
+                    padding: 10,
+                    descriptor: [0x01, 0x02, 0x03, 0x04],
+                }),
+                bytes: vec![
+                    0x02, 0x11, 0x02, 0x01, 0x32, 0x01, 0x0a, 0x01, 0x02, 0x03, 0x04,
+                ],
+            },
+            PayloadTest {
+                uplink: true,
+                pl: Payload::FragSessionSetupAns(FragSessionSetupAnsPayload {
+                    frag_index: 3,
+                    wrong_descriptor: true,
+                    frag_session_index_not_supported: false,
+                    not_enough_memory: true,
+                    encoding_unsupported: false,
+                }),
+                bytes: vec![0x02, 0xca],
+            },
+            PayloadTest {
+                uplink: false,
+                pl: Payload::FragSessionDeleteReq(FragSessionDeleteReqPayload { frag_index: 3 }),
+                bytes: vec![0x03, 0x03],
+            },
+            PayloadTest {
+                uplink: true,
+                pl: Payload::FragSessionDeleteAns(FragSessionDeleteAnsPayload {
+                    frag_index: 1,
+                    session_does_not_exist: true,
+                }),
+                bytes: vec![0x03, 0x05],
+            },
+            PayloadTest {
+                uplink: false,
+                pl: Payload::DataFragment(DataFragmentPayload {
+                    frag_index: 1,
+                    n: 2,
+                    data: vec![0x01, 0x02, 0x03],
+                }),
+                bytes: vec![0x08, 0x02, 0x40, 0x01, 0x02, 0x03],
+            },
+        ];
+
+        for tst in tests {
+            assert_eq!(tst.bytes, tst.pl.to_vec().unwrap());
+            assert_eq!(tst.pl, Payload::from_slice(tst.uplink, &tst.bytes).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_payload_errors() {
+        assert!(Payload::from_slice(true, &[]).is_err());
+        assert!(Payload::from_slice(true, &[0x08, 0x00, 0x00]).is_err());
+        assert!(Payload::from_slice(false, &[0x02, 0x00]).is_err());
+        assert!(
+            Payload::FragSessionDeleteReq(FragSessionDeleteReqPayload { frag_index: 4 })
+                .to_vec()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_get_padding() {
+        assert_eq!(0, get_padding(20, 10));
+        assert_eq!(5, get_padding(25, 10));
+        assert_eq!(9, get_padding(1, 10));
+    }
+
+    #[test]
+    fn test_encode() {
+        let data: Vec<u8> = (0..50).collect();
+        let fragments = encode(&data, 5, 3).unwrap();
+        assert_eq!(13, fragments.len());
+
+        for (i, f) in fragments.iter().take(10).enumerate() {
+            assert_eq!(data[i * 5..(i + 1) * 5], f[..]);
+        }
+
+        for n in 1..=3 {
+            let mut expected = vec![0; 5];
+            for (i, v) in matrix_line(n, 10).iter().enumerate() {
+                if *v {
+                    for j in 0..5 {
+                        expected[j] ^= fragments[i][j];
+                    }
+                }
+            }
+            assert_eq!(expected, fragments[9 + n]);
+        }
+
+        assert!(encode(&data, 7, 1).is_err());
+        assert!(encode(&data, 0, 1).is_err());
+    }
+
+    #[test]
+    fn test_matrix_line() {
+        assert_eq!(
+            vec![false, false, true, false, false, true, false, false, false, false],
+            matrix_line(1, 10)
+        );
+        assert_eq!(
+            vec![true, true, false, false, true, false, true, false],
+            matrix_line(1, 8)
+        );
+    }
+}
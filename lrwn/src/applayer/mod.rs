@@ -0,0 +1,4 @@
+//! LoRaWAN Application Layer packages.
+pub mod clocksync;
+pub mod fragmentation;
+pub mod multicastsetup;
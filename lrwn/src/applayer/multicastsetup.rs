@@ -0,0 +1,855 @@
+//! Remote Multicast Setup (TS005) v1.0.0.
+#[cfg(feature = "crypto")]
+use aes::{
+    cipher::{generic_array::GenericArray, BlockDecrypt, BlockEncrypt, KeyInit},
+    Aes128, Block,
+};
+use anyhow::Result;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+#[cfg(feature = "crypto")]
+use crate::AES128Key;
+use crate::DevAddr;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Cid {
+    PackageVersionReq,
+    PackageVersionAns,
+    McGroupStatusReq,
+    McGroupStatusAns,
+    McGroupSetupReq,
+    McGroupSetupAns,
+    McGroupDeleteReq,
+    McGroupDeleteAns,
+    McClassCSessionReq,
+    McClassCSessionAns,
+    McClassBSessionReq,
+    McClassBSessionAns,
+}
+
+impl Cid {
+    pub fn from_u8(uplink: bool, v: u8) -> Result<Self> {
+        Ok(match (uplink, v) {
+            (false, 0x00) => Cid::PackageVersionReq,
+            (true, 0x00) => Cid::PackageVersionAns,
+            (false, 0x01) => Cid::McGroupStatusReq,
+            (true, 0x01) => Cid::McGroupStatusAns,
+            (false, 0x02) => Cid::McGroupSetupReq,
+            (true, 0x02) => Cid::McGroupSetupAns,
+            (false, 0x03) => Cid::McGroupDeleteReq,
+            (true, 0x03) => Cid::McGroupDeleteAns,
+            (false, 0x04) => Cid::McClassCSessionReq,
+            (true, 0x04) => Cid::McClassCSessionAns,
+            (false, 0x05) => Cid::McClassBSessionReq,
+            (true, 0x05) => Cid::McClassBSessionAns,
+            _ => return Err(anyhow!("Invalid CID: {}", v)),
+        })
+    }
+
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            Cid::PackageVersionReq | Cid::PackageVersionAns => 0x00,
+            Cid::McGroupStatusReq | Cid::McGroupStatusAns => 0x01,
+            Cid::McGroupSetupReq | Cid::McGroupSetupAns => 0x02,
+            Cid::McGroupDeleteReq | Cid::McGroupDeleteAns => 0x03,
+            Cid::McClassCSessionReq | Cid::McClassCSessionAns => 0x04,
+            Cid::McClassBSessionReq | Cid::McClassBSessionAns => 0x05,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Payload {
+    PackageVersionReq,
+    PackageVersionAns(PackageVersionAnsPayload),
+    McGroupStatusReq(McGroupStatusReqPayload),
+    McGroupStatusAns(McGroupStatusAnsPayload),
+    McGroupSetupReq(McGroupSetupReqPayload),
+    McGroupSetupAns(McGroupSetupAnsPayload),
+    McGroupDeleteReq(McGroupDeleteReqPayload),
+    McGroupDeleteAns(McGroupDeleteAnsPayload),
+    McClassCSessionReq(McClassCSessionReqPayload),
+    McClassCSessionAns(McSessionAnsPayload),
+    McClassBSessionReq(McClassBSessionReqPayload),
+    McClassBSessionAns(McSessionAnsPayload),
+}
+
+impl Payload {
+    pub fn cid(&self) -> Cid {
+        match self {
+            Payload::PackageVersionReq => Cid::PackageVersionReq,
+            Payload::PackageVersionAns(_) => Cid::PackageVersionAns,
+            Payload::McGroupStatusReq(_) => Cid::McGroupStatusReq,
+            Payload::McGroupStatusAns(_) => Cid::McGroupStatusAns,
+            Payload::McGroupSetupReq(_) => Cid::McGroupSetupReq,
+            Payload::McGroupSetupAns(_) => Cid::McGroupSetupAns,
+            Payload::McGroupDeleteReq(_) => Cid::McGroupDeleteReq,
+            Payload::McGroupDeleteAns(_) => Cid::McGroupDeleteAns,
+            Payload::McClassCSessionReq(_) => Cid::McClassCSessionReq,
+            Payload::McClassCSessionAns(_) => Cid::McClassCSessionAns,
+            Payload::McClassBSessionReq(_) => Cid::McClassBSessionReq,
+            Payload::McClassBSessionAns(_) => Cid::McClassBSessionAns,
+        }
+    }
+
+    pub fn from_slice(uplink: bool, b: &[u8]) -> Result<Self> {
+        if b.is_empty() {
+            return Err(anyhow!("at least 1 byte is expected"));
+        }
+
+        let cid = Cid::from_u8(uplink, b[0])?;
+        let b = &b[1..];
+
+        Ok(match cid {
+            Cid::PackageVersionReq => Payload::PackageVersionReq,
+            Cid::PackageVersionAns => {
+                Payload::PackageVersionAns(PackageVersionAnsPayload::from_slice(b)?)
+            }
+            Cid::McGroupStatusReq => {
+                Payload::McGroupStatusReq(McGroupStatusReqPayload::from_slice(b)?)
+            }
+            Cid::McGroupStatusAns => {
+                Payload::McGroupStatusAns(McGroupStatusAnsPayload::from_slice(b)?)
+            }
+            Cid::McGroupSetupReq => {
+                Payload::McGroupSetupReq(McGroupSetupReqPayload::from_slice(b)?)
+            }
+            Cid::McGroupSetupAns => {
+                Payload::McGroupSetupAns(McGroupSetupAnsPayload::from_slice(b)?)
+            }
+            Cid::McGroupDeleteReq => {
+                Payload::McGroupDeleteReq(McGroupDeleteReqPayload::from_slice(b)?)
+            }
+            Cid::McGroupDeleteAns => {
+                Payload::McGroupDeleteAns(McGroupDeleteAnsPayload::from_slice(b)?)
+            }
+            Cid::McClassCSessionReq => {
+                Payload::McClassCSessionReq(McClassCSessionReqPayload::from_slice(b)?)
+            }
+            Cid::McClassCSessionAns => {
+                Payload::McClassCSessionAns(McSessionAnsPayload::from_slice(b)?)
+            }
+            Cid::McClassBSessionReq => {
+                Payload::McClassBSessionReq(McClassBSessionReqPayload::from_slice(b)?)
+            }
+            Cid::McClassBSessionAns => {
+                Payload::McClassBSessionAns(McSessionAnsPayload::from_slice(b)?)
+            }
+        })
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        let mut out = vec![self.cid().to_u8()];
+
+        match self {
+            Payload::PackageVersionReq => {}
+            Payload::PackageVersionAns(pl) => out.extend_from_slice(&pl.to_bytes()),
+            Payload::McGroupStatusReq(pl) => out.extend_from_slice(&pl.to_bytes()),
+            Payload::McGroupStatusAns(pl) => out.extend_from_slice(&pl.to_vec()?),
+            Payload::McGroupSetupReq(pl) => out.extend_from_slice(&pl.to_bytes()?),
+            Payload::McGroupSetupAns(pl) => out.extend_from_slice(&pl.to_bytes()?),
+            Payload::McGroupDeleteReq(pl) => out.extend_from_slice(&pl.to_bytes()?),
+            Payload::McGroupDeleteAns(pl) => out.extend_from_slice(&pl.to_bytes()?),
+            Payload::McClassCSessionReq(pl) => out.extend_from_slice(&pl.to_bytes()?),
+            Payload::McClassCSessionAns(pl) => out.extend_from_slice(&pl.to_vec()?),
+            Payload::McClassBSessionReq(pl) => out.extend_from_slice(&pl.to_bytes()?),
+            Payload::McClassBSessionAns(pl) => out.extend_from_slice(&pl.to_vec()?),
+        }
+
+        Ok(out)
+    }
+}
+
+fn check_len(b: &[u8], n: usize) -> Result<()> {
+    if b.len() != n {
+        return Err(anyhow!("{} bytes are expected", n));
+    }
+    Ok(())
+}
+
+fn check_mc_group_id(mc_group_id: u8) -> Result<()> {
+    if mc_group_id > 3 {
+        return Err(anyhow!("max mc_group_id value is 3"));
+    }
+    Ok(())
+}
+
+fn check_freq(freq: u32) -> Result<()> {
+    if freq % 100 != 0 || freq / 100 > 0xffffff {
+        return Err(anyhow!(
+            "frequency must be a multiple of 100 and fit in 3 bytes"
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PackageVersionAnsPayload {
+    pub package_identifier: u8,
+    pub package_version: u8,
+}
+
+impl PackageVersionAnsPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 2)?;
+        Ok(PackageVersionAnsPayload {
+            package_identifier: b[0],
+            package_version: b[1],
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 2] {
+        [self.package_identifier, self.package_version]
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupStatusReqPayload {
+    pub req_group_mask: [bool; 4],
+}
+
+impl McGroupStatusReqPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 1)?;
+        Ok(McGroupStatusReqPayload {
+            req_group_mask: [
+                b[0] & 0x01 != 0,
+                b[0] & 0x02 != 0,
+                b[0] & 0x04 != 0,
+                b[0] & 0x08 != 0,
+            ],
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 1] {
+        let mut b = 0;
+        for (i, v) in self.req_group_mask.iter().enumerate() {
+            if *v {
+                b |= 1 << i;
+            }
+        }
+        [b]
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupStatusAnsPayload {
+    pub nb_total_groups: u8,
+    pub ans_group_mask: [bool; 4],
+    pub items: Vec<McGroupStatusAnsItem>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupStatusAnsItem {
+    pub mc_group_id: u8,
+    pub mc_addr: DevAddr,
+}
+
+impl McGroupStatusAnsPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        if b.is_empty() || (b.len() - 1) % 5 != 0 {
+            return Err(anyhow!("1 + n * 5 bytes are expected"));
+        }
+
+        let mut items = Vec::with_capacity((b.len() - 1) / 5);
+        for item in b[1..].chunks(5) {
+            items.push(McGroupStatusAnsItem {
+                mc_group_id: item[0] & 0x03,
+                mc_addr: DevAddr::from_le_bytes([item[1], item[2], item[3], item[4]]),
+            });
+        }
+
+        Ok(McGroupStatusAnsPayload {
+            nb_total_groups: (b[0] >> 4) & 0x07,
+            ans_group_mask: [
+                b[0] & 0x01 != 0,
+                b[0] & 0x02 != 0,
+                b[0] & 0x04 != 0,
+                b[0] & 0x08 != 0,
+            ],
+            items,
+        })
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        if self.nb_total_groups > 7 {
+            return Err(anyhow!("max nb_total_groups value is 7"));
+        }
+
+        let mut status = self.nb_total_groups << 4;
+        for (i, v) in self.ans_group_mask.iter().enumerate() {
+            if *v {
+                status |= 1 << i;
+            }
+        }
+
+        let mut b = vec![status];
+        for item in &self.items {
+            check_mc_group_id(item.mc_group_id)?;
+            b.push(item.mc_group_id);
+            b.extend_from_slice(&item.mc_addr.to_le_bytes());
+        }
+
+        Ok(b)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupSetupReqPayload {
+    pub mc_group_id: u8,
+    pub mc_addr: DevAddr,
+    pub mc_key_encrypted: [u8; 16],
+    pub min_mc_f_count: u32,
+    pub max_mc_f_count: u32,
+}
+
+impl McGroupSetupReqPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 29)?;
+
+        let mut mc_key_encrypted: [u8; 16] = [0; 16];
+        mc_key_encrypted.copy_from_slice(&b[5..21]);
+
+        Ok(McGroupSetupReqPayload {
+            mc_group_id: b[0] & 0x03,
+            mc_addr: DevAddr::from_le_bytes([b[1], b[2], b[3], b[4]]),
+            mc_key_encrypted,
+            min_mc_f_count: u32::from_le_bytes([b[21], b[22], b[23], b[24]]),
+            max_mc_f_count: u32::from_le_bytes([b[25], b[26], b[27], b[28]]),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; 29]> {
+        check_mc_group_id(self.mc_group_id)?;
+
+        let mut b: [u8; 29] = [0; 29];
+        b[0] = self.mc_group_id;
+        b[1..5].copy_from_slice(&self.mc_addr.to_le_bytes());
+        b[5..21].copy_from_slice(&self.mc_key_encrypted);
+        b[21..25].copy_from_slice(&self.min_mc_f_count.to_le_bytes());
+        b[25..29].copy_from_slice(&self.max_mc_f_count.to_le_bytes());
+        Ok(b)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupSetupAnsPayload {
+    pub mc_group_id: u8,
+    pub id_error: bool,
+}
+
+impl McGroupSetupAnsPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 1)?;
+        Ok(McGroupSetupAnsPayload {
+            mc_group_id: b[0] & 0x03,
+            id_error: b[0] & 0x04 != 0,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; 1]> {
+        check_mc_group_id(self.mc_group_id)?;
+        Ok([self.mc_group_id | (self.id_error as u8) << 2])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupDeleteReqPayload {
+    pub mc_group_id: u8,
+}
+
+impl McGroupDeleteReqPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 1)?;
+        Ok(McGroupDeleteReqPayload {
+            mc_group_id: b[0] & 0x03,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; 1]> {
+        check_mc_group_id(self.mc_group_id)?;
+        Ok([self.mc_group_id])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupDeleteAnsPayload {
+    pub mc_group_id: u8,
+    pub mc_group_undefined: bool,
+}
+
+impl McGroupDeleteAnsPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 1)?;
+        Ok(McGroupDeleteAnsPayload {
+            mc_group_id: b[0] & 0x03,
+            mc_group_undefined: b[0] & 0x04 != 0,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; 1]> {
+        check_mc_group_id(self.mc_group_id)?;
+        Ok([self.mc_group_id | (self.mc_group_undefined as u8) << 2])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McClassCSessionReqPayload {
+    pub mc_group_id: u8,
+    /// Start of the session, in seconds since GPS epoch (modulo 2^32).
+    pub session_time: u32,
+    /// Max. duration of the session, as 2^session_time_out seconds.
+    pub session_time_out: u8,
+    /// Frequency (Hz).
+    pub dl_frequency: u32,
+    pub dr: u8,
+}
+
+impl McClassCSessionReqPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 10)?;
+        Ok(McClassCSessionReqPayload {
+            mc_group_id: b[0] & 0x03,
+            session_time: u32::from_le_bytes([b[1], b[2], b[3], b[4]]),
+            session_time_out: b[5] & 0x0f,
+            dl_frequency: u32::from_le_bytes([b[6], b[7], b[8], 0]) * 100,
+            dr: b[9],
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; 10]> {
+        check_mc_group_id(self.mc_group_id)?;
+        check_freq(self.dl_frequency)?;
+        if self.session_time_out > 15 {
+            return Err(anyhow!("max session_time_out value is 15"));
+        }
+
+        let session_time = self.session_time.to_le_bytes();
+        let freq = (self.dl_frequency / 100).to_le_bytes();
+
+        Ok([
+            self.mc_group_id,
+            session_time[0],
+            session_time[1],
+            session_time[2],
+            session_time[3],
+            self.session_time_out,
+            freq[0],
+            freq[1],
+            freq[2],
+            self.dr,
+        ])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McClassBSessionReqPayload {
+    pub mc_group_id: u8,
+    /// Start of the session, in seconds since GPS epoch (modulo 2^32).
+    pub session_time: u32,
+    /// Max. duration of the session, as 2^time_out beacon periods.
+    pub time_out: u8,
+    /// Ping-slot periodicity (0 - 7).
+    pub periodicity: u8,
+    /// Frequency (Hz).
+    pub dl_frequency: u32,
+    pub dr: u8,
+}
+
+impl McClassBSessionReqPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        check_len(b, 10)?;
+        Ok(McClassBSessionReqPayload {
+            mc_group_id: b[0] & 0x03,
+            session_time: u32::from_le_bytes([b[1], b[2], b[3], b[4]]),
+            time_out: b[5] & 0x0f,
+            periodicity: (b[5] >> 4) & 0x07,
+            dl_frequency: u32::from_le_bytes([b[6], b[7], b[8], 0]) * 100,
+            dr: b[9],
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; 10]> {
+        check_mc_group_id(self.mc_group_id)?;
+        check_freq(self.dl_frequency)?;
+        if self.time_out > 15 {
+            return Err(anyhow!("max time_out value is 15"));
+        }
+        if self.periodicity > 7 {
+            return Err(anyhow!("max periodicity value is 7"));
+        }
+
+        let session_time = self.session_time.to_le_bytes();
+        let freq = (self.dl_frequency / 100).to_le_bytes();
+
+        Ok([
+            self.mc_group_id,
+            session_time[0],
+            session_time[1],
+            session_time[2],
+            session_time[3],
+            self.periodicity << 4 | self.time_out,
+            freq[0],
+            freq[1],
+            freq[2],
+            self.dr,
+        ])
+    }
+}
+
+/// Answer to the McClassCSessionReq and McClassBSessionReq commands.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McSessionAnsPayload {
+    pub mc_group_id: u8,
+    pub dr_error: bool,
+    pub freq_error: bool,
+    pub mc_group_undefined: bool,
+    /// Seconds until the start of the session (only set on success).
+    pub time_to_start: Option<u32>,
+}
+
+impl McSessionAnsPayload {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        if b.len() != 1 && b.len() != 4 {
+            return Err(anyhow!("1 or 4 bytes are expected"));
+        }
+
+        Ok(McSessionAnsPayload {
+            mc_group_id: b[0] & 0x03,
+            dr_error: b[0] & 0x04 != 0,
+            freq_error: b[0] & 0x08 != 0,
+            mc_group_undefined: b[0] & 0x10 != 0,
+            time_to_start: if b.len() == 4 {
+                Some(u32::from_le_bytes([b[1], b[2], b[3], 0]))
+            } else {
+                None
+            },
+        })
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        check_mc_group_id(self.mc_group_id)?;
+
+        let mut b = vec![
+            self.mc_group_id
+                | (self.dr_error as u8) << 2
+                | (self.freq_error as u8) << 3
+                | (self.mc_group_undefined as u8) << 4,
+        ];
+
+        if let Some(v) = self.time_to_start {
+            if v > 0xffffff {
+                return Err(anyhow!("max time_to_start value is 16777215"));
+            }
+            b.extend_from_slice(&v.to_le_bytes()[0..3]);
+        }
+
+        Ok(b)
+    }
+
+    /// Returns true when the device reported an error.
+    pub fn is_error(&self) -> bool {
+        self.dr_error || self.freq_error || self.mc_group_undefined
+    }
+}
+
+/// Returns the McRootKey for a LoRaWAN 1.0.x device, derived from the GenAppKey.
+#[cfg(feature = "crypto")]
+pub fn get_mc_root_key_for_gen_app_key(gen_app_key: &AES128Key) -> Result<AES128Key> {
+    encrypt_block(gen_app_key, [0x00; 16])
+}
+
+/// Returns the McRootKey for a LoRaWAN 1.1.x device, derived from the AppKey.
+#[cfg(feature = "crypto")]
+pub fn get_mc_root_key_for_app_key(app_key: &AES128Key) -> Result<AES128Key> {
+    let mut b = [0x00; 16];
+    b[0] = 0x20;
+    encrypt_block(app_key, b)
+}
+
+#[cfg(feature = "crypto")]
+pub fn get_mc_ke_key(mc_root_key: &AES128Key) -> Result<AES128Key> {
+    encrypt_block(mc_root_key, [0x00; 16])
+}
+
+#[cfg(feature = "crypto")]
+pub fn get_mc_app_s_key(mc_key: &AES128Key, mc_addr: &DevAddr) -> Result<AES128Key> {
+    get_mc_s_key(0x01, mc_key, mc_addr)
+}
+
+#[cfg(feature = "crypto")]
+pub fn get_mc_net_s_key(mc_key: &AES128Key, mc_addr: &DevAddr) -> Result<AES128Key> {
+    get_mc_s_key(0x02, mc_key, mc_addr)
+}
+
+/// Returns the McKey encrypted with the McKEKey, as it must be sent to the end-device in the
+/// McGroupSetupReq. Note that the specification uses the AES decrypt operation for this.
+#[cfg(feature = "crypto")]
+pub fn encrypt_mc_key(mc_ke_key: &AES128Key, mc_key: &AES128Key) -> [u8; 16] {
+    let key_bytes = mc_ke_key.to_bytes();
+    let key = GenericArray::from_slice(&key_bytes);
+    let cipher = Aes128::new(key);
+
+    let mut b = mc_key.to_bytes();
+    let block = Block::from_mut_slice(&mut b);
+    cipher.decrypt_block(block);
+    b
+}
+
+#[cfg(feature = "crypto")]
+fn get_mc_s_key(typ: u8, mc_key: &AES128Key, mc_addr: &DevAddr) -> Result<AES128Key> {
+    let mut b = [0x00; 16];
+    b[0] = typ;
+    b[1..5].copy_from_slice(&mc_addr.to_le_bytes());
+    encrypt_block(mc_key, b)
+}
+
+#[cfg(feature = "crypto")]
+fn encrypt_block(key: &AES128Key, mut b: [u8; 16]) -> Result<AES128Key> {
+    let key_bytes = key.to_bytes();
+    let key = GenericArray::from_slice(&key_bytes);
+    let cipher = Aes128::new(key);
+
+    let block = Block::from_mut_slice(&mut b);
+    cipher.encrypt_block(block);
+    Ok(AES128Key::from_slice(block)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct PayloadTest {
+        uplink: bool,
+        pl: Payload,
+        bytes: Vec<u8>,
+    }
+
+    #[test]
+    fn test_payloads() {
+        let tests = vec![
+            PayloadTest {
+                uplink: false,
+                pl: Payload::PackageVersionReq,
+                bytes: vec![0x00],
+            },
+            PayloadTest {
+                uplink: true,
+                pl: Payload::PackageVersionAns(PackageVersionAnsPayload {
+                    package_identifier: 2,
+                    package_version: 1,
+                }),
+                bytes: vec![0x00, 0x02, 0x01],
+            },
+            PayloadTest {
+                uplink: false,
+                pl: Payload::McGroupStatusReq(McGroupStatusReqPayload {
+                    req_group_mask: [true, false, true, false],
+                }),
+                bytes: vec![0x01, 0x05],
+            },
+            PayloadTest {
+                uplink: true,
+                pl: Payload::McGroupStatusAns(McGroupStatusAnsPayload {
+                    nb_total_groups: 2,
+                    ans_group_mask: [true, false, false, false],
+                    items: vec![McGroupStatusAnsItem {
+                        mc_group_id: 0,
+                        mc_addr: DevAddr::from_be_bytes([0x01, 0x02, 0x03, 0x04]),
+                    }],
+                }),
+                bytes: vec![0x01, 0x21, 0x00, 0x04, 0x03, 0x02, 0x01],
+            },
+            PayloadTest {
+                uplink: false,
+                pl: Payload::McGroupSetupReq(McGroupSetupReqPayload {
+                    mc_group_id: 1,
+                    mc_addr: DevAddr::from_be_bytes([0x01, 0x02, 0x03, 0x04]),
+                    mc_key_encrypted: [
+                        0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c,
+                        0x0d, 0x0e, 0x0f, 0x10,
+                    ],
+                    min_mc_f_count: 10,
+                    max_mc_f_count: 1024,
+                }),
+                bytes: vec![
+                    0x02, 0x01, 0x04, 0x03, 0x02, 0x01, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+                    0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0x10, 0x0a, 0x00, 0x00, 0x00,
+                    0x00, 0x04, 0x00, 0x00,
+                ],
+            },
+            PayloadTest {
+                uplink: true,
+                pl: Payload::McGroupSetupAns(McGroupSetupAnsPayload {
+                    mc_group_id: 1,
+                    id_error: true,
+                }),
+                bytes: vec![0x02, 0x05],
+            },
+            PayloadTest {
+                uplink: false,
+                pl: Payload::McGroupDeleteReq(McGroupDeleteReqPayload { mc_group_id: 3 }),
+                bytes: vec![0x03, 0x03],
+            },
+            PayloadTest {
+                uplink: true,
+                pl: Payload::McGroupDeleteAns(McGroupDeleteAnsPayload {
+                    mc_group_id: 2,
+                    mc_group_undefined: true,
+                }),
+                bytes: vec![0x03, 0x06],
+            },
+            PayloadTest {
+                uplink: false,
+                pl: Payload::McClassCSessionReq(McClassCSessionReqPayload {
+                    mc_group_id: 1,
+                    session_time: 1_000_000,
+                    session_time_out: 8,
+                    dl_frequency: 869525000,
+                    dr: 3,
+                }),
+                bytes: vec![
+                    0x04, 0x01, 0x40, 0x42, 0x0f, 0x00, 0x08, 0xd2, 0xad, 0x84, 0x03,
+                ],
+            },
+            PayloadTest {
+                uplink: true,
+                pl: Payload::McClassCSessionAns(McSessionAnsPayload {
+                    mc_group_id: 1,
+                    dr_error: false,
+                    freq_error: false,
+                    mc_group_undefined: false,
+                    time_to_start: Some(300),
+                }),
+                bytes: vec![0x04, 0x01, 0x2c, 0x01, 0x00],
+            },
+            PayloadTest {
+                uplink: false,
+                pl: Payload::McClassBSessionReq(McClassBSessionReqPayload {
+                    mc_group_id: 2,
+                    session_time: 1_000_000,
+                    time_out: 3,
+                    periodicity: 5,
+                    dl_frequency: 869525000,
+                    dr: 3,
+                }),
+                bytes: vec![
+                    0x05, 0x02, 0x40, 0x42, 0x0f, 0x00, 0x53, 0xd2, 0xad, 0x84, 0x03,
+                ],
+            },
+            PayloadTest {
+                uplink: true,
+                pl: Payload::McClassBSessionAns(McSessionAnsPayload {
+                    mc_group_id: 2,
+                    dr_error: true,
+                    freq_error: true,
+                    mc_group_undefined: false,
+                    time_to_start: None,
+                }),
+                bytes: vec![0x05, 0x0e],
+            },
+        ];
+
+        for tst in tests {
+            assert_eq!(tst.bytes, tst.pl.to_vec().unwrap());
+            assert_eq!(tst.pl, Payload::from_slice(tst.uplink, &tst.bytes).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_payload_errors() {
+        assert!(Payload::from_slice(true, &[]).is_err());
+        assert!(Payload::from_slice(true, &[0x06]).is_err());
+        assert!(Payload::from_slice(false, &[0x02, 0x00]).is_err());
+        assert!(
+            Payload::McGroupDeleteReq(McGroupDeleteReqPayload { mc_group_id: 4 })
+                .to_vec()
+                .is_err()
+        );
+        assert!(Payload::McClassCSessionReq(McClassCSessionReqPayload {
+            mc_group_id: 0,
+            session_time: 0,
+            session_time_out: 0,
+            dl_frequency: 869525050,
+            dr: 0,
+        })
+        .to_vec()
+        .is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "crypto")]
+    fn test_keys() {
+        let key = AES128Key::from_bytes([
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+            0x0f, 0x10,
+        ]);
+        let mc_key = AES128Key::from_bytes([
+            0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d,
+            0x1e, 0x1f,
+        ]);
+        let mc_addr = DevAddr::from_be_bytes([0x01, 0x02, 0x03, 0x04]);
+
+        let mc_root_key = get_mc_root_key_for_gen_app_key(&key).unwrap();
+        assert_eq!(
+            AES128Key::from_bytes([
+                0xdb, 0xf1, 0x84, 0x11, 0x2e, 0xb9, 0x11, 0x16, 0x59, 0x71, 0x2b, 0xaf, 0xcf, 0xf2,
+                0xab, 0x24
+            ]),
+            mc_root_key
+        );
+
+        assert_eq!(
+            AES128Key::from_bytes([
+                0x6e, 0x7f, 0xef, 0x39, 0x78, 0x52, 0x93, 0x02, 0x96, 0xc4, 0x77, 0x40, 0x6b, 0x8b,
+                0xcd, 0x1f
+            ]),
+            get_mc_root_key_for_app_key(&key).unwrap()
+        );
+
+        let mc_ke_key = get_mc_ke_key(&mc_root_key).unwrap();
+        assert_eq!(
+            AES128Key::from_bytes([
+                0xe4, 0xd4, 0x71, 0x47, 0xb0, 0xfe, 0xe0, 0x82, 0xc8, 0x12, 0xc8, 0xdb, 0xf9, 0xa8,
+                0x46, 0xf2
+            ]),
+            mc_ke_key
+        );
+
+        assert_eq!(
+            [
+                0xba, 0x32, 0x8d, 0x7c, 0x34, 0x13, 0xd5, 0x1d, 0xd7, 0xad, 0xa4, 0x59, 0xd6, 0xdd,
+                0x12, 0x09
+            ],
+            encrypt_mc_key(&mc_ke_key, &mc_key)
+        );
+
+        assert_eq!(
+            AES128Key::from_bytes([
+                0x10, 0xd0, 0x92, 0x8a, 0xd0, 0xfa, 0x54, 0x41, 0x48, 0x10, 0xf2, 0x4e, 0xe0, 0x10,
+                0x02, 0x30
+            ]),
+            get_mc_app_s_key(&mc_key, &mc_addr).unwrap()
+        );
+
+        assert_eq!(
+            AES128Key::from_bytes([
+                0x53, 0x5a, 0x8d, 0x2d, 0x62, 0x8d, 0x6c, 0xdf, 0x86, 0xd4, 0x7f, 0xf9, 0xec, 0x05,
+                0x65, 0xc2
+            ]),
+            get_mc_net_s_key(&mc_key, &mc_addr).unwrap()
+        );
+    }
+}
@@ -21,6 +21,7 @@ pub use self::phy_payload::*;
 pub use self::relay::*;
 
 mod aes128;
+pub mod applayer;
 mod cflist;
 mod devaddr;
 mod dl_settings;
@@ -39,6 +40,9 @@ mod phy_payload;
 pub mod region;
 mod relay;
 
+pub const LA_FPORT_MULTICAST_SETUP: u8 = 200;
+pub const LA_FPORT_FRAGMENTATION: u8 = 201;
+pub const LA_FPORT_CLOCK_SYNC: u8 = 202;
 pub const LA_FPORT_RELAY: u8 = 226;
 
 lazy_static! {